@@ -0,0 +1,239 @@
+//! Automatic gain control
+//!
+//! Pulls quiet sources up and loud ones down towards a target RMS level so
+//! presets react consistently regardless of the input volume. Gain drops at
+//! the attack rate when the input gets louder and recovers at the release rate
+//! when it gets quieter; near-silence is never amplified.
+
+use serde::{Deserialize, Serialize};
+
+/// Inputs below this RMS level are treated as silence and hold the gain
+const GATE_LEVEL: f32 = 0.001;
+/// Lowest gain ever applied
+const MIN_GAIN: f32 = 0.05;
+/// Level above which `soft_clip` starts to compress
+const CLIP_KNEE: f32 = 0.9;
+
+/// AGC tuning parameters
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct AgcConfig {
+    /// Target RMS level (0.0-1.0)
+    pub target_level: f32,
+    /// Time constant for reducing gain when the input gets louder (seconds)
+    pub attack_secs: f32,
+    /// Time constant for raising gain when the input gets quieter (seconds)
+    pub release_secs: f32,
+    /// Maximum gain applied (linear)
+    pub max_gain: f32,
+}
+
+impl Default for AgcConfig {
+    fn default() -> Self {
+        Self {
+            target_level: 0.25,
+            attack_secs: 0.05,
+            release_secs: 2.0,
+            max_gain: 10.0,
+        }
+    }
+}
+
+/// Block-based automatic gain control for interleaved audio
+#[derive(Debug, Clone)]
+pub struct AutoGain {
+    config: AgcConfig,
+    sample_rate: u32,
+    channels: u16,
+    gain: f32,
+}
+
+impl AutoGain {
+    /// Create an AGC stage starting at unity gain
+    pub fn new(config: AgcConfig, sample_rate: u32, channels: u16) -> Self {
+        Self {
+            config: sanitize(config),
+            sample_rate: sample_rate.max(1),
+            channels: channels.max(1),
+            gain: 1.0,
+        }
+    }
+
+    /// Current tuning
+    pub fn config(&self) -> AgcConfig {
+        self.config
+    }
+
+    /// Replace the tuning, keeping the current gain
+    pub fn set_config(&mut self, config: AgcConfig) {
+        self.config = sanitize(config);
+        self.gain = self.gain.clamp(MIN_GAIN, self.config.max_gain);
+    }
+
+    /// Update the input format
+    pub fn set_format(&mut self, sample_rate: u32, channels: u16) {
+        self.sample_rate = sample_rate.max(1);
+        self.channels = channels.max(1);
+    }
+
+    /// Return to unity gain
+    pub fn reset(&mut self) {
+        self.gain = 1.0;
+    }
+
+    /// Gain currently applied (linear)
+    pub fn gain(&self) -> f32 {
+        self.gain
+    }
+
+    /// Apply gain to a block, returning the adjusted samples (clamped to -1..1)
+    pub fn process(&mut self, samples: &[f32]) -> Vec<f32> {
+        if samples.is_empty() {
+            return Vec::new();
+        }
+
+        let rms = (samples.iter().map(|s| s * s).sum::<f32>() / samples.len() as f32).sqrt();
+        let start_gain = self.gain;
+
+        if rms > GATE_LEVEL {
+            let desired = (self.config.target_level / rms).clamp(MIN_GAIN, self.config.max_gain);
+            let tau = if desired < self.gain {
+                self.config.attack_secs
+            } else {
+                self.config.release_secs
+            };
+            let block_secs =
+                samples.len() as f32 / (self.sample_rate as f32 * self.channels as f32);
+            let coeff = (-block_secs / tau).exp();
+            self.gain = desired + (self.gain - desired) * coeff;
+        }
+
+        // Ramp across the block so gain changes don't produce steps
+        let frames = (samples.len() / self.channels as usize).max(1);
+        let delta = (self.gain - start_gain) / frames as f32;
+        samples
+            .chunks(self.channels as usize)
+            .enumerate()
+            .flat_map(|(i, frame)| {
+                let gain = start_gain + delta * (i + 1) as f32;
+                frame.iter().map(move |s| (s * gain).clamp(-1.0, 1.0))
+            })
+            .collect()
+    }
+}
+
+/// Limit a sample to -1..1, rounding off peaks above the knee instead of
+/// clipping them flat
+pub fn soft_clip(sample: f32) -> f32 {
+    let level = sample.abs();
+    if level <= CLIP_KNEE {
+        return sample;
+    }
+    let headroom = 1.0 - CLIP_KNEE;
+    let limited = CLIP_KNEE + headroom * ((level - CLIP_KNEE) / headroom).tanh();
+    limited.copysign(sample)
+}
+
+/// Keep parameters in a usable range
+fn sanitize(config: AgcConfig) -> AgcConfig {
+    AgcConfig {
+        target_level: config.target_level.clamp(0.01, 1.0),
+        attack_secs: config.attack_secs.max(0.001),
+        release_secs: config.release_secs.max(0.001),
+        max_gain: config.max_gain.max(1.0),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rms(samples: &[f32]) -> f32 {
+        (samples.iter().map(|s| s * s).sum::<f32>() / samples.len() as f32).sqrt()
+    }
+
+    /// Run `secs` of a constant-amplitude square wave through the AGC
+    fn settle(agc: &mut AutoGain, amplitude: f32, secs: f32) -> Vec<f32> {
+        let block: Vec<f32> = (0..960)
+            .map(|i| {
+                if (i / 2) % 2 == 0 {
+                    amplitude
+                } else {
+                    -amplitude
+                }
+            })
+            .collect();
+        let mut out = Vec::new();
+        for _ in 0..(secs * 100.0) as usize {
+            out = agc.process(&block);
+        }
+        out
+    }
+
+    #[test]
+    fn test_quiet_input_is_boosted() {
+        let mut agc = AutoGain::new(AgcConfig::default(), 48000, 2);
+        let out = settle(&mut agc, 0.05, 10.0);
+        assert!((rms(&out) - 0.25).abs() < 0.02, "rms {}", rms(&out));
+    }
+
+    #[test]
+    fn test_loud_input_is_reduced_quickly() {
+        let mut agc = AutoGain::new(AgcConfig::default(), 48000, 2);
+        let out = settle(&mut agc, 0.9, 0.5);
+        assert!((rms(&out) - 0.25).abs() < 0.02, "rms {}", rms(&out));
+    }
+
+    #[test]
+    fn test_release_is_slower_than_attack() {
+        let mut agc = AutoGain::new(AgcConfig::default(), 48000, 2);
+        settle(&mut agc, 0.05, 0.2);
+        let boosted = agc.gain();
+        agc.reset();
+        settle(&mut agc, 1.0, 0.2);
+        let cut = agc.gain();
+
+        // After the same time the cut is nearly complete, the boost is not
+        assert!((cut - 0.25).abs() < 0.05);
+        assert!(boosted < 3.0);
+    }
+
+    #[test]
+    fn test_gain_is_capped() {
+        let config = AgcConfig {
+            max_gain: 2.0,
+            ..Default::default()
+        };
+        let mut agc = AutoGain::new(config, 48000, 2);
+        settle(&mut agc, 0.01, 30.0);
+        assert!(agc.gain() <= 2.0 + 1e-6);
+    }
+
+    #[test]
+    fn test_silence_holds_gain() {
+        let mut agc = AutoGain::new(AgcConfig::default(), 48000, 2);
+        let out = agc.process(&[0.0; 960]);
+        assert_eq!(agc.gain(), 1.0);
+        assert!(out.iter().all(|&s| s == 0.0));
+    }
+
+    #[test]
+    fn test_soft_clip() {
+        assert_eq!(soft_clip(0.5), 0.5);
+        assert_eq!(soft_clip(-0.9), -0.9);
+        assert!(soft_clip(1.2) < 1.0 && soft_clip(1.2) > 0.95);
+        assert!(soft_clip(-4.0) >= -1.0);
+        // Monotonic above the knee
+        assert!(soft_clip(1.5) > soft_clip(1.1));
+    }
+
+    #[test]
+    fn test_output_is_clamped() {
+        let config = AgcConfig {
+            target_level: 1.0,
+            ..Default::default()
+        };
+        let mut agc = AutoGain::new(config, 48000, 2);
+        let out = settle(&mut agc, 0.5, 5.0);
+        assert!(out.iter().all(|s| s.abs() <= 1.0));
+    }
+}
@@ -0,0 +1,485 @@
+//! Beat and tempo analysis
+//!
+//! Runs an energy-based onset detector over the captured stream and estimates
+//! the tempo from inter-onset intervals. The detector keeps a beat grid so
+//! callers can read the current BPM and the phase within the current beat, and
+//! queues an [`OnsetEvent`] for every detected onset.
+
+use std::collections::VecDeque;
+
+use serde::{Deserialize, Serialize};
+
+/// Lowest tempo reported; slower estimates are doubled into range
+pub const MIN_BPM: f32 = 80.0;
+/// Highest tempo reported (exclusive); faster estimates are halved into range
+pub const MAX_BPM: f32 = 160.0;
+
+/// Analysis window in seconds (~11.6 ms at 44.1 kHz)
+const HOP_SECONDS: f32 = 0.0116;
+/// Length of the energy history used as the onset threshold baseline
+const HISTORY_SECONDS: f32 = 1.0;
+/// How long onsets are kept for tempo estimation
+const ONSET_WINDOW_SECONDS: f32 = 8.0;
+/// Energy must exceed the local average by this factor to count as an onset
+const ONSET_THRESHOLD: f32 = 1.5;
+/// Windows quieter than this are never onsets
+const NOISE_FLOOR: f32 = 1e-5;
+/// Minimum spacing between onsets in seconds
+const MIN_ONSET_INTERVAL: f32 = 0.2;
+/// Onsets within this fraction of a beat period re-anchor the beat grid
+const PHASE_SNAP: f32 = 0.25;
+/// Onsets this many times louder than the local average get full confidence
+const FULL_CONFIDENCE_RATIO: f32 = 3.0;
+/// Undrained onset events kept before the oldest are discarded
+const MAX_PENDING_EVENTS: usize = 64;
+
+/// Snapshot of the tempo tracker state
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub struct BeatInfo {
+    /// Estimated tempo in beats per minute (0.0 until enough onsets are seen)
+    pub bpm: f32,
+    /// Position within the current beat (0.0 = on the beat, approaching 1.0 before the next)
+    pub phase: f32,
+    /// Agreement of recent onsets with the estimate (0.0-1.0)
+    pub confidence: f32,
+    /// Number of beats elapsed since the detector was reset
+    pub beat_count: u64,
+}
+
+/// A single detected onset
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct OnsetEvent {
+    /// RMS level of the analysis window that triggered the onset (0.0-1.0)
+    pub energy: f32,
+    /// Energy relative to the recent average
+    pub strength: f32,
+    /// How clearly the onset stands out from the background (0.0-1.0)
+    pub confidence: f32,
+    /// Whether the onset landed on the tracked beat grid
+    pub on_beat: bool,
+    /// Tempo estimate at the time of the onset (0.0 if unknown)
+    pub bpm: f32,
+    /// Seconds of audio analysed since the detector was reset
+    pub time_secs: f64,
+}
+
+/// Onset detector and BPM estimator
+pub struct BeatDetector {
+    sample_rate: u32,
+    channels: u16,
+    hop_size: usize,
+    /// Mono samples not yet forming a full analysis window
+    pending: Vec<f32>,
+    /// Recent window energies
+    energy_history: VecDeque<f32>,
+    history_len: usize,
+    /// Total mono frames analysed
+    position: u64,
+    /// Frame positions of recent onsets
+    onsets: VecDeque<u64>,
+    bpm: f32,
+    confidence: f32,
+    /// Frame position of the most recent beat grid point
+    last_beat: Option<u64>,
+    beat_count: u64,
+    /// Onsets not yet collected with `take_onsets`
+    events: VecDeque<OnsetEvent>,
+}
+
+impl BeatDetector {
+    /// Create a detector for interleaved audio at the given rate and channel count
+    pub fn new(sample_rate: u32, channels: u16) -> Self {
+        let sample_rate = sample_rate.max(1);
+        let hop_size = ((sample_rate as f32 * HOP_SECONDS) as usize).max(1);
+        let history_len = ((HISTORY_SECONDS * sample_rate as f32) as usize / hop_size).max(1);
+
+        Self {
+            sample_rate,
+            channels: channels.max(1),
+            hop_size,
+            pending: Vec::with_capacity(hop_size),
+            energy_history: VecDeque::with_capacity(history_len),
+            history_len,
+            position: 0,
+            onsets: VecDeque::new(),
+            bpm: 0.0,
+            confidence: 0.0,
+            last_beat: None,
+            beat_count: 0,
+            events: VecDeque::new(),
+        }
+    }
+
+    /// Sample rate the detector was configured for
+    pub fn sample_rate(&self) -> u32 {
+        self.sample_rate
+    }
+
+    /// Reconfigure the input format, resetting state if it changed
+    pub fn set_format(&mut self, sample_rate: u32, channels: u16) {
+        if sample_rate != self.sample_rate || channels != self.channels {
+            *self = Self::new(sample_rate, channels);
+        }
+    }
+
+    /// Clear all analysis state
+    pub fn reset(&mut self) {
+        *self = Self::new(self.sample_rate, self.channels);
+    }
+
+    /// Analyse a block of interleaved samples.
+    ///
+    /// Returns `true` if at least one beat of the tempo grid elapsed during the block.
+    pub fn process(&mut self, samples: &[f32]) -> bool {
+        let channels = self.channels as usize;
+        let mut beat = false;
+
+        for frame in samples.chunks(channels) {
+            let mono = frame.iter().sum::<f32>() / frame.len() as f32;
+            self.pending.push(mono);
+
+            if self.pending.len() >= self.hop_size {
+                beat |= self.analyse_window();
+                self.pending.clear();
+            }
+        }
+
+        beat
+    }
+
+    /// Current tempo estimate and beat phase
+    pub fn info(&self) -> BeatInfo {
+        BeatInfo {
+            bpm: self.bpm,
+            phase: self.phase(),
+            confidence: self.confidence,
+            beat_count: self.beat_count,
+        }
+    }
+
+    /// Drain the onsets detected since the last call, oldest first
+    pub fn take_onsets(&mut self) -> Vec<OnsetEvent> {
+        self.events.drain(..).collect()
+    }
+
+    /// Current tempo estimate (0.0 if unknown)
+    pub fn bpm(&self) -> f32 {
+        self.bpm
+    }
+
+    fn period_frames(&self) -> Option<f32> {
+        (self.bpm > 0.0).then(|| 60.0 / self.bpm * self.sample_rate as f32)
+    }
+
+    fn phase(&self) -> f32 {
+        match (self.period_frames(), self.last_beat) {
+            (Some(period), Some(last)) => {
+                let elapsed = self.position.saturating_sub(last) as f32;
+                (elapsed / period).fract()
+            }
+            _ => 0.0,
+        }
+    }
+
+    /// Process one full window from `pending`; returns true if a beat elapsed
+    fn analyse_window(&mut self) -> bool {
+        let energy = self.pending.iter().map(|s| s * s).sum::<f32>() / self.pending.len() as f32;
+        self.position += self.pending.len() as u64;
+
+        let average = if self.energy_history.is_empty() {
+            0.0
+        } else {
+            self.energy_history.iter().sum::<f32>() / self.energy_history.len() as f32
+        };
+
+        let min_gap = (MIN_ONSET_INTERVAL * self.sample_rate as f32) as u64;
+        let spaced = match self.onsets.back() {
+            Some(&last) => self.position - last >= min_gap,
+            None => true,
+        };
+
+        // Advance the grid first so an onset on a due beat isn't counted twice
+        let mut beat = self.advance_grid();
+        if energy > NOISE_FLOOR && energy > average * ONSET_THRESHOLD && spaced {
+            beat |= self.on_onset(self.position);
+            self.push_event(energy, average);
+        }
+
+        if self.energy_history.len() >= self.history_len {
+            self.energy_history.pop_front();
+        }
+        self.energy_history.push_back(energy);
+
+        beat
+    }
+
+    /// Queue an event for the onset just recorded at the current position
+    fn push_event(&mut self, energy: f32, average: f32) {
+        let strength = if average > 0.0 {
+            energy / average
+        } else {
+            FULL_CONFIDENCE_RATIO
+        };
+        let confidence = ((strength - ONSET_THRESHOLD)
+            / (FULL_CONFIDENCE_RATIO - ONSET_THRESHOLD))
+            .clamp(0.0, 1.0);
+
+        if self.events.len() >= MAX_PENDING_EVENTS {
+            self.events.pop_front();
+        }
+        self.events.push_back(OnsetEvent {
+            energy: energy.sqrt().min(1.0),
+            strength,
+            confidence,
+            on_beat: self.bpm > 0.0 && self.last_beat == Some(self.position),
+            bpm: self.bpm,
+            time_secs: self.position as f64 / self.sample_rate as f64,
+        });
+    }
+
+    /// Record an onset; returns true if it was taken as an early beat of the grid
+    fn on_onset(&mut self, at: u64) -> bool {
+        self.onsets.push_back(at);
+        let window = (ONSET_WINDOW_SECONDS * self.sample_rate as f32) as u64;
+        while self.onsets.front().is_some_and(|&t| at - t > window) {
+            self.onsets.pop_front();
+        }
+
+        self.estimate_tempo();
+
+        // Re-anchor the beat grid when the onset lands close to a predicted beat
+        match (self.period_frames(), self.last_beat) {
+            (Some(period), Some(last)) => {
+                let offset = (at.saturating_sub(last) as f32 / period).fract();
+                if offset < PHASE_SNAP {
+                    // Slightly late: the grid already counted this beat
+                    self.last_beat = Some(at);
+                } else if offset > 1.0 - PHASE_SNAP {
+                    // Slightly early: this onset is the next beat
+                    self.last_beat = Some(at);
+                    self.beat_count += 1;
+                    return true;
+                }
+            }
+            _ => self.last_beat = Some(at),
+        }
+        false
+    }
+
+    /// Build a histogram of folded inter-onset intervals and pick the peak
+    fn estimate_tempo(&mut self) {
+        if self.onsets.len() < 3 {
+            return;
+        }
+
+        let bins = (MAX_BPM - MIN_BPM) as usize;
+        let mut histogram = vec![0.0f32; bins];
+        let mut candidates = Vec::new();
+
+        let onsets: Vec<u64> = self.onsets.iter().copied().collect();
+        for (i, &start) in onsets.iter().enumerate() {
+            // Compare against the next few onsets so a missed beat still votes
+            for (k, &end) in onsets[i + 1..].iter().take(4).enumerate() {
+                let seconds = (end - start) as f32 / self.sample_rate as f32;
+                if let Some(bpm) = fold_bpm(60.0 / seconds) {
+                    let weight = 1.0 / (k + 1) as f32;
+                    let bin = ((bpm - MIN_BPM) as usize).min(bins - 1);
+                    histogram[bin] += weight;
+                    if let Some(below) = bin.checked_sub(1) {
+                        histogram[below] += weight * 0.5;
+                    }
+                    if bin + 1 < bins {
+                        histogram[bin + 1] += weight * 0.5;
+                    }
+                    candidates.push(bpm);
+                }
+            }
+        }
+
+        let Some((peak, _)) = histogram
+            .iter()
+            .enumerate()
+            .max_by(|a, b| a.1.total_cmp(b.1))
+        else {
+            return;
+        };
+
+        // Refine with the mean of candidates close to the peak bin
+        let center = MIN_BPM + peak as f32 + 0.5;
+        let close: Vec<f32> = candidates
+            .iter()
+            .copied()
+            .filter(|bpm| (bpm - center).abs() <= 1.5)
+            .collect();
+        if close.is_empty() {
+            return;
+        }
+
+        let estimate = close.iter().sum::<f32>() / close.len() as f32;
+        self.confidence = close.len() as f32 / candidates.len() as f32;
+        self.bpm = if self.bpm > 0.0 && (estimate - self.bpm).abs() < 2.0 {
+            // Smooth small corrections so the display doesn't jitter
+            self.bpm * 0.8 + estimate * 0.2
+        } else {
+            estimate
+        };
+    }
+
+    /// Move the beat grid forward to the current position
+    fn advance_grid(&mut self) -> bool {
+        let (Some(period), Some(mut last)) = (self.period_frames(), self.last_beat) else {
+            return false;
+        };
+
+        let mut beat = false;
+        while (self.position.saturating_sub(last) as f32) >= period {
+            last += period as u64;
+            self.beat_count += 1;
+            beat = true;
+        }
+        self.last_beat = Some(last);
+        beat
+    }
+}
+
+/// Fold a tempo into `[MIN_BPM, MAX_BPM)` by octave doubling/halving
+fn fold_bpm(bpm: f32) -> Option<f32> {
+    if !bpm.is_finite() || bpm <= 0.0 {
+        return None;
+    }
+    let mut bpm = bpm;
+    while bpm < MIN_BPM {
+        bpm *= 2.0;
+    }
+    while bpm >= MAX_BPM {
+        bpm /= 2.0;
+    }
+    Some(bpm)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Interleaved stereo click track: short noise-like bursts at `bpm`
+    fn click_track(bpm: f32, seconds: f32, sample_rate: u32) -> Vec<f32> {
+        let frames = (seconds * sample_rate as f32) as usize;
+        let period = (60.0 / bpm * sample_rate as f32) as usize;
+        let click_len = sample_rate as usize / 50;
+        let mut out = Vec::with_capacity(frames * 2);
+        for i in 0..frames {
+            let pos = i % period;
+            let s = if pos < click_len {
+                let decay = 1.0 - pos as f32 / click_len as f32;
+                (pos as f32 * 0.7).sin() * decay * 0.8
+            } else {
+                0.0
+            };
+            out.push(s);
+            out.push(s);
+        }
+        out
+    }
+
+    #[test]
+    fn test_fold_bpm() {
+        assert_eq!(fold_bpm(60.0), Some(120.0));
+        assert_eq!(fold_bpm(240.0), Some(120.0));
+        assert_eq!(fold_bpm(128.0), Some(128.0));
+        assert_eq!(fold_bpm(0.0), None);
+        assert_eq!(fold_bpm(f32::INFINITY), None);
+    }
+
+    #[test]
+    fn test_silence_has_no_tempo() {
+        let mut detector = BeatDetector::new(44100, 2);
+        let silence = vec![0.0; 44100 * 2 * 4];
+        assert!(!detector.process(&silence));
+        let info = detector.info();
+        assert_eq!(info.bpm, 0.0);
+        assert_eq!(info.beat_count, 0);
+    }
+
+    #[test]
+    fn test_detects_120_bpm() {
+        let mut detector = BeatDetector::new(44100, 2);
+        let track = click_track(120.0, 10.0, 44100);
+        for chunk in track.chunks(4096) {
+            detector.process(chunk);
+        }
+        let info = detector.info();
+        assert!((info.bpm - 120.0).abs() < 2.0, "bpm = {}", info.bpm);
+        assert!(info.confidence > 0.5);
+        assert!(info.beat_count >= 15);
+    }
+
+    #[test]
+    fn test_detects_128_bpm_at_48k() {
+        let mut detector = BeatDetector::new(48000, 2);
+        let track = click_track(128.0, 10.0, 48000);
+        for chunk in track.chunks(2048) {
+            detector.process(chunk);
+        }
+        assert!((detector.bpm() - 128.0).abs() < 2.0, "bpm = {}", detector.bpm());
+    }
+
+    #[test]
+    fn test_phase_in_range() {
+        let mut detector = BeatDetector::new(44100, 2);
+        let track = click_track(100.0, 6.0, 44100);
+        for chunk in track.chunks(1000) {
+            detector.process(chunk);
+            let phase = detector.info().phase;
+            assert!((0.0..1.0).contains(&phase));
+        }
+    }
+
+    #[test]
+    fn test_onset_events() {
+        let mut detector = BeatDetector::new(44100, 2);
+        let mut events = Vec::new();
+        for chunk in click_track(120.0, 8.0, 44100).chunks(4096) {
+            detector.process(chunk);
+            events.extend(detector.take_onsets());
+        }
+
+        // One onset per click, roughly half a second apart
+        assert!((14..=17).contains(&events.len()), "{} onsets", events.len());
+        for pair in events.windows(2) {
+            let gap = pair[1].time_secs - pair[0].time_secs;
+            assert!((gap - 0.5).abs() < 0.03, "gap {}", gap);
+        }
+        for event in &events {
+            assert!(event.energy > 0.0 && event.energy <= 1.0);
+            assert!((0.0..=1.0).contains(&event.confidence));
+        }
+
+        // Once the tempo is locked, clicks land on the grid
+        let late = &events[events.len() - 4..];
+        assert!(late.iter().all(|e| e.on_beat && e.bpm > 0.0));
+        assert!(detector.take_onsets().is_empty());
+    }
+
+    #[test]
+    fn test_silence_has_no_onsets() {
+        let mut detector = BeatDetector::new(44100, 2);
+        detector.process(&vec![0.0; 44100 * 2]);
+        assert!(detector.take_onsets().is_empty());
+    }
+
+    #[test]
+    fn test_set_format_resets() {
+        let mut detector = BeatDetector::new(44100, 2);
+        for chunk in click_track(120.0, 6.0, 44100).chunks(4096) {
+            detector.process(chunk);
+        }
+        assert!(detector.bpm() > 0.0);
+
+        detector.set_format(44100, 2);
+        assert!(detector.bpm() > 0.0);
+
+        detector.set_format(48000, 2);
+        assert_eq!(detector.bpm(), 0.0);
+        assert_eq!(detector.sample_rate(), 48000);
+    }
+}
@@ -1,29 +1,46 @@
 //! Audio capture using CPAL (and PipeWire on Linux)
 //!
 //! Captures audio from system input devices and distributes it to visualization decks.
-//! On Linux, can use native PipeWire for monitor devices instead of parec subprocess.
+//! On Linux, captures through native PipeWire, falling back to a parec subprocess
+//! when no PipeWire server is reachable.
 
+use std::path::Path;
+use std::sync::atomic::{AtomicU16, AtomicU32, Ordering};
 use std::sync::mpsc::{self, Receiver, Sender};
+use std::sync::{Arc, Mutex};
 use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant};
 
 // CPAL is used on Windows/macOS, but not on Linux (we use parec)
 #[cfg(not(target_os = "linux"))]
 use cpal::traits::{DeviceTrait, HostTrait, StreamTrait};
 #[cfg(not(target_os = "linux"))]
-use cpal::{Device, SampleFormat, Stream, StreamConfig};
+use cpal::{BufferSize, Device, SampleFormat, Stream, StreamConfig, SupportedBufferSize};
+#[cfg(not(target_os = "linux"))]
+use std::sync::atomic::{AtomicBool, AtomicU64};
 #[cfg(target_os = "linux")]
 use cpal::SampleFormat;
 
 // WASAPI host ID for explicit Windows audio handling
 #[cfg(target_os = "windows")]
 use cpal::HostId;
+use serde::Serialize;
 use thiserror::Error;
 use tracing::{debug, error, info, warn};
 
-// PipeWire native capture is available but we use parec for stability
+use super::generator::{SignalGenerator, TestSignal};
+use super::latency::SampleClock;
+use super::mixer::{AudioSource, Crossfade, Mixer};
+use super::network::{to_stereo, VbanReceiver, VbanSource, VBAN_DEFAULT_PORT};
+use super::recorder::{RecordingInfo, WavRecorder};
+use super::resample::LinearResampler;
+use super::ring_buffer::{AudioConsumer, AudioProducer, AudioRingBuffer};
+
+#[cfg(target_os = "macos")]
+use super::{macos_screen_capture, macos_tap};
+
 #[cfg(target_os = "linux")]
-#[allow(unused_imports)]
-use super::pipewire::{PipeWireCapture, PipeWireConfig};
+use super::pipewire::{PipeWireCapture, PipeWireConfig, APP_DEVICE_PREFIX};
 
 #[derive(Error, Debug)]
 pub enum AudioError {
@@ -39,8 +56,35 @@ pub enum AudioError {
     UnsupportedFormat(SampleFormat),
     #[error("Channel error: {0}")]
     ChannelError(String),
+    #[error("Recording error: {0}")]
+    RecordingError(String),
+    #[error("Audio device lost: {0}")]
+    DeviceLost(String),
 }
 
+/// How often silence is injected while a loopback stream delivers nothing
+#[cfg(not(target_os = "linux"))]
+const SILENCE_FILL_INTERVAL: Duration = Duration::from_millis(20);
+
+/// Ring buffer capacity per reader, in seconds of audio
+const RING_CAPACITY_SECS: usize = 1;
+
+/// Largest chunk returned by `AudioEngine::try_recv`, in samples
+const READ_CHUNK_SAMPLES: usize = 2048;
+/// Crossfade length when switching capture devices while running
+const DEVICE_CROSSFADE: Duration = Duration::from_millis(500);
+/// How often a capture thread checks whether its device is still there
+const DEVICE_CHECK_INTERVAL: Duration = Duration::from_millis(100);
+/// How often a lost device is looked for again
+const RECONNECT_INTERVAL: Duration = Duration::from_secs(2);
+/// Name reported for the system default device
+const DEFAULT_DEVICE_LABEL: &str = "default";
+
+/// Smallest capture buffer requested from a backend (frames)
+const MIN_BUFFER_FRAMES: usize = 64;
+/// Largest capture buffer requested from a backend (frames)
+const MAX_BUFFER_FRAMES: usize = 16384;
+
 /// Audio capture configuration
 #[derive(Debug, Clone)]
 pub struct AudioConfig {
@@ -48,10 +92,19 @@ pub struct AudioConfig {
     pub sample_rate: u32,
     /// Number of channels (1 or 2)
     pub channels: u16,
-    /// Buffer size in samples
+    /// Capture buffer size in frames (clamped to what the backend supports)
     pub buffer_size: usize,
     /// Device name (None for default)
     pub device_name: Option<String>,
+    /// Maximum audio backlog per reader before stale audio is dropped
+    pub max_latency_ms: u32,
+}
+
+impl AudioConfig {
+    /// Buffer size in frames, limited to a range every backend accepts
+    pub fn buffer_frames(&self) -> usize {
+        self.buffer_size.clamp(MIN_BUFFER_FRAMES, MAX_BUFFER_FRAMES)
+    }
 }
 
 impl Default for AudioConfig {
@@ -61,6 +114,7 @@ impl Default for AudioConfig {
             channels: 2,
             buffer_size: 1024,
             device_name: None,
+            max_latency_ms: 100,
         }
     }
 }
@@ -74,6 +128,8 @@ pub enum DeviceType {
     Output,
     /// Monitor device (Linux PulseAudio/PipeWire monitor)
     Monitor,
+    /// Output of a single application (Linux PipeWire)
+    Application,
 }
 
 /// Information about an audio device
@@ -104,6 +160,37 @@ pub enum AudioBackend {
     /// PulseAudio parec (Linux fallback)
     #[cfg(target_os = "linux")]
     PulseAudio,
+    /// ASIO driver (Windows, requires the `asio` feature)
+    #[cfg(all(target_os = "windows", feature = "asio"))]
+    Asio,
+    /// VBAN network stream
+    Network,
+    /// Built-in test signal
+    Generator,
+}
+
+/// Change in the availability of the capture device
+#[derive(Debug, Clone, PartialEq)]
+pub enum DeviceEvent {
+    /// The device stopped delivering audio
+    Lost {
+        device: String,
+        reason: String,
+        /// Device capture moved to in the meantime, if any
+        fallback: Option<String>,
+    },
+    /// A lost device is back and capture has returned to it
+    Reconnected { device: String },
+    /// Capture stopped for a reason other than the device going away
+    Failed { device: String, reason: String },
+}
+
+/// Format delivered by the capture device, before conversion to the
+/// configured output format
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub struct CaptureFormat {
+    pub sample_rate: u32,
+    pub channels: u16,
 }
 
 /// Commands sent to the audio thread
@@ -112,28 +199,129 @@ enum AudioCommand {
     Stop,
 }
 
+/// A capture thread that ended because its device was unavailable
+struct CaptureFailure {
+    generation: u64,
+    device_name: Option<String>,
+    error: AudioError,
+}
+
+/// Background check for a lost device coming back
+struct Reconnect {
+    /// Device to return to (`None` for the default device)
+    device: Option<String>,
+    /// Generation opened to reconnect, once the device was found
+    attempt: Option<u64>,
+    found_rx: Receiver<()>,
+    /// Dropping this ends the check
+    _cancel_tx: Sender<()>,
+}
+
+impl Reconnect {
+    /// Look for `device` every `RECONNECT_INTERVAL`
+    ///
+    /// The default device can't be looked up by name, so it is simply
+    /// reported as found after one interval to have it reopened.
+    fn watch(device: Option<String>) -> Self {
+        let (found_tx, found_rx) = mpsc::channel();
+        let (cancel_tx, cancel_rx) = mpsc::channel::<()>();
+        let name = device.clone();
+
+        thread::spawn(move || loop {
+            if cancel_rx.recv_timeout(RECONNECT_INTERVAL) != Err(mpsc::RecvTimeoutError::Timeout) {
+                return;
+            }
+            let present = match &name {
+                Some(name) => AudioEngine::list_devices().iter().any(|d| &d.name == name),
+                None => true,
+            };
+            if present {
+                let _ = found_tx.send(());
+                return;
+            }
+        });
+
+        Self {
+            device,
+            attempt: None,
+            found_rx,
+            _cancel_tx: cancel_tx,
+        }
+    }
+}
+
 /// Audio engine handle - manages audio capture in a separate thread
 ///
-/// This struct is Send + Sync safe because it only holds channels.
+/// This struct is Send + Sync safe because it only holds channels and ring
+/// buffer halves. Captured audio is published into a lock-free ring buffer per
+/// reader; readers that fall behind drop stale audio instead of queueing it.
 pub struct AudioEngine {
-    /// Channel to send commands to the audio thread
-    command_tx: Option<Sender<AudioCommand>>,
-    /// Channel to receive audio samples from the audio thread
-    sample_rx: Option<Receiver<Vec<f32>>>,
-    /// Thread handle
-    thread_handle: Option<JoinHandle<()>>,
+    /// Channels to send commands to the capture threads (one per source)
+    command_txs: Vec<Sender<AudioCommand>>,
+    /// Reader backing `try_recv`
+    reader: Option<AudioConsumer>,
+    /// Channel to register additional readers with the publishing thread
+    reader_tx: Option<Sender<AudioProducer>>,
+    /// Backlog limit for new readers, in frames
+    max_latency_frames: usize,
+    /// Capture threads followed by the resampling/mixing threads they feed
+    threads: Vec<JoinHandle<()>>,
     /// Whether the engine is running
     running: bool,
+    /// Sample rate actually delivered by the (first) capture backend
+    device_sample_rate: Arc<AtomicU32>,
+    /// Channel count actually delivered by the (first) capture backend
+    device_channels: Arc<AtomicU16>,
+    /// Sample rate of the stream handed to consumers
+    sample_rate: u32,
+    /// Active WAV recording, fed from `try_recv`
+    recorder: Option<WavRecorder>,
+    /// Config the engine was last started with
+    config: AudioConfig,
+    /// Generation-tagged feed into the output thread (single-device mode only)
+    feed_tx: Option<Sender<(u64, Vec<f32>)>>,
+    /// Generation of the most recently opened capture device
+    generation: u64,
+    /// Replaced capture threads, stopped once the fade away from them is done
+    retiring: Vec<(u64, Sender<AudioCommand>)>,
+    /// Generations that have fully faded in, reported by the output thread
+    switched_rx: Option<Receiver<u64>>,
+    /// Generation currently heard (single-device mode only)
+    active_generation: u64,
+    /// Capture threads report unavailable devices here
+    failure_tx: Sender<CaptureFailure>,
+    failure_rx: Receiver<CaptureFailure>,
+    /// Lost device being waited for
+    reconnect: Option<Reconnect>,
+    /// Publish times of the output stream, for timing reads
+    clock: Arc<Mutex<SampleClock>>,
 }
 
 impl AudioEngine {
     /// Create a new audio engine (not started yet)
     pub fn new() -> Self {
+        let (failure_tx, failure_rx) = mpsc::channel();
         Self {
-            command_tx: None,
-            sample_rx: None,
-            thread_handle: None,
+            command_txs: Vec::new(),
+            reader: None,
+            reader_tx: None,
+            max_latency_frames: 0,
+            threads: Vec::new(),
             running: false,
+            device_sample_rate: Arc::new(AtomicU32::new(AudioConfig::default().sample_rate)),
+            device_channels: Arc::new(AtomicU16::new(2)),
+            sample_rate: AudioConfig::default().sample_rate,
+            recorder: None,
+            config: AudioConfig::default(),
+            feed_tx: None,
+            generation: 0,
+            retiring: Vec::new(),
+            switched_rx: None,
+            active_generation: 0,
+            failure_tx,
+            failure_rx,
+            reconnect: None,
+            clock: Arc::new(Mutex::new(SampleClock::new())),
         }
     }
 
@@ -145,6 +333,13 @@ impl AudioEngine {
         // Don't use CPAL ALSA devices - they cause panics and block system audio
         #[cfg(target_os = "linux")]
         {
+            // Capture goes through PipeWire when its server is running, parec otherwise
+            let backend = if PipeWireCapture::is_available() {
+                AudioBackend::PipeWire
+            } else {
+                AudioBackend::PulseAudio
+            };
+
             // Add "System Audio (Auto)" as first option - will auto-detect monitor
             devices.push(DeviceInfo {
                 name: "auto".to_string(),
@@ -152,7 +347,7 @@ impl AudioEngine {
                 is_default: true,
                 is_monitor: true,
                 device_type: DeviceType::Monitor,
-                backend: AudioBackend::PulseAudio,
+                backend,
             });
 
             // List monitors from pactl (works with both PipeWire and PulseAudio)
@@ -182,7 +377,7 @@ impl AudioEngine {
                                     is_default: false,
                                     is_monitor: true,
                                     device_type: DeviceType::Monitor,
-                                    backend: AudioBackend::PulseAudio,
+                                    backend,
                                 });
                             }
                         }
@@ -190,6 +385,21 @@ impl AudioEngine {
                 }
             }
 
+            // Applications playing audio can be captured on their own through PipeWire
+            if backend == AudioBackend::PipeWire {
+                for app in PipeWireCapture::list_applications() {
+                    let label = app.application.as_deref().unwrap_or(&app.description);
+                    devices.push(DeviceInfo {
+                        description: format!("{} (Application)", label),
+                        name: format!("{}{}", APP_DEVICE_PREFIX, app.name),
+                        is_default: false,
+                        is_monitor: false,
+                        device_type: DeviceType::Application,
+                        backend,
+                    });
+                }
+            }
+
             info!("Found {} audio devices on Linux", devices.len());
         }
 
@@ -243,19 +453,50 @@ impl AudioEngine {
                 }
             }
 
-            // On macOS, loopback requires virtual audio devices (BlackHole, Loopback app)
-            // We still list output devices but they won't work without virtual device software
+            // List ASIO drivers (low-latency inputs exposed by most DJ mixers)
+            #[cfg(all(target_os = "windows", feature = "asio"))]
+            {
+                match cpal::host_from_id(HostId::Asio) {
+                    Ok(asio_host) => {
+                        if let Ok(input_devices) = asio_host.input_devices() {
+                            for device in input_devices {
+                                if let Ok(name) = device.name() {
+                                    devices.push(DeviceInfo {
+                                        description: format!("{} (ASIO)", name),
+                                        is_default: false,
+                                        is_monitor: false,
+                                        device_type: DeviceType::Input,
+                                        backend: AudioBackend::Asio,
+                                        name: format!("asio:{}", name), // Prefix to select the ASIO host
+                                    });
+                                }
+                            }
+                        }
+                    }
+                    Err(e) => debug!("ASIO host unavailable: {}", e),
+                }
+            }
+
+            // On macOS 13+, system audio is captured natively: through a CoreAudio tap
+            // on 14.2+, ScreenCaptureKit before that. Older versions need virtual audio
+            // devices (BlackHole, Loopback app)
             #[cfg(target_os = "macos")]
             {
+                if macos_tap::is_supported() || macos_screen_capture::is_supported() {
+                    devices.push(DeviceInfo {
+                        name: macos_tap::SYSTEM_AUDIO_DEVICE.to_string(),
+                        description: "System Audio".to_string(),
+                        is_default: false,
+                        is_monitor: true,
+                        device_type: DeviceType::Monitor,
+                        backend: AudioBackend::Cpal,
+                    });
+                }
+
                 if let Ok(output_devices) = host.output_devices() {
                     for device in output_devices {
                         if let Ok(name) = device.name() {
-                            // Check if this looks like a virtual loopback device
-                            let is_virtual = name.to_lowercase().contains("blackhole")
-                                || name.to_lowercase().contains("loopback")
-                                || name.to_lowercase().contains("soundflower");
-
-                            if is_virtual {
+                            if is_virtual_loopback(&name) {
                                 devices.push(DeviceInfo {
                                     description: format!("{} (Virtual)", name),
                                     is_default: false,
@@ -273,6 +514,29 @@ impl AudioEngine {
             info!("Found {} audio devices", devices.len());
         }
 
+
+        // Network audio is available on every platform
+        devices.push(DeviceInfo {
+            name: "vban".to_string(),
+            description: format!("Network Audio (VBAN, port {})", VBAN_DEFAULT_PORT),
+            is_default: false,
+            is_monitor: false,
+            device_type: DeviceType::Input,
+            backend: AudioBackend::Network,
+        });
+
+        // Test signals for setting up without live audio
+        for signal in TestSignal::presets() {
+            devices.push(DeviceInfo {
+                name: signal.device_name(),
+                description: signal.description(),
+                is_default: false,
+                is_monitor: false,
+                device_type: DeviceType::Input,
+                backend: AudioBackend::Generator,
+            });
+        }
+
         devices
     }
 
@@ -301,55 +565,464 @@ impl AudioEngine {
 
     /// Start the audio engine
     pub fn start(&mut self, config: AudioConfig) -> Result<(), AudioError> {
+        let device_name = config.device_name.clone();
+        self.launch(config, vec![(device_name, 1.0)])
+    }
+
+    /// Start capturing several devices mixed into one stream
+    ///
+    /// Each source is captured and resampled on its own, then mixed with its
+    /// gain before being handed to readers. `config.device_name` is ignored.
+    pub fn start_multi(&mut self, config: AudioConfig, sources: Vec<AudioSource>) -> Result<(), AudioError> {
+        if sources.is_empty() {
+            return Err(AudioError::NoInputDevice);
+        }
+        let sources = sources
+            .into_iter()
+            .map(|s| (Some(s.device_name), s.gain.max(0.0)))
+            .collect();
+        self.launch(config, sources)
+    }
+
+    fn launch(&mut self, config: AudioConfig, sources: Vec<(Option<String>, f32)>) -> Result<(), AudioError> {
         if self.running {
             warn!("Audio engine already running");
             return Ok(());
         }
 
-        let (command_tx, command_rx) = mpsc::channel();
-        let (sample_tx, sample_rx) = mpsc::channel();
+        let (reader_tx, reader_rx) = mpsc::channel();
+        let output_rate = config.sample_rate;
+
+        self.config = config.clone();
+        self.sample_rate = output_rate;
+        // A reader must be able to hold at least one capture buffer
+        self.max_latency_frames = ((output_rate as u64 * config.max_latency_ms as u64 / 1000) as usize)
+            .max(config.buffer_frames());
+        self.device_sample_rate.store(output_rate, Ordering::Relaxed);
+        self.device_channels.store(2, Ordering::Relaxed);
+
+        // Spawn one capture thread per source
+        let mut stages = Vec::new();
+        for (index, (device_name, _)) in sources.iter().enumerate() {
+            let (device_rate, device_channels) = if index == 0 {
+                (Arc::clone(&self.device_sample_rate), Arc::clone(&self.device_channels))
+            } else {
+                (Arc::new(AtomicU32::new(output_rate)), Arc::new(AtomicU16::new(2)))
+            };
+            let config = AudioConfig {
+                device_name: device_name.clone(),
+                ..config.clone()
+            };
+
+            let (command_tx, raw_rx) = self.spawn_capture(config, Arc::clone(&device_rate), device_channels, 0);
+            self.command_txs.push(command_tx);
+            stages.push((raw_rx, device_rate));
+        }
 
-        // Spawn the audio thread
-        let thread_handle = thread::spawn(move || {
-            if let Err(e) = run_audio_thread(config, command_rx, sample_tx) {
-                error!("Audio thread error: {}", e);
+        // Convert whatever each device delivers to the configured rate, mix if
+        // needed and publish to all readers. These threads end once the capture
+        // threads drop their senders.
+        self.clock = Arc::new(Mutex::new(SampleClock::new()));
+        let mut publisher = Publisher::new(reader_rx, Arc::clone(&self.clock));
+        if stages.len() == 1 {
+            let (raw_rx, device_rate) = stages.remove(0);
+            let (feed_tx, feed_rx) = mpsc::channel();
+            let (switched_tx, switched_rx) = mpsc::channel();
+            self.spawn_feed_stage(raw_rx, device_rate, 0, feed_tx.clone());
+
+            let gain = sources[0].1;
+            let fade_frames = (output_rate as u128 * DEVICE_CROSSFADE.as_millis() / 1000) as usize;
+            let max_backlog = self.max_latency_frames;
+            self.threads.push(thread::spawn(move || {
+                run_output_stage(feed_rx, switched_tx, publisher, gain, fade_frames, max_backlog);
+            }));
+            self.feed_tx = Some(feed_tx);
+            self.switched_rx = Some(switched_rx);
+        } else {
+            let (mix_tx, mix_rx) = mpsc::channel::<(usize, Vec<f32>)>();
+            for (index, (raw_rx, device_rate)) in stages.into_iter().enumerate() {
+                let mix_tx = mix_tx.clone();
+                self.threads.push(thread::spawn(move || {
+                    run_resample_stage(raw_rx, device_rate, output_rate, |samples| {
+                        let _ = mix_tx.send((index, samples));
+                    });
+                }));
             }
-        });
+            drop(mix_tx);
+
+            let gains = sources.iter().map(|(_, gain)| *gain).collect();
+            let max_backlog = self.max_latency_frames;
+            self.threads.push(thread::spawn(move || {
+                let mut mixer = Mixer::new(gains, 2, max_backlog);
+                for (index, samples) in mix_rx {
+                    mixer.push(index, &samples);
+                    let mixed = mixer.mix();
+                    if !mixed.is_empty() {
+                        publisher.publish(&mixed);
+                    }
+                }
+            }));
+            info!("Mixing {} audio sources", sources.len());
+        }
 
-        self.command_tx = Some(command_tx);
-        self.sample_rx = Some(sample_rx);
-        self.thread_handle = Some(thread_handle);
+        self.reader_tx = Some(reader_tx);
+        self.reader = self.subscribe();
         self.running = true;
 
         info!("Audio engine started");
         Ok(())
     }
 
+    /// Spawn a capture thread for one device, returning its command and sample channels
+    fn spawn_capture(
+        &mut self,
+        config: AudioConfig,
+        device_rate: Arc<AtomicU32>,
+        device_channels: Arc<AtomicU16>,
+        generation: u64,
+    ) -> (Sender<AudioCommand>, Receiver<Vec<f32>>) {
+        let (command_tx, command_rx) = mpsc::channel();
+        let (raw_tx, raw_rx) = mpsc::channel();
+        let failure_tx = self.failure_tx.clone();
+        self.threads.push(thread::spawn(move || {
+            let device_name = config.device_name.clone();
+            if let Err(error) = run_audio_thread(config, command_rx, raw_tx, device_rate, device_channels) {
+                error!("Audio thread error: {}", error);
+                let _ = failure_tx.send(CaptureFailure {
+                    generation,
+                    device_name,
+                    error,
+                });
+            }
+        }));
+        (command_tx, raw_rx)
+    }
+
+    /// Resample one device's audio and tag it with its generation for the output thread
+    fn spawn_feed_stage(
+        &mut self,
+        raw_rx: Receiver<Vec<f32>>,
+        device_rate: Arc<AtomicU32>,
+        generation: u64,
+        feed_tx: Sender<(u64, Vec<f32>)>,
+    ) {
+        let output_rate = self.sample_rate;
+        self.threads.push(thread::spawn(move || {
+            run_resample_stage(raw_rx, device_rate, output_rate, |samples| {
+                let _ = feed_tx.send((generation, samples));
+            });
+        }));
+    }
+
+    /// Switch to another capture device without stopping the engine
+    ///
+    /// The new device is opened next to the current one and crossfaded in as
+    /// soon as it delivers audio; the old device is closed once the fade is
+    /// done. Not available while mixing several sources.
+    pub fn switch_device(&mut self, device_name: Option<String>) -> Result<(), AudioError> {
+        // An explicit choice replaces any device being waited for
+        self.reconnect = None;
+        self.open_device(device_name)
+    }
+
+    /// Open a device as the newest generation and crossfade to it
+    fn open_device(&mut self, device_name: Option<String>) -> Result<(), AudioError> {
+        if !self.running {
+            return Err(AudioError::StreamError("Audio capture is not running".to_string()));
+        }
+        let feed_tx = self.feed_tx.clone().ok_or_else(|| {
+            AudioError::StreamError("Cannot switch devices while mixing several sources".to_string())
+        })?;
+
+        info!(
+            "Switching audio device to {}",
+            device_name.as_deref().unwrap_or("default")
+        );
+
+        self.generation += 1;
+        let device_rate = Arc::new(AtomicU32::new(self.sample_rate));
+        let device_channels = Arc::new(AtomicU16::new(2));
+        let config = AudioConfig {
+            device_name,
+            ..self.config.clone()
+        };
+        let (command_tx, raw_rx) =
+            self.spawn_capture(config, Arc::clone(&device_rate), Arc::clone(&device_channels), self.generation);
+        self.spawn_feed_stage(raw_rx, Arc::clone(&device_rate), self.generation, feed_tx);
+
+        if let Some(previous) = self.command_txs.pop() {
+            self.retiring.push((self.generation - 1, previous));
+        }
+        self.command_txs.push(command_tx);
+        self.device_sample_rate = device_rate;
+        self.device_channels = device_channels;
+        Ok(())
+    }
+
+    /// Stop capture threads whose audio has been fully faded out
+    fn retire_switched(&mut self) {
+        let Some(active) = self.switched_rx.as_ref().and_then(|rx| rx.try_iter().last()) else {
+            return;
+        };
+        self.active_generation = active;
+        self.retiring.retain(|(generation, tx)| {
+            if *generation < active {
+                let _ = tx.send(AudioCommand::Stop);
+                false
+            } else {
+                true
+            }
+        });
+    }
+
+    /// Recover from capture devices disappearing, returning what happened
+    ///
+    /// When the device in use is lost, capture falls back to the default
+    /// device and the lost one is looked for in the background; once it is
+    /// back the engine crossfades to it again. Sources of a mix are only
+    /// reported. Call regularly, e.g. alongside `try_recv`.
+    pub fn poll_device_events(&mut self) -> Vec<DeviceEvent> {
+        let mut events = Vec::new();
+        if !self.running {
+            return events;
+        }
+        self.retire_switched();
+
+        let failures: Vec<CaptureFailure> = self.failure_rx.try_iter().collect();
+        for failure in failures {
+            // Devices already being replaced don't matter any more
+            if failure.generation == self.generation {
+                events.extend(self.handle_failure(failure));
+            }
+        }
+
+        let Some(reconnect) = &mut self.reconnect else {
+            return events;
+        };
+        match reconnect.attempt {
+            Some(attempt) if self.active_generation >= attempt => {
+                let device = device_label(reconnect.device.as_deref());
+                info!("Reconnected to audio device {}", device);
+                events.push(DeviceEvent::Reconnected { device });
+                self.reconnect = None;
+            }
+            None if reconnect.found_rx.try_recv().is_ok() => {
+                let device = reconnect.device.clone();
+                reconnect.attempt = Some(self.generation + 1);
+                if let Err(e) = self.open_device(device) {
+                    warn!("Failed to reopen audio device: {}", e);
+                    self.reconnect = None;
+                }
+            }
+            _ => {}
+        }
+        events
+    }
+
+    /// Fall back from a lost device and start waiting for it
+    fn handle_failure(&mut self, failure: CaptureFailure) -> Option<DeviceEvent> {
+        let CaptureFailure {
+            generation,
+            device_name,
+            error,
+        } = failure;
+        let device_name = device_name.filter(|name| name != "auto" && !name.is_empty());
+        let device = device_label(device_name.as_deref());
+
+        // The device was listed again but still couldn't be opened: keep waiting
+        if self.reconnect.as_ref().is_some_and(|r| r.attempt == Some(generation)) {
+            debug!("Reconnecting to audio device {} failed: {}", device, error);
+            self.reconnect = Some(Reconnect::watch(device_name.clone()));
+            if device_name.is_some() {
+                if let Err(e) = self.open_device(None) {
+                    warn!("Failed to reopen default audio device: {}", e);
+                }
+            }
+            return None;
+        }
+
+        let unavailable = matches!(
+            error,
+            AudioError::DeviceLost(_) | AudioError::DeviceNotFound(_) | AudioError::NoInputDevice
+        );
+        if !unavailable {
+            return Some(DeviceEvent::Failed {
+                device,
+                reason: error.to_string(),
+            });
+        }
+        // Sources of a mix can't be replaced on their own
+        if self.feed_tx.is_none() {
+            return Some(DeviceEvent::Lost {
+                device,
+                reason: error.to_string(),
+                fallback: None,
+            });
+        }
+
+        let mut fallback = None;
+        if device_name.is_some() {
+            match self.open_device(None) {
+                Ok(()) => fallback = Some(DEFAULT_DEVICE_LABEL.to_string()),
+                Err(e) => warn!("Failed to open default audio device: {}", e),
+            }
+        }
+        // Losing the fallback doesn't change which device we want back
+        if self.reconnect.is_none() {
+            self.reconnect = Some(Reconnect::watch(device_name));
+        }
+
+        Some(DeviceEvent::Lost {
+            device,
+            reason: error.to_string(),
+            fallback,
+        })
+    }
+
     /// Stop the audio engine
     pub fn stop(&mut self) {
-        if let Some(tx) = self.command_tx.take() {
+        self.reconnect = None;
+
+        let retiring = self.retiring.drain(..).map(|(_, tx)| tx);
+        for tx in self.command_txs.drain(..).chain(retiring) {
             let _ = tx.send(AudioCommand::Stop);
         }
 
-        if let Some(handle) = self.thread_handle.take() {
+        // The output thread runs until every feed sender is gone
+        self.feed_tx = None;
+        self.switched_rx = None;
+        self.generation = 0;
+        self.active_generation = 0;
+
+        // Every capture thread has been told to stop, so the stages they feed wind down in turn
+        for handle in self.threads.drain(..) {
             let _ = handle.join();
         }
+        self.failure_rx.try_iter().for_each(drop);
 
-        self.sample_rx = None;
+        self.reader = None;
+        self.reader_tx = None;
         self.running = false;
 
+        if let Err(e) = self.stop_recording() {
+            warn!("{}", e);
+        }
+
         info!("Audio engine stopped");
     }
 
     /// Try to receive audio samples (non-blocking)
-    pub fn try_recv(&self) -> Option<Vec<f32>> {
-        self.sample_rx.as_ref()?.try_recv().ok()
+    ///
+    /// Returns interleaved chunks of at most a few thousand samples; call
+    /// until `None` to drain. Received samples are also written to the active
+    /// recording, if any.
+    pub fn try_recv(&mut self) -> Option<Vec<f32>> {
+        self.try_recv_timed().map(|(samples, _)| samples)
+    }
+
+    /// Like `try_recv`, also returning when the first sample of the chunk
+    /// was published by the capture pipeline
+    pub fn try_recv_timed(&mut self) -> Option<(Vec<f32>, Instant)> {
+        self.retire_switched();
+        let clock = self.clock.lock().unwrap();
+        let reader = self.reader.as_mut()?;
+        let samples = reader.pop_chunk(READ_CHUNK_SAMPLES)?;
+        // Whatever is still queued follows the chunk in the stream
+        let queued = (reader.available() + samples.len()) as u64;
+        let published_at = clock
+            .published_at(clock.published().saturating_sub(queued))
+            .unwrap_or_else(Instant::now);
+        drop(clock);
+
+        if let Some(recorder) = &self.recorder {
+            recorder.write(&samples);
+        }
+        Some((samples, published_at))
+    }
+
+    /// Register an additional reader of the captured stream
+    ///
+    /// Each reader gets its own lock-free ring buffer and sees every sample
+    /// published after it subscribed. Returns `None` if the engine isn't running.
+    pub fn subscribe(&self) -> Option<AudioConsumer> {
+        let reader_tx = self.reader_tx.as_ref()?;
+        let capacity = self.sample_rate as usize * 2 * RING_CAPACITY_SECS;
+        let (producer, consumer) =
+            AudioRingBuffer::new(capacity).into_split(2, self.max_latency_frames);
+        reader_tx.send(producer).ok()?;
+        Some(consumer)
+    }
+
+    /// Start recording the captured stream to a WAV file
+    pub fn start_recording(&mut self, path: impl AsRef<Path>) -> Result<(), AudioError> {
+        if !self.running {
+            return Err(AudioError::RecordingError("Audio capture is not running".to_string()));
+        }
+        if self.recorder.is_some() {
+            return Err(AudioError::RecordingError("Already recording".to_string()));
+        }
+
+        self.recorder = Some(WavRecorder::start(path, self.sample_rate(), 2)?);
+        Ok(())
+    }
+
+    /// Stop recording, returning details of the finished file
+    ///
+    /// Returns `Ok(None)` if no recording was active.
+    pub fn stop_recording(&mut self) -> Result<Option<RecordingInfo>, AudioError> {
+        self.recorder.take().map(WavRecorder::stop).transpose()
+    }
+
+    /// Path of the active recording, if any
+    pub fn recording_path(&self) -> Option<&Path> {
+        self.recorder.as_ref().map(WavRecorder::path)
     }
 
     /// Check if the engine is running
     pub fn is_running(&self) -> bool {
         self.running
     }
+
+    /// Config the engine was last started with
+    pub fn config(&self) -> &AudioConfig {
+        &self.config
+    }
+
+    /// Sample rate of the samples returned by `try_recv`
+    ///
+    /// Always the configured `AudioConfig::sample_rate`; the capture stream is
+    /// resampled to it when the device runs at a different rate.
+    pub fn sample_rate(&self) -> u32 {
+        self.sample_rate
+    }
+
+    /// Sample rate negotiated with the capture device
+    pub fn device_sample_rate(&self) -> u32 {
+        self.device_sample_rate.load(Ordering::Relaxed)
+    }
+
+    /// Format negotiated with the capture device
+    ///
+    /// Captured audio is always converted to stereo at `sample_rate()`; this
+    /// is what the device delivered before that.
+    pub fn capture_format(&self) -> CaptureFormat {
+        CaptureFormat {
+            sample_rate: self.device_sample_rate(),
+            channels: self.device_channels.load(Ordering::Relaxed),
+        }
+    }
+
+    /// Channels carrying distinct audio in the stream from `try_recv`
+    ///
+    /// The stream is always interleaved stereo; when a mono device is the
+    /// only source both channels are identical and this returns 1.
+    pub fn content_channels(&self) -> u16 {
+        if self.feed_tx.is_some() && self.device_channels.load(Ordering::Relaxed) == 1 {
+            1
+        } else {
+            2
+        }
+    }
 }
 
 impl Default for AudioEngine {
@@ -364,32 +1037,195 @@ impl Drop for AudioEngine {
     }
 }
 
+/// Name of a device for events and logs
+fn device_label(device_name: Option<&str>) -> String {
+    device_name.unwrap_or(DEFAULT_DEVICE_LABEL).to_string()
+}
+
+/// Fans processed audio out to every subscribed reader
+struct Publisher {
+    reader_rx: Receiver<AudioProducer>,
+    readers: Vec<AudioProducer>,
+    clock: Arc<Mutex<SampleClock>>,
+}
+
+impl Publisher {
+    fn new(reader_rx: Receiver<AudioProducer>, clock: Arc<Mutex<SampleClock>>) -> Self {
+        Self {
+            reader_rx,
+            readers: Vec::new(),
+            clock,
+        }
+    }
+
+    fn publish(&mut self, samples: &[f32]) {
+        self.readers.extend(self.reader_rx.try_iter());
+        self.readers.retain(AudioProducer::is_connected);
+        // Stamp under the clock lock so readers never see unstamped samples
+        let mut clock = self.clock.lock().unwrap();
+        for reader in &mut self.readers {
+            reader.push(samples);
+        }
+        clock.mark(samples.len(), Instant::now());
+    }
+}
+
+/// Publish the single-device stream, crossfading to each newer device
+/// generation as soon as it starts delivering audio
+///
+/// Generations that have fully taken over are reported on `switched_tx` so
+/// the engine can close the devices they replaced.
+fn run_output_stage(
+    feed_rx: Receiver<(u64, Vec<f32>)>,
+    switched_tx: Sender<u64>,
+    mut publisher: Publisher,
+    gain: f32,
+    fade_frames: usize,
+    max_backlog: usize,
+) {
+    let mut active = 0;
+    let mut fade: Option<(u64, Crossfade)> = None;
+
+    for (generation, samples) in feed_rx {
+        // A newer device is delivering: (re)start the fade from the active one
+        if generation > fade.as_ref().map_or(active, |(incoming, _)| *incoming) {
+            fade = Some((generation, Crossfade::new(2, fade_frames, max_backlog)));
+        }
+
+        let mut output = match &mut fade {
+            Some((incoming, crossfade)) => {
+                if generation == *incoming {
+                    crossfade.push_incoming(&samples);
+                } else if generation == active {
+                    crossfade.push_outgoing(&samples);
+                } else {
+                    continue;
+                }
+
+                let mut mixed = crossfade.mix();
+                if crossfade.is_finished() {
+                    mixed.extend(crossfade.take_incoming());
+                    active = *incoming;
+                    info!("Audio device switch complete");
+                    let _ = switched_tx.send(active);
+                }
+                mixed
+            }
+            None if generation == active => samples,
+            None => continue,
+        };
+        if fade.as_ref().is_some_and(|(incoming, _)| *incoming == active) {
+            fade = None;
+        }
+
+        if gain != 1.0 {
+            output.iter_mut().for_each(|s| *s *= gain);
+        }
+        if !output.is_empty() {
+            publisher.publish(&output);
+        }
+    }
+}
+
+/// Resample captured audio to the output rate and pass it on
+///
+/// The device rate is re-read for every buffer since it is only known once the
+/// capture backend has opened its stream.
+fn run_resample_stage(
+    raw_rx: Receiver<Vec<f32>>,
+    device_sample_rate: Arc<AtomicU32>,
+    output_rate: u32,
+    mut sink: impl FnMut(Vec<f32>),
+) {
+    let mut resampler = LinearResampler::new(2, output_rate, output_rate);
+    let mut logged_rate = output_rate;
+
+    for samples in raw_rx {
+        let input_rate = device_sample_rate.load(Ordering::Relaxed);
+        if input_rate != logged_rate {
+            info!("Resampling capture from {} Hz to {} Hz", input_rate, output_rate);
+            logged_rate = input_rate;
+        }
+        resampler.set_rates(input_rate, output_rate);
+
+        let samples = if resampler.is_passthrough() {
+            samples
+        } else {
+            resampler.process(&samples)
+        };
+        if !samples.is_empty() {
+            sink(samples);
+        }
+    }
+}
+
 /// Run the audio capture in a separate thread
 fn run_audio_thread(
     config: AudioConfig,
     command_rx: Receiver<AudioCommand>,
     sample_tx: Sender<Vec<f32>>,
+    sample_rate: Arc<AtomicU32>,
+    channels: Arc<AtomicU16>,
 ) -> Result<(), AudioError> {
-    // On Linux, ALWAYS use parec to avoid CPAL/ALSA panics and system audio blocking
+    // Network streams are handled the same on every platform
+    if let Some(name) = config.device_name.as_deref().filter(|n| VbanSource::matches(n)) {
+        let source = VbanSource::parse(name)?;
+        return run_vban_capture(source, command_rx, sample_tx, sample_rate, &channels);
+    }
+    if let Some(name) = config.device_name.as_deref().filter(|n| TestSignal::matches(n)) {
+        let signal = TestSignal::parse(name)?;
+        return run_test_signal(signal, &config, command_rx, sample_tx, sample_rate);
+    }
+
+    // On Linux, never use CPAL/ALSA (panics, blocks system audio): capture through
+    // native PipeWire, or parec when no PipeWire server is reachable
     #[cfg(target_os = "linux")]
     {
-        let device_name = config.device_name.clone().unwrap_or_else(|| "auto".to_string());
+        let device_name = config
+            .device_name
+            .clone()
+            .filter(|name| name != "auto" && !name.is_empty());
+
+        // "auto" captures the default sink's monitor
+        let pw_config = PipeWireConfig {
+            capture_sink: device_name.is_none(),
+            target: device_name.clone(),
+            sample_rate: config.sample_rate,
+            channels: config.channels.clamp(1, 2) as u32,
+            buffer_frames: config.buffer_frames() as u32,
+        };
+        channels.store(pw_config.channels as u16, Ordering::Relaxed);
+        match run_pipewire_native_capture(pw_config, &command_rx, sample_tx.clone(), Arc::clone(&sample_rate)) {
+            Ok(()) => return Ok(()),
+            Err(e @ AudioError::DeviceLost(_)) => return Err(e),
+            Err(e) => warn!("Native PipeWire capture unavailable ({}), falling back to parec", e),
+        }
 
         // If "auto" or empty, find the default monitor
-        let actual_device = if device_name == "auto" || device_name.is_empty() {
-            AudioEngine::find_default_monitor()
-                .ok_or_else(|| AudioError::NoInputDevice)?
-        } else {
-            device_name
+        let actual_device = match device_name {
+            Some(name) if name.starts_with(APP_DEVICE_PREFIX) => {
+                return Err(AudioError::ConfigError(format!(
+                    "Capturing {} requires a PipeWire server",
+                    name
+                )));
+            }
+            Some(name) => name,
+            None => AudioEngine::find_default_monitor().ok_or(AudioError::NoInputDevice)?,
         };
 
         info!("Linux audio capture using parec with device: {}", actual_device);
-        return run_parec_capture(actual_device, command_rx, sample_tx);
+        run_parec_capture(actual_device, &config, command_rx, sample_tx, &sample_rate, &channels)
     }
 
     // On Windows/macOS, use CPAL
     #[cfg(not(target_os = "linux"))]
     {
+        // ASIO devices are addressed as "asio:<driver name>" and use their own host
+        #[cfg(all(target_os = "windows", feature = "asio"))]
+        if let Some(driver) = config.device_name.as_deref().and_then(|n| n.strip_prefix("asio:")) {
+            return run_asio_capture(driver, config.buffer_frames(), command_rx, sample_tx, sample_rate, &channels);
+        }
+
         // On Windows, explicitly use WASAPI host for proper loopback support
         #[cfg(target_os = "windows")]
         let host = cpal::host_from_id(HostId::Wasapi)
@@ -400,6 +1236,41 @@ fn run_audio_thread(
 
         info!("Using audio host: {:?}", host.id());
 
+        // macOS system audio: wrap a process tap in an aggregate device (14.2+) or
+        // stream through ScreenCaptureKit (13.0-14.1), falling back to a virtual
+        // loopback device (BlackHole etc.) when neither works. The tap is torn down
+        // when this thread exits.
+        #[cfg(target_os = "macos")]
+        let mut config = config;
+        #[cfg(target_os = "macos")]
+        let _system_tap = if config.device_name.as_deref() == Some(macos_tap::SYSTEM_AUDIO_DEVICE) {
+            let tap = if macos_tap::is_supported() {
+                macos_tap::SystemAudioTap::create()
+            } else {
+                match run_screen_capture_audio(&command_rx, sample_tx.clone(), &sample_rate, &channels) {
+                    Ok(()) => return Ok(()),
+                    Err(e @ AudioError::DeviceLost(_)) => return Err(e),
+                    Err(e) => Err(e),
+                }
+            };
+            match tap {
+                Ok(tap) => {
+                    config.device_name = Some(tap.device_name().to_string());
+                    Some(tap)
+                }
+                Err(e) => {
+                    warn!("System audio capture unavailable ({}), looking for a virtual loopback device", e);
+                    let fallback = host.input_devices().ok().and_then(|mut devices| {
+                        devices.find_map(|d| d.name().ok().filter(|n| is_virtual_loopback(n)))
+                    });
+                    config.device_name = Some(fallback.ok_or(e)?);
+                    None
+                }
+            }
+        } else {
+            None
+        };
+
         // Check if this is a loopback device (Windows only)
         let (device, is_loopback) = if let Some(ref name) = config.device_name {
             if name.starts_with("loopback:") {
@@ -474,24 +1345,32 @@ fn run_audio_thread(
         );
 
         let sample_format = supported_config.sample_format();
-        let stream_config: StreamConfig = supported_config.into();
+        let buffer_size = fixed_buffer_size(supported_config.buffer_size(), config.buffer_frames());
+        let mut stream_config: StreamConfig = supported_config.into();
+        stream_config.buffer_size = buffer_size;
+        sample_rate.store(stream_config.sample_rate, Ordering::Relaxed);
+        channels.store(stream_config.channels, Ordering::Relaxed);
 
         // Build the stream based on sample format
         // Note: For loopback on Windows WASAPI, we use build_input_stream on an output device.
         // WASAPI handles loopback capture internally. Important: loopback only produces audio
         // when something is actually playing through that device.
         info!(
-            "Building audio stream: format={:?}, rate={}, channels={}, loopback={}",
+            "Building audio stream: format={:?}, rate={}, channels={}, buffer={:?}, loopback={}",
             sample_format,
             stream_config.sample_rate,
             stream_config.channels,
+            stream_config.buffer_size,
             is_loopback
         );
 
+        let callback_count = Arc::new(AtomicU64::new(0));
+        let device_lost = Arc::new(AtomicBool::new(false));
+        let stream_tx = sample_tx.clone();
         let stream = match sample_format {
-            SampleFormat::F32 => build_stream::<f32>(&device, &stream_config, sample_tx, is_loopback)?,
-            SampleFormat::I16 => build_stream::<i16>(&device, &stream_config, sample_tx, is_loopback)?,
-            SampleFormat::U16 => build_stream::<u16>(&device, &stream_config, sample_tx, is_loopback)?,
+            SampleFormat::F32 => build_stream::<f32>(&device, &stream_config, stream_tx, is_loopback, callback_count.clone(), device_lost.clone())?,
+            SampleFormat::I16 => build_stream::<i16>(&device, &stream_config, stream_tx, is_loopback, callback_count.clone(), device_lost.clone())?,
+            SampleFormat::U16 => build_stream::<u16>(&device, &stream_config, stream_tx, is_loopback, callback_count.clone(), device_lost.clone())?,
             format => return Err(AudioError::UnsupportedFormat(format)),
         };
 
@@ -500,10 +1379,11 @@ fn run_audio_thread(
 
         if is_loopback {
             info!("Note: WASAPI loopback only captures audio when something is playing through the device");
+            // Keep decks fed with silence so visuals don't freeze between tracks
+            run_silence_fill(&command_rx, &sample_tx, &callback_count, &device_lost, &stream_config)?;
+        } else {
+            wait_for_stop(&command_rx, || device_lost.load(Ordering::Relaxed))?;
         }
-
-        // Wait for stop command (blocks until Stop received or channel closed)
-        let _ = command_rx.recv();
         info!("Audio thread stopping");
 
         // Stream is dropped here, stopping capture
@@ -511,74 +1391,264 @@ fn run_audio_thread(
     }
 }
 
+/// Capture audio from an ASIO driver (Windows, `asio` feature)
+#[cfg(all(target_os = "windows", feature = "asio"))]
+fn run_asio_capture(
+    driver_name: &str,
+    buffer_frames: usize,
+    command_rx: Receiver<AudioCommand>,
+    sample_tx: Sender<Vec<f32>>,
+    sample_rate: Arc<AtomicU32>,
+    channels: &AtomicU16,
+) -> Result<(), AudioError> {
+    let host = cpal::host_from_id(HostId::Asio)
+        .map_err(|e| AudioError::StreamError(format!("Failed to get ASIO host: {}", e)))?;
+
+    let device = host
+        .input_devices()
+        .map_err(|e| AudioError::StreamError(e.to_string()))?
+        .find(|d| d.name().ok().as_deref() == Some(driver_name))
+        .ok_or_else(|| AudioError::DeviceNotFound(driver_name.to_string()))?;
+
+    let supported_config = device
+        .default_input_config()
+        .map_err(|e| AudioError::ConfigError(format!("ASIO config error: {}", e)))?;
+
+    let sample_format = supported_config.sample_format();
+    let buffer_size = fixed_buffer_size(supported_config.buffer_size(), buffer_frames);
+    let mut stream_config: StreamConfig = supported_config.into();
+    stream_config.buffer_size = buffer_size;
+    sample_rate.store(stream_config.sample_rate, Ordering::Relaxed);
+    channels.store(stream_config.channels, Ordering::Relaxed);
+
+    info!(
+        "Building ASIO stream: driver={}, format={:?}, rate={}, channels={}, buffer={:?}",
+        driver_name, sample_format, stream_config.sample_rate, stream_config.channels,
+        stream_config.buffer_size
+    );
+
+    // ASIO drivers commonly deliver 32-bit integer samples
+    let callback_count = Arc::new(AtomicU64::new(0));
+    let device_lost = Arc::new(AtomicBool::new(false));
+    let stream = match sample_format {
+        SampleFormat::F32 => build_stream::<f32>(&device, &stream_config, sample_tx, false, callback_count, device_lost.clone())?,
+        SampleFormat::I32 => build_stream::<i32>(&device, &stream_config, sample_tx, false, callback_count, device_lost.clone())?,
+        SampleFormat::I16 => build_stream::<i16>(&device, &stream_config, sample_tx, false, callback_count, device_lost.clone())?,
+        format => return Err(AudioError::UnsupportedFormat(format)),
+    };
+
+    stream.play().map_err(|e| AudioError::StreamError(e.to_string()))?;
+    info!("ASIO stream started");
+
+    let result = wait_for_stop(&command_rx, || device_lost.load(Ordering::Relaxed));
+    info!("ASIO capture stopping");
+
+    result
+}
+
+/// Request a fixed buffer size within the range the device supports
+///
+/// Devices that don't report a range keep their default buffer size, since
+/// an unsupported fixed size makes stream creation fail.
+#[cfg(not(target_os = "linux"))]
+fn fixed_buffer_size(supported: &SupportedBufferSize, frames: usize) -> BufferSize {
+    match *supported {
+        SupportedBufferSize::Range { min, max } => {
+            BufferSize::Fixed((frames as u32).clamp(min, max.max(min)))
+        }
+        SupportedBufferSize::Unknown => BufferSize::Default,
+    }
+}
+
+/// Whether a device name looks like virtual loopback software (macOS)
+#[cfg(target_os = "macos")]
+fn is_virtual_loopback(name: &str) -> bool {
+    let name = name.to_lowercase();
+    name.contains("blackhole") || name.contains("loopback") || name.contains("soundflower")
+}
+
+/// Receive a VBAN stream over UDP
+///
+/// The stream's own rate is reported through `sample_rate` so the resampling
+/// stage can convert it; mono and multichannel streams are mapped to stereo.
+fn run_vban_capture(
+    source: VbanSource,
+    command_rx: Receiver<AudioCommand>,
+    sample_tx: Sender<Vec<f32>>,
+    sample_rate: Arc<AtomicU32>,
+    channels: &AtomicU16,
+) -> Result<(), AudioError> {
+    let mut receiver = VbanReceiver::bind(source, Duration::from_millis(50))?;
+    let mut format: Option<(u32, u16)> = None;
+    let mut last_counter: Option<u32> = None;
+
+    loop {
+        match command_rx.try_recv() {
+            Ok(AudioCommand::Stop) | Err(mpsc::TryRecvError::Disconnected) => {
+                info!("Stopping VBAN capture");
+                return Ok(());
+            }
+            Err(mpsc::TryRecvError::Empty) => {}
+        }
+
+        let frame = match receiver.recv() {
+            Ok(Some(frame)) => frame,
+            Ok(None) => continue,
+            Err(e) => return Err(AudioError::StreamError(format!("VBAN receive failed: {}", e))),
+        };
+
+        if format != Some((frame.sample_rate, frame.channels)) {
+            info!(
+                "VBAN stream '{}': {} Hz, {} channels",
+                frame.stream_name, frame.sample_rate, frame.channels
+            );
+            sample_rate.store(frame.sample_rate, Ordering::Relaxed);
+            channels.store(frame.channels, Ordering::Relaxed);
+            format = Some((frame.sample_rate, frame.channels));
+        }
+        if let Some(last) = last_counter {
+            let lost = frame.frame_counter.wrapping_sub(last).wrapping_sub(1);
+            if lost > 0 && lost < u32::MAX / 2 {
+                debug!("VBAN: {} packets lost", lost);
+            }
+        }
+        last_counter = Some(frame.frame_counter);
+
+        if sample_tx.send(to_stereo(&frame.samples, frame.channels)).is_err() {
+            return Ok(());
+        }
+    }
+}
+
+/// Play a test signal in real time at the configured rate
+fn run_test_signal(
+    signal: TestSignal,
+    config: &AudioConfig,
+    command_rx: Receiver<AudioCommand>,
+    sample_tx: Sender<Vec<f32>>,
+    sample_rate: Arc<AtomicU32>,
+) -> Result<(), AudioError> {
+    let rate = config.sample_rate.max(1);
+    let frames = config.buffer_frames();
+    sample_rate.store(rate, Ordering::Relaxed);
+    info!("Playing {}", signal.description());
+
+    let mut generator = SignalGenerator::new(signal, rate);
+    let start = Instant::now();
+    let mut sent_frames = 0u64;
+
+    loop {
+        // Pace by the wall clock so buffers neither drift nor arrive in bursts
+        let due = start + Duration::from_secs_f64(sent_frames as f64 / rate as f64);
+        match command_rx.recv_timeout(due.saturating_duration_since(Instant::now())) {
+            Ok(AudioCommand::Stop) | Err(mpsc::RecvTimeoutError::Disconnected) => {
+                info!("Stopping test signal");
+                return Ok(());
+            }
+            Err(mpsc::RecvTimeoutError::Timeout) => {}
+        }
+
+        if sample_tx.send(generator.generate(frames)).is_err() {
+            return Ok(());
+        }
+        sent_frames += frames as u64;
+    }
+}
+
 /// Capture audio from a PulseAudio/PipeWire monitor device using parec
+///
+/// parec records 32-bit float at the configured rate and channel count
+/// (mono or stereo); mono is mapped to stereo like every other backend.
 #[cfg(target_os = "linux")]
 fn run_parec_capture(
     device_name: String,
+    config: &AudioConfig,
     command_rx: Receiver<AudioCommand>,
     sample_tx: Sender<Vec<f32>>,
+    sample_rate: &AtomicU32,
+    channels: &AtomicU16,
 ) -> Result<(), AudioError> {
     use std::io::Read;
     use std::process::{Command, Stdio};
 
     info!("Using PulseAudio monitor device: {}", device_name);
 
+    let rate = config.sample_rate.max(1);
+    let channel_count = config.channels.clamp(1, 2);
+    let buffer_frames = config.buffer_frames();
+
     // Start parec to capture audio
-    // Format: 32-bit float, stereo, 44100 Hz
+    let rate_arg = format!("--rate={}", rate);
+    let channels_arg = format!("--channels={}", channel_count);
+    let latency_arg = format!("--latency-msec={}", buffer_latency_ms(buffer_frames, rate));
     let mut child = Command::new("parec")
         .args([
             "--device", &device_name,
             "--format=float32le",
-            "--channels=2",
-            "--rate=44100",
-            "--latency-msec=50",
+            &channels_arg,
+            &rate_arg,
+            &latency_arg,
         ])
         .stdout(Stdio::piped())
-        .stderr(Stdio::null())
+        .stderr(Stdio::piped())
         .spawn()
-        .map_err(|e| AudioError::StreamError(format!("Failed to start parec: {}", e)))?;
+        .map_err(|e| match e.kind() {
+            std::io::ErrorKind::NotFound => AudioError::StreamError(
+                "parec not found; install pulseaudio-utils to capture without PipeWire".to_string(),
+            ),
+            _ => AudioError::StreamError(format!("Failed to start parec: {}", e)),
+        })?;
 
     let mut stdout = child.stdout.take()
         .ok_or_else(|| AudioError::StreamError("Failed to get parec stdout".to_string()))?;
 
-    info!("PulseAudio capture started");
+    sample_rate.store(rate, Ordering::Relaxed);
+    channels.store(channel_count, Ordering::Relaxed);
+    info!(
+        "PulseAudio capture started: float32le, {} Hz, {} channels ({})",
+        rate, channel_count, latency_arg
+    );
 
-    // Read audio data in chunks
-    let chunk_size = 4096; // samples (2 channels * 2048 frames)
-    let mut buffer = vec![0u8; chunk_size * 4]; // 4 bytes per f32
+    // Read whole buffers so frames never split across reads
+    let mut buffer = vec![0u8; buffer_frames * channel_count as usize * 4]; // 4 bytes per f32
 
-    loop {
+    let result = loop {
         // Check for stop command
         match command_rx.try_recv() {
             Ok(AudioCommand::Stop) | Err(std::sync::mpsc::TryRecvError::Disconnected) => {
                 info!("PulseAudio capture stopping");
-                break;
+                break Ok(());
             }
             Err(std::sync::mpsc::TryRecvError::Empty) => {}
         }
 
         // Read audio data
-        match stdout.read(&mut buffer) {
-            Ok(0) => {
-                // EOF - parec exited
-                warn!("parec exited unexpectedly");
-                break;
-            }
-            Ok(n) => {
+        match stdout.read_exact(&mut buffer) {
+            Ok(()) => {
                 // Convert bytes to f32 samples
-                let samples: Vec<f32> = buffer[..n]
+                let samples: Vec<f32> = buffer
                     .chunks_exact(4)
                     .map(|chunk| f32::from_le_bytes([chunk[0], chunk[1], chunk[2], chunk[3]]))
                     .collect();
 
-                let _ = sample_tx.send(samples);
+                let _ = sample_tx.send(to_stereo(&samples, channel_count));
+            }
+            Err(e) if e.kind() == std::io::ErrorKind::UnexpectedEof => {
+                // parec exits when its source is removed; its last words say why
+                let mut stderr = String::new();
+                if let Some(mut pipe) = child.stderr.take() {
+                    let _ = pipe.read_to_string(&mut stderr);
+                }
+                let reason = stderr.lines().last().unwrap_or("parec exited").to_string();
+                warn!("parec exited unexpectedly: {}", reason);
+                break Err(AudioError::DeviceLost(format!("{}: {}", device_name, reason)));
             }
             Err(e) => {
                 error!("Error reading from parec: {}", e);
-                break;
+                break Err(AudioError::DeviceLost(format!("parec read failed: {}", e)));
             }
         }
-    }
+    };
 
     // Proper cleanup: kill then wait to avoid zombie processes
     // drop stdout first to close the pipe, which helps parec exit cleanly
@@ -603,36 +1673,129 @@ fn run_parec_capture(
         }
     }
 
-    Ok(())
+    result
+}
+
+/// Duration of `frames` at `sample_rate`, in whole milliseconds (at least 1)
+#[cfg_attr(not(target_os = "linux"), allow(dead_code))]
+fn buffer_latency_ms(frames: usize, sample_rate: u32) -> u32 {
+    let ms = frames as u64 * 1000 / sample_rate.max(1) as u64;
+    ms.clamp(1, u32::MAX as u64) as u32
 }
 
 /// Capture audio using native PipeWire (no subprocess)
-/// Note: Currently unused - using parec for better stability
+///
+/// Fails without consuming a stop command when the stream can't be set up,
+/// so the caller can fall back to parec.
 #[cfg(target_os = "linux")]
-#[allow(dead_code)]
 fn run_pipewire_native_capture(
-    device_name: String,
-    command_rx: Receiver<AudioCommand>,
+    config: PipeWireConfig,
+    command_rx: &Receiver<AudioCommand>,
     sample_tx: Sender<Vec<f32>>,
+    sample_rate: Arc<AtomicU32>,
 ) -> Result<(), AudioError> {
-    info!("Starting PipeWire native capture for: {}", device_name);
+    info!(
+        "Starting PipeWire native capture for: {}",
+        config.target.as_deref().unwrap_or("default monitor")
+    );
 
     let mut capture = PipeWireCapture::new();
+    capture.start(config, sample_tx, sample_rate)?;
 
-    let config = PipeWireConfig {
-        target: Some(device_name.clone()),
-        sample_rate: 44100,
-        channels: 2,
-    };
-
-    capture.start(config, sample_tx.clone())?;
-
-    // Wait for stop command (blocks until Stop received or channel closed)
-    let _ = command_rx.recv();
+    let result = wait_for_stop(command_rx, || capture.is_disconnected());
     info!("PipeWire native capture stopping");
 
     capture.stop();
-    Ok(())
+    result
+}
+
+/// Capture system audio through ScreenCaptureKit (macOS 13.0-14.1)
+///
+/// Fails without consuming a stop command when the stream can't be set up,
+/// so the caller can fall back to a virtual loopback device.
+#[cfg(target_os = "macos")]
+fn run_screen_capture_audio(
+    command_rx: &Receiver<AudioCommand>,
+    sample_tx: Sender<Vec<f32>>,
+    sample_rate: &AtomicU32,
+    channels: &AtomicU16,
+) -> Result<(), AudioError> {
+    sample_rate.store(macos_screen_capture::SAMPLE_RATE, Ordering::Relaxed);
+    channels.store(2, Ordering::Relaxed);
+    let capture = macos_screen_capture::ScreenCaptureAudio::start(sample_tx)?;
+
+    let result = wait_for_stop(command_rx, || capture.is_stopped());
+    info!("ScreenCaptureKit capture stopping");
+    result
+}
+
+/// Block until stop is requested, or fail once `is_lost` reports the device gone
+fn wait_for_stop(command_rx: &Receiver<AudioCommand>, is_lost: impl Fn() -> bool) -> Result<(), AudioError> {
+    loop {
+        match command_rx.recv_timeout(DEVICE_CHECK_INTERVAL) {
+            Ok(AudioCommand::Stop) | Err(mpsc::RecvTimeoutError::Disconnected) => return Ok(()),
+            Err(mpsc::RecvTimeoutError::Timeout) => {}
+        }
+        if is_lost() {
+            return Err(AudioError::DeviceLost("device disconnected".to_string()));
+        }
+    }
+}
+
+/// Push zeroed buffers while a loopback stream is idle, until stop is requested
+///
+/// WASAPI loopback only fires callbacks while something is playing. When no
+/// callback arrived during the last interval, an interval's worth of silence
+/// is sent at the stream's rate so projectM keeps animating. Fails once the
+/// stream reports its device gone.
+///
+/// The silence is stereo like the callbacks' output, whatever the device's
+/// channel count.
+#[cfg(not(target_os = "linux"))]
+fn run_silence_fill(
+    command_rx: &Receiver<AudioCommand>,
+    sample_tx: &Sender<Vec<f32>>,
+    callback_count: &AtomicU64,
+    device_lost: &AtomicBool,
+    config: &StreamConfig,
+) -> Result<(), AudioError> {
+    let frames = config.sample_rate as u128 * SILENCE_FILL_INTERVAL.as_millis() / 1000;
+    let silence = vec![0.0f32; frames as usize * 2];
+
+    let mut last_count = callback_count.load(Ordering::Relaxed);
+    let mut last_activity = Instant::now();
+    let mut filling = false;
+
+    loop {
+        match command_rx.recv_timeout(SILENCE_FILL_INTERVAL) {
+            Ok(AudioCommand::Stop) | Err(mpsc::RecvTimeoutError::Disconnected) => return Ok(()),
+            Err(mpsc::RecvTimeoutError::Timeout) => {}
+        }
+        if device_lost.load(Ordering::Relaxed) {
+            return Err(AudioError::DeviceLost("device disconnected".to_string()));
+        }
+
+        let count = callback_count.load(Ordering::Relaxed);
+        if count != last_count {
+            last_count = count;
+            last_activity = Instant::now();
+            if filling {
+                debug!("Loopback audio resumed, stopping silence fill");
+                filling = false;
+            }
+            continue;
+        }
+
+        if last_activity.elapsed() >= SILENCE_FILL_INTERVAL {
+            if !filling {
+                debug!("No loopback audio, injecting silence");
+                filling = true;
+            }
+            if sample_tx.send(silence.clone()).is_err() {
+                return Ok(());
+            }
+        }
+    }
 }
 
 #[cfg(not(target_os = "linux"))]
@@ -641,25 +1804,24 @@ fn build_stream<T>(
     config: &StreamConfig,
     tx: Sender<Vec<f32>>,
     is_loopback: bool,
+    callback_count: Arc<AtomicU64>,
+    device_lost: Arc<AtomicBool>,
 ) -> Result<Stream, AudioError>
 where
     T: cpal::Sample + cpal::SizedSample,
     f32: cpal::FromSample<T>,
 {
-    use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
-    use std::sync::Arc;
-
-    // Track callback activity for debugging
-    let callback_count = Arc::new(AtomicU64::new(0));
-    let callback_count_clone = callback_count.clone();
+    // callback_count tracks activity for debugging and loopback silence fill
     let has_logged_first_callback = Arc::new(AtomicBool::new(false));
     let has_logged_first_clone = has_logged_first_callback.clone();
 
+    let channels = config.channels;
+
     let stream = device
         .build_input_stream(
             config,
             move |data: &[T], _: &cpal::InputCallbackInfo| {
-                let count = callback_count_clone.fetch_add(1, Ordering::Relaxed);
+                let count = callback_count.fetch_add(1, Ordering::Relaxed);
 
                 // Log first callback to confirm stream is working
                 if !has_logged_first_clone.swap(true, Ordering::Relaxed) {
@@ -675,17 +1837,16 @@ where
                     debug!("Audio callback count: {}", count);
                 }
 
-                // Convert samples to f32
-                let samples: Vec<f32> = data
-                    .iter()
-                    .map(|s| cpal::Sample::from_sample(*s))
-                    .collect();
+                let samples = callback_to_stereo(data, channels);
 
                 // Send samples (non-blocking, drop if channel is full)
                 let _ = tx.send(samples);
             },
             move |err| {
                 error!("Audio stream error: {}", err);
+                if matches!(err, cpal::StreamError::DeviceNotAvailable) {
+                    device_lost.store(true, Ordering::Relaxed);
+                }
             },
             None,
         )
@@ -694,6 +1855,23 @@ where
     Ok(stream)
 }
 
+/// Convert a CPAL callback's samples to the interleaved stereo the decks expect
+///
+/// Mono devices are duplicated to both channels; multichannel interfaces
+/// (ASIO, surround outputs) keep their first two channels.
+#[cfg_attr(target_os = "linux", allow(dead_code))]
+fn callback_to_stereo<T>(data: &[T], channels: u16) -> Vec<f32>
+where
+    T: cpal::Sample,
+    f32: cpal::FromSample<T>,
+{
+    let samples: Vec<f32> = data.iter().map(|s| cpal::Sample::from_sample(*s)).collect();
+    match channels {
+        2 => samples,
+        n => to_stereo(&samples, n),
+    }
+}
+
 // ============ Legacy types for compatibility ============
 
 /// Legacy AudioCapture - now wraps AudioEngine
@@ -726,7 +1904,7 @@ impl AudioCapture {
         self.engine.stop()
     }
 
-    pub fn try_recv(&self) -> Option<Vec<f32>> {
+    pub fn try_recv(&mut self) -> Option<Vec<f32>> {
         self.engine.try_recv()
     }
 
@@ -734,3 +1912,39 @@ impl AudioCapture {
         self.engine.is_running()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_mono_callback_becomes_stereo() {
+        let mono = [0.25f32, -0.5, 1.0];
+        assert_eq!(
+            callback_to_stereo(&mono, 1),
+            vec![0.25, 0.25, -0.5, -0.5, 1.0, 1.0]
+        );
+    }
+
+    #[test]
+    fn test_mono_i16_callback_becomes_stereo() {
+        let mono = [i16::MAX, 0];
+        let stereo = callback_to_stereo(&mono, 1);
+        assert_eq!(stereo.len(), 4);
+        assert_eq!(stereo[0], stereo[1]);
+        assert!((stereo[0] - 1.0).abs() < 1e-4);
+        assert_eq!(stereo[2..], [0.0, 0.0]);
+    }
+
+    #[test]
+    fn test_multichannel_callback_keeps_first_pair() {
+        let quad = [0.1f32, 0.2, 0.3, 0.4, 0.5, 0.6, 0.7, 0.8];
+        assert_eq!(callback_to_stereo(&quad, 4), vec![0.1, 0.2, 0.5, 0.6]);
+    }
+
+    #[test]
+    fn test_stereo_callback_unchanged() {
+        let stereo = [0.1f32, 0.2, 0.3, 0.4];
+        assert_eq!(callback_to_stereo(&stereo, 2), stereo.to_vec());
+    }
+}
@@ -0,0 +1,179 @@
+//! Music energy tracking
+//!
+//! Keeps a rolling loudness score of the input over several seconds, so
+//! preset selection can follow how calm or intense the music currently is
+//! rather than reacting to single hits.
+
+use serde::{Deserialize, Serialize};
+
+/// Default length of the loudness average
+pub const DEFAULT_ENERGY_WINDOW_SECS: f32 = 8.0;
+/// Scores below this count as calm music
+pub const CALM_BELOW: f32 = 0.4;
+/// Scores above this count as intense music
+pub const INTENSE_ABOVE: f32 = 0.6;
+
+/// Loudness (dBFS RMS) scored as 0.0
+const FLOOR_DB: f32 = -50.0;
+/// Loudness (dBFS RMS) scored as 1.0
+const CEILING_DB: f32 = -10.0;
+/// Pick weight of presets tagged with the music's energy
+const MATCH_WEIGHT: f32 = 4.0;
+/// Pick weight of presets tagged with the opposite energy
+const MISMATCH_WEIGHT: f32 = 0.25;
+
+/// How calm or intense music or a preset is
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Energy {
+    Calm,
+    Intense,
+}
+
+impl Energy {
+    /// Relative chance of picking a preset of energy `preset` while the
+    /// music has energy `target`; untagged presets and in-between music are
+    /// neutral
+    pub fn match_weight(target: Option<Energy>, preset: Option<Energy>) -> f32 {
+        match (target, preset) {
+            (Some(target), Some(preset)) if target == preset => MATCH_WEIGHT,
+            (Some(_), Some(_)) => MISMATCH_WEIGHT,
+            _ => 1.0,
+        }
+    }
+}
+
+/// Current energy score and what it classifies as
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize)]
+pub struct EnergyInfo {
+    /// Rolling loudness score (0.0-1.0)
+    pub score: f32,
+    /// Calm or intense, `None` in between
+    pub energy: Option<Energy>,
+}
+
+/// Rolling loudness score of interleaved audio
+#[derive(Debug, Clone)]
+pub struct EnergyTracker {
+    window_secs: f32,
+    sample_rate: u32,
+    channels: u16,
+    /// Exponential moving average of the block level in dB, so single loud
+    /// hits don't dominate the score
+    level_db: f32,
+}
+
+impl EnergyTracker {
+    /// Create a tracker averaging over `window_secs`
+    pub fn new(window_secs: f32, sample_rate: u32, channels: u16) -> Self {
+        Self {
+            window_secs: window_secs.max(0.1),
+            sample_rate: sample_rate.max(1),
+            channels: channels.max(1),
+            level_db: FLOOR_DB,
+        }
+    }
+
+    /// Update the input format
+    pub fn set_format(&mut self, sample_rate: u32, channels: u16) {
+        self.sample_rate = sample_rate.max(1);
+        self.channels = channels.max(1);
+    }
+
+    /// Feed interleaved samples
+    pub fn process(&mut self, samples: &[f32]) {
+        if samples.is_empty() {
+            return;
+        }
+        let mean_square = samples.iter().map(|s| s * s).sum::<f32>() / samples.len() as f32;
+        let block_db = if mean_square > 0.0 {
+            (10.0 * mean_square.log10()).max(FLOOR_DB)
+        } else {
+            FLOOR_DB
+        };
+        let frames = samples.len() / self.channels as usize;
+        let secs = frames as f32 / self.sample_rate as f32;
+        let alpha = 1.0 - (-secs / self.window_secs).exp();
+        self.level_db += alpha * (block_db - self.level_db);
+    }
+
+    /// Rolling loudness score (0.0 quiet - 1.0 loud)
+    pub fn score(&self) -> f32 {
+        ((self.level_db - FLOOR_DB) / (CEILING_DB - FLOOR_DB)).clamp(0.0, 1.0)
+    }
+
+    /// Energy of the music, `None` while in between calm and intense
+    pub fn energy(&self) -> Option<Energy> {
+        let score = self.score();
+        if score < CALM_BELOW {
+            Some(Energy::Calm)
+        } else if score > INTENSE_ABOVE {
+            Some(Energy::Intense)
+        } else {
+            None
+        }
+    }
+
+    /// Score together with its classification
+    pub fn info(&self) -> EnergyInfo {
+        EnergyInfo {
+            score: self.score(),
+            energy: self.energy(),
+        }
+    }
+
+    /// Forget the loudness history
+    pub fn reset(&mut self) {
+        self.level_db = FLOOR_DB;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn feed(tracker: &mut EnergyTracker, level: f32, secs: u32) {
+        for _ in 0..secs * 10 {
+            tracker.process(&vec![level; 200]);
+        }
+    }
+
+    #[test]
+    fn test_quiet_music_is_calm_and_loud_is_intense() {
+        let mut tracker = EnergyTracker::new(2.0, 1000, 2);
+        feed(&mut tracker, 0.01, 20);
+        assert_eq!(tracker.energy(), Some(Energy::Calm));
+
+        feed(&mut tracker, 0.5, 20);
+        assert_eq!(tracker.energy(), Some(Energy::Intense));
+        assert!(tracker.score() > 0.9);
+    }
+
+    #[test]
+    fn test_score_follows_slowly() {
+        let mut tracker = EnergyTracker::new(8.0, 1000, 2);
+        feed(&mut tracker, 0.01, 30);
+        let calm = tracker.score();
+
+        // A one-second burst barely moves an eight-second average
+        feed(&mut tracker, 0.5, 1);
+        assert!(tracker.score() - calm < 0.3, "score {}", tracker.score());
+    }
+
+    #[test]
+    fn test_match_weights() {
+        let calm = Some(Energy::Calm);
+        let intense = Some(Energy::Intense);
+        assert!(Energy::match_weight(calm, calm) > Energy::match_weight(calm, None));
+        assert!(Energy::match_weight(calm, intense) < Energy::match_weight(calm, None));
+        assert_eq!(Energy::match_weight(None, intense), 1.0);
+    }
+
+    #[test]
+    fn test_silence_scores_zero() {
+        let mut tracker = EnergyTracker::new(8.0, 48000, 2);
+        assert_eq!(tracker.score(), 0.0);
+        tracker.process(&[0.0; 512]);
+        assert_eq!(tracker.info().score, 0.0);
+    }
+}
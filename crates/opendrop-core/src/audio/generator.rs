@@ -0,0 +1,273 @@
+//! Test signal generator
+//!
+//! Synthesizes audio so decks, outputs and MIDI mappings can be checked
+//! before the venue's audio is live. Signals are selected like capture
+//! devices: `test:sweep`, `test:pink` or `test:beats[:bpm]`.
+
+use std::f32::consts::TAU;
+
+use super::capture::AudioError;
+
+/// Device name prefix selecting a test signal
+pub const TEST_DEVICE_PREFIX: &str = "test:";
+/// Tempo of `test:beats` when none is given
+pub const DEFAULT_TEST_BPM: f32 = 120.0;
+
+/// Duration of one sweep from 20 Hz to 20 kHz
+const SWEEP_SECS: f32 = 10.0;
+const SWEEP_MIN_HZ: f32 = 20.0;
+const SWEEP_MAX_HZ: f32 = 20000.0;
+/// Peak level of the sweep and noise signals
+const LEVEL: f32 = 0.5;
+/// Accepted tempo range for simulated beats
+const BPM_RANGE: std::ops::RangeInclusive<f32> = 30.0..=300.0;
+
+/// Kind of test signal
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum TestSignal {
+    /// Logarithmic sine sweep across the audible range, repeating
+    Sweep,
+    /// Pink noise (equal energy per octave)
+    PinkNoise,
+    /// Kick drum on every beat with a hi-hat in between
+    Beats {
+        /// Tempo in beats per minute
+        bpm: f32,
+    },
+}
+
+impl TestSignal {
+    /// Signals offered as virtual devices
+    pub fn presets() -> [TestSignal; 3] {
+        [
+            TestSignal::Sweep,
+            TestSignal::PinkNoise,
+            TestSignal::Beats {
+                bpm: DEFAULT_TEST_BPM,
+            },
+        ]
+    }
+
+    /// Whether a device name selects a test signal
+    pub fn matches(device_name: &str) -> bool {
+        device_name.starts_with(TEST_DEVICE_PREFIX)
+    }
+
+    /// Parse a `test:...` device name
+    pub fn parse(device_name: &str) -> Result<Self, AudioError> {
+        let invalid =
+            || AudioError::DeviceNotFound(format!("Unknown test signal: {}", device_name));
+        let spec = device_name
+            .strip_prefix(TEST_DEVICE_PREFIX)
+            .ok_or_else(invalid)?;
+
+        match spec.split_once(':').unwrap_or((spec, "")) {
+            ("sweep", "") => Ok(TestSignal::Sweep),
+            ("pink", "") => Ok(TestSignal::PinkNoise),
+            ("beats", "") => Ok(TestSignal::Beats {
+                bpm: DEFAULT_TEST_BPM,
+            }),
+            ("beats", bpm) => bpm
+                .parse()
+                .ok()
+                .filter(|bpm| BPM_RANGE.contains(bpm))
+                .map(|bpm| TestSignal::Beats { bpm })
+                .ok_or_else(invalid),
+            _ => Err(invalid()),
+        }
+    }
+
+    /// Device name selecting this signal
+    pub fn device_name(&self) -> String {
+        match self {
+            TestSignal::Sweep => format!("{}sweep", TEST_DEVICE_PREFIX),
+            TestSignal::PinkNoise => format!("{}pink", TEST_DEVICE_PREFIX),
+            TestSignal::Beats { bpm } => format!("{}beats:{}", TEST_DEVICE_PREFIX, bpm),
+        }
+    }
+
+    /// Human-readable description for device lists
+    pub fn description(&self) -> String {
+        match self {
+            TestSignal::Sweep => "Test Signal: Sine Sweep".to_string(),
+            TestSignal::PinkNoise => "Test Signal: Pink Noise".to_string(),
+            TestSignal::Beats { bpm } => format!("Test Signal: Beats ({} BPM)", bpm),
+        }
+    }
+}
+
+/// Produces a test signal as interleaved stereo
+pub struct SignalGenerator {
+    signal: TestSignal,
+    sample_rate: u32,
+    /// Frames generated so far
+    position: u64,
+    /// Oscillator phase in radians
+    phase: f32,
+    /// Pink noise filter state
+    pink: [f32; 7],
+    /// xorshift32 state for white noise
+    rng: u32,
+}
+
+impl SignalGenerator {
+    /// Create a generator at the given sample rate
+    pub fn new(signal: TestSignal, sample_rate: u32) -> Self {
+        Self {
+            signal,
+            sample_rate: sample_rate.max(1),
+            position: 0,
+            phase: 0.0,
+            pink: [0.0; 7],
+            rng: 0x9E37_79B9,
+        }
+    }
+
+    /// Signal being generated
+    pub fn signal(&self) -> TestSignal {
+        self.signal
+    }
+
+    /// Generate the next `frames` frames of interleaved stereo
+    pub fn generate(&mut self, frames: usize) -> Vec<f32> {
+        let mut samples = Vec::with_capacity(frames * 2);
+        for _ in 0..frames {
+            let t = self.position as f32 / self.sample_rate as f32;
+            let sample = match self.signal {
+                TestSignal::Sweep => self.sweep(t),
+                TestSignal::PinkNoise => self.pink_noise() * LEVEL,
+                TestSignal::Beats { bpm } => self.beats(t, bpm),
+            };
+            samples.extend_from_slice(&[sample, sample]);
+            self.position += 1;
+        }
+        samples
+    }
+
+    fn sweep(&mut self, t: f32) -> f32 {
+        let progress = (t / SWEEP_SECS).fract();
+        let freq = SWEEP_MIN_HZ * (SWEEP_MAX_HZ / SWEEP_MIN_HZ).powf(progress);
+        self.oscillate(freq) * LEVEL
+    }
+
+    fn beats(&mut self, t: f32, bpm: f32) -> f32 {
+        let beat_secs = 60.0 / bpm;
+        let since_beat = t % beat_secs;
+        let since_offbeat = (t + beat_secs / 2.0) % beat_secs;
+
+        // Kick: pitch drops from 150 Hz to 50 Hz with a fast decay
+        let kick_freq = 50.0 + 100.0 * (-since_beat * 30.0).exp();
+        let kick = self.oscillate(kick_freq) * (-since_beat * 12.0).exp() * 0.9;
+        // Hi-hat: short burst of noise between beats
+        let hat = self.white_noise() * (-since_offbeat * 60.0).exp() * 0.2;
+
+        (kick + hat).clamp(-1.0, 1.0)
+    }
+
+    /// Advance the oscillator at `freq` and return its value
+    fn oscillate(&mut self, freq: f32) -> f32 {
+        self.phase = (self.phase + TAU * freq / self.sample_rate as f32) % TAU;
+        self.phase.sin()
+    }
+
+    /// Uniform white noise in -1..1
+    fn white_noise(&mut self) -> f32 {
+        self.rng ^= self.rng << 13;
+        self.rng ^= self.rng >> 17;
+        self.rng ^= self.rng << 5;
+        self.rng as f32 / u32::MAX as f32 * 2.0 - 1.0
+    }
+
+    /// Pink noise using Paul Kellet's filter, roughly in -1..1
+    fn pink_noise(&mut self) -> f32 {
+        let white = self.white_noise();
+        let b = &mut self.pink;
+        b[0] = 0.99886 * b[0] + white * 0.0555179;
+        b[1] = 0.99332 * b[1] + white * 0.0750759;
+        b[2] = 0.96900 * b[2] + white * 0.153852;
+        b[3] = 0.86650 * b[3] + white * 0.3104856;
+        b[4] = 0.55000 * b[4] + white * 0.5329522;
+        b[5] = -0.7616 * b[5] - white * 0.0168980;
+        let pink = b[0] + b[1] + b[2] + b[3] + b[4] + b[5] + b[6] + white * 0.5362;
+        b[6] = white * 0.115926;
+        (pink * 0.11).clamp(-1.0, 1.0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::audio::BeatDetector;
+
+    #[test]
+    fn test_parse_device_names() {
+        assert_eq!(TestSignal::parse("test:sweep").unwrap(), TestSignal::Sweep);
+        assert_eq!(
+            TestSignal::parse("test:pink").unwrap(),
+            TestSignal::PinkNoise
+        );
+        assert_eq!(
+            TestSignal::parse("test:beats").unwrap(),
+            TestSignal::Beats { bpm: 120.0 }
+        );
+        assert_eq!(
+            TestSignal::parse("test:beats:128").unwrap(),
+            TestSignal::Beats { bpm: 128.0 }
+        );
+        assert!(TestSignal::parse("test:beats:5").is_err());
+        assert!(TestSignal::parse("test:square").is_err());
+        assert!(!TestSignal::matches("vban"));
+
+        for signal in TestSignal::presets() {
+            assert_eq!(TestSignal::parse(&signal.device_name()).unwrap(), signal);
+        }
+    }
+
+    #[test]
+    fn test_output_is_stereo_and_bounded() {
+        for signal in TestSignal::presets() {
+            let mut generator = SignalGenerator::new(signal, 48000);
+            let samples = generator.generate(48000);
+            assert_eq!(samples.len(), 96000);
+            assert!(samples.iter().all(|s| s.abs() <= 1.0));
+            assert!(
+                samples.iter().any(|s| s.abs() > 0.1),
+                "{:?} is silent",
+                signal
+            );
+        }
+    }
+
+    #[test]
+    fn test_sweep_rises_in_frequency() {
+        let mut generator = SignalGenerator::new(TestSignal::Sweep, 48000);
+        let crossings = |samples: &[f32]| {
+            samples
+                .chunks(2)
+                .map(|f| f[0])
+                .collect::<Vec<_>>()
+                .windows(2)
+                .filter(|w| (w[0] < 0.0) != (w[1] < 0.0))
+                .count()
+        };
+
+        let start = crossings(&generator.generate(4800));
+        generator.generate(48000 * 5);
+        let later = crossings(&generator.generate(4800));
+        assert!(later > start * 10, "{} vs {}", later, start);
+    }
+
+    #[test]
+    fn test_beats_match_tempo() {
+        let mut generator = SignalGenerator::new(TestSignal::Beats { bpm: 128.0 }, 48000);
+        let mut detector = BeatDetector::new(48000, 2);
+        for _ in 0..1500 {
+            detector.process(&generator.generate(480));
+        }
+        assert!(
+            (detector.bpm() - 128.0).abs() < 2.0,
+            "bpm {}",
+            detector.bpm()
+        );
+    }
+}
@@ -0,0 +1,193 @@
+//! Audio latency measurement
+//!
+//! Captured audio is stamped with the time it is published to readers, so
+//! the age of every chunk can be worked out when it is read and again when
+//! it reaches the renderers. Recent measurements are summarized as average
+//! and percentile latency.
+
+use std::collections::VecDeque;
+use std::time::{Duration, Instant};
+
+use serde::Serialize;
+
+/// Measurements kept for the latency summary
+pub const DEFAULT_LATENCY_WINDOW: usize = 1000;
+
+/// Publish times remembered by a `SampleClock`
+const MAX_MARKS: usize = 4096;
+
+/// Publish times of a sample stream
+#[derive(Debug, Default)]
+pub struct SampleClock {
+    /// Samples published so far
+    published: u64,
+    /// Stream position at the end of each recent buffer and its publish time
+    marks: VecDeque<(u64, Instant)>,
+}
+
+impl SampleClock {
+    /// Create a clock for a new stream
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Record a buffer of `len` samples published at `now`
+    pub fn mark(&mut self, len: usize, now: Instant) {
+        self.published += len as u64;
+        if self.marks.len() == MAX_MARKS {
+            self.marks.pop_front();
+        }
+        self.marks.push_back((self.published, now));
+    }
+
+    /// Samples published so far
+    pub fn published(&self) -> u64 {
+        self.published
+    }
+
+    /// When the sample at `position` was published, if still remembered
+    pub fn published_at(&self, position: u64) -> Option<Instant> {
+        // The first buffer ending after the position contains it
+        let index = self.marks.partition_point(|&(end, _)| end <= position);
+        self.marks.get(index).map(|&(_, at)| at)
+    }
+}
+
+/// Summary of recent latency measurements, in milliseconds
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize)]
+pub struct LatencyReport {
+    /// Measurements summarized
+    pub count: usize,
+    pub avg_ms: f32,
+    pub p50_ms: f32,
+    pub p95_ms: f32,
+    pub p99_ms: f32,
+    pub max_ms: f32,
+}
+
+/// Rolling window of latency measurements
+#[derive(Debug, Clone)]
+pub struct LatencyStats {
+    capacity: usize,
+    window: VecDeque<Duration>,
+}
+
+impl LatencyStats {
+    /// Keep the last `capacity` measurements
+    pub fn new(capacity: usize) -> Self {
+        let capacity = capacity.max(1);
+        Self {
+            capacity,
+            window: VecDeque::with_capacity(capacity),
+        }
+    }
+
+    /// Add a measurement
+    pub fn record(&mut self, latency: Duration) {
+        if self.window.len() == self.capacity {
+            self.window.pop_front();
+        }
+        self.window.push_back(latency);
+    }
+
+    /// Drop all measurements
+    pub fn clear(&mut self) {
+        self.window.clear();
+    }
+
+    /// Average and percentiles of the current window
+    pub fn report(&self) -> LatencyReport {
+        if self.window.is_empty() {
+            return LatencyReport::default();
+        }
+
+        let mut sorted: Vec<f32> = self
+            .window
+            .iter()
+            .map(|d| d.as_secs_f32() * 1000.0)
+            .collect();
+        sorted.sort_by(f32::total_cmp);
+
+        // Nearest-rank percentile
+        let percentile = |p: f32| {
+            let rank = (p / 100.0 * sorted.len() as f32).ceil() as usize;
+            sorted[rank.clamp(1, sorted.len()) - 1]
+        };
+        LatencyReport {
+            count: sorted.len(),
+            avg_ms: sorted.iter().sum::<f32>() / sorted.len() as f32,
+            p50_ms: percentile(50.0),
+            p95_ms: percentile(95.0),
+            p99_ms: percentile(99.0),
+            max_ms: sorted[sorted.len() - 1],
+        }
+    }
+}
+
+impl Default for LatencyStats {
+    fn default() -> Self {
+        Self::new(DEFAULT_LATENCY_WINDOW)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_clock_finds_buffer_of_position() {
+        let start = Instant::now();
+        let mut clock = SampleClock::new();
+        clock.mark(100, start);
+        clock.mark(100, start + Duration::from_millis(10));
+
+        assert_eq!(clock.published(), 200);
+        assert_eq!(clock.published_at(0), Some(start));
+        assert_eq!(clock.published_at(99), Some(start));
+        assert_eq!(
+            clock.published_at(100),
+            Some(start + Duration::from_millis(10))
+        );
+        assert_eq!(clock.published_at(200), None);
+    }
+
+    #[test]
+    fn test_clock_forgets_old_buffers() {
+        let now = Instant::now();
+        let mut clock = SampleClock::new();
+        for _ in 0..MAX_MARKS + 10 {
+            clock.mark(10, now);
+        }
+        assert_eq!(clock.published_at(0), Some(now));
+        assert_eq!(clock.marks.len(), MAX_MARKS);
+    }
+
+    #[test]
+    fn test_report_percentiles() {
+        let mut stats = LatencyStats::new(100);
+        for ms in 1..=100 {
+            stats.record(Duration::from_millis(ms));
+        }
+
+        let report = stats.report();
+        assert_eq!(report.count, 100);
+        assert!((report.avg_ms - 50.5).abs() < 1e-3);
+        assert!((report.p50_ms - 50.0).abs() < 1e-3);
+        assert!((report.p95_ms - 95.0).abs() < 1e-3);
+        assert!((report.p99_ms - 99.0).abs() < 1e-3);
+        assert!((report.max_ms - 100.0).abs() < 1e-3);
+    }
+
+    #[test]
+    fn test_window_is_bounded() {
+        let mut stats = LatencyStats::new(2);
+        stats.record(Duration::from_millis(50));
+        stats.record(Duration::from_millis(10));
+        stats.record(Duration::from_millis(20));
+        assert_eq!(stats.report().count, 2);
+        assert!((stats.report().max_ms - 20.0).abs() < 1e-3);
+
+        stats.clear();
+        assert_eq!(stats.report(), LatencyReport::default());
+    }
+}
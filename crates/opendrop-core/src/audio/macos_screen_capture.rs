@@ -0,0 +1,428 @@
+//! System audio capture on macOS 13.0-14.1 via ScreenCaptureKit
+//!
+//! Process taps only arrived in macOS 14.2. Before that, an `SCStream` with
+//! `capturesAudio` set delivers what the system plays, at the cost of a video
+//! stream nobody looks at (kept at 2x2 pixels, one frame a second). Capturing
+//! needs the Screen Recording permission.
+//!
+//! ScreenCaptureKit.framework is loaded at runtime, which registers its
+//! Objective-C classes, so the app still launches on systems without it.
+
+use std::ffi::c_void;
+use std::ptr;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{self, Sender};
+use std::sync::{Arc, Once};
+use std::time::Duration;
+
+use block2::RcBlock;
+use libloading::Library;
+use objc2::encode::{Encode, Encoding, RefEncode};
+use objc2::rc::{Allocated, Retained};
+use objc2::runtime::{AnyClass, AnyObject, AnyProtocol, NSObject, NSObjectProtocol};
+use objc2::{define_class, msg_send, AnyThread, ClassType, DefinedClass};
+use objc2_foundation::{NSArray, NSError};
+use tracing::{info, warn};
+
+use super::capture::AudioError;
+use super::macos_tap;
+
+/// Rate the stream is asked for (ScreenCaptureKit supports 8, 16, 24 and 48 kHz)
+pub const SAMPLE_RATE: u32 = 48_000;
+
+/// How long to wait for ScreenCaptureKit to answer (first use shows a permission prompt)
+const START_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// `SCStreamOutputTypeAudio`
+const OUTPUT_TYPE_AUDIO: isize = 1;
+
+mod ffi {
+    use std::ffi::c_void;
+
+    use objc2::encode::{Encoding, RefEncode};
+
+    /// Opaque CoreMedia sample buffer (`CMSampleBufferRef` points to one)
+    #[repr(C)]
+    pub struct OpaqueCMSampleBuffer {
+        _private: [u8; 0],
+    }
+
+    unsafe impl RefEncode for OpaqueCMSampleBuffer {
+        const ENCODING_REF: Encoding =
+            Encoding::Pointer(&Encoding::Struct("opaqueCMSampleBuffer", &[]));
+    }
+
+    #[repr(C)]
+    pub struct AudioBuffer {
+        pub number_channels: u32,
+        pub data_byte_size: u32,
+        pub data: *mut c_void,
+    }
+
+    /// `AudioBufferList` with room for two buffers (planar stereo)
+    #[repr(C)]
+    pub struct StereoBufferList {
+        pub number_buffers: u32,
+        pub buffers: [AudioBuffer; 2],
+    }
+
+    pub const ASSURE_16_BYTE_ALIGNMENT: u32 = 1 << 0;
+
+    #[link(name = "CoreMedia", kind = "framework")]
+    extern "C" {
+        pub fn CMSampleBufferGetAudioBufferListWithRetainedBlockBuffer(
+            sample_buffer: *mut OpaqueCMSampleBuffer,
+            buffer_list_size_needed: *mut usize,
+            buffer_list: *mut StereoBufferList,
+            buffer_list_size: usize,
+            structure_allocator: *const c_void,
+            block_allocator: *const c_void,
+            flags: u32,
+            block_buffer: *mut *const c_void,
+        ) -> i32;
+    }
+
+    #[link(name = "CoreFoundation", kind = "framework")]
+    extern "C" {
+        pub fn CFRelease(object: *const c_void);
+    }
+}
+
+/// `CMTime`, for the stream's frame interval
+#[repr(C)]
+struct CMTime {
+    value: i64,
+    timescale: i32,
+    flags: u32,
+    epoch: i64,
+}
+
+unsafe impl Encode for CMTime {
+    const ENCODING: Encoding = Encoding::Struct(
+        "?",
+        &[i64::ENCODING, i32::ENCODING, u32::ENCODING, i64::ENCODING],
+    );
+}
+
+/// Whether ScreenCaptureKit can capture audio on this macOS version (13.0+)
+pub fn is_supported() -> bool {
+    supports_version(macos_tap::macos_version())
+}
+
+pub(super) fn supports_version(version: (u32, u32)) -> bool {
+    version >= (13, 0)
+}
+
+/// State shared with the stream's callbacks
+struct OutputIvars {
+    sample_tx: Sender<Vec<f32>>,
+    stopped: Arc<AtomicBool>,
+}
+
+define_class!(
+    // SAFETY: NSObject has no subclassing requirements and AudioOutput
+    // doesn't implement Drop
+    #[unsafe(super(NSObject))]
+    #[name = "OpenDropSystemAudioOutput"]
+    #[ivars = OutputIvars]
+    struct AudioOutput;
+
+    unsafe impl NSObjectProtocol for AudioOutput {}
+
+    impl AudioOutput {
+        /// `SCStreamOutput`; called on a ScreenCaptureKit queue
+        #[unsafe(method(stream:didOutputSampleBuffer:ofType:))]
+        fn stream_did_output(
+            &self,
+            _stream: &AnyObject,
+            sample_buffer: *mut ffi::OpaqueCMSampleBuffer,
+            output_type: isize,
+        ) {
+            if output_type != OUTPUT_TYPE_AUDIO {
+                return;
+            }
+            if let Some(samples) = unsafe { stereo_samples(sample_buffer) } {
+                let _ = self.ivars().sample_tx.send(samples);
+            }
+        }
+
+        /// `SCStreamDelegate`; the stream ended on its own (permission revoked,
+        /// display gone)
+        #[unsafe(method(stream:didStopWithError:))]
+        fn stream_did_stop(&self, _stream: &AnyObject, error: &NSError) {
+            warn!("ScreenCaptureKit stream stopped: {}", error.localizedDescription());
+            self.ivars().stopped.store(true, Ordering::Relaxed);
+        }
+    }
+);
+
+impl AudioOutput {
+    fn new(ivars: OutputIvars) -> Retained<Self> {
+        // ScreenCaptureKit checks its outputs conform to the protocols, which
+        // define_class can only declare for protocols known to objc2
+        static PROTOCOLS: Once = Once::new();
+        PROTOCOLS.call_once(|| {
+            let class = Self::class() as *const AnyClass as *mut AnyClass;
+            for name in [c"SCStreamOutput", c"SCStreamDelegate"] {
+                if let Some(protocol) = AnyProtocol::get(name) {
+                    unsafe { objc2::ffi::class_addProtocol(class, protocol) };
+                }
+            }
+        });
+
+        let this = Self::alloc().set_ivars(ivars);
+        unsafe { msg_send![super(this), init] }
+    }
+}
+
+/// Interleaved stereo from an audio sample buffer; ScreenCaptureKit delivers
+/// 32-bit float, one buffer per channel
+unsafe fn stereo_samples(sample_buffer: *mut ffi::OpaqueCMSampleBuffer) -> Option<Vec<f32>> {
+    let mut list: ffi::StereoBufferList = std::mem::zeroed();
+    let mut block_buffer: *const c_void = ptr::null();
+    let status = ffi::CMSampleBufferGetAudioBufferListWithRetainedBlockBuffer(
+        sample_buffer,
+        ptr::null_mut(),
+        &mut list,
+        std::mem::size_of::<ffi::StereoBufferList>(),
+        ptr::null(),
+        ptr::null(),
+        ffi::ASSURE_16_BYTE_ALIGNMENT,
+        &mut block_buffer,
+    );
+    if status != 0 || block_buffer.is_null() {
+        return None;
+    }
+
+    unsafe fn channel(buffer: &ffi::AudioBuffer) -> &[f32] {
+        if buffer.data.is_null() {
+            return &[];
+        }
+        let len = buffer.data_byte_size as usize / std::mem::size_of::<f32>();
+        std::slice::from_raw_parts(buffer.data as *const f32, len)
+    }
+    let samples = match list.number_buffers {
+        1 => {
+            let buffer = &list.buffers[0];
+            let samples = channel(buffer);
+            match buffer.number_channels {
+                1 => samples.iter().flat_map(|&s| [s, s]).collect(),
+                _ => samples.to_vec(),
+            }
+        }
+        2 => {
+            let left = channel(&list.buffers[0]);
+            let right = channel(&list.buffers[1]);
+            left.iter().zip(right).flat_map(|(&l, &r)| [l, r]).collect()
+        }
+        _ => Vec::new(),
+    };
+
+    // The block buffer owns the sample data
+    ffi::CFRelease(block_buffer);
+    (!samples.is_empty()).then_some(samples)
+}
+
+/// Retained object handed back from a ScreenCaptureKit completion handler
+struct Delivered(Retained<AnyObject>);
+
+// Only moved out of the completion handler's queue once, and not used there
+// afterwards
+unsafe impl Send for Delivered {}
+
+fn error_text(error: *mut NSError) -> String {
+    unsafe { error.as_ref() }
+        .map(|e| e.localizedDescription().to_string())
+        .unwrap_or_else(|| "unknown error".to_string())
+}
+
+/// The first display, which the audio stream has to be attached to
+fn first_display(content_class: &AnyClass) -> Result<Retained<AnyObject>, AudioError> {
+    let (tx, rx) = mpsc::channel();
+    let handler = RcBlock::new(move |content: *mut AnyObject, error: *mut NSError| {
+        let display = match unsafe { content.as_ref() } {
+            Some(content) => {
+                let displays: Retained<NSArray<AnyObject>> =
+                    unsafe { msg_send![content, displays] };
+                displays
+                    .firstObject()
+                    .map(Delivered)
+                    .ok_or_else(|| "no display".to_string())
+            }
+            None => Err(error_text(error)),
+        };
+        let _ = tx.send(display);
+    });
+    unsafe {
+        let _: () = msg_send![content_class, getShareableContentWithCompletionHandler: &*handler];
+    }
+
+    match rx.recv_timeout(START_TIMEOUT) {
+        Ok(Ok(display)) => Ok(display.0),
+        Ok(Err(e)) => Err(AudioError::StreamError(format!(
+            "Failed to list shareable content ({}). Check the Screen Recording permission in System Settings",
+            e
+        ))),
+        Err(_) => Err(AudioError::StreamError(
+            "Timed out waiting for ScreenCaptureKit".to_string(),
+        )),
+    }
+}
+
+/// A running ScreenCaptureKit stream sending system audio as interleaved stereo
+pub struct ScreenCaptureAudio {
+    stream: Retained<AnyObject>,
+    /// The stream only holds its output and delegate weakly
+    _output: Retained<AudioOutput>,
+    stopped: Arc<AtomicBool>,
+    /// Keeps the ScreenCaptureKit classes loaded
+    _library: Library,
+}
+
+impl ScreenCaptureAudio {
+    /// Start the stream; returns once it's capturing, or with the error that
+    /// prevented it
+    pub fn start(sample_tx: Sender<Vec<f32>>) -> Result<Self, AudioError> {
+        if !is_supported() {
+            return Err(AudioError::StreamError(
+                "System audio capture requires macOS 13 or later".to_string(),
+            ));
+        }
+
+        let library = unsafe {
+            Library::new("/System/Library/Frameworks/ScreenCaptureKit.framework/ScreenCaptureKit")
+        }
+        .map_err(|e| AudioError::StreamError(format!("Failed to load ScreenCaptureKit: {}", e)))?;
+        let class = |name: &std::ffi::CStr| {
+            AnyClass::get(name).ok_or_else(|| {
+                AudioError::StreamError(format!(
+                    "{} not found in ScreenCaptureKit",
+                    name.to_string_lossy()
+                ))
+            })
+        };
+
+        let display = first_display(class(c"SCShareableContent")?)?;
+
+        let filter: Option<Retained<AnyObject>> = unsafe {
+            let allocated: Allocated<AnyObject> = msg_send![class(c"SCContentFilter")?, alloc];
+            msg_send![
+                allocated,
+                initWithDisplay: &*display,
+                excludingWindows: &*NSArray::<AnyObject>::new()
+            ]
+        };
+        let filter = filter.ok_or_else(|| {
+            AudioError::StreamError("Failed to create content filter".to_string())
+        })?;
+
+        let configuration: Retained<AnyObject> =
+            unsafe { msg_send![class(c"SCStreamConfiguration")?, new] };
+        unsafe {
+            let _: () = msg_send![&*configuration, setCapturesAudio: true];
+            let _: () = msg_send![&*configuration, setExcludesCurrentProcessAudio: true];
+            let _: () = msg_send![&*configuration, setSampleRate: SAMPLE_RATE as isize];
+            let _: () = msg_send![&*configuration, setChannelCount: 2isize];
+            // Video can't be turned off; keep it as small and rare as allowed
+            let _: () = msg_send![&*configuration, setWidth: 2usize];
+            let _: () = msg_send![&*configuration, setHeight: 2usize];
+            let interval = CMTime {
+                value: 1,
+                timescale: 1,
+                flags: 1, // kCMTimeFlags_Valid
+                epoch: 0,
+            };
+            let _: () = msg_send![&*configuration, setMinimumFrameInterval: interval];
+        }
+
+        let stopped = Arc::new(AtomicBool::new(false));
+        let output = AudioOutput::new(OutputIvars {
+            sample_tx,
+            stopped: Arc::clone(&stopped),
+        });
+
+        let stream: Option<Retained<AnyObject>> = unsafe {
+            let allocated: Allocated<AnyObject> = msg_send![class(c"SCStream")?, alloc];
+            msg_send![
+                allocated,
+                initWithFilter: &*filter,
+                configuration: &*configuration,
+                delegate: &*output
+            ]
+        };
+        let stream = stream
+            .ok_or_else(|| AudioError::StreamError("Failed to create SCStream".to_string()))?;
+
+        // A null queue lets ScreenCaptureKit pick one
+        let added: Result<(), Retained<NSError>> = unsafe {
+            msg_send![
+                &*stream,
+                addStreamOutput: &*output,
+                type: OUTPUT_TYPE_AUDIO,
+                sampleHandlerQueue: ptr::null_mut::<AnyObject>(),
+                error: _
+            ]
+        };
+        added.map_err(|e| {
+            AudioError::StreamError(format!(
+                "Failed to add audio output: {}",
+                e.localizedDescription()
+            ))
+        })?;
+
+        let (tx, rx) = mpsc::channel();
+        let handler = RcBlock::new(move |error: *mut NSError| {
+            let _ = tx.send((!error.is_null()).then(|| error_text(error)));
+        });
+        unsafe {
+            let _: () = msg_send![&*stream, startCaptureWithCompletionHandler: &*handler];
+        }
+        match rx.recv_timeout(START_TIMEOUT) {
+            Ok(None) => {}
+            Ok(Some(e)) => {
+                return Err(AudioError::StreamError(format!(
+                    "Failed to start system audio capture ({}). Check the Screen Recording permission in System Settings",
+                    e
+                )));
+            }
+            Err(_) => {
+                return Err(AudioError::StreamError(
+                    "Timed out starting system audio capture".to_string(),
+                ));
+            }
+        }
+
+        info!(
+            "ScreenCaptureKit system audio capture started ({} Hz)",
+            SAMPLE_RATE
+        );
+        Ok(Self {
+            stream,
+            _output: output,
+            stopped,
+            _library: library,
+        })
+    }
+
+    /// Whether the stream stopped on its own
+    pub fn is_stopped(&self) -> bool {
+        self.stopped.load(Ordering::Relaxed)
+    }
+}
+
+impl Drop for ScreenCaptureAudio {
+    fn drop(&mut self) {
+        let (tx, rx) = mpsc::channel();
+        let handler = RcBlock::new(move |_error: *mut NSError| {
+            let _ = tx.send(());
+        });
+        unsafe {
+            let _: () = msg_send![&*self.stream, stopCaptureWithCompletionHandler: &*handler];
+        }
+        // Callbacks may still be queued until the stream confirms
+        if rx.recv_timeout(Duration::from_secs(2)).is_err() {
+            warn!("ScreenCaptureKit stream didn't confirm stopping");
+        }
+        info!("ScreenCaptureKit system audio capture stopped");
+    }
+}
@@ -0,0 +1,178 @@
+//! System audio capture on macOS via CoreAudio process taps
+//!
+//! macOS has no loopback device of its own. On macOS 14.2+ a global process
+//! tap can be wrapped in a private aggregate device, which then shows up as a
+//! regular input device that the CPAL capture path can open. macOS 13.0-14.1
+//! captures through ScreenCaptureKit instead (see `macos_screen_capture`), and
+//! older systems keep using virtual devices such as BlackHole or Loopback.
+
+use std::ptr::NonNull;
+
+use objc2::rc::Retained;
+use objc2::runtime::AnyObject;
+use objc2::AnyThread;
+use objc2_core_audio::{
+    kAudioAggregateDeviceIsPrivateKey, kAudioAggregateDeviceNameKey,
+    kAudioAggregateDeviceTapAutoStartKey, kAudioAggregateDeviceTapListKey,
+    kAudioAggregateDeviceUIDKey, kAudioSubTapUIDKey, AudioHardwareCreateAggregateDevice,
+    AudioHardwareCreateProcessTap, AudioHardwareDestroyAggregateDevice,
+    AudioHardwareDestroyProcessTap, AudioObjectID, CATapDescription, CATapMuteBehavior,
+};
+use objc2_core_foundation::CFDictionary;
+use objc2_foundation::{NSArray, NSDictionary, NSNumber, NSProcessInfo, NSString};
+use tracing::{info, warn};
+
+use super::capture::AudioError;
+
+/// Device name used to select system audio capture
+pub const SYSTEM_AUDIO_DEVICE: &str = "system-audio";
+
+/// Name of the aggregate device wrapping the tap (what CPAL sees)
+const AGGREGATE_DEVICE_NAME: &str = "OpenDrop System Audio";
+
+/// Whether this macOS version supports process taps (14.2+)
+pub fn is_supported() -> bool {
+    supports_version(macos_version())
+}
+
+fn supports_version(version: (u32, u32)) -> bool {
+    version >= (14, 2)
+}
+
+/// Major and minor macOS version
+pub(super) fn macos_version() -> (u32, u32) {
+    let version = NSProcessInfo::processInfo().operatingSystemVersion();
+    (version.majorVersion as u32, version.minorVersion as u32)
+}
+
+/// A global stereo tap exposed as an input device for the lifetime of this value
+pub struct SystemAudioTap {
+    tap_id: AudioObjectID,
+    aggregate_id: AudioObjectID,
+}
+
+impl SystemAudioTap {
+    /// Create the process tap and its aggregate device
+    pub fn create() -> Result<Self, AudioError> {
+        if !is_supported() {
+            return Err(AudioError::StreamError(
+                "System audio capture requires macOS 14.2 or later".to_string(),
+            ));
+        }
+
+        // Tap every process; nothing excluded
+        let description = unsafe {
+            CATapDescription::initStereoGlobalTapButExcludeProcesses(
+                CATapDescription::alloc(),
+                &NSArray::<NSNumber>::new(),
+            )
+        };
+        unsafe {
+            description.setName(&NSString::from_str("OpenDrop"));
+            description.setPrivate(true);
+            description.setMuteBehavior(CATapMuteBehavior::Unmuted);
+        }
+
+        let mut tap_id: AudioObjectID = 0;
+        let status = unsafe { AudioHardwareCreateProcessTap(Some(&description), &mut tap_id) };
+        if status != 0 {
+            return Err(AudioError::StreamError(format!(
+                "Failed to create process tap (OSStatus {}). Check the audio capture permission in System Settings",
+                status
+            )));
+        }
+
+        let tap_uid = unsafe { description.UUID() }.UUIDString();
+        let aggregate = aggregate_description(&tap_uid);
+
+        let mut aggregate_id: AudioObjectID = 0;
+        // NSDictionary is toll-free bridged to CFDictionary
+        let cf_description = unsafe { &*(Retained::as_ptr(&aggregate) as *const CFDictionary) };
+        let status =
+            unsafe { AudioHardwareCreateAggregateDevice(cf_description, NonNull::from(&mut aggregate_id)) };
+        if status != 0 {
+            unsafe { AudioHardwareDestroyProcessTap(tap_id) };
+            return Err(AudioError::StreamError(format!(
+                "Failed to create aggregate device for system audio (OSStatus {})",
+                status
+            )));
+        }
+
+        info!("Created system audio tap {} (aggregate device {})", tap_id, aggregate_id);
+        Ok(Self { tap_id, aggregate_id })
+    }
+
+    /// Input device name to open with CPAL
+    pub fn device_name(&self) -> &'static str {
+        AGGREGATE_DEVICE_NAME
+    }
+}
+
+impl Drop for SystemAudioTap {
+    fn drop(&mut self) {
+        unsafe {
+            if AudioHardwareDestroyAggregateDevice(self.aggregate_id) != 0 {
+                warn!("Failed to destroy aggregate device {}", self.aggregate_id);
+            }
+            if AudioHardwareDestroyProcessTap(self.tap_id) != 0 {
+                warn!("Failed to destroy process tap {}", self.tap_id);
+            }
+        }
+    }
+}
+
+/// Build the aggregate device description containing the tap
+fn aggregate_description(tap_uid: &NSString) -> Retained<NSDictionary<NSString, AnyObject>> {
+    let key = |k: &std::ffi::CStr| NSString::from_str(&k.to_string_lossy());
+
+    let tap_uid_string = tap_uid.to_string();
+    let tap_uid: &AnyObject = tap_uid;
+    let sub_tap = NSDictionary::from_slices(&[&*key(kAudioSubTapUIDKey)], &[tap_uid]);
+    let tap_list = NSArray::from_retained_slice(&[sub_tap]);
+
+    let name = NSString::from_str(AGGREGATE_DEVICE_NAME);
+    let uid = NSString::from_str(&format!("com.opendrop.system-audio.{}", tap_uid_string));
+    let private = NSNumber::new_bool(true);
+    let auto_start = NSNumber::new_bool(true);
+    let values: [&AnyObject; 5] = [&name, &uid, &private, &auto_start, &tap_list];
+
+    NSDictionary::from_slices(
+        &[
+            &*key(kAudioAggregateDeviceNameKey),
+            &*key(kAudioAggregateDeviceUIDKey),
+            &*key(kAudioAggregateDeviceIsPrivateKey),
+            &*key(kAudioAggregateDeviceTapAutoStartKey),
+            &*key(kAudioAggregateDeviceTapListKey),
+        ],
+        &values,
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::audio::macos_screen_capture;
+
+    #[test]
+    fn test_taps_need_14_2() {
+        assert!(!supports_version((13, 5)));
+        assert!(!supports_version((14, 1)));
+        assert!(supports_version((14, 2)));
+        assert!(supports_version((15, 0)));
+    }
+
+    #[test]
+    fn test_screen_capture_covers_versions_before_taps() {
+        assert!(!macos_screen_capture::supports_version((12, 7)));
+        assert!(macos_screen_capture::supports_version((13, 0)));
+        assert!(macos_screen_capture::supports_version((14, 1)));
+        assert!(!supports_version((14, 1)));
+    }
+
+    #[test]
+    fn test_version_of_running_system() {
+        let version = macos_version();
+        assert!(version >= (10, 0), "{:?}", version);
+        assert_eq!(is_supported(), version >= (14, 2));
+    }
+}
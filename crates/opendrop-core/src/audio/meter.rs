@@ -0,0 +1,233 @@
+//! Level metering
+//!
+//! RMS level, held peak and clip indication per channel of the captured
+//! stream. Peaks are held for a while and then fall at a fixed rate in dB,
+//! like a hardware peak meter; timing follows the audio, not the wall clock.
+
+use serde::{Deserialize, Serialize};
+
+/// Default time a peak is held before it starts to fall
+pub const DEFAULT_PEAK_HOLD_MS: u32 = 1500;
+/// Default fall rate of a released peak
+pub const DEFAULT_PEAK_DECAY_DB_PER_SEC: f32 = 20.0;
+/// Default time the clip flag stays set after the last clipped sample
+pub const DEFAULT_CLIP_HOLD_MS: u32 = 2000;
+/// Sample magnitude counted as clipping
+pub const CLIP_THRESHOLD: f32 = 0.999;
+
+/// Peak hold and decay behaviour
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct MeterConfig {
+    pub peak_hold_ms: u32,
+    pub peak_decay_db_per_sec: f32,
+    pub clip_hold_ms: u32,
+}
+
+impl Default for MeterConfig {
+    fn default() -> Self {
+        Self {
+            peak_hold_ms: DEFAULT_PEAK_HOLD_MS,
+            peak_decay_db_per_sec: DEFAULT_PEAK_DECAY_DB_PER_SEC,
+            clip_hold_ms: DEFAULT_CLIP_HOLD_MS,
+        }
+    }
+}
+
+/// Level of one channel (linear, 0.0-1.0)
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize)]
+pub struct ChannelLevel {
+    /// RMS of the latest block
+    pub rms: f32,
+    /// Held peak
+    pub peak: f32,
+    /// Whether the channel clipped recently
+    pub clip: bool,
+}
+
+/// Levels of a stereo stream
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize)]
+pub struct AudioLevels {
+    pub left: ChannelLevel,
+    pub right: ChannelLevel,
+}
+
+/// Peak and clip state of one channel
+#[derive(Debug, Clone, Copy, Default)]
+struct PeakState {
+    peak: f32,
+    /// Seconds the current peak is still held
+    hold_secs: f32,
+    /// Seconds the clip flag stays set
+    clip_secs: f32,
+}
+
+impl PeakState {
+    fn update(&mut self, config: &MeterConfig, block_peak: f32, elapsed: f32) {
+        if block_peak >= self.peak {
+            self.peak = block_peak;
+            self.hold_secs = config.peak_hold_ms as f32 / 1000.0;
+        } else if self.hold_secs > 0.0 {
+            self.hold_secs -= elapsed;
+        } else {
+            let fall_db = config.peak_decay_db_per_sec.max(0.0) * elapsed;
+            self.peak = (self.peak * 10f32.powf(-fall_db / 20.0)).max(block_peak);
+        }
+
+        if block_peak >= CLIP_THRESHOLD {
+            self.clip_secs = config.clip_hold_ms as f32 / 1000.0;
+        } else {
+            self.clip_secs = (self.clip_secs - elapsed).max(0.0);
+        }
+    }
+}
+
+/// Meters interleaved stereo audio
+#[derive(Debug, Clone)]
+pub struct LevelMeter {
+    config: MeterConfig,
+    sample_rate: u32,
+    peaks: [PeakState; 2],
+    levels: AudioLevels,
+}
+
+impl LevelMeter {
+    /// Create a meter for stereo audio at `sample_rate`
+    pub fn new(config: MeterConfig, sample_rate: u32) -> Self {
+        Self {
+            config,
+            sample_rate: sample_rate.max(1),
+            peaks: [PeakState::default(); 2],
+            levels: AudioLevels::default(),
+        }
+    }
+
+    /// Current hold and decay settings
+    pub fn config(&self) -> MeterConfig {
+        self.config
+    }
+
+    /// Change hold and decay settings
+    pub fn configure(&mut self, config: MeterConfig) {
+        self.config = config;
+    }
+
+    /// Update the input sample rate
+    pub fn set_sample_rate(&mut self, sample_rate: u32) {
+        self.sample_rate = sample_rate.max(1);
+    }
+
+    /// Latest levels
+    pub fn levels(&self) -> AudioLevels {
+        self.levels
+    }
+
+    /// Meter a block of interleaved stereo samples
+    pub fn process(&mut self, samples: &[f32]) -> AudioLevels {
+        let frames = samples.len() / 2;
+        if frames == 0 {
+            return self.levels;
+        }
+
+        let mut sum = [0.0f32; 2];
+        let mut block_peak = [0.0f32; 2];
+        for frame in samples.chunks_exact(2) {
+            for (ch, &s) in frame.iter().enumerate() {
+                sum[ch] += s * s;
+                block_peak[ch] = block_peak[ch].max(s.abs());
+            }
+        }
+
+        let elapsed = frames as f32 / self.sample_rate as f32;
+        let mut channels = [ChannelLevel::default(); 2];
+        for ch in 0..2 {
+            let state = &mut self.peaks[ch];
+            state.update(&self.config, block_peak[ch].min(1.0), elapsed);
+            channels[ch] = ChannelLevel {
+                rms: (sum[ch] / frames as f32).sqrt().min(1.0),
+                peak: state.peak,
+                clip: state.clip_secs > 0.0,
+            };
+        }
+
+        self.levels = AudioLevels {
+            left: channels[0],
+            right: channels[1],
+        };
+        self.levels
+    }
+
+    /// Clear all levels and held peaks
+    pub fn reset(&mut self) {
+        self.peaks = [PeakState::default(); 2];
+        self.levels = AudioLevels::default();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Ten milliseconds of stereo at 1 kHz with the given left/right value
+    fn block(left: f32, right: f32) -> Vec<f32> {
+        [left, right].repeat(10)
+    }
+
+    #[test]
+    fn test_rms_and_peak_per_channel() {
+        let mut meter = LevelMeter::new(MeterConfig::default(), 1000);
+        let levels = meter.process(&[0.5, 0.0, -0.5, 0.25]);
+        assert!((levels.left.rms - 0.5).abs() < 1e-6);
+        assert!((levels.left.peak - 0.5).abs() < 1e-6);
+        assert!((levels.right.peak - 0.25).abs() < 1e-6);
+        assert!(!levels.left.clip);
+    }
+
+    #[test]
+    fn test_peak_holds_then_decays() {
+        let config = MeterConfig {
+            peak_hold_ms: 100,
+            peak_decay_db_per_sec: 20.0,
+            clip_hold_ms: 0,
+        };
+        let mut meter = LevelMeter::new(config, 1000);
+        meter.process(&block(0.8, 0.8));
+
+        // Held for 100 ms of quiet audio
+        for _ in 0..10 {
+            meter.process(&block(0.1, 0.1));
+        }
+        assert!((meter.levels().left.peak - 0.8).abs() < 1e-6);
+
+        // Then falls by 20 dB per second: 1 second takes it to a tenth
+        for _ in 0..100 {
+            meter.process(&block(0.0, 0.0));
+        }
+        let peak = meter.levels().left.peak;
+        assert!((peak - 0.08).abs() < 0.01, "peak {}", peak);
+    }
+
+    #[test]
+    fn test_clip_flag_is_held() {
+        let config = MeterConfig {
+            clip_hold_ms: 50,
+            ..Default::default()
+        };
+        let mut meter = LevelMeter::new(config, 1000);
+        let levels = meter.process(&block(1.0, 0.5));
+        assert!(levels.left.clip);
+        assert!(!levels.right.clip);
+
+        for _ in 0..4 {
+            assert!(meter.process(&block(0.1, 0.1)).left.clip);
+        }
+        assert!(!meter.process(&block(0.1, 0.1)).left.clip);
+    }
+
+    #[test]
+    fn test_reset_clears_levels() {
+        let mut meter = LevelMeter::new(MeterConfig::default(), 1000);
+        meter.process(&block(1.0, 1.0));
+        meter.reset();
+        assert_eq!(meter.levels(), AudioLevels::default());
+    }
+}
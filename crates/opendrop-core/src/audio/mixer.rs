@@ -0,0 +1,228 @@
+//! Mixing of several capture sources into one stream
+//!
+//! Each source delivers audio on its own clock, so samples are queued per
+//! source and mixed as soon as every source has caught up. A source that falls
+//! too far behind (stalled or slightly slower clock) is filled with silence
+//! rather than holding back the mix. The same queueing is used to crossfade
+//! from one device to another when the capture device is switched live.
+
+use std::collections::VecDeque;
+
+use serde::{Deserialize, Serialize};
+
+/// A capture device taking part in a mix
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AudioSource {
+    /// Device name as accepted by `AudioConfig::device_name`
+    pub device_name: String,
+    /// Linear gain applied before mixing
+    pub gain: f32,
+}
+
+/// Sums queued audio from several sources with per-source gain
+pub struct Mixer {
+    channels: usize,
+    /// Largest backlog (in samples) any source may build up before the
+    /// slowest sources are treated as silent
+    max_backlog: usize,
+    gains: Vec<f32>,
+    queues: Vec<VecDeque<f32>>,
+}
+
+impl Mixer {
+    /// Create a mixer for `gains.len()` interleaved sources
+    pub fn new(gains: Vec<f32>, channels: u16, max_backlog_frames: usize) -> Self {
+        let channels = channels.max(1) as usize;
+        Self {
+            channels,
+            max_backlog: max_backlog_frames.max(1) * channels,
+            queues: vec![VecDeque::new(); gains.len()],
+            gains,
+        }
+    }
+
+    /// Queue samples from one source
+    pub fn push(&mut self, source: usize, samples: &[f32]) {
+        if let Some(queue) = self.queues.get_mut(source) {
+            queue.extend(samples);
+        }
+    }
+
+    /// Mix everything that is ready, returning an empty buffer if nothing is
+    pub fn mix(&mut self) -> Vec<f32> {
+        let Some(shortest) = self.queues.iter().map(VecDeque::len).min() else {
+            return Vec::new();
+        };
+        let longest = self.queues.iter().map(VecDeque::len).max().unwrap_or(0);
+
+        // Mix what every source has, or more if one has run too far ahead
+        let len = shortest.max(longest.saturating_sub(self.max_backlog));
+        let len = len - len % self.channels;
+        if len == 0 {
+            return Vec::new();
+        }
+
+        let mut mixed = vec![0.0f32; len];
+        for (queue, &gain) in self.queues.iter_mut().zip(&self.gains) {
+            let take = len.min(queue.len());
+            for (out, sample) in mixed.iter_mut().zip(queue.drain(..take)) {
+                *out += sample * gain;
+            }
+        }
+
+        for sample in &mut mixed {
+            *sample = sample.clamp(-1.0, 1.0);
+        }
+        mixed
+    }
+}
+
+/// Equal-gain ramp from an outgoing source to an incoming one
+pub struct Crossfade {
+    channels: usize,
+    max_backlog: usize,
+    fade_frames: usize,
+    /// Frames of the fade already produced
+    position: usize,
+    outgoing: VecDeque<f32>,
+    incoming: VecDeque<f32>,
+}
+
+impl Crossfade {
+    /// Create a fade lasting `fade_frames` frames
+    pub fn new(channels: u16, fade_frames: usize, max_backlog_frames: usize) -> Self {
+        let channels = channels.max(1) as usize;
+        Self {
+            channels,
+            max_backlog: max_backlog_frames.max(1) * channels,
+            fade_frames: fade_frames.max(1),
+            position: 0,
+            outgoing: VecDeque::new(),
+            incoming: VecDeque::new(),
+        }
+    }
+
+    /// Queue samples from the source being faded out
+    pub fn push_outgoing(&mut self, samples: &[f32]) {
+        self.outgoing.extend(samples);
+    }
+
+    /// Queue samples from the source being faded in
+    pub fn push_incoming(&mut self, samples: &[f32]) {
+        self.incoming.extend(samples);
+    }
+
+    /// Whether the incoming source has fully taken over
+    pub fn is_finished(&self) -> bool {
+        self.position >= self.fade_frames
+    }
+
+    /// Mix everything that is ready, returning an empty buffer if nothing is
+    pub fn mix(&mut self) -> Vec<f32> {
+        let (a, b) = (self.outgoing.len(), self.incoming.len());
+        let len = a.min(b).max(a.max(b).saturating_sub(self.max_backlog));
+        let len = len - len % self.channels;
+        if len == 0 {
+            return Vec::new();
+        }
+
+        let take_out = len.min(a);
+        let take_in = len.min(b);
+        let mut outgoing = self.outgoing.drain(..take_out);
+        let mut incoming = self.incoming.drain(..take_in);
+
+        let mut mixed = Vec::with_capacity(len);
+        for frame in 0..len / self.channels {
+            let t = ((self.position + frame + 1) as f32 / self.fade_frames as f32).min(1.0);
+            for _ in 0..self.channels {
+                let from = outgoing.next().unwrap_or(0.0);
+                let to = incoming.next().unwrap_or(0.0);
+                mixed.push((from * (1.0 - t) + to * t).clamp(-1.0, 1.0));
+            }
+        }
+        self.position += len / self.channels;
+        mixed
+    }
+
+    /// Incoming samples not yet mixed, for use once the fade is finished
+    pub fn take_incoming(&mut self) -> Vec<f32> {
+        self.incoming.drain(..).collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_mixes_with_gain() {
+        let mut mixer = Mixer::new(vec![1.0, 0.5], 2, 100);
+        mixer.push(0, &[0.2, 0.2, 0.2, 0.2]);
+        mixer.push(1, &[0.4, -0.4, 0.4, -0.4]);
+        let mixed = mixer.mix();
+        let expected = [0.4, 0.0, 0.4, 0.0];
+        for (a, b) in mixed.iter().zip(expected) {
+            assert!((a - b).abs() < 1e-6);
+        }
+    }
+
+    #[test]
+    fn test_waits_for_slowest_source() {
+        let mut mixer = Mixer::new(vec![1.0, 1.0], 2, 100);
+        mixer.push(0, &[0.1; 8]);
+        mixer.push(1, &[0.1; 4]);
+        assert_eq!(mixer.mix().len(), 4);
+
+        // Source 0 still has 4 samples queued
+        assert!(mixer.mix().is_empty());
+        mixer.push(1, &[0.1; 4]);
+        assert_eq!(mixer.mix(), vec![0.2; 4]);
+    }
+
+    #[test]
+    fn test_stalled_source_is_silent() {
+        let mut mixer = Mixer::new(vec![1.0, 1.0], 2, 4);
+        mixer.push(0, &[0.5; 12]);
+
+        // Source 1 never delivers; everything beyond the backlog is mixed alone
+        assert_eq!(mixer.mix(), vec![0.5; 4]);
+        assert!(mixer.mix().is_empty());
+    }
+
+    #[test]
+    fn test_output_is_clamped() {
+        let mut mixer = Mixer::new(vec![1.0, 1.0], 1, 100);
+        mixer.push(0, &[0.8, -0.8]);
+        mixer.push(1, &[0.8, -0.8]);
+        assert_eq!(mixer.mix(), vec![1.0, -1.0]);
+    }
+
+    #[test]
+    fn test_crossfade_ramps_between_sources() {
+        let mut fade = Crossfade::new(1, 4, 100);
+        fade.push_outgoing(&[1.0; 6]);
+        fade.push_incoming(&[-1.0; 6]);
+        let mixed = fade.mix();
+        let expected = [0.5, 0.0, -0.5, -1.0, -1.0, -1.0];
+        for (a, b) in mixed.iter().zip(expected) {
+            assert!((a - b).abs() < 1e-6, "{:?}", mixed);
+        }
+        assert!(fade.is_finished());
+    }
+
+    #[test]
+    fn test_crossfade_does_not_wait_for_stalled_source() {
+        let mut fade = Crossfade::new(2, 1000, 4);
+        fade.push_incoming(&[0.5; 12]);
+        assert_eq!(fade.mix().len(), 4);
+        assert!(!fade.is_finished());
+        assert_eq!(fade.take_incoming().len(), 8);
+    }
+
+    #[test]
+    fn test_single_source_passes_through() {
+        let mut mixer = Mixer::new(vec![1.0], 2, 100);
+        mixer.push(0, &[0.1, 0.2, 0.3, 0.4]);
+        assert_eq!(mixer.mix(), vec![0.1, 0.2, 0.3, 0.4]);
+    }
+}
@@ -1,12 +1,47 @@
 //! Audio capture and processing module
 
+pub mod agc;
+pub mod beat;
 pub mod capture;
+pub mod energy;
+pub mod generator;
+pub mod latency;
+pub mod meter;
+pub mod mixer;
+pub mod network;
+pub mod recorder;
+pub mod resample;
 pub mod ring_buffer;
+pub mod silence;
+pub mod spectrum;
+pub mod tempo;
+pub mod waveform;
 
 #[cfg(target_os = "linux")]
 pub mod pipewire;
 
-pub use capture::{AudioBackend, AudioCapture, AudioConfig, AudioEngine, AudioError, DeviceInfo, DeviceType};
+#[cfg(target_os = "macos")]
+pub mod macos_tap;
+
+#[cfg(target_os = "macos")]
+pub mod macos_screen_capture;
+
+pub use agc::{soft_clip, AgcConfig, AutoGain};
+pub use beat::{BeatDetector, BeatInfo, OnsetEvent};
+pub use capture::{AudioBackend, AudioCapture, AudioConfig, AudioEngine, AudioError, CaptureFormat, DeviceEvent, DeviceInfo, DeviceType};
+pub use energy::{Energy, EnergyInfo, EnergyTracker};
+pub use generator::{SignalGenerator, TestSignal};
+pub use latency::{LatencyReport, LatencyStats, SampleClock};
+pub use meter::{AudioLevels, ChannelLevel, LevelMeter, MeterConfig};
+pub use mixer::{AudioSource, Crossfade, Mixer};
+pub use network::{VbanReceiver, VbanSource};
+pub use recorder::{RecordingInfo, WavRecorder};
+pub use resample::LinearResampler;
+pub use ring_buffer::{AudioConsumer, AudioProducer, AudioRingBuffer};
+pub use silence::SilenceDetector;
+pub use spectrum::{BandEnergy, SpectrumAnalyzer};
+pub use tempo::{TempoClock, TempoInfo, TempoSource};
+pub use waveform::{Waveform, WaveformHistory, WaveformPoint};
 
 #[cfg(target_os = "linux")]
-pub use pipewire::{PipeWireCapture, PipeWireConfig, PipeWireSource};
+pub use pipewire::{PipeWireCapture, PipeWireConfig, PipeWireSource, APP_DEVICE_PREFIX};
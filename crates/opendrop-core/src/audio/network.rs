@@ -0,0 +1,393 @@
+//! Network audio input (VBAN)
+//!
+//! Receives PCM audio streamed over the LAN with the VBAN protocol (as sent by
+//! Voicemeeter, VB-Audio's VBAN apps and many DAW plugins), so a FOH laptop can
+//! feed the visuals machine without extra hardware.
+//!
+//! Sources are selected with device names of the form
+//! `vban[:[host:]port][/stream]`, e.g. `vban`, `vban:6981` or
+//! `vban:192.168.1.20:6980/Stream1`. The host is the local address to bind.
+
+use std::io;
+use std::net::{IpAddr, Ipv4Addr, SocketAddr, UdpSocket};
+use std::time::Duration;
+
+use tracing::info;
+
+use super::capture::AudioError;
+
+/// Default VBAN UDP port
+pub const VBAN_DEFAULT_PORT: u16 = 6980;
+/// Device name prefix selecting the VBAN receiver
+pub const VBAN_DEVICE_PREFIX: &str = "vban";
+
+/// VBAN header length in bytes
+const HEADER_LEN: usize = 28;
+/// Largest VBAN packet (header + 1436 bytes of data)
+const MAX_PACKET_LEN: usize = 1464;
+/// Sample rates indexed by the low 5 bits of the format byte
+const SAMPLE_RATES: [u32; 21] = [
+    6000, 12000, 24000, 48000, 96000, 192000, 384000, 8000, 16000, 32000, 64000, 128000, 256000,
+    512000, 11025, 22050, 44100, 88200, 176400, 352800, 705600,
+];
+
+/// Where to listen for a VBAN stream
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct VbanSource {
+    /// Local address to bind
+    pub bind_addr: SocketAddr,
+    /// Only accept packets for this stream name (any stream if `None`)
+    pub stream_name: Option<String>,
+}
+
+impl VbanSource {
+    /// Whether a device name selects the VBAN receiver
+    pub fn matches(device_name: &str) -> bool {
+        device_name == VBAN_DEVICE_PREFIX
+            || device_name.starts_with("vban:")
+            || device_name.starts_with("vban/")
+    }
+
+    /// Parse a `vban[:[host:]port][/stream]` device name
+    pub fn parse(device_name: &str) -> Result<Self, AudioError> {
+        let invalid =
+            || AudioError::DeviceNotFound(format!("Invalid VBAN source: {}", device_name));
+
+        let spec = device_name
+            .strip_prefix(VBAN_DEVICE_PREFIX)
+            .filter(|_| Self::matches(device_name))
+            .ok_or_else(invalid)?;
+        let spec = spec.strip_prefix(':').unwrap_or(spec);
+
+        let (addr, stream_name) = match spec.split_once('/') {
+            Some((addr, stream)) if !stream.is_empty() => (addr, Some(stream.to_string())),
+            Some((addr, _)) => (addr, None),
+            None => (spec, None),
+        };
+
+        let any = IpAddr::V4(Ipv4Addr::UNSPECIFIED);
+        let bind_addr = if addr.is_empty() {
+            SocketAddr::new(any, VBAN_DEFAULT_PORT)
+        } else if let Ok(port) = addr.parse::<u16>() {
+            SocketAddr::new(any, port)
+        } else if let Ok(ip) = addr.parse::<IpAddr>() {
+            SocketAddr::new(ip, VBAN_DEFAULT_PORT)
+        } else {
+            addr.parse().map_err(|_| invalid())?
+        };
+
+        Ok(Self {
+            bind_addr,
+            stream_name,
+        })
+    }
+}
+
+/// Decoded audio from one VBAN packet
+#[derive(Debug, Clone, PartialEq)]
+pub struct VbanFrame {
+    /// Stream name from the header
+    pub stream_name: String,
+    /// Sample rate of the stream
+    pub sample_rate: u32,
+    /// Channel count of the stream
+    pub channels: u16,
+    /// Packet counter, for detecting loss
+    pub frame_counter: u32,
+    /// Interleaved samples converted to f32
+    pub samples: Vec<f32>,
+}
+
+/// Parse a VBAN audio packet
+///
+/// Returns `None` for anything that isn't uncompressed VBAN audio (text,
+/// serial and service sub-protocols, codecs, malformed packets).
+pub fn parse_vban_packet(packet: &[u8]) -> Option<VbanFrame> {
+    if packet.len() < HEADER_LEN || &packet[..4] != b"VBAN" {
+        return None;
+    }
+
+    let format_sr = packet[4];
+    // Sub-protocol 0 is audio
+    if format_sr & 0xE0 != 0 {
+        return None;
+    }
+    let sample_rate = *SAMPLE_RATES.get((format_sr & 0x1F) as usize)?;
+    let frames = packet[5] as usize + 1;
+    let channels = packet[6] as usize + 1;
+    let format_bit = packet[7];
+    // Only the PCM codec is supported
+    if format_bit & 0xF0 != 0 {
+        return None;
+    }
+
+    let stream_name = String::from_utf8_lossy(&packet[8..24])
+        .trim_end_matches('\0')
+        .to_string();
+    let frame_counter = u32::from_le_bytes([packet[24], packet[25], packet[26], packet[27]]);
+
+    let data = &packet[HEADER_LEN..];
+    let count = frames * channels;
+    let samples: Vec<f32> = match format_bit & 0x07 {
+        // 16-bit signed
+        1 => decode(data, 2, count, |b| {
+            i16::from_le_bytes([b[0], b[1]]) as f32 / 32768.0
+        })?,
+        // 24-bit signed
+        2 => decode(data, 3, count, |b| {
+            (i32::from_le_bytes([0, b[0], b[1], b[2]]) >> 8) as f32 / 8_388_608.0
+        })?,
+        // 32-bit signed
+        3 => decode(data, 4, count, |b| {
+            i32::from_le_bytes([b[0], b[1], b[2], b[3]]) as f32 / 2_147_483_648.0
+        })?,
+        // 32-bit float
+        4 => decode(data, 4, count, |b| {
+            f32::from_le_bytes([b[0], b[1], b[2], b[3]])
+        })?,
+        // 64-bit float
+        5 => decode(data, 8, count, |b| {
+            f64::from_le_bytes([b[0], b[1], b[2], b[3], b[4], b[5], b[6], b[7]]) as f32
+        })?,
+        _ => return None,
+    };
+
+    Some(VbanFrame {
+        stream_name,
+        sample_rate,
+        channels: channels as u16,
+        frame_counter,
+        samples,
+    })
+}
+
+/// Convert `count` little-endian samples of `width` bytes each
+fn decode(
+    data: &[u8],
+    width: usize,
+    count: usize,
+    convert: impl Fn(&[u8]) -> f32,
+) -> Option<Vec<f32>> {
+    let bytes = data.get(..count * width)?;
+    Some(bytes.chunks_exact(width).map(convert).collect())
+}
+
+/// Convert interleaved audio to stereo: mono is duplicated, extra channels dropped
+pub fn to_stereo(samples: &[f32], channels: u16) -> Vec<f32> {
+    match channels {
+        2 => samples.to_vec(),
+        1 => samples.iter().flat_map(|&s| [s, s]).collect(),
+        n => samples
+            .chunks_exact(n as usize)
+            .flat_map(|frame| [frame[0], frame[1]])
+            .collect(),
+    }
+}
+
+/// Downmix interleaved audio to mono by averaging each frame
+pub fn to_mono(samples: &[f32], channels: u16) -> Vec<f32> {
+    match channels {
+        0 | 1 => samples.to_vec(),
+        n => samples
+            .chunks_exact(n as usize)
+            .map(|frame| frame.iter().sum::<f32>() / n as f32)
+            .collect(),
+    }
+}
+
+/// UDP socket receiving one VBAN stream
+pub struct VbanReceiver {
+    socket: UdpSocket,
+    source: VbanSource,
+    buffer: Vec<u8>,
+}
+
+impl VbanReceiver {
+    /// Bind the socket; `poll_interval` bounds how long `recv` blocks
+    pub fn bind(source: VbanSource, poll_interval: Duration) -> Result<Self, AudioError> {
+        let socket = UdpSocket::bind(source.bind_addr).map_err(|e| {
+            AudioError::StreamError(format!(
+                "Failed to bind VBAN socket {}: {}",
+                source.bind_addr, e
+            ))
+        })?;
+        socket
+            .set_read_timeout(Some(poll_interval))
+            .map_err(|e| AudioError::StreamError(e.to_string()))?;
+
+        info!(
+            "Listening for VBAN audio on {} (stream: {})",
+            source.bind_addr,
+            source.stream_name.as_deref().unwrap_or("any")
+        );
+
+        Ok(Self {
+            socket,
+            source,
+            buffer: vec![0; MAX_PACKET_LEN],
+        })
+    }
+
+    /// Address the socket is bound to
+    pub fn local_addr(&self) -> io::Result<SocketAddr> {
+        self.socket.local_addr()
+    }
+
+    /// Wait for the next matching audio packet
+    ///
+    /// Returns `Ok(None)` when the poll interval elapses or a packet is ignored.
+    pub fn recv(&mut self) -> io::Result<Option<VbanFrame>> {
+        let len = match self.socket.recv(&mut self.buffer) {
+            Ok(len) => len,
+            Err(e)
+                if matches!(
+                    e.kind(),
+                    io::ErrorKind::WouldBlock | io::ErrorKind::TimedOut
+                ) =>
+            {
+                return Ok(None);
+            }
+            Err(e) => return Err(e),
+        };
+
+        Ok(parse_vban_packet(&self.buffer[..len]).filter(|frame| {
+            match self.source.stream_name.as_deref() {
+                Some(wanted) => wanted.eq_ignore_ascii_case(&frame.stream_name),
+                None => true,
+            }
+        }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Build a VBAN audio packet header followed by `data`
+    fn packet(
+        sr_index: u8,
+        frames: u8,
+        channels: u8,
+        format: u8,
+        name: &str,
+        data: &[u8],
+    ) -> Vec<u8> {
+        let mut p = b"VBAN".to_vec();
+        p.extend_from_slice(&[sr_index, frames - 1, channels - 1, format]);
+        let mut stream = [0u8; 16];
+        stream[..name.len()].copy_from_slice(name.as_bytes());
+        p.extend_from_slice(&stream);
+        p.extend_from_slice(&7u32.to_le_bytes());
+        p.extend_from_slice(data);
+        p
+    }
+
+    #[test]
+    fn test_parse_source_names() {
+        let any = IpAddr::V4(Ipv4Addr::UNSPECIFIED);
+        let source = VbanSource::parse("vban").unwrap();
+        assert_eq!(source.bind_addr, SocketAddr::new(any, VBAN_DEFAULT_PORT));
+        assert_eq!(source.stream_name, None);
+
+        let source = VbanSource::parse("vban:7000/Stream1").unwrap();
+        assert_eq!(source.bind_addr, SocketAddr::new(any, 7000));
+        assert_eq!(source.stream_name.as_deref(), Some("Stream1"));
+
+        let source = VbanSource::parse("vban:192.168.1.20:6981").unwrap();
+        assert_eq!(source.bind_addr, "192.168.1.20:6981".parse().unwrap());
+
+        let source = VbanSource::parse("vban:10.0.0.2").unwrap();
+        assert_eq!(source.bind_addr, "10.0.0.2:6980".parse().unwrap());
+
+        assert!(VbanSource::parse("vban:not-an-address").is_err());
+        assert!(!VbanSource::matches("vbanana"));
+        assert!(!VbanSource::matches("alsa_output.monitor"));
+    }
+
+    #[test]
+    fn test_parse_int16_packet() {
+        let data: Vec<u8> = [16384i16, -16384, 0, 32767]
+            .iter()
+            .flat_map(|s| s.to_le_bytes())
+            .collect();
+        // Index 3 = 48 kHz, 2 frames of stereo 16-bit
+        let frame = parse_vban_packet(&packet(3, 2, 2, 1, "Mixer", &data)).unwrap();
+
+        assert_eq!(frame.sample_rate, 48000);
+        assert_eq!(frame.channels, 2);
+        assert_eq!(frame.stream_name, "Mixer");
+        assert_eq!(frame.frame_counter, 7);
+        assert_eq!(frame.samples.len(), 4);
+        assert!((frame.samples[0] - 0.5).abs() < 1e-4);
+        assert!((frame.samples[1] + 0.5).abs() < 1e-4);
+        assert!((frame.samples[3] - 1.0).abs() < 1e-4);
+    }
+
+    #[test]
+    fn test_parse_int24_and_float_packets() {
+        let data = [0x00, 0x00, 0xC0]; // -0.5 in 24-bit
+        let frame = parse_vban_packet(&packet(16, 1, 1, 2, "", &data)).unwrap();
+        assert_eq!(frame.sample_rate, 44100);
+        assert!((frame.samples[0] + 0.5).abs() < 1e-6);
+
+        let data = 0.25f32.to_le_bytes();
+        let frame = parse_vban_packet(&packet(3, 1, 1, 4, "", &data)).unwrap();
+        assert_eq!(frame.samples, vec![0.25]);
+    }
+
+    #[test]
+    fn test_rejects_non_audio_packets() {
+        let data = [0u8; 8];
+        // Truncated data
+        assert!(parse_vban_packet(&packet(3, 4, 2, 1, "", &data[..4])).is_none());
+        // Text sub-protocol
+        assert!(parse_vban_packet(&packet(0x40 | 3, 1, 1, 1, "", &data)).is_none());
+        // Non-PCM codec
+        assert!(parse_vban_packet(&packet(3, 1, 1, 0x10 | 1, "", &data)).is_none());
+        // Bad magic
+        let mut bad = packet(3, 1, 1, 1, "", &data);
+        bad[0] = b'X';
+        assert!(parse_vban_packet(&bad).is_none());
+    }
+
+    #[test]
+    fn test_to_stereo() {
+        assert_eq!(to_stereo(&[0.1, 0.2], 1), vec![0.1, 0.1, 0.2, 0.2]);
+        assert_eq!(
+            to_stereo(&[0.1, 0.2, 0.3, 0.4, 0.5, 0.6], 3),
+            vec![0.1, 0.2, 0.4, 0.5]
+        );
+    }
+
+    #[test]
+    fn test_to_mono() {
+        assert_eq!(to_mono(&[0.1, 0.2], 1), vec![0.1, 0.2]);
+        assert_eq!(to_mono(&[0.2, 0.4, -0.5, 0.5], 2), vec![0.3, 0.0]);
+        // A duplicated mono signal comes back unchanged
+        assert_eq!(to_mono(&to_stereo(&[0.25, -0.75], 1), 2), vec![0.25, -0.75]);
+    }
+
+    #[test]
+    fn test_receiver_filters_stream_name() {
+        let source = VbanSource {
+            bind_addr: "127.0.0.1:0".parse().unwrap(),
+            stream_name: Some("Stream1".to_string()),
+        };
+        let mut receiver = VbanReceiver::bind(source, Duration::from_millis(200)).unwrap();
+        let target = receiver.local_addr().unwrap();
+
+        let sender = UdpSocket::bind("127.0.0.1:0").unwrap();
+        let data = 0.5f32.to_le_bytes();
+        sender
+            .send_to(&packet(3, 1, 1, 4, "Other", &data), target)
+            .unwrap();
+        sender
+            .send_to(&packet(3, 1, 1, 4, "Stream1", &data), target)
+            .unwrap();
+
+        assert_eq!(receiver.recv().unwrap(), None);
+        let frame = receiver.recv().unwrap().unwrap();
+        assert_eq!(frame.stream_name, "Stream1");
+        assert_eq!(frame.samples, vec![0.5]);
+    }
+}
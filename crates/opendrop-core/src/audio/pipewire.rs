@@ -1,38 +1,81 @@
 //! Native PipeWire audio capture
 //!
 //! Captures audio directly from PipeWire without subprocess overhead.
+//! The stream asks for 32-bit float audio at the pipeline's rate; whatever
+//! the server negotiates is reported back so the capture can be resampled.
+//! Besides devices and monitors, the output stream of a single application
+//! can be captured by targeting `app:<node name>`.
 
+use std::io::Cursor;
+use std::sync::atomic::{AtomicBool, AtomicU32, Ordering};
 use std::sync::mpsc::{self, Receiver, Sender};
-use std::sync::{Arc, Mutex};
+use std::sync::Arc;
 use std::thread::{self, JoinHandle};
 
+use libspa::param::audio::{AudioFormat, AudioInfoRaw};
+use libspa::param::format::{MediaSubtype, MediaType};
+use libspa::param::format_utils;
+use libspa::param::ParamType;
+use libspa::pod::serialize::PodSerializer;
+use libspa::pod::{Object, Pod, Value};
+use libspa::utils::SpaTypes;
 use pipewire as pw;
 use pw::context::Context;
 use pw::main_loop::MainLoop;
 use pw::properties::properties;
-use pw::stream::{Stream, StreamFlags};
+use pw::stream::{Stream, StreamFlags, StreamState};
 
 use tracing::{debug, error, info, warn};
 
+use super::network::to_stereo;
 use super::AudioError;
 
+/// Device name prefix selecting an application's output stream
+pub const APP_DEVICE_PREFIX: &str = "app:";
+/// Media class of application playback streams
+const APP_STREAM_CLASS: &str = "Stream/Output/Audio";
+
 /// PipeWire capture configuration
 #[derive(Debug, Clone)]
 pub struct PipeWireConfig {
     /// Target node name (e.g., "alsa_output.pci-0000_00_1f.3.analog-stereo.monitor")
     pub target: Option<String>,
-    /// Sample rate (default: 44100)
+    /// Capture the monitor of the target (or default) sink rather than a
+    /// source; implied for targets ending in ".monitor"
+    pub capture_sink: bool,
+    /// Requested sample rate (default: 44100)
     pub sample_rate: u32,
-    /// Channels (default: 2)
+    /// Requested channels (default: 2)
     pub channels: u32,
+    /// Requested buffer size in frames (default: 1024)
+    pub buffer_frames: u32,
+}
+
+impl PipeWireConfig {
+    /// Node to connect to and whether it is a sink to monitor
+    fn resolve_target(&self) -> (Option<&str>, bool) {
+        match self.target.as_deref() {
+            // Application streams are linked to directly, never monitored
+            Some(target) if target.starts_with(APP_DEVICE_PREFIX) => {
+                (Some(&target[APP_DEVICE_PREFIX.len()..]), false)
+            }
+            Some(target) => match target.strip_suffix(".monitor") {
+                Some(sink) => (Some(sink), true),
+                None => (Some(target), self.capture_sink),
+            },
+            None => (None, self.capture_sink),
+        }
+    }
 }
 
 impl Default for PipeWireConfig {
     fn default() -> Self {
         Self {
             target: None,
+            capture_sink: false,
             sample_rate: 44100,
             channels: 2,
+            buffer_frames: 1024,
         }
     }
 }
@@ -50,6 +93,37 @@ pub struct PipeWireSource {
     pub media_class: String,
     /// Whether this is a monitor (captures output audio)
     pub is_monitor: bool,
+    /// Application owning the node, if it is an application stream
+    pub application: Option<String>,
+}
+
+/// Properties collected for a node while parsing pw-cli output
+#[derive(Default)]
+struct NodeProps {
+    id: u32,
+    name: Option<String>,
+    description: Option<String>,
+    class: Option<String>,
+    application: Option<String>,
+}
+
+impl NodeProps {
+    /// The audio device or application stream described, if it is one
+    fn into_source(self) -> Option<PipeWireSource> {
+        let name = self.name?;
+        let class = self.class?;
+        if !class.starts_with("Audio/") && class != APP_STREAM_CLASS {
+            return None;
+        }
+        Some(PipeWireSource {
+            id: self.id,
+            is_monitor: name.contains(".monitor") || class.contains("Monitor"),
+            description: self.description.unwrap_or_else(|| name.clone()),
+            name,
+            media_class: class,
+            application: self.application,
+        })
+    }
 }
 
 /// Commands for the PipeWire thread
@@ -60,8 +134,12 @@ enum PipeWireCommand {
 /// User data for stream callbacks
 struct StreamData {
     sample_tx: Sender<Vec<f32>>,
-    #[allow(dead_code)]
-    channels: u32, // Reserved for future format negotiation
+    /// Format negotiated with the server
+    format: AudioInfoRaw,
+    /// Negotiated sample rate, shared with the consumer
+    sample_rate: Arc<AtomicU32>,
+    /// Set when the stream loses its target node
+    disconnected: Arc<AtomicBool>,
 }
 
 /// PipeWire audio capture handle
@@ -69,6 +147,7 @@ pub struct PipeWireCapture {
     command_tx: Option<Sender<PipeWireCommand>>,
     thread_handle: Option<JoinHandle<()>>,
     running: bool,
+    disconnected: Arc<AtomicBool>,
 }
 
 impl PipeWireCapture {
@@ -78,104 +157,28 @@ impl PipeWireCapture {
             command_tx: None,
             thread_handle: None,
             running: false,
+            disconnected: Arc::new(AtomicBool::new(false)),
         }
     }
 
-    /// Check if PipeWire is available on this system
+    /// Check if a PipeWire server is reachable
     pub fn is_available() -> bool {
-        // Try to init PipeWire
         pw::init();
-        true
+        let Ok(mainloop) = MainLoop::new(None) else {
+            return false;
+        };
+        let Ok(context) = Context::new(&mainloop) else {
+            return false;
+        };
+        context.connect(None).is_ok()
     }
 
     /// List available PipeWire audio sources
     pub fn list_sources() -> Vec<PipeWireSource> {
-        let mut sources = Vec::new();
-
-        pw::init();
-
-        // Use pw-cli or pw-dump to list sources
-        // This is a workaround since the registry API is complex
-        if let Ok(output) = std::process::Command::new("pw-cli")
-            .args(["list-objects"])
-            .output()
-        {
-            if let Ok(stdout) = String::from_utf8(output.stdout) {
-                // Parse pw-cli output to find audio nodes
-                let mut current_id: Option<u32> = None;
-                let mut current_name: Option<String> = None;
-                let mut current_desc: Option<String> = None;
-                let mut current_class: Option<String> = None;
-
-                for line in stdout.lines() {
-                    let line = line.trim();
-
-                    // New object starts with "id X, type PipeWire:Interface:Node"
-                    if line.starts_with("id ") && line.contains("type PipeWire:Interface:Node") {
-                        // Save previous if it was audio
-                        if let (Some(id), Some(name), Some(class)) =
-                            (current_id, current_name.take(), current_class.take())
-                        {
-                            if class.starts_with("Audio/") {
-                                let is_monitor = name.contains(".monitor") ||
-                                                 class.contains("Monitor");
-                                sources.push(PipeWireSource {
-                                    id,
-                                    name: name.clone(),
-                                    description: current_desc.take().unwrap_or(name),
-                                    media_class: class,
-                                    is_monitor,
-                                });
-                            }
-                        }
-
-                        // Parse new ID
-                        if let Some(id_str) = line.split(',').next() {
-                            if let Some(id_num) = id_str.strip_prefix("id ") {
-                                current_id = id_num.trim().parse().ok();
-                            }
-                        }
-                        current_name = None;
-                        current_desc = None;
-                        current_class = None;
-                    }
-
-                    // Parse properties
-                    if line.contains("node.name") {
-                        if let Some(val) = extract_property_value(line) {
-                            current_name = Some(val);
-                        }
-                    }
-                    if line.contains("node.description") {
-                        if let Some(val) = extract_property_value(line) {
-                            current_desc = Some(val);
-                        }
-                    }
-                    if line.contains("media.class") {
-                        if let Some(val) = extract_property_value(line) {
-                            current_class = Some(val);
-                        }
-                    }
-                }
-
-                // Don't forget last one
-                if let (Some(id), Some(name), Some(class)) =
-                    (current_id, current_name.take(), current_class.take())
-                {
-                    if class.starts_with("Audio/") {
-                        let is_monitor = name.contains(".monitor") ||
-                                         class.contains("Monitor");
-                        sources.push(PipeWireSource {
-                            id,
-                            name: name.clone(),
-                            description: current_desc.take().unwrap_or(name),
-                            media_class: class,
-                            is_monitor,
-                        });
-                    }
-                }
-            }
-        }
+        let mut sources: Vec<PipeWireSource> = Self::list_nodes()
+            .into_iter()
+            .filter(|node| node.media_class.starts_with("Audio/"))
+            .collect();
 
         // Fallback: try wpctl
         if sources.is_empty() {
@@ -192,11 +195,42 @@ impl PipeWireCapture {
         sources
     }
 
+    /// List applications currently playing audio, one stream per node name
+    pub fn list_applications() -> Vec<PipeWireSource> {
+        let mut apps: Vec<PipeWireSource> = Vec::new();
+        for node in Self::list_nodes() {
+            if node.media_class == APP_STREAM_CLASS && !apps.iter().any(|app| app.name == node.name) {
+                apps.push(node);
+            }
+        }
+        apps
+    }
+
+    /// Audio nodes and application streams known to the server
+    fn list_nodes() -> Vec<PipeWireSource> {
+        pw::init();
+
+        // Use pw-cli to list nodes
+        // This is a workaround since the registry API is complex
+        std::process::Command::new("pw-cli")
+            .args(["list-objects"])
+            .output()
+            .ok()
+            .and_then(|output| String::from_utf8(output.stdout).ok())
+            .map(|stdout| parse_pw_cli_objects(&stdout))
+            .unwrap_or_default()
+    }
+
     /// Start capturing audio
+    ///
+    /// Samples are delivered as interleaved stereo; `sample_rate` is updated
+    /// with the rate negotiated with the server. Returns once the stream is
+    /// connected, or with the error that prevented it.
     pub fn start(
         &mut self,
         config: PipeWireConfig,
         sample_tx: Sender<Vec<f32>>,
+        sample_rate: Arc<AtomicU32>,
     ) -> Result<(), AudioError> {
         if self.running {
             warn!("PipeWire capture already running");
@@ -204,13 +238,35 @@ impl PipeWireCapture {
         }
 
         let (command_tx, command_rx) = mpsc::channel();
+        let (ready_tx, ready_rx) = mpsc::channel();
+        self.disconnected.store(false, Ordering::Relaxed);
+        let disconnected = Arc::clone(&self.disconnected);
 
         let thread_handle = thread::spawn(move || {
-            if let Err(e) = run_pipewire_capture(config, command_rx, sample_tx) {
-                error!("PipeWire capture error: {}", e);
+            let result =
+                run_pipewire_capture(config, command_rx, sample_tx, sample_rate, disconnected, &ready_tx);
+            if let Err(e) = result {
+                // Before the stream is up the error goes back to `start`
+                if let Err(mpsc::SendError(Err(e))) = ready_tx.send(Err(e)) {
+                    error!("PipeWire capture error: {}", e);
+                }
             }
         });
 
+        match ready_rx.recv() {
+            Ok(Ok(())) => {}
+            Ok(Err(e)) => {
+                let _ = thread_handle.join();
+                return Err(e);
+            }
+            Err(_) => {
+                let _ = thread_handle.join();
+                return Err(AudioError::StreamError(
+                    "PipeWire capture thread exited".to_string(),
+                ));
+            }
+        }
+
         self.command_tx = Some(command_tx);
         self.thread_handle = Some(thread_handle);
         self.running = true;
@@ -237,6 +293,11 @@ impl PipeWireCapture {
     pub fn is_running(&self) -> bool {
         self.running
     }
+
+    /// Whether the stream lost its target node (device unplugged or removed)
+    pub fn is_disconnected(&self) -> bool {
+        self.disconnected.load(Ordering::Relaxed)
+    }
 }
 
 impl Default for PipeWireCapture {
@@ -256,6 +317,9 @@ fn run_pipewire_capture(
     config: PipeWireConfig,
     command_rx: Receiver<PipeWireCommand>,
     sample_tx: Sender<Vec<f32>>,
+    sample_rate: Arc<AtomicU32>,
+    disconnected: Arc<AtomicBool>,
+    ready_tx: &Sender<Result<(), AudioError>>,
 ) -> Result<(), AudioError> {
     pw::init();
 
@@ -276,27 +340,71 @@ fn run_pipewire_capture(
         *pw::keys::MEDIA_ROLE => "Music",
     };
 
-    // Set target node if specified (use raw property name)
-    if let Some(ref target) = config.target {
-        props.insert("node.target", target.as_str());
+    props.insert(
+        *pw::keys::NODE_LATENCY,
+        format!("{}/{}", config.buffer_frames, config.sample_rate),
+    );
+
+    // Monitors are captured by targeting the sink itself
+    let (target, capture_sink) = config.resolve_target();
+    if let Some(target) = target {
+        props.insert(*pw::keys::TARGET_OBJECT, target);
+        // Fail instead of being moved to another device when the target goes away
+        props.insert(*pw::keys::NODE_DONT_RECONNECT, "true");
+    }
+    if capture_sink {
+        props.insert(*pw::keys::STREAM_CAPTURE_SINK, "true");
     }
 
     let stream = Stream::new(&core, "opendrop-capture", props)
         .map_err(|e| AudioError::StreamError(format!("Failed to create stream: {}", e)))?;
 
-    // Stream user data
-    let data = Arc::new(Mutex::new(StreamData {
-        sample_tx: sample_tx.clone(),
-        channels: config.channels,
-    }));
-
-    let data_clone = Arc::clone(&data);
+    let data = StreamData {
+        sample_tx,
+        format: AudioInfoRaw::new(),
+        sample_rate,
+        disconnected: Arc::clone(&disconnected),
+    };
 
     // Register stream listener
     let _listener = stream
-        .add_local_listener_with_user_data(data_clone)
-        .state_changed(|_, _, old, new| {
+        .add_local_listener_with_user_data(data)
+        .state_changed(|_, data, old, new| {
             debug!("PipeWire stream state: {:?} -> {:?}", old, new);
+            let lost = match &new {
+                StreamState::Error(e) => {
+                    warn!("PipeWire stream error: {}", e);
+                    true
+                }
+                StreamState::Unconnected => !matches!(old, StreamState::Unconnected),
+                _ => false,
+            };
+            if lost {
+                data.disconnected.store(true, Ordering::Relaxed);
+            }
+        })
+        .param_changed(|_, data, id, param| {
+            let Some(param) = param else {
+                return;
+            };
+            if id != ParamType::Format.as_raw() {
+                return;
+            }
+            if !matches!(
+                format_utils::parse_format(param),
+                Ok((MediaType::Audio, MediaSubtype::Raw))
+            ) {
+                return;
+            }
+
+            if data.format.parse(param).is_ok() {
+                info!(
+                    "PipeWire format negotiated: {} Hz, {} channels",
+                    data.format.rate(),
+                    data.format.channels()
+                );
+                data.sample_rate.store(data.format.rate(), Ordering::Relaxed);
+            }
         })
         .process(|stream_ref, data| {
             // Process audio buffer inline to avoid type issues
@@ -306,11 +414,6 @@ fn run_pipewire_capture(
                     return;
                 }
 
-                let data_guard = match data.lock() {
-                    Ok(g) => g,
-                    Err(_) => return,
-                };
-
                 // Get the chunk info (always present, use offset/size from it)
                 let chunk = datas[0].chunk();
                 let offset = chunk.offset() as usize;
@@ -318,14 +421,15 @@ fn run_pipewire_capture(
 
                 if let Some(slice) = datas[0].data() {
                     if size > 0 && offset + size <= slice.len() {
-                        // Convert bytes to f32 samples (assuming F32LE)
+                        // Convert bytes to f32 samples (F32LE was requested)
                         let samples: Vec<f32> = slice[offset..offset + size]
                             .chunks_exact(4)
                             .map(|c| f32::from_le_bytes([c[0], c[1], c[2], c[3]]))
                             .collect();
 
-                        // Send samples
-                        let _ = data_guard.sample_tx.send(samples);
+                        // Send samples, mapped to the stereo layout decks expect
+                        let channels = data.format.channels().clamp(1, u16::MAX as u32) as u16;
+                        let _ = data.sample_tx.send(to_stereo(&samples, channels));
                     }
                 }
             }
@@ -333,8 +437,10 @@ fn run_pipewire_capture(
         .register()
         .map_err(|e| AudioError::StreamError(format!("Failed to register listener: {}", e)))?;
 
-    // Connect without format params (let PipeWire negotiate)
-    let mut params: Vec<&libspa::pod::Pod> = Vec::new();
+    // Ask for F32LE at the requested rate and channel count
+    let format = format_param(&config)?;
+    let mut params = [Pod::from_bytes(&format)
+        .ok_or_else(|| AudioError::ConfigError("Invalid PipeWire format param".to_string()))?];
 
     // Connect the stream
     stream
@@ -347,6 +453,7 @@ fn run_pipewire_capture(
         .map_err(|e| AudioError::StreamError(format!("Failed to connect stream: {}", e)))?;
 
     info!("PipeWire stream connected, running main loop");
+    let _ = ready_tx.send(Ok(()));
 
     // Create a PipeWire channel to signal quit from the stop check thread
     let (pw_sender, pw_receiver) = pw::channel::channel::<()>();
@@ -357,7 +464,7 @@ fn run_pipewire_capture(
         mainloop_clone.quit();
     });
 
-    // Spawn a thread to check for stop command and signal the main loop
+    // Spawn a thread to check for stop command or a lost stream and signal the main loop
     let stop_check = thread::spawn(move || {
         loop {
             match command_rx.recv_timeout(std::time::Duration::from_millis(100)) {
@@ -366,7 +473,13 @@ fn run_pipewire_capture(
                     let _ = pw_sender.send(());
                     break;
                 }
-                Err(mpsc::RecvTimeoutError::Timeout) => continue,
+                Err(mpsc::RecvTimeoutError::Timeout) => {
+                    if disconnected.load(Ordering::Relaxed) {
+                        warn!("PipeWire stream disconnected from its target");
+                        let _ = pw_sender.send(());
+                        break;
+                    }
+                }
             }
         }
     });
@@ -378,6 +491,23 @@ fn run_pipewire_capture(
     Ok(())
 }
 
+/// Serialize an EnumFormat param requesting F32LE audio in the configured format
+fn format_param(config: &PipeWireConfig) -> Result<Vec<u8>, AudioError> {
+    let mut info = AudioInfoRaw::new();
+    info.set_format(AudioFormat::F32LE);
+    info.set_rate(config.sample_rate);
+    info.set_channels(config.channels);
+
+    let object = Object {
+        type_: SpaTypes::ObjectParamFormat.as_raw(),
+        id: ParamType::EnumFormat.as_raw(),
+        properties: info.into(),
+    };
+    PodSerializer::serialize(Cursor::new(Vec::new()), &Value::Object(object))
+        .map(|(cursor, _)| cursor.into_inner())
+        .map_err(|e| AudioError::ConfigError(format!("Failed to build PipeWire format: {:?}", e)))
+}
+
 /// Extract property value from pw-cli output line
 fn extract_property_value(line: &str) -> Option<String> {
     // Format: "  *key = value" or "  key = value"
@@ -390,6 +520,51 @@ fn extract_property_value(line: &str) -> Option<String> {
     }
 }
 
+/// Parse pw-cli list-objects output into audio nodes and application streams
+fn parse_pw_cli_objects(output: &str) -> Vec<PipeWireSource> {
+    let mut nodes = Vec::new();
+    let mut current: Option<NodeProps> = None;
+
+    for line in output.lines() {
+        let line = line.trim();
+
+        // Objects start with "id X, type ..."; only nodes are of interest
+        if let Some(rest) = line.strip_prefix("id ") {
+            nodes.extend(current.take().and_then(NodeProps::into_source));
+            if line.contains("type PipeWire:Interface:Node") {
+                current = rest
+                    .split(',')
+                    .next()
+                    .and_then(|id| id.trim().parse().ok())
+                    .map(|id| NodeProps {
+                        id,
+                        ..Default::default()
+                    });
+            }
+            continue;
+        }
+
+        // Parse properties
+        let Some(node) = current.as_mut() else {
+            continue;
+        };
+        let Some((key, _)) = line.split_once('=') else {
+            continue;
+        };
+        let value = extract_property_value(line);
+        match key.trim().trim_start_matches('*').trim() {
+            "node.name" => node.name = value,
+            "node.description" => node.description = value,
+            "media.class" => node.class = value,
+            "application.name" => node.application = value,
+            _ => {}
+        }
+    }
+
+    nodes.extend(current.and_then(NodeProps::into_source));
+    nodes
+}
+
 /// Parse wpctl status output
 fn parse_wpctl_status(output: &str, sources: &mut Vec<PipeWireSource>) {
     let mut in_audio_section = false;
@@ -432,6 +607,7 @@ fn parse_wpctl_status(output: &str, sources: &mut Vec<PipeWireSource>) {
                                     "Audio/Sink".to_string()
                                 },
                                 is_monitor,
+                                application: None,
                             });
                         }
                     }
@@ -457,6 +633,68 @@ mod tests {
         assert_eq!(config.sample_rate, 44100);
         assert_eq!(config.channels, 2);
         assert!(config.target.is_none());
+        assert!(!config.capture_sink);
+    }
+
+    #[test]
+    fn test_monitor_targets_capture_sink() {
+        let config = PipeWireConfig {
+            target: Some("alsa_output.pci-0000_00_1f.3.analog-stereo.monitor".to_string()),
+            ..Default::default()
+        };
+        assert_eq!(
+            config.resolve_target(),
+            (Some("alsa_output.pci-0000_00_1f.3.analog-stereo"), true)
+        );
+
+        let config = PipeWireConfig {
+            target: Some("alsa_input.usb-mic".to_string()),
+            ..Default::default()
+        };
+        assert_eq!(config.resolve_target(), (Some("alsa_input.usb-mic"), false));
+
+        let config = PipeWireConfig {
+            capture_sink: true,
+            ..Default::default()
+        };
+        assert_eq!(config.resolve_target(), (None, true));
+
+        let config = PipeWireConfig {
+            target: Some("app:spotify".to_string()),
+            capture_sink: true,
+            ..Default::default()
+        };
+        assert_eq!(config.resolve_target(), (Some("spotify"), false));
+    }
+
+    #[test]
+    fn test_parse_pw_cli_objects() {
+        let output = "\
+\tid 31, type PipeWire:Interface:Node/3
+\t\tnode.name = \"alsa_output.pci-0000_00_1f.3.analog-stereo\"
+\t\tnode.description = \"Built-in Audio Analog Stereo\"
+\t\tmedia.class = \"Audio/Sink\"
+\tid 74, type PipeWire:Interface:Client/3
+\t\tapplication.name = \"Spotify\"
+\tid 75, type PipeWire:Interface:Node/3
+\t\tapplication.name = \"Spotify\"
+\t\tnode.name = \"spotify\"
+\t\tmedia.class = \"Stream/Output/Audio\"
+\tid 80, type PipeWire:Interface:Node/3
+\t\tnode.name = \"v4l2_input.camera\"
+\t\tmedia.class = \"Video/Source\"
+";
+        let nodes = parse_pw_cli_objects(output);
+        assert_eq!(nodes.len(), 2);
+
+        assert_eq!(nodes[0].id, 31);
+        assert_eq!(nodes[0].description, "Built-in Audio Analog Stereo");
+        assert_eq!(nodes[0].application, None);
+
+        assert_eq!(nodes[1].id, 75);
+        assert_eq!(nodes[1].name, "spotify");
+        assert_eq!(nodes[1].media_class, APP_STREAM_CLASS);
+        assert_eq!(nodes[1].application.as_deref(), Some("Spotify"));
     }
 
     #[test]
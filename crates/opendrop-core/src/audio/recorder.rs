@@ -0,0 +1,316 @@
+//! WAV recording of the captured audio stream
+//!
+//! Samples are handed to a writer thread over a channel so the capture path
+//! never blocks on disk I/O. Files are 32-bit float WAV, matching the samples
+//! exactly as they were fed to the visualizers.
+
+use std::fs::File;
+use std::io::{self, BufWriter, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{self, Sender};
+use std::thread::{self, JoinHandle};
+
+use tracing::{info, warn};
+
+use super::capture::AudioError;
+
+/// WAVE_FORMAT_IEEE_FLOAT
+const FORMAT_IEEE_FLOAT: u16 = 3;
+/// Size of the RIFF/fmt/fact/data headers preceding the sample data
+const HEADER_LEN: u32 = 58;
+
+/// Streaming 32-bit float WAV writer
+///
+/// Header sizes are placeholders until [`WavWriter::finalize`] is called.
+pub struct WavWriter<W: Write + Seek> {
+    inner: W,
+    sample_rate: u32,
+    channels: u16,
+    data_len: u32,
+}
+
+impl<W: Write + Seek> WavWriter<W> {
+    /// Write a placeholder header and prepare for sample data
+    pub fn new(inner: W, sample_rate: u32, channels: u16) -> io::Result<Self> {
+        let mut writer = Self {
+            inner,
+            sample_rate,
+            channels: channels.max(1),
+            data_len: 0,
+        };
+        writer.write_header()?;
+        Ok(writer)
+    }
+
+    /// Append interleaved samples
+    ///
+    /// Returns `false` once the 4 GiB WAV size limit is reached; the
+    /// remaining samples are discarded.
+    pub fn write_samples(&mut self, samples: &[f32]) -> io::Result<bool> {
+        let frame_bytes = 4 * self.channels as u32;
+        let room_frames = (u32::MAX - HEADER_LEN - self.data_len) / frame_bytes;
+        let count = samples
+            .len()
+            .min(room_frames as usize * self.channels as usize);
+
+        for sample in &samples[..count] {
+            self.inner.write_all(&sample.to_le_bytes())?;
+        }
+        self.data_len += count as u32 * 4;
+
+        Ok(count == samples.len())
+    }
+
+    /// Number of complete frames written so far
+    pub fn frames(&self) -> u32 {
+        self.data_len / (4 * self.channels as u32)
+    }
+
+    /// Fill in the header sizes and flush, returning the underlying writer
+    pub fn finalize(mut self) -> io::Result<W> {
+        self.inner.seek(SeekFrom::Start(0))?;
+        self.write_header()?;
+        self.inner.seek(SeekFrom::End(0))?;
+        self.inner.flush()?;
+        Ok(self.inner)
+    }
+
+    fn write_header(&mut self) -> io::Result<()> {
+        let block_align = self.channels * 4;
+        let byte_rate = self.sample_rate * block_align as u32;
+
+        let mut header = Vec::with_capacity(HEADER_LEN as usize);
+        header.extend_from_slice(b"RIFF");
+        header.extend_from_slice(&(HEADER_LEN - 8 + self.data_len).to_le_bytes());
+        header.extend_from_slice(b"WAVE");
+
+        // Non-PCM formats carry an (empty) extension size and a fact chunk
+        header.extend_from_slice(b"fmt ");
+        header.extend_from_slice(&18u32.to_le_bytes());
+        header.extend_from_slice(&FORMAT_IEEE_FLOAT.to_le_bytes());
+        header.extend_from_slice(&self.channels.to_le_bytes());
+        header.extend_from_slice(&self.sample_rate.to_le_bytes());
+        header.extend_from_slice(&byte_rate.to_le_bytes());
+        header.extend_from_slice(&block_align.to_le_bytes());
+        header.extend_from_slice(&32u16.to_le_bytes());
+        header.extend_from_slice(&0u16.to_le_bytes());
+
+        header.extend_from_slice(b"fact");
+        header.extend_from_slice(&4u32.to_le_bytes());
+        header.extend_from_slice(&self.frames().to_le_bytes());
+
+        header.extend_from_slice(b"data");
+        header.extend_from_slice(&self.data_len.to_le_bytes());
+
+        self.inner.write_all(&header)
+    }
+}
+
+/// Summary of a finished recording
+#[derive(Debug, Clone)]
+pub struct RecordingInfo {
+    /// Path of the WAV file
+    pub path: PathBuf,
+    /// Number of frames written
+    pub frames: u32,
+    /// Sample rate of the file
+    pub sample_rate: u32,
+    /// Number of channels in the file
+    pub channels: u16,
+}
+
+impl RecordingInfo {
+    /// Recording length in seconds
+    pub fn duration_secs(&self) -> f64 {
+        self.frames as f64 / self.sample_rate.max(1) as f64
+    }
+}
+
+/// Records audio to a WAV file on a background thread
+pub struct WavRecorder {
+    sample_tx: Option<Sender<Vec<f32>>>,
+    thread_handle: Option<JoinHandle<io::Result<u32>>>,
+    path: PathBuf,
+    sample_rate: u32,
+    channels: u16,
+}
+
+impl WavRecorder {
+    /// Create the file and start the writer thread
+    pub fn start(
+        path: impl AsRef<Path>,
+        sample_rate: u32,
+        channels: u16,
+    ) -> Result<Self, AudioError> {
+        let path = path.as_ref().to_path_buf();
+        let file = File::create(&path).map_err(|e| {
+            AudioError::RecordingError(format!("Failed to create {}: {}", path.display(), e))
+        })?;
+        let mut writer = WavWriter::new(BufWriter::new(file), sample_rate, channels)
+            .map_err(|e| AudioError::RecordingError(e.to_string()))?;
+
+        let (sample_tx, sample_rx) = mpsc::channel::<Vec<f32>>();
+        let thread_handle = thread::spawn(move || {
+            let mut truncated = false;
+            for samples in sample_rx {
+                if !truncated && !writer.write_samples(&samples)? {
+                    warn!("WAV size limit reached, further audio is not recorded");
+                    truncated = true;
+                }
+            }
+            let frames = writer.frames();
+            writer.finalize()?;
+            Ok(frames)
+        });
+
+        info!("Recording audio to {}", path.display());
+        Ok(Self {
+            sample_tx: Some(sample_tx),
+            thread_handle: Some(thread_handle),
+            path,
+            sample_rate,
+            channels: channels.max(1),
+        })
+    }
+
+    /// Queue interleaved samples for writing
+    pub fn write(&self, samples: &[f32]) {
+        if let Some(tx) = &self.sample_tx {
+            let _ = tx.send(samples.to_vec());
+        }
+    }
+
+    /// Path being recorded to
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Flush remaining samples, finalize the file and stop the writer thread
+    pub fn stop(mut self) -> Result<RecordingInfo, AudioError> {
+        self.finish()
+    }
+
+    fn finish(&mut self) -> Result<RecordingInfo, AudioError> {
+        // Closing the channel lets the writer drain and finalize
+        self.sample_tx = None;
+
+        let frames = match self.thread_handle.take() {
+            Some(handle) => handle
+                .join()
+                .map_err(|_| AudioError::RecordingError("WAV writer thread panicked".to_string()))?
+                .map_err(|e| AudioError::RecordingError(e.to_string()))?,
+            None => 0,
+        };
+
+        info!(
+            "Finished recording {} ({} frames)",
+            self.path.display(),
+            frames
+        );
+        Ok(RecordingInfo {
+            path: self.path.clone(),
+            frames,
+            sample_rate: self.sample_rate,
+            channels: self.channels,
+        })
+    }
+}
+
+impl Drop for WavRecorder {
+    fn drop(&mut self) {
+        if self.thread_handle.is_some() {
+            if let Err(e) = self.finish() {
+                warn!("Failed to finalize recording: {}", e);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Cursor;
+
+    fn u32_at(bytes: &[u8], offset: usize) -> u32 {
+        u32::from_le_bytes(bytes[offset..offset + 4].try_into().unwrap())
+    }
+
+    #[test]
+    fn test_header_layout() {
+        let mut writer = WavWriter::new(Cursor::new(Vec::new()), 48000, 2).unwrap();
+        writer.write_samples(&[0.5, -0.5, 0.25, -0.25]).unwrap();
+        let bytes = writer.finalize().unwrap().into_inner();
+
+        assert_eq!(bytes.len(), HEADER_LEN as usize + 16);
+        assert_eq!(&bytes[0..4], b"RIFF");
+        assert_eq!(u32_at(&bytes, 4), bytes.len() as u32 - 8);
+        assert_eq!(&bytes[8..12], b"WAVE");
+        assert_eq!(&bytes[12..16], b"fmt ");
+        assert_eq!(
+            u16::from_le_bytes([bytes[20], bytes[21]]),
+            FORMAT_IEEE_FLOAT
+        );
+        assert_eq!(u16::from_le_bytes([bytes[22], bytes[23]]), 2);
+        assert_eq!(u32_at(&bytes, 24), 48000);
+        assert_eq!(u32_at(&bytes, 28), 48000 * 8);
+        assert_eq!(&bytes[38..42], b"fact");
+        assert_eq!(u32_at(&bytes, 46), 2);
+        assert_eq!(&bytes[50..54], b"data");
+        assert_eq!(u32_at(&bytes, 54), 16);
+    }
+
+    #[test]
+    fn test_samples_round_trip() {
+        let samples = [0.1f32, -0.2, 0.3, -0.4, 1.0, -1.0];
+        let mut writer = WavWriter::new(Cursor::new(Vec::new()), 44100, 2).unwrap();
+        writer.write_samples(&samples).unwrap();
+        assert_eq!(writer.frames(), 3);
+        let bytes = writer.finalize().unwrap().into_inner();
+
+        let decoded: Vec<f32> = bytes[HEADER_LEN as usize..]
+            .chunks(4)
+            .map(|b| f32::from_le_bytes(b.try_into().unwrap()))
+            .collect();
+        assert_eq!(decoded, samples);
+    }
+
+    #[test]
+    fn test_recorder_writes_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("take.wav");
+
+        let recorder = WavRecorder::start(&path, 44100, 2).unwrap();
+        recorder.write(&vec![0.5; 44100 * 2]);
+        recorder.write(&vec![-0.5; 44100]);
+        let info = recorder.stop().unwrap();
+
+        assert_eq!(info.frames, 44100 + 22050);
+        assert!((info.duration_secs() - 1.5).abs() < 1e-9);
+
+        let bytes = std::fs::read(&path).unwrap();
+        assert_eq!(bytes.len(), HEADER_LEN as usize + (44100 * 3) * 4);
+        assert_eq!(u32_at(&bytes, 54), 44100 * 3 * 4);
+    }
+
+    #[test]
+    fn test_drop_finalizes_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("dropped.wav");
+
+        {
+            let recorder = WavRecorder::start(&path, 48000, 2).unwrap();
+            recorder.write(&[0.0; 96]);
+        }
+
+        let bytes = std::fs::read(&path).unwrap();
+        assert_eq!(u32_at(&bytes, 54), 96 * 4);
+        assert_eq!(u32_at(&bytes, 46), 48);
+    }
+
+    #[test]
+    fn test_start_fails_for_missing_directory() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("missing").join("take.wav");
+        assert!(WavRecorder::start(&path, 48000, 2).is_err());
+    }
+}
@@ -0,0 +1,190 @@
+//! Sample-rate conversion
+//!
+//! Capture devices run at whatever rate they were configured with (44.1 kHz,
+//! 48 kHz, ...). The resampler converts the interleaved stream to the rate the
+//! rest of the pipeline expects using linear interpolation, carrying state
+//! across buffers so block boundaries don't click.
+
+/// Streaming linear-interpolation resampler for interleaved audio
+#[derive(Debug, Clone)]
+pub struct LinearResampler {
+    channels: usize,
+    input_rate: u32,
+    output_rate: u32,
+    /// Read position in input frames, relative to `prev`
+    pos: f64,
+    /// Last frame of the previous buffer
+    prev: Vec<f32>,
+    has_prev: bool,
+}
+
+impl LinearResampler {
+    /// Create a resampler between two rates
+    pub fn new(channels: u16, input_rate: u32, output_rate: u32) -> Self {
+        let channels = channels.max(1) as usize;
+        Self {
+            channels,
+            input_rate: input_rate.max(1),
+            output_rate: output_rate.max(1),
+            pos: 0.0,
+            prev: vec![0.0; channels],
+            has_prev: false,
+        }
+    }
+
+    /// Rate of the incoming stream
+    pub fn input_rate(&self) -> u32 {
+        self.input_rate
+    }
+
+    /// Rate of the produced stream
+    pub fn output_rate(&self) -> u32 {
+        self.output_rate
+    }
+
+    /// Whether samples pass through unchanged
+    pub fn is_passthrough(&self) -> bool {
+        self.input_rate == self.output_rate
+    }
+
+    /// Change the conversion rates, resetting state if they differ
+    pub fn set_rates(&mut self, input_rate: u32, output_rate: u32) {
+        let (input_rate, output_rate) = (input_rate.max(1), output_rate.max(1));
+        if input_rate != self.input_rate || output_rate != self.output_rate {
+            self.input_rate = input_rate;
+            self.output_rate = output_rate;
+            self.reset();
+        }
+    }
+
+    /// Forget the interpolation state (e.g. after a stream restart)
+    pub fn reset(&mut self) {
+        self.pos = 0.0;
+        self.has_prev = false;
+    }
+
+    /// Convert a buffer of interleaved samples
+    pub fn process(&mut self, input: &[f32]) -> Vec<f32> {
+        if self.is_passthrough() {
+            return input.to_vec();
+        }
+
+        let ch = self.channels;
+        let frames = input.len() / ch;
+        if frames == 0 {
+            return Vec::new();
+        }
+
+        // First buffer: start exactly on its first frame
+        if !self.has_prev {
+            self.prev.copy_from_slice(&input[..ch]);
+            self.has_prev = true;
+            self.pos = 1.0;
+        }
+
+        // Frame 0 is the carried-over `prev`, frames 1..=n are this buffer
+        let frame = |i: usize| -> &[f32] {
+            if i == 0 {
+                &self.prev
+            } else {
+                &input[(i - 1) * ch..i * ch]
+            }
+        };
+
+        let step = self.input_rate as f64 / self.output_rate as f64;
+        let estimate = (frames as f64 / step).ceil() as usize + 1;
+        let mut output = Vec::with_capacity(estimate * ch);
+
+        let mut pos = self.pos;
+        while pos < frames as f64 {
+            let i = pos as usize;
+            let t = (pos - i as f64) as f32;
+            let (a, b) = (frame(i), frame(i + 1));
+            output.extend(a.iter().zip(b).map(|(a, b)| a + (b - a) * t));
+            pos += step;
+        }
+
+        self.pos = pos - frames as f64;
+        self.prev
+            .copy_from_slice(&input[(frames - 1) * ch..frames * ch]);
+        output
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_passthrough_when_rates_match() {
+        let mut resampler = LinearResampler::new(2, 48000, 48000);
+        let input = vec![0.1, 0.2, 0.3, 0.4];
+        assert_eq!(resampler.process(&input), input);
+    }
+
+    #[test]
+    fn test_output_length_tracks_ratio() {
+        let mut resampler = LinearResampler::new(2, 44100, 48000);
+        let mut produced = 0;
+        // Feed one second in uneven blocks
+        for block in [441, 1000, 2048, 37] {
+            produced += resampler.process(&vec![0.0; block * 2]).len();
+        }
+        let mut remaining = 44100 - (441 + 1000 + 2048 + 37);
+        while remaining > 0 {
+            let n = remaining.min(512);
+            produced += resampler.process(&vec![0.0; n * 2]).len();
+            remaining -= n;
+        }
+        let frames = produced / 2;
+        assert!(frames.abs_diff(48000) <= 2, "got {} frames", frames);
+    }
+
+    #[test]
+    fn test_downsample_halves_length() {
+        let mut resampler = LinearResampler::new(1, 96000, 48000);
+        let output = resampler.process(&vec![0.5; 960]);
+        assert_eq!(output.len(), 480);
+        assert!(output.iter().all(|&s| (s - 0.5).abs() < 1e-6));
+    }
+
+    #[test]
+    fn test_ramp_is_continuous_across_blocks() {
+        // A linear ramp stays a linear ramp after interpolation
+        let input: Vec<f32> = (0..1000).map(|i| i as f32).collect();
+        let mut resampler = LinearResampler::new(1, 44100, 48000);
+        let mut output = resampler.process(&input[..333]);
+        output.extend(resampler.process(&input[333..]));
+
+        let step = 44100.0 / 48000.0;
+        for (k, &s) in output.iter().enumerate() {
+            let expected = k as f32 * step as f32;
+            assert!(
+                (s - expected).abs() < 1e-2,
+                "sample {}: {} vs {}",
+                k,
+                s,
+                expected
+            );
+        }
+    }
+
+    #[test]
+    fn test_channels_stay_separate() {
+        let mut resampler = LinearResampler::new(2, 22050, 44100);
+        let input: Vec<f32> = (0..100).flat_map(|_| [1.0, -1.0]).collect();
+        let output = resampler.process(&input);
+        for frame in output.chunks(2) {
+            assert_eq!(frame, [1.0, -1.0]);
+        }
+    }
+
+    #[test]
+    fn test_set_rates_resets_state() {
+        let mut resampler = LinearResampler::new(1, 44100, 48000);
+        resampler.process(&[0.0; 100]);
+        resampler.set_rates(48000, 48000);
+        assert!(resampler.is_passthrough());
+        assert_eq!(resampler.process(&[0.25; 4]), vec![0.25; 4]);
+    }
+}
@@ -1,5 +1,8 @@
 //! Lock-free ring buffer for audio data
 
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+
 use ringbuf::{traits::*, HeapRb};
 
 /// Audio ring buffer for lock-free audio distribution
@@ -30,6 +33,109 @@ impl AudioRingBuffer {
     pub fn available(&self) -> usize {
         self.consumer.occupied_len()
     }
+
+    /// Split into a writer and a latency-bounded reader
+    ///
+    /// The reader keeps at most `max_latency_frames` frames of backlog and
+    /// discards older audio rather than letting it queue up.
+    pub fn into_split(
+        self,
+        channels: u16,
+        max_latency_frames: usize,
+    ) -> (AudioProducer, AudioConsumer) {
+        let overrun = Arc::new(AtomicBool::new(false));
+        let channels = channels.max(1) as usize;
+        (
+            AudioProducer {
+                producer: self.producer,
+                overrun: Arc::clone(&overrun),
+            },
+            AudioConsumer {
+                consumer: self.consumer,
+                overrun,
+                channels,
+                max_latency: max_latency_frames.max(1) * channels,
+            },
+        )
+    }
+}
+
+/// Writing half of a split audio ring buffer
+pub struct AudioProducer {
+    producer: ringbuf::HeapProd<f32>,
+    overrun: Arc<AtomicBool>,
+}
+
+impl AudioProducer {
+    /// Push a whole buffer of samples (non-blocking)
+    ///
+    /// Buffers that don't fit are discarded and the reader is told to skip
+    /// ahead, so it never sees a partially written buffer.
+    pub fn push(&mut self, samples: &[f32]) -> bool {
+        if self.producer.vacant_len() < samples.len() {
+            self.overrun.store(true, Ordering::Release);
+            return false;
+        }
+        self.producer.push_slice(samples);
+        true
+    }
+
+    /// Whether the reading half still exists
+    pub fn is_connected(&self) -> bool {
+        self.producer.read_is_held()
+    }
+}
+
+/// Reading half of a split audio ring buffer
+pub struct AudioConsumer {
+    consumer: ringbuf::HeapCons<f32>,
+    overrun: Arc<AtomicBool>,
+    channels: usize,
+    /// Maximum backlog in samples
+    max_latency: usize,
+}
+
+impl AudioConsumer {
+    /// Pop up to `max_samples` whole frames (non-blocking)
+    ///
+    /// Stale audio beyond the latency limit is dropped first. Returns `None`
+    /// when nothing is available.
+    pub fn pop_chunk(&mut self, max_samples: usize) -> Option<Vec<f32>> {
+        self.drop_stale();
+
+        let available = self.consumer.occupied_len();
+        let len = available.min(max_samples.max(self.channels));
+        let len = len - len % self.channels;
+        if len == 0 {
+            return None;
+        }
+
+        let mut chunk = vec![0.0; len];
+        let popped = self.consumer.pop_slice(&mut chunk);
+        chunk.truncate(popped);
+        Some(chunk)
+    }
+
+    /// Get the number of samples available to read
+    pub fn available(&self) -> usize {
+        self.consumer.occupied_len()
+    }
+
+    /// Discard everything after an overrun, or the oldest frames beyond the
+    /// latency limit. Returns the number of samples dropped.
+    fn drop_stale(&mut self) -> usize {
+        if self.overrun.swap(false, Ordering::Acquire) {
+            return self.consumer.clear();
+        }
+
+        let available = self.consumer.occupied_len();
+        if available > self.max_latency {
+            let excess = available - self.max_latency;
+            let excess = excess.div_ceil(self.channels) * self.channels;
+            return self.consumer.skip(excess);
+        }
+        0
+    }
 }
 
 #[cfg(test)]
@@ -113,4 +219,50 @@ mod tests {
         assert!((output[10] - 0.0).abs() < 0.001);
         assert!((output[20] - 1.0).abs() < 0.001);
     }
+
+    #[test]
+    fn test_split_round_trip() {
+        let (mut producer, mut consumer) = AudioRingBuffer::new(64).into_split(2, 16);
+        assert!(producer.push(&[0.1, 0.2, 0.3, 0.4]));
+        assert_eq!(consumer.pop_chunk(64), Some(vec![0.1, 0.2, 0.3, 0.4]));
+        assert_eq!(consumer.pop_chunk(64), None);
+    }
+
+    #[test]
+    fn test_pop_chunk_respects_limit_and_frames() {
+        let (mut producer, mut consumer) = AudioRingBuffer::new(64).into_split(2, 32);
+        producer.push(&[1.0; 10]);
+        assert_eq!(consumer.pop_chunk(5).map(|c| c.len()), Some(4));
+        assert_eq!(consumer.available(), 6);
+    }
+
+    #[test]
+    fn test_stale_audio_is_dropped() {
+        let (mut producer, mut consumer) = AudioRingBuffer::new(64).into_split(2, 4);
+        let samples: Vec<f32> = (0..20).map(|i| i as f32).collect();
+        producer.push(&samples);
+
+        // Only the newest 4 frames survive
+        assert_eq!(consumer.pop_chunk(64), Some(samples[12..].to_vec()));
+    }
+
+    #[test]
+    fn test_overrun_discards_backlog() {
+        let (mut producer, mut consumer) = AudioRingBuffer::new(8).into_split(2, 4);
+        assert!(producer.push(&[1.0; 6]));
+        assert!(!producer.push(&[2.0; 4]));
+
+        // The reader skips the backlog, then picks up fresh audio
+        assert_eq!(consumer.pop_chunk(64), None);
+        assert!(producer.push(&[3.0; 4]));
+        assert_eq!(consumer.pop_chunk(64), Some(vec![3.0; 4]));
+    }
+
+    #[test]
+    fn test_producer_sees_dropped_consumer() {
+        let (producer, consumer) = AudioRingBuffer::new(8).into_split(2, 4);
+        assert!(producer.is_connected());
+        drop(consumer);
+        assert!(!producer.is_connected());
+    }
 }
@@ -0,0 +1,157 @@
+//! Silence detection
+//!
+//! Tracks how long the captured stream has stayed below a level threshold so
+//! unattended installations can switch to idle visuals when the music stops
+//! and resume as soon as signal returns.
+
+/// Default level below which audio counts as silent (about -54 dBFS RMS)
+pub const DEFAULT_SILENCE_THRESHOLD: f32 = 0.002;
+/// Default time the stream must stay silent before going idle
+pub const DEFAULT_IDLE_TIMEOUT_SECS: f32 = 10.0;
+
+/// Detects prolonged silence in interleaved audio
+#[derive(Debug, Clone)]
+pub struct SilenceDetector {
+    threshold: f32,
+    timeout_secs: f32,
+    sample_rate: u32,
+    channels: u16,
+    /// Consecutive silent frames seen
+    silent_frames: u64,
+    idle: bool,
+}
+
+impl SilenceDetector {
+    /// Create a detector going idle after `timeout_secs` below `threshold` (RMS)
+    pub fn new(threshold: f32, timeout_secs: f32, sample_rate: u32, channels: u16) -> Self {
+        Self {
+            threshold: threshold.max(0.0),
+            timeout_secs: timeout_secs.max(0.0),
+            sample_rate: sample_rate.max(1),
+            channels: channels.max(1),
+            silent_frames: 0,
+            idle: false,
+        }
+    }
+
+    /// Change the level threshold and timeout
+    pub fn configure(&mut self, threshold: f32, timeout_secs: f32) {
+        self.threshold = threshold.max(0.0);
+        self.timeout_secs = timeout_secs.max(0.0);
+    }
+
+    /// Level threshold (RMS)
+    pub fn threshold(&self) -> f32 {
+        self.threshold
+    }
+
+    /// Seconds of silence before going idle
+    pub fn timeout_secs(&self) -> f32 {
+        self.timeout_secs
+    }
+
+    /// Update the input format
+    pub fn set_format(&mut self, sample_rate: u32, channels: u16) {
+        self.sample_rate = sample_rate.max(1);
+        self.channels = channels.max(1);
+    }
+
+    /// Forget accumulated silence and leave the idle state
+    pub fn reset(&mut self) {
+        self.silent_frames = 0;
+        self.idle = false;
+    }
+
+    /// Whether the stream is currently considered idle
+    pub fn is_idle(&self) -> bool {
+        self.idle
+    }
+
+    /// Seconds of uninterrupted silence so far
+    pub fn silent_secs(&self) -> f32 {
+        self.silent_frames as f32 / self.sample_rate as f32
+    }
+
+    /// Analyse a block; returns the new state when it changes
+    pub fn process(&mut self, samples: &[f32]) -> Option<bool> {
+        if samples.is_empty() {
+            return None;
+        }
+
+        let rms = (samples.iter().map(|s| s * s).sum::<f32>() / samples.len() as f32).sqrt();
+        if rms < self.threshold {
+            self.silent_frames += (samples.len() / self.channels as usize) as u64;
+        } else {
+            self.silent_frames = 0;
+        }
+
+        let idle = self.silent_secs() >= self.timeout_secs && self.silent_frames > 0;
+        if idle != self.idle {
+            self.idle = idle;
+            Some(idle)
+        } else {
+            None
+        }
+    }
+}
+
+impl Default for SilenceDetector {
+    fn default() -> Self {
+        Self::new(DEFAULT_SILENCE_THRESHOLD, DEFAULT_IDLE_TIMEOUT_SECS, 48000, 2)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Feed `secs` of constant-level stereo audio in 10 ms blocks
+    fn feed(detector: &mut SilenceDetector, level: f32, secs: f32) -> Vec<bool> {
+        let block = vec![level; 960];
+        (0..(secs * 100.0) as usize)
+            .filter_map(|_| detector.process(&block))
+            .collect()
+    }
+
+    #[test]
+    fn test_goes_idle_after_timeout() {
+        let mut detector = SilenceDetector::new(0.01, 2.0, 48000, 2);
+        assert!(feed(&mut detector, 0.0, 1.9).is_empty());
+        assert!(!detector.is_idle());
+        assert_eq!(feed(&mut detector, 0.0, 0.2), vec![true]);
+        assert!(detector.is_idle());
+    }
+
+    #[test]
+    fn test_resumes_when_signal_returns() {
+        let mut detector = SilenceDetector::new(0.01, 1.0, 48000, 2);
+        feed(&mut detector, 0.0, 2.0);
+        assert_eq!(feed(&mut detector, 0.5, 0.01), vec![false]);
+        assert!(!detector.is_idle());
+        assert_eq!(detector.silent_secs(), 0.0);
+    }
+
+    #[test]
+    fn test_short_gaps_do_not_trigger() {
+        let mut detector = SilenceDetector::new(0.01, 1.0, 48000, 2);
+        for _ in 0..5 {
+            assert!(feed(&mut detector, 0.0, 0.8).is_empty());
+            assert!(feed(&mut detector, 0.3, 0.1).is_empty());
+        }
+    }
+
+    #[test]
+    fn test_quiet_noise_counts_as_silence() {
+        let mut detector = SilenceDetector::new(0.01, 0.5, 48000, 2);
+        assert_eq!(feed(&mut detector, 0.005, 1.0), vec![true]);
+    }
+
+    #[test]
+    fn test_reset_leaves_idle() {
+        let mut detector = SilenceDetector::new(0.01, 0.5, 48000, 2);
+        feed(&mut detector, 0.0, 1.0);
+        detector.reset();
+        assert!(!detector.is_idle());
+        assert_eq!(detector.silent_secs(), 0.0);
+    }
+}
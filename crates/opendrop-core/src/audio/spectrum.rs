@@ -0,0 +1,325 @@
+//! FFT spectrum analysis
+//!
+//! Keeps a sliding window of the captured stream and turns it into
+//! log-spaced frequency bands for spectrum analyzer displays, plus a coarse
+//! bass/mid/treble split for meters and triggers.
+
+use std::collections::VecDeque;
+use std::f32::consts::PI;
+
+use serde::{Deserialize, Serialize};
+
+/// Default FFT window length in samples
+pub const DEFAULT_FFT_SIZE: usize = 2048;
+/// Default number of output bands
+pub const DEFAULT_BANDS: usize = 32;
+
+/// Lowest frequency shown in the band layout
+const MIN_FREQ: f32 = 20.0;
+/// Highest frequency shown (clamped to Nyquist)
+const MAX_FREQ: f32 = 20000.0;
+/// Levels below this (dBFS) map to 0.0
+const FLOOR_DB: f32 = -70.0;
+/// Upper edge of the bass range
+pub const BASS_MAX_HZ: f32 = 250.0;
+/// Upper edge of the mid range (treble extends to Nyquist)
+pub const MID_MAX_HZ: f32 = 4000.0;
+/// Summed power of a full-scale sine across Hann-windowed bins, relative to its peak
+const HANN_POWER_GAIN: f32 = 1.5;
+
+/// Energy in the three broad frequency ranges (0.0-1.0 each)
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub struct BandEnergy {
+    /// 20-250 Hz
+    pub bass: f32,
+    /// 250 Hz-4 kHz
+    pub mid: f32,
+    /// 4 kHz and up
+    pub treble: f32,
+}
+
+/// Sliding-window spectrum analyzer
+pub struct SpectrumAnalyzer {
+    fft_size: usize,
+    sample_rate: u32,
+    channels: u16,
+    /// Most recent mono samples, at most `fft_size` long
+    window: VecDeque<f32>,
+    /// Precomputed Hann window coefficients
+    hann: Vec<f32>,
+}
+
+impl SpectrumAnalyzer {
+    /// Create an analyzer; `fft_size` is rounded up to a power of two
+    pub fn new(fft_size: usize, sample_rate: u32, channels: u16) -> Self {
+        let fft_size = fft_size.max(16).next_power_of_two();
+        let hann = (0..fft_size)
+            .map(|i| 0.5 - 0.5 * (2.0 * PI * i as f32 / fft_size as f32).cos())
+            .collect();
+
+        Self {
+            fft_size,
+            sample_rate: sample_rate.max(1),
+            channels: channels.max(1),
+            window: VecDeque::with_capacity(fft_size),
+            hann,
+        }
+    }
+
+    /// FFT window length in samples
+    pub fn fft_size(&self) -> usize {
+        self.fft_size
+    }
+
+    /// Reconfigure the input format, clearing the window if it changed
+    pub fn set_format(&mut self, sample_rate: u32, channels: u16) {
+        if sample_rate != self.sample_rate || channels != self.channels {
+            self.sample_rate = sample_rate.max(1);
+            self.channels = channels.max(1);
+            self.window.clear();
+        }
+    }
+
+    /// Drop all buffered audio
+    pub fn clear(&mut self) {
+        self.window.clear();
+    }
+
+    /// Append interleaved samples to the analysis window
+    pub fn push(&mut self, samples: &[f32]) {
+        for frame in samples.chunks(self.channels as usize) {
+            if self.window.len() == self.fft_size {
+                self.window.pop_front();
+            }
+            self.window.push_back(frame.iter().sum::<f32>() / frame.len() as f32);
+        }
+    }
+
+    /// Compute `count` log-spaced band levels (0.0-1.0, low to high frequency)
+    pub fn bands(&self, count: usize) -> Vec<f32> {
+        let count = count.max(1);
+        let Some(magnitudes) = self.magnitudes() else {
+            return vec![0.0; count];
+        };
+
+        let half = magnitudes.len();
+        let bin_hz = self.bin_hz();
+        let max_freq = MAX_FREQ.min(self.sample_rate as f32 / 2.0);
+        let ratio = max_freq / MIN_FREQ;
+
+        (0..count)
+            .map(|band| {
+                let lo_hz = MIN_FREQ * ratio.powf(band as f32 / count as f32);
+                let hi_hz = MIN_FREQ * ratio.powf((band + 1) as f32 / count as f32);
+                let lo = ((lo_hz / bin_hz) as usize).clamp(1, half - 1);
+                let hi = ((hi_hz / bin_hz).ceil() as usize).clamp(lo + 1, half);
+
+                let peak = magnitudes[lo..hi].iter().copied().fold(0.0f32, f32::max);
+                normalize_db(peak)
+            })
+            .collect()
+    }
+
+    /// Total energy in the bass, mid and treble ranges
+    pub fn energy(&self) -> BandEnergy {
+        let Some(magnitudes) = self.magnitudes() else {
+            return BandEnergy::default();
+        };
+
+        let bin_hz = self.bin_hz();
+        let range = |lo_hz: f32, hi_hz: f32| {
+            let lo = ((lo_hz / bin_hz) as usize).clamp(1, magnitudes.len());
+            let hi = ((hi_hz / bin_hz) as usize).clamp(lo, magnitudes.len());
+            let power: f32 = magnitudes[lo..hi].iter().map(|m| m * m).sum();
+            normalize_db((power / HANN_POWER_GAIN).sqrt())
+        };
+
+        BandEnergy {
+            bass: range(MIN_FREQ, BASS_MAX_HZ),
+            mid: range(BASS_MAX_HZ, MID_MAX_HZ),
+            treble: range(MID_MAX_HZ, self.sample_rate as f32 / 2.0),
+        }
+    }
+
+    /// Width of one FFT bin in Hz
+    fn bin_hz(&self) -> f32 {
+        self.sample_rate as f32 / self.fft_size as f32
+    }
+
+    /// Magnitude spectrum of the current window (scaled so a sine of
+    /// amplitude A peaks at A), or `None` until the window is full
+    fn magnitudes(&self) -> Option<Vec<f32>> {
+        if self.window.len() < self.fft_size {
+            return None;
+        }
+
+        let mut re: Vec<f32> = self
+            .window
+            .iter()
+            .zip(&self.hann)
+            .map(|(s, w)| s * w)
+            .collect();
+        let mut im = vec![0.0; self.fft_size];
+        fft_in_place(&mut re, &mut im);
+
+        // Hann-windowed sine of amplitude A peaks at A * N / 4
+        let scale = 4.0 / self.fft_size as f32;
+        let half = self.fft_size / 2;
+        Some(
+            (0..half)
+                .map(|i| (re[i] * re[i] + im[i] * im[i]).sqrt() * scale)
+                .collect(),
+        )
+    }
+}
+
+/// Map a linear amplitude to 0.0-1.0 over the `FLOOR_DB..0` dB range
+fn normalize_db(amplitude: f32) -> f32 {
+    if amplitude <= 0.0 {
+        return 0.0;
+    }
+    let db = 20.0 * amplitude.log10();
+    ((db - FLOOR_DB) / -FLOOR_DB).clamp(0.0, 1.0)
+}
+
+/// Iterative radix-2 Cooley-Tukey FFT; length must be a power of two
+fn fft_in_place(re: &mut [f32], im: &mut [f32]) {
+    let n = re.len();
+    debug_assert!(n.is_power_of_two() && im.len() == n);
+
+    // Bit-reversal permutation
+    let mut j = 0;
+    for i in 1..n {
+        let mut bit = n >> 1;
+        while j & bit != 0 {
+            j ^= bit;
+            bit >>= 1;
+        }
+        j |= bit;
+        if i < j {
+            re.swap(i, j);
+            im.swap(i, j);
+        }
+    }
+
+    let mut len = 2;
+    while len <= n {
+        let angle = -2.0 * PI / len as f32;
+        let (w_im, w_re) = angle.sin_cos();
+        for start in (0..n).step_by(len) {
+            let (mut cur_re, mut cur_im) = (1.0f32, 0.0f32);
+            for k in 0..len / 2 {
+                let a = start + k;
+                let b = a + len / 2;
+                let t_re = re[b] * cur_re - im[b] * cur_im;
+                let t_im = re[b] * cur_im + im[b] * cur_re;
+                re[b] = re[a] - t_re;
+                im[b] = im[a] - t_im;
+                re[a] += t_re;
+                im[a] += t_im;
+
+                let next_re = cur_re * w_re - cur_im * w_im;
+                cur_im = cur_re * w_im + cur_im * w_re;
+                cur_re = next_re;
+            }
+        }
+        len <<= 1;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sine(freq: f32, sample_rate: u32, frames: usize) -> Vec<f32> {
+        (0..frames)
+            .flat_map(|i| {
+                let s = (2.0 * PI * freq * i as f32 / sample_rate as f32).sin() * 0.5;
+                [s, s]
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_fft_impulse_is_flat() {
+        let mut re = vec![0.0; 8];
+        let mut im = vec![0.0; 8];
+        re[0] = 1.0;
+        fft_in_place(&mut re, &mut im);
+        for i in 0..8 {
+            assert!((re[i] - 1.0).abs() < 1e-6);
+            assert!(im[i].abs() < 1e-6);
+        }
+    }
+
+    #[test]
+    fn test_fft_size_rounded_to_power_of_two() {
+        let analyzer = SpectrumAnalyzer::new(1000, 44100, 2);
+        assert_eq!(analyzer.fft_size(), 1024);
+    }
+
+    #[test]
+    fn test_not_enough_samples_is_silent() {
+        let mut analyzer = SpectrumAnalyzer::new(DEFAULT_FFT_SIZE, 44100, 2);
+        analyzer.push(&sine(440.0, 44100, 100));
+        assert_eq!(analyzer.bands(DEFAULT_BANDS), vec![0.0; DEFAULT_BANDS]);
+    }
+
+    #[test]
+    fn test_silence_bands_are_zero() {
+        let mut analyzer = SpectrumAnalyzer::new(DEFAULT_FFT_SIZE, 44100, 2);
+        analyzer.push(&vec![0.0; DEFAULT_FFT_SIZE * 2]);
+        assert!(analyzer.bands(64).iter().all(|&b| b == 0.0));
+    }
+
+    #[test]
+    fn test_sine_peaks_in_matching_band() {
+        let mut analyzer = SpectrumAnalyzer::new(DEFAULT_FFT_SIZE, 44100, 2);
+        analyzer.push(&sine(1000.0, 44100, DEFAULT_FFT_SIZE));
+        let bands = analyzer.bands(DEFAULT_BANDS);
+
+        let ratio = MAX_FREQ / MIN_FREQ;
+        let expected = ((1000.0f32 / MIN_FREQ).ln() / ratio.ln() * DEFAULT_BANDS as f32) as usize;
+        let (loudest, level) = bands
+            .iter()
+            .enumerate()
+            .max_by(|a, b| a.1.total_cmp(b.1))
+            .unwrap();
+
+        assert!(loudest.abs_diff(expected) <= 1, "peak in band {} expected {}", loudest, expected);
+        assert!(*level > 0.8);
+        assert!(bands[0] < 0.5);
+    }
+
+    #[test]
+    fn test_energy_follows_frequency() {
+        let mut analyzer = SpectrumAnalyzer::new(DEFAULT_FFT_SIZE, 44100, 2);
+        analyzer.push(&sine(80.0, 44100, DEFAULT_FFT_SIZE));
+        let energy = analyzer.energy();
+        assert!(energy.bass > 0.8, "{:?}", energy);
+        assert!(energy.bass > energy.mid && energy.bass > energy.treble);
+
+        analyzer.push(&sine(8000.0, 44100, DEFAULT_FFT_SIZE));
+        let energy = analyzer.energy();
+        assert!(energy.treble > 0.8, "{:?}", energy);
+        assert!(energy.treble > energy.mid && energy.treble > energy.bass);
+    }
+
+    #[test]
+    fn test_energy_of_silence_is_zero() {
+        let mut analyzer = SpectrumAnalyzer::new(DEFAULT_FFT_SIZE, 44100, 2);
+        assert_eq!(analyzer.energy(), BandEnergy::default());
+        analyzer.push(&vec![0.0; DEFAULT_FFT_SIZE * 2]);
+        assert_eq!(analyzer.energy(), BandEnergy::default());
+    }
+
+    #[test]
+    fn test_set_format_clears_window() {
+        let mut analyzer = SpectrumAnalyzer::new(256, 44100, 2);
+        analyzer.push(&sine(1000.0, 44100, 256));
+        assert!(analyzer.bands(8).iter().any(|&b| b > 0.0));
+
+        analyzer.set_format(48000, 2);
+        assert!(analyzer.bands(8).iter().all(|&b| b == 0.0));
+    }
+}
@@ -0,0 +1,282 @@
+//! Tempo clock
+//!
+//! Single source of tempo for beat-synced features. The clock follows the
+//! beat detector until a tempo is set by hand or tapped in; a manual tempo
+//! then runs a free beat grid in wall-clock time, so it keeps going without
+//! audio, until it is cleared again.
+
+use std::collections::VecDeque;
+use std::time::{Duration, Instant};
+
+use serde::{Deserialize, Serialize};
+
+use super::beat::BeatInfo;
+
+/// Slowest tempo accepted for a manual override
+pub const MIN_MANUAL_BPM: f32 = 30.0;
+/// Fastest tempo accepted for a manual override
+pub const MAX_MANUAL_BPM: f32 = 300.0;
+
+/// Taps further apart than this start a new tap sequence
+const TAP_TIMEOUT: Duration = Duration::from_secs(2);
+/// Most recent taps averaged into the tapped tempo
+const MAX_TAPS: usize = 8;
+
+/// Where the current tempo comes from
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum TempoSource {
+    /// Beat detector on the captured audio
+    #[default]
+    Detected,
+    /// Set explicitly with a BPM value
+    Manual,
+    /// Tapped in
+    Tap,
+}
+
+/// Tempo and beat position along with their source
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub struct TempoInfo {
+    #[serde(flatten)]
+    pub beat: BeatInfo,
+    pub source: TempoSource,
+}
+
+/// Manually set tempo with the grid it runs on
+#[derive(Debug, Clone, Copy)]
+struct ManualTempo {
+    bpm: f32,
+    source: TempoSource,
+    /// Time of a beat on the grid
+    anchor: Instant,
+    /// Beats counted up to `anchor`
+    anchor_beats: u64,
+}
+
+impl ManualTempo {
+    fn period(&self) -> f32 {
+        60.0 / self.bpm
+    }
+
+    /// Beats elapsed (with fraction) at `now`
+    fn position(&self, now: Instant) -> f64 {
+        let elapsed = now.saturating_duration_since(self.anchor).as_secs_f64();
+        self.anchor_beats as f64 + elapsed / self.period() as f64
+    }
+}
+
+/// Central tempo clock: detected tempo unless overridden by hand
+#[derive(Debug, Clone, Default)]
+pub struct TempoClock {
+    manual: Option<ManualTempo>,
+    /// Latest state of the beat detector
+    detected: BeatInfo,
+    taps: VecDeque<Instant>,
+    /// Beat count seen by the last `update`
+    last_beat_count: u64,
+}
+
+impl TempoClock {
+    /// Create a clock following the beat detector
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Override detection with a fixed tempo, clamped to the manual range
+    ///
+    /// Changing an existing override keeps the current beat phase.
+    pub fn set_manual(&mut self, bpm: f32, now: Instant) {
+        let bpm = bpm.clamp(MIN_MANUAL_BPM, MAX_MANUAL_BPM);
+        let (anchor, anchor_beats) = match &self.manual {
+            // Place the anchor so the new grid continues at the same phase
+            Some(manual) => {
+                let position = manual.position(now);
+                let into_beat = Duration::from_secs_f64(position.fract() * 60.0 / bpm as f64);
+                (now.checked_sub(into_beat).unwrap_or(now), position as u64)
+            }
+            None => (now, self.detected.beat_count),
+        };
+        self.manual = Some(ManualTempo {
+            bpm,
+            source: TempoSource::Manual,
+            anchor,
+            anchor_beats,
+        });
+        self.taps.clear();
+    }
+
+    /// Return to the detected tempo
+    pub fn clear_manual(&mut self) {
+        self.manual = None;
+        self.taps.clear();
+    }
+
+    /// Register a tap, returning the tapped tempo once two taps are in
+    ///
+    /// Taps land on the beat, so each one also realigns the grid.
+    pub fn tap(&mut self, now: Instant) -> Option<f32> {
+        if self
+            .taps
+            .back()
+            .is_some_and(|&last| now.saturating_duration_since(last) > TAP_TIMEOUT)
+        {
+            self.taps.clear();
+        }
+        if self.taps.len() == MAX_TAPS {
+            self.taps.pop_front();
+        }
+        self.taps.push_back(now);
+
+        let first = *self.taps.front()?;
+        let intervals = self.taps.len() as u32 - 1;
+        if intervals == 0 {
+            return None;
+        }
+        let period = now.saturating_duration_since(first).as_secs_f32() / intervals as f32;
+        if period <= 0.0 {
+            return None;
+        }
+
+        let beats = self.beat_count(now);
+        let bpm = (60.0 / period).clamp(MIN_MANUAL_BPM, MAX_MANUAL_BPM);
+        self.manual = Some(ManualTempo {
+            bpm,
+            source: TempoSource::Tap,
+            anchor: now,
+            anchor_beats: beats,
+        });
+        Some(bpm)
+    }
+
+    /// Feed the latest detector state; returns true if a beat of the active
+    /// grid elapsed since the previous update
+    pub fn update(&mut self, detected: BeatInfo, now: Instant) -> bool {
+        self.detected = detected;
+        let count = self.beat_count(now);
+        let beat = count != self.last_beat_count;
+        self.last_beat_count = count;
+        beat
+    }
+
+    /// Where the current tempo comes from
+    pub fn source(&self) -> TempoSource {
+        self.manual.map_or(TempoSource::Detected, |m| m.source)
+    }
+
+    /// Current tempo (0.0 if unknown)
+    pub fn bpm(&self) -> f32 {
+        self.manual.map_or(self.detected.bpm, |m| m.bpm)
+    }
+
+    /// Current tempo and beat position
+    pub fn info(&self, now: Instant) -> TempoInfo {
+        let beat = match &self.manual {
+            Some(manual) => {
+                let position = manual.position(now);
+                BeatInfo {
+                    bpm: manual.bpm,
+                    phase: position.fract() as f32,
+                    confidence: 1.0,
+                    beat_count: position as u64,
+                }
+            }
+            None => self.detected,
+        };
+        TempoInfo {
+            beat,
+            source: self.source(),
+        }
+    }
+
+    fn beat_count(&self, now: Instant) -> u64 {
+        self.info(now).beat.beat_count
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn detected(bpm: f32, beat_count: u64) -> BeatInfo {
+        BeatInfo {
+            bpm,
+            phase: 0.0,
+            confidence: 0.8,
+            beat_count,
+        }
+    }
+
+    #[test]
+    fn test_follows_detector_by_default() {
+        let now = Instant::now();
+        let mut clock = TempoClock::new();
+        assert!(!clock.update(detected(128.0, 0), now));
+        assert!(clock.update(detected(128.0, 1), now));
+
+        let info = clock.info(now);
+        assert_eq!(info.source, TempoSource::Detected);
+        assert_eq!(info.beat.bpm, 128.0);
+        assert_eq!(info.beat.beat_count, 1);
+    }
+
+    #[test]
+    fn test_manual_tempo_runs_its_own_grid() {
+        let start = Instant::now();
+        let mut clock = TempoClock::new();
+        clock.update(detected(90.0, 10), start);
+        clock.set_manual(120.0, start);
+
+        // Two beats per second at 120 BPM, detector updates are ignored
+        let later = start + Duration::from_millis(1250);
+        assert!(clock.update(detected(90.0, 11), later));
+        let info = clock.info(later);
+        assert_eq!(info.source, TempoSource::Manual);
+        assert_eq!(info.beat.bpm, 120.0);
+        assert_eq!(info.beat.beat_count, 12);
+        assert!((info.beat.phase - 0.5).abs() < 1e-3);
+
+        clock.clear_manual();
+        assert_eq!(clock.info(later).beat.bpm, 90.0);
+    }
+
+    #[test]
+    fn test_tap_tempo_averages_taps() {
+        let start = Instant::now();
+        let mut clock = TempoClock::new();
+        assert_eq!(clock.tap(start), None);
+        for i in 1..=4u64 {
+            clock.tap(start + Duration::from_millis(500 * i));
+        }
+
+        let last_tap = start + Duration::from_millis(2000);
+        let info = clock.info(last_tap);
+        assert_eq!(info.source, TempoSource::Tap);
+        assert!((info.beat.bpm - 120.0).abs() < 0.5, "bpm {}", info.beat.bpm);
+        assert_eq!(info.beat.phase, 0.0);
+    }
+
+    #[test]
+    fn test_slow_taps_start_new_sequence() {
+        let start = Instant::now();
+        let mut clock = TempoClock::new();
+        clock.tap(start);
+        clock.tap(start + Duration::from_millis(400));
+        assert_eq!(clock.tap(start + Duration::from_secs(5)), None);
+        let bpm = clock.tap(start + Duration::from_millis(5600)).unwrap();
+        assert!((bpm - 100.0).abs() < 0.5, "bpm {}", bpm);
+    }
+
+    #[test]
+    fn test_changing_manual_tempo_keeps_phase() {
+        let start = Instant::now();
+        let mut clock = TempoClock::new();
+        clock.set_manual(120.0, start);
+        let now = start + Duration::from_millis(2250);
+        clock.set_manual(60.0, now);
+
+        let info = clock.info(now);
+        assert_eq!(info.beat.beat_count, 4);
+        assert!((info.beat.phase - 0.5).abs() < 1e-3);
+    }
+}
@@ -0,0 +1,162 @@
+//! Waveform history
+//!
+//! Keeps the last few seconds of the captured stream as min/max pairs at a
+//! fixed number of points per second, which is all a scrolling waveform
+//! display needs.
+
+use std::collections::VecDeque;
+
+use serde::{Deserialize, Serialize};
+
+/// Default length of the history
+pub const DEFAULT_WAVEFORM_SECS: u32 = 10;
+/// Default resolution of the history
+pub const DEFAULT_WAVEFORM_POINTS_PER_SEC: u32 = 200;
+
+/// Signal range covered by one point (mono mix of all channels)
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct WaveformPoint {
+    pub min: f32,
+    pub max: f32,
+}
+
+/// A window of the waveform history, oldest point first
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Waveform {
+    pub points_per_sec: u32,
+    pub points: Vec<WaveformPoint>,
+}
+
+/// Rolling min/max history of interleaved audio
+pub struct WaveformHistory {
+    points_per_sec: u32,
+    capacity: usize,
+    sample_rate: u32,
+    channels: u16,
+    history: VecDeque<WaveformPoint>,
+    /// Point being accumulated and the frames it covers so far
+    current: Option<WaveformPoint>,
+    current_frames: usize,
+}
+
+impl WaveformHistory {
+    /// Create a history of `secs` seconds at `points_per_sec` resolution
+    pub fn new(secs: u32, points_per_sec: u32, sample_rate: u32, channels: u16) -> Self {
+        let points_per_sec = points_per_sec.max(1);
+        let capacity = (secs.max(1) * points_per_sec) as usize;
+        Self {
+            points_per_sec,
+            capacity,
+            sample_rate: sample_rate.max(1),
+            channels: channels.max(1),
+            history: VecDeque::with_capacity(capacity),
+            current: None,
+            current_frames: 0,
+        }
+    }
+
+    /// Points stored per second of audio
+    pub fn points_per_sec(&self) -> u32 {
+        self.points_per_sec
+    }
+
+    /// Reconfigure the input format, clearing the history if it changed
+    pub fn set_format(&mut self, sample_rate: u32, channels: u16) {
+        if sample_rate != self.sample_rate || channels != self.channels {
+            self.sample_rate = sample_rate.max(1);
+            self.channels = channels.max(1);
+            self.clear();
+        }
+    }
+
+    /// Drop all history
+    pub fn clear(&mut self) {
+        self.history.clear();
+        self.current = None;
+        self.current_frames = 0;
+    }
+
+    /// Append interleaved samples
+    pub fn push(&mut self, samples: &[f32]) {
+        let frames_per_point = (self.sample_rate / self.points_per_sec).max(1) as usize;
+
+        for frame in samples.chunks(self.channels as usize) {
+            let value = frame.iter().sum::<f32>() / frame.len() as f32;
+            let point = self.current.get_or_insert(WaveformPoint {
+                min: value,
+                max: value,
+            });
+            point.min = point.min.min(value);
+            point.max = point.max.max(value);
+            self.current_frames += 1;
+
+            if self.current_frames >= frames_per_point {
+                if self.history.len() == self.capacity {
+                    self.history.pop_front();
+                }
+                self.history.extend(self.current.take());
+                self.current_frames = 0;
+            }
+        }
+    }
+
+    /// The most recent `window_ms` of history (less if not yet recorded)
+    pub fn window(&self, window_ms: u32) -> Waveform {
+        let count = (window_ms as u64 * self.points_per_sec as u64).div_ceil(1000) as usize;
+        let skip = self.history.len().saturating_sub(count);
+        Waveform {
+            points_per_sec: self.points_per_sec,
+            points: self.history.iter().skip(skip).copied().collect(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_points_track_min_and_max() {
+        let mut history = WaveformHistory::new(1, 100, 1000, 2);
+        // 10 frames per point: a ramp from -0.5 to 0.4
+        let samples: Vec<f32> = (0..10)
+            .flat_map(|i| {
+                let s = i as f32 / 10.0 - 0.5;
+                [s, s]
+            })
+            .collect();
+        history.push(&samples);
+
+        let waveform = history.window(1000);
+        assert_eq!(waveform.points.len(), 1);
+        assert!((waveform.points[0].min + 0.5).abs() < 1e-6);
+        assert!((waveform.points[0].max - 0.4).abs() < 1e-6);
+    }
+
+    #[test]
+    fn test_history_is_bounded() {
+        let mut history = WaveformHistory::new(2, 50, 1000, 1);
+        history.push(&vec![0.1; 5000]);
+        assert_eq!(history.window(10_000).points.len(), 100);
+    }
+
+    #[test]
+    fn test_window_returns_latest_points() {
+        let mut history = WaveformHistory::new(10, 100, 1000, 1);
+        history.push(&vec![0.2; 1000]);
+        history.push(&vec![0.8; 100]);
+
+        let waveform = history.window(100);
+        assert_eq!(waveform.points_per_sec, 100);
+        assert_eq!(waveform.points.len(), 10);
+        assert!(waveform.points.iter().all(|p| p.max == 0.8));
+    }
+
+    #[test]
+    fn test_set_format_clears_history() {
+        let mut history = WaveformHistory::new(1, 100, 1000, 2);
+        history.push(&[0.5; 200]);
+        history.set_format(48000, 2);
+        assert!(history.window(1000).points.is_empty());
+    }
+}
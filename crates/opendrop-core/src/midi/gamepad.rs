@@ -0,0 +1,177 @@
+//! Game controller input
+//!
+//! Buttons and analog sticks of gamepads (through gilrs) feed the same
+//! mappings and learn mode as MIDI. Buttons send 1.0 when pressed and 0.0
+//! when released; axes are scaled from -1.0..1.0 to 0.0..1.0, so a centred
+//! stick sits at 0.5. Controls are named after gilrs' standard layout
+//! ("South", "LeftStickX") and match on every connected gamepad.
+
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{mpsc, Arc, Mutex};
+use std::thread::{self, JoinHandle};
+use std::time::Duration;
+
+use gilrs::{Axis, Button, EventType, Gilrs};
+
+use super::{InputRouter, MidiError, MidiMessage, MidiParser};
+
+/// How often the input thread checks whether it should stop
+const POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+/// Message for a button, named by its standard name or, for buttons
+/// outside the standard layout, its raw code
+pub fn button_message(button: Button, code: impl std::fmt::Display, pressed: bool) -> MidiMessage {
+    let button = match button {
+        Button::Unknown => format!("Button {}", code),
+        button => format!("{:?}", button),
+    };
+    MidiMessage::GamepadButton {
+        button,
+        value: if pressed { 1.0 } else { 0.0 },
+    }
+}
+
+/// Message for an axis at `value` (-1.0..1.0), named like buttons
+pub fn axis_message(axis: Axis, code: impl std::fmt::Display, value: f32) -> MidiMessage {
+    let axis = match axis {
+        Axis::Unknown => format!("Axis {}", code),
+        axis => format!("{:?}", axis),
+    };
+    MidiMessage::GamepadAxis {
+        axis,
+        value: ((value + 1.0) / 2.0).clamp(0.0, 1.0),
+    }
+}
+
+/// Message for a gamepad event, `None` for events that aren't input
+fn event_message(event: &EventType) -> Option<MidiMessage> {
+    match *event {
+        EventType::ButtonPressed(button, code) => Some(button_message(button, code, true)),
+        EventType::ButtonReleased(button, code) => Some(button_message(button, code, false)),
+        EventType::AxisChanged(axis, value, code) => Some(axis_message(axis, code, value)),
+        _ => None,
+    }
+}
+
+/// Gamepad input feeding a controller's mappings
+pub struct GamepadInput {
+    /// Names of the connected gamepads
+    gamepads: Arc<Mutex<Vec<String>>>,
+    running: Arc<AtomicBool>,
+    thread: Option<JoinHandle<()>>,
+}
+
+impl GamepadInput {
+    /// Start reading all connected gamepads
+    pub(crate) fn start(mut router: InputRouter) -> Result<Self, MidiError> {
+        let gamepads = Arc::new(Mutex::new(Vec::new()));
+        let running = Arc::new(AtomicBool::new(true));
+        let (ready_tx, ready_rx) = mpsc::channel();
+
+        // gilrs has to stay on the thread that created it
+        let connected = Arc::clone(&gamepads);
+        let keep_running = Arc::clone(&running);
+        let thread = thread::spawn(move || {
+            let mut gilrs = match Gilrs::new() {
+                Ok(gilrs) => gilrs,
+                Err(e) => {
+                    let _ = ready_tx.send(Err(e.to_string()));
+                    return;
+                }
+            };
+            let names = |gilrs: &Gilrs| {
+                gilrs
+                    .gamepads()
+                    .map(|(_, pad)| pad.name().to_string())
+                    .collect::<Vec<_>>()
+            };
+            *connected.lock().unwrap() = names(&gilrs);
+            let _ = ready_tx.send(Ok(()));
+
+            let parser = MidiParser::new();
+            while keep_running.load(Ordering::Relaxed) {
+                let Some(event) = gilrs.next_event_blocking(Some(POLL_INTERVAL)) else {
+                    continue;
+                };
+                match event.event {
+                    EventType::Connected | EventType::Disconnected => {
+                        *connected.lock().unwrap() = names(&gilrs);
+                    }
+                    ref other => {
+                        if let Some(message) = event_message(other) {
+                            router.route(0, &message, &parser);
+                        }
+                    }
+                }
+            }
+        });
+
+        match ready_rx.recv() {
+            Ok(Ok(())) => {}
+            Ok(Err(e)) => return Err(MidiError::InitError(format!("Gamepads: {}", e))),
+            Err(_) => return Err(MidiError::InitError("Gamepad input stopped".to_string())),
+        }
+
+        tracing::info!(
+            "Gamepad input started ({} connected)",
+            gamepads.lock().unwrap().len()
+        );
+        Ok(Self {
+            gamepads,
+            running,
+            thread: Some(thread),
+        })
+    }
+
+    /// Names of the connected gamepads
+    pub fn gamepads(&self) -> Vec<String> {
+        self.gamepads.lock().unwrap().clone()
+    }
+}
+
+impl Drop for GamepadInput {
+    fn drop(&mut self) {
+        self.running.store(false, Ordering::Relaxed);
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_button_messages() {
+        assert_eq!(
+            button_message(Button::South, 304, true),
+            MidiMessage::GamepadButton {
+                button: "South".to_string(),
+                value: 1.0,
+            }
+        );
+        assert_eq!(
+            button_message(Button::Unknown, 300, false),
+            MidiMessage::GamepadButton {
+                button: "Button 300".to_string(),
+                value: 0.0,
+            }
+        );
+        assert_eq!(event_message(&EventType::Connected), None);
+    }
+
+    #[test]
+    fn test_axis_is_scaled_to_unit_range() {
+        let value = |v| match axis_message(Axis::LeftStickX, 0, v) {
+            MidiMessage::GamepadAxis { axis, value } => {
+                assert_eq!(axis, "LeftStickX");
+                value
+            }
+            other => panic!("unexpected {:?}", other),
+        };
+        assert_eq!(value(-1.0), 0.0);
+        assert_eq!(value(0.0), 0.5);
+        assert_eq!(value(1.0), 1.0);
+    }
+}
@@ -2,9 +2,30 @@
 //!
 //! Defines the mapping between MIDI messages and OpenDrop actions.
 
+use std::time::{Duration, Instant};
+
 use serde::{Deserialize, Serialize};
 use uuid::Uuid;
 
+use crate::video::{ColorControl, Effect};
+
+/// Offset from the MSB controller of a 14-bit CC pair to its LSB (CC 0-31 pair with CC 32-63)
+pub const CC14_LSB_OFFSET: u8 = 32;
+/// Data Entry MSB/LSB controllers carrying NRPN values
+const CC_DATA_ENTRY_MSB: u8 = 6;
+const CC_DATA_ENTRY_LSB: u8 = 38;
+/// NRPN and RPN parameter number controllers
+const CC_NRPN_LSB: u8 = 98;
+const CC_NRPN_MSB: u8 = 99;
+const CC_RPN_LSB: u8 = 100;
+const CC_RPN_MSB: u8 = 101;
+/// Largest 14-bit value
+const MAX_14BIT: f32 = 16383.0;
+/// How close a control must come to the software value to pick it up
+const PICKUP_TOLERANCE: f32 = 0.02;
+/// Share of the output range moved by one relative encoder tick
+const RELATIVE_STEP: f32 = 1.0 / 127.0;
+
 /// A MIDI mapping connects a MIDI message to an OpenDrop action
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct MidiMapping {
@@ -22,6 +43,26 @@ pub struct MidiMapping {
     /// Whether this mapping is enabled
     #[serde(default = "default_true")]
     pub enabled: bool,
+    /// Soft takeover: a continuous control only takes effect once it
+    /// crosses the current software value
+    #[serde(default)]
+    pub pickup: bool,
+    /// Minimum time between two presses of a trigger action (0 = no limit)
+    #[serde(default)]
+    pub debounce_ms: u32,
+    /// Further actions run together with `action` on a press (macro)
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub macro_steps: Vec<MacroStep>,
+}
+
+/// One further action of a macro mapping
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct MacroStep {
+    pub action: MidiAction,
+    /// Fixed value for the action (e.g. crossfader to 1.0), or the
+    /// triggering message's value when `None`
+    #[serde(default)]
+    pub value: Option<f32>,
 }
 
 fn default_true() -> bool {
@@ -38,9 +79,42 @@ impl MidiMapping {
             action,
             value_transform: None,
             enabled: true,
+            pickup: false,
+            debounce_ms: 0,
+            macro_steps: Vec::new(),
         }
     }
 
+    /// Action to run for `message`: a Program Change selects the bank of a
+    /// bank action by its program number
+    pub fn action_for(&self, message: &MidiMessage) -> MidiAction {
+        match (self.action, message) {
+            (MidiAction::LoadPlaylistBank { deck, .. }, MidiMessage::ProgramChange { program }) => {
+                MidiAction::LoadPlaylistBank {
+                    deck,
+                    bank: *program,
+                }
+            }
+            (action, _) => action,
+        }
+    }
+
+    /// Whether the mapping runs several actions
+    pub fn is_macro(&self) -> bool {
+        !self.macro_steps.is_empty()
+    }
+
+    /// Actions to run, in order, for a message of `value`
+    pub fn actions(&self, value: f32) -> Vec<(MidiAction, f32)> {
+        std::iter::once((self.action, value))
+            .chain(
+                self.macro_steps
+                    .iter()
+                    .map(|step| (step.action, step.value.unwrap_or(value))),
+            )
+            .collect()
+    }
+
     /// Check if this mapping matches a MIDI message
     pub fn matches(&self, channel: u8, message: &MidiMessage) -> bool {
         if !self.enabled {
@@ -49,6 +123,44 @@ impl MidiMapping {
         self.midi_message.matches(channel, message)
     }
 
+    /// Value of a matching message (0.0-1.0, transformed), or `None` while a
+    /// 14-bit control waits for the fine half of its value
+    pub fn value(&self, channel: u8, message: &MidiMessage, parser: &MidiParser) -> Option<f32> {
+        let value = match (self.midi_message.base(), message) {
+            (
+                MidiMessageType::ControlChange14 { msb, lsb, .. },
+                MidiMessage::ControlChange { controller, .. },
+            ) => {
+                // The coarse half alone would step; the fine half that
+                // follows it carries the full value
+                if controller == msb && parser.has_seen(channel, *lsb) {
+                    return None;
+                }
+                parser.cc14(channel, *msb, *lsb) as f32 / MAX_14BIT
+            }
+            (MidiMessageType::SysEx { pattern }, MidiMessage::SysEx { data }) => {
+                // A byte following the pattern carries the control's state
+                data.get(pattern.len()).map_or(1.0, |&v| (v & 0x7F) as f32 / 127.0)
+            }
+            _ => message.value(),
+        };
+        Some(self.transform_value(value))
+    }
+
+    /// Encoder ticks of a relative control change (negative turns down), or
+    /// `None` when the mapping takes absolute values
+    pub fn relative_delta(&self, message: &MidiMessage) -> Option<i32> {
+        let transform = self.value_transform.as_ref()?;
+        let encoding = transform.relative?;
+        match (self.midi_message.base(), message) {
+            (MidiMessageType::ControlChange { .. }, MidiMessage::ControlChange { value, .. }) => {
+                let delta = encoding.delta(*value);
+                Some(if transform.invert { -delta } else { delta })
+            }
+            _ => None,
+        }
+    }
+
     /// Apply value transformation if configured
     pub fn transform_value(&self, value: f32) -> f32 {
         match &self.value_transform {
@@ -59,7 +171,7 @@ impl MidiMapping {
 }
 
 /// Types of MIDI messages that can be mapped
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub enum MidiMessageType {
     /// Note On event
     NoteOn {
@@ -76,6 +188,17 @@ pub enum MidiMessageType {
         channel: u8,
         controller: u8,
     },
+    /// High-resolution control sent as a coarse (MSB) and fine (LSB) CC pair
+    ControlChange14 {
+        channel: u8,
+        msb: u8,
+        lsb: u8,
+    },
+    /// Non-Registered Parameter Number with a 14-bit value
+    Nrpn {
+        channel: u8,
+        parameter: u16,
+    },
     /// Pitch Bend event
     PitchBend {
         channel: u8,
@@ -84,16 +207,128 @@ pub enum MidiMessageType {
     ProgramChange {
         channel: u8,
     },
+    /// System Exclusive message whose body (without F0/F7) starts with `pattern`
+    SysEx {
+        pattern: Vec<u8>,
+    },
+    /// OSC message sent to `address` (e.g. "/1/fader1")
+    Osc {
+        address: String,
+    },
+    /// Gamepad button (e.g. "South"), on any connected gamepad
+    GamepadButton {
+        button: String,
+    },
+    /// Gamepad axis (e.g. "LeftStickX"), on any connected gamepad
+    GamepadAxis {
+        axis: String,
+    },
     /// Any MIDI message on a specific channel (for learn mode)
     AnyOnChannel {
         channel: u8,
     },
+    /// The wrapped message on whatever channel it arrives (omni), for
+    /// controllers that switch channels per layer
+    AnyChannel(Box<MidiMessageType>),
+}
+
+/// Human-readable form, with channels numbered from 1 as on hardware
+impl std::fmt::Display for MidiMessageType {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            MidiMessageType::NoteOn { channel, note } => {
+                write!(f, "Note {} on channel {}", note, channel + 1)
+            }
+            MidiMessageType::NoteOff { channel, note } => {
+                write!(f, "Note Off {} on channel {}", note, channel + 1)
+            }
+            MidiMessageType::ControlChange { channel, controller } => {
+                write!(f, "CC {} on channel {}", controller, channel + 1)
+            }
+            MidiMessageType::ControlChange14 { channel, msb, lsb } => {
+                write!(f, "14-bit CC {}/{} on channel {}", msb, lsb, channel + 1)
+            }
+            MidiMessageType::Nrpn { channel, parameter } => {
+                write!(f, "NRPN {} on channel {}", parameter, channel + 1)
+            }
+            MidiMessageType::PitchBend { channel } => {
+                write!(f, "Pitch Bend on channel {}", channel + 1)
+            }
+            MidiMessageType::ProgramChange { channel } => {
+                write!(f, "Program Change on channel {}", channel + 1)
+            }
+            MidiMessageType::SysEx { pattern } => {
+                write!(f, "SysEx")?;
+                for byte in pattern {
+                    write!(f, " {:02X}", byte)?;
+                }
+                Ok(())
+            }
+            MidiMessageType::Osc { address } => write!(f, "OSC {}", address),
+            MidiMessageType::GamepadButton { button } => write!(f, "Gamepad {}", button),
+            MidiMessageType::GamepadAxis { axis } => write!(f, "Gamepad {} axis", axis),
+            MidiMessageType::AnyOnChannel { channel } => {
+                write!(f, "Any message on channel {}", channel + 1)
+            }
+            MidiMessageType::AnyChannel(inner) => {
+                let text = inner.to_string();
+                match text.split_once(" on channel") {
+                    Some((message, _)) => write!(f, "{} on any channel", message),
+                    None => write!(f, "{}", text),
+                }
+            }
+        }
+    }
 }
 
 impl MidiMessageType {
+    /// Channel this type listens on (`None` for SysEx, OSC, gamepad and omni
+    /// types)
+    pub fn channel(&self) -> Option<u8> {
+        match self {
+            MidiMessageType::NoteOn { channel, .. }
+            | MidiMessageType::NoteOff { channel, .. }
+            | MidiMessageType::ControlChange { channel, .. }
+            | MidiMessageType::ControlChange14 { channel, .. }
+            | MidiMessageType::Nrpn { channel, .. }
+            | MidiMessageType::PitchBend { channel }
+            | MidiMessageType::ProgramChange { channel }
+            | MidiMessageType::AnyOnChannel { channel } => Some(*channel),
+            MidiMessageType::SysEx { .. }
+            | MidiMessageType::Osc { .. }
+            | MidiMessageType::GamepadButton { .. }
+            | MidiMessageType::GamepadAxis { .. }
+            | MidiMessageType::AnyChannel(_) => None,
+        }
+    }
+
+    /// The message an omni type wraps, or the type itself
+    pub fn base(&self) -> &MidiMessageType {
+        match self {
+            MidiMessageType::AnyChannel(inner) => inner.base(),
+            other => other,
+        }
+    }
+
+    /// Respond on every channel (`true`) or only the base type's own
+    pub fn with_any_channel(self, any: bool) -> MidiMessageType {
+        let base = match self {
+            MidiMessageType::AnyChannel(inner) => *inner,
+            other => other,
+        };
+        if any && base.channel().is_some() {
+            MidiMessageType::AnyChannel(Box::new(base))
+        } else {
+            base
+        }
+    }
+
     /// Check if this type matches an incoming MIDI message
     pub fn matches(&self, channel: u8, message: &MidiMessage) -> bool {
         match (self, message) {
+            (MidiMessageType::AnyChannel(inner), _) => {
+                inner.matches(inner.channel().unwrap_or(channel), message)
+            }
             (MidiMessageType::NoteOn { channel: c, note: n }, MidiMessage::NoteOn { note, .. }) => {
                 *c == channel && *n == *note
             }
@@ -104,12 +339,38 @@ impl MidiMessageType {
                 MidiMessageType::ControlChange { channel: c, controller: ctrl },
                 MidiMessage::ControlChange { controller, .. },
             ) => *c == channel && *ctrl == *controller,
+            (
+                MidiMessageType::ControlChange14 { channel: c, msb, lsb },
+                MidiMessage::ControlChange { controller, .. },
+            ) => *c == channel && (*msb == *controller || *lsb == *controller),
+            (MidiMessageType::Nrpn { channel: c, parameter: p }, MidiMessage::Nrpn { parameter, .. }) => {
+                *c == channel && *p == *parameter
+            }
             (MidiMessageType::PitchBend { channel: c }, MidiMessage::PitchBend { .. }) => {
                 *c == channel
             }
             (MidiMessageType::ProgramChange { channel: c }, MidiMessage::ProgramChange { .. }) => {
                 *c == channel
             }
+            (MidiMessageType::SysEx { pattern }, MidiMessage::SysEx { data }) => {
+                data.starts_with(pattern)
+            }
+            (MidiMessageType::Osc { address: a }, MidiMessage::Osc { address, .. }) => a == address,
+            (
+                MidiMessageType::GamepadButton { button: b },
+                MidiMessage::GamepadButton { button, .. },
+            ) => b == button,
+            (MidiMessageType::GamepadAxis { axis: a }, MidiMessage::GamepadAxis { axis, .. }) => {
+                a == axis
+            }
+            // SysEx, OSC and gamepads have no channel
+            (
+                MidiMessageType::AnyOnChannel { .. },
+                MidiMessage::SysEx { .. }
+                | MidiMessage::Osc { .. }
+                | MidiMessage::GamepadButton { .. }
+                | MidiMessage::GamepadAxis { .. },
+            ) => false,
             (MidiMessageType::AnyOnChannel { channel: c }, _) => *c == channel,
             _ => false,
         }
@@ -117,18 +378,30 @@ impl MidiMessageType {
 }
 
 /// Parsed MIDI message
-#[derive(Debug, Clone, Copy, PartialEq)]
+#[derive(Debug, Clone, PartialEq)]
 pub enum MidiMessage {
     NoteOn { note: u8, velocity: u8 },
     NoteOff { note: u8, velocity: u8 },
     ControlChange { controller: u8, value: u8 },
+    Nrpn { parameter: u16, value: u16 },
     PitchBend { value: u16 },
     ProgramChange { program: u8 },
+    /// System Exclusive body, without the F0/F7 framing
+    SysEx { data: Vec<u8> },
+    /// OSC message, its arguments read as one value (0.0-1.0)
+    Osc { address: String, value: f32 },
+    /// Gamepad button, 1.0 while pressed
+    GamepadButton { button: String, value: f32 },
+    /// Gamepad axis (0.0-1.0, centred at 0.5)
+    GamepadAxis { axis: String, value: f32 },
     Unknown,
 }
 
 impl MidiMessage {
     /// Parse raw MIDI bytes into a MidiMessage
+    ///
+    /// Each message is parsed on its own; use a `MidiParser` to decode NRPN
+    /// and 14-bit values spread over several CC messages.
     pub fn parse(data: &[u8]) -> (u8, Self) {
         if data.is_empty() {
             return (0, MidiMessage::Unknown);
@@ -138,6 +411,18 @@ impl MidiMessage {
         let channel = status & 0x0F;
         let message_type = status & 0xF0;
 
+        if status == 0xF0 {
+            // SysEx has no channel; the body runs to the End of Exclusive byte
+            let body = &data[1..];
+            let end = body.iter().position(|&b| b == 0xF7).unwrap_or(body.len());
+            return (
+                0,
+                MidiMessage::SysEx {
+                    data: body[..end].to_vec(),
+                },
+            );
+        }
+
         let message = match message_type {
             0x90 if data.len() >= 3 => {
                 if data[2] == 0 {
@@ -178,13 +463,182 @@ impl MidiMessage {
             MidiMessage::NoteOn { velocity, .. } => *velocity as f32 / 127.0,
             MidiMessage::NoteOff { .. } => 0.0,
             MidiMessage::ControlChange { value, .. } => *value as f32 / 127.0,
+            MidiMessage::Nrpn { value, .. } => *value as f32 / MAX_14BIT,
             MidiMessage::PitchBend { value } => *value as f32 / 16383.0,
             MidiMessage::ProgramChange { .. } => 1.0,
+            MidiMessage::SysEx { .. } => 1.0,
+            MidiMessage::Osc { value, .. }
+            | MidiMessage::GamepadButton { value, .. }
+            | MidiMessage::GamepadAxis { value, .. } => *value,
             MidiMessage::Unknown => 0.0,
         }
     }
 }
 
+/// Soft takeover state of one mapping
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Pickup {
+    /// Previous value of the physical control
+    last_hardware: Option<f32>,
+    /// Value last applied while in control
+    applied: Option<f32>,
+}
+
+impl Pickup {
+    /// Whether the control at `hardware` may set a parameter now at
+    /// `software`; control is picked up once the two meet or cross
+    pub fn update(&mut self, hardware: f32, software: f32) -> bool {
+        // Changed from elsewhere (UI, another layer): wait for the control again
+        if self
+            .applied
+            .is_some_and(|applied| (applied - software).abs() > PICKUP_TOLERANCE)
+        {
+            self.applied = None;
+        }
+
+        let crossed = self.last_hardware.is_some_and(|last| {
+            (last - software).signum() != (hardware - software).signum()
+        });
+        let engaged = self.applied.is_some()
+            || crossed
+            || (hardware - software).abs() <= PICKUP_TOLERANCE;
+
+        self.last_hardware = Some(hardware);
+        if engaged {
+            self.applied = Some(hardware);
+        }
+        engaged
+    }
+}
+
+/// Repeat limit of one trigger mapping
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Throttle {
+    /// When the trigger last fired
+    last_fired: Option<Instant>,
+}
+
+impl Throttle {
+    /// Whether a press at `now` may fire, at most once per `interval`
+    pub fn allow(&mut self, now: Instant, interval: Duration) -> bool {
+        if self
+            .last_fired
+            .is_some_and(|last| now.saturating_duration_since(last) < interval)
+        {
+            return false;
+        }
+        self.last_fired = Some(now);
+        true
+    }
+}
+
+/// Controller state of one MIDI channel
+#[derive(Debug, Clone, Copy)]
+struct ChannelState {
+    /// Latest value of every controller
+    cc: [u8; 128],
+    /// Controllers received at least once
+    seen: [bool; 128],
+    /// Selected NRPN parameter (MSB, LSB), `None` while an RPN or nothing is selected
+    nrpn: Option<(u8, u8)>,
+    /// Whether the selected NRPN sends fine (Data Entry LSB) values
+    nrpn_fine: bool,
+}
+
+impl Default for ChannelState {
+    fn default() -> Self {
+        Self {
+            cc: [0; 128],
+            seen: [false; 128],
+            nrpn: None,
+            nrpn_fine: false,
+        }
+    }
+}
+
+/// Stateful MIDI parser that decodes NRPN and tracks controller values for
+/// 14-bit CC pairs
+#[derive(Debug, Clone)]
+pub struct MidiParser {
+    channels: [ChannelState; 16],
+}
+
+impl MidiParser {
+    pub fn new() -> Self {
+        Self {
+            channels: [ChannelState::default(); 16],
+        }
+    }
+
+    /// Parse raw MIDI bytes, turning Data Entry for a selected NRPN into
+    /// `MidiMessage::Nrpn`
+    ///
+    /// Parameter number CCs are consumed and returned as `Unknown`.
+    pub fn parse(&mut self, data: &[u8]) -> (u8, MidiMessage) {
+        let (channel, message) = MidiMessage::parse(data);
+        let MidiMessage::ControlChange { controller, value } = message else {
+            return (channel, message);
+        };
+        let controller = controller & 0x7F;
+        let state = &mut self.channels[channel as usize];
+        state.cc[controller as usize] = value;
+        state.seen[controller as usize] = true;
+
+        let message = match (controller, state.nrpn) {
+            (CC_NRPN_MSB, nrpn) => {
+                state.nrpn = Some((value, nrpn.map_or(0, |(_, lsb)| lsb)));
+                state.nrpn_fine = false;
+                MidiMessage::Unknown
+            }
+            (CC_NRPN_LSB, nrpn) => {
+                state.nrpn = Some((nrpn.map_or(0, |(msb, _)| msb), value));
+                state.nrpn_fine = false;
+                MidiMessage::Unknown
+            }
+            (CC_RPN_MSB | CC_RPN_LSB, _) => {
+                state.nrpn = None;
+                MidiMessage::Unknown
+            }
+            (CC_DATA_ENTRY_MSB, Some(_)) if state.nrpn_fine => {
+                // Wait for the fine half instead of stepping
+                MidiMessage::Unknown
+            }
+            (CC_DATA_ENTRY_MSB | CC_DATA_ENTRY_LSB, Some((msb, lsb))) => {
+                state.nrpn_fine |= controller == CC_DATA_ENTRY_LSB;
+                let data_lsb = if state.nrpn_fine {
+                    state.cc[CC_DATA_ENTRY_LSB as usize]
+                } else {
+                    0
+                };
+                MidiMessage::Nrpn {
+                    parameter: ((msb as u16) << 7) | lsb as u16,
+                    value: ((state.cc[CC_DATA_ENTRY_MSB as usize] as u16) << 7)
+                        | data_lsb as u16,
+                }
+            }
+            _ => message,
+        };
+        (channel, message)
+    }
+
+    /// Whether `controller` has been received on `channel`
+    pub fn has_seen(&self, channel: u8, controller: u8) -> bool {
+        self.channels[(channel & 0x0F) as usize].seen[(controller & 0x7F) as usize]
+    }
+
+    /// Combined 14-bit value of a CC pair
+    pub fn cc14(&self, channel: u8, msb: u8, lsb: u8) -> u16 {
+        let state = &self.channels[(channel & 0x0F) as usize];
+        ((state.cc[(msb & 0x7F) as usize] as u16) << 7) | state.cc[(lsb & 0x7F) as usize] as u16
+    }
+}
+
+impl Default for MidiParser {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 /// Actions that can be triggered by MIDI
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
 pub enum MidiAction {
@@ -194,12 +648,30 @@ pub enum MidiAction {
     DeckToggle(u8),
     DeckVolume(u8),
     DeckBeatSensitivity(u8),
+    /// Intensity of a post-processing effect on the deck
+    DeckEffect { deck: u8, effect: Effect },
+    /// Brightness, contrast or saturation of the deck; the middle of the
+    /// control leaves the picture as rendered
+    DeckColor { deck: u8, control: ColorControl },
+    /// Cut the deck to black (again to bring it back)
+    DeckBlackout(u8),
+    /// Flash the deck on the beat while held
+    DeckStrobe(u8),
+    /// Hold the deck on its last frame (again to play on)
+    DeckFreeze(u8),
+    /// Playback speed of the deck; the middle of the control is normal speed
+    DeckTimeScale(u8),
 
     // Preset controls
     NextPreset(u8),
     PreviousPreset(u8),
     RandomPreset(u8),
     LoadPresetByIndex { deck: u8, index: usize },
+    /// Jump to a position in the deck's playlist (pad banks)
+    LoadPlaylistIndex { deck: u8, index: usize },
+    /// Load a saved playlist (bank) onto the deck; mapped to Program Change,
+    /// the program number picks the bank
+    LoadPlaylistBank { deck: u8, bank: u8 },
 
     // Playlist controls
     PlaylistNext(u8),
@@ -211,6 +683,15 @@ pub enum MidiAction {
     CrossfaderPosition,
     CrossfaderCurve,
     CrossfaderToggle,
+    /// Put a deck on a side of the fader (again to take it off)
+    CrossfaderAssign { deck: u8, side: CrossfaderSide },
+    /// Slam the fader to one side while held
+    CrossfaderCut(CrossfaderSide),
+
+    // Compositor
+    CompositorDeckOpacity(u8),
+    CompositorBlendModeNext(u8),
+    CompositorDeckToggle(u8),
 
     // Global
     MasterVolume,
@@ -229,6 +710,10 @@ impl MidiAction {
             | MidiAction::DeckToggle(d)
             | MidiAction::DeckVolume(d)
             | MidiAction::DeckBeatSensitivity(d)
+            | MidiAction::DeckBlackout(d)
+            | MidiAction::DeckStrobe(d)
+            | MidiAction::DeckFreeze(d)
+            | MidiAction::DeckTimeScale(d)
             | MidiAction::NextPreset(d)
             | MidiAction::PreviousPreset(d)
             | MidiAction::RandomPreset(d)
@@ -236,9 +721,17 @@ impl MidiAction {
             | MidiAction::PlaylistPrevious(d)
             | MidiAction::PlaylistToggleShuffle(d)
             | MidiAction::PlaylistToggleAutoCycle(d)
+            | MidiAction::CompositorDeckOpacity(d)
+            | MidiAction::CompositorBlendModeNext(d)
+            | MidiAction::CompositorDeckToggle(d)
             | MidiAction::ToggleFullscreen(d)
             | MidiAction::VideoOutputToggle(d) => Some(*d),
-            MidiAction::LoadPresetByIndex { deck, .. } => Some(*deck),
+            MidiAction::LoadPresetByIndex { deck, .. }
+            | MidiAction::LoadPlaylistIndex { deck, .. }
+            | MidiAction::LoadPlaylistBank { deck, .. }
+            | MidiAction::CrossfaderAssign { deck, .. }
+            | MidiAction::DeckEffect { deck, .. }
+            | MidiAction::DeckColor { deck, .. } => Some(*deck),
             _ => None,
         }
     }
@@ -249,14 +742,25 @@ impl MidiAction {
             self,
             MidiAction::DeckVolume(_)
                 | MidiAction::DeckBeatSensitivity(_)
+                | MidiAction::DeckEffect { .. }
+                | MidiAction::DeckColor { .. }
+                | MidiAction::DeckTimeScale(_)
                 | MidiAction::CrossfaderPosition
+                | MidiAction::CompositorDeckOpacity(_)
                 | MidiAction::MasterVolume
         )
     }
 }
 
+/// Side of the crossfader
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum CrossfaderSide {
+    A,
+    B,
+}
+
 /// Value transformation for continuous controls
-#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ValueTransform {
     /// Minimum output value
     #[serde(default)]
@@ -270,6 +774,9 @@ pub struct ValueTransform {
     /// Invert the value
     #[serde(default)]
     pub invert: bool,
+    /// Endless encoder sending increments instead of positions
+    #[serde(default)]
+    pub relative: Option<RelativeEncoding>,
 }
 
 fn default_max() -> f32 {
@@ -283,6 +790,7 @@ impl Default for ValueTransform {
             max: 1.0,
             curve: TransformCurve::Linear,
             invert: false,
+            relative: None,
         }
     }
 }
@@ -292,23 +800,108 @@ impl ValueTransform {
     pub fn apply(&self, value: f32) -> f32 {
         let value = if self.invert { 1.0 - value } else { value };
 
-        let curved = match self.curve {
-            TransformCurve::Linear => value,
-            TransformCurve::Logarithmic => value.sqrt(),
-            TransformCurve::Exponential => value * value,
-        };
+        let curved = self.curve.apply(value);
 
         self.min + curved * (self.max - self.min)
     }
+
+    /// Move `current` by `delta` encoder ticks, staying within min/max
+    pub fn step(&self, current: f32, delta: i32) -> f32 {
+        let (low, high) = if self.min <= self.max {
+            (self.min, self.max)
+        } else {
+            (self.max, self.min)
+        };
+        (current + delta as f32 * RELATIVE_STEP * (high - low)).clamp(low, high)
+    }
+}
+
+/// How an endless encoder encodes its increments in a CC value
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RelativeEncoding {
+    /// 1-63 up, 127-65 down by 1-63 (0x01 / 0x7F)
+    TwosComplement,
+    /// Bit 6 set turns down: 1-63 up, 65-127 down by 1-63 (0x01 / 0x41)
+    SignMagnitude,
+    /// Centred on 64: 65 up by one, 63 down by one (0x41 / 0x3F)
+    BinOffset,
+}
+
+impl RelativeEncoding {
+    /// Signed increment carried by a 7-bit CC value
+    pub fn delta(&self, value: u8) -> i32 {
+        let value = (value & 0x7F) as i32;
+        match self {
+            RelativeEncoding::TwosComplement => {
+                if value >= 64 {
+                    value - 128
+                } else {
+                    value
+                }
+            }
+            RelativeEncoding::SignMagnitude => {
+                if value & 0x40 != 0 {
+                    -(value & 0x3F)
+                } else {
+                    value
+                }
+            }
+            RelativeEncoding::BinOffset => value - 64,
+        }
+    }
 }
 
 /// Curve type for value transformation
-#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
 pub enum TransformCurve {
     #[default]
     Linear,
     Logarithmic,
     Exponential,
+    /// `value ^ gamma`: below 1 is more sensitive at the bottom of the
+    /// travel, above 1 at the top
+    Gamma(f32),
+    /// Straight lines between `[input, output]` points (0.0-1.0), sorted by
+    /// input; flat beyond the first and last point
+    Breakpoints(Vec<[f32; 2]>),
+}
+
+impl TransformCurve {
+    /// Breakpoint curve through `points`, in any order
+    pub fn breakpoints(mut points: Vec<[f32; 2]>) -> Self {
+        points.sort_by(|a, b| a[0].total_cmp(&b[0]));
+        TransformCurve::Breakpoints(points)
+    }
+
+    /// Shape a 0.0-1.0 value
+    pub fn apply(&self, value: f32) -> f32 {
+        match self {
+            TransformCurve::Linear => value,
+            TransformCurve::Logarithmic => value.sqrt(),
+            TransformCurve::Exponential => value * value,
+            TransformCurve::Gamma(gamma) => value.max(0.0).powf(gamma.max(0.01)),
+            TransformCurve::Breakpoints(points) => {
+                let (Some(first), Some(last)) = (points.first(), points.last()) else {
+                    return value;
+                };
+                if value <= first[0] {
+                    return first[1];
+                }
+                points
+                    .windows(2)
+                    .find(|pair| value <= pair[1][0])
+                    .map_or(last[1], |pair| {
+                        let ([x0, y0], [x1, y1]) = (pair[0], pair[1]);
+                        if x1 > x0 {
+                            y0 + (value - x0) / (x1 - x0) * (y1 - y0)
+                        } else {
+                            y1
+                        }
+                    })
+            }
+        }
+    }
 }
 
 #[cfg(test)]
@@ -359,6 +952,164 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_parse_sysex() {
+        let (channel, msg) = MidiMessage::parse(&[0xF0, 0x47, 0x7F, 0x4A, 0x01, 0xF7]);
+        assert_eq!(channel, 0);
+        assert_eq!(msg, MidiMessage::SysEx { data: vec![0x47, 0x7F, 0x4A, 0x01] });
+
+        // Other system messages are not SysEx
+        assert_eq!(MidiMessage::parse(&[0xF8]).1, MidiMessage::Unknown);
+    }
+
+    #[test]
+    fn test_sysex_mapping() {
+        let mapping = MidiMapping::new(
+            "Pad",
+            MidiMessageType::SysEx { pattern: vec![0x47, 0x7F, 0x4A] },
+            MidiAction::NextPreset(0),
+        );
+        let parser = MidiParser::new();
+
+        let (ch, msg) = MidiMessage::parse(&[0xF0, 0x47, 0x7F, 0x4A, 0x7F, 0xF7]);
+        assert!(mapping.matches(ch, &msg));
+        assert_eq!(mapping.value(ch, &msg, &parser), Some(1.0));
+
+        let (ch, msg) = MidiMessage::parse(&[0xF0, 0x47, 0x7F, 0x4A, 0x00, 0xF7]);
+        assert_eq!(mapping.value(ch, &msg, &parser), Some(0.0));
+
+        let (ch, msg) = MidiMessage::parse(&[0xF0, 0x47, 0x7F, 0x4B, 0xF7]);
+        assert!(!mapping.matches(ch, &msg));
+        let any = MidiMessageType::AnyOnChannel { channel: 0 };
+        assert!(!any.matches(ch, &msg));
+    }
+
+    #[test]
+    fn test_any_channel_matches_every_channel() {
+        let cc = MidiMessageType::ControlChange {
+            channel: 0,
+            controller: 7,
+        };
+        let omni = cc.clone().with_any_channel(true);
+        let msg = MidiMessage::ControlChange { controller: 7, value: 64 };
+
+        assert!(!cc.matches(3, &msg));
+        assert!(omni.matches(0, &msg));
+        assert!(omni.matches(3, &msg));
+        assert!(!omni.matches(3, &MidiMessage::ControlChange { controller: 8, value: 64 }));
+        assert_eq!(omni.to_string(), "CC 7 on any channel");
+
+        // Back to the original channel, and never nested
+        assert_eq!(omni.clone().with_any_channel(false), cc);
+        assert_eq!(omni.clone().with_any_channel(true), omni);
+    }
+
+    #[test]
+    fn test_any_channel_14bit_value() {
+        let mut parser = MidiParser::new();
+        let mapping = MidiMapping::new(
+            "Fader",
+            MidiMessageType::AnyChannel(Box::new(MidiMessageType::ControlChange14 {
+                channel: 0,
+                msb: 7,
+                lsb: 39,
+            })),
+            MidiAction::MasterVolume,
+        );
+
+        // Layer 2 sends the same fader on channel 1
+        let (ch, msg) = parser.parse(&[0xB1, 7, 127]);
+        assert!(mapping.matches(ch, &msg));
+        let (ch, msg) = parser.parse(&[0xB1, 39, 127]);
+        assert_eq!(mapping.value(ch, &msg, &parser), Some(1.0));
+    }
+
+    #[test]
+    fn test_parse_nrpn() {
+        let mut parser = MidiParser::new();
+        assert_eq!(parser.parse(&[0xB2, 99, 1]).1, MidiMessage::Unknown);
+        assert_eq!(parser.parse(&[0xB2, 98, 5]).1, MidiMessage::Unknown);
+
+        let (channel, msg) = parser.parse(&[0xB2, 6, 64]);
+        assert_eq!(channel, 2);
+        assert_eq!(msg, MidiMessage::Nrpn { parameter: 133, value: 64 << 7 });
+
+        let (_, msg) = parser.parse(&[0xB2, 38, 3]);
+        assert_eq!(msg, MidiMessage::Nrpn { parameter: 133, value: (64 << 7) | 3 });
+
+        // Once fine values arrive the coarse half waits for them
+        assert_eq!(parser.parse(&[0xB2, 6, 65]).1, MidiMessage::Unknown);
+        let (_, msg) = parser.parse(&[0xB2, 38, 0]);
+        assert_eq!(msg, MidiMessage::Nrpn { parameter: 133, value: 65 << 7 });
+    }
+
+    #[test]
+    fn test_data_entry_without_nrpn_is_plain_cc() {
+        let mut parser = MidiParser::new();
+        parser.parse(&[0xB0, 101, 0]);
+        let (_, msg) = parser.parse(&[0xB0, 6, 12]);
+        assert!(matches!(msg, MidiMessage::ControlChange { controller: 6, value: 12 }));
+    }
+
+    #[test]
+    fn test_cc14_mapping_value() {
+        let mapping = MidiMapping::new(
+            "Fader",
+            MidiMessageType::ControlChange14 { channel: 0, msb: 7, lsb: 39 },
+            MidiAction::CrossfaderPosition,
+        );
+        let mut parser = MidiParser::new();
+
+        // A lone coarse value is used until the controller sends fine values
+        let (ch, msg) = parser.parse(&[0xB0, 7, 64]);
+        assert!(mapping.matches(ch, &msg));
+        let value = mapping.value(ch, &msg, &parser).unwrap();
+        assert!((value - (64 << 7) as f32 / 16383.0).abs() < 1e-6);
+
+        let (ch, msg) = parser.parse(&[0xB0, 39, 127]);
+        assert!(mapping.matches(ch, &msg));
+        let value = mapping.value(ch, &msg, &parser).unwrap();
+        assert!((value - ((64 << 7) | 127) as f32 / 16383.0).abs() < 1e-6);
+
+        let (ch, msg) = parser.parse(&[0xB0, 7, 65]);
+        assert_eq!(mapping.value(ch, &msg, &parser), None);
+        assert!(!mapping.matches(1, &msg));
+    }
+
+    #[test]
+    fn test_pickup_waits_for_crossing() {
+        let mut pickup = Pickup::default();
+
+        // Fader well below the software value does nothing
+        assert!(!pickup.update(0.1, 0.6));
+        assert!(!pickup.update(0.3, 0.6));
+        // Crossing the value takes over, then it follows freely
+        assert!(pickup.update(0.65, 0.6));
+        assert!(pickup.update(0.2, 0.65));
+    }
+
+    #[test]
+    fn test_pickup_released_by_outside_change() {
+        let mut pickup = Pickup::default();
+        assert!(pickup.update(0.5, 0.5));
+
+        // The UI moved the value; the fader has to catch it again
+        assert!(!pickup.update(0.52, 0.9));
+        assert!(pickup.update(0.89, 0.9));
+    }
+
+    #[test]
+    fn test_throttle_limits_repeat_rate() {
+        let start = Instant::now();
+        let interval = Duration::from_millis(100);
+        let mut throttle = Throttle::default();
+
+        assert!(throttle.allow(start, interval));
+        // A bounce right after the press is dropped
+        assert!(!throttle.allow(start + Duration::from_millis(20), interval));
+        assert!(throttle.allow(start + Duration::from_millis(100), interval));
+    }
+
     #[test]
     fn test_mapping_matches() {
         let mapping = MidiMapping::new(
@@ -381,6 +1132,7 @@ mod tests {
             max: 100.0,
             curve: TransformCurve::Linear,
             invert: false,
+            relative: None,
         };
 
         assert!((transform.apply(0.0) - 0.0).abs() < 0.001);
@@ -395,12 +1147,173 @@ mod tests {
             max: 1.0,
             curve: TransformCurve::Linear,
             invert: true,
+            relative: None,
         };
 
         assert!((transform.apply(0.0) - 1.0).abs() < 0.001);
         assert!((transform.apply(1.0) - 0.0).abs() < 0.001);
     }
 
+    #[test]
+    fn test_message_type_display() {
+        let cc = MidiMessageType::ControlChange {
+            channel: 0,
+            controller: 7,
+        };
+        assert_eq!(cc.to_string(), "CC 7 on channel 1");
+
+        let sysex = MidiMessageType::SysEx {
+            pattern: vec![0x7E, 0x01],
+        };
+        assert_eq!(sysex.to_string(), "SysEx 7E 01");
+    }
+
+    #[test]
+    fn test_gamepad_mapping() {
+        let mapping = MidiMapping::new(
+            "Crossfader",
+            MidiMessageType::GamepadAxis {
+                axis: "RightStickX".to_string(),
+            },
+            MidiAction::CrossfaderPosition,
+        );
+        let message = MidiMessage::GamepadAxis {
+            axis: "RightStickX".to_string(),
+            value: 0.75,
+        };
+        assert!(mapping.matches(3, &message));
+        assert_eq!(mapping.value(3, &message, &MidiParser::new()), Some(0.75));
+        assert!(!mapping.matches(
+            0,
+            &MidiMessage::GamepadAxis {
+                axis: "RightStickY".to_string(),
+                value: 0.75,
+            }
+        ));
+        assert_eq!(mapping.midi_message.to_string(), "Gamepad RightStickX axis");
+    }
+
+    #[test]
+    fn test_program_change_selects_bank() {
+        let mapping = MidiMapping::new(
+            "Banks",
+            MidiMessageType::ProgramChange { channel: 0 },
+            MidiAction::LoadPlaylistBank { deck: 1, bank: 0 },
+        );
+        let (channel, message) = MidiParser::new().parse(&[0xC0, 5]);
+        assert!(mapping.matches(channel, &message));
+        assert_eq!(
+            mapping.action_for(&message),
+            MidiAction::LoadPlaylistBank { deck: 1, bank: 5 }
+        );
+
+        // A button mapped to a fixed bank keeps it
+        let note = MidiMessage::NoteOn { note: 36, velocity: 127 };
+        assert_eq!(
+            mapping.action_for(&note),
+            MidiAction::LoadPlaylistBank { deck: 1, bank: 0 }
+        );
+    }
+
+    #[test]
+    fn test_macro_actions_in_order() {
+        let mut mapping = MidiMapping::new(
+            "Scene",
+            MidiMessageType::NoteOn { channel: 0, note: 36 },
+            MidiAction::DeckStop(0),
+        );
+        assert!(!mapping.is_macro());
+        assert_eq!(mapping.actions(1.0), vec![(MidiAction::DeckStop(0), 1.0)]);
+
+        mapping.macro_steps = vec![
+            MacroStep { action: MidiAction::DeckStart(1), value: None },
+            MacroStep { action: MidiAction::CrossfaderPosition, value: Some(1.0) },
+        ];
+        assert!(mapping.is_macro());
+        assert_eq!(
+            mapping.actions(0.5),
+            vec![
+                (MidiAction::DeckStop(0), 0.5),
+                (MidiAction::DeckStart(1), 0.5),
+                (MidiAction::CrossfaderPosition, 1.0),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_gamma_curve() {
+        let curve = TransformCurve::Gamma(2.0);
+        assert!((curve.apply(0.5) - 0.25).abs() < 1e-6);
+        assert_eq!(curve.apply(1.0), 1.0);
+
+        let gentle = TransformCurve::Gamma(0.5);
+        assert!(gentle.apply(0.25) > 0.25);
+    }
+
+    #[test]
+    fn test_breakpoint_curve() {
+        // Fine control over the first half, then a quick rise
+        let curve = TransformCurve::breakpoints(vec![[1.0, 1.0], [0.0, 0.0], [0.5, 0.2]]);
+        assert!((curve.apply(0.25) - 0.1).abs() < 1e-6);
+        assert!((curve.apply(0.5) - 0.2).abs() < 1e-6);
+        assert!((curve.apply(0.75) - 0.6).abs() < 1e-6);
+        assert_eq!(curve.apply(1.0), 1.0);
+
+        let transform = ValueTransform {
+            max: 10.0,
+            curve,
+            ..Default::default()
+        };
+        assert!((transform.apply(0.25) - 1.0).abs() < 1e-5);
+
+        assert_eq!(TransformCurve::Breakpoints(Vec::new()).apply(0.3), 0.3);
+    }
+
+    #[test]
+    fn test_relative_encodings() {
+        let twos = RelativeEncoding::TwosComplement;
+        assert_eq!(twos.delta(0x01), 1);
+        assert_eq!(twos.delta(0x7F), -1);
+        assert_eq!(twos.delta(0x7D), -3);
+
+        let sign = RelativeEncoding::SignMagnitude;
+        assert_eq!(sign.delta(0x01), 1);
+        assert_eq!(sign.delta(0x41), -1);
+        assert_eq!(sign.delta(0x43), -3);
+
+        let offset = RelativeEncoding::BinOffset;
+        assert_eq!(offset.delta(0x41), 1);
+        assert_eq!(offset.delta(0x3F), -1);
+        assert_eq!(offset.delta(0x40), 0);
+    }
+
+    #[test]
+    fn test_relative_step_is_clamped() {
+        let mut mapping = MidiMapping::new(
+            "Encoder",
+            MidiMessageType::ControlChange {
+                channel: 0,
+                controller: 16,
+            },
+            MidiAction::MasterVolume,
+        );
+        let transform = ValueTransform {
+            relative: Some(RelativeEncoding::TwosComplement),
+            ..Default::default()
+        };
+        mapping.value_transform = Some(transform.clone());
+
+        let down = MidiMessage::ControlChange { controller: 16, value: 0x7F };
+        let delta = mapping.relative_delta(&down).unwrap();
+        assert_eq!(delta, -1);
+        assert!((transform.step(0.5, delta) - (0.5 - 1.0 / 127.0)).abs() < 1e-6);
+        assert_eq!(transform.step(0.0, delta), 0.0);
+        assert_eq!(transform.step(1.0, 10), 1.0);
+
+        mapping.value_transform = None;
+        assert_eq!(mapping.relative_delta(&down), None);
+    }
+
     #[test]
     fn test_midi_message_value() {
         let note_on = MidiMessage::NoteOn { note: 60, velocity: 127 };
@@ -417,14 +1330,31 @@ mod tests {
     fn test_action_deck_id() {
         assert_eq!(MidiAction::DeckStart(2).deck_id(), Some(2));
         assert_eq!(MidiAction::CrossfaderPosition.deck_id(), None);
+        assert_eq!(MidiAction::CompositorDeckToggle(3).deck_id(), Some(3));
+        let assign = MidiAction::CrossfaderAssign { deck: 1, side: CrossfaderSide::B };
+        assert_eq!(assign.deck_id(), Some(1));
+        assert_eq!(MidiAction::CrossfaderCut(CrossfaderSide::A).deck_id(), None);
         assert_eq!(MidiAction::MasterVolume.deck_id(), None);
+        let effect = MidiAction::DeckEffect { deck: 2, effect: Effect::Bloom };
+        assert_eq!(effect.deck_id(), Some(2));
+        let color = MidiAction::DeckColor { deck: 1, control: ColorControl::Brightness };
+        assert_eq!(color.deck_id(), Some(1));
+        assert_eq!(MidiAction::DeckStrobe(3).deck_id(), Some(3));
+        assert_eq!(MidiAction::DeckTimeScale(2).deck_id(), Some(2));
     }
 
     #[test]
     fn test_action_is_continuous() {
         assert!(MidiAction::DeckVolume(0).is_continuous());
         assert!(MidiAction::CrossfaderPosition.is_continuous());
+        assert!(MidiAction::CompositorDeckOpacity(1).is_continuous());
+        assert!(MidiAction::DeckEffect { deck: 0, effect: Effect::Pixelate }.is_continuous());
+        assert!(MidiAction::DeckColor { deck: 0, control: ColorControl::Contrast }.is_continuous());
+        assert!(MidiAction::DeckTimeScale(0).is_continuous());
+        assert!(!MidiAction::CompositorBlendModeNext(1).is_continuous());
         assert!(!MidiAction::DeckStart(0).is_continuous());
+        assert!(!MidiAction::DeckBlackout(0).is_continuous());
+        assert!(!MidiAction::DeckFreeze(0).is_continuous());
         assert!(!MidiAction::NextPreset(0).is_continuous());
     }
 }
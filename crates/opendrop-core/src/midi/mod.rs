@@ -2,19 +2,31 @@
 //!
 //! Provides MIDI device enumeration, event processing, and mapping to OpenDrop actions.
 
+pub mod gamepad;
 pub mod mapping;
+pub mod osc;
+pub mod output;
 pub mod persistence;
 
 use midir::{MidiInput, MidiInputConnection};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
 use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
 use thiserror::Error;
 
 pub use mapping::{
-    MidiAction, MidiMapping, MidiMessage, MidiMessageType, TransformCurve, ValueTransform,
+    CrossfaderSide, MacroStep, MidiAction, MidiMapping, MidiMessage, MidiMessageType, MidiParser, Pickup,
+    RelativeEncoding, Throttle, TransformCurve, ValueTransform, CC14_LSB_OFFSET,
 };
+pub use gamepad::GamepadInput;
+pub use osc::{OscServer, DEFAULT_OSC_PORT};
+pub use output::{MidiOut, OutputEvent, VIRTUAL_OUTPUT_NAME};
 pub use persistence::{
-    create_apc_mini_preset, create_generic_dj_preset, create_launchpad_preset,
-    create_nanokontrol2_preset, list_presets, presets_dir, MidiPreset,
+    create_apc_mini_preset, create_ddj400_preset, create_generic_dj_preset,
+    create_launchpad_preset, create_midi_fighter_twister_preset, create_nanokontrol2_preset,
+    create_pad_bank_mappings, create_traktor_kontrol_preset, list_playlist_banks, list_presets,
+    playlist_banks_dir, presets_dir, MidiPreset, MidiSession, PadGrid, SESSION_FILE,
 };
 
 #[derive(Error, Debug)]
@@ -29,6 +41,8 @@ pub enum MidiError {
     ConnectionError(String),
     #[error("Mapping not found: {0}")]
     MappingNotFound(String),
+    #[error("Virtual MIDI ports are not supported on this platform")]
+    VirtualPortUnsupported,
 }
 
 /// Information about a MIDI input port
@@ -60,12 +74,25 @@ pub fn list_midi_ports() -> Result<Vec<MidiPortInfo>, MidiError> {
     Ok(ports)
 }
 
+/// How far from centre a gamepad axis must move to be learned, so a
+/// drifting stick doesn't take over learn mode
+const LEARN_AXIS_DEFLECTION: f32 = 0.25;
+
 /// Callback type for MIDI events
 pub type MidiCallback = Box<dyn Fn(u8, MidiMessage, f32) + Send + 'static>;
 
 /// Callback type for MIDI action events
 pub type ActionCallback = Box<dyn Fn(MidiAction, f32) + Send + 'static>;
 
+/// Callback reporting the current value (0.0-1.0) of a continuous action's target
+pub type ValueCallback = Box<dyn Fn(MidiAction) -> Option<f32> + Send + 'static>;
+
+/// Callback for the actions of a macro mapping, to be applied together
+pub type MacroCallback = Box<dyn Fn(&[(MidiAction, f32)]) + Send + 'static>;
+
+/// Callback for controls detected in learn mode
+pub type LearnCallback = Box<dyn Fn(&MidiMessageType) + Send + 'static>;
+
 /// MIDI controller manager
 pub struct MidiController {
     /// Currently active connection
@@ -76,8 +103,44 @@ pub struct MidiController {
     mappings: Arc<Mutex<Vec<MidiMapping>>>,
     /// Callback for processed MIDI actions
     action_callback: Arc<Mutex<Option<ActionCallback>>>,
+    /// Callback for macro mappings (falls back to one action at a time)
+    macro_callback: Arc<Mutex<Option<MacroCallback>>>,
+    /// Current software values, for soft takeover
+    value_callback: Arc<Mutex<Option<ValueCallback>>>,
     /// Learn mode state
     learn_mode: Arc<Mutex<Option<LearnModeState>>>,
+    /// Callback for controls detected while learning
+    learn_callback: Arc<Mutex<Option<LearnCallback>>>,
+    /// Session file kept up to date with every change, if enabled
+    autosave: Arc<Mutex<Option<Autosave>>>,
+    /// Output republishing incoming messages, if enabled
+    output: Arc<Mutex<Option<MidiOut>>>,
+    /// OSC input feeding the same mappings, if enabled
+    osc: Option<OscServer>,
+    /// Gamepad input feeding the same mappings, if enabled
+    gamepad: Option<GamepadInput>,
+}
+
+/// Where the session is saved and the port it remembers
+#[derive(Debug, Clone)]
+struct Autosave {
+    path: PathBuf,
+    /// Last port connected to; kept while the device is unplugged
+    port_name: Option<String>,
+}
+
+/// Write the session if autosave is enabled
+fn save_session(autosave: &Mutex<Option<Autosave>>, mappings: &[MidiMapping]) {
+    let Some(autosave) = autosave.lock().unwrap().clone() else {
+        return;
+    };
+    let session = MidiSession {
+        port_name: autosave.port_name,
+        mappings: mappings.to_vec(),
+    };
+    if let Err(e) = session.save(&autosave.path) {
+        tracing::warn!("Failed to save MIDI session: {}", e);
+    }
 }
 
 /// State for MIDI learn mode
@@ -87,6 +150,195 @@ pub struct LearnModeState {
     pub target_action: MidiAction,
     /// Name for the new mapping
     pub mapping_name: String,
+    /// Coarse CC (channel, controller) received while learning, held until
+    /// the next message shows whether it is half of a 14-bit pair
+    pub pending_msb: Option<(u8, u8)>,
+    /// Control detected so far, waiting to be confirmed
+    pub detected: Option<MidiMessageType>,
+}
+
+impl LearnModeState {
+    /// Start learning a control for `action`
+    pub fn new(action: MidiAction, name: impl Into<String>) -> Self {
+        Self {
+            target_action: action,
+            mapping_name: name.into(),
+            pending_msb: None,
+            detected: None,
+        }
+    }
+
+    /// Update the detected control from an incoming message; returns the
+    /// control when it changed
+    pub fn detect(&mut self, channel: u8, message: &MidiMessage) -> Option<MidiMessageType> {
+        let detected = self.detected.as_ref();
+        // More of the same control, or a released pad
+        if detected.is_some_and(|d| d.matches(channel, message)) {
+            return None;
+        }
+        if let (
+            Some(MidiMessageType::NoteOn { channel: c, note: n }),
+            MidiMessage::NoteOff { note, .. },
+        ) = (detected, message)
+        {
+            if *c == channel && n == note {
+                return None;
+            }
+        }
+
+        let pending_msb = self.pending_msb.take();
+        let midi_type = match message {
+            MidiMessage::ControlChange { controller, .. } => match pending_msb {
+                Some((ch, msb)) if ch == channel && *controller == msb + CC14_LSB_OFFSET => {
+                    MidiMessageType::ControlChange14 {
+                        channel,
+                        msb,
+                        lsb: *controller,
+                    }
+                }
+                _ => {
+                    // Could be the coarse half of a 14-bit pair
+                    if *controller < CC14_LSB_OFFSET {
+                        self.pending_msb = Some((channel, *controller));
+                    }
+                    MidiMessageType::ControlChange {
+                        channel,
+                        controller: *controller,
+                    }
+                }
+            },
+            MidiMessage::NoteOn { note, .. } => MidiMessageType::NoteOn { channel, note: *note },
+            MidiMessage::NoteOff { note, .. } => MidiMessageType::NoteOff { channel, note: *note },
+            MidiMessage::Nrpn { parameter, .. } => MidiMessageType::Nrpn {
+                channel,
+                parameter: *parameter,
+            },
+            MidiMessage::SysEx { data } => MidiMessageType::SysEx { pattern: data.clone() },
+            MidiMessage::PitchBend { .. } => MidiMessageType::PitchBend { channel },
+            MidiMessage::ProgramChange { .. } => MidiMessageType::ProgramChange { channel },
+            MidiMessage::Osc { address, .. } => MidiMessageType::Osc {
+                address: address.clone(),
+            },
+            MidiMessage::GamepadButton { button, .. } => MidiMessageType::GamepadButton {
+                button: button.clone(),
+            },
+            MidiMessage::GamepadAxis { axis, value } => {
+                if (value - 0.5).abs() < LEARN_AXIS_DEFLECTION {
+                    self.pending_msb = pending_msb;
+                    return None;
+                }
+                MidiMessageType::GamepadAxis { axis: axis.clone() }
+            }
+            MidiMessage::Unknown => {
+                // Keep waiting (e.g. NRPN parameter select)
+                self.pending_msb = pending_msb;
+                return None;
+            }
+        };
+
+        self.detected = Some(midi_type.clone());
+        Some(midi_type)
+    }
+}
+
+/// Feeds the messages of one input to learn mode or the mappings
+pub(crate) struct InputRouter {
+    mappings: Arc<Mutex<Vec<MidiMapping>>>,
+    action_callback: Arc<Mutex<Option<ActionCallback>>>,
+    macro_callback: Arc<Mutex<Option<MacroCallback>>>,
+    value_callback: Arc<Mutex<Option<ValueCallback>>>,
+    learn_mode: Arc<Mutex<Option<LearnModeState>>>,
+    learn_callback: Arc<Mutex<Option<LearnCallback>>>,
+    // Takeover starts over on every connection
+    pickups: HashMap<uuid::Uuid, Pickup>,
+    // Encoder positions of actions that can't report their value
+    encoders: HashMap<uuid::Uuid, f32>,
+    throttles: HashMap<uuid::Uuid, Throttle>,
+}
+
+impl InputRouter {
+    /// Handle a message received on `channel`
+    pub(crate) fn route(&mut self, channel: u8, message: &MidiMessage, parser: &MidiParser) {
+        // Learn mode: report the control, mapping it is confirmed separately
+        let detected = self
+            .learn_mode
+            .lock()
+            .unwrap()
+            .as_mut()
+            .map(|state| state.detect(channel, message));
+        if let Some(detected) = detected {
+            if let (Some(detected), Some(ref callback)) =
+                (detected, &*self.learn_callback.lock().unwrap())
+            {
+                callback(&detected);
+            }
+            return;
+        }
+
+        // Normal processing: check mappings
+        let mappings_guard = self.mappings.lock().unwrap();
+        for mapping in mappings_guard.iter() {
+            if !mapping.matches(channel, message) {
+                continue;
+            }
+            let current = |action| {
+                self.value_callback
+                    .lock()
+                    .unwrap()
+                    .as_ref()
+                    .and_then(|current: &ValueCallback| current(action))
+            };
+            if let (Some(delta), Some(transform)) =
+                (mapping.relative_delta(message), &mapping.value_transform)
+            {
+                // Endless encoder: move on from the current value
+                let position = self.encoders.entry(mapping.id).or_insert(transform.min);
+                let from = current(mapping.action).unwrap_or(*position);
+                *position = transform.step(from, delta);
+                if let Some(ref callback) = *self.action_callback.lock().unwrap() {
+                    callback(mapping.action, *position);
+                }
+                continue;
+            }
+            let Some(value) = mapping.value(channel, message, parser) else {
+                continue;
+            };
+            // Debounce presses; releases always go through
+            let press = value > 0.0 && !mapping.action.is_continuous();
+            if press && mapping.debounce_ms > 0 {
+                let interval = Duration::from_millis(mapping.debounce_ms as u64);
+                let throttle = self.throttles.entry(mapping.id).or_default();
+                if !throttle.allow(Instant::now(), interval) {
+                    continue;
+                }
+            }
+            if mapping.is_macro() {
+                // Macros run on the press only, all actions at once
+                if value > 0.0 {
+                    let actions = mapping.actions(value);
+                    if let Some(ref callback) = *self.macro_callback.lock().unwrap() {
+                        callback(&actions);
+                    } else if let Some(ref callback) = *self.action_callback.lock().unwrap() {
+                        for (action, value) in actions {
+                            callback(action, value);
+                        }
+                    }
+                }
+                continue;
+            }
+            if mapping.pickup && mapping.action.is_continuous() {
+                if let Some(software) = current(mapping.action) {
+                    let pickup = self.pickups.entry(mapping.id).or_default();
+                    if !pickup.update(value, software) {
+                        continue;
+                    }
+                }
+            }
+            if let Some(ref callback) = *self.action_callback.lock().unwrap() {
+                callback(mapping.action_for(message), value);
+            }
+        }
+    }
 }
 
 impl MidiController {
@@ -97,14 +349,68 @@ impl MidiController {
             connected_port_name: None,
             mappings: Arc::new(Mutex::new(Vec::new())),
             action_callback: Arc::new(Mutex::new(None)),
+            macro_callback: Arc::new(Mutex::new(None)),
+            value_callback: Arc::new(Mutex::new(None)),
             learn_mode: Arc::new(Mutex::new(None)),
+            learn_callback: Arc::new(Mutex::new(None)),
+            autosave: Arc::new(Mutex::new(None)),
+            output: Arc::new(Mutex::new(None)),
+            osc: None,
+            gamepad: None,
+        }
+    }
+
+    /// Restore the session saved at `path` (mappings, then the port if it
+    /// is plugged in) and keep it saved there on every change
+    pub fn restore_session(&mut self, path: impl AsRef<Path>) -> Result<(), MidiError> {
+        let path = path.as_ref();
+        let session = if path.exists() {
+            MidiSession::load(path).map_err(|e| MidiError::InitError(e.to_string()))?
+        } else {
+            MidiSession::default()
+        };
+
+        *self.mappings.lock().unwrap() = session.mappings;
+        *self.autosave.lock().unwrap() = Some(Autosave {
+            path: path.to_path_buf(),
+            port_name: session.port_name.clone(),
+        });
+
+        match session.port_name {
+            Some(name) => self.connect_by_name(&name),
+            None => Ok(()),
+        }
+    }
+
+    /// Connect to a MIDI input port by name
+    pub fn connect_by_name(&mut self, name: &str) -> Result<(), MidiError> {
+        let index = list_midi_ports()?
+            .into_iter()
+            .find(|p| p.name == name)
+            .map(|p| p.index)
+            .ok_or_else(|| MidiError::DeviceNotFound(name.to_string()))?;
+        self.connect(index)
+    }
+
+    /// Router feeding a new input into the mappings
+    pub(crate) fn router(&self) -> InputRouter {
+        InputRouter {
+            mappings: Arc::clone(&self.mappings),
+            action_callback: Arc::clone(&self.action_callback),
+            macro_callback: Arc::clone(&self.macro_callback),
+            value_callback: Arc::clone(&self.value_callback),
+            learn_mode: Arc::clone(&self.learn_mode),
+            learn_callback: Arc::clone(&self.learn_callback),
+            pickups: HashMap::new(),
+            encoders: HashMap::new(),
+            throttles: HashMap::new(),
         }
     }
 
     /// Connect to a MIDI input port by index
     pub fn connect(&mut self, port_index: usize) -> Result<(), MidiError> {
         // Disconnect existing connection
-        self.disconnect();
+        self.close_connection();
 
         let midi_in =
             MidiInput::new("OpenDrop").map_err(|e| MidiError::InitError(e.to_string()))?;
@@ -118,75 +424,46 @@ impl MidiController {
             .port_name(port)
             .unwrap_or_else(|_| "Unknown".to_string());
 
-        // Clone Arcs for the callback closure
-        let mappings = Arc::clone(&self.mappings);
-        let action_callback = Arc::clone(&self.action_callback);
-        let learn_mode = Arc::clone(&self.learn_mode);
+        let output = Arc::clone(&self.output);
+        let mut parser = MidiParser::new();
+        let mut router = self.router();
 
         let connection = midi_in
             .connect(
                 port,
                 "opendrop-midi",
                 move |_timestamp, data, _| {
-                    let (channel, message) = MidiMessage::parse(data);
-
-                    // Check learn mode first
-                    {
-                        let mut learn = learn_mode.lock().unwrap();
-                        if let Some(state) = learn.take() {
-                            // Create new mapping from this MIDI message
-                            let midi_type = match message {
-                                MidiMessage::NoteOn { note, .. } => {
-                                    MidiMessageType::NoteOn { channel, note }
-                                }
-                                MidiMessage::NoteOff { note, .. } => {
-                                    MidiMessageType::NoteOff { channel, note }
-                                }
-                                MidiMessage::ControlChange { controller, .. } => {
-                                    MidiMessageType::ControlChange { channel, controller }
-                                }
-                                MidiMessage::PitchBend { .. } => {
-                                    MidiMessageType::PitchBend { channel }
-                                }
-                                MidiMessage::ProgramChange { .. } => {
-                                    MidiMessageType::ProgramChange { channel }
-                                }
-                                MidiMessage::Unknown => return,
-                            };
-
-                            let new_mapping =
-                                MidiMapping::new(state.mapping_name, midi_type, state.target_action);
-
-                            mappings.lock().unwrap().push(new_mapping);
-                            tracing::info!("Learned MIDI mapping for {:?}", state.target_action);
-                            return;
-                        }
-                    }
-
-                    // Normal processing: check mappings
-                    let mappings_guard = mappings.lock().unwrap();
-                    for mapping in mappings_guard.iter() {
-                        if mapping.matches(channel, &message) {
-                            let value = mapping.transform_value(message.value());
-
-                            if let Some(ref callback) = *action_callback.lock().unwrap() {
-                                callback(mapping.action, value);
-                            }
-                        }
+                    if let Some(out) = output.lock().unwrap().as_mut() {
+                        out.send(data);
                     }
+                    let (channel, message) = parser.parse(data);
+                    router.route(channel, &message, &parser);
                 },
                 (),
             )
             .map_err(|e| MidiError::ConnectionError(e.to_string()))?;
 
         tracing::info!("Connected to MIDI port: {}", port_name);
+        if let Some(autosave) = self.autosave.lock().unwrap().as_mut() {
+            autosave.port_name = Some(port_name.clone());
+        }
         self.connected_port_name = Some(port_name);
         self.connection = Some(connection);
+        self.save();
         Ok(())
     }
 
-    /// Disconnect from the current MIDI port
+    /// Disconnect from the current MIDI port (and stop reconnecting to it)
     pub fn disconnect(&mut self) {
+        self.close_connection();
+        if let Some(autosave) = self.autosave.lock().unwrap().as_mut() {
+            autosave.port_name = None;
+        }
+        self.save();
+    }
+
+    /// Close the connection, keeping the port for the next session
+    fn close_connection(&mut self) {
         if let Some(conn) = self.connection.take() {
             conn.close();
             self.connected_port_name = None;
@@ -212,9 +489,136 @@ impl MidiController {
         *self.action_callback.lock().unwrap() = Some(Box::new(callback));
     }
 
+    /// Set the callback running all actions of a macro mapping at once
+    pub fn set_macro_callback<F>(&self, callback: F)
+    where
+        F: Fn(&[(MidiAction, f32)]) + Send + 'static,
+    {
+        *self.macro_callback.lock().unwrap() = Some(Box::new(callback));
+    }
+
+    /// Set the callback for controls detected in learn mode
+    pub fn set_learn_callback<F>(&self, callback: F)
+    where
+        F: Fn(&MidiMessageType) + Send + 'static,
+    {
+        *self.learn_callback.lock().unwrap() = Some(Box::new(callback));
+    }
+
+    /// Set the callback reporting current software values for soft takeover
+    pub fn set_value_callback<F>(&self, callback: F)
+    where
+        F: Fn(MidiAction) -> Option<f32> + Send + 'static,
+    {
+        *self.value_callback.lock().unwrap() = Some(Box::new(callback));
+    }
+
+    /// Turn soft takeover on or off for a mapping
+    pub fn set_pickup(&self, id: uuid::Uuid, pickup: bool) -> bool {
+        let mut mappings = self.mappings.lock().unwrap();
+        match mappings.iter_mut().find(|m| m.id == id) {
+            Some(mapping) => {
+                mapping.pickup = pickup;
+                save_session(&self.autosave, &mappings);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Save the session now (no-op unless restored with `restore_session`)
+    fn save(&self) {
+        save_session(&self.autosave, &self.mappings.lock().unwrap());
+    }
+
+    /// Make a mapping respond on every channel, or only its own again
+    pub fn set_any_channel(&self, id: uuid::Uuid, any: bool) -> bool {
+        let mut mappings = self.mappings.lock().unwrap();
+        match mappings.iter_mut().find(|m| m.id == id) {
+            Some(mapping) => {
+                mapping.midi_message = mapping.midi_message.clone().with_any_channel(any);
+                save_session(&self.autosave, &mappings);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Append a step to a mapping's macro
+    pub fn add_macro_step(&self, id: uuid::Uuid, step: MacroStep) -> bool {
+        let mut mappings = self.mappings.lock().unwrap();
+        match mappings.iter_mut().find(|m| m.id == id) {
+            Some(mapping) => {
+                mapping.macro_steps.push(step);
+                save_session(&self.autosave, &mappings);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Make a macro mapping run only its own action again
+    pub fn clear_macro(&self, id: uuid::Uuid) -> bool {
+        let mut mappings = self.mappings.lock().unwrap();
+        match mappings.iter_mut().find(|m| m.id == id) {
+            Some(mapping) => {
+                mapping.macro_steps.clear();
+                save_session(&self.autosave, &mappings);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Limit how often a trigger mapping fires (0 = no limit)
+    pub fn set_debounce(&self, id: uuid::Uuid, debounce_ms: u32) -> bool {
+        let mut mappings = self.mappings.lock().unwrap();
+        match mappings.iter_mut().find(|m| m.id == id) {
+            Some(mapping) => {
+                mapping.debounce_ms = debounce_ms;
+                save_session(&self.autosave, &mappings);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Set the response curve of a mapping
+    pub fn set_curve(&self, id: uuid::Uuid, curve: TransformCurve) -> bool {
+        let curve = match curve {
+            TransformCurve::Breakpoints(points) => TransformCurve::breakpoints(points),
+            curve => curve,
+        };
+        let mut mappings = self.mappings.lock().unwrap();
+        match mappings.iter_mut().find(|m| m.id == id) {
+            Some(mapping) => {
+                mapping.value_transform.get_or_insert_with(Default::default).curve = curve;
+                save_session(&self.autosave, &mappings);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Set how an endless encoder mapping reads increments, or make it
+    /// absolute again with `None`
+    pub fn set_relative(&self, id: uuid::Uuid, encoding: Option<RelativeEncoding>) -> bool {
+        let mut mappings = self.mappings.lock().unwrap();
+        match mappings.iter_mut().find(|m| m.id == id) {
+            Some(mapping) => {
+                mapping.value_transform.get_or_insert_with(Default::default).relative = encoding;
+                save_session(&self.autosave, &mappings);
+                true
+            }
+            None => false,
+        }
+    }
+
     /// Add a MIDI mapping
     pub fn add_mapping(&self, mapping: MidiMapping) {
-        self.mappings.lock().unwrap().push(mapping);
+        let mut mappings = self.mappings.lock().unwrap();
+        mappings.push(mapping);
+        save_session(&self.autosave, &mappings);
     }
 
     /// Remove a MIDI mapping by ID
@@ -222,6 +626,7 @@ impl MidiController {
         let mut mappings = self.mappings.lock().unwrap();
         let len_before = mappings.len();
         mappings.retain(|m| m.id != id);
+        save_session(&self.autosave, &mappings);
         mappings.len() < len_before
     }
 
@@ -232,23 +637,122 @@ impl MidiController {
 
     /// Clear all mappings
     pub fn clear_mappings(&self) {
-        self.mappings.lock().unwrap().clear();
+        let mut mappings = self.mappings.lock().unwrap();
+        mappings.clear();
+        save_session(&self.autosave, &mappings);
     }
 
     /// Load mappings from a list
     pub fn load_mappings(&self, mappings: Vec<MidiMapping>) {
-        *self.mappings.lock().unwrap() = mappings;
+        let mut guard = self.mappings.lock().unwrap();
+        *guard = mappings;
+        save_session(&self.autosave, &guard);
+    }
+
+    /// Open the output port: the virtual "OpenDrop Out", or an existing
+    /// port by name; returns the port name
+    pub fn enable_output(&self, port_name: Option<&str>) -> Result<String, MidiError> {
+        let out = match port_name {
+            Some(name) => MidiOut::open_port(name)?,
+            None => MidiOut::open_virtual()?,
+        };
+        let name = out.name().to_string();
+        *self.output.lock().unwrap() = Some(out);
+        tracing::info!("MIDI output enabled on {}", name);
+        Ok(name)
+    }
+
+    /// Close the output port
+    pub fn disable_output(&self) {
+        *self.output.lock().unwrap() = None;
+    }
+
+    /// Name of the open output port
+    pub fn output_name(&self) -> Option<String> {
+        self.output.lock().unwrap().as_ref().map(|o| o.name().to_string())
+    }
+
+    /// Shared handle to the output, for publishing events without holding
+    /// the controller
+    pub fn output(&self) -> Arc<Mutex<Option<MidiOut>>> {
+        Arc::clone(&self.output)
+    }
+
+    /// Listen for OSC messages on a UDP port (0 picks a free one); returns
+    /// the port
+    pub fn enable_osc(&mut self, port: u16) -> Result<u16, MidiError> {
+        // Free the port first when restarting on the same one
+        self.osc = None;
+        let server = OscServer::start(port, self.router())?;
+        let port = server.port();
+        self.osc = Some(server);
+        Ok(port)
+    }
+
+    /// Stop listening for OSC messages
+    pub fn disable_osc(&mut self) {
+        self.osc = None;
+    }
+
+    /// Port of the OSC input, if enabled
+    pub fn osc_port(&self) -> Option<u16> {
+        self.osc.as_ref().map(|osc| osc.port())
+    }
+
+    /// Read connected gamepads (and ones plugged in later) into the mappings
+    pub fn enable_gamepads(&mut self) -> Result<(), MidiError> {
+        if self.gamepad.is_none() {
+            self.gamepad = Some(GamepadInput::start(self.router())?);
+        }
+        Ok(())
+    }
+
+    /// Stop reading gamepads
+    pub fn disable_gamepads(&mut self) {
+        self.gamepad = None;
+    }
+
+    /// Names of the connected gamepads, `None` while gamepad input is off
+    pub fn gamepads(&self) -> Option<Vec<String>> {
+        self.gamepad.as_ref().map(|gamepad| gamepad.gamepads())
     }
 
     /// Enter learn mode for a specific action
     pub fn start_learn_mode(&self, action: MidiAction, name: String) {
-        *self.learn_mode.lock().unwrap() = Some(LearnModeState {
-            target_action: action,
-            mapping_name: name,
-        });
+        *self.learn_mode.lock().unwrap() = Some(LearnModeState::new(action, name));
         tracing::info!("Started MIDI learn mode for {:?}", action);
     }
 
+    /// Control detected in learn mode, waiting to be confirmed
+    pub fn learn_detected(&self) -> Option<MidiMessageType> {
+        self.learn_mode
+            .lock()
+            .unwrap()
+            .as_ref()
+            .and_then(|state| state.detected.clone())
+    }
+
+    /// Map the detected control and leave learn mode; `None` (still
+    /// learning) while nothing has been detected
+    pub fn confirm_learn(&self) -> Option<MidiMapping> {
+        let mut learn = self.learn_mode.lock().unwrap();
+        let midi_type = learn.as_ref()?.detected.clone()?;
+        let state = learn.take()?;
+
+        let mapping = MidiMapping::new(state.mapping_name, midi_type, state.target_action);
+        self.add_mapping(mapping.clone());
+        tracing::info!("Learned MIDI mapping for {:?}", state.target_action);
+        Some(mapping)
+    }
+
+    /// Forget the detected control and keep learning
+    pub fn reject_learn(&self) {
+        if let Some(state) = self.learn_mode.lock().unwrap().as_mut() {
+            state.detected = None;
+            state.pending_msb = None;
+        }
+    }
+
     /// Cancel learn mode
     pub fn cancel_learn_mode(&self) {
         *self.learn_mode.lock().unwrap() = None;
@@ -269,7 +773,7 @@ impl Default for MidiController {
 
 impl Drop for MidiController {
     fn drop(&mut self) {
-        self.disconnect();
+        self.close_connection();
     }
 }
 
@@ -327,6 +831,151 @@ mod tests {
         assert!(!controller.is_learning());
     }
 
+    #[test]
+    fn test_learn_confirm_and_reject() {
+        let controller = MidiController::new();
+        controller.start_learn_mode(MidiAction::CrossfaderPosition, "Crossfader".to_string());
+
+        // Nothing detected yet
+        assert!(controller.confirm_learn().is_none());
+        assert!(controller.is_learning());
+
+        let cc = MidiMessage::ControlChange { controller: 7, value: 10 };
+        let detected = controller
+            .learn_mode
+            .lock()
+            .unwrap()
+            .as_mut()
+            .unwrap()
+            .detect(0, &cc);
+        assert!(detected.is_some());
+
+        controller.reject_learn();
+        assert!(controller.learn_detected().is_none());
+        assert!(controller.is_learning());
+
+        controller
+            .learn_mode
+            .lock()
+            .unwrap()
+            .as_mut()
+            .unwrap()
+            .detect(0, &cc);
+        let mapping = controller.confirm_learn().unwrap();
+        assert_eq!(
+            mapping.midi_message,
+            MidiMessageType::ControlChange {
+                channel: 0,
+                controller: 7
+            }
+        );
+        assert_eq!(controller.get_mappings().len(), 1);
+        assert!(!controller.is_learning());
+    }
+
+    #[test]
+    fn test_learn_detects_14bit_pair() {
+        let mut state = LearnModeState::new(MidiAction::MasterVolume, "Master");
+        let msb = MidiMessage::ControlChange { controller: 7, value: 64 };
+        let lsb = MidiMessage::ControlChange { controller: 39, value: 0 };
+
+        assert!(matches!(
+            state.detect(0, &msb),
+            Some(MidiMessageType::ControlChange { controller: 7, .. })
+        ));
+        assert!(matches!(
+            state.detect(0, &lsb),
+            Some(MidiMessageType::ControlChange14 { msb: 7, lsb: 39, .. })
+        ));
+        // Further movement of the pair changes nothing
+        assert!(state.detect(0, &msb).is_none());
+        assert!(state.detect(0, &lsb).is_none());
+    }
+
+    #[test]
+    fn test_learn_ignores_note_release() {
+        let mut state = LearnModeState::new(MidiAction::DeckToggle(0), "Play");
+        assert!(state
+            .detect(2, &MidiMessage::NoteOn { note: 36, velocity: 100 })
+            .is_some());
+        assert!(state
+            .detect(2, &MidiMessage::NoteOff { note: 36, velocity: 0 })
+            .is_none());
+        assert!(matches!(
+            state.detected,
+            Some(MidiMessageType::NoteOn { note: 36, .. })
+        ));
+    }
+
+    #[test]
+    fn test_learn_ignores_resting_stick() {
+        let mut state = LearnModeState::new(MidiAction::CrossfaderPosition, "Crossfader");
+        let axis = |value| MidiMessage::GamepadAxis {
+            axis: "LeftStickX".to_string(),
+            value,
+        };
+        assert!(state.detect(0, &axis(0.55)).is_none());
+        assert_eq!(
+            state.detect(0, &axis(0.9)),
+            Some(MidiMessageType::GamepadAxis {
+                axis: "LeftStickX".to_string()
+            })
+        );
+    }
+
+    #[test]
+    fn test_session_autosave() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join(SESSION_FILE);
+
+        // No session yet: starts empty and saves from then on
+        let mut controller = MidiController::new();
+        controller.restore_session(&path).unwrap();
+        controller.add_mapping(MidiMapping::new(
+            "Crossfader",
+            MidiMessageType::ControlChange {
+                channel: 0,
+                controller: 1,
+            },
+            MidiAction::CrossfaderPosition,
+        ));
+
+        let mut restored = MidiController::new();
+        restored.restore_session(&path).unwrap();
+        assert_eq!(restored.get_mappings().len(), 1);
+
+        restored.clear_mappings();
+        assert!(MidiSession::load(&path).unwrap().mappings.is_empty());
+    }
+
+    #[test]
+    fn test_osc_drives_mappings() {
+        let mut controller = MidiController::new();
+        controller.add_mapping(MidiMapping::new(
+            "Crossfader",
+            MidiMessageType::Osc {
+                address: "/1/fader1".to_string(),
+            },
+            MidiAction::CrossfaderPosition,
+        ));
+        let (tx, rx) = std::sync::mpsc::channel();
+        controller.set_action_callback(move |action, value| {
+            let _ = tx.send((action, value));
+        });
+        let port = controller.enable_osc(0).unwrap();
+        assert_eq!(controller.osc_port(), Some(port));
+
+        let mut packet = b"/1/fader1\0\0\0,f\0\0".to_vec();
+        packet.extend_from_slice(&0.5f32.to_be_bytes());
+        let socket = std::net::UdpSocket::bind("127.0.0.1:0").unwrap();
+        socket.send_to(&packet, ("127.0.0.1", port)).unwrap();
+
+        let received = rx.recv_timeout(Duration::from_secs(2)).unwrap();
+        assert_eq!(received, (MidiAction::CrossfaderPosition, 0.5));
+        controller.disable_osc();
+        assert_eq!(controller.osc_port(), None);
+    }
+
     #[test]
     fn test_load_mappings() {
         let controller = MidiController::new();
@@ -0,0 +1,267 @@
+//! OSC input
+//!
+//! A UDP server decoding Open Sound Control messages (TouchOSC layouts,
+//! lighting consoles) into the same mappings and actions as MIDI. The
+//! address of a message is the control, its first argument the value.
+
+use std::net::UdpSocket;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::thread::{self, JoinHandle};
+use std::time::Duration;
+
+use super::{InputRouter, MidiError, MidiMessage, MidiParser};
+
+/// Default UDP port (where TouchOSC sends unless told otherwise)
+pub const DEFAULT_OSC_PORT: u16 = 8000;
+
+/// How often the server checks whether it should stop
+const POLL_INTERVAL: Duration = Duration::from_millis(100);
+/// Largest UDP payload
+const MAX_PACKET: usize = 65_507;
+/// Deepest bundle nesting decoded
+const MAX_BUNDLE_DEPTH: usize = 8;
+const BUNDLE_TAG: &[u8] = b"#bundle\0";
+
+/// Decode an OSC packet (a message or a bundle of them); malformed parts are
+/// skipped
+pub fn decode_packet(packet: &[u8]) -> Vec<MidiMessage> {
+    let mut messages = Vec::new();
+    decode_into(packet, 0, &mut messages);
+    messages
+}
+
+fn decode_into(packet: &[u8], depth: usize, messages: &mut Vec<MidiMessage>) {
+    if !packet.starts_with(BUNDLE_TAG) {
+        messages.extend(decode_message(packet));
+        return;
+    }
+    if depth >= MAX_BUNDLE_DEPTH {
+        return;
+    }
+
+    // Time tag ignored: bundles apply on arrival
+    let mut pos = BUNDLE_TAG.len() + 8;
+    while let Some(size) = read_u32(packet, &mut pos) {
+        let Some(element) = packet.get(pos..pos + size as usize) else {
+            return;
+        };
+        decode_into(element, depth + 1, messages);
+        pos += size as usize;
+    }
+}
+
+fn decode_message(packet: &[u8]) -> Option<MidiMessage> {
+    let mut pos = 0;
+    let address = read_string(packet, &mut pos)?;
+    if !address.starts_with('/') {
+        return None;
+    }
+    // Messages without arguments (or type tags) are triggers
+    let tags = read_string(packet, &mut pos).unwrap_or_default();
+    let mut value = None;
+    for tag in tags.strip_prefix(',').unwrap_or("").chars() {
+        let arg = match tag {
+            'f' => read_u32(packet, &mut pos).map(|v| Some(f32::from_bits(v))),
+            'd' => read_u64(packet, &mut pos).map(|v| Some(f64::from_bits(v) as f32)),
+            'i' => read_u32(packet, &mut pos).map(|v| Some(int_value(v as i32 as i64))),
+            'h' => read_u64(packet, &mut pos).map(|v| Some(int_value(v as i64))),
+            'T' | 'I' => Some(Some(1.0)),
+            'F' | 'N' => Some(Some(0.0)),
+            's' | 'S' => read_string(packet, &mut pos).map(|_| None),
+            'b' => read_u32(packet, &mut pos).map(|len| {
+                pos += padded(len as usize);
+                None
+            }),
+            'c' | 'r' | 'm' => read_u32(packet, &mut pos).map(|_| None),
+            't' => read_u64(packet, &mut pos).map(|_| None),
+            _ => None,
+        };
+        match arg {
+            Some(Some(v)) => {
+                value = Some(v);
+                break;
+            }
+            Some(None) => {}
+            None => break,
+        }
+    }
+
+    Some(MidiMessage::Osc {
+        address,
+        value: value.map_or(1.0, |v| {
+            if v.is_finite() {
+                v.clamp(0.0, 1.0)
+            } else {
+                0.0
+            }
+        }),
+    })
+}
+
+/// Integer argument as a value: 0 and 1 (toggles) as they are, anything
+/// larger as a 7-bit MIDI-style value
+fn int_value(v: i64) -> f32 {
+    if v <= 1 {
+        v.max(0) as f32
+    } else {
+        v as f32 / 127.0
+    }
+}
+
+/// Length of `len` bytes padded to OSC's 4-byte alignment
+fn padded(len: usize) -> usize {
+    (len + 3) & !3
+}
+
+fn read_string(data: &[u8], pos: &mut usize) -> Option<String> {
+    let rest = data.get(*pos..)?;
+    let end = rest.iter().position(|&b| b == 0)?;
+    let text = std::str::from_utf8(&rest[..end]).ok()?.to_string();
+    *pos += padded(end + 1);
+    Some(text)
+}
+
+fn read_u32(data: &[u8], pos: &mut usize) -> Option<u32> {
+    let bytes = data.get(*pos..*pos + 4)?;
+    *pos += 4;
+    Some(u32::from_be_bytes(bytes.try_into().ok()?))
+}
+
+fn read_u64(data: &[u8], pos: &mut usize) -> Option<u64> {
+    let bytes = data.get(*pos..*pos + 8)?;
+    *pos += 8;
+    Some(u64::from_be_bytes(bytes.try_into().ok()?))
+}
+
+/// OSC server feeding a controller's mappings
+pub struct OscServer {
+    port: u16,
+    running: Arc<AtomicBool>,
+    thread: Option<JoinHandle<()>>,
+}
+
+impl OscServer {
+    /// Listen on `port` (0 picks a free port)
+    pub(crate) fn start(port: u16, mut router: InputRouter) -> Result<Self, MidiError> {
+        let socket = UdpSocket::bind(("0.0.0.0", port))
+            .map_err(|e| MidiError::ConnectionError(format!("OSC port {}: {}", port, e)))?;
+        socket
+            .set_read_timeout(Some(POLL_INTERVAL))
+            .map_err(|e| MidiError::InitError(e.to_string()))?;
+        let port = socket.local_addr().map_or(port, |a| a.port());
+
+        let running = Arc::new(AtomicBool::new(true));
+        let keep_running = Arc::clone(&running);
+        let thread = thread::spawn(move || {
+            let parser = MidiParser::new();
+            let mut buf = vec![0u8; MAX_PACKET];
+            while keep_running.load(Ordering::Relaxed) {
+                match socket.recv(&mut buf) {
+                    Ok(len) => {
+                        for message in decode_packet(&buf[..len]) {
+                            router.route(0, &message, &parser);
+                        }
+                    }
+                    Err(e)
+                        if matches!(
+                            e.kind(),
+                            std::io::ErrorKind::WouldBlock | std::io::ErrorKind::TimedOut
+                        ) => {}
+                    Err(e) => tracing::debug!("OSC receive failed: {}", e),
+                }
+            }
+        });
+
+        tracing::info!("OSC input listening on UDP port {}", port);
+        Ok(Self {
+            port,
+            running,
+            thread: Some(thread),
+        })
+    }
+
+    /// UDP port listened on
+    pub fn port(&self) -> u16 {
+        self.port
+    }
+}
+
+impl Drop for OscServer {
+    fn drop(&mut self) {
+        self.running.store(false, Ordering::Relaxed);
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Encode a message the way OSC senders do
+    fn message(address: &str, tags: &str, args: &[u8]) -> Vec<u8> {
+        let mut packet = Vec::new();
+        for text in [address, tags] {
+            packet.extend_from_slice(text.as_bytes());
+            packet.resize(padded(packet.len() + 1), 0);
+        }
+        packet.extend_from_slice(args);
+        packet
+    }
+
+    #[test]
+    fn test_decode_float_message() {
+        let packet = message("/1/fader1", ",f", &0.25f32.to_be_bytes());
+        assert_eq!(
+            decode_packet(&packet),
+            vec![MidiMessage::Osc {
+                address: "/1/fader1".to_string(),
+                value: 0.25,
+            }]
+        );
+    }
+
+    #[test]
+    fn test_decode_argument_types() {
+        let value = |packet: Vec<u8>| match decode_packet(&packet).as_slice() {
+            [MidiMessage::Osc { value, .. }] => *value,
+            other => panic!("unexpected {:?}", other),
+        };
+        assert_eq!(value(message("/push", ",i", &1i32.to_be_bytes())), 1.0);
+        assert_eq!(value(message("/cc", ",i", &127i32.to_be_bytes())), 1.0);
+        assert_eq!(value(message("/toggle", ",F", &[])), 0.0);
+        assert_eq!(value(message("/go", "", &[])), 1.0);
+        // Leading labels are skipped
+        let mut args = b"deck\0\0\0\0".to_vec();
+        args.extend_from_slice(&0.5f32.to_be_bytes());
+        assert_eq!(value(message("/x", ",sf", &args)), 0.5);
+    }
+
+    #[test]
+    fn test_decode_bundle() {
+        let first = message("/a", ",T", &[]);
+        let second = message("/b", ",f", &1.0f32.to_be_bytes());
+        let mut bundle = BUNDLE_TAG.to_vec();
+        bundle.extend_from_slice(&1u64.to_be_bytes());
+        for element in [&first, &second] {
+            bundle.extend_from_slice(&(element.len() as u32).to_be_bytes());
+            bundle.extend_from_slice(element);
+        }
+
+        let messages = decode_packet(&bundle);
+        assert_eq!(messages.len(), 2);
+        assert!(matches!(&messages[1], MidiMessage::Osc { address, .. } if address == "/b"));
+    }
+
+    #[test]
+    fn test_malformed_packets_are_ignored() {
+        assert!(decode_packet(b"no-slash\0\0\0\0").is_empty());
+        assert!(decode_packet(b"/unterminated").is_empty());
+        let mut bundle = BUNDLE_TAG.to_vec();
+        bundle.extend_from_slice(&[0; 8]);
+        bundle.extend_from_slice(&100u32.to_be_bytes());
+        assert!(decode_packet(&bundle).is_empty());
+    }
+}
@@ -0,0 +1,253 @@
+//! MIDI output
+//!
+//! A virtual output port ("OpenDrop Out") republishing the messages of the
+//! connected controller together with OpenDrop's own events: MIDI clock and
+//! a note on every beat, and a note whenever a deck changes preset. Lighting
+//! software or a DAW can follow along by listening to it.
+
+use std::collections::HashMap;
+use std::time::Instant;
+
+use midir::{MidiOutput, MidiOutputConnection};
+
+use super::MidiError;
+
+/// Name of the virtual output port
+pub const VIRTUAL_OUTPUT_NAME: &str = "OpenDrop Out";
+/// Channel of OpenDrop's own notes (MIDI channel 16)
+pub const EVENT_CHANNEL: u8 = 15;
+/// Note played on every beat
+pub const BEAT_NOTE: u8 = 0;
+/// Note played when deck 1 changes preset; deck N plays this + N - 1
+pub const PRESET_CHANGE_NOTE: u8 = 8;
+
+/// MIDI clock pulses per beat
+const CLOCK_PPQN: f64 = 24.0;
+/// Most clock pulses sent at once, so a stall doesn't end in a burst
+const MAX_PULSES_PER_TICK: u32 = 24;
+const CLOCK_PULSE: u8 = 0xF8;
+const CLOCK_START: u8 = 0xFA;
+const CLOCK_STOP: u8 = 0xFC;
+
+/// Event published on the output port
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OutputEvent {
+    /// A beat of the tempo grid
+    Beat,
+    /// A deck loaded another preset
+    PresetChanged { deck: u8 },
+}
+
+impl OutputEvent {
+    /// Note On and Note Off of the note announcing the event
+    pub fn messages(&self) -> [[u8; 3]; 2] {
+        let note = match self {
+            OutputEvent::Beat => BEAT_NOTE,
+            OutputEvent::PresetChanged { deck } => PRESET_CHANGE_NOTE + deck,
+        };
+        [
+            [0x90 | EVENT_CHANNEL, note, 127],
+            [0x80 | EVENT_CHANNEL, note, 0],
+        ]
+    }
+}
+
+/// MIDI clock (24 pulses per beat) following the tempo
+#[derive(Debug, Clone, Default)]
+pub struct MidiClock {
+    /// Time of the previous update
+    last: Option<Instant>,
+    /// Pulses due but not yet sent
+    phase: f64,
+    running: bool,
+}
+
+impl MidiClock {
+    /// Pulses to send for the time since the previous update; `None` (and
+    /// the clock stops) while there is no tempo
+    pub fn advance(&mut self, bpm: f32, now: Instant) -> Option<u32> {
+        if bpm <= 0.0 {
+            *self = Self::default();
+            return None;
+        }
+
+        let elapsed = self.last.map_or(0.0, |last| {
+            now.saturating_duration_since(last).as_secs_f64()
+        });
+        self.last = Some(now);
+        self.phase += elapsed * bpm as f64 / 60.0 * CLOCK_PPQN;
+
+        // Rounding must not hold back a pulse that is due
+        let pulses = (self.phase + 1e-9).floor();
+        self.phase = (self.phase - pulses).max(0.0);
+        Some((pulses as u32).min(MAX_PULSES_PER_TICK))
+    }
+}
+
+/// Open output port
+pub struct MidiOut {
+    connection: MidiOutputConnection,
+    name: String,
+    clock: MidiClock,
+    /// Last preset announced per deck
+    presets: HashMap<u8, String>,
+}
+
+impl MidiOut {
+    /// Create the virtual "OpenDrop Out" port
+    #[cfg(unix)]
+    pub fn open_virtual() -> Result<Self, MidiError> {
+        use midir::os::unix::VirtualOutput;
+
+        let output =
+            MidiOutput::new("OpenDrop").map_err(|e| MidiError::InitError(e.to_string()))?;
+        let connection = output
+            .create_virtual(VIRTUAL_OUTPUT_NAME)
+            .map_err(|e| MidiError::ConnectionError(e.to_string()))?;
+        Ok(Self::new(connection, VIRTUAL_OUTPUT_NAME.to_string()))
+    }
+
+    /// Virtual ports need CoreMIDI or ALSA
+    #[cfg(not(unix))]
+    pub fn open_virtual() -> Result<Self, MidiError> {
+        Err(MidiError::VirtualPortUnsupported)
+    }
+
+    /// Send to an existing output port by name instead (e.g. a loopMIDI
+    /// port on Windows)
+    pub fn open_port(name: &str) -> Result<Self, MidiError> {
+        let output =
+            MidiOutput::new("OpenDrop").map_err(|e| MidiError::InitError(e.to_string()))?;
+        let port = output
+            .ports()
+            .into_iter()
+            .find(|p| output.port_name(p).is_ok_and(|n| n == name))
+            .ok_or_else(|| MidiError::DeviceNotFound(name.to_string()))?;
+        let connection = output
+            .connect(&port, VIRTUAL_OUTPUT_NAME)
+            .map_err(|e| MidiError::ConnectionError(e.to_string()))?;
+        Ok(Self::new(connection, name.to_string()))
+    }
+
+    fn new(connection: MidiOutputConnection, name: String) -> Self {
+        Self {
+            connection,
+            name,
+            clock: MidiClock::default(),
+            presets: HashMap::new(),
+        }
+    }
+
+    /// Name of the port
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Send a raw message
+    pub fn send(&mut self, data: &[u8]) {
+        if let Err(e) = self.connection.send(data) {
+            tracing::debug!("Failed to send MIDI output: {}", e);
+        }
+    }
+
+    /// Publish an event
+    pub fn send_event(&mut self, event: OutputEvent) {
+        for message in event.messages() {
+            self.send(&message);
+        }
+    }
+
+    /// Keep the MIDI clock going at `bpm` (no clock while it is 0)
+    pub fn tick(&mut self, bpm: f32, now: Instant) {
+        let was_running = self.clock.running;
+        match self.clock.advance(bpm, now) {
+            Some(pulses) => {
+                if !was_running {
+                    self.clock.running = true;
+                    self.send(&[CLOCK_START]);
+                }
+                for _ in 0..pulses {
+                    self.send(&[CLOCK_PULSE]);
+                }
+            }
+            None if was_running => self.send(&[CLOCK_STOP]),
+            None => {}
+        }
+    }
+
+    /// Announce a deck's preset if it changed since the last call
+    pub fn update_preset(&mut self, deck: u8, preset: Option<&str>) {
+        let Some(preset) = preset else {
+            self.presets.remove(&deck);
+            return;
+        };
+        if self.presets.get(&deck).is_some_and(|last| last == preset) {
+            return;
+        }
+        self.presets.insert(deck, preset.to_string());
+        self.send_event(OutputEvent::PresetChanged { deck });
+    }
+}
+
+impl Drop for MidiOut {
+    fn drop(&mut self) {
+        if self.clock.running {
+            self.send(&[CLOCK_STOP]);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    #[test]
+    fn test_event_notes() {
+        assert_eq!(
+            OutputEvent::Beat.messages(),
+            [[0x9F, BEAT_NOTE, 127], [0x8F, BEAT_NOTE, 0]]
+        );
+        let [on, _] = OutputEvent::PresetChanged { deck: 2 }.messages();
+        assert_eq!(on[1], PRESET_CHANGE_NOTE + 2);
+    }
+
+    #[test]
+    fn test_clock_pulses_follow_tempo() {
+        let start = Instant::now();
+        let mut clock = MidiClock::default();
+        assert_eq!(clock.advance(120.0, start), Some(0));
+
+        // 120 BPM is 48 pulses per second; sent a few at a time
+        let mut pulses = 0;
+        for ms in (10..=1000).step_by(10) {
+            pulses += clock
+                .advance(120.0, start + Duration::from_millis(ms))
+                .unwrap();
+        }
+        assert_eq!(pulses, 48);
+    }
+
+    #[test]
+    fn test_clock_stops_without_tempo() {
+        let start = Instant::now();
+        let mut clock = MidiClock::default();
+        clock.advance(120.0, start);
+        assert_eq!(clock.advance(0.0, start + Duration::from_secs(1)), None);
+
+        // Restarts from scratch rather than catching up
+        assert_eq!(
+            clock.advance(120.0, start + Duration::from_secs(5)),
+            Some(0)
+        );
+    }
+
+    #[test]
+    fn test_clock_limits_bursts() {
+        let start = Instant::now();
+        let mut clock = MidiClock::default();
+        clock.advance(120.0, start);
+        let pulses = clock.advance(120.0, start + Duration::from_secs(10));
+        assert_eq!(pulses, Some(MAX_PULSES_PER_TICK));
+    }
+}
@@ -48,6 +48,44 @@ impl MidiPreset {
     }
 }
 
+/// File in the presets directory holding the last session's mappings and port
+pub const SESSION_FILE: &str = "session.json";
+
+/// Mappings and port restored when the app starts
+#[derive(Debug, Clone, Default, serde::Serialize, serde::Deserialize)]
+pub struct MidiSession {
+    /// Port to reconnect to
+    #[serde(default)]
+    pub port_name: Option<String>,
+    #[serde(default)]
+    pub mappings: Vec<MidiMapping>,
+}
+
+impl MidiSession {
+    /// Default session file location
+    pub fn default_path() -> Option<PathBuf> {
+        presets_dir().map(|d| d.join(SESSION_FILE))
+    }
+
+    /// Save the session, creating its directory if needed
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<(), std::io::Error> {
+        let path = path.as_ref();
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        let json = serde_json::to_string_pretty(self)
+            .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidData, e))?;
+        fs::write(path, json)
+    }
+
+    /// Load a saved session
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, std::io::Error> {
+        let json = fs::read_to_string(path)?;
+        serde_json::from_str(&json)
+            .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidData, e))
+    }
+}
+
 /// Get the default MIDI presets directory
 pub fn presets_dir() -> Option<PathBuf> {
     dirs::config_dir().map(|d| d.join("opendrop").join("midi"))
@@ -70,11 +108,32 @@ pub fn list_presets() -> Vec<PathBuf> {
                 .filter_map(|e| e.ok())
                 .map(|e| e.path())
                 .filter(|p| p.extension().is_some_and(|ext| ext == "json"))
+                .filter(|p| p.file_name().is_some_and(|name| name != SESSION_FILE))
                 .collect()
         })
         .unwrap_or_default()
 }
 
+/// Get the directory of playlist banks switched by Program Change
+pub fn playlist_banks_dir() -> Option<PathBuf> {
+    dirs::config_dir().map(|d| d.join("opendrop").join("banks"))
+}
+
+/// Playlist files in `dir`, sorted by name; bank N is the Nth file
+pub fn list_playlist_banks(dir: impl AsRef<Path>) -> Vec<PathBuf> {
+    let mut banks: Vec<PathBuf> = fs::read_dir(dir)
+        .map(|entries| {
+            entries
+                .filter_map(|e| e.ok())
+                .map(|e| e.path())
+                .filter(|p| p.extension().is_some_and(|ext| ext == "json"))
+                .collect()
+        })
+        .unwrap_or_default();
+    banks.sort();
+    banks
+}
+
 /// Create a default preset for common DJ controllers
 pub fn create_generic_dj_preset() -> MidiPreset {
     let mut preset = MidiPreset::new("Generic DJ Controller");
@@ -280,6 +339,60 @@ pub fn create_launchpad_preset() -> MidiPreset {
     preset
 }
 
+/// Layout of a grid of pads: `columns` pads per row, rows `row_stride` notes apart
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub struct PadGrid {
+    pub channel: u8,
+    /// Note of the top-left pad
+    pub first_note: u8,
+    pub columns: u8,
+    pub rows: u8,
+    pub row_stride: u8,
+}
+
+impl PadGrid {
+    /// Novation Launchpad 8x8 grid (rows 16 notes apart, note 0 top-left)
+    pub fn launchpad() -> Self {
+        Self {
+            channel: 0,
+            first_note: 0,
+            columns: 8,
+            rows: 8,
+            row_stride: 16,
+        }
+    }
+
+    /// Pad notes in playlist order: left to right, top row first
+    pub fn notes(&self) -> Vec<u8> {
+        (0..self.rows as u16)
+            .flat_map(|row| {
+                (0..self.columns as u16)
+                    .map(move |col| self.first_note as u16 + row * self.row_stride as u16 + col)
+            })
+            .take_while(|&note| note < 128)
+            .map(|note| note as u8)
+            .collect()
+    }
+}
+
+/// Map each pad of a grid to a playlist position of `deck`
+pub fn create_pad_bank_mappings(grid: &PadGrid, deck: u8) -> Vec<MidiMapping> {
+    grid.notes()
+        .into_iter()
+        .enumerate()
+        .map(|(index, note)| {
+            MidiMapping::new(
+                format!("Deck {} Pad {}", deck + 1, index + 1),
+                MidiMessageType::NoteOn {
+                    channel: grid.channel,
+                    note,
+                },
+                MidiAction::LoadPlaylistIndex { deck, index },
+            )
+        })
+        .collect()
+}
+
 /// Create a Korg nanoKONTROL2 preset
 pub fn create_nanokontrol2_preset() -> MidiPreset {
     let mut preset = MidiPreset::new("Korg nanoKONTROL2");
@@ -361,6 +474,295 @@ pub fn create_nanokontrol2_preset() -> MidiPreset {
     preset
 }
 
+/// Create a Pioneer DDJ-400 / DDJ-FLX4 preset (2 decks)
+pub fn create_ddj400_preset() -> MidiPreset {
+    let mut preset = MidiPreset::new("Pioneer DDJ-400/FLX4");
+    preset.description = "Mapping for Pioneer DDJ-400 and DDJ-FLX4".to_string();
+    preset.controller = "Pioneer DDJ-400".to_string();
+
+    // Each deck sends on its own channel (0 = left, 1 = right); faders and
+    // knobs are 14-bit CC pairs
+    for deck in 0u8..2 {
+        preset.mappings.push(MidiMapping::new(
+            format!("Deck {} Volume", deck + 1),
+            MidiMessageType::ControlChange14 {
+                channel: deck,
+                msb: 19,
+                lsb: 51,
+            },
+            MidiAction::DeckVolume(deck),
+        ));
+
+        // Trim knob
+        preset.mappings.push(MidiMapping::new(
+            format!("Deck {} Beat Sens", deck + 1),
+            MidiMessageType::ControlChange14 {
+                channel: deck,
+                msb: 4,
+                lsb: 36,
+            },
+            MidiAction::DeckBeatSensitivity(deck),
+        ));
+
+        // Play/Pause
+        preset.mappings.push(MidiMapping::new(
+            format!("Deck {} Toggle", deck + 1),
+            MidiMessageType::NoteOn {
+                channel: deck,
+                note: 11,
+            },
+            MidiAction::DeckToggle(deck),
+        ));
+
+        // Cue
+        preset.mappings.push(MidiMapping::new(
+            format!("Deck {} Next", deck + 1),
+            MidiMessageType::NoteOn {
+                channel: deck,
+                note: 12,
+            },
+            MidiAction::NextPreset(deck),
+        ));
+
+        // Beat Sync
+        preset.mappings.push(MidiMapping::new(
+            format!("Deck {} Random", deck + 1),
+            MidiMessageType::NoteOn {
+                channel: deck,
+                note: 88,
+            },
+            MidiAction::RandomPreset(deck),
+        ));
+
+        // CFX knobs on the mixer channel (6)
+        preset.mappings.push(MidiMapping::new(
+            format!("Deck {} Opacity", deck + 1),
+            MidiMessageType::ControlChange14 {
+                channel: 6,
+                msb: 23 + deck,
+                lsb: 55 + deck,
+            },
+            MidiAction::CompositorDeckOpacity(deck),
+        ));
+
+        // Hot cue pads: channel 7 (left) and 9 (right) -> playlist positions
+        for pad in 0u8..8 {
+            preset.mappings.push(MidiMapping::new(
+                format!("Deck {} Pad {}", deck + 1, pad + 1),
+                MidiMessageType::NoteOn {
+                    channel: 7 + deck * 2,
+                    note: pad,
+                },
+                MidiAction::LoadPlaylistIndex {
+                    deck,
+                    index: pad as usize,
+                },
+            ));
+        }
+    }
+
+    preset.mappings.push(MidiMapping::new(
+        "Crossfader",
+        MidiMessageType::ControlChange14 {
+            channel: 6,
+            msb: 31,
+            lsb: 63,
+        },
+        MidiAction::CrossfaderPosition,
+    ));
+
+    preset
+}
+
+/// Create a Native Instruments Traktor Kontrol S2/X1 preset (2 decks)
+pub fn create_traktor_kontrol_preset() -> MidiPreset {
+    let mut preset = MidiPreset::new("Traktor Kontrol S2/X1");
+    preset.description =
+        "Mapping for Traktor Kontrol S2/X1 in MIDI mode (Controller Editor)".to_string();
+    preset.controller = "Traktor Kontrol".to_string();
+
+    // MIDI mode on channel 0: FX knobs CC 1-4 (left) and 5-8 (right),
+    // S2 channel faders CC 9/10 and crossfader CC 11, buttons as notes
+    for deck in 0u8..2 {
+        // First FX knob
+        preset.mappings.push(MidiMapping::new(
+            format!("Deck {} Beat Sens", deck + 1),
+            MidiMessageType::ControlChange {
+                channel: 0,
+                controller: 1 + deck * 4,
+            },
+            MidiAction::DeckBeatSensitivity(deck),
+        ));
+
+        // Dry/Wet knob
+        preset.mappings.push(MidiMapping::new(
+            format!("Deck {} Opacity", deck + 1),
+            MidiMessageType::ControlChange {
+                channel: 0,
+                controller: 4 + deck * 4,
+            },
+            MidiAction::CompositorDeckOpacity(deck),
+        ));
+
+        preset.mappings.push(MidiMapping::new(
+            format!("Deck {} Volume", deck + 1),
+            MidiMessageType::ControlChange {
+                channel: 0,
+                controller: 9 + deck,
+            },
+            MidiAction::DeckVolume(deck),
+        ));
+
+        // Play: notes 36/37
+        preset.mappings.push(MidiMapping::new(
+            format!("Deck {} Toggle", deck + 1),
+            MidiMessageType::NoteOn {
+                channel: 0,
+                note: 36 + deck,
+            },
+            MidiAction::DeckToggle(deck),
+        ));
+
+        // Cue: notes 38/39
+        preset.mappings.push(MidiMapping::new(
+            format!("Deck {} Next", deck + 1),
+            MidiMessageType::NoteOn {
+                channel: 0,
+                note: 38 + deck,
+            },
+            MidiAction::NextPreset(deck),
+        ));
+
+        // Sync: notes 40/41
+        preset.mappings.push(MidiMapping::new(
+            format!("Deck {} Random", deck + 1),
+            MidiMessageType::NoteOn {
+                channel: 0,
+                note: 40 + deck,
+            },
+            MidiAction::RandomPreset(deck),
+        ));
+
+        // Hot cues 1-4: notes 42-45 (left) and 46-49 (right)
+        for pad in 0u8..4 {
+            preset.mappings.push(MidiMapping::new(
+                format!("Deck {} Cue {}", deck + 1, pad + 1),
+                MidiMessageType::NoteOn {
+                    channel: 0,
+                    note: 42 + deck * 4 + pad,
+                },
+                MidiAction::LoadPlaylistIndex {
+                    deck,
+                    index: pad as usize,
+                },
+            ));
+        }
+    }
+
+    preset.mappings.push(MidiMapping::new(
+        "Crossfader",
+        MidiMessageType::ControlChange {
+            channel: 0,
+            controller: 11,
+        },
+        MidiAction::CrossfaderPosition,
+    ));
+
+    preset
+}
+
+/// Create a DJ TechTools MIDI Fighter Twister preset (4 decks)
+pub fn create_midi_fighter_twister_preset() -> MidiPreset {
+    let mut preset = MidiPreset::new("MIDI Fighter Twister");
+    preset.description = "Mapping for DJ TechTools MIDI Fighter Twister".to_string();
+    preset.controller = "MIDI Fighter Twister".to_string();
+
+    // Bank 1: encoders send CC 0-15 on channel 0, their push switches the
+    // same CCs on channel 1. One row of four encoders per deck.
+    for deck in 0u8..4 {
+        let row = deck * 4;
+
+        preset.mappings.push(MidiMapping::new(
+            format!("Deck {} Volume", deck + 1),
+            MidiMessageType::ControlChange {
+                channel: 0,
+                controller: row,
+            },
+            MidiAction::DeckVolume(deck),
+        ));
+        preset.mappings.push(MidiMapping::new(
+            format!("Deck {} Beat Sens", deck + 1),
+            MidiMessageType::ControlChange {
+                channel: 0,
+                controller: row + 1,
+            },
+            MidiAction::DeckBeatSensitivity(deck),
+        ));
+        preset.mappings.push(MidiMapping::new(
+            format!("Deck {} Opacity", deck + 1),
+            MidiMessageType::ControlChange {
+                channel: 0,
+                controller: row + 2,
+            },
+            MidiAction::CompositorDeckOpacity(deck),
+        ));
+
+        // Push switches
+        preset.mappings.push(MidiMapping::new(
+            format!("Deck {} Toggle", deck + 1),
+            MidiMessageType::ControlChange {
+                channel: 1,
+                controller: row,
+            },
+            MidiAction::DeckToggle(deck),
+        ));
+        preset.mappings.push(MidiMapping::new(
+            format!("Deck {} Next", deck + 1),
+            MidiMessageType::ControlChange {
+                channel: 1,
+                controller: row + 1,
+            },
+            MidiAction::NextPreset(deck),
+        ));
+        preset.mappings.push(MidiMapping::new(
+            format!("Deck {} Layer Toggle", deck + 1),
+            MidiMessageType::ControlChange {
+                channel: 1,
+                controller: row + 2,
+            },
+            MidiAction::CompositorDeckToggle(deck),
+        ));
+        preset.mappings.push(MidiMapping::new(
+            format!("Deck {} Random", deck + 1),
+            MidiMessageType::ControlChange {
+                channel: 1,
+                controller: row + 3,
+            },
+            MidiAction::RandomPreset(deck),
+        ));
+    }
+
+    // Last column: crossfader and master volume
+    preset.mappings.push(MidiMapping::new(
+        "Crossfader",
+        MidiMessageType::ControlChange {
+            channel: 0,
+            controller: 3,
+        },
+        MidiAction::CrossfaderPosition,
+    ));
+    preset.mappings.push(MidiMapping::new(
+        "Master Volume",
+        MidiMessageType::ControlChange {
+            channel: 0,
+            controller: 7,
+        },
+        MidiAction::MasterVolume,
+    ));
+
+    preset
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -400,6 +802,35 @@ mod tests {
         assert_eq!(loaded.mappings[0].name, "Test Mapping");
     }
 
+    #[test]
+    fn test_session_save_load() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("midi").join(SESSION_FILE);
+
+        let session = MidiSession {
+            port_name: Some("APC MINI".to_string()),
+            mappings: create_apc_mini_preset().mappings,
+        };
+        session.save(&path).unwrap();
+
+        let loaded = MidiSession::load(&path).unwrap();
+        assert_eq!(loaded.port_name.as_deref(), Some("APC MINI"));
+        assert_eq!(loaded.mappings.len(), session.mappings.len());
+    }
+
+    #[test]
+    fn test_playlist_banks_sorted_by_name() {
+        let dir = tempfile::tempdir().unwrap();
+        for name in ["02 Peak.json", "01 Warmup.json", "notes.txt"] {
+            fs::write(dir.path().join(name), "{}").unwrap();
+        }
+
+        let banks = list_playlist_banks(dir.path());
+        let names: Vec<_> = banks.iter().filter_map(|p| p.file_name()).collect();
+        assert_eq!(names, ["01 Warmup.json", "02 Peak.json"]);
+        assert!(list_playlist_banks(dir.path().join("missing")).is_empty());
+    }
+
     #[test]
     fn test_generic_dj_preset() {
         let preset = create_generic_dj_preset();
@@ -421,6 +852,79 @@ mod tests {
         assert_eq!(preset.controller, "Novation Launchpad");
     }
 
+    #[test]
+    fn test_ddj400_preset() {
+        let preset = create_ddj400_preset();
+        assert_eq!(preset.mappings.len(), 29);
+        assert_eq!(preset.controller, "Pioneer DDJ-400");
+        let crossfader = preset.mappings.iter().find(|m| m.name == "Crossfader").unwrap();
+        assert!(matches!(
+            crossfader.midi_message,
+            MidiMessageType::ControlChange14 { msb: 31, lsb: 63, .. }
+        ));
+
+        // Right deck pads on channel 9
+        let pad = preset.mappings.iter().find(|m| m.name == "Deck 2 Pad 1").unwrap();
+        assert_eq!(
+            pad.midi_message,
+            MidiMessageType::NoteOn {
+                channel: 9,
+                note: 0
+            }
+        );
+    }
+
+    #[test]
+    fn test_traktor_kontrol_preset() {
+        let preset = create_traktor_kontrol_preset();
+        assert_eq!(preset.mappings.len(), 21);
+        assert_eq!(preset.controller, "Traktor Kontrol");
+    }
+
+    #[test]
+    fn test_midi_fighter_twister_preset() {
+        let preset = create_midi_fighter_twister_preset();
+        assert_eq!(preset.mappings.len(), 30);
+        assert_eq!(preset.controller, "MIDI Fighter Twister");
+
+        // Every encoder and push switch is used at most once
+        let controls: Vec<_> = preset.mappings.iter().map(|m| &m.midi_message).collect();
+        for (i, control) in controls.iter().enumerate() {
+            assert!(!controls[i + 1..].contains(control), "{} mapped twice", control);
+        }
+    }
+
+    #[test]
+    fn test_launchpad_pad_bank() {
+        let mappings = create_pad_bank_mappings(&PadGrid::launchpad(), 1);
+        assert_eq!(mappings.len(), 64);
+
+        // Second row starts 16 notes in, at playlist position 8
+        assert_eq!(
+            mappings[8].midi_message,
+            MidiMessageType::NoteOn {
+                channel: 0,
+                note: 16
+            }
+        );
+        assert_eq!(
+            mappings[8].action,
+            MidiAction::LoadPlaylistIndex { deck: 1, index: 8 }
+        );
+    }
+
+    #[test]
+    fn test_pad_grid_stops_at_last_note() {
+        let grid = PadGrid {
+            channel: 0,
+            first_note: 120,
+            columns: 4,
+            rows: 4,
+            row_stride: 4,
+        };
+        assert_eq!(grid.notes(), (120..128).collect::<Vec<u8>>());
+    }
+
     #[test]
     fn test_nanokontrol2_preset() {
         let preset = create_nanokontrol2_preset();
@@ -0,0 +1,156 @@
+//! Adaptive render resolution
+//!
+//! When frames run over budget, projectM renders at a smaller internal size
+//! that is stretched to the frame, and steps back up once the load allows.
+//! Decisions are made on each stats report, so one slow frame changes
+//! nothing.
+
+/// Scales of the render size stepped through, largest first
+pub const SCALE_STEPS: [f32; 5] = [1.0, 0.85, 0.7, 0.55, 0.4];
+
+/// Frame time, as a share of the budget, above which the scale steps down
+const OVER_BUDGET: f32 = 1.15;
+
+/// Frame time, as a share of the budget, that counts as on target
+const ON_BUDGET: f32 = 1.05;
+
+/// Reports on target before trying the next scale up
+const STEP_UP_AFTER: u32 = 3;
+
+/// Longest wait, in reports, after step ups that had to be undone
+const MAX_STEP_UP_AFTER: u32 = 60;
+
+/// Picks the render scale from frame times
+///
+/// Frame times at vsync can't show how much headroom is left, so stepping
+/// up is a probe: if the larger size runs over budget straight away it is
+/// undone, and the next try waits twice as long.
+#[derive(Debug, Clone)]
+pub struct AdaptiveScale {
+    step: usize,
+    /// Reports on target in a row
+    on_target: u32,
+    /// Reports on target needed before stepping up
+    step_up_after: u32,
+    /// The last change was a step up, not yet confirmed by a report
+    probing: bool,
+}
+
+impl Default for AdaptiveScale {
+    fn default() -> Self {
+        Self {
+            step: 0,
+            on_target: 0,
+            step_up_after: STEP_UP_AFTER,
+            probing: false,
+        }
+    }
+}
+
+impl AdaptiveScale {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Share of the frame size to render at
+    pub fn scale(&self) -> f32 {
+        SCALE_STEPS[self.step]
+    }
+
+    /// Take a report's average frame time against the `budget_ms` a frame
+    /// may take; returns the new scale when it changes
+    pub fn update(&mut self, frame_time_ms: f32, budget_ms: f32) -> Option<f32> {
+        let load = frame_time_ms / budget_ms;
+        let probing = std::mem::take(&mut self.probing);
+
+        if load > OVER_BUDGET {
+            self.on_target = 0;
+            if probing {
+                self.step_up_after = (self.step_up_after * 2).min(MAX_STEP_UP_AFTER);
+            }
+            if self.step + 1 == SCALE_STEPS.len() {
+                return None;
+            }
+            self.step += 1;
+            return Some(self.scale());
+        }
+
+        if probing {
+            self.step_up_after = STEP_UP_AFTER;
+        }
+        if load > ON_BUDGET {
+            self.on_target = 0;
+            return None;
+        }
+        self.on_target += 1;
+        if self.step == 0 || self.on_target < self.step_up_after {
+            return None;
+        }
+        self.on_target = 0;
+        self.step -= 1;
+        self.probing = true;
+        Some(self.scale())
+    }
+}
+
+/// Scale a render size, keeping at least a pixel per side
+pub fn scale_size((width, height): (u32, u32), scale: f32) -> (u32, u32) {
+    if scale >= 1.0 {
+        return (width, height);
+    }
+    let scaled = |side: u32| ((side as f32 * scale).round() as u32).max(1);
+    (scaled(width), scaled(height))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const BUDGET: f32 = 1000.0 / 60.0;
+
+    #[test]
+    fn test_steps_down_over_budget() {
+        let mut adaptive = AdaptiveScale::new();
+        assert_eq!(adaptive.update(BUDGET, BUDGET), None);
+        assert_eq!(adaptive.update(33.3, BUDGET), Some(0.85));
+        assert_eq!(adaptive.update(25.0, BUDGET), Some(0.7));
+        // Slightly slow frames aren't worth a step
+        assert_eq!(adaptive.update(BUDGET * 1.1, BUDGET), None);
+
+        for _ in 0..10 {
+            adaptive.update(100.0, BUDGET);
+        }
+        assert_eq!(adaptive.scale(), 0.4);
+    }
+
+    #[test]
+    fn test_steps_up_with_backoff() {
+        let mut adaptive = AdaptiveScale::new();
+        adaptive.update(33.3, BUDGET);
+        adaptive.update(33.3, BUDGET);
+        assert_eq!(adaptive.scale(), 0.7);
+
+        assert_eq!(adaptive.update(BUDGET, BUDGET), None);
+        assert_eq!(adaptive.update(BUDGET, BUDGET), None);
+        assert_eq!(adaptive.update(BUDGET, BUDGET), Some(0.85));
+        // Too slow at the larger size: undone, and the next try waits longer
+        assert_eq!(adaptive.update(30.0, BUDGET), Some(0.7));
+        for _ in 0..5 {
+            assert_eq!(adaptive.update(BUDGET, BUDGET), None);
+        }
+        assert_eq!(adaptive.update(BUDGET, BUDGET), Some(0.85));
+
+        // This one holds, so the wait is back to normal
+        assert_eq!(adaptive.update(BUDGET, BUDGET), None);
+        assert_eq!(adaptive.update(BUDGET, BUDGET), None);
+        assert_eq!(adaptive.update(BUDGET, BUDGET), Some(1.0));
+        assert_eq!(adaptive.update(8.0, BUDGET), None);
+    }
+
+    #[test]
+    fn test_scale_size() {
+        assert_eq!(scale_size((1920, 1080), 1.0), (1920, 1080));
+        assert_eq!(scale_size((1920, 1080), 0.5), (960, 540));
+        assert_eq!(scale_size((1, 1), 0.4), (1, 1));
+    }
+}
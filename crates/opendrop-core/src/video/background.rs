@@ -0,0 +1,523 @@
+//! Background layer behind the visualization
+//!
+//! Venues often keep a branded backdrop on screen while the visuals play.
+//! A PNG is decoded once; other stills (JPEG, WebP, ...) and videos go
+//! through an `ffmpeg` process, videos looped at their own frame rate. The
+//! renderer draws the picture under projectM's output, showing through by
+//! the preset's alpha or by adding light.
+
+use std::io::Read;
+use std::path::{Path, PathBuf};
+use std::process::{Child, Command, Stdio};
+use std::thread::{self, JoinHandle};
+
+use crossbeam_channel::{bounded, Receiver, Sender, TryRecvError, TrySendError};
+use serde::{Deserialize, Serialize};
+
+use super::input::{fit_rect, InputFrame};
+use super::output::VideoOutputError;
+
+/// Longest side stills and videos are decoded at; larger ones are scaled down
+pub const MAX_DECODE_SIZE: u32 = 1920;
+
+/// Stills that ffmpeg decodes once instead of looping
+const STILL_EXTENSIONS: [&str; 6] = ["jpg", "jpeg", "webp", "bmp", "tif", "tiff"];
+
+/// How the visualization is laid over the background
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum BackgroundBlend {
+    /// The background shows where the preset draws with transparency
+    Alpha,
+    /// Add the visualization's light to the background
+    Add,
+    /// Lighten: the background shows through where the visualization is dark
+    #[default]
+    Screen,
+}
+
+impl std::str::FromStr for BackgroundBlend {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "alpha" => Ok(BackgroundBlend::Alpha),
+            "add" | "additive" => Ok(BackgroundBlend::Add),
+            "screen" => Ok(BackgroundBlend::Screen),
+            _ => Err(format!(
+                "Unknown background blend: {}. Use: alpha, add, screen",
+                s
+            )),
+        }
+    }
+}
+
+/// How the background is sized to the frame
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum BackgroundFit {
+    /// Fill the frame, cropping what doesn't fit
+    #[default]
+    Cover,
+    /// Show the whole picture, with bars
+    Contain,
+    /// Fill the frame, ignoring the aspect ratio
+    Stretch,
+}
+
+impl BackgroundFit {
+    /// Quad for a `src_width`x`src_height` picture in a
+    /// `dst_width`x`dst_height` frame, as normalized device coordinates
+    /// `(x0, y0, x1, y1)`; covering quads reach past the frame edges
+    pub fn rect(
+        self,
+        src_width: u32,
+        src_height: u32,
+        dst_width: u32,
+        dst_height: u32,
+    ) -> (f32, f32, f32, f32) {
+        match self {
+            BackgroundFit::Stretch => (-1.0, -1.0, 1.0, 1.0),
+            BackgroundFit::Contain => fit_rect(src_width, src_height, dst_width, dst_height),
+            BackgroundFit::Cover => {
+                let (x0, y0, x1, y1) = fit_rect(src_width, src_height, dst_width, dst_height);
+                // Grow the letterboxed quad until its short side fills the frame
+                let grow = 1.0 / x1.min(y1);
+                (x0 * grow, y0 * grow, x1 * grow, y1 * grow)
+            }
+        }
+    }
+}
+
+/// Background layer settings
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct BackgroundConfig {
+    /// Image or video file
+    pub path: PathBuf,
+    #[serde(default)]
+    pub blend: BackgroundBlend,
+    #[serde(default)]
+    pub fit: BackgroundFit,
+    /// 0 (hidden) to 1 (as decoded)
+    #[serde(default = "default_opacity")]
+    pub opacity: f32,
+}
+
+fn default_opacity() -> f32 {
+    1.0
+}
+
+impl Default for BackgroundConfig {
+    fn default() -> Self {
+        Self {
+            path: PathBuf::new(),
+            blend: BackgroundBlend::default(),
+            fit: BackgroundFit::default(),
+            opacity: default_opacity(),
+        }
+    }
+}
+
+impl BackgroundConfig {
+    /// Reject an out-of-range opacity
+    pub fn validate(&self) -> Result<(), VideoOutputError> {
+        if !(0.0..=1.0).contains(&self.opacity) {
+            return Err(VideoOutputError::InitError(format!(
+                "Invalid background opacity {}: use 0-1",
+                self.opacity
+            )));
+        }
+        Ok(())
+    }
+}
+
+/// How a background file is decoded
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BackgroundKind {
+    /// Decoded once, without ffmpeg
+    Png,
+    /// Decoded once by ffmpeg
+    Still,
+    /// Looped by ffmpeg
+    Video,
+}
+
+impl BackgroundKind {
+    /// Kind of `path`, by its extension
+    pub fn of(path: &Path) -> Self {
+        let extension = path
+            .extension()
+            .map(|ext| ext.to_string_lossy().to_lowercase())
+            .unwrap_or_default();
+        if extension == "png" {
+            BackgroundKind::Png
+        } else if STILL_EXTENSIONS.contains(&extension.as_str()) {
+            BackgroundKind::Still
+        } else {
+            BackgroundKind::Video
+        }
+    }
+}
+
+/// Size to decode a `width`x`height` picture at: scaled down, keeping the
+/// aspect ratio, so the longest side fits [`MAX_DECODE_SIZE`]
+pub fn decode_size(width: u32, height: u32) -> (u32, u32) {
+    let longest = width.max(height);
+    if longest <= MAX_DECODE_SIZE {
+        return (width, height);
+    }
+    let scale = MAX_DECODE_SIZE as f64 / longest as f64;
+    let scaled = |side: u32| ((side as f64 * scale).round() as u32).max(1);
+    (scaled(width), scaled(height))
+}
+
+/// ffprobe arguments printing the first video stream's size as `WxH`
+fn probe_args(path: &Path) -> Vec<String> {
+    let mut args: Vec<String> = [
+        "-v",
+        "error",
+        "-select_streams",
+        "v:0",
+        "-show_entries",
+        "stream=width,height",
+        "-of",
+        "csv=s=x:p=0",
+    ]
+    .iter()
+    .map(|s| s.to_string())
+    .collect();
+    args.push(path.to_string_lossy().to_string());
+    args
+}
+
+/// Picture size from ffprobe's output
+pub fn parse_probe(output: &str) -> Option<(u32, u32)> {
+    let line = output.lines().find(|line| !line.trim().is_empty())?;
+    let (width, height) = line.trim().trim_end_matches('x').split_once('x')?;
+    let size = (width.parse().ok()?, height.parse().ok()?);
+    (size.0 > 0 && size.1 > 0).then_some(size)
+}
+
+/// ffmpeg arguments decoding `path` to raw RGBA frames of `width`x`height`
+/// on stdout; videos loop forever at their own frame rate
+pub fn decode_args(path: &Path, kind: BackgroundKind, width: u32, height: u32) -> Vec<String> {
+    let mut args = vec!["-v", "error", "-nostdin"];
+    if kind == BackgroundKind::Video {
+        args.extend(["-stream_loop", "-1", "-re"]);
+    }
+    let mut args: Vec<String> = args.iter().map(|s| s.to_string()).collect();
+    args.push("-i".to_string());
+    args.push(path.to_string_lossy().to_string());
+    args.push("-an".to_string());
+    args.push("-vf".to_string());
+    args.push(format!("scale={}:{}", width, height));
+    if kind == BackgroundKind::Still {
+        args.push("-frames:v".to_string());
+        args.push("1".to_string());
+    }
+    args.extend(
+        ["-f", "rawvideo", "-pix_fmt", "rgba", "-"]
+            .iter()
+            .map(|s| s.to_string()),
+    );
+    args
+}
+
+/// Size of the picture in `path`, asked of ffprobe
+fn probe_size(path: &Path) -> Result<(u32, u32), VideoOutputError> {
+    let output = Command::new("ffprobe")
+        .args(probe_args(path))
+        .stdin(Stdio::null())
+        .output()
+        .map_err(|e| {
+            VideoOutputError::InitError(format!(
+                "Background videos need ffmpeg installed and on the PATH: {}",
+                e
+            ))
+        })?;
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    parse_probe(&stdout).ok_or_else(|| {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        VideoOutputError::InitError(format!(
+            "No picture in {}: {}",
+            path.display(),
+            stderr.trim()
+        ))
+    })
+}
+
+/// ffmpeg process decoding frames on a thread
+struct Decoder {
+    child: Child,
+    frames: Receiver<Vec<u8>>,
+    /// Buffers handed back for the next frames
+    recycle: Sender<Vec<u8>>,
+    reader: Option<JoinHandle<()>>,
+}
+
+impl Decoder {
+    fn spawn(
+        path: &Path,
+        kind: BackgroundKind,
+        size: (u32, u32),
+    ) -> Result<Self, VideoOutputError> {
+        let mut child = Command::new("ffmpeg")
+            .args(decode_args(path, kind, size.0, size.1))
+            .stdin(Stdio::null())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::inherit())
+            .spawn()
+            .map_err(|e| {
+                VideoOutputError::InitError(format!(
+                    "Background videos need ffmpeg installed and on the PATH: {}",
+                    e
+                ))
+            })?;
+        let mut stdout = child
+            .stdout
+            .take()
+            .ok_or_else(|| VideoOutputError::InitError("ffmpeg has no output pipe".to_string()))?;
+
+        let frame_len = size.0 as usize * size.1 as usize * 4;
+        let (frame_tx, frames) = bounded(1);
+        let (recycle, recycled) = bounded::<Vec<u8>>(2);
+        let reader = thread::Builder::new()
+            .name("background-decoder".to_string())
+            .spawn(move || {
+                let mut spare = None;
+                loop {
+                    let mut frame = spare
+                        .take()
+                        .or_else(|| recycled.try_recv().ok())
+                        .unwrap_or_default();
+                    frame.resize(frame_len, 0);
+                    if stdout.read_exact(&mut frame).is_err() {
+                        break;
+                    }
+                    match frame_tx.try_send(frame) {
+                        Ok(()) => {}
+                        // The renderer hasn't taken the last one; skip this
+                        Err(TrySendError::Full(frame)) => spare = Some(frame),
+                        Err(TrySendError::Disconnected(_)) => break,
+                    }
+                }
+            })
+            .map_err(|e| VideoOutputError::InitError(e.to_string()))?;
+
+        Ok(Self {
+            child,
+            frames,
+            recycle,
+            reader: Some(reader),
+        })
+    }
+}
+
+impl Drop for Decoder {
+    fn drop(&mut self) {
+        // Closing ffmpeg's pipe ends the reader's read
+        let _ = self.child.kill();
+        let _ = self.child.wait();
+        if let Some(reader) = self.reader.take() {
+            let _ = reader.join();
+        }
+    }
+}
+
+/// A background picture, decoded once or looping
+pub struct BackgroundMedia {
+    path: PathBuf,
+    width: u32,
+    height: u32,
+    pixels: Vec<u8>,
+    /// A picture arrived that hasn't been handed out
+    fresh: bool,
+    /// Still running for videos, and for stills until their frame arrives
+    decoder: Option<Decoder>,
+}
+
+impl BackgroundMedia {
+    /// Open `path`; PNGs are decoded straight away, anything else is handed
+    /// to ffmpeg
+    pub fn open(path: &Path) -> Result<Self, VideoOutputError> {
+        let kind = BackgroundKind::of(path);
+        if kind == BackgroundKind::Png {
+            let (pixels, width, height) = super::overlay::load_png(path)?;
+            return Ok(Self {
+                path: path.to_path_buf(),
+                width,
+                height,
+                pixels,
+                fresh: true,
+                decoder: None,
+            });
+        }
+
+        let (width, height) = probe_size(path)?;
+        let (width, height) = decode_size(width, height);
+        Ok(Self {
+            path: path.to_path_buf(),
+            width,
+            height,
+            pixels: Vec::new(),
+            fresh: false,
+            decoder: Some(Decoder::spawn(path, kind, (width, height))?),
+        })
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Whether frames keep coming after the first
+    pub fn is_video(&self) -> bool {
+        BackgroundKind::of(&self.path) == BackgroundKind::Video
+    }
+
+    /// The picture, when it changed since the last call
+    pub fn receive_frame(&mut self) -> Result<Option<InputFrame<'_>>, VideoOutputError> {
+        let mut finished = false;
+        if let Some(ref decoder) = self.decoder {
+            loop {
+                match decoder.frames.try_recv() {
+                    Ok(frame) => {
+                        let used = std::mem::replace(&mut self.pixels, frame);
+                        let _ = decoder.recycle.try_send(used);
+                        self.fresh = true;
+                    }
+                    Err(TryRecvError::Empty) => break,
+                    Err(TryRecvError::Disconnected) => {
+                        finished = true;
+                        break;
+                    }
+                }
+            }
+        }
+        if finished {
+            self.decoder = None;
+            // A still is done after its one frame; videos loop until stopped
+            if self.pixels.is_empty() || self.is_video() {
+                return Err(VideoOutputError::InitError(format!(
+                    "ffmpeg stopped decoding {}",
+                    self.path.display()
+                )));
+            }
+        }
+
+        if !std::mem::take(&mut self.fresh) {
+            return Ok(None);
+        }
+        Ok(Some(InputFrame {
+            pixels: &self.pixels,
+            width: self.width,
+            height: self.height,
+        }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_config_defaults_and_validate() {
+        let config: BackgroundConfig =
+            serde_json::from_str(r#"{"path": "/venue/backdrop.mp4"}"#).unwrap();
+        assert_eq!(config.blend, BackgroundBlend::Screen);
+        assert_eq!(config.fit, BackgroundFit::Cover);
+        assert_eq!(config.opacity, 1.0);
+        assert!(config.validate().is_ok());
+
+        let config = BackgroundConfig {
+            opacity: 1.5,
+            ..config
+        };
+        assert!(config.validate().is_err());
+        assert_eq!(
+            "Additive".parse::<BackgroundBlend>(),
+            Ok(BackgroundBlend::Add)
+        );
+        assert!("multiply".parse::<BackgroundBlend>().is_err());
+    }
+
+    #[test]
+    fn test_kind_by_extension() {
+        assert_eq!(
+            BackgroundKind::of(Path::new("logo.PNG")),
+            BackgroundKind::Png
+        );
+        assert_eq!(
+            BackgroundKind::of(Path::new("wall.jpg")),
+            BackgroundKind::Still
+        );
+        assert_eq!(
+            BackgroundKind::of(Path::new("loop.mp4")),
+            BackgroundKind::Video
+        );
+        assert_eq!(BackgroundKind::of(Path::new("loop")), BackgroundKind::Video);
+    }
+
+    #[test]
+    fn test_fit_rects() {
+        assert_eq!(
+            BackgroundFit::Stretch.rect(640, 480, 1920, 1080),
+            (-1.0, -1.0, 1.0, 1.0)
+        );
+        let (x0, _, x1, y1) = BackgroundFit::Contain.rect(640, 480, 1920, 1080);
+        assert!((x1 - 0.75).abs() < 1e-6 && x0 == -x1 && y1 == 1.0);
+        // 4:3 covering 16:9 fills the width and crops top and bottom
+        let (x0, y0, x1, y1) = BackgroundFit::Cover.rect(640, 480, 1920, 1080);
+        assert_eq!((x0, x1), (-1.0, 1.0));
+        assert!((y1 - 4.0 / 3.0).abs() < 1e-5 && y0 == -y1);
+    }
+
+    #[test]
+    fn test_decode_size() {
+        assert_eq!(decode_size(1280, 720), (1280, 720));
+        assert_eq!(decode_size(3840, 2160), (1920, 1080));
+        assert_eq!(decode_size(1080, 3840), (540, 1920));
+    }
+
+    #[test]
+    fn test_parse_probe() {
+        assert_eq!(parse_probe("1920x1080\n"), Some((1920, 1080)));
+        // Some ffprobe versions end the line with the separator
+        assert_eq!(parse_probe("\n640x480x\n"), Some((640, 480)));
+        assert_eq!(parse_probe(""), None);
+        assert_eq!(parse_probe("0x0"), None);
+    }
+
+    #[test]
+    fn test_decode_args() {
+        let path = Path::new("/venue/loop.mp4");
+        let args = decode_args(path, BackgroundKind::Video, 1280, 720).join(" ");
+        assert_eq!(
+            args,
+            "-v error -nostdin -stream_loop -1 -re -i /venue/loop.mp4 -an \
+             -vf scale=1280:720 -f rawvideo -pix_fmt rgba -"
+        );
+        let args = decode_args(Path::new("wall.jpg"), BackgroundKind::Still, 800, 600);
+        assert!(args.windows(2).any(|w| w == ["-frames:v", "1"]));
+        assert!(!args.contains(&"-stream_loop".to_string()));
+    }
+
+    #[test]
+    fn test_png_background() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("backdrop.png");
+        let mut encoder = png::Encoder::new(std::fs::File::create(&path).unwrap(), 1, 1);
+        encoder.set_color(png::ColorType::Rgba);
+        encoder.set_depth(png::BitDepth::Eight);
+        let mut writer = encoder.write_header().unwrap();
+        writer.write_image_data(&[10, 20, 30, 255]).unwrap();
+        writer.finish().unwrap();
+
+        let mut media = BackgroundMedia::open(&path).unwrap();
+        assert!(!media.is_video());
+        let frame = media.receive_frame().unwrap().unwrap();
+        assert_eq!((frame.width, frame.height), (1, 1));
+        assert_eq!(frame.pixels, [10, 20, 30, 255]);
+        // Handed out once
+        assert!(media.receive_frame().unwrap().is_none());
+    }
+}
@@ -0,0 +1,230 @@
+//! Brightness, contrast and saturation per deck
+//!
+//! Projectors in the same room rarely match. Each deck's output can be
+//! corrected to line up with the others, and dimmed with a fade instead of
+//! through the projector's menu. The renderer applies it to the finished
+//! frame, so outputs and recordings get the corrected picture.
+
+use std::fmt;
+use std::str::FromStr;
+use std::time::{Duration, Instant};
+
+use serde::{Deserialize, Serialize};
+
+use super::output::VideoOutputError;
+
+/// Values each control takes; 1.0 leaves the picture as it is
+pub const COLOR_RANGE: std::ops::RangeInclusive<f32> = 0.0..=2.0;
+
+/// Longest fade between two adjustments, in seconds
+pub const MAX_COLOR_FADE_SECS: f64 = 30.0;
+
+/// One of the colour controls
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ColorControl {
+    /// 0 is black, 2 twice as bright
+    Brightness,
+    /// 0 is flat grey, 2 twice the contrast
+    Contrast,
+    /// 0 is greyscale, 2 twice as colourful
+    Saturation,
+}
+
+impl ColorControl {
+    pub const ALL: [ColorControl; 3] = [
+        ColorControl::Brightness,
+        ColorControl::Contrast,
+        ColorControl::Saturation,
+    ];
+}
+
+impl fmt::Display for ColorControl {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            ColorControl::Brightness => "brightness",
+            ColorControl::Contrast => "contrast",
+            ColorControl::Saturation => "saturation",
+        })
+    }
+}
+
+impl FromStr for ColorControl {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "brightness" => Ok(ColorControl::Brightness),
+            "contrast" => Ok(ColorControl::Contrast),
+            "saturation" => Ok(ColorControl::Saturation),
+            _ => Err(format!(
+                "Unknown colour control: {}. Use: brightness, contrast, saturation",
+                s
+            )),
+        }
+    }
+}
+
+/// Colour correction for a deck
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ColorAdjust {
+    pub brightness: f32,
+    pub contrast: f32,
+    pub saturation: f32,
+}
+
+impl Default for ColorAdjust {
+    /// The picture as rendered
+    fn default() -> Self {
+        Self {
+            brightness: 1.0,
+            contrast: 1.0,
+            saturation: 1.0,
+        }
+    }
+}
+
+impl ColorAdjust {
+    pub fn validate(&self) -> Result<(), VideoOutputError> {
+        for control in ColorControl::ALL {
+            let value = self.get(control);
+            if !COLOR_RANGE.contains(&value) {
+                return Err(VideoOutputError::InitError(format!(
+                    "Invalid {} {}: use {}-{}",
+                    control,
+                    value,
+                    COLOR_RANGE.start(),
+                    COLOR_RANGE.end()
+                )));
+            }
+        }
+        Ok(())
+    }
+
+    pub fn get(&self, control: ColorControl) -> f32 {
+        match control {
+            ColorControl::Brightness => self.brightness,
+            ColorControl::Contrast => self.contrast,
+            ColorControl::Saturation => self.saturation,
+        }
+    }
+
+    /// Set one control, clamped to [`COLOR_RANGE`]
+    pub fn set(&mut self, control: ColorControl, value: f32) {
+        let value = if value.is_finite() {
+            value.clamp(*COLOR_RANGE.start(), *COLOR_RANGE.end())
+        } else {
+            1.0
+        };
+        match control {
+            ColorControl::Brightness => self.brightness = value,
+            ColorControl::Contrast => self.contrast = value,
+            ColorControl::Saturation => self.saturation = value,
+        }
+    }
+
+    /// Whether the picture is left as rendered
+    pub fn is_neutral(&self) -> bool {
+        *self == Self::default()
+    }
+
+    /// `t` of the way from this adjustment to `to`
+    pub fn mix(&self, to: &ColorAdjust, t: f32) -> ColorAdjust {
+        let t = t.clamp(0.0, 1.0);
+        let mix = |from: f32, to: f32| from + (to - from) * t;
+        ColorAdjust {
+            brightness: mix(self.brightness, to.brightness),
+            contrast: mix(self.contrast, to.contrast),
+            saturation: mix(self.saturation, to.saturation),
+        }
+    }
+}
+
+/// Linear fade from one adjustment to another
+#[derive(Debug, Clone, Copy)]
+pub struct ColorFade {
+    from: ColorAdjust,
+    to: ColorAdjust,
+    started: Instant,
+    duration: Duration,
+}
+
+impl ColorFade {
+    pub fn new(from: ColorAdjust, to: ColorAdjust, duration: Duration, now: Instant) -> Self {
+        Self {
+            from,
+            to,
+            started: now,
+            duration,
+        }
+    }
+
+    /// Adjustment to apply at `now`
+    pub fn at(&self, now: Instant) -> ColorAdjust {
+        if self.is_done(now) {
+            return self.to;
+        }
+        let t =
+            now.saturating_duration_since(self.started).as_secs_f32() / self.duration.as_secs_f32();
+        self.from.mix(&self.to, t)
+    }
+
+    pub fn is_done(&self, now: Instant) -> bool {
+        now.saturating_duration_since(self.started) >= self.duration
+    }
+
+    /// Where the fade ends
+    pub fn target(&self) -> ColorAdjust {
+        self.to
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_adjust_defaults_and_validate() {
+        let adjust: ColorAdjust = serde_json::from_str(r#"{"brightness": 0.5}"#).unwrap();
+        assert_eq!(adjust.contrast, 1.0);
+        assert!(adjust.validate().is_ok());
+        assert!(!adjust.is_neutral());
+        assert!(ColorAdjust::default().is_neutral());
+
+        let invalid = ColorAdjust {
+            saturation: 3.0,
+            ..adjust
+        };
+        assert!(invalid.validate().is_err());
+
+        let mut adjust = ColorAdjust::default();
+        adjust.set(ColorControl::Contrast, 5.0);
+        adjust.set(ColorControl::Brightness, f32::NAN);
+        assert_eq!(adjust.contrast, 2.0);
+        assert_eq!(adjust.brightness, 1.0);
+        assert_eq!("Saturation".parse(), Ok(ColorControl::Saturation));
+        assert!("hue".parse::<ColorControl>().is_err());
+    }
+
+    #[test]
+    fn test_fade() {
+        let start = Instant::now();
+        let dark = ColorAdjust {
+            brightness: 0.0,
+            ..ColorAdjust::default()
+        };
+        let fade = ColorFade::new(ColorAdjust::default(), dark, Duration::from_secs(2), start);
+        assert_eq!(fade.at(start).brightness, 1.0);
+        let halfway = fade.at(start + Duration::from_secs(1));
+        assert!((halfway.brightness - 0.5).abs() < 1e-6);
+        assert_eq!(halfway.contrast, 1.0);
+        assert!(!fade.is_done(start + Duration::from_secs(1)));
+        assert_eq!(fade.at(start + Duration::from_secs(3)), dark);
+        assert!(fade.is_done(start + Duration::from_secs(2)));
+
+        // A zero-length fade is a cut
+        let cut = ColorFade::new(ColorAdjust::default(), dark, Duration::ZERO, start);
+        assert_eq!(cut.at(start), dark);
+    }
+}
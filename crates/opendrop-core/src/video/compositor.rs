@@ -0,0 +1,1088 @@
+//! Compositing of deck outputs into one picture
+//!
+//! Each deck renders in its own process, so decks stream their captured
+//! frames to the compositor renderer over a local TCP connection. The
+//! compositor keeps the latest frame of every deck and blends them with the
+//! opacity, blend mode and layer order from the compositor settings.
+//!
+//! A frame on the wire is a 13-byte header (`ODCF`, deck id, width and
+//! height as little-endian u32) followed by `width * height * 4` bytes of
+//! RGBA, top row first.
+//!
+//! On Linux, decks whose GL driver can export DMA-BUFs skip the copy to and
+//! from system memory: they render into a few shared textures and pass the
+//! buffers' file descriptors to the compositor once over a Unix socket, then
+//! only announce which buffer holds the newest frame. Every message on that
+//! socket is `GPU_MESSAGE_LEN` bytes (see [`GpuMessage`]).
+
+use std::collections::HashMap;
+use std::io::{Read, Write};
+use std::net::{Ipv4Addr, TcpListener, TcpStream};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread::{self, JoinHandle};
+use std::time::Duration;
+
+use crossbeam_channel::{bounded, Sender, TrySendError};
+use serde::{Deserialize, Serialize};
+
+use super::input::InputFrame;
+use super::output::{OutputBackend, VideoOutput, VideoOutputError};
+
+/// Marks the start of every frame
+const FRAME_MAGIC: [u8; 4] = *b"ODCF";
+
+/// Size of a frame header in bytes
+pub const FRAME_HEADER_LEN: usize = 13;
+
+/// Largest frame side accepted from a deck
+const MAX_FRAME_SIDE: u32 = 8192;
+
+/// How often the accept loop checks whether the receiver was dropped
+const ACCEPT_POLL: Duration = Duration::from_millis(100);
+
+/// Marks the start of every GPU sharing message
+const GPU_MAGIC: [u8; 4] = *b"ODGS";
+
+/// Size of a GPU sharing message in bytes
+pub const GPU_MESSAGE_LEN: usize = 64;
+
+/// Most buffers a deck shares at once
+pub const MAX_SHARED_BUFFERS: usize = 3;
+
+/// DRM format modifier for buffers with a driver-chosen layout
+pub const DRM_FORMAT_MOD_INVALID: u64 = 0x00ff_ffff_ffff_ffff;
+
+/// Blend mode for compositor
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, Default, PartialEq, Eq)]
+pub enum BlendMode {
+    /// Standard alpha blend
+    #[default]
+    Normal,
+    /// Additive blend (bright)
+    Add,
+    /// Darken blend
+    Multiply,
+    /// Lighten blend
+    Screen,
+    /// Contrast blend
+    Overlay,
+}
+
+impl BlendMode {
+    /// Following mode, wrapping around (for stepping through modes from MIDI)
+    pub fn next(self) -> Self {
+        match self {
+            BlendMode::Normal => BlendMode::Add,
+            BlendMode::Add => BlendMode::Multiply,
+            BlendMode::Multiply => BlendMode::Screen,
+            BlendMode::Screen => BlendMode::Overlay,
+            BlendMode::Overlay => BlendMode::Normal,
+        }
+    }
+}
+
+/// A deck drawn by the compositor
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct CompositeLayer {
+    pub deck_id: u8,
+    /// Opacity after any crossfader link (0.0 to 1.0)
+    pub opacity: f32,
+    pub blend_mode: BlendMode,
+}
+
+/// Header in front of each streamed frame
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FrameHeader {
+    pub deck_id: u8,
+    pub width: u32,
+    pub height: u32,
+}
+
+impl FrameHeader {
+    /// Encode for the wire
+    pub fn to_bytes(self) -> [u8; FRAME_HEADER_LEN] {
+        let mut bytes = [0u8; FRAME_HEADER_LEN];
+        bytes[..4].copy_from_slice(&FRAME_MAGIC);
+        bytes[4] = self.deck_id;
+        bytes[5..9].copy_from_slice(&self.width.to_le_bytes());
+        bytes[9..].copy_from_slice(&self.height.to_le_bytes());
+        bytes
+    }
+
+    /// Decode and check a header read from the wire
+    pub fn parse(bytes: &[u8; FRAME_HEADER_LEN]) -> Result<Self, VideoOutputError> {
+        if bytes[..4] != FRAME_MAGIC {
+            return Err(VideoOutputError::ReceiveError(
+                "Not a compositor frame".to_string(),
+            ));
+        }
+        let header = Self {
+            deck_id: bytes[4],
+            width: u32::from_le_bytes([bytes[5], bytes[6], bytes[7], bytes[8]]),
+            height: u32::from_le_bytes([bytes[9], bytes[10], bytes[11], bytes[12]]),
+        };
+        if header.width == 0
+            || header.height == 0
+            || header.width > MAX_FRAME_SIDE
+            || header.height > MAX_FRAME_SIDE
+        {
+            return Err(VideoOutputError::ReceiveError(format!(
+                "Invalid frame size {}x{}",
+                header.width, header.height
+            )));
+        }
+        Ok(header)
+    }
+
+    /// Bytes of RGBA following the header
+    pub fn pixel_len(self) -> usize {
+        self.width as usize * self.height as usize * 4
+    }
+}
+
+/// Layout of one shared single-plane buffer
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct SharedPlane {
+    /// Bytes per row
+    pub stride: u32,
+    /// Bytes before the first row
+    pub offset: u32,
+}
+
+/// GPU buffers a deck renders into, shared with the compositor
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SharedBuffers {
+    pub width: u32,
+    pub height: u32,
+    /// DRM fourcc of the pixel format
+    pub fourcc: u32,
+    /// DRM format modifier, or `DRM_FORMAT_MOD_INVALID`
+    pub modifier: u64,
+    /// One per buffer; the buffers' file descriptors travel with the message
+    pub planes: Vec<SharedPlane>,
+}
+
+/// Message on a deck's GPU sharing connection
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum GpuMessage {
+    /// Buffers replacing any shared before, top row first
+    Buffers { deck_id: u8, buffers: SharedBuffers },
+    /// Buffer `index` holds a finished frame
+    Frame { deck_id: u8, index: u8 },
+}
+
+impl GpuMessage {
+    /// Deck the message is from
+    pub fn deck_id(&self) -> u8 {
+        match self {
+            GpuMessage::Buffers { deck_id, .. } | GpuMessage::Frame { deck_id, .. } => *deck_id,
+        }
+    }
+
+    /// Encode for the wire
+    pub fn to_bytes(&self) -> [u8; GPU_MESSAGE_LEN] {
+        let mut bytes = [0u8; GPU_MESSAGE_LEN];
+        bytes[..4].copy_from_slice(&GPU_MAGIC);
+        bytes[5] = self.deck_id();
+        match self {
+            GpuMessage::Buffers { buffers, .. } => {
+                bytes[4] = 0;
+                bytes[6] = buffers.planes.len().min(MAX_SHARED_BUFFERS) as u8;
+                bytes[8..12].copy_from_slice(&buffers.width.to_le_bytes());
+                bytes[12..16].copy_from_slice(&buffers.height.to_le_bytes());
+                bytes[16..20].copy_from_slice(&buffers.fourcc.to_le_bytes());
+                bytes[20..28].copy_from_slice(&buffers.modifier.to_le_bytes());
+                for (i, plane) in buffers.planes.iter().take(MAX_SHARED_BUFFERS).enumerate() {
+                    let at = 28 + i * 8;
+                    bytes[at..at + 4].copy_from_slice(&plane.stride.to_le_bytes());
+                    bytes[at + 4..at + 8].copy_from_slice(&plane.offset.to_le_bytes());
+                }
+            }
+            GpuMessage::Frame { index, .. } => {
+                bytes[4] = 1;
+                bytes[6] = *index;
+            }
+        }
+        bytes
+    }
+
+    /// Decode and check a message read from the wire
+    pub fn parse(bytes: &[u8; GPU_MESSAGE_LEN]) -> Result<Self, VideoOutputError> {
+        let invalid = |what: &str| VideoOutputError::ReceiveError(what.to_string());
+        if bytes[..4] != GPU_MAGIC {
+            return Err(invalid("Not a GPU sharing message"));
+        }
+        let u32_at = |at: usize| {
+            u32::from_le_bytes([bytes[at], bytes[at + 1], bytes[at + 2], bytes[at + 3]])
+        };
+        let deck_id = bytes[5];
+        match bytes[4] {
+            0 => {
+                let count = bytes[6] as usize;
+                if count == 0 || count > MAX_SHARED_BUFFERS {
+                    return Err(invalid("Invalid shared buffer count"));
+                }
+                let mut modifier = [0u8; 8];
+                modifier.copy_from_slice(&bytes[20..28]);
+                let buffers = SharedBuffers {
+                    width: u32_at(8),
+                    height: u32_at(12),
+                    fourcc: u32_at(16),
+                    modifier: u64::from_le_bytes(modifier),
+                    planes: (0..count)
+                        .map(|i| SharedPlane {
+                            stride: u32_at(28 + i * 8),
+                            offset: u32_at(32 + i * 8),
+                        })
+                        .collect(),
+                };
+                if buffers.width == 0
+                    || buffers.height == 0
+                    || buffers.width > MAX_FRAME_SIDE
+                    || buffers.height > MAX_FRAME_SIDE
+                {
+                    return Err(VideoOutputError::ReceiveError(format!(
+                        "Invalid frame size {}x{}",
+                        buffers.width, buffers.height
+                    )));
+                }
+                Ok(GpuMessage::Buffers { deck_id, buffers })
+            }
+            1 if (bytes[6] as usize) < MAX_SHARED_BUFFERS => Ok(GpuMessage::Frame {
+                deck_id,
+                index: bytes[6],
+            }),
+            1 => Err(invalid("Invalid shared buffer index")),
+            _ => Err(invalid("Unknown GPU sharing message")),
+        }
+    }
+}
+
+/// Latest frame received from a deck
+struct ReceivedFrame {
+    pixels: Vec<u8>,
+    width: u32,
+    height: u32,
+    /// Not yet handed to the compositor
+    fresh: bool,
+}
+
+type FrameMap = Arc<Mutex<HashMap<u8, ReceivedFrame>>>;
+
+/// Receives deck frames for the compositor
+pub struct CompositorReceiver {
+    port: u16,
+    frames: FrameMap,
+    running: Arc<AtomicBool>,
+    accept_thread: Option<JoinHandle<()>>,
+    /// Decks sharing GPU buffers, once `listen_gpu` is called
+    #[cfg(target_os = "linux")]
+    gpu: Option<gpu::Listener>,
+}
+
+impl CompositorReceiver {
+    /// Listen on a free local port
+    pub fn bind() -> Result<Self, VideoOutputError> {
+        let listener = TcpListener::bind((Ipv4Addr::LOCALHOST, 0))
+            .and_then(|listener| {
+                listener.set_nonblocking(true)?;
+                Ok(listener)
+            })
+            .map_err(|e| VideoOutputError::InitError(format!("Compositor listen failed: {}", e)))?;
+        let port = listener
+            .local_addr()
+            .map_err(|e| VideoOutputError::InitError(e.to_string()))?
+            .port();
+
+        let frames: FrameMap = Arc::new(Mutex::new(HashMap::new()));
+        let running = Arc::new(AtomicBool::new(true));
+        let accept_thread = {
+            let frames = Arc::clone(&frames);
+            let running = Arc::clone(&running);
+            thread::spawn(move || {
+                while running.load(Ordering::Relaxed) {
+                    match listener.accept() {
+                        Ok((stream, _)) => {
+                            let frames = Arc::clone(&frames);
+                            let running = Arc::clone(&running);
+                            thread::spawn(move || receive_frames(stream, frames, running));
+                        }
+                        Err(e) if e.kind() == std::io::ErrorKind::WouldBlock => {
+                            thread::sleep(ACCEPT_POLL);
+                        }
+                        Err(e) => {
+                            tracing::warn!("Compositor accept failed: {}", e);
+                            thread::sleep(ACCEPT_POLL);
+                        }
+                    }
+                }
+            })
+        };
+
+        tracing::info!("Compositor receiving deck frames on port {}", port);
+        Ok(Self {
+            port,
+            frames,
+            running,
+            accept_thread: Some(accept_thread),
+            #[cfg(target_os = "linux")]
+            gpu: None,
+        })
+    }
+
+    /// Port decks connect to
+    pub fn port(&self) -> u16 {
+        self.port
+    }
+
+    /// Call `f` with the latest frame of `deck_id` and whether it's new since
+    /// the last call; returns false while the deck isn't connected
+    pub fn with_frame(&self, deck_id: u8, f: impl FnOnce(InputFrame<'_>, bool)) -> bool {
+        let Ok(mut frames) = self.frames.lock() else {
+            return false;
+        };
+        match frames.get_mut(&deck_id) {
+            Some(frame) => {
+                let fresh = std::mem::replace(&mut frame.fresh, false);
+                f(
+                    InputFrame {
+                        pixels: &frame.pixels,
+                        width: frame.width,
+                        height: frame.height,
+                    },
+                    fresh,
+                );
+                true
+            }
+            None => false,
+        }
+    }
+}
+
+#[cfg(target_os = "linux")]
+impl CompositorReceiver {
+    /// Also accept GPU buffers from decks; returns the socket they connect to
+    pub fn listen_gpu(&mut self) -> Result<&std::path::Path, VideoOutputError> {
+        let gpu = match self.gpu.take() {
+            Some(gpu) => gpu,
+            None => gpu::Listener::bind(Arc::clone(&self.running))?,
+        };
+        Ok(self.gpu.insert(gpu).path())
+    }
+
+    /// Call `f` with the GPU buffers `deck_id` shares; returns false while
+    /// the deck isn't sharing any
+    pub fn with_shared_frame(&self, deck_id: u8, f: impl FnOnce(SharedFrame<'_>)) -> bool {
+        match &self.gpu {
+            Some(gpu) => gpu.with_shared_frame(deck_id, f),
+            None => false,
+        }
+    }
+
+    /// Give up on a deck's buffers (e.g. they can't be imported); its
+    /// connection is closed so it falls back to sending pixels
+    pub fn reject_shared(&self, deck_id: u8) {
+        if let Some(gpu) = &self.gpu {
+            gpu.reject(deck_id);
+        }
+    }
+}
+
+impl Drop for CompositorReceiver {
+    fn drop(&mut self) {
+        self.running.store(false, Ordering::Relaxed);
+        if let Some(handle) = self.accept_thread.take() {
+            let _ = handle.join();
+        }
+    }
+}
+
+/// Read frames from one deck until it disconnects
+fn receive_frames(mut stream: TcpStream, frames: FrameMap, running: Arc<AtomicBool>) {
+    let _ = stream.set_nonblocking(false);
+    let mut buffer = Vec::new();
+    let mut deck = None;
+    while running.load(Ordering::Relaxed) {
+        let mut header = [0u8; FRAME_HEADER_LEN];
+        if stream.read_exact(&mut header).is_err() {
+            break;
+        }
+        let header = match FrameHeader::parse(&header) {
+            Ok(header) => header,
+            Err(e) => {
+                tracing::warn!("Dropping compositor connection: {}", e);
+                break;
+            }
+        };
+        buffer.resize(header.pixel_len(), 0);
+        if stream.read_exact(&mut buffer).is_err() {
+            break;
+        }
+        if deck.is_none() {
+            tracing::info!("Deck {} connected to the compositor", header.deck_id + 1);
+        }
+        deck = Some(header.deck_id);
+
+        let Ok(mut frames) = frames.lock() else {
+            break;
+        };
+        let frame = frames
+            .entry(header.deck_id)
+            .or_insert_with(|| ReceivedFrame {
+                pixels: Vec::new(),
+                width: 0,
+                height: 0,
+                fresh: false,
+            });
+        // Swap rather than copy; the old buffer is reused for the next read
+        std::mem::swap(&mut frame.pixels, &mut buffer);
+        frame.width = header.width;
+        frame.height = header.height;
+        frame.fresh = true;
+    }
+
+    // A stopped deck leaves the composite
+    if let Some(deck_id) = deck {
+        tracing::info!("Deck {} disconnected from the compositor", deck_id + 1);
+        if let Ok(mut frames) = frames.lock() {
+            frames.remove(&deck_id);
+        }
+    }
+}
+
+/// Streams a deck's frames to the compositor
+pub struct CompositorFeed {
+    deck_id: u8,
+    frame_tx: Option<Sender<(Vec<u8>, u32, u32)>>,
+    writer: Option<JoinHandle<()>>,
+    name: String,
+    active: bool,
+}
+
+impl CompositorFeed {
+    /// Connect to the compositor listening on `port`
+    pub fn connect(port: u16, deck_id: u8) -> Result<Self, VideoOutputError> {
+        let mut stream = TcpStream::connect((Ipv4Addr::LOCALHOST, port)).map_err(|e| {
+            VideoOutputError::InitError(format!("Compositor connection failed: {}", e))
+        })?;
+
+        // One frame in flight; newer frames replace ones the socket can't take
+        let (frame_tx, frame_rx) = bounded::<(Vec<u8>, u32, u32)>(1);
+        let writer = thread::spawn(move || {
+            for (pixels, width, height) in frame_rx {
+                let header = FrameHeader {
+                    deck_id,
+                    width,
+                    height,
+                };
+                if stream
+                    .write_all(&header.to_bytes())
+                    .and_then(|_| stream.write_all(&pixels))
+                    .is_err()
+                {
+                    break;
+                }
+            }
+        });
+
+        tracing::info!("Deck {} streaming to compositor port {}", deck_id + 1, port);
+        Ok(Self {
+            deck_id,
+            frame_tx: Some(frame_tx),
+            writer: Some(writer),
+            name: format!("compositor:{}", port),
+            active: true,
+        })
+    }
+
+    /// Deck the frames belong to
+    pub fn deck_id(&self) -> u8 {
+        self.deck_id
+    }
+}
+
+impl VideoOutput for CompositorFeed {
+    fn backend(&self) -> OutputBackend {
+        OutputBackend::Compositor
+    }
+
+    fn send_frame(
+        &mut self,
+        _texture_id: u32,
+        _width: u32,
+        _height: u32,
+    ) -> Result<(), VideoOutputError> {
+        Err(VideoOutputError::SendError(
+            "Compositor feed needs RGBA frames".to_string(),
+        ))
+    }
+
+    fn send_frame_rgba(
+        &mut self,
+        pixels: &[u8],
+        width: u32,
+        height: u32,
+    ) -> Result<(), VideoOutputError> {
+        if !self.active {
+            return Ok(());
+        }
+        let expected_size = (width * height * 4) as usize;
+        if pixels.len() != expected_size {
+            return Err(VideoOutputError::SendError(format!(
+                "Invalid pixel buffer size: got {}, expected {}",
+                pixels.len(),
+                expected_size
+            )));
+        }
+        let Some(tx) = &self.frame_tx else {
+            return Ok(());
+        };
+        match tx.try_send((pixels.to_vec(), width, height)) {
+            Ok(()) | Err(TrySendError::Full(_)) => Ok(()),
+            Err(TrySendError::Disconnected(_)) => Err(VideoOutputError::SendError(
+                "Compositor disconnected".to_string(),
+            )),
+        }
+    }
+
+    fn is_active(&self) -> bool {
+        self.active
+    }
+
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn set_active(&mut self, active: bool) {
+        self.active = active;
+    }
+}
+
+impl Drop for CompositorFeed {
+    fn drop(&mut self) {
+        // Closing the channel ends the writer, which closes the connection
+        self.frame_tx = None;
+        if let Some(handle) = self.writer.take() {
+            let _ = handle.join();
+        }
+    }
+}
+
+#[cfg(target_os = "linux")]
+pub use gpu::{CompositorGpuFeed, SharedFrame};
+
+/// GPU buffer sharing over a Unix socket, with descriptors passed as
+/// `SCM_RIGHTS` ancillary data
+#[cfg(target_os = "linux")]
+mod gpu {
+    use super::*;
+    use std::io::ErrorKind;
+    use std::os::fd::{AsRawFd, BorrowedFd, FromRawFd, OwnedFd, RawFd};
+    use std::os::unix::net::{UnixListener, UnixStream};
+    use std::path::{Path, PathBuf};
+    use std::sync::atomic::{AtomicU64, AtomicUsize};
+
+    /// Sockets created by this process, so several receivers don't collide
+    static NEXT_SOCKET: AtomicUsize = AtomicUsize::new(0);
+
+    /// Tells imported buffer sets apart, also across reconnects
+    static NEXT_GENERATION: AtomicU64 = AtomicU64::new(1);
+
+    /// A deck's shared buffers as handed to the compositor's GL thread
+    pub struct SharedFrame<'a> {
+        pub buffers: &'a SharedBuffers,
+        /// One per plane in `buffers`
+        pub fds: &'a [OwnedFd],
+        /// Changes whenever the deck shares new buffers
+        pub generation: u64,
+        /// Buffer holding the newest frame, if one was announced yet
+        pub ready: Option<u8>,
+        /// `ready` changed since the last call
+        pub fresh: bool,
+    }
+
+    struct SharedDeck {
+        buffers: SharedBuffers,
+        fds: Vec<OwnedFd>,
+        generation: u64,
+        ready: Option<u8>,
+        fresh: bool,
+        rejected: bool,
+    }
+
+    type SharedMap = Arc<Mutex<HashMap<u8, SharedDeck>>>;
+
+    /// Unix socket decks share GPU buffers over
+    pub(super) struct Listener {
+        path: PathBuf,
+        decks: SharedMap,
+        thread: Option<JoinHandle<()>>,
+    }
+
+    impl Listener {
+        pub(super) fn bind(running: Arc<AtomicBool>) -> Result<Self, VideoOutputError> {
+            let dir = dirs::runtime_dir().unwrap_or_else(std::env::temp_dir);
+            let path = dir.join(format!(
+                "opendrop-compositor-{}-{}.sock",
+                std::process::id(),
+                NEXT_SOCKET.fetch_add(1, Ordering::Relaxed)
+            ));
+            let _ = std::fs::remove_file(&path);
+            let listener = UnixListener::bind(&path)
+                .and_then(|listener| {
+                    listener.set_nonblocking(true)?;
+                    Ok(listener)
+                })
+                .map_err(|e| {
+                    VideoOutputError::InitError(format!("GPU sharing socket failed: {}", e))
+                })?;
+
+            let decks: SharedMap = Arc::new(Mutex::new(HashMap::new()));
+            let thread = {
+                let decks = Arc::clone(&decks);
+                thread::spawn(move || {
+                    while running.load(Ordering::Relaxed) {
+                        match listener.accept() {
+                            Ok((stream, _)) => {
+                                let decks = Arc::clone(&decks);
+                                let running = Arc::clone(&running);
+                                thread::spawn(move || receive_shared(stream, decks, running));
+                            }
+                            Err(e) if e.kind() == ErrorKind::WouldBlock => {
+                                thread::sleep(ACCEPT_POLL);
+                            }
+                            Err(e) => {
+                                tracing::warn!("GPU sharing accept failed: {}", e);
+                                thread::sleep(ACCEPT_POLL);
+                            }
+                        }
+                    }
+                })
+            };
+
+            tracing::info!("Compositor accepting GPU buffers on {}", path.display());
+            Ok(Self {
+                path,
+                decks,
+                thread: Some(thread),
+            })
+        }
+
+        pub(super) fn path(&self) -> &Path {
+            &self.path
+        }
+
+        pub(super) fn with_shared_frame(
+            &self,
+            deck_id: u8,
+            f: impl FnOnce(SharedFrame<'_>),
+        ) -> bool {
+            let Ok(mut decks) = self.decks.lock() else {
+                return false;
+            };
+            match decks.get_mut(&deck_id).filter(|deck| !deck.rejected) {
+                Some(deck) => {
+                    let fresh = std::mem::replace(&mut deck.fresh, false);
+                    f(SharedFrame {
+                        buffers: &deck.buffers,
+                        fds: &deck.fds,
+                        generation: deck.generation,
+                        ready: deck.ready,
+                        fresh,
+                    });
+                    true
+                }
+                None => false,
+            }
+        }
+
+        pub(super) fn reject(&self, deck_id: u8) {
+            if let Ok(mut decks) = self.decks.lock() {
+                if let Some(deck) = decks.get_mut(&deck_id) {
+                    deck.rejected = true;
+                }
+            }
+        }
+    }
+
+    impl Drop for Listener {
+        fn drop(&mut self) {
+            // The receiver has already cleared `running`
+            if let Some(handle) = self.thread.take() {
+                let _ = handle.join();
+            }
+            let _ = std::fs::remove_file(&self.path);
+        }
+    }
+
+    /// Read buffer and frame announcements from one deck until it disconnects
+    fn receive_shared(stream: UnixStream, decks: SharedMap, running: Arc<AtomicBool>) {
+        let _ = stream.set_nonblocking(false);
+        let mut deck = None;
+        while running.load(Ordering::Relaxed) {
+            let mut bytes = [0u8; GPU_MESSAGE_LEN];
+            let fds = match recv_with_fds(&stream, &mut bytes) {
+                Ok(fds) => fds,
+                Err(_) => break,
+            };
+            let message = match GpuMessage::parse(&bytes) {
+                Ok(message) => message,
+                Err(e) => {
+                    tracing::warn!("Dropping GPU sharing connection: {}", e);
+                    break;
+                }
+            };
+            let deck_id = message.deck_id();
+            if deck.is_none() {
+                tracing::info!(
+                    "Deck {} sharing GPU buffers with the compositor",
+                    deck_id + 1
+                );
+            }
+            deck = Some(deck_id);
+
+            let Ok(mut decks) = decks.lock() else {
+                break;
+            };
+            match message {
+                GpuMessage::Buffers { buffers, .. } => {
+                    if fds.len() != buffers.planes.len() {
+                        tracing::warn!("Deck {} sent buffers without descriptors", deck_id + 1);
+                        break;
+                    }
+                    decks.insert(
+                        deck_id,
+                        SharedDeck {
+                            buffers,
+                            fds,
+                            generation: NEXT_GENERATION.fetch_add(1, Ordering::Relaxed),
+                            ready: None,
+                            fresh: false,
+                            rejected: false,
+                        },
+                    );
+                }
+                GpuMessage::Frame { index, .. } => match decks.get_mut(&deck_id) {
+                    Some(shared) if shared.rejected => break,
+                    Some(shared) if (index as usize) < shared.fds.len() => {
+                        shared.ready = Some(index);
+                        shared.fresh = true;
+                    }
+                    _ => {}
+                },
+            }
+        }
+
+        // The deck falls back to pixels, or stopped
+        if let Some(deck_id) = deck {
+            tracing::info!("Deck {} stopped sharing GPU buffers", deck_id + 1);
+            if let Ok(mut decks) = decks.lock() {
+                decks.remove(&deck_id);
+            }
+        }
+    }
+
+    /// A deck's connection for sharing GPU buffers with the compositor
+    pub struct CompositorGpuFeed {
+        stream: UnixStream,
+        deck_id: u8,
+    }
+
+    impl CompositorGpuFeed {
+        /// Connect to the compositor's GPU sharing socket
+        pub fn connect(socket: &Path, deck_id: u8) -> Result<Self, VideoOutputError> {
+            let stream = UnixStream::connect(socket)
+                .and_then(|stream| {
+                    // Never hold up rendering for a busy compositor
+                    stream.set_nonblocking(true)?;
+                    Ok(stream)
+                })
+                .map_err(|e| {
+                    VideoOutputError::InitError(format!("GPU sharing connection failed: {}", e))
+                })?;
+            tracing::info!(
+                "Deck {} sharing GPU buffers via {}",
+                deck_id + 1,
+                socket.display()
+            );
+            Ok(Self { stream, deck_id })
+        }
+
+        /// Deck the buffers belong to
+        pub fn deck_id(&self) -> u8 {
+            self.deck_id
+        }
+
+        /// Share a new set of buffers, one descriptor per plane
+        pub fn send_buffers(
+            &mut self,
+            buffers: SharedBuffers,
+            fds: &[BorrowedFd<'_>],
+        ) -> Result<(), VideoOutputError> {
+            if fds.len() != buffers.planes.len() || fds.len() > MAX_SHARED_BUFFERS {
+                return Err(VideoOutputError::SendError(
+                    "One descriptor per shared buffer is needed".to_string(),
+                ));
+            }
+            let message = GpuMessage::Buffers {
+                deck_id: self.deck_id,
+                buffers,
+            };
+            let raw: Vec<RawFd> = fds.iter().map(|fd| fd.as_raw_fd()).collect();
+            send_with_fds(&self.stream, &message.to_bytes(), &raw)
+                .map_err(|e| VideoOutputError::SendError(format!("Sharing buffers failed: {}", e)))
+        }
+
+        /// Announce that buffer `index` holds a finished frame; skipped while
+        /// the compositor is behind
+        pub fn send_frame(&mut self, index: u8) -> Result<(), VideoOutputError> {
+            let message = GpuMessage::Frame {
+                deck_id: self.deck_id,
+                index,
+            };
+            match send_with_fds(&self.stream, &message.to_bytes(), &[]) {
+                Err(e) if e.kind() == ErrorKind::WouldBlock => Ok(()),
+                result => result.map_err(|e| {
+                    VideoOutputError::SendError(format!("Compositor disconnected: {}", e))
+                }),
+            }
+        }
+    }
+
+    /// Room for `MAX_SHARED_BUFFERS` descriptors, aligned for `cmsghdr`
+    type ControlBuffer = [u64; 8];
+
+    /// Send one message, with `fds` attached to its first byte
+    fn send_with_fds(stream: &UnixStream, bytes: &[u8], fds: &[RawFd]) -> std::io::Result<()> {
+        let mut iov = libc::iovec {
+            iov_base: bytes.as_ptr() as *mut _,
+            iov_len: bytes.len(),
+        };
+        let mut control: ControlBuffer = [0; 8];
+        // SAFETY: msghdr is plain data; all-zero is a valid empty header
+        let mut msg: libc::msghdr = unsafe { std::mem::zeroed() };
+        msg.msg_iov = &mut iov;
+        msg.msg_iovlen = 1;
+        if !fds.is_empty() {
+            let fd_bytes = std::mem::size_of_val(fds);
+            // SAFETY: the control buffer has room for MAX_SHARED_BUFFERS
+            // descriptors, which `send_buffers` checks
+            unsafe {
+                msg.msg_control = control.as_mut_ptr() as *mut _;
+                msg.msg_controllen = libc::CMSG_SPACE(fd_bytes as u32) as _;
+                let cmsg = libc::CMSG_FIRSTHDR(&msg);
+                (*cmsg).cmsg_level = libc::SOL_SOCKET;
+                (*cmsg).cmsg_type = libc::SCM_RIGHTS;
+                (*cmsg).cmsg_len = libc::CMSG_LEN(fd_bytes as u32) as _;
+                std::ptr::copy_nonoverlapping(
+                    fds.as_ptr() as *const u8,
+                    libc::CMSG_DATA(cmsg),
+                    fd_bytes,
+                );
+            }
+        }
+
+        // SAFETY: msg points at iov and control, which outlive the call
+        let sent = unsafe { libc::sendmsg(stream.as_raw_fd(), &msg, libc::MSG_NOSIGNAL) };
+        if sent < 0 {
+            return Err(std::io::Error::last_os_error());
+        }
+        let sent = sent as usize;
+        if sent < bytes.len() {
+            // A partial message would desync the stream, so finish it
+            let _ = stream.set_nonblocking(false);
+            let result = (&*stream).write_all(&bytes[sent..]);
+            let _ = stream.set_nonblocking(true);
+            result?;
+        }
+        Ok(())
+    }
+
+    /// Receive one message into `bytes`, returning descriptors sent with it
+    fn recv_with_fds(stream: &UnixStream, bytes: &mut [u8]) -> std::io::Result<Vec<OwnedFd>> {
+        let mut iov = libc::iovec {
+            iov_base: bytes.as_mut_ptr() as *mut _,
+            iov_len: bytes.len(),
+        };
+        let mut control: ControlBuffer = [0; 8];
+        // SAFETY: as in send_with_fds
+        let mut msg: libc::msghdr = unsafe { std::mem::zeroed() };
+        msg.msg_iov = &mut iov;
+        msg.msg_iovlen = 1;
+        msg.msg_control = control.as_mut_ptr() as *mut _;
+        msg.msg_controllen = std::mem::size_of::<ControlBuffer>() as _;
+
+        // SAFETY: msg points at iov and control, which outlive the call
+        let received =
+            unsafe { libc::recvmsg(stream.as_raw_fd(), &mut msg, libc::MSG_CMSG_CLOEXEC) };
+        if received < 0 {
+            return Err(std::io::Error::last_os_error());
+        }
+        if received == 0 {
+            return Err(ErrorKind::UnexpectedEof.into());
+        }
+
+        let mut fds = Vec::new();
+        // SAFETY: the kernel filled in the control messages it reported
+        unsafe {
+            let mut cmsg = libc::CMSG_FIRSTHDR(&msg);
+            while !cmsg.is_null() {
+                if (*cmsg).cmsg_level == libc::SOL_SOCKET && (*cmsg).cmsg_type == libc::SCM_RIGHTS {
+                    let data = libc::CMSG_DATA(cmsg) as *const RawFd;
+                    let count = ((*cmsg).cmsg_len as usize - libc::CMSG_LEN(0) as usize)
+                        / std::mem::size_of::<RawFd>();
+                    for i in 0..count {
+                        fds.push(OwnedFd::from_raw_fd(std::ptr::read_unaligned(data.add(i))));
+                    }
+                }
+                cmsg = libc::CMSG_NXTHDR(&msg, cmsg);
+            }
+        }
+
+        let received = received as usize;
+        if received < bytes.len() {
+            (&*stream).read_exact(&mut bytes[received..])?;
+        }
+        Ok(fds)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Instant;
+
+    #[test]
+    fn test_frame_header_round_trip() {
+        let header = FrameHeader {
+            deck_id: 2,
+            width: 1920,
+            height: 1080,
+        };
+        assert_eq!(FrameHeader::parse(&header.to_bytes()).unwrap(), header);
+        assert_eq!(header.pixel_len(), 1920 * 1080 * 4);
+
+        let mut bad = header.to_bytes();
+        bad[0] = b'X';
+        assert!(FrameHeader::parse(&bad).is_err());
+        let empty = FrameHeader { width: 0, ..header };
+        assert!(FrameHeader::parse(&empty.to_bytes()).is_err());
+    }
+
+    #[test]
+    fn test_gpu_message_round_trip() {
+        let buffers = GpuMessage::Buffers {
+            deck_id: 3,
+            buffers: SharedBuffers {
+                width: 1280,
+                height: 720,
+                fourcc: u32::from_le_bytes(*b"AB24"),
+                modifier: DRM_FORMAT_MOD_INVALID,
+                planes: vec![
+                    SharedPlane {
+                        stride: 5120,
+                        offset: 0,
+                    };
+                    MAX_SHARED_BUFFERS
+                ],
+            },
+        };
+        assert_eq!(GpuMessage::parse(&buffers.to_bytes()).unwrap(), buffers);
+        let frame = GpuMessage::Frame {
+            deck_id: 1,
+            index: 2,
+        };
+        assert_eq!(GpuMessage::parse(&frame.to_bytes()).unwrap(), frame);
+
+        let out_of_range = GpuMessage::Frame {
+            deck_id: 1,
+            index: MAX_SHARED_BUFFERS as u8,
+        };
+        assert!(GpuMessage::parse(&out_of_range.to_bytes()).is_err());
+        let mut bad = frame.to_bytes();
+        bad[4] = 7;
+        assert!(GpuMessage::parse(&bad).is_err());
+    }
+
+    #[test]
+    #[cfg(target_os = "linux")]
+    fn test_gpu_feed_passes_descriptors() {
+        use std::io::Seek;
+        use std::os::fd::{AsFd, OwnedFd};
+
+        let mut receiver = CompositorReceiver::bind().unwrap();
+        let socket = receiver.listen_gpu().unwrap().to_path_buf();
+        let mut feed = CompositorGpuFeed::connect(&socket, 2).unwrap();
+
+        // Any descriptor will do to check it arrives
+        let mut file = tempfile::tempfile().unwrap();
+        file.write_all(b"shared").unwrap();
+        file.rewind().unwrap();
+        let buffers = SharedBuffers {
+            width: 4,
+            height: 4,
+            fourcc: 0,
+            modifier: DRM_FORMAT_MOD_INVALID,
+            planes: vec![SharedPlane::default()],
+        };
+        feed.send_buffers(buffers.clone(), &[file.as_fd()]).unwrap();
+        feed.send_frame(0).unwrap();
+
+        let deadline = Instant::now() + Duration::from_secs(5);
+        let mut received = None;
+        while received.is_none() && Instant::now() < deadline {
+            receiver.with_shared_frame(2, |frame| {
+                if frame.ready.is_some() {
+                    let fd: OwnedFd = frame.fds[0].try_clone().unwrap();
+                    received = Some((frame.buffers.clone(), fd, frame.fresh));
+                }
+            });
+            thread::sleep(Duration::from_millis(10));
+        }
+        let (shared, fd, fresh) = received.expect("buffers should arrive");
+        assert_eq!(shared, buffers);
+        assert!(fresh);
+        let mut contents = String::new();
+        std::fs::File::from(fd)
+            .read_to_string(&mut contents)
+            .unwrap();
+        assert_eq!(contents, "shared");
+
+        // Rejected decks are dropped
+        receiver.reject_shared(2);
+        assert!(!receiver.with_shared_frame(2, |_| {}));
+        drop(receiver);
+        assert!(!socket.exists());
+    }
+
+    #[test]
+    fn test_blend_mode_cycles() {
+        let mut mode = BlendMode::Normal;
+        for _ in 0..5 {
+            mode = mode.next();
+        }
+        assert_eq!(mode, BlendMode::Normal);
+    }
+
+    #[test]
+    fn test_feed_reaches_receiver() {
+        let receiver = CompositorReceiver::bind().unwrap();
+        let mut feed = CompositorFeed::connect(receiver.port(), 1).unwrap();
+        let pixels = [10u8, 20, 30, 255].repeat(2 * 2);
+        feed.send_frame_rgba(&pixels, 2, 2).unwrap();
+
+        let deadline = Instant::now() + Duration::from_secs(5);
+        let mut received = None;
+        while received.is_none() && Instant::now() < deadline {
+            receiver.with_frame(1, |frame, fresh| {
+                received = Some((frame.pixels.to_vec(), frame.width, fresh));
+            });
+            thread::sleep(Duration::from_millis(10));
+        }
+        assert_eq!(received, Some((pixels, 2, true)));
+        // Handed out once
+        receiver.with_frame(1, |_, fresh| assert!(!fresh));
+        assert!(!receiver.with_frame(0, |_, _| {}));
+    }
+}
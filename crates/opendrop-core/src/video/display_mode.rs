@@ -0,0 +1,183 @@
+//! Monitor video modes for exclusive fullscreen
+//!
+//! Borderless fullscreen runs at whatever the desktop is set to. Exclusive
+//! fullscreen switches the monitor to a chosen resolution and refresh rate
+//! while the deck window covers it, e.g. 1280x720 at 50 Hz to match a
+//! projector or a PAL broadcast chain.
+
+use serde::{Deserialize, Serialize};
+
+use super::output::VideoOutputError;
+
+/// A resolution and refresh rate a monitor can run at
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DisplayMode {
+    pub width: u32,
+    pub height: u32,
+    /// Refresh rate in millihertz, e.g. 59940 for 59.94 Hz
+    pub refresh_millihertz: u32,
+}
+
+impl std::fmt::Display for DisplayMode {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{}x{} @ {:.2} Hz",
+            self.width,
+            self.height,
+            self.refresh_millihertz as f64 / 1000.0
+        )
+    }
+}
+
+/// Mode asked for when a deck goes exclusive fullscreen
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ExclusiveMode {
+    pub width: u32,
+    pub height: u32,
+    /// Refresh rate in millihertz; the fastest the resolution offers when
+    /// unset
+    #[serde(default)]
+    pub refresh_millihertz: Option<u32>,
+}
+
+impl ExclusiveMode {
+    pub fn validate(&self) -> Result<(), VideoOutputError> {
+        if self.width == 0 || self.height == 0 {
+            return Err(VideoOutputError::InitError(format!(
+                "Invalid fullscreen resolution {}x{}",
+                self.width, self.height
+            )));
+        }
+        if self.refresh_millihertz == Some(0) {
+            return Err(VideoOutputError::InitError(
+                "Invalid fullscreen refresh rate 0".to_string(),
+            ));
+        }
+        Ok(())
+    }
+
+    /// Index of the mode in `modes` closest to this one: the same
+    /// resolution at the nearest refresh rate, or the fastest; the first
+    /// of equally good modes wins
+    pub fn pick(&self, modes: &[DisplayMode]) -> Option<usize> {
+        let candidates = modes
+            .iter()
+            .enumerate()
+            .filter(|(_, mode)| mode.width == self.width && mode.height == self.height);
+        match self.refresh_millihertz {
+            Some(refresh) => candidates
+                .min_by_key(|(_, mode)| mode.refresh_millihertz.abs_diff(refresh))
+                .map(|(index, _)| index),
+            // max_by_key keeps the last of equals, so compare reversed
+            None => candidates
+                .min_by_key(|(_, mode)| std::cmp::Reverse(mode.refresh_millihertz))
+                .map(|(index, _)| index),
+        }
+    }
+}
+
+/// Modes on an indented mode line of `xrandr --query`, like
+/// `   1920x1080     60.00*+  59.94    50.00`; interlaced modes are skipped
+pub fn parse_xrandr_modes(line: &str) -> Vec<DisplayMode> {
+    if !line.starts_with(char::is_whitespace) {
+        return Vec::new();
+    }
+    let mut parts = line.split_whitespace();
+    let Some((width, height)) = parts.next().and_then(|size| size.split_once('x')) else {
+        return Vec::new();
+    };
+    let (Ok(width), Ok(height)) = (width.parse::<u32>(), height.parse::<u32>()) else {
+        return Vec::new();
+    };
+    parts
+        .filter_map(|rate| rate.trim_end_matches(['*', '+']).parse::<f64>().ok())
+        .filter(|rate| *rate > 0.0)
+        .map(|rate| DisplayMode {
+            width,
+            height,
+            refresh_millihertz: (rate * 1000.0).round() as u32,
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn mode(width: u32, height: u32, refresh_millihertz: u32) -> DisplayMode {
+        DisplayMode {
+            width,
+            height,
+            refresh_millihertz,
+        }
+    }
+
+    #[test]
+    fn test_pick() {
+        let modes = [
+            mode(1920, 1080, 60000),
+            mode(1920, 1080, 59940),
+            mode(1920, 1080, 50000),
+            mode(1280, 720, 60000),
+        ];
+        let wanted = ExclusiveMode {
+            width: 1920,
+            height: 1080,
+            refresh_millihertz: Some(50000),
+        };
+        assert_eq!(wanted.pick(&modes), Some(2));
+        let wanted = ExclusiveMode {
+            refresh_millihertz: Some(59950),
+            ..wanted
+        };
+        assert_eq!(wanted.pick(&modes), Some(1));
+        // The fastest, and the first of the same rate
+        let fastest = ExclusiveMode {
+            refresh_millihertz: None,
+            ..wanted
+        };
+        assert_eq!(fastest.pick(&modes), Some(0));
+        let missing = ExclusiveMode {
+            width: 800,
+            height: 600,
+            refresh_millihertz: None,
+        };
+        assert_eq!(missing.pick(&modes), None);
+    }
+
+    #[test]
+    fn test_validate() {
+        let wanted: ExclusiveMode =
+            serde_json::from_str(r#"{"width": 1280, "height": 720}"#).unwrap();
+        assert!(wanted.validate().is_ok());
+        for invalid in [
+            ExclusiveMode { width: 0, ..wanted },
+            ExclusiveMode {
+                refresh_millihertz: Some(0),
+                ..wanted
+            },
+        ] {
+            assert!(invalid.validate().is_err(), "{:?}", invalid);
+        }
+    }
+
+    #[test]
+    fn test_parse_xrandr_modes() {
+        assert_eq!(
+            parse_xrandr_modes("   1920x1080     60.00*+  59.94    50.00  "),
+            vec![
+                mode(1920, 1080, 60000),
+                mode(1920, 1080, 59940),
+                mode(1920, 1080, 50000)
+            ]
+        );
+        assert_eq!(
+            parse_xrandr_modes("   1280x720      60.00 +  50.00"),
+            vec![mode(1280, 720, 60000), mode(1280, 720, 50000)]
+        );
+        assert!(parse_xrandr_modes("   1920x1080i    60.00    50.00").is_empty());
+        assert!(parse_xrandr_modes("HDMI-1 connected primary 1920x1080+0+0").is_empty());
+        assert_eq!(mode(1920, 1080, 59940).to_string(), "1920x1080 @ 59.94 Hz");
+    }
+}
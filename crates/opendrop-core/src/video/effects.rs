@@ -0,0 +1,148 @@
+//! Post-processing effects drawn over a deck's frame
+//!
+//! The renderer runs every effect with a non-zero intensity on the GPU, in a
+//! fixed order: pixelate and kaleidoscope move pixels around, RGB shift and
+//! chromatic aberration split the colour channels, and bloom adds glow last.
+
+use std::fmt;
+use std::str::FromStr;
+
+use serde::{Deserialize, Serialize};
+
+/// A post-processing effect
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Effect {
+    /// Glow around bright areas
+    Bloom,
+    /// Colour fringes growing towards the edges, like a cheap lens
+    ChromaticAberration,
+    /// Blocky low-resolution look
+    Pixelate,
+    /// Mirrored segments around the centre
+    Kaleidoscope,
+    /// Red and blue channels pushed apart sideways
+    RgbShift,
+}
+
+impl Effect {
+    /// Every effect, in the order the renderer applies them
+    pub const ALL: [Effect; 5] = [
+        Effect::Pixelate,
+        Effect::Kaleidoscope,
+        Effect::RgbShift,
+        Effect::ChromaticAberration,
+        Effect::Bloom,
+    ];
+
+    /// Position in [`Effect::ALL`]
+    pub fn index(self) -> usize {
+        match self {
+            Effect::Pixelate => 0,
+            Effect::Kaleidoscope => 1,
+            Effect::RgbShift => 2,
+            Effect::ChromaticAberration => 3,
+            Effect::Bloom => 4,
+        }
+    }
+}
+
+impl fmt::Display for Effect {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Effect::Bloom => "bloom",
+            Effect::ChromaticAberration => "chromatic_aberration",
+            Effect::Pixelate => "pixelate",
+            Effect::Kaleidoscope => "kaleidoscope",
+            Effect::RgbShift => "rgb_shift",
+        })
+    }
+}
+
+impl FromStr for Effect {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().replace('-', "_").as_str() {
+            "bloom" => Ok(Effect::Bloom),
+            "chromatic_aberration" | "chroma" => Ok(Effect::ChromaticAberration),
+            "pixelate" => Ok(Effect::Pixelate),
+            "kaleidoscope" => Ok(Effect::Kaleidoscope),
+            "rgb_shift" | "rgb" => Ok(Effect::RgbShift),
+            _ => Err(format!(
+                "Unknown effect: {}. Use: bloom, chromatic_aberration, pixelate, kaleidoscope, rgb_shift",
+                s
+            )),
+        }
+    }
+}
+
+/// Intensity of each effect, 0.0 (off) to 1.0
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub struct EffectChain {
+    intensities: [f32; Effect::ALL.len()],
+}
+
+impl EffectChain {
+    /// Set an effect's intensity, clamped to 0.0-1.0
+    pub fn set(&mut self, effect: Effect, intensity: f32) {
+        self.intensities[effect.index()] = if intensity.is_finite() {
+            intensity.clamp(0.0, 1.0)
+        } else {
+            0.0
+        };
+    }
+
+    /// An effect's intensity
+    pub fn get(&self, effect: Effect) -> f32 {
+        self.intensities[effect.index()]
+    }
+
+    /// Whether every effect is off
+    pub fn is_empty(&self) -> bool {
+        self.intensities.iter().all(|&intensity| intensity == 0.0)
+    }
+
+    /// Effects that are on, in the order they're applied
+    pub fn active(&self) -> impl Iterator<Item = (Effect, f32)> + '_ {
+        Effect::ALL
+            .into_iter()
+            .map(|effect| (effect, self.get(effect)))
+            .filter(|&(_, intensity)| intensity > 0.0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_effect_names_round_trip() {
+        for (i, effect) in Effect::ALL.into_iter().enumerate() {
+            assert_eq!(effect.index(), i);
+            assert_eq!(effect.to_string().parse::<Effect>(), Ok(effect));
+            let json = serde_json::to_string(&effect).unwrap();
+            assert_eq!(json, format!("\"{}\"", effect));
+        }
+        assert_eq!("RGB-Shift".parse::<Effect>(), Ok(Effect::RgbShift));
+        assert!("blur".parse::<Effect>().is_err());
+    }
+
+    #[test]
+    fn test_effect_chain() {
+        let mut chain = EffectChain::default();
+        assert!(chain.is_empty());
+
+        chain.set(Effect::Bloom, 0.5);
+        chain.set(Effect::Pixelate, 3.0);
+        chain.set(Effect::RgbShift, f32::NAN);
+        assert_eq!(chain.get(Effect::Pixelate), 1.0);
+        assert_eq!(chain.get(Effect::RgbShift), 0.0);
+        let active: Vec<_> = chain.active().collect();
+        assert_eq!(active, [(Effect::Pixelate, 1.0), (Effect::Bloom, 0.5)]);
+
+        chain.set(Effect::Bloom, 0.0);
+        chain.set(Effect::Pixelate, -1.0);
+        assert!(chain.is_empty());
+    }
+}
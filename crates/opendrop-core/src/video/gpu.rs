@@ -0,0 +1,115 @@
+//! GPU choice for renderer processes
+//!
+//! On laptops with an integrated and a discrete GPU, GL picks one for the
+//! whole process when the context is created. The app can only steer that
+//! from outside, before the renderer starts: through the environment on
+//! Linux (Mesa's PRIME offload and NVIDIA's render offload) and the per-app
+//! graphics preference on Windows. The renderer reports the GL strings of
+//! the GPU it got, so the choice can be checked.
+
+use serde::{Deserialize, Serialize};
+
+/// Which GPU renderer processes ask for
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum GpuPreference {
+    /// Whatever the system picks
+    #[default]
+    Default,
+    /// The integrated GPU
+    LowPower,
+    /// The discrete GPU
+    HighPerformance,
+}
+
+impl GpuPreference {
+    /// Environment for renderer processes on Linux; NVIDIA's variables are
+    /// only set when its driver is loaded, as GLVND fails without it
+    pub fn linux_env(self, nvidia: bool) -> Vec<(&'static str, &'static str)> {
+        match self {
+            GpuPreference::Default => Vec::new(),
+            GpuPreference::LowPower => vec![("DRI_PRIME", "0")],
+            GpuPreference::HighPerformance if nvidia => vec![
+                ("DRI_PRIME", "1"),
+                ("__NV_PRIME_RENDER_OFFLOAD", "1"),
+                ("__GLX_VENDOR_LIBRARY_NAME", "nvidia"),
+            ],
+            GpuPreference::HighPerformance => vec![("DRI_PRIME", "1")],
+        }
+    }
+
+    /// Value of the Windows per-app graphics preference
+    /// (`HKCU\Software\Microsoft\DirectX\UserGpuPreferences`); None leaves
+    /// the choice to Windows
+    pub fn windows_value(self) -> Option<&'static str> {
+        match self {
+            GpuPreference::Default => None,
+            GpuPreference::LowPower => Some("GpuPreference=1;"),
+            GpuPreference::HighPerformance => Some("GpuPreference=2;"),
+        }
+    }
+}
+
+/// The GPU a renderer's GL context runs on, from its GL strings
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct GpuInfo {
+    pub vendor: String,
+    pub renderer: String,
+    pub version: String,
+}
+
+impl GpuInfo {
+    /// Whether GL fell back to drawing on the CPU
+    pub fn is_software(&self) -> bool {
+        let renderer = self.renderer.to_lowercase();
+        [
+            "llvmpipe",
+            "softpipe",
+            "swrast",
+            "software rasterizer",
+            "gdi generic",
+        ]
+        .iter()
+        .any(|name| renderer.contains(name))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_linux_env() {
+        assert!(GpuPreference::Default.linux_env(true).is_empty());
+        assert_eq!(
+            GpuPreference::LowPower.linux_env(true),
+            vec![("DRI_PRIME", "0")]
+        );
+        assert_eq!(
+            GpuPreference::HighPerformance.linux_env(false),
+            vec![("DRI_PRIME", "1")]
+        );
+        assert!(GpuPreference::HighPerformance
+            .linux_env(true)
+            .contains(&("__GLX_VENDOR_LIBRARY_NAME", "nvidia")));
+        assert_eq!(GpuPreference::Default.windows_value(), None);
+        assert_eq!(
+            GpuPreference::HighPerformance.windows_value(),
+            Some("GpuPreference=2;")
+        );
+    }
+
+    #[test]
+    fn test_software_renderer() {
+        let info = |renderer: &str| GpuInfo {
+            vendor: "Mesa".to_string(),
+            renderer: renderer.to_string(),
+            version: "4.5 (Core Profile) Mesa 24.0.5".to_string(),
+        };
+        assert!(info("llvmpipe (LLVM 17.0.6, 256 bits)").is_software());
+        assert!(
+            !info("AMD Radeon 780M (radeonsi, gfx1103_r1, LLVM 17.0.6, DRM 3.57)").is_software()
+        );
+        assert!(!info("NVIDIA GeForce RTX 4060 Laptop GPU/PCIe/SSE2").is_software());
+    }
+}
@@ -0,0 +1,111 @@
+//! Idle visual for decks without a preset
+//!
+//! A deck started with an empty preset folder would otherwise show a black
+//! window. Instead the renderer draws the logo over a row of spectrum bars,
+//! so the deck looks presentable and shows it is getting audio.
+
+use std::time::Instant;
+
+use crate::audio::spectrum::SpectrumAnalyzer;
+use crate::audio::AudioConfig;
+
+/// Bars across the idle visual
+pub const IDLE_BARS: usize = 32;
+
+/// FFT window for the bars; short, so they follow the music closely
+const IDLE_FFT_SIZE: usize = 1024;
+
+/// How far a bar drops per second once the level under it falls
+const BAR_FALL_PER_SEC: f32 = 1.5;
+
+/// Spectrum bars that jump up with the audio and fall back slowly
+pub struct IdleBars {
+    spectrum: SpectrumAnalyzer,
+    levels: [f32; IDLE_BARS],
+    /// Whether audio arrived since the last update
+    fresh: bool,
+    updated: Option<Instant>,
+}
+
+impl Default for IdleBars {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl IdleBars {
+    pub fn new() -> Self {
+        let config = AudioConfig::default();
+        Self {
+            spectrum: SpectrumAnalyzer::new(IDLE_FFT_SIZE, config.sample_rate, config.channels),
+            levels: [0.0; IDLE_BARS],
+            fresh: false,
+            updated: None,
+        }
+    }
+
+    /// Feed interleaved samples with `channels` channels
+    pub fn push(&mut self, samples: &[f32], channels: u16) {
+        let config = AudioConfig::default();
+        self.spectrum.set_format(config.sample_rate, channels);
+        self.spectrum.push(samples);
+        self.fresh = true;
+    }
+
+    /// Bar heights at `now`, 0-1, low to high frequency; they fall to 0
+    /// when the audio stops
+    pub fn update(&mut self, now: Instant) -> &[f32; IDLE_BARS] {
+        let elapsed = self.updated.map_or(0.0, |updated| {
+            now.saturating_duration_since(updated).as_secs_f32()
+        });
+        self.updated = Some(now);
+
+        let bands = if self.fresh {
+            self.spectrum.bands(IDLE_BARS)
+        } else {
+            vec![0.0; IDLE_BARS]
+        };
+        self.fresh = false;
+
+        let fall = BAR_FALL_PER_SEC * elapsed;
+        for (level, band) in self.levels.iter_mut().zip(bands) {
+            *level = band.max(*level - fall).max(0.0);
+        }
+        &self.levels
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::f32::consts::PI;
+    use std::time::Duration;
+
+    #[test]
+    fn test_bars_follow_audio() {
+        let start = Instant::now();
+        let mut bars = IdleBars::new();
+        assert!(bars.update(start).iter().all(|&level| level == 0.0));
+
+        // A loud 100 Hz tone lifts the low bars only
+        let tone: Vec<f32> = (0..IDLE_FFT_SIZE)
+            .map(|i| (2.0 * PI * 100.0 * i as f32 / 48000.0).sin())
+            .collect();
+        bars.push(&tone, 1);
+        let levels = *bars.update(start + Duration::from_millis(16));
+        let peak = (0..IDLE_BARS)
+            .max_by(|&a, &b| levels[a].total_cmp(&levels[b]))
+            .unwrap();
+        assert!(levels[peak] > 0.5);
+        assert!(peak < IDLE_BARS / 3);
+        assert!(levels[IDLE_BARS - 1] < levels[peak]);
+
+        // Without more audio the bars fall back, slowly, then to nothing
+        let falling = bars.update(start + Duration::from_millis(116))[peak];
+        assert!(falling > 0.0 && falling < levels[peak]);
+        assert!(bars
+            .update(start + Duration::from_secs(2))
+            .iter()
+            .all(|&level| level == 0.0));
+    }
+}
@@ -0,0 +1,189 @@
+//! Video input abstraction
+//!
+//! Receives frames from other applications so a deck can mix them into its
+//! visualization, e.g. a camera feed or another VJ tool:
+//! - Spout (Windows texture sharing)
+//! - NDI (network video streaming)
+//! - Screen capture (the desktop or a region of it, through ffmpeg)
+
+use serde::{Deserialize, Serialize};
+
+use super::output::VideoOutputError;
+
+/// Video input backend type
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum InputBackend {
+    /// NDI (cross-platform network video)
+    Ndi,
+    /// Spout (Windows texture sharing)
+    Spout,
+    /// Desktop capture through ffmpeg
+    Screen,
+}
+
+impl std::fmt::Display for InputBackend {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            InputBackend::Ndi => write!(f, "NDI"),
+            InputBackend::Spout => write!(f, "Spout"),
+            InputBackend::Screen => write!(f, "Screen"),
+        }
+    }
+}
+
+impl InputBackend {
+    /// Check if the backend can receive on this system
+    pub fn is_available(self) -> bool {
+        match self {
+            InputBackend::Ndi => super::ndi::NdiInput::is_available(),
+            InputBackend::Spout => super::spout::SpoutInput::is_available(),
+            InputBackend::Screen => super::screen::ScreenInput::is_available(),
+        }
+    }
+
+    /// Open a receiver for `source`, or the first/active source if `None`;
+    /// for screen capture `source` is a region, the whole screen if `None`
+    pub fn open(self, source: Option<&str>) -> Result<Box<dyn VideoInput>, VideoOutputError> {
+        match self {
+            InputBackend::Ndi => Ok(Box::new(super::ndi::NdiInput::new(source)?)),
+            InputBackend::Spout => Ok(Box::new(super::spout::SpoutInput::new(source)?)),
+            InputBackend::Screen => Ok(Box::new(super::screen::ScreenInput::new(source)?)),
+        }
+    }
+}
+
+/// How an input layer is mixed into the visualization
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum InputBlend {
+    /// Crossfade over the visualization by opacity
+    #[default]
+    Normal,
+    /// Add the input's light
+    Add,
+    /// Darken the visualization with the input
+    Multiply,
+    /// Lighten: the input shows through where the visualization is dark
+    Screen,
+}
+
+impl std::str::FromStr for InputBlend {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "normal" => Ok(InputBlend::Normal),
+            "add" | "additive" => Ok(InputBlend::Add),
+            "multiply" => Ok(InputBlend::Multiply),
+            "screen" => Ok(InputBlend::Screen),
+            _ => Err(format!(
+                "Unknown input blend: {}. Use: normal, add, multiply, screen",
+                s
+            )),
+        }
+    }
+}
+
+/// Whether an input layer goes over or under the visualization
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum InputPlacement {
+    /// Over the visualization, after post effects
+    #[default]
+    Over,
+    /// Under the visualization, before post effects; with the normal blend
+    /// it shows where the preset is transparent
+    Under,
+}
+
+impl std::str::FromStr for InputPlacement {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "over" => Ok(InputPlacement::Over),
+            "under" => Ok(InputPlacement::Under),
+            _ => Err(format!("Unknown input placement: {}. Use: over, under", s)),
+        }
+    }
+}
+
+/// A received frame (RGBA, top row first)
+#[derive(Debug, Clone, Copy)]
+pub struct InputFrame<'a> {
+    pub pixels: &'a [u8],
+    pub width: u32,
+    pub height: u32,
+}
+
+/// Trait for video input implementations
+pub trait VideoInput: Send {
+    /// Get the backend type
+    fn backend(&self) -> InputBackend;
+
+    /// Name of the source being received
+    fn source_name(&self) -> &str;
+
+    /// Poll for a new frame; `None` until the source sends one
+    fn receive_frame(&mut self) -> Result<Option<InputFrame<'_>>, VideoOutputError>;
+}
+
+/// Largest rectangle with the source's aspect ratio that fits the target,
+/// centred, as normalized device coordinates `(x0, y0, x1, y1)`
+pub fn fit_rect(
+    src_width: u32,
+    src_height: u32,
+    dst_width: u32,
+    dst_height: u32,
+) -> (f32, f32, f32, f32) {
+    if src_width == 0 || src_height == 0 || dst_width == 0 || dst_height == 0 {
+        return (-1.0, -1.0, 1.0, 1.0);
+    }
+    let src_aspect = src_width as f32 / src_height as f32;
+    let dst_aspect = dst_width as f32 / dst_height as f32;
+    if src_aspect > dst_aspect {
+        // Wider than the target: bars above and below
+        let h = dst_aspect / src_aspect;
+        (-1.0, -h, 1.0, h)
+    } else {
+        let w = src_aspect / dst_aspect;
+        (-w, -1.0, w, 1.0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_input_blend_from_str() {
+        assert_eq!("screen".parse::<InputBlend>(), Ok(InputBlend::Screen));
+        assert_eq!("Additive".parse::<InputBlend>(), Ok(InputBlend::Add));
+        assert!("overlay".parse::<InputBlend>().is_err());
+        assert_eq!("Under".parse::<InputPlacement>(), Ok(InputPlacement::Under));
+        assert!("behind".parse::<InputPlacement>().is_err());
+    }
+
+    #[test]
+    fn test_input_backend_serde() {
+        let backend: InputBackend = serde_json::from_str("\"spout\"").unwrap();
+        assert_eq!(backend, InputBackend::Spout);
+        assert_eq!(format!("{}", InputBackend::Ndi), "NDI");
+        let backend: InputBackend = serde_json::from_str("\"screen\"").unwrap();
+        assert_eq!(backend, InputBackend::Screen);
+    }
+
+    #[test]
+    fn test_fit_rect_letterboxes() {
+        // Same aspect fills the target
+        assert_eq!(fit_rect(1920, 1080, 1280, 720), (-1.0, -1.0, 1.0, 1.0));
+        // 4:3 camera in a 16:9 deck gets side bars
+        let (x0, y0, x1, y1) = fit_rect(640, 480, 1920, 1080);
+        assert!((x1 - 0.75).abs() < 1e-6 && x0 == -x1);
+        assert_eq!((y0, y1), (-1.0, 1.0));
+        // Ultra-wide source gets bars above and below
+        let (_, y0, _, y1) = fit_rect(3840, 1080, 1920, 1080);
+        assert_eq!((y0, y1), (-0.5, 0.5));
+    }
+}
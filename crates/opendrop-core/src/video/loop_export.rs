@@ -0,0 +1,428 @@
+//! Seamlessly looping clips of deck output
+//!
+//! Records a fixed number of beats and turns them into a WebM or GIF that
+//! loops without a visible jump, for social clips of presets. Frames are
+//! first recorded to an intermediate H.264 file like any recording; once the
+//! loop is long enough a second ffmpeg pass crossfades its end into its
+//! start and encodes the final file.
+
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+
+use serde::{Deserialize, Serialize};
+
+use super::output::{OutputBackend, VideoOutput, VideoOutputError};
+use super::recorder::{RecordingConfig, VideoRecorder};
+
+/// Default frame rate of loops
+pub const DEFAULT_LOOP_FPS: u32 = 30;
+
+/// Default crossfade between the end and start of a loop (seconds)
+pub const DEFAULT_LOOP_CROSSFADE_SECS: f64 = 1.0;
+
+/// Longest loop that can be recorded (seconds)
+pub const MAX_LOOP_SECS: f64 = 60.0;
+
+/// GIF frame rate; GIF delays are in 1/100 s, so 25 fps keeps time exactly
+const GIF_FPS: u32 = 25;
+
+/// GIFs wider than this are scaled down
+const GIF_MAX_WIDTH: u32 = 640;
+
+/// Bitrate of the intermediate recording, high enough to be near-lossless
+const INTERMEDIATE_BITRATE_KBPS: u32 = 40_000;
+
+/// File format of an exported loop
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum LoopFormat {
+    /// VP9 WebM
+    #[default]
+    WebM,
+    /// Animated GIF with an optimized palette
+    Gif,
+}
+
+impl std::fmt::Display for LoopFormat {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            LoopFormat::WebM => write!(f, "WebM"),
+            LoopFormat::Gif => write!(f, "GIF"),
+        }
+    }
+}
+
+impl std::str::FromStr for LoopFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "webm" => Ok(LoopFormat::WebM),
+            "gif" => Ok(LoopFormat::Gif),
+            _ => Err(format!("Unknown loop format: {}. Use: webm, gif", s)),
+        }
+    }
+}
+
+impl LoopFormat {
+    /// File extension of the format
+    pub fn extension(self) -> &'static str {
+        match self {
+            LoopFormat::WebM => "webm",
+            LoopFormat::Gif => "gif",
+        }
+    }
+}
+
+/// Length of `beats` beats at `bpm` in seconds
+pub fn loop_duration_secs(beats: u32, bpm: f32) -> f64 {
+    if bpm <= 0.0 {
+        return 0.0;
+    }
+    beats as f64 * 60.0 / bpm as f64
+}
+
+/// Loop export settings
+#[derive(Debug, Clone)]
+pub struct LoopExportConfig {
+    /// WebM or GIF file to write
+    pub path: PathBuf,
+    pub format: LoopFormat,
+    /// Length of the finished loop (seconds)
+    pub duration_secs: f64,
+    /// Crossfade of the end into the start (seconds, 0 for a hard cut); this
+    /// much is recorded on top of the loop's length
+    pub crossfade_secs: f64,
+    /// Recording frame rate
+    pub fps: u32,
+    /// Frame size
+    pub width: u32,
+    pub height: u32,
+    /// ffmpeg executable
+    pub ffmpeg: PathBuf,
+}
+
+impl LoopExportConfig {
+    /// Loop of `duration_secs` with the default frame rate and crossfade
+    pub fn new(
+        path: impl Into<PathBuf>,
+        format: LoopFormat,
+        duration_secs: f64,
+        width: u32,
+        height: u32,
+    ) -> Self {
+        Self {
+            path: path.into(),
+            format,
+            duration_secs,
+            crossfade_secs: DEFAULT_LOOP_CROSSFADE_SECS,
+            fps: DEFAULT_LOOP_FPS,
+            width,
+            height,
+            ffmpeg: PathBuf::from("ffmpeg"),
+        }
+    }
+
+    /// Length of the section to record (seconds)
+    pub fn record_secs(&self) -> f64 {
+        self.duration_secs + self.crossfade_secs.clamp(0.0, self.duration_secs)
+    }
+
+    /// Frames to record
+    pub fn frames(&self) -> u64 {
+        (self.record_secs() * self.fps.max(1) as f64).round() as u64
+    }
+
+    /// Intermediate recording next to the final file
+    pub fn intermediate_path(&self) -> PathBuf {
+        let mut name = self.path.file_name().unwrap_or_default().to_os_string();
+        name.push(".part.mp4");
+        self.path.with_file_name(name)
+    }
+
+    /// Filter graph turning a recorded section of `duration_secs` into the loop
+    pub fn filter_graph(&self, duration_secs: f64) -> String {
+        // At most half the section can be blended into the other half
+        let fade = self.crossfade_secs.clamp(0.0, duration_secs / 2.0);
+        let looped = if fade > 0.0 {
+            // The tail fades into the first `fade` seconds, which are cut from
+            // the start, so the last frame leads straight into the first
+            format!(
+                "[0:v]split[tail][start];\
+                 [tail]trim=start={fade:.3},setpts=PTS-STARTPTS[body];\
+                 [start]trim=end={fade:.3},setpts=PTS-STARTPTS[head];\
+                 [body][head]xfade=transition=fade:duration={fade:.3}:offset={offset:.3}[loop]",
+                fade = fade,
+                offset = duration_secs - 2.0 * fade,
+            )
+        } else {
+            "[0:v]null[loop]".to_string()
+        };
+        let output = match self.format {
+            LoopFormat::WebM => "[loop]format=yuv420p[out]".to_string(),
+            LoopFormat::Gif => format!(
+                "[loop]fps={},scale='min({},iw)':-2:flags=lanczos,split[frames][colors];\
+                 [colors]palettegen=stats_mode=diff[palette];\
+                 [frames][palette]paletteuse=dither=bayer:bayer_scale=3[out]",
+                GIF_FPS, GIF_MAX_WIDTH
+            ),
+        };
+        format!("{};{}", looped, output)
+    }
+
+    /// ffmpeg arguments encoding the intermediate `input` of `duration_secs`
+    pub fn encode_args(&self, input: &Path, duration_secs: f64) -> Vec<String> {
+        let mut args: Vec<String> = [
+            "-hide_banner",
+            "-loglevel",
+            "error",
+            "-i",
+            &input.to_string_lossy(),
+            "-filter_complex",
+            &self.filter_graph(duration_secs),
+            "-map",
+            "[out]",
+            "-an",
+        ]
+        .iter()
+        .map(|arg| arg.to_string())
+        .collect();
+        let codec: &[&str] = match self.format {
+            LoopFormat::WebM => &[
+                "-c:v",
+                "libvpx-vp9",
+                "-b:v",
+                "0",
+                "-crf",
+                "32",
+                "-row-mt",
+                "1",
+            ],
+            LoopFormat::Gif => &["-loop", "0"],
+        };
+        args.extend(codec.iter().map(|arg| arg.to_string()));
+        args.push("-y".to_string());
+        args.push(self.path.to_string_lossy().to_string());
+        args
+    }
+}
+
+/// Summary of an exported loop
+#[derive(Debug, Clone)]
+pub struct LoopExportInfo {
+    /// Path of the loop file
+    pub path: PathBuf,
+    /// Length of the finished loop (seconds)
+    pub duration_secs: f64,
+}
+
+/// Records a loop and encodes it once enough frames have been captured
+pub struct LoopRecorder {
+    config: LoopExportConfig,
+    recorder: Option<VideoRecorder>,
+    target_frames: u64,
+    name: String,
+    active: bool,
+}
+
+impl LoopRecorder {
+    /// Start recording the section the loop is made from
+    pub fn start(config: LoopExportConfig) -> Result<Self, VideoOutputError> {
+        if !(config.duration_secs > 0.0 && config.duration_secs <= MAX_LOOP_SECS) {
+            return Err(VideoOutputError::InitError(format!(
+                "Loop length must be 0-{} seconds",
+                MAX_LOOP_SECS
+            )));
+        }
+
+        let mut recording =
+            RecordingConfig::new(config.intermediate_path(), config.width, config.height);
+        recording.fps = config.fps;
+        recording.bitrate_kbps = INTERMEDIATE_BITRATE_KBPS;
+        recording.ffmpeg = config.ffmpeg.clone();
+        let recorder = VideoRecorder::start(recording)?;
+
+        tracing::info!(
+            "Recording {:.1}s {} loop to {}",
+            config.record_secs(),
+            config.format,
+            config.path.display()
+        );
+        Ok(Self {
+            name: format!("loop:{}", config.path.display()),
+            target_frames: config.frames().max(1),
+            config,
+            recorder: Some(recorder),
+            active: true,
+        })
+    }
+
+    /// Path of the loop file
+    pub fn path(&self) -> &Path {
+        &self.config.path
+    }
+
+    /// Whether all frames of the loop have been captured
+    pub fn is_complete(&self) -> bool {
+        match self.recorder {
+            Some(ref recorder) => recorder.frames() >= self.target_frames,
+            None => true,
+        }
+    }
+
+    /// Close the recording and encode the loop; blocks until ffmpeg is done,
+    /// so run it off the render thread
+    pub fn finish(mut self) -> Result<LoopExportInfo, VideoOutputError> {
+        let intermediate = self.config.intermediate_path();
+        let result = self.encode(&intermediate);
+        let _ = std::fs::remove_file(&intermediate);
+        result
+    }
+
+    fn encode(&mut self, intermediate: &Path) -> Result<LoopExportInfo, VideoOutputError> {
+        let recorded = match self.recorder.take() {
+            Some(recorder) => recorder.stop()?,
+            None => {
+                return Err(VideoOutputError::SendError(
+                    "Loop recording already finished".to_string(),
+                ))
+            }
+        };
+        // Frames dropped by a slow encoder make the section shorter than asked
+        let duration = recorded.duration_secs();
+        if recorded.frames == 0 {
+            return Err(VideoOutputError::SendError(
+                "No frames were recorded for the loop".to_string(),
+            ));
+        }
+
+        let output = Command::new(&self.config.ffmpeg)
+            .args(self.config.encode_args(intermediate, duration))
+            .stdin(Stdio::null())
+            .stdout(Stdio::null())
+            .output()
+            .map_err(|e| VideoOutputError::SendError(e.to_string()))?;
+        if !output.status.success() {
+            return Err(VideoOutputError::SendError(format!(
+                "ffmpeg failed to encode the loop: {}",
+                String::from_utf8_lossy(&output.stderr).trim()
+            )));
+        }
+
+        let fade = self.config.crossfade_secs.clamp(0.0, duration / 2.0);
+        tracing::info!("Exported loop {}", self.config.path.display());
+        Ok(LoopExportInfo {
+            path: self.config.path.clone(),
+            duration_secs: duration - fade,
+        })
+    }
+}
+
+impl VideoOutput for LoopRecorder {
+    fn backend(&self) -> OutputBackend {
+        OutputBackend::Recording
+    }
+
+    fn send_frame(
+        &mut self,
+        _texture_id: u32,
+        _width: u32,
+        _height: u32,
+    ) -> Result<(), VideoOutputError> {
+        Err(VideoOutputError::SendError(
+            "Loop export needs RGBA frames".to_string(),
+        ))
+    }
+
+    fn send_frame_rgba(
+        &mut self,
+        pixels: &[u8],
+        width: u32,
+        height: u32,
+    ) -> Result<(), VideoOutputError> {
+        if !self.active || self.is_complete() {
+            return Ok(());
+        }
+        match self.recorder {
+            Some(ref mut recorder) => recorder.send_frame_rgba(pixels, width, height),
+            None => Ok(()),
+        }
+    }
+
+    fn is_active(&self) -> bool {
+        self.active
+    }
+
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn set_active(&mut self, active: bool) {
+        self.active = active;
+    }
+}
+
+impl Drop for LoopRecorder {
+    fn drop(&mut self) {
+        // Cancelled before finishing: close ffmpeg and discard the section
+        if let Some(recorder) = self.recorder.take() {
+            let _ = recorder.stop();
+            let _ = std::fs::remove_file(self.config.intermediate_path());
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_loop_duration_from_beats() {
+        // 4 bars of 4/4 at 120 BPM
+        assert_eq!(loop_duration_secs(16, 120.0), 8.0);
+        assert_eq!(loop_duration_secs(4, 0.0), 0.0);
+        assert_eq!("GIF".parse::<LoopFormat>(), Ok(LoopFormat::Gif));
+        assert!("mp4".parse::<LoopFormat>().is_err());
+    }
+
+    #[test]
+    fn test_crossfade_filter_graph() {
+        let config = LoopExportConfig::new("/tmp/loop.webm", LoopFormat::WebM, 8.0, 1280, 720);
+        // A second is recorded on top of the 8 s loop for the crossfade
+        assert_eq!(config.record_secs(), 9.0);
+        assert_eq!(config.frames(), 270);
+        let graph = config.filter_graph(9.0);
+        assert!(graph.contains("trim=start=1.000"));
+        assert!(graph.contains("xfade=transition=fade:duration=1.000:offset=7.000"));
+        assert!(graph.ends_with("format=yuv420p[out]"));
+
+        // The crossfade can't take more than half the section
+        let graph = config.filter_graph(1.0);
+        assert!(graph.contains("duration=0.500:offset=0.000"));
+    }
+
+    #[test]
+    fn test_hard_cut_gif_args() {
+        let mut config = LoopExportConfig::new("/tmp/loop.gif", LoopFormat::Gif, 4.0, 1920, 1080);
+        config.crossfade_secs = 0.0;
+        let args = config.encode_args(Path::new("/tmp/loop.gif.part.mp4"), 4.0);
+        let graph = &args[args.iter().position(|a| a == "-filter_complex").unwrap() + 1];
+        assert!(!graph.contains("xfade"));
+        assert!(graph.contains("palettegen"));
+        assert!(args.windows(2).any(|w| w == ["-loop", "0"]));
+        assert_eq!(args.last().unwrap(), "/tmp/loop.gif");
+        assert_eq!(
+            config.intermediate_path(),
+            PathBuf::from("/tmp/loop.gif.part.mp4")
+        );
+    }
+
+    #[test]
+    fn test_rejects_bad_length() {
+        let config = LoopExportConfig::new("/tmp/loop.webm", LoopFormat::WebM, 0.0, 64, 64);
+        assert!(matches!(
+            LoopRecorder::start(config),
+            Err(VideoOutputError::InitError(_))
+        ));
+    }
+}
@@ -2,20 +2,119 @@
 
 pub mod output;
 
+pub mod pixel_format;
+
+pub mod input;
+
 #[cfg(target_os = "linux")]
 pub mod v4l2;
 
-#[cfg(target_os = "windows")]
 pub mod spout;
 
 pub mod ndi;
 
+pub mod syphon;
+
+pub mod screen;
+
+pub mod virtual_camera;
+
+pub mod recorder;
+
+pub mod sequence;
+
+pub mod loop_export;
+
+pub mod compositor;
+
+pub mod overlay;
+
+pub mod test_pattern;
+
+pub mod effects;
+
+pub mod text_overlay;
+
+pub mod stats;
+
+pub mod quality;
+
+pub mod adaptive;
+
+pub mod background;
+
+pub mod display_mode;
+
+pub mod color_adjust;
+
+pub mod strobe;
+
+pub mod gpu;
+
+pub mod idle;
+
+pub mod thumbnail;
+
+pub mod time_scale;
+
+pub mod preset_cuts;
+
 pub use output::{VideoOutput, VideoOutputError, OutputBackend};
 
+pub use pixel_format::{convert_rgba, PixelFormat};
+
+pub use input::{InputBackend, InputBlend, InputFrame, InputPlacement, VideoInput};
+
 #[cfg(target_os = "linux")]
-pub use v4l2::{V4l2Config, V4l2DeviceInfo, V4l2Output};
+pub use v4l2::{V4l2Config, V4l2DeviceInfo, V4l2Output, V4l2SetupOptions, V4l2SetupStatus};
+
+pub use spout::{SpoutConfig, SpoutInput, SpoutOutput, SpoutSenderInfo};
+
+pub use ndi::{NdiConfig, NdiInput, NdiMetadata, NdiOutput, NdiSenderInfo};
+
+pub use syphon::{SyphonConfig, SyphonOutput};
+
+pub use screen::{ScreenInput, ScreenRegion};
+
+pub use virtual_camera::{VirtualCameraConfig, VirtualCameraOutput};
+
+pub use recorder::{RecordingCodec, RecordingConfig, VideoRecorder, VideoRecordingInfo};
+
+pub use sequence::{ImageSequenceExporter, ImageSequenceInfo};
+
+pub use loop_export::{LoopExportConfig, LoopExportInfo, LoopFormat, LoopRecorder};
+
+pub use compositor::{BlendMode, CompositeLayer, CompositorFeed, CompositorReceiver};
+
+pub use overlay::{LogoOverlay, OverlayConfig, OverlayPosition};
+
+pub use effects::{Effect, EffectChain};
+
+pub use text_overlay::TextOverlayConfig;
 
-#[cfg(target_os = "windows")]
-pub use spout::{SpoutConfig, SpoutOutput, SpoutSenderInfo};
+pub use stats::{FrameTimer, RenderStats};
 
-pub use ndi::{NdiConfig, NdiOutput, NdiSenderInfo};
+pub use quality::RenderQuality;
+
+pub use adaptive::AdaptiveScale;
+
+pub use color_adjust::{ColorAdjust, ColorControl, ColorFade};
+
+pub use strobe::{Blackout, Strobe, StrobeConfig, StrobeRate};
+
+pub use gpu::{GpuInfo, GpuPreference};
+
+pub use idle::{IdleBars, IDLE_BARS};
+
+pub use thumbnail::{ThumbnailFormat, ThumbnailWriter};
+
+pub use time_scale::PresetClock;
+
+pub use preset_cuts::PresetCuts;
+
+pub use display_mode::{DisplayMode, ExclusiveMode};
+
+pub use background::{BackgroundBlend, BackgroundConfig, BackgroundFit, BackgroundMedia};
+
+#[cfg(target_os = "linux")]
+pub use compositor::CompositorGpuFeed;
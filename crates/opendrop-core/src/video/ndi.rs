@@ -11,17 +11,25 @@
 //! # Feature Flag
 //! Enable with `--features ndi` in Cargo.toml
 
+use super::input::{InputBackend, InputFrame, VideoInput};
 use super::output::{OutputBackend, VideoOutput, VideoOutputError};
+use super::pixel_format::PixelFormat;
+#[cfg(feature = "ndi")]
+use super::pixel_format::convert_rgba;
 
 /// Configuration for NDI output
 #[derive(Debug, Clone)]
 pub struct NdiConfig {
     /// NDI source name (visible to receivers)
     pub name: String,
-    /// NDI groups (empty for default)
+    /// NDI groups, comma separated (empty for default)
     pub groups: Option<String>,
+    /// Source receivers switch to if this sender goes away
+    pub failover: Option<String>,
     /// Whether to clock video (rate-limit to framerate)
     pub clock_video: bool,
+    /// Frame format sent to receivers (UYVY is NDI's native format)
+    pub pixel_format: PixelFormat,
 }
 
 impl Default for NdiConfig {
@@ -29,7 +37,9 @@ impl Default for NdiConfig {
         Self {
             name: "OpenDrop".to_string(),
             groups: None,
+            failover: None,
             clock_video: true,
+            pixel_format: PixelFormat::Rgba,
         }
     }
 }
@@ -42,6 +52,58 @@ impl NdiConfig {
             ..Default::default()
         }
     }
+
+    /// Reject settings NDI cannot send
+    pub fn validate(&self) -> Result<(), VideoOutputError> {
+        if self.pixel_format == PixelFormat::Yuyv {
+            return Err(VideoOutputError::InitError(
+                "NDI does not carry YUYV frames; use UYVY".to_string(),
+            ));
+        }
+        Ok(())
+    }
+}
+
+/// What a deck is playing, attached to every NDI frame so receivers can
+/// show it
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct NdiMetadata {
+    /// Preset name
+    pub preset: Option<String>,
+    pub deck_id: u8,
+    /// Current tempo
+    pub bpm: Option<f32>,
+}
+
+impl NdiMetadata {
+    /// NDI frame metadata is an XML element
+    pub fn to_xml(&self) -> String {
+        let mut xml = format!("<opendrop deck=\"{}\"", self.deck_id + 1);
+        if let Some(ref preset) = self.preset {
+            xml.push_str(&format!(" preset=\"{}\"", escape_xml(preset)));
+        }
+        if let Some(bpm) = self.bpm {
+            xml.push_str(&format!(" bpm=\"{:.1}\"", bpm));
+        }
+        xml.push_str("/>");
+        xml
+    }
+}
+
+/// Escape text for an XML attribute value
+fn escape_xml(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&apos;"),
+            _ => escaped.push(c),
+        }
+    }
+    escaped
 }
 
 /// NDI sender information
@@ -64,8 +126,10 @@ pub struct NdiOutput {
     active: bool,
     width: u32,
     height: u32,
-    /// Frame buffer for RGBA to NDI conversion
+    /// Frame buffer for conversion from RGBA
     frame_buffer: Vec<u8>,
+    /// XML metadata attached to each frame
+    metadata: String,
 }
 
 #[cfg(feature = "ndi")]
@@ -87,6 +151,8 @@ impl NdiOutput {
 
     /// Create a new NDI output with custom config
     pub fn with_config(config: NdiConfig) -> Result<Self, VideoOutputError> {
+        config.validate()?;
+
         // Initialize NDI library
         let _ndi = grafton_ndi::Ndi::new()
             .map_err(|e| VideoOutputError::InitError(format!("NDI init failed: {}", e)))?;
@@ -97,9 +163,15 @@ impl NdiOutput {
             width: 1280,
             height: 720,
             frame_buffer: Vec::new(),
+            metadata: String::new(),
         })
     }
 
+    /// Set what's playing, sent with every following frame
+    pub fn set_metadata(&mut self, metadata: &NdiMetadata) {
+        self.metadata = metadata.to_xml();
+    }
+
     /// Get information about the sender
     pub fn info(&self) -> NdiSenderInfo {
         NdiSenderInfo {
@@ -115,10 +187,17 @@ impl NdiOutput {
             .clock_video(self.config.clock_video)
             .build();
 
-        // Note: Actual sender creation would go here
+        // Note: Actual sender creation would go here, with the groups and
+        // failover source applied
         // self.sender = Some(grafton_ndi::Sender::new(&self.ndi, &options)?);
-
-        tracing::info!("NDI sender started: {}", self.config.name);
+        // sender.set_failover(self.config.failover.as_deref());
+
+        tracing::info!(
+            "NDI sender started: {} (groups: {}, failover: {})",
+            self.config.name,
+            self.config.groups.as_deref().unwrap_or("default"),
+            self.config.failover.as_deref().unwrap_or("none")
+        );
         Ok(())
     }
 
@@ -173,9 +252,18 @@ impl VideoOutput for NdiOutput {
         self.width = width;
         self.height = height;
 
+        let pixel_format = self.config.pixel_format;
+        let _frame = if pixel_format == PixelFormat::Rgba {
+            pixels
+        } else {
+            convert_rgba(pixels, width, height, pixel_format, &mut self.frame_buffer)?;
+            &self.frame_buffer
+        };
+
         // Create NDI video frame
         // Note: Actual implementation would create VideoFrame and send
-        // let frame = grafton_ndi::VideoFrame::new(width, height, FourCC::RGBA, pixels);
+        // let frame = grafton_ndi::VideoFrame::new(width, height, pixel_format.fourcc(), _frame)
+        //     .with_metadata(&self.metadata);
         // self.sender.as_mut().unwrap().send_video(&frame);
 
         Ok(())
@@ -211,6 +299,73 @@ impl Drop for NdiOutput {
     }
 }
 
+/// NDI video input implementation
+///
+/// Receives a network source (a camera app, OBS, another VJ tool) to mix
+/// into a deck.
+#[cfg(feature = "ndi")]
+pub struct NdiInput {
+    /// Source name, empty for the first source found
+    source: String,
+    // Note: grafton-ndi types would go here
+    // receiver: Option<grafton_ndi::Receiver>,
+    /// Received frame, converted to RGBA
+    frame_buffer: Vec<u8>,
+    width: u32,
+    height: u32,
+}
+
+#[cfg(feature = "ndi")]
+impl NdiInput {
+    /// Check if NDI runtime is available
+    pub fn is_available() -> bool {
+        NdiOutput::is_available()
+    }
+
+    /// Receive from the named source, or the first source found if `None`
+    pub fn new(source: Option<&str>) -> Result<Self, VideoOutputError> {
+        let _ndi = grafton_ndi::Ndi::new()
+            .map_err(|e| VideoOutputError::InitError(format!("NDI init failed: {}", e)))?;
+
+        // Note: source discovery and receiver creation would go here
+        // let finder = grafton_ndi::Finder::new(&ndi, &finder_options)?;
+        // self.receiver = Some(grafton_ndi::Receiver::new(&ndi, &receiver_options)?);
+
+        tracing::info!("NDI receiver started: {}", source.unwrap_or("first source"));
+        Ok(Self {
+            source: source.unwrap_or_default().to_string(),
+            frame_buffer: Vec::new(),
+            width: 0,
+            height: 0,
+        })
+    }
+}
+
+#[cfg(feature = "ndi")]
+impl VideoInput for NdiInput {
+    fn backend(&self) -> InputBackend {
+        InputBackend::Ndi
+    }
+
+    fn source_name(&self) -> &str {
+        &self.source
+    }
+
+    fn receive_frame(&mut self) -> Result<Option<InputFrame<'_>>, VideoOutputError> {
+        // Note: Actual implementation would capture without blocking and
+        // copy the RGBA frame into frame_buffer
+        // let frame = self.receiver.as_ref().unwrap().capture_video(0)?;
+        if self.frame_buffer.is_empty() {
+            return Ok(None);
+        }
+        Ok(Some(InputFrame {
+            pixels: &self.frame_buffer,
+            width: self.width,
+            height: self.height,
+        }))
+    }
+}
+
 // Stub implementation when NDI feature is disabled
 #[cfg(not(feature = "ndi"))]
 pub struct NdiOutput {
@@ -242,6 +397,9 @@ impl NdiOutput {
             connected_receivers: 0,
         }
     }
+
+    /// Set what's playing (no-op when feature disabled)
+    pub fn set_metadata(&mut self, _metadata: &NdiMetadata) {}
 }
 
 #[cfg(not(feature = "ndi"))]
@@ -267,6 +425,39 @@ impl VideoOutput for NdiOutput {
     }
 }
 
+#[cfg(not(feature = "ndi"))]
+pub struct NdiInput {
+    _private: (),
+}
+
+#[cfg(not(feature = "ndi"))]
+impl NdiInput {
+    /// Check if NDI runtime is available
+    pub fn is_available() -> bool {
+        false
+    }
+
+    /// Create a new NDI input (returns error when feature disabled)
+    pub fn new(_source: Option<&str>) -> Result<Self, VideoOutputError> {
+        Err(VideoOutputError::NotSupported)
+    }
+}
+
+#[cfg(not(feature = "ndi"))]
+impl VideoInput for NdiInput {
+    fn backend(&self) -> InputBackend {
+        InputBackend::Ndi
+    }
+
+    fn source_name(&self) -> &str {
+        ""
+    }
+
+    fn receive_frame(&mut self) -> Result<Option<InputFrame<'_>>, VideoOutputError> {
+        Err(VideoOutputError::NotSupported)
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -277,6 +468,29 @@ mod tests {
         assert_eq!(config.name, "OpenDrop");
         assert!(config.clock_video);
         assert!(config.groups.is_none());
+        assert_eq!(config.pixel_format, PixelFormat::Rgba);
+    }
+
+    #[test]
+    fn test_ndi_config_rejects_yuyv() {
+        let config = |pixel_format| NdiConfig { pixel_format, ..Default::default() };
+        assert!(config(PixelFormat::Uyvy).validate().is_ok());
+        assert!(config(PixelFormat::Nv12).validate().is_ok());
+        assert!(config(PixelFormat::Yuyv).validate().is_err());
+    }
+
+    #[test]
+    fn test_ndi_metadata_xml() {
+        let metadata = NdiMetadata {
+            preset: Some("Geiss & \"Rovastar\" <fx>".to_string()),
+            deck_id: 1,
+            bpm: Some(128.04),
+        };
+        assert_eq!(
+            metadata.to_xml(),
+            r#"<opendrop deck="2" preset="Geiss &amp; &quot;Rovastar&quot; &lt;fx&gt;" bpm="128.0"/>"#
+        );
+        assert_eq!(NdiMetadata::default().to_xml(), r#"<opendrop deck="1"/>"#);
     }
 
     #[test]
@@ -289,5 +503,7 @@ mod tests {
     fn test_ndi_not_available_without_feature() {
         #[cfg(not(feature = "ndi"))]
         assert!(!NdiOutput::is_available());
+        #[cfg(not(feature = "ndi"))]
+        assert!(matches!(NdiInput::new(None), Err(VideoOutputError::NotSupported)));
     }
 }
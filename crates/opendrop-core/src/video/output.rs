@@ -4,6 +4,8 @@
 //! - Window (default, OpenGL direct rendering)
 //! - v4l2loopback (Linux virtual camera for OBS/VLC)
 //! - Spout (Windows texture sharing)
+//! - Syphon (macOS texture sharing)
+//! - softcam (Windows virtual camera)
 //! - NDI (network video streaming)
 //! - PipeWire (Linux native video)
 
@@ -15,6 +17,8 @@ pub enum VideoOutputError {
     InitError(String),
     #[error("Frame send failed: {0}")]
     SendError(String),
+    #[error("Frame receive failed: {0}")]
+    ReceiveError(String),
     #[error("Output not supported on this platform")]
     NotSupported,
 }
@@ -22,14 +26,24 @@ pub enum VideoOutputError {
 /// Video output backend type
 #[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
 pub enum OutputBackend {
+    /// Frames streamed to the compositor renderer
+    Compositor,
+    /// Numbered PNG files for offline editing
+    ImageSequence,
     /// NDI (cross-platform network video)
     Ndi,
     /// PipeWire (Linux native)
     PipeWire,
+    /// MP4 file recording (through ffmpeg)
+    Recording,
     /// Spout (Windows texture sharing)
     Spout,
+    /// Syphon (macOS texture sharing)
+    Syphon,
     /// v4l2loopback (Linux virtual camera)
     V4l2Loopback,
+    /// softcam (Windows virtual camera)
+    VirtualCamera,
     /// Direct window output (default)
     Window,
 }
@@ -37,15 +51,40 @@ pub enum OutputBackend {
 impl std::fmt::Display for OutputBackend {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
+            OutputBackend::Compositor => write!(f, "Compositor"),
+            OutputBackend::ImageSequence => write!(f, "PNG sequence"),
             OutputBackend::Ndi => write!(f, "NDI"),
             OutputBackend::PipeWire => write!(f, "PipeWire"),
+            OutputBackend::Recording => write!(f, "Recording"),
             OutputBackend::Spout => write!(f, "Spout"),
+            OutputBackend::Syphon => write!(f, "Syphon"),
             OutputBackend::V4l2Loopback => write!(f, "v4l2loopback"),
+            OutputBackend::VirtualCamera => write!(f, "Virtual camera"),
             OutputBackend::Window => write!(f, "Window"),
         }
     }
 }
 
+impl std::str::FromStr for OutputBackend {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "compositor" => Ok(OutputBackend::Compositor),
+            "image_sequence" | "png" => Ok(OutputBackend::ImageSequence),
+            "ndi" => Ok(OutputBackend::Ndi),
+            "pipewire" => Ok(OutputBackend::PipeWire),
+            "recording" | "mp4" => Ok(OutputBackend::Recording),
+            "spout" => Ok(OutputBackend::Spout),
+            "syphon" => Ok(OutputBackend::Syphon),
+            "v4l2" | "v4l2loopback" => Ok(OutputBackend::V4l2Loopback),
+            "virtual_camera" | "camera" => Ok(OutputBackend::VirtualCamera),
+            "window" => Ok(OutputBackend::Window),
+            _ => Err(format!("Unknown video output: {}", s)),
+        }
+    }
+}
+
 /// Trait for video output implementations
 pub trait VideoOutput: Send {
     /// Get the backend type
@@ -75,6 +114,37 @@ pub trait VideoOutput: Send {
     }
 }
 
+/// Copy an OpenGL readback (bottom row first) into `dst` top row first,
+/// as the RGBA outputs expect
+pub fn copy_flipped(src: &[u8], dst: &mut [u8], row_bytes: usize) {
+    if row_bytes == 0 {
+        return;
+    }
+    for (dst_row, src_row) in dst
+        .chunks_exact_mut(row_bytes)
+        .zip(src.chunks_exact(row_bytes).rev())
+    {
+        dst_row.copy_from_slice(src_row);
+    }
+}
+
+/// Key out a dark background for outputs that carry alpha: alpha is the
+/// brightest channel and the colour is un-premultiplied, so compositing
+/// `dst` over black reproduces `src`
+pub fn key_alpha_from_luma(src: &[u8], dst: &mut [u8]) {
+    for (out, px) in dst.chunks_exact_mut(4).zip(src.chunks_exact(4)) {
+        let alpha = px[0].max(px[1]).max(px[2]) as u32;
+        if alpha == 0 {
+            out.copy_from_slice(&[0, 0, 0, 0]);
+            continue;
+        }
+        for c in 0..3 {
+            out[c] = ((px[c] as u32 * 255 + alpha / 2) / alpha) as u8;
+        }
+        out[3] = alpha as u8;
+    }
+}
+
 /// Window-based video output (default)
 pub struct WindowOutput {
     active: bool,
@@ -142,6 +212,20 @@ pub fn list_outputs(backend: OutputBackend) -> Vec<String> {
                 vec![]
             }
         }
+        OutputBackend::Syphon => {
+            if super::syphon::SyphonOutput::is_available() {
+                vec!["Syphon:OpenDrop".to_string()]
+            } else {
+                vec![]
+            }
+        }
+        OutputBackend::VirtualCamera => {
+            if super::virtual_camera::VirtualCameraOutput::is_available() {
+                vec!["DirectShow Softcam".to_string()]
+            } else {
+                vec![]
+            }
+        }
         OutputBackend::Ndi => {
             // NDI senders are named, check if runtime available
             if super::ndi::NdiOutput::is_available() {
@@ -159,6 +243,8 @@ pub fn list_outputs(backend: OutputBackend) -> Vec<String> {
 pub fn is_backend_available(backend: OutputBackend) -> bool {
     match backend {
         OutputBackend::Window => true,
+        OutputBackend::ImageSequence => true,
+        OutputBackend::Compositor => true,
         #[cfg(target_os = "linux")]
         OutputBackend::V4l2Loopback => {
             // Check if any v4l2loopback devices exist
@@ -168,9 +254,18 @@ pub fn is_backend_available(backend: OutputBackend) -> bool {
         OutputBackend::Spout => {
             super::spout::SpoutOutput::is_available()
         }
+        OutputBackend::Syphon => {
+            super::syphon::SyphonOutput::is_available()
+        }
+        OutputBackend::VirtualCamera => {
+            super::virtual_camera::VirtualCameraOutput::is_available()
+        }
         OutputBackend::Ndi => {
             super::ndi::NdiOutput::is_available()
         }
+        OutputBackend::Recording => {
+            super::recorder::VideoRecorder::is_available()
+        }
         _ => false,
     }
 }
@@ -181,13 +276,42 @@ mod tests {
 
     #[test]
     fn test_output_backend_display() {
+        assert_eq!(format!("{}", OutputBackend::ImageSequence), "PNG sequence");
         assert_eq!(format!("{}", OutputBackend::Ndi), "NDI");
         assert_eq!(format!("{}", OutputBackend::PipeWire), "PipeWire");
+        assert_eq!(format!("{}", OutputBackend::Recording), "Recording");
         assert_eq!(format!("{}", OutputBackend::Spout), "Spout");
+        assert_eq!(format!("{}", OutputBackend::Syphon), "Syphon");
         assert_eq!(format!("{}", OutputBackend::V4l2Loopback), "v4l2loopback");
+        assert_eq!(format!("{}", OutputBackend::VirtualCamera), "Virtual camera");
         assert_eq!(format!("{}", OutputBackend::Window), "Window");
     }
 
+    #[test]
+    fn test_copy_flipped_reverses_rows() {
+        let src = [1, 1, 2, 2, 3, 3];
+        let mut dst = [0; 6];
+        copy_flipped(&src, &mut dst, 2);
+        assert_eq!(dst, [3, 3, 2, 2, 1, 1]);
+    }
+
+    #[test]
+    fn test_output_backend_from_str() {
+        assert_eq!("NDI".parse::<OutputBackend>(), Ok(OutputBackend::Ndi));
+        assert_eq!("v4l2".parse::<OutputBackend>(), Ok(OutputBackend::V4l2Loopback));
+        assert_eq!("virtual_camera".parse::<OutputBackend>(), Ok(OutputBackend::VirtualCamera));
+        assert!("rtmp".parse::<OutputBackend>().is_err());
+    }
+
+    #[test]
+    fn test_key_alpha_from_luma() {
+        let src = [0, 0, 0, 255, 255, 255, 255, 255, 128, 64, 0, 255];
+        let mut dst = [0u8; 12];
+        key_alpha_from_luma(&src, &mut dst);
+        // Black is transparent, white opaque, colours keep their hue
+        assert_eq!(dst, [0, 0, 0, 0, 255, 255, 255, 255, 255, 128, 0, 128]);
+    }
+
     #[test]
     fn test_window_output_creation() {
         let output = WindowOutput::new();
@@ -0,0 +1,403 @@
+//! Logo overlay for output frames
+//!
+//! Venues often require their branding on screen. [`LogoOverlay`] blends a
+//! PNG logo onto the top-down RGBA frames sent to outputs and recordings, at
+//! a corner or the centre, sized relative to the frame width. The deck
+//! window itself stays clean.
+
+use std::fs::File;
+use std::io::BufReader;
+use std::path::PathBuf;
+
+use serde::{Deserialize, Serialize};
+
+use super::output::VideoOutputError;
+
+/// Largest logo accepted, per side
+const MAX_LOGO_SIZE: u32 = 8192;
+
+/// Gap between the logo and the frame edge, as a fraction of the shorter side
+const MARGIN: f32 = 0.03;
+
+/// Where a logo or text sits in the frame
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum OverlayPosition {
+    TopLeft,
+    TopRight,
+    BottomLeft,
+    #[default]
+    BottomRight,
+    Center,
+    /// Centred along the top edge
+    Top,
+    /// Centred along the bottom edge
+    Bottom,
+}
+
+impl std::fmt::Display for OverlayPosition {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            OverlayPosition::TopLeft => write!(f, "top left"),
+            OverlayPosition::TopRight => write!(f, "top right"),
+            OverlayPosition::BottomLeft => write!(f, "bottom left"),
+            OverlayPosition::BottomRight => write!(f, "bottom right"),
+            OverlayPosition::Center => write!(f, "center"),
+            OverlayPosition::Top => write!(f, "top"),
+            OverlayPosition::Bottom => write!(f, "bottom"),
+        }
+    }
+}
+
+impl OverlayPosition {
+    /// Left and top of a `w`x`h` item at this position in a
+    /// `width`x`height` frame, inset from the edges by a margin
+    pub fn place(self, width: u32, height: u32, w: u32, h: u32) -> (u32, u32) {
+        let (w, h) = (w.min(width), h.min(height));
+        let margin = (width.min(height) as f32 * MARGIN) as u32;
+        let right = width.saturating_sub(w + margin);
+        let bottom = height.saturating_sub(h + margin);
+        let (center_x, center_y) = ((width - w) / 2, (height - h) / 2);
+        let (left, top) = match self {
+            OverlayPosition::TopLeft => (margin, margin),
+            OverlayPosition::TopRight => (right, margin),
+            OverlayPosition::BottomLeft => (margin, bottom),
+            OverlayPosition::BottomRight => (right, bottom),
+            OverlayPosition::Center => (center_x, center_y),
+            OverlayPosition::Top => (center_x, margin),
+            OverlayPosition::Bottom => (center_x, bottom),
+        };
+        // A margin wider than the space left would push the item off the frame
+        (left.min(width - w), top.min(height - h))
+    }
+}
+
+/// Logo overlay settings
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct OverlayConfig {
+    /// PNG file of the logo
+    pub path: PathBuf,
+    #[serde(default)]
+    pub position: OverlayPosition,
+    /// Logo width as a fraction of the frame width (0.01-1)
+    pub scale: f32,
+    /// 0 (invisible) to 1 (as drawn)
+    pub opacity: f32,
+}
+
+impl Default for OverlayConfig {
+    fn default() -> Self {
+        Self {
+            path: PathBuf::new(),
+            position: OverlayPosition::default(),
+            scale: 0.15,
+            opacity: 1.0,
+        }
+    }
+}
+
+impl OverlayConfig {
+    /// Reject out-of-range scale or opacity
+    pub fn validate(&self) -> Result<(), VideoOutputError> {
+        if !(0.01..=1.0).contains(&self.scale) {
+            return Err(VideoOutputError::InitError(format!(
+                "Invalid logo scale {}: use 0.01-1",
+                self.scale
+            )));
+        }
+        if !(0.0..=1.0).contains(&self.opacity) {
+            return Err(VideoOutputError::InitError(format!(
+                "Invalid logo opacity {}: use 0-1",
+                self.opacity
+            )));
+        }
+        Ok(())
+    }
+}
+
+/// Decode a PNG file to RGBA
+pub(crate) fn load_png(path: &std::path::Path) -> Result<(Vec<u8>, u32, u32), VideoOutputError> {
+    let error = |e: &dyn std::fmt::Display| {
+        VideoOutputError::InitError(format!("Failed to load image {}: {}", path.display(), e))
+    };
+    let file = File::open(path).map_err(|e| error(&e))?;
+    let mut decoder = png::Decoder::new(BufReader::new(file));
+    // Palette and low bit depths come out as 8-bit gray or RGB
+    decoder.set_transformations(png::Transformations::normalize_to_color8());
+    let mut reader = decoder.read_info().map_err(|e| error(&e))?;
+    let (width, height) = (reader.info().width, reader.info().height);
+    if width > MAX_LOGO_SIZE || height > MAX_LOGO_SIZE {
+        return Err(error(&format!(
+            "{}x{} is larger than {}x{}",
+            width, height, MAX_LOGO_SIZE, MAX_LOGO_SIZE
+        )));
+    }
+    let mut buffer = vec![0; reader.output_buffer_size()];
+    let frame = reader.next_frame(&mut buffer).map_err(|e| error(&e))?;
+    buffer.truncate(frame.buffer_size());
+
+    let rgba = match frame.color_type {
+        png::ColorType::Rgba => buffer,
+        png::ColorType::Rgb => buffer
+            .chunks_exact(3)
+            .flat_map(|p| [p[0], p[1], p[2], 255])
+            .collect(),
+        png::ColorType::GrayscaleAlpha => buffer
+            .chunks_exact(2)
+            .flat_map(|p| [p[0], p[0], p[0], p[1]])
+            .collect(),
+        png::ColorType::Grayscale => buffer.iter().flat_map(|&g| [g, g, g, 255]).collect(),
+        png::ColorType::Indexed => return Err(error(&"unexpanded palette")),
+    };
+    Ok((rgba, width, height))
+}
+
+/// `value * alpha / 255`, rounded
+fn mul_alpha(value: u8, alpha: u32) -> u8 {
+    ((value as u32 * alpha + 127) / 255) as u8
+}
+
+/// PNG logo blended onto output frames
+pub struct LogoOverlay {
+    config: OverlayConfig,
+    /// Logo pixels with premultiplied alpha
+    logo: Vec<u8>,
+    logo_width: u32,
+    logo_height: u32,
+    /// Frame size `scaled` was made for
+    frame_size: (u32, u32),
+    /// Logo resampled for `frame_size`, opacity applied, premultiplied
+    scaled: Vec<u8>,
+    /// Left, top, width and height of the logo in the frame
+    placement: (u32, u32, u32, u32),
+}
+
+impl LogoOverlay {
+    /// Load the logo named by `config`
+    pub fn load(config: OverlayConfig) -> Result<Self, VideoOutputError> {
+        let (rgba, width, height) = load_png(&config.path)?;
+        Self::from_rgba(config, rgba, width, height)
+    }
+
+    /// Overlay an RGBA logo already in memory
+    pub fn from_rgba(
+        config: OverlayConfig,
+        mut rgba: Vec<u8>,
+        width: u32,
+        height: u32,
+    ) -> Result<Self, VideoOutputError> {
+        config.validate()?;
+        if width == 0 || height == 0 || rgba.len() != (width * height * 4) as usize {
+            return Err(VideoOutputError::InitError(format!(
+                "Invalid logo: {} bytes for {}x{}",
+                rgba.len(),
+                width,
+                height
+            )));
+        }
+        for px in rgba.chunks_exact_mut(4) {
+            let alpha = px[3] as u32;
+            for channel in &mut px[..3] {
+                *channel = mul_alpha(*channel, alpha);
+            }
+        }
+        Ok(Self {
+            config,
+            logo: rgba,
+            logo_width: width,
+            logo_height: height,
+            frame_size: (0, 0),
+            scaled: Vec::new(),
+            placement: (0, 0, 0, 0),
+        })
+    }
+
+    pub fn config(&self) -> &OverlayConfig {
+        &self.config
+    }
+
+    /// Change position, scale or opacity, keeping the loaded logo
+    pub fn set_config(&mut self, config: OverlayConfig) -> Result<(), VideoOutputError> {
+        config.validate()?;
+        self.config = config;
+        self.frame_size = (0, 0);
+        Ok(())
+    }
+
+    /// Blend the logo onto a top-down RGBA frame
+    pub fn apply(&mut self, frame: &mut [u8], width: u32, height: u32) {
+        if width == 0 || height == 0 || frame.len() != (width * height * 4) as usize {
+            return;
+        }
+        if self.frame_size != (width, height) {
+            self.rescale(width, height);
+        }
+
+        let (left, top, w, _) = self.placement;
+        let (left, w) = (left as usize, w as usize);
+        let stride = width as usize * 4;
+        for (y, src_row) in self.scaled.chunks_exact(w * 4).enumerate() {
+            let start = (top as usize + y) * stride + left * 4;
+            let dst_row = &mut frame[start..start + w * 4];
+            for (dst, src) in dst_row.chunks_exact_mut(4).zip(src_row.chunks_exact(4)) {
+                let inverse = 255 - src[3] as u32;
+                for (d, &s) in dst.iter_mut().zip(src) {
+                    *d = s + mul_alpha(*d, inverse);
+                }
+            }
+        }
+    }
+
+    /// Resample the logo for a frame size and place it
+    fn rescale(&mut self, width: u32, height: u32) {
+        let aspect = self.logo_height as f32 / self.logo_width as f32;
+        let mut w = (width as f32 * self.config.scale).round().max(1.0);
+        let mut h = (w * aspect).round().max(1.0);
+        // Tall logos are shrunk to fit the frame height
+        if h > height as f32 {
+            h = height as f32;
+            w = (h / aspect).round().clamp(1.0, width as f32);
+        }
+        let (w, h) = (w as u32, h as u32);
+
+        // Box filter: each output pixel averages the logo pixels it covers
+        let opacity = (self.config.opacity * 255.0).round() as u32;
+        self.scaled.clear();
+        self.scaled.reserve((w * h * 4) as usize);
+        for dy in 0..h {
+            let y0 = dy * self.logo_height / h;
+            let y1 = ((dy + 1) * self.logo_height / h).max(y0 + 1);
+            for dx in 0..w {
+                let x0 = dx * self.logo_width / w;
+                let x1 = ((dx + 1) * self.logo_width / w).max(x0 + 1);
+                let mut sum = [0u32; 4];
+                for sy in y0..y1 {
+                    let row = (sy * self.logo_width) as usize * 4;
+                    for sx in x0..x1 {
+                        let px = &self.logo[row + sx as usize * 4..][..4];
+                        for (s, &c) in sum.iter_mut().zip(px) {
+                            *s += c as u32;
+                        }
+                    }
+                }
+                let count = (y1 - y0) * (x1 - x0);
+                for s in sum {
+                    let average = ((s + count / 2) / count) as u8;
+                    self.scaled.push(mul_alpha(average, opacity));
+                }
+            }
+        }
+
+        let (left, top) = self.config.position.place(width, height, w, h);
+        self.placement = (left, top, w, h);
+        self.frame_size = (width, height);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config(position: OverlayPosition, scale: f32, opacity: f32) -> OverlayConfig {
+        OverlayConfig {
+            position,
+            scale,
+            opacity,
+            ..Default::default()
+        }
+    }
+
+    /// Opaque white 2x2 logo
+    fn white_logo(config: OverlayConfig) -> LogoOverlay {
+        LogoOverlay::from_rgba(config, vec![255; 16], 2, 2).unwrap()
+    }
+
+    #[test]
+    fn test_config_validate() {
+        assert!(OverlayConfig::default().validate().is_ok());
+        assert!(config(OverlayPosition::Center, 0.0, 1.0)
+            .validate()
+            .is_err());
+        assert!(config(OverlayPosition::Center, 0.5, 1.5)
+            .validate()
+            .is_err());
+        let json = serde_json::to_string(&OverlayConfig::default()).unwrap();
+        assert!(json.contains("\"bottom_right\""));
+    }
+
+    #[test]
+    fn test_logo_placement() {
+        let mut overlay = white_logo(config(OverlayPosition::BottomRight, 0.1, 1.0));
+        let mut frame = vec![0u8; 100 * 50 * 4];
+        overlay.apply(&mut frame, 100, 50);
+        // 10% of 100 wide, square; 3% of 50 is a 1 pixel margin
+        assert_eq!(overlay.placement, (89, 39, 10, 10));
+
+        overlay
+            .set_config(config(OverlayPosition::Center, 0.1, 1.0))
+            .unwrap();
+        overlay.apply(&mut frame, 100, 50);
+        assert_eq!(overlay.placement, (45, 20, 10, 10));
+        assert_eq!(OverlayPosition::Top.place(100, 50, 10, 10), (45, 1));
+        assert_eq!(OverlayPosition::Bottom.place(100, 50, 10, 10), (45, 39));
+
+        // Tall logos are limited to the frame height
+        let mut tall = LogoOverlay::from_rgba(
+            config(OverlayPosition::TopLeft, 1.0, 1.0),
+            vec![255; 4 * 4],
+            1,
+            4,
+        )
+        .unwrap();
+        tall.apply(&mut frame, 100, 50);
+        assert_eq!(tall.placement, (1, 0, 13, 50));
+    }
+
+    #[test]
+    fn test_logo_blends_with_opacity() {
+        let mut overlay = white_logo(config(OverlayPosition::TopLeft, 0.5, 0.5));
+        let mut frame = [0u8, 0, 0, 255].repeat(4 * 4);
+        overlay.apply(&mut frame, 4, 4);
+        // 2x2 logo at the top left (margin rounds to 0)
+        assert_eq!(&frame[..4], &[128, 128, 128, 255]);
+        assert_eq!(&frame[8..12], &[0, 0, 0, 255]);
+        assert_eq!(&frame[16..20], &[128, 128, 128, 255]);
+        assert_eq!(&frame[32..36], &[0, 0, 0, 255]);
+    }
+
+    #[test]
+    fn test_transparent_logo_pixels_keep_frame() {
+        let logo = [[0u8, 0, 0, 0], [255, 0, 0, 255]].concat();
+        let mut overlay =
+            LogoOverlay::from_rgba(config(OverlayPosition::TopLeft, 1.0, 1.0), logo, 2, 1).unwrap();
+        let mut frame = [0u8, 0, 255, 255].repeat(2);
+        overlay.apply(&mut frame, 2, 1);
+        assert_eq!(frame, [0, 0, 255, 255, 255, 0, 0, 255]);
+    }
+
+    #[test]
+    fn test_load_png() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("logo.png");
+        let mut encoder = png::Encoder::new(File::create(&path).unwrap(), 2, 1);
+        encoder.set_color(png::ColorType::Rgb);
+        encoder.set_depth(png::BitDepth::Eight);
+        let mut writer = encoder.write_header().unwrap();
+        writer.write_image_data(&[255, 0, 0, 0, 255, 0]).unwrap();
+        writer.finish().unwrap();
+
+        let overlay = LogoOverlay::load(OverlayConfig {
+            path,
+            ..Default::default()
+        })
+        .unwrap();
+        assert_eq!((overlay.logo_width, overlay.logo_height), (2, 1));
+        assert_eq!(overlay.logo, [255, 0, 0, 255, 0, 255, 0, 255]);
+
+        let missing = OverlayConfig {
+            path: dir.path().join("missing.png"),
+            ..Default::default()
+        };
+        assert!(LogoOverlay::load(missing).is_err());
+    }
+}
@@ -0,0 +1,446 @@
+//! Pixel formats for video outputs
+//!
+//! Renderers read frames back as RGBA; outputs that want another layout
+//! convert them with [`convert_rgba`]. YUV formats use BT.601 studio swing
+//! (Y 16-235, chroma 16-240), with chroma averaged over each 2-pixel pair
+//! (YUYV, UYVY) or 2x2 block (NV12).
+//!
+//! On x86_64 the conversions run on SSE2, eight pixels at a time (four for
+//! BGRA); other targets and leftover pixels take the scalar path, which
+//! gives the same bytes.
+
+use serde::{Deserialize, Serialize};
+
+use super::output::VideoOutputError;
+
+/// Byte layout of frames sent to an output
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum PixelFormat {
+    /// 8-bit R, G, B, A (as read back from the GPU)
+    #[default]
+    Rgba,
+    /// 8-bit B, G, R, A
+    Bgra,
+    /// Packed 4:2:2, Y0 U Y1 V
+    Yuyv,
+    /// Packed 4:2:2, U Y0 V Y1
+    Uyvy,
+    /// Y plane followed by interleaved U/V at half resolution (4:2:0)
+    Nv12,
+}
+
+impl std::fmt::Display for PixelFormat {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            PixelFormat::Rgba => write!(f, "RGBA"),
+            PixelFormat::Bgra => write!(f, "BGRA"),
+            PixelFormat::Yuyv => write!(f, "YUYV"),
+            PixelFormat::Uyvy => write!(f, "UYVY"),
+            PixelFormat::Nv12 => write!(f, "NV12"),
+        }
+    }
+}
+
+impl std::str::FromStr for PixelFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "rgba" => Ok(PixelFormat::Rgba),
+            "bgra" => Ok(PixelFormat::Bgra),
+            "yuyv" | "yuy2" => Ok(PixelFormat::Yuyv),
+            "uyvy" => Ok(PixelFormat::Uyvy),
+            "nv12" => Ok(PixelFormat::Nv12),
+            _ => Err(format!(
+                "Unknown pixel format: {}. Use: rgba, bgra, yuyv, uyvy, nv12",
+                s
+            )),
+        }
+    }
+}
+
+impl PixelFormat {
+    /// FourCC code as NDI names it (V4L2 differs for RGBA and BGRA)
+    pub fn fourcc(&self) -> [u8; 4] {
+        match self {
+            PixelFormat::Rgba => *b"RGBA",
+            PixelFormat::Bgra => *b"BGRA",
+            PixelFormat::Yuyv => *b"YUYV",
+            PixelFormat::Uyvy => *b"UYVY",
+            PixelFormat::Nv12 => *b"NV12",
+        }
+    }
+
+    /// Bytes in one frame
+    pub fn frame_size(&self, width: u32, height: u32) -> usize {
+        let pixels = width as usize * height as usize;
+        match self {
+            PixelFormat::Rgba | PixelFormat::Bgra => pixels * 4,
+            PixelFormat::Yuyv | PixelFormat::Uyvy => pixels * 2,
+            PixelFormat::Nv12 => pixels * 3 / 2,
+        }
+    }
+
+    /// Check the frame size suits the chroma subsampling
+    pub fn check_size(&self, width: u32, height: u32) -> Result<(), VideoOutputError> {
+        let even = match self {
+            PixelFormat::Rgba | PixelFormat::Bgra => true,
+            PixelFormat::Yuyv | PixelFormat::Uyvy => width & 1 == 0,
+            PixelFormat::Nv12 => width & 1 == 0 && height & 1 == 0,
+        };
+        if even {
+            Ok(())
+        } else {
+            Err(VideoOutputError::InitError(format!(
+                "{} needs an even frame size, got {}x{}",
+                self, width, height
+            )))
+        }
+    }
+}
+
+/// Convert an RGBA frame to `format`, resizing `out` to fit
+pub fn convert_rgba(
+    rgba: &[u8],
+    width: u32,
+    height: u32,
+    format: PixelFormat,
+    out: &mut Vec<u8>,
+) -> Result<(), VideoOutputError> {
+    let expected = PixelFormat::Rgba.frame_size(width, height);
+    if rgba.len() != expected {
+        return Err(VideoOutputError::SendError(format!(
+            "Invalid pixel buffer size: got {}, expected {}",
+            rgba.len(),
+            expected
+        )));
+    }
+    format.check_size(width, height)?;
+    out.resize(format.frame_size(width, height), 0);
+
+    match format {
+        PixelFormat::Rgba => out.copy_from_slice(rgba),
+        PixelFormat::Bgra => {
+            #[cfg(target_arch = "x86_64")]
+            let done = sse2::bgra(rgba, out);
+            #[cfg(not(target_arch = "x86_64"))]
+            let done = 0;
+            scalar::bgra(&rgba[done * 4..], &mut out[done * 4..]);
+        }
+        PixelFormat::Yuyv | PixelFormat::Uyvy => {
+            let uyvy = format == PixelFormat::Uyvy;
+            #[cfg(target_arch = "x86_64")]
+            let done = sse2::packed(rgba, out, uyvy);
+            #[cfg(not(target_arch = "x86_64"))]
+            let done = 0;
+            scalar::packed(&rgba[done * 4..], &mut out[done * 2..], uyvy);
+        }
+        PixelFormat::Nv12 => {
+            let width = width as usize;
+            let (luma, chroma) = out.split_at_mut(width * height as usize);
+            let rows = rgba.chunks_exact(width * 8);
+            let luma_rows = luma.chunks_exact_mut(width * 2);
+            for ((src, dst), uv) in rows.zip(luma_rows).zip(chroma.chunks_exact_mut(width)) {
+                let (row0, row1) = src.split_at(width * 4);
+                let (y0, y1) = dst.split_at_mut(width);
+                #[cfg(target_arch = "x86_64")]
+                let done = sse2::nv12_rows(row0, row1, y0, y1, uv);
+                #[cfg(not(target_arch = "x86_64"))]
+                let done = 0;
+                scalar::nv12_rows(
+                    &row0[done * 4..],
+                    &row1[done * 4..],
+                    &mut y0[done..],
+                    &mut y1[done..],
+                    &mut uv[done..],
+                );
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Reference conversions, also used for what the SIMD path leaves over
+mod scalar {
+    /// BT.601 studio-swing Y, U, V of an RGBA pixel
+    fn yuv(px: &[u8]) -> (i32, i32, i32) {
+        let (r, g, b) = (px[0] as i32, px[1] as i32, px[2] as i32);
+        let y = ((66 * r + 129 * g + 25 * b + 128) >> 8) + 16;
+        let u = ((-38 * r - 74 * g + 112 * b + 128) >> 8) + 128;
+        let v = ((112 * r - 94 * g - 18 * b + 128) >> 8) + 128;
+        (y, u, v)
+    }
+
+    /// Rounding-up average, as SSE2 `pavgw` does
+    fn avg(a: i32, b: i32) -> i32 {
+        (a + b + 1) >> 1
+    }
+
+    pub(super) fn bgra(rgba: &[u8], out: &mut [u8]) {
+        for (src, dst) in rgba.chunks_exact(4).zip(out.chunks_exact_mut(4)) {
+            dst.copy_from_slice(&[src[2], src[1], src[0], src[3]]);
+        }
+    }
+
+    pub(super) fn packed(rgba: &[u8], out: &mut [u8], uyvy: bool) {
+        for (src, dst) in rgba.chunks_exact(8).zip(out.chunks_exact_mut(4)) {
+            let (y0, u0, v0) = yuv(&src[..4]);
+            let (y1, u1, v1) = yuv(&src[4..]);
+            let (u, v) = (avg(u0, u1) as u8, avg(v0, v1) as u8);
+            if uyvy {
+                dst.copy_from_slice(&[u, y0 as u8, v, y1 as u8]);
+            } else {
+                dst.copy_from_slice(&[y0 as u8, u, y1 as u8, v]);
+            }
+        }
+    }
+
+    pub(super) fn nv12_rows(row0: &[u8], row1: &[u8], y0: &mut [u8], y1: &mut [u8], uv: &mut [u8]) {
+        let pairs = row0.chunks_exact(8).zip(row1.chunks_exact(8));
+        let luma = y0.chunks_exact_mut(2).zip(y1.chunks_exact_mut(2));
+        for (((top, bottom), (y0, y1)), uv) in pairs.zip(luma).zip(uv.chunks_exact_mut(2)) {
+            let (ya, ua, va) = yuv(&top[..4]);
+            let (yb, ub, vb) = yuv(&top[4..]);
+            let (yc, uc, vc) = yuv(&bottom[..4]);
+            let (yd, ud, vd) = yuv(&bottom[4..]);
+            y0.copy_from_slice(&[ya as u8, yb as u8]);
+            y1.copy_from_slice(&[yc as u8, yd as u8]);
+            uv[0] = avg(avg(ua, uc), avg(ub, ud)) as u8;
+            uv[1] = avg(avg(va, vc), avg(vb, vd)) as u8;
+        }
+    }
+}
+
+/// SSE2 conversions (always available on x86_64)
+///
+/// Each returns how many pixels it converted; the caller finishes the rest.
+#[cfg(target_arch = "x86_64")]
+mod sse2 {
+    use std::arch::x86_64::*;
+
+    /// Y, U, V of 8 RGBA pixels at `px` as 16-bit lanes
+    ///
+    /// # Safety
+    /// `px` must point to 32 readable bytes.
+    unsafe fn yuv8(px: *const u8) -> (__m128i, __m128i, __m128i) {
+        let lo = _mm_loadu_si128(px as *const __m128i);
+        let hi = _mm_loadu_si128(px.add(16) as *const __m128i);
+        let mask = _mm_set1_epi32(0xff);
+        let r = _mm_packs_epi32(_mm_and_si128(lo, mask), _mm_and_si128(hi, mask));
+        let g = _mm_packs_epi32(
+            _mm_and_si128(_mm_srli_epi32(lo, 8), mask),
+            _mm_and_si128(_mm_srli_epi32(hi, 8), mask),
+        );
+        let b = _mm_packs_epi32(
+            _mm_and_si128(_mm_srli_epi32(lo, 16), mask),
+            _mm_and_si128(_mm_srli_epi32(hi, 16), mask),
+        );
+
+        let weigh = |cr: i16, cg: i16, cb: i16| {
+            let sum = _mm_add_epi16(
+                _mm_mullo_epi16(r, _mm_set1_epi16(cr)),
+                _mm_mullo_epi16(g, _mm_set1_epi16(cg)),
+            );
+            _mm_add_epi16(
+                _mm_add_epi16(sum, _mm_mullo_epi16(b, _mm_set1_epi16(cb))),
+                _mm_set1_epi16(128),
+            )
+        };
+        // Luma sums reach 56228, so they are shifted unsigned; 129 wraps
+        // to the same low 16 bits as a signed coefficient
+        let y = _mm_add_epi16(
+            _mm_srli_epi16(weigh(66, 129u16 as i16, 25), 8),
+            _mm_set1_epi16(16),
+        );
+        let u = _mm_add_epi16(_mm_srai_epi16(weigh(-38, -74, 112), 8), _mm_set1_epi16(128));
+        let v = _mm_add_epi16(_mm_srai_epi16(weigh(112, -94, -18), 8), _mm_set1_epi16(128));
+        (y, u, v)
+    }
+
+    /// Average each pair of 16-bit lanes into the low half of a 32-bit lane
+    unsafe fn pair_average(x: __m128i) -> __m128i {
+        _mm_and_si128(
+            _mm_avg_epu16(x, _mm_srli_epi32(x, 16)),
+            _mm_set1_epi32(0xffff),
+        )
+    }
+
+    pub(super) fn bgra(rgba: &[u8], out: &mut [u8]) -> usize {
+        let blocks = rgba.len().min(out.len()) / 16;
+        // SAFETY: every block reads and writes 16 bytes within both slices
+        unsafe {
+            let green_alpha = _mm_set1_epi32(0xff00ff00u32 as i32);
+            let red_blue = _mm_set1_epi32(0x00ff00ff);
+            for i in 0..blocks {
+                let x = _mm_loadu_si128(rgba.as_ptr().add(i * 16) as *const __m128i);
+                let rb = _mm_and_si128(x, red_blue);
+                let swapped = _mm_or_si128(_mm_slli_epi32(rb, 16), _mm_srli_epi32(rb, 16));
+                let bgra = _mm_or_si128(_mm_and_si128(x, green_alpha), swapped);
+                _mm_storeu_si128(out.as_mut_ptr().add(i * 16) as *mut __m128i, bgra);
+            }
+        }
+        blocks * 4
+    }
+
+    pub(super) fn packed(rgba: &[u8], out: &mut [u8], uyvy: bool) -> usize {
+        let blocks = (rgba.len() / 32).min(out.len() / 16);
+        // SAFETY: every block reads 32 bytes of `rgba` and writes 16 of `out`
+        unsafe {
+            for i in 0..blocks {
+                let (y, u, v) = yuv8(rgba.as_ptr().add(i * 32));
+                // 16-bit lanes U0 V0 U1 V1 ... one pair per two pixels
+                let uv = _mm_or_si128(pair_average(u), _mm_slli_epi32(pair_average(v), 16));
+                let packed = if uyvy {
+                    _mm_or_si128(uv, _mm_slli_epi16(y, 8))
+                } else {
+                    _mm_or_si128(y, _mm_slli_epi16(uv, 8))
+                };
+                _mm_storeu_si128(out.as_mut_ptr().add(i * 16) as *mut __m128i, packed);
+            }
+        }
+        blocks * 8
+    }
+
+    pub(super) fn nv12_rows(
+        row0: &[u8],
+        row1: &[u8],
+        y0: &mut [u8],
+        y1: &mut [u8],
+        uv: &mut [u8],
+    ) -> usize {
+        let blocks = (row0.len().min(row1.len()) / 32)
+            .min(y0.len().min(y1.len()) / 8)
+            .min(uv.len() / 8);
+        // SAFETY: every block reads 32 bytes of each row and writes 8 bytes
+        // of each output
+        unsafe {
+            for i in 0..blocks {
+                let (ya, ua, va) = yuv8(row0.as_ptr().add(i * 32));
+                let (yb, ub, vb) = yuv8(row1.as_ptr().add(i * 32));
+                _mm_storel_epi64(
+                    y0.as_mut_ptr().add(i * 8) as *mut __m128i,
+                    _mm_packus_epi16(ya, ya),
+                );
+                _mm_storel_epi64(
+                    y1.as_mut_ptr().add(i * 8) as *mut __m128i,
+                    _mm_packus_epi16(yb, yb),
+                );
+                let u = pair_average(_mm_avg_epu16(ua, ub));
+                let v = pair_average(_mm_avg_epu16(va, vb));
+                let chroma = _mm_or_si128(u, _mm_slli_epi32(v, 16));
+                _mm_storel_epi64(
+                    uv.as_mut_ptr().add(i * 8) as *mut __m128i,
+                    _mm_packus_epi16(chroma, chroma),
+                );
+            }
+        }
+        blocks * 8
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Deterministic test frame with every channel value mixed in
+    fn noise(width: u32, height: u32) -> Vec<u8> {
+        let mut state = 0x1234_5678u32;
+        (0..width * height * 4)
+            .map(|_| {
+                state = state.wrapping_mul(1_664_525).wrapping_add(1_013_904_223);
+                (state >> 24) as u8
+            })
+            .collect()
+    }
+
+    fn convert(rgba: &[u8], width: u32, height: u32, format: PixelFormat) -> Vec<u8> {
+        let mut out = Vec::new();
+        convert_rgba(rgba, width, height, format, &mut out).unwrap();
+        out
+    }
+
+    #[test]
+    fn test_pixel_format_parse_and_sizes() {
+        assert_eq!("NV12".parse::<PixelFormat>().unwrap(), PixelFormat::Nv12);
+        assert_eq!("yuy2".parse::<PixelFormat>().unwrap(), PixelFormat::Yuyv);
+        assert!("i420".parse::<PixelFormat>().is_err());
+        assert_eq!(PixelFormat::Rgba.frame_size(4, 2), 32);
+        assert_eq!(PixelFormat::Uyvy.frame_size(4, 2), 16);
+        assert_eq!(PixelFormat::Nv12.frame_size(4, 2), 12);
+        assert_eq!(
+            serde_json::to_string(&PixelFormat::Uyvy).unwrap(),
+            "\"uyvy\""
+        );
+    }
+
+    #[test]
+    fn test_odd_sizes_rejected() {
+        let mut out = Vec::new();
+        let rgba = vec![0; 3 * 2 * 4];
+        assert!(convert_rgba(&rgba, 3, 2, PixelFormat::Yuyv, &mut out).is_err());
+        assert!(convert_rgba(&rgba, 3, 2, PixelFormat::Bgra, &mut out).is_ok());
+        assert!(convert_rgba(&rgba[..24], 2, 3, PixelFormat::Nv12, &mut out).is_err());
+        assert!(convert_rgba(&rgba, 4, 2, PixelFormat::Rgba, &mut out).is_err());
+    }
+
+    #[test]
+    fn test_bgra_swaps_red_and_blue() {
+        let rgba = noise(5, 1);
+        let bgra = convert(&rgba, 5, 1, PixelFormat::Bgra);
+        for (src, dst) in rgba.chunks_exact(4).zip(bgra.chunks_exact(4)) {
+            assert_eq!(dst, [src[2], src[1], src[0], src[3]]);
+        }
+    }
+
+    #[test]
+    fn test_yuv_studio_swing() {
+        let white = [255u8; 8];
+        let black = [0, 0, 0, 255, 0, 0, 0, 255];
+        let gray = [128, 128, 128, 255, 128, 128, 128, 255];
+        assert_eq!(
+            convert(&white, 2, 1, PixelFormat::Yuyv),
+            [235, 128, 235, 128]
+        );
+        assert_eq!(convert(&black, 2, 1, PixelFormat::Yuyv), [16, 128, 16, 128]);
+        assert_eq!(
+            convert(&gray, 2, 1, PixelFormat::Uyvy),
+            [128, 126, 128, 126]
+        );
+        let red = [255, 0, 0, 255, 255, 0, 0, 255];
+        assert_eq!(convert(&red, 2, 1, PixelFormat::Yuyv), [82, 90, 82, 240]);
+    }
+
+    #[test]
+    fn test_simd_matches_scalar() {
+        // 22 wide: two SIMD blocks per row plus a scalar tail
+        let (width, height) = (22, 4);
+        let rgba = noise(width, height);
+
+        let mut expected = vec![0; 22 * 4 * 4];
+        scalar::bgra(&rgba, &mut expected);
+        assert_eq!(convert(&rgba, width, height, PixelFormat::Bgra), expected);
+
+        for uyvy in [false, true] {
+            let mut expected = vec![0; 22 * 4 * 2];
+            scalar::packed(&rgba, &mut expected, uyvy);
+            let format = if uyvy {
+                PixelFormat::Uyvy
+            } else {
+                PixelFormat::Yuyv
+            };
+            assert_eq!(convert(&rgba, width, height, format), expected);
+        }
+
+        let w = width as usize;
+        let mut expected = vec![0; PixelFormat::Nv12.frame_size(width, height)];
+        let (luma, chroma) = expected.split_at_mut(w * 4);
+        for pair in 0..2 {
+            let rows = &rgba[pair * w * 8..(pair + 1) * w * 8];
+            let (y0, y1) = luma[pair * w * 2..(pair + 1) * w * 2].split_at_mut(w);
+            let uv = &mut chroma[pair * w..(pair + 1) * w];
+            scalar::nv12_rows(&rows[..w * 4], &rows[w * 4..], y0, y1, uv);
+        }
+        assert_eq!(convert(&rgba, width, height, PixelFormat::Nv12), expected);
+    }
+}
@@ -0,0 +1,146 @@
+//! projectM's own preset switching
+//!
+//! Besides switches from the playlist, projectM can ask for the next preset
+//! when a preset has played for a while (a soft cut, blended) or on a loud
+//! beat (a hard cut). The deck's playlist picks the preset either way; these
+//! settings only decide when projectM asks and how the switch looks. Both
+//! are off by default, leaving switching to the playlist's auto-cycle.
+
+use serde::{Deserialize, Serialize};
+
+use super::output::VideoOutputError;
+
+/// Preset durations allowed, besides 0 for never
+pub const PRESET_DURATION_RANGE: std::ops::RangeInclusive<f64> = 1.0..=3600.0;
+
+/// Soft cut blend times allowed
+pub const SOFT_CUT_RANGE: std::ops::RangeInclusive<f64> = 0.0..=30.0;
+
+/// Minimum times between hard cuts allowed
+pub const HARD_CUT_DURATION_RANGE: std::ops::RangeInclusive<f64> = 1.0..=600.0;
+
+/// Hard cut sensitivities allowed
+pub const HARD_CUT_SENSITIVITY_RANGE: std::ops::RangeInclusive<f32> = 0.0..=5.0;
+
+/// When projectM switches presets on its own, for a deck
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct PresetCuts {
+    /// Seconds a preset plays before projectM asks for the next one; 0
+    /// never asks
+    pub preset_duration: f64,
+    /// Seconds a timed switch blends from the old preset to the new one
+    pub soft_cut_duration: f64,
+    /// Cut straight to the next preset on loud beats
+    pub hard_cut_enabled: bool,
+    /// Minimum seconds between hard cuts
+    pub hard_cut_duration: f64,
+    /// How far above average a beat must be to cut; higher cuts less often
+    pub hard_cut_sensitivity: f32,
+}
+
+impl Default for PresetCuts {
+    /// projectM's own timings, with both kinds of switch off
+    fn default() -> Self {
+        Self {
+            preset_duration: 0.0,
+            soft_cut_duration: 3.0,
+            hard_cut_enabled: false,
+            hard_cut_duration: 20.0,
+            hard_cut_sensitivity: 2.0,
+        }
+    }
+}
+
+impl PresetCuts {
+    pub fn validate(&self) -> Result<(), VideoOutputError> {
+        let invalid = |message: String| Err(VideoOutputError::InitError(message));
+        if self.preset_duration != 0.0 && !PRESET_DURATION_RANGE.contains(&self.preset_duration) {
+            return invalid(format!(
+                "Invalid preset duration {}: use 0 (never) or {}-{} seconds",
+                self.preset_duration,
+                PRESET_DURATION_RANGE.start(),
+                PRESET_DURATION_RANGE.end()
+            ));
+        }
+        if !SOFT_CUT_RANGE.contains(&self.soft_cut_duration) {
+            return invalid(format!(
+                "Invalid soft cut duration {}: use {}-{} seconds",
+                self.soft_cut_duration,
+                SOFT_CUT_RANGE.start(),
+                SOFT_CUT_RANGE.end()
+            ));
+        }
+        if !HARD_CUT_DURATION_RANGE.contains(&self.hard_cut_duration) {
+            return invalid(format!(
+                "Invalid hard cut duration {}: use {}-{} seconds",
+                self.hard_cut_duration,
+                HARD_CUT_DURATION_RANGE.start(),
+                HARD_CUT_DURATION_RANGE.end()
+            ));
+        }
+        if !HARD_CUT_SENSITIVITY_RANGE.contains(&self.hard_cut_sensitivity) {
+            return invalid(format!(
+                "Invalid hard cut sensitivity {}: use {}-{}",
+                self.hard_cut_sensitivity,
+                HARD_CUT_SENSITIVITY_RANGE.start(),
+                HARD_CUT_SENSITIVITY_RANGE.end()
+            ));
+        }
+        Ok(())
+    }
+
+    /// Whether projectM may ask for switches at all
+    pub fn any(&self) -> bool {
+        self.preset_duration > 0.0 || self.hard_cut_enabled
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_validate() {
+        let cuts = PresetCuts::default();
+        assert!(cuts.validate().is_ok());
+        assert!(!cuts.any());
+
+        let timed = PresetCuts {
+            preset_duration: 45.0,
+            hard_cut_enabled: true,
+            ..cuts
+        };
+        assert!(timed.validate().is_ok());
+        assert!(timed.any());
+
+        for invalid in [
+            PresetCuts {
+                preset_duration: 0.5,
+                ..cuts
+            },
+            PresetCuts {
+                soft_cut_duration: -1.0,
+                ..cuts
+            },
+            PresetCuts {
+                hard_cut_duration: 0.0,
+                ..cuts
+            },
+            PresetCuts {
+                hard_cut_sensitivity: f32::NAN,
+                ..cuts
+            },
+        ] {
+            assert!(invalid.validate().is_err(), "{:?}", invalid);
+        }
+    }
+
+    #[test]
+    fn test_missing_fields_default() {
+        let cuts: PresetCuts = serde_json::from_str(r#"{"hard_cut_enabled":true}"#).unwrap();
+        assert!(cuts.hard_cut_enabled);
+        assert_eq!(cuts.hard_cut_duration, 20.0);
+        assert_eq!(cuts.preset_duration, 0.0);
+    }
+}
@@ -0,0 +1,145 @@
+//! projectM rendering quality
+//!
+//! Low-end machines can trade picture quality for frame rate per deck: a
+//! coarser warp mesh, a lower frame rate hint, and a smaller internal render
+//! that is stretched to the frame, optionally shrunk further while frames
+//! run over budget.
+
+use serde::{Deserialize, Serialize};
+
+use super::output::VideoOutputError;
+
+/// Warp mesh columns or rows allowed
+pub const MESH_RANGE: std::ops::RangeInclusive<u32> = 8..=256;
+
+/// Frame rate hints allowed
+pub const FPS_RANGE: std::ops::RangeInclusive<u32> = 10..=144;
+
+/// Internal render sizes allowed, besides 0 for the frame size
+pub const TEXTURE_SIZE_RANGE: std::ops::RangeInclusive<u32> = 128..=8192;
+
+/// projectM quality settings for a deck
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct RenderQuality {
+    /// Columns of the mesh presets warp the image with
+    pub mesh_width: u32,
+    /// Rows of the warp mesh
+    pub mesh_height: u32,
+    /// Frame rate presets time their animation to
+    pub fps: u32,
+    /// Longest side of the internal render in pixels; 0 renders at the
+    /// frame size
+    pub texture_size: u32,
+    /// Render smaller while frames take longer than the frame budget
+    pub adaptive: bool,
+}
+
+impl Default for RenderQuality {
+    /// projectM's own defaults, at the frame size
+    fn default() -> Self {
+        Self {
+            mesh_width: 32,
+            mesh_height: 24,
+            fps: 60,
+            texture_size: 0,
+            adaptive: false,
+        }
+    }
+}
+
+impl RenderQuality {
+    pub fn validate(&self) -> Result<(), VideoOutputError> {
+        let invalid = |message: String| Err(VideoOutputError::InitError(message));
+        for mesh in [self.mesh_width, self.mesh_height] {
+            if !MESH_RANGE.contains(&mesh) {
+                return invalid(format!(
+                    "Invalid mesh size {}: use {}-{}",
+                    mesh,
+                    MESH_RANGE.start(),
+                    MESH_RANGE.end()
+                ));
+            }
+        }
+        if !FPS_RANGE.contains(&self.fps) {
+            return invalid(format!(
+                "Invalid FPS {}: use {}-{}",
+                self.fps,
+                FPS_RANGE.start(),
+                FPS_RANGE.end()
+            ));
+        }
+        if self.texture_size != 0 && !TEXTURE_SIZE_RANGE.contains(&self.texture_size) {
+            return invalid(format!(
+                "Invalid texture size {}: use 0 (frame size) or {}-{}",
+                self.texture_size,
+                TEXTURE_SIZE_RANGE.start(),
+                TEXTURE_SIZE_RANGE.end()
+            ));
+        }
+        Ok(())
+    }
+
+    /// Size projectM renders at for a `width`x`height` frame: scaled down,
+    /// keeping the aspect ratio, so the longest side fits the texture size
+    pub fn render_size(&self, width: u32, height: u32) -> (u32, u32) {
+        let longest = width.max(height);
+        if self.texture_size == 0 || longest <= self.texture_size {
+            return (width, height);
+        }
+        let scale = self.texture_size as f64 / longest as f64;
+        let scaled = |side: u32| ((side as f64 * scale).round() as u32).max(1);
+        (scaled(width), scaled(height))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_validate() {
+        assert!(RenderQuality::default().validate().is_ok());
+        let low = RenderQuality {
+            mesh_width: 16,
+            mesh_height: 12,
+            fps: 30,
+            texture_size: 512,
+            adaptive: true,
+        };
+        assert!(low.validate().is_ok());
+
+        for invalid in [
+            RenderQuality {
+                mesh_width: 4,
+                ..low
+            },
+            RenderQuality {
+                mesh_height: 1000,
+                ..low
+            },
+            RenderQuality { fps: 0, ..low },
+            RenderQuality {
+                texture_size: 64,
+                ..low
+            },
+        ] {
+            assert!(invalid.validate().is_err(), "{:?}", invalid);
+        }
+    }
+
+    #[test]
+    fn test_render_size() {
+        let full = RenderQuality::default();
+        assert_eq!(full.render_size(1920, 1080), (1920, 1080));
+
+        let reduced = RenderQuality {
+            texture_size: 1024,
+            ..full
+        };
+        assert_eq!(reduced.render_size(1920, 1080), (1024, 576));
+        assert_eq!(reduced.render_size(1080, 1920), (576, 1024));
+        // Frames already small enough aren't scaled up
+        assert_eq!(reduced.render_size(800, 600), (800, 600));
+    }
+}
@@ -0,0 +1,621 @@
+//! Video recording of deck output
+//!
+//! Captured RGBA frames are piped to an `ffmpeg` process that encodes them
+//! to H.264 in an MP4 file, or to HAP or ProRes in a QuickTime file for media
+//! servers and editing. Frames go through a writer thread so the render
+//! loop never waits on the encoder; when it falls behind, frames are dropped
+//! rather than stalling the visuals. Frames arrive at the render rate and are
+//! paced to the recording's frame rate by dropping or repeating them.
+
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::process::{Child, Command, Stdio};
+use std::thread::{self, JoinHandle};
+use std::time::Instant;
+
+use crossbeam_channel::{bounded, Sender, TrySendError};
+use serde::{Deserialize, Serialize};
+
+use super::output::{OutputBackend, VideoOutput, VideoOutputError};
+
+/// Default frame rate of recordings
+pub const DEFAULT_RECORDING_FPS: u32 = 30;
+/// Default video bitrate of recordings (kbit/s)
+pub const DEFAULT_RECORDING_BITRATE_KBPS: u32 = 8000;
+
+/// Frames waiting for the encoder before new ones are dropped
+const QUEUE_FRAMES: usize = 8;
+
+/// Video codec of a recording
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum RecordingCodec {
+    /// H.264 in MP4: small files for sharing
+    #[default]
+    #[serde(rename = "h264")]
+    H264,
+    /// HAP in QuickTime: decoded on the GPU by Resolume, TouchDesigner, etc.
+    #[serde(rename = "hap")]
+    Hap,
+    /// HAP Q: sharper HAP at about twice the size
+    #[serde(rename = "hap_q")]
+    HapQ,
+    /// ProRes 422 HQ in QuickTime: for editing
+    #[serde(rename = "prores")]
+    ProRes,
+}
+
+impl std::fmt::Display for RecordingCodec {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            RecordingCodec::H264 => write!(f, "H.264"),
+            RecordingCodec::Hap => write!(f, "HAP"),
+            RecordingCodec::HapQ => write!(f, "HAP Q"),
+            RecordingCodec::ProRes => write!(f, "ProRes 422 HQ"),
+        }
+    }
+}
+
+impl std::str::FromStr for RecordingCodec {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "h264" | "h.264" | "mp4" => Ok(RecordingCodec::H264),
+            "hap" => Ok(RecordingCodec::Hap),
+            "hap_q" | "hapq" => Ok(RecordingCodec::HapQ),
+            "prores" => Ok(RecordingCodec::ProRes),
+            _ => Err(format!(
+                "Unknown recording codec: {}. Use: h264, hap, hap_q, prores",
+                s
+            )),
+        }
+    }
+}
+
+impl RecordingCodec {
+    /// File extension of the container
+    pub fn extension(self) -> &'static str {
+        match self {
+            RecordingCodec::H264 => "mp4",
+            RecordingCodec::Hap | RecordingCodec::HapQ | RecordingCodec::ProRes => "mov",
+        }
+    }
+
+    /// ffmpeg encoder that has to be built in
+    pub fn encoder(self) -> &'static str {
+        match self {
+            RecordingCodec::H264 => "libx264",
+            RecordingCodec::Hap | RecordingCodec::HapQ => "hap",
+            RecordingCodec::ProRes => "prores_ks",
+        }
+    }
+
+    /// Whether the bitrate setting applies (HAP and ProRes have fixed quality)
+    pub fn uses_bitrate(self) -> bool {
+        self == RecordingCodec::H264
+    }
+
+    /// `path` with the container's extension
+    pub fn output_path(self, path: &Path) -> PathBuf {
+        let matches = path
+            .extension()
+            .is_some_and(|ext| ext.eq_ignore_ascii_case(self.extension()));
+        if matches {
+            path.to_path_buf()
+        } else {
+            path.with_extension(self.extension())
+        }
+    }
+
+    /// Encoder arguments
+    fn ffmpeg_args(self, bitrate_kbps: u32) -> Vec<String> {
+        let bitrate = format!("{}k", bitrate_kbps.max(1));
+        let args: &[&str] = match self {
+            RecordingCodec::H264 => &[
+                "-c:v",
+                "libx264",
+                "-preset",
+                "veryfast",
+                // Players expect 4:2:0, which needs even dimensions
+                "-vf",
+                "scale=trunc(iw/2)*2:trunc(ih/2)*2",
+                "-pix_fmt",
+                "yuv420p",
+                "-b:v",
+                &bitrate,
+                "-maxrate",
+                &bitrate,
+                "-bufsize",
+                &format!("{}k", bitrate_kbps.max(1) * 2),
+            ],
+            RecordingCodec::Hap | RecordingCodec::HapQ => &[
+                "-c:v",
+                "hap",
+                "-format",
+                if self == RecordingCodec::HapQ {
+                    "hap_q"
+                } else {
+                    "hap"
+                },
+                // HAP compresses 4x4 blocks
+                "-vf",
+                "scale=trunc(iw/4)*4:trunc(ih/4)*4",
+            ],
+            RecordingCodec::ProRes => &[
+                "-c:v",
+                "prores_ks",
+                "-profile:v",
+                "hq",
+                "-vendor",
+                "apl0",
+                "-vf",
+                "scale=trunc(iw/2)*2:trunc(ih/2)*2",
+                "-pix_fmt",
+                "yuv422p10le",
+            ],
+        };
+        args.iter().map(|arg| arg.to_string()).collect()
+    }
+}
+
+/// Whether `ffmpeg -encoders` output lists `encoder`
+fn lists_encoder(encoders: &str, encoder: &str) -> bool {
+    // Lines look like " V....D libx264   libx264 H.264 / AVC ..."
+    encoders
+        .lines()
+        .any(|line| line.split_whitespace().nth(1) == Some(encoder))
+}
+
+/// Recording settings
+#[derive(Debug, Clone)]
+pub struct RecordingConfig {
+    /// Video file to write
+    pub path: PathBuf,
+    /// Frame size (must match the captured frames)
+    pub width: u32,
+    pub height: u32,
+    /// Frames per second of the file
+    pub fps: u32,
+    /// Video bitrate (kbit/s, H.264 only)
+    pub bitrate_kbps: u32,
+    /// Video codec and container
+    pub codec: RecordingCodec,
+    /// ffmpeg executable
+    pub ffmpeg: PathBuf,
+}
+
+impl RecordingConfig {
+    /// Record `width`x`height` frames to `path` with default settings
+    pub fn new(path: impl Into<PathBuf>, width: u32, height: u32) -> Self {
+        Self {
+            path: path.into(),
+            width,
+            height,
+            fps: DEFAULT_RECORDING_FPS,
+            bitrate_kbps: DEFAULT_RECORDING_BITRATE_KBPS,
+            codec: RecordingCodec::default(),
+            ffmpeg: PathBuf::from("ffmpeg"),
+        }
+    }
+
+    /// ffmpeg arguments reading raw RGBA from stdin and writing the codec's file
+    pub fn ffmpeg_args(&self) -> Vec<String> {
+        let input = [
+            "-hide_banner",
+            "-loglevel",
+            "error",
+            "-f",
+            "rawvideo",
+            "-pixel_format",
+            "rgba",
+            "-video_size",
+            &format!("{}x{}", self.width, self.height),
+            "-framerate",
+            &self.fps.max(1).to_string(),
+            "-i",
+            "-",
+        ];
+        input
+            .iter()
+            .map(|arg| arg.to_string())
+            .chain(self.codec.ffmpeg_args(self.bitrate_kbps))
+            .chain(["-movflags", "+faststart", "-y"].map(String::from))
+            .chain(std::iter::once(self.path.to_string_lossy().to_string()))
+            .collect()
+    }
+}
+
+/// Paces frames arriving at the render rate to a fixed frame rate
+#[derive(Debug, Clone)]
+pub struct FramePacer {
+    fps: u32,
+    start: Option<Instant>,
+    /// Frames handed out so far
+    frames: u64,
+}
+
+impl FramePacer {
+    /// Pace to `fps` frames per second
+    pub fn new(fps: u32) -> Self {
+        Self {
+            fps: fps.max(1),
+            start: None,
+            frames: 0,
+        }
+    }
+
+    /// How many times to write the frame rendered at `now`: 0 drops it, more
+    /// than 1 repeats it to fill a gap (at most a second's worth)
+    pub fn frames_due(&mut self, now: Instant) -> u32 {
+        let start = *self.start.get_or_insert(now);
+        let elapsed = now.saturating_duration_since(start).as_secs_f64();
+        // The frame at time 0 is due immediately
+        let target = (elapsed * self.fps as f64).floor() as u64 + 1;
+        let due = target.saturating_sub(self.frames).min(self.fps as u64);
+        self.frames = self.frames.max(target - due) + due;
+        due as u32
+    }
+
+    /// Frames handed out so far
+    pub fn frames(&self) -> u64 {
+        self.frames
+    }
+}
+
+/// Summary of a finished recording
+#[derive(Debug, Clone)]
+pub struct VideoRecordingInfo {
+    /// Path of the video file
+    pub path: PathBuf,
+    /// Frames written to the file
+    pub frames: u64,
+    /// Frames dropped because the encoder fell behind
+    pub dropped: u64,
+    /// Frame rate of the file
+    pub fps: u32,
+}
+
+impl VideoRecordingInfo {
+    /// Recording length in seconds
+    pub fn duration_secs(&self) -> f64 {
+        self.frames as f64 / self.fps.max(1) as f64
+    }
+}
+
+/// Records frames to a video file through ffmpeg
+pub struct VideoRecorder {
+    config: RecordingConfig,
+    name: String,
+    frame_tx: Option<Sender<(Vec<u8>, u32)>>,
+    writer: Option<JoinHandle<Result<u64, String>>>,
+    child: Child,
+    pacer: FramePacer,
+    dropped: u64,
+    active: bool,
+}
+
+impl VideoRecorder {
+    /// Start ffmpeg and the writer thread
+    pub fn start(config: RecordingConfig) -> Result<Self, VideoOutputError> {
+        if config.width == 0 || config.height == 0 {
+            return Err(VideoOutputError::InitError(
+                "Recording needs a non-empty frame size".to_string(),
+            ));
+        }
+
+        let mut child = Command::new(&config.ffmpeg)
+            .args(config.ffmpeg_args())
+            .stdin(Stdio::piped())
+            .stdout(Stdio::null())
+            .stderr(Stdio::inherit())
+            .spawn()
+            .map_err(|e| {
+                VideoOutputError::InitError(format!(
+                    "Failed to start {}: {}. Install ffmpeg (with libx264) to record",
+                    config.ffmpeg.display(),
+                    e
+                ))
+            })?;
+        let mut stdin = child
+            .stdin
+            .take()
+            .ok_or_else(|| VideoOutputError::InitError("ffmpeg has no stdin".to_string()))?;
+
+        // Each frame carries how many times to write it
+        let (frame_tx, frame_rx) = bounded::<(Vec<u8>, u32)>(QUEUE_FRAMES);
+        let writer = thread::spawn(move || {
+            let mut written = 0u64;
+            for (pixels, repeat) in frame_rx {
+                for _ in 0..repeat {
+                    stdin
+                        .write_all(&pixels)
+                        .map_err(|e| format!("ffmpeg stopped accepting frames: {}", e))?;
+                    written += 1;
+                }
+            }
+            // Dropping stdin ends the stream so ffmpeg finalizes the file
+            Ok(written)
+        });
+
+        tracing::info!(
+            "Recording {}x{} {} at {} fps to {}",
+            config.width,
+            config.height,
+            config.codec,
+            config.fps,
+            config.path.display()
+        );
+        Ok(Self {
+            name: format!("recording:{}", config.path.display()),
+            pacer: FramePacer::new(config.fps),
+            config,
+            frame_tx: Some(frame_tx),
+            writer: Some(writer),
+            child,
+            dropped: 0,
+            active: true,
+        })
+    }
+
+    /// Check if ffmpeg can be run
+    pub fn is_available() -> bool {
+        Command::new("ffmpeg")
+            .arg("-version")
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .status()
+            .is_ok_and(|status| status.success())
+    }
+
+    /// Check if ffmpeg was built with the encoder `codec` needs
+    pub fn supports_codec(codec: RecordingCodec) -> bool {
+        Command::new("ffmpeg")
+            .args(["-hide_banner", "-encoders"])
+            .stderr(Stdio::null())
+            .output()
+            .is_ok_and(|out| {
+                out.status.success()
+                    && lists_encoder(&String::from_utf8_lossy(&out.stdout), codec.encoder())
+            })
+    }
+
+    /// Path being recorded to
+    pub fn path(&self) -> &Path {
+        &self.config.path
+    }
+
+    /// Frames taken so far at the recording's frame rate, including dropped ones
+    pub fn frames(&self) -> u64 {
+        self.pacer.frames()
+    }
+
+    /// Finish encoding and close the file
+    pub fn stop(mut self) -> Result<VideoRecordingInfo, VideoOutputError> {
+        self.finish()
+    }
+
+    fn finish(&mut self) -> Result<VideoRecordingInfo, VideoOutputError> {
+        // Closing the channel lets the writer drain and close ffmpeg's stdin
+        self.frame_tx = None;
+        let written = match self.writer.take() {
+            Some(handle) => handle.join().map_err(|_| {
+                VideoOutputError::SendError("Recording writer panicked".to_string())
+            })?,
+            None => Ok(0),
+        };
+
+        let status = self
+            .child
+            .wait()
+            .map_err(|e| VideoOutputError::SendError(e.to_string()))?;
+        let frames = written.map_err(VideoOutputError::SendError)?;
+        if !status.success() {
+            return Err(VideoOutputError::SendError(format!(
+                "ffmpeg exited with {}",
+                status
+            )));
+        }
+
+        tracing::info!(
+            "Finished recording {} ({} frames, {} dropped)",
+            self.config.path.display(),
+            frames,
+            self.dropped
+        );
+        Ok(VideoRecordingInfo {
+            path: self.config.path.clone(),
+            frames,
+            dropped: self.dropped,
+            fps: self.config.fps.max(1),
+        })
+    }
+}
+
+impl VideoOutput for VideoRecorder {
+    fn backend(&self) -> OutputBackend {
+        OutputBackend::Recording
+    }
+
+    fn send_frame(
+        &mut self,
+        _texture_id: u32,
+        _width: u32,
+        _height: u32,
+    ) -> Result<(), VideoOutputError> {
+        Err(VideoOutputError::SendError(
+            "Recording needs RGBA frames".to_string(),
+        ))
+    }
+
+    fn send_frame_rgba(
+        &mut self,
+        pixels: &[u8],
+        width: u32,
+        height: u32,
+    ) -> Result<(), VideoOutputError> {
+        if !self.active {
+            return Ok(());
+        }
+        if width != self.config.width || height != self.config.height {
+            return Err(VideoOutputError::SendError(format!(
+                "Frame size changed to {}x{} while recording {}x{}",
+                width, height, self.config.width, self.config.height
+            )));
+        }
+
+        let repeat = self.pacer.frames_due(Instant::now());
+        if repeat == 0 {
+            return Ok(());
+        }
+        let Some(tx) = &self.frame_tx else {
+            return Ok(());
+        };
+        match tx.try_send((pixels.to_vec(), repeat)) {
+            Ok(()) => Ok(()),
+            Err(TrySendError::Full(_)) => {
+                self.dropped += repeat as u64;
+                Ok(())
+            }
+            Err(TrySendError::Disconnected(_)) => Err(VideoOutputError::SendError(
+                "ffmpeg stopped recording".to_string(),
+            )),
+        }
+    }
+
+    fn is_active(&self) -> bool {
+        self.active
+    }
+
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn set_active(&mut self, active: bool) {
+        self.active = active;
+    }
+}
+
+impl Drop for VideoRecorder {
+    fn drop(&mut self) {
+        if self.writer.is_some() {
+            if let Err(e) = self.finish() {
+                tracing::warn!("Failed to finalize recording: {}", e);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    #[test]
+    fn test_ffmpeg_args() {
+        let mut config = RecordingConfig::new("/tmp/deck.mp4", 1280, 720);
+        config.fps = 60;
+        config.bitrate_kbps = 12000;
+        let args = config.ffmpeg_args();
+
+        let value = |flag: &str| {
+            let index = args.iter().position(|a| a == flag).unwrap();
+            args[index + 1].clone()
+        };
+        assert_eq!(value("-video_size"), "1280x720");
+        assert_eq!(value("-framerate"), "60");
+        assert_eq!(value("-c:v"), "libx264");
+        assert_eq!(value("-b:v"), "12000k");
+        assert_eq!(args.last().unwrap(), "/tmp/deck.mp4");
+    }
+
+    #[test]
+    fn test_ffmpeg_args_for_media_server_codecs() {
+        let mut config = RecordingConfig::new("/tmp/loop.mov", 1280, 720);
+        config.codec = RecordingCodec::HapQ;
+        let args = config.ffmpeg_args();
+        let value = |flag: &str| {
+            let index = args.iter().position(|a| a == flag).unwrap();
+            args[index + 1].clone()
+        };
+        assert_eq!(value("-c:v"), "hap");
+        assert_eq!(value("-format"), "hap_q");
+        assert!(!args.contains(&"-b:v".to_string()));
+
+        config.codec = RecordingCodec::ProRes;
+        let args = config.ffmpeg_args();
+        let index = args.iter().position(|a| a == "-c:v").unwrap();
+        assert_eq!(args[index + 1], "prores_ks");
+        assert_eq!(args.last().unwrap(), "/tmp/loop.mov");
+    }
+
+    #[test]
+    fn test_codec_output_path() {
+        let path = Path::new("/tmp/set.mp4");
+        assert_eq!(
+            RecordingCodec::H264.output_path(path),
+            PathBuf::from("/tmp/set.mp4")
+        );
+        assert_eq!(
+            RecordingCodec::Hap.output_path(path),
+            PathBuf::from("/tmp/set.mov")
+        );
+        assert_eq!(
+            RecordingCodec::ProRes.output_path(Path::new("/tmp/set.MOV")),
+            PathBuf::from("/tmp/set.MOV")
+        );
+        assert_eq!("HAP_Q".parse::<RecordingCodec>(), Ok(RecordingCodec::HapQ));
+        assert!("vp9".parse::<RecordingCodec>().is_err());
+    }
+
+    #[test]
+    fn test_lists_encoder() {
+        let encoders = " V....D hap                  Vidvox Hap\n V....D prores_ks            Apple ProRes (iCodec Pro) (codec prores)\n";
+        assert!(lists_encoder(encoders, "hap"));
+        assert!(lists_encoder(encoders, "prores_ks"));
+        assert!(!lists_encoder(encoders, "libx264"));
+    }
+
+    #[test]
+    fn test_pacer_drops_frames_above_rate() {
+        // 60 fps rendering recorded at 30 fps keeps every other frame
+        let start = Instant::now();
+        let mut pacer = FramePacer::new(30);
+        let written: u32 = (0..60)
+            .map(|i| pacer.frames_due(start + Duration::from_micros(i * 16_667)))
+            .sum();
+        assert_eq!(written, 30);
+    }
+
+    #[test]
+    fn test_pacer_repeats_frames_after_stall() {
+        let start = Instant::now();
+        let mut pacer = FramePacer::new(30);
+        assert_eq!(pacer.frames_due(start), 1);
+        // A 100 ms hitch is filled with repeats of the next frame
+        assert_eq!(pacer.frames_due(start + Duration::from_millis(100)), 3);
+        // A long stall is capped at a second of repeats
+        assert_eq!(pacer.frames_due(start + Duration::from_secs(10)), 30);
+        assert_eq!(pacer.frames_due(start + Duration::from_millis(10_010)), 0);
+    }
+
+    #[test]
+    fn test_missing_ffmpeg_is_an_init_error() {
+        let mut config = RecordingConfig::new("/tmp/never.mp4", 64, 64);
+        config.ffmpeg = PathBuf::from("/nonexistent/ffmpeg");
+        assert!(matches!(
+            VideoRecorder::start(config),
+            Err(VideoOutputError::InitError(_))
+        ));
+    }
+
+    #[test]
+    fn test_recording_duration() {
+        let info = VideoRecordingInfo {
+            path: PathBuf::from("deck.mp4"),
+            frames: 90,
+            dropped: 0,
+            fps: 30,
+        };
+        assert_eq!(info.duration_secs(), 3.0);
+    }
+}
@@ -0,0 +1,437 @@
+//! Screen capture input
+//!
+//! Captures the desktop, or a region of it, through an `ffmpeg` process so
+//! slides or a video player can be mixed into a deck: x11grab on Linux
+//! (X11 and XWayland only), DXGI desktop duplication (`ddagrab`, ffmpeg 6
+//! or later) on Windows and AVFoundation on macOS. Frames come back as PAM
+//! images, whose headers carry the size, so the desktop's resolution
+//! doesn't have to be known up front.
+
+use std::io::{BufRead, BufReader};
+use std::process::{Child, Command, Stdio};
+use std::thread::{self, JoinHandle};
+
+use crossbeam_channel::{bounded, Receiver, Sender, TryRecvError, TrySendError};
+
+use super::background::MAX_DECODE_SIZE;
+use super::input::{InputBackend, InputFrame, VideoInput};
+use super::output::VideoOutputError;
+use super::recorder::VideoRecorder;
+
+/// Frames per second the screen is captured at
+pub const SCREEN_CAPTURE_FPS: u32 = 30;
+
+/// Part of the screen to capture, in pixels from the top left corner
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ScreenRegion {
+    pub width: u32,
+    pub height: u32,
+    pub x: u32,
+    pub y: u32,
+}
+
+impl std::str::FromStr for ScreenRegion {
+    type Err = String;
+
+    /// X11 geometry: `WIDTHxHEIGHT`, optionally followed by `+X+Y`
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || {
+            format!(
+                "Invalid screen region: {}. Use WIDTHxHEIGHT+X+Y, e.g. 1280x720+0+0",
+                s
+            )
+        };
+        let mut parts = s.trim().split('+');
+        let size = parts.next().ok_or_else(invalid)?;
+        let (width, height) = size.split_once(['x', 'X']).ok_or_else(invalid)?;
+        let number = |part: &str| part.trim().parse::<u32>().map_err(|_| invalid());
+        let width = number(width)?;
+        let height = number(height)?;
+        let (x, y) = match (parts.next(), parts.next()) {
+            (None, _) => (0, 0),
+            (Some(x), Some(y)) => (number(x)?, number(y)?),
+            (Some(_), None) => return Err(invalid()),
+        };
+        if parts.next().is_some() || width == 0 || height == 0 {
+            return Err(invalid());
+        }
+        Ok(Self {
+            width,
+            height,
+            x,
+            y,
+        })
+    }
+}
+
+impl std::fmt::Display for ScreenRegion {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}x{}+{}+{}", self.width, self.height, self.x, self.y)
+    }
+}
+
+/// ffmpeg input arguments for x11grab on `display`
+pub fn x11grab_args(display: &str, region: Option<ScreenRegion>) -> Vec<String> {
+    let mut args = vec![
+        "-f".to_string(),
+        "x11grab".to_string(),
+        "-framerate".to_string(),
+        SCREEN_CAPTURE_FPS.to_string(),
+    ];
+    let input = match region {
+        Some(region) => {
+            args.push("-video_size".to_string());
+            args.push(format!("{}x{}", region.width, region.height));
+            format!("{}+{},{}", display, region.x, region.y)
+        }
+        None => display.to_string(),
+    };
+    args.push("-i".to_string());
+    args.push(input);
+    args
+}
+
+/// ffmpeg input arguments for DXGI desktop duplication of the first monitor;
+/// frames stay on the GPU until the filters download them
+pub fn ddagrab_args(region: Option<ScreenRegion>) -> Vec<String> {
+    let mut source = format!("ddagrab=output_idx=0:framerate={}", SCREEN_CAPTURE_FPS);
+    if let Some(region) = region {
+        source.push_str(&format!(
+            ":video_size={}x{}:offset_x={}:offset_y={}",
+            region.width, region.height, region.x, region.y
+        ));
+    }
+    vec![
+        "-f".to_string(),
+        "lavfi".to_string(),
+        "-i".to_string(),
+        source,
+    ]
+}
+
+/// ffmpeg input arguments for AVFoundation's capture of the main screen;
+/// it has no region option, so the region is cropped by the filters
+pub fn avfoundation_args() -> Vec<String> {
+    [
+        "-f",
+        "avfoundation",
+        "-capture_cursor",
+        "1",
+        "-framerate",
+        &SCREEN_CAPTURE_FPS.to_string(),
+        "-i",
+        "Capture screen 0:none",
+    ]
+    .iter()
+    .map(|s| s.to_string())
+    .collect()
+}
+
+/// Filters between the capture and the PAM frames: `download` for captures
+/// kept on the GPU, `crop` for captures that can't take a region, then
+/// scaled down to fit [`MAX_DECODE_SIZE`]
+pub fn filter_chain(download: bool, crop: Option<ScreenRegion>) -> String {
+    let mut filters = Vec::new();
+    if download {
+        filters.push("hwdownload,format=bgra".to_string());
+    }
+    if let Some(region) = crop {
+        filters.push(format!(
+            "crop={}:{}:{}:{}",
+            region.width, region.height, region.x, region.y
+        ));
+    }
+    filters.push(format!(
+        "scale='min(iw,{0})':'min(ih,{0})':force_original_aspect_ratio=decrease",
+        MAX_DECODE_SIZE
+    ));
+    filters.join(",")
+}
+
+/// Full ffmpeg arguments capturing `region` (or the whole screen) on this
+/// platform to PAM frames on stdout
+fn capture_args(region: Option<ScreenRegion>) -> Result<Vec<String>, VideoOutputError> {
+    let mut args: Vec<String> = ["-v", "error", "-nostdin"]
+        .iter()
+        .map(|s| s.to_string())
+        .collect();
+
+    #[cfg(target_os = "windows")]
+    let filters = {
+        args.extend(ddagrab_args(region));
+        filter_chain(true, None)
+    };
+    #[cfg(target_os = "macos")]
+    let filters = {
+        args.extend(avfoundation_args());
+        filter_chain(false, region)
+    };
+    #[cfg(not(any(target_os = "windows", target_os = "macos")))]
+    let filters = {
+        let display = std::env::var("DISPLAY").map_err(|_| {
+            VideoOutputError::InitError(
+                "Screen capture needs an X11 display; DISPLAY is not set".to_string(),
+            )
+        })?;
+        args.extend(x11grab_args(&display, region));
+        filter_chain(false, None)
+    };
+
+    args.push("-an".to_string());
+    args.push("-vf".to_string());
+    args.push(filters);
+    args.extend(
+        ["-c:v", "pam", "-pix_fmt", "rgba", "-f", "image2pipe", "-"]
+            .iter()
+            .map(|s| s.to_string()),
+    );
+    Ok(args)
+}
+
+/// Size of an RGBA frame from the lines of a PAM header, up to `ENDHDR`
+pub fn parse_pam_header(header: &str) -> Option<(u32, u32)> {
+    let mut lines = header.lines();
+    if lines.next()?.trim() != "P7" {
+        return None;
+    }
+    let (mut width, mut height, mut depth) = (None, None, None);
+    for line in lines {
+        let Some((key, value)) = line.trim().split_once(' ') else {
+            continue;
+        };
+        let value = value.trim().parse::<u32>().ok();
+        match key {
+            "WIDTH" => width = value,
+            "HEIGHT" => height = value,
+            "DEPTH" => depth = value,
+            _ => {}
+        }
+    }
+    let size = (width?, height?);
+    (depth? == 4 && size.0 > 0 && size.1 > 0).then_some(size)
+}
+
+/// Read one PAM frame into `pixels`; `None` when ffmpeg stopped or sent
+/// something else
+fn read_frame(reader: &mut impl BufRead, pixels: &mut Vec<u8>) -> Option<(u32, u32)> {
+    let mut header = String::new();
+    loop {
+        let start = header.len();
+        if reader.read_line(&mut header).ok()? == 0 {
+            return None;
+        }
+        if header[start..].trim() == "ENDHDR" {
+            break;
+        }
+    }
+    let (width, height) = parse_pam_header(&header)?;
+    pixels.resize(width as usize * height as usize * 4, 0);
+    reader.read_exact(pixels).ok()?;
+    Some((width, height))
+}
+
+/// A captured frame and its size
+struct Frame {
+    pixels: Vec<u8>,
+    width: u32,
+    height: u32,
+}
+
+/// Receives the desktop from an ffmpeg capture process
+pub struct ScreenInput {
+    name: String,
+    child: Child,
+    frames: Receiver<Frame>,
+    /// Buffers handed back for the next frames
+    recycle: Sender<Vec<u8>>,
+    reader: Option<JoinHandle<()>>,
+    current: Option<Frame>,
+}
+
+impl ScreenInput {
+    /// Whether ffmpeg can be run, and on Linux an X11 display is there to
+    /// capture
+    pub fn is_available() -> bool {
+        #[cfg(not(any(target_os = "windows", target_os = "macos")))]
+        if std::env::var_os("DISPLAY").is_none() {
+            return false;
+        }
+        VideoRecorder::is_available()
+    }
+
+    /// Start capturing; `source` is a region as `WIDTHxHEIGHT+X+Y`, or the
+    /// whole screen if `None`
+    pub fn new(source: Option<&str>) -> Result<Self, VideoOutputError> {
+        let region = source
+            .filter(|source| !source.trim().is_empty())
+            .map(|source| source.parse::<ScreenRegion>())
+            .transpose()
+            .map_err(VideoOutputError::InitError)?;
+
+        let mut child = Command::new("ffmpeg")
+            .args(capture_args(region)?)
+            .stdin(Stdio::null())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::inherit())
+            .spawn()
+            .map_err(|e| {
+                VideoOutputError::InitError(format!(
+                    "Screen capture needs ffmpeg installed and on the PATH: {}",
+                    e
+                ))
+            })?;
+        let stdout = child
+            .stdout
+            .take()
+            .ok_or_else(|| VideoOutputError::InitError("ffmpeg has no output pipe".to_string()))?;
+
+        let (frame_tx, frames) = bounded(1);
+        let (recycle, recycled) = bounded::<Vec<u8>>(2);
+        let reader = thread::Builder::new()
+            .name("screen-capture".to_string())
+            .spawn(move || {
+                let mut stdout = BufReader::new(stdout);
+                let mut spare = None;
+                loop {
+                    let mut pixels = spare
+                        .take()
+                        .or_else(|| recycled.try_recv().ok())
+                        .unwrap_or_default();
+                    let Some((width, height)) = read_frame(&mut stdout, &mut pixels) else {
+                        break;
+                    };
+                    match frame_tx.try_send(Frame {
+                        pixels,
+                        width,
+                        height,
+                    }) {
+                        Ok(()) => {}
+                        // The renderer hasn't taken the last one; skip this
+                        Err(TrySendError::Full(frame)) => spare = Some(frame.pixels),
+                        Err(TrySendError::Disconnected(_)) => break,
+                    }
+                }
+            })
+            .map_err(|e| VideoOutputError::InitError(e.to_string()))?;
+
+        Ok(Self {
+            name: region.map_or_else(|| "Desktop".to_string(), |region| region.to_string()),
+            child,
+            frames,
+            recycle,
+            reader: Some(reader),
+            current: None,
+        })
+    }
+}
+
+impl VideoInput for ScreenInput {
+    fn backend(&self) -> InputBackend {
+        InputBackend::Screen
+    }
+
+    fn source_name(&self) -> &str {
+        &self.name
+    }
+
+    fn receive_frame(&mut self) -> Result<Option<InputFrame<'_>>, VideoOutputError> {
+        let mut fresh = false;
+        loop {
+            match self.frames.try_recv() {
+                Ok(frame) => {
+                    if let Some(used) = self.current.replace(frame) {
+                        let _ = self.recycle.try_send(used.pixels);
+                    }
+                    fresh = true;
+                }
+                Err(TryRecvError::Empty) => break,
+                Err(TryRecvError::Disconnected) => {
+                    return Err(VideoOutputError::InitError(
+                        "ffmpeg stopped capturing the screen".to_string(),
+                    ));
+                }
+            }
+        }
+
+        match self.current {
+            Some(ref frame) if fresh => Ok(Some(InputFrame {
+                pixels: &frame.pixels,
+                width: frame.width,
+                height: frame.height,
+            })),
+            _ => Ok(None),
+        }
+    }
+}
+
+impl Drop for ScreenInput {
+    fn drop(&mut self) {
+        // Closing ffmpeg's pipe ends the reader's read
+        let _ = self.child.kill();
+        let _ = self.child.wait();
+        if let Some(reader) = self.reader.take() {
+            let _ = reader.join();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_region_from_str() {
+        let region: ScreenRegion = "1280x720+1920+0".parse().unwrap();
+        assert_eq!(
+            region,
+            ScreenRegion {
+                width: 1280,
+                height: 720,
+                x: 1920,
+                y: 0
+            }
+        );
+        assert_eq!(region.to_string(), "1280x720+1920+0");
+        assert_eq!("640X480".parse::<ScreenRegion>().unwrap().x, 0);
+        assert!("0x720".parse::<ScreenRegion>().is_err());
+        assert!("1280x720+10".parse::<ScreenRegion>().is_err());
+        assert!("screen".parse::<ScreenRegion>().is_err());
+    }
+
+    #[test]
+    fn test_capture_args() {
+        let region = "800x600+100+50".parse().ok();
+        let args = x11grab_args(":0", region);
+        assert_eq!(args[args.len() - 2..], ["-i", ":0+100,50"]);
+        assert!(args.contains(&"800x600".to_string()));
+        assert_eq!(x11grab_args(":1", None).last().unwrap(), ":1");
+        assert!(ddagrab_args(region)[3].ends_with(":offset_x=100:offset_y=50"));
+
+        assert!(filter_chain(true, None).starts_with("hwdownload,format=bgra,scale="));
+        assert!(filter_chain(false, region).starts_with("crop=800:600:100:50,scale="));
+    }
+
+    #[test]
+    fn test_read_pam_frames() {
+        let mut stream = Vec::new();
+        for value in [10u8, 20] {
+            stream.extend_from_slice(
+                b"P7\nWIDTH 2\nHEIGHT 1\nDEPTH 4\nMAXVAL 255\nTUPLTYPE RGB_ALPHA\nENDHDR\n",
+            );
+            stream.extend_from_slice(&[value; 8]);
+        }
+        let mut reader = &stream[..];
+        let mut pixels = Vec::new();
+        assert_eq!(read_frame(&mut reader, &mut pixels), Some((2, 1)));
+        assert_eq!(pixels, [10; 8]);
+        assert_eq!(read_frame(&mut reader, &mut pixels), Some((2, 1)));
+        assert_eq!(pixels, [20; 8]);
+        assert_eq!(read_frame(&mut reader, &mut pixels), None);
+
+        // RGB without alpha isn't what was asked for
+        assert_eq!(
+            parse_pam_header("P7\nWIDTH 2\nHEIGHT 1\nDEPTH 3\nENDHDR\n"),
+            None
+        );
+    }
+}
@@ -0,0 +1,318 @@
+//! PNG image-sequence export of deck output
+//!
+//! Writes numbered lossless frames (`frame_000000.png`, ...) at a fixed frame
+//! rate for offline editing, where an MP4's compression would cost quality.
+//! Frames are paced like recordings and encoded on a writer thread; when the
+//! disk or encoder falls behind, a dropped frame's slot is filled with a copy
+//! of the next one so the sequence keeps time.
+
+use std::fs::{self, File};
+use std::io::BufWriter;
+use std::path::{Path, PathBuf};
+use std::thread::{self, JoinHandle};
+use std::time::Instant;
+
+use crossbeam_channel::{bounded, Sender, TrySendError};
+
+use super::output::{OutputBackend, VideoOutput, VideoOutputError};
+use super::recorder::FramePacer;
+
+/// Default frame rate of image sequences
+pub const DEFAULT_SEQUENCE_FPS: u32 = 30;
+
+/// Frames waiting to be written before new ones are dropped
+const QUEUE_FRAMES: usize = 4;
+
+/// File name of frame `index` in a sequence
+pub fn frame_file_name(index: u64) -> String {
+    format!("frame_{:06}.png", index)
+}
+
+/// Whether `dir` already holds frames of a sequence
+fn has_frames(dir: &Path) -> bool {
+    fs::read_dir(dir)
+        .map(|entries| {
+            entries.flatten().any(|entry| {
+                let name = entry.file_name();
+                let name = name.to_string_lossy();
+                name.starts_with("frame_") && name.ends_with(".png")
+            })
+        })
+        .unwrap_or(false)
+}
+
+/// Encode an RGBA frame as a PNG file
+pub(super) fn write_png(
+    path: &Path,
+    pixels: &[u8],
+    width: u32,
+    height: u32,
+) -> Result<(), String> {
+    let file = File::create(path).map_err(|e| format!("{}: {}", path.display(), e))?;
+    let mut encoder = png::Encoder::new(BufWriter::new(file), width, height);
+    encoder.set_color(png::ColorType::Rgba);
+    encoder.set_depth(png::BitDepth::Eight);
+    // Lossless either way; fast compression keeps up with real time
+    encoder.set_compression(png::Compression::Fast);
+    let mut writer = encoder
+        .write_header()
+        .map_err(|e| format!("{}: {}", path.display(), e))?;
+    writer
+        .write_image_data(pixels)
+        .map_err(|e| format!("{}: {}", path.display(), e))?;
+    writer
+        .finish()
+        .map_err(|e| format!("{}: {}", path.display(), e))
+}
+
+/// Frame waiting for the writer thread
+struct QueuedFrame {
+    pixels: Vec<u8>,
+    width: u32,
+    height: u32,
+    /// Numbered copies to write
+    repeat: u32,
+}
+
+/// Summary of a finished image sequence
+#[derive(Debug, Clone)]
+pub struct ImageSequenceInfo {
+    /// Directory holding the frames
+    pub directory: PathBuf,
+    /// Frames written
+    pub frames: u64,
+    /// Frames dropped because writing fell behind (replaced by copies)
+    pub dropped: u64,
+    /// Frame rate of the sequence
+    pub fps: u32,
+}
+
+/// Writes frames to a directory as numbered PNGs
+pub struct ImageSequenceExporter {
+    directory: PathBuf,
+    fps: u32,
+    name: String,
+    frame_tx: Option<Sender<QueuedFrame>>,
+    writer: Option<JoinHandle<Result<u64, String>>>,
+    pacer: FramePacer,
+    /// Slots of dropped frames still to be filled
+    backlog: u32,
+    dropped: u64,
+    active: bool,
+}
+
+impl ImageSequenceExporter {
+    /// Start exporting to `directory` (created if missing) at `fps`
+    pub fn start(directory: impl Into<PathBuf>, fps: u32) -> Result<Self, VideoOutputError> {
+        let directory = directory.into();
+        fs::create_dir_all(&directory)
+            .map_err(|e| VideoOutputError::InitError(format!("{}: {}", directory.display(), e)))?;
+        // Numbering restarts at 0, so mixing with an earlier export would be silent
+        if has_frames(&directory) {
+            return Err(VideoOutputError::InitError(format!(
+                "{} already contains an image sequence",
+                directory.display()
+            )));
+        }
+
+        let (frame_tx, frame_rx) = bounded::<QueuedFrame>(QUEUE_FRAMES);
+        let dir = directory.clone();
+        let writer = thread::spawn(move || {
+            let mut written = 0u64;
+            for frame in frame_rx {
+                let first = dir.join(frame_file_name(written));
+                write_png(&first, &frame.pixels, frame.width, frame.height)?;
+                written += 1;
+                // Repeats are copies rather than re-encodes
+                for _ in 1..frame.repeat {
+                    let copy = dir.join(frame_file_name(written));
+                    fs::copy(&first, &copy).map_err(|e| format!("{}: {}", copy.display(), e))?;
+                    written += 1;
+                }
+            }
+            Ok(written)
+        });
+
+        tracing::info!(
+            "Exporting PNG frames at {} fps to {}",
+            fps,
+            directory.display()
+        );
+        Ok(Self {
+            name: format!("sequence:{}", directory.display()),
+            directory,
+            fps: fps.max(1),
+            frame_tx: Some(frame_tx),
+            writer: Some(writer),
+            pacer: FramePacer::new(fps),
+            backlog: 0,
+            dropped: 0,
+            active: true,
+        })
+    }
+
+    /// Directory being exported to
+    pub fn directory(&self) -> &Path {
+        &self.directory
+    }
+
+    /// Write the remaining frames and finish the sequence
+    pub fn stop(mut self) -> Result<ImageSequenceInfo, VideoOutputError> {
+        self.finish()
+    }
+
+    fn finish(&mut self) -> Result<ImageSequenceInfo, VideoOutputError> {
+        // Closing the channel lets the writer drain the queue and exit
+        self.frame_tx = None;
+        let written = match self.writer.take() {
+            Some(handle) => handle.join().map_err(|_| {
+                VideoOutputError::SendError("Image sequence writer panicked".to_string())
+            })?,
+            None => Ok(0),
+        };
+        let frames = written.map_err(VideoOutputError::SendError)?;
+
+        tracing::info!(
+            "Finished image sequence {} ({} frames, {} dropped)",
+            self.directory.display(),
+            frames,
+            self.dropped
+        );
+        Ok(ImageSequenceInfo {
+            directory: self.directory.clone(),
+            frames,
+            dropped: self.dropped,
+            fps: self.fps,
+        })
+    }
+}
+
+impl VideoOutput for ImageSequenceExporter {
+    fn backend(&self) -> OutputBackend {
+        OutputBackend::ImageSequence
+    }
+
+    fn send_frame(
+        &mut self,
+        _texture_id: u32,
+        _width: u32,
+        _height: u32,
+    ) -> Result<(), VideoOutputError> {
+        Err(VideoOutputError::SendError(
+            "Image sequences need RGBA frames".to_string(),
+        ))
+    }
+
+    fn send_frame_rgba(
+        &mut self,
+        pixels: &[u8],
+        width: u32,
+        height: u32,
+    ) -> Result<(), VideoOutputError> {
+        if !self.active {
+            return Ok(());
+        }
+        let expected_size = (width * height * 4) as usize;
+        if pixels.len() != expected_size {
+            return Err(VideoOutputError::SendError(format!(
+                "Invalid pixel buffer size: got {}, expected {}",
+                pixels.len(),
+                expected_size
+            )));
+        }
+
+        let repeat = self.pacer.frames_due(Instant::now());
+        if repeat == 0 {
+            return Ok(());
+        }
+        let Some(tx) = &self.frame_tx else {
+            return Ok(());
+        };
+        let frame = QueuedFrame {
+            pixels: pixels.to_vec(),
+            width,
+            height,
+            repeat: repeat + self.backlog,
+        };
+        match tx.try_send(frame) {
+            Ok(()) => {
+                self.backlog = 0;
+                Ok(())
+            }
+            Err(TrySendError::Full(_)) => {
+                self.backlog += repeat;
+                self.dropped += repeat as u64;
+                Ok(())
+            }
+            Err(TrySendError::Disconnected(_)) => Err(VideoOutputError::SendError(
+                "Image sequence writer stopped".to_string(),
+            )),
+        }
+    }
+
+    fn is_active(&self) -> bool {
+        self.active
+    }
+
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn set_active(&mut self, active: bool) {
+        self.active = active;
+    }
+}
+
+impl Drop for ImageSequenceExporter {
+    fn drop(&mut self) {
+        if self.writer.is_some() {
+            if let Err(e) = self.finish() {
+                tracing::warn!("Failed to finish image sequence: {}", e);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_frame_file_names_sort_in_order() {
+        assert_eq!(frame_file_name(0), "frame_000000.png");
+        assert_eq!(frame_file_name(42), "frame_000042.png");
+        assert!(frame_file_name(9) < frame_file_name(10));
+    }
+
+    #[test]
+    fn test_exports_png_frames() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut exporter = ImageSequenceExporter::start(dir.path().join("set"), 30).unwrap();
+        let pixels = [255u8, 0, 0, 255].repeat(4 * 2);
+        exporter.send_frame_rgba(&pixels, 4, 2).unwrap();
+        let info = exporter.stop().unwrap();
+
+        assert_eq!(info.frames, 1);
+        let decoder =
+            png::Decoder::new(File::open(info.directory.join("frame_000000.png")).unwrap());
+        let reader = decoder.read_info().unwrap();
+        assert_eq!((reader.info().width, reader.info().height), (4, 2));
+    }
+
+    #[test]
+    fn test_refuses_directory_with_a_sequence() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::write(dir.path().join(frame_file_name(0)), b"").unwrap();
+        assert!(matches!(
+            ImageSequenceExporter::start(dir.path(), 30),
+            Err(VideoOutputError::InitError(_))
+        ));
+    }
+
+    #[test]
+    fn test_rejects_wrong_buffer_size() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut exporter = ImageSequenceExporter::start(dir.path(), 30).unwrap();
+        assert!(exporter.send_frame_rgba(&[0; 3], 4, 2).is_err());
+    }
+}
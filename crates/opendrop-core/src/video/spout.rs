@@ -13,12 +13,15 @@
 //!
 //! 3. In OBS/Resolume: Add a "Spout2 Capture" source and select "OpenDrop"
 //!
+//! `SpoutInput` receives from another Spout sender the same way.
+//!
 //! ## Implementation
 //!
 //! Uses SpoutLibrary.dll with COM-like vtable interface.
 //! The vtable structure follows SPOUTLIBRARY from SpoutLibrary.h:
 //! <https://github.com/leadedge/Spout2/blob/master/SPOUTSDK/SpoutLibrary/SpoutLibrary.h>
 
+use super::input::{InputBackend, InputFrame, VideoInput};
 use super::output::{OutputBackend, VideoOutput, VideoOutputError};
 
 #[cfg(target_os = "windows")]
@@ -105,6 +108,15 @@ mod ffi {
     pub type GetHeightFn = unsafe extern "system" fn() -> u32;
     pub type ReleaseFn = unsafe extern "system" fn();
 
+    // Receiver methods, declared with the object as the first argument as
+    // the C++ ABI passes it
+    pub type SetReceiverNameFn = unsafe extern "system" fn(SpoutHandle, *const i8);
+    pub type ReleaseReceiverFn = unsafe extern "system" fn(SpoutHandle);
+    pub type ReceiveImageFn = unsafe extern "system" fn(SpoutHandle, *mut u8, GLenum, bool, GLuint) -> bool;
+    pub type HandleBoolFn = unsafe extern "system" fn(SpoutHandle) -> bool;
+    pub type HandleNameFn = unsafe extern "system" fn(SpoutHandle) -> *const i8;
+    pub type HandleSizeFn = unsafe extern "system" fn(SpoutHandle) -> u32;
+
     /// Vtable indices for SPOUTLIBRARY interface
     pub const VTABLE_SET_SENDER_NAME: usize = 0;
     pub const VTABLE_SET_SENDER_FORMAT: usize = 1;
@@ -116,6 +128,14 @@ mod ffi {
     pub const VTABLE_GET_NAME: usize = 7;
     pub const VTABLE_GET_WIDTH: usize = 8;
     pub const VTABLE_GET_HEIGHT: usize = 9;
+    pub const VTABLE_SET_RECEIVER_NAME: usize = 15;
+    pub const VTABLE_RELEASE_RECEIVER: usize = 16;
+    pub const VTABLE_RECEIVE_IMAGE: usize = 18;
+    pub const VTABLE_IS_UPDATED: usize = 19;
+    pub const VTABLE_IS_FRAME_NEW: usize = 21;
+    pub const VTABLE_GET_SENDER_NAME: usize = 22;
+    pub const VTABLE_GET_SENDER_WIDTH: usize = 23;
+    pub const VTABLE_GET_SENDER_HEIGHT: usize = 24;
     // ... many more methods, we only use what we need
     pub const VTABLE_RELEASE: usize = 145; // Last method: Release()
 }
@@ -159,46 +179,7 @@ unsafe impl Send for SpoutOutput {}
 impl SpoutOutput {
     /// Create a new Spout output
     pub fn new(config: SpoutConfig) -> Result<Self, VideoOutputError> {
-        // Try to load SpoutLibrary.dll from multiple locations
-        let library = unsafe {
-            Library::new("SpoutLibrary.dll")
-                .or_else(|_| Library::new("./SpoutLibrary.dll"))
-                .or_else(|_| Library::new("bin/SpoutLibrary.dll"))
-                .map_err(|e| {
-                    VideoOutputError::InitError(format!(
-                        "Failed to load SpoutLibrary.dll: {}. \n\
-                        Download from https://github.com/leadedge/Spout2/releases \n\
-                        and place in the application folder.",
-                        e
-                    ))
-                })?
-        };
-
-        // Get the GetSpout factory function
-        let get_spout: libloading::Symbol<ffi::GetSpoutFn> = unsafe {
-            library.get(b"GetSpout\0").map_err(|e| {
-                VideoOutputError::InitError(format!(
-                    "Failed to find GetSpout function in SpoutLibrary.dll: {}",
-                    e
-                ))
-            })?
-        };
-
-        // Create Spout instance
-        let handle = unsafe { get_spout() };
-        if handle.is_null() {
-            return Err(VideoOutputError::InitError(
-                "GetSpout() returned null handle".to_string()
-            ));
-        }
-
-        // Get vtable pointer (first pointer in the C++ object)
-        let vtable = unsafe { *(handle as *const *const *const c_void) };
-        if vtable.is_null() {
-            return Err(VideoOutputError::InitError(
-                "Spout vtable is null".to_string()
-            ));
-        }
+        let (library, handle, vtable) = load_spout()?;
 
         // Create sender name C string (must be kept alive)
         let sender_name_cstr = CString::new(config.sender_name.as_str())
@@ -394,6 +375,193 @@ impl Drop for SpoutOutput {
     }
 }
 
+/// Load SpoutLibrary.dll and create a Spout instance
+#[cfg(target_os = "windows")]
+fn load_spout() -> Result<(Library, ffi::SpoutHandle, *const *const c_void), VideoOutputError> {
+    let library = unsafe {
+        Library::new("SpoutLibrary.dll")
+            .or_else(|_| Library::new("./SpoutLibrary.dll"))
+            .or_else(|_| Library::new("bin/SpoutLibrary.dll"))
+            .map_err(|e| {
+                VideoOutputError::InitError(format!(
+                    "Failed to load SpoutLibrary.dll: {}. \n\
+                    Download from https://github.com/leadedge/Spout2/releases \n\
+                    and place in the application folder.",
+                    e
+                ))
+            })?
+    };
+
+    let handle = unsafe {
+        let get_spout: libloading::Symbol<ffi::GetSpoutFn> = library.get(b"GetSpout\0").map_err(|e| {
+            VideoOutputError::InitError(format!(
+                "Failed to find GetSpout function in SpoutLibrary.dll: {}",
+                e
+            ))
+        })?;
+        get_spout()
+    };
+    if handle.is_null() {
+        return Err(VideoOutputError::InitError(
+            "GetSpout() returned null handle".to_string()
+        ));
+    }
+
+    // Get vtable pointer (first pointer in the C++ object)
+    let vtable = unsafe { *(handle as *const *const *const c_void) };
+    if vtable.is_null() {
+        return Err(VideoOutputError::InitError(
+            "Spout vtable is null".to_string()
+        ));
+    }
+    Ok((library, handle, vtable))
+}
+
+/// Spout video input receiving from another application's sender
+#[cfg(target_os = "windows")]
+pub struct SpoutInput {
+    /// Handle to loaded SpoutLibrary.dll
+    _library: Library,
+    /// Spout instance handle (C++ object pointer)
+    handle: ffi::SpoutHandle,
+    /// Pointer to vtable
+    vtable: *const *const c_void,
+    /// Name of the sender being received
+    source: String,
+    /// Received pixels, sized to the sender
+    buffer: Vec<u8>,
+    width: u32,
+    height: u32,
+}
+
+// SpoutInput is Send for the same reasons as SpoutOutput
+#[cfg(target_os = "windows")]
+unsafe impl Send for SpoutInput {}
+
+#[cfg(target_os = "windows")]
+impl SpoutInput {
+    /// Receive from the named sender, or the active sender if `None`
+    pub fn new(source: Option<&str>) -> Result<Self, VideoOutputError> {
+        let (library, handle, vtable) = load_spout()?;
+
+        if let Some(name) = source {
+            let name_cstr = CString::new(name)
+                .map_err(|_| VideoOutputError::InitError("Invalid sender name (contains null byte)".to_string()))?;
+            // Spout copies the name, so the string needn't outlive the call
+            unsafe {
+                let set_receiver_name: ffi::SetReceiverNameFn =
+                    std::mem::transmute(*vtable.add(ffi::VTABLE_SET_RECEIVER_NAME));
+                set_receiver_name(handle, name_cstr.as_ptr());
+            }
+        }
+
+        tracing::info!("Spout input created: {}", source.unwrap_or("active sender"));
+
+        Ok(Self {
+            _library: library,
+            handle,
+            vtable,
+            source: source.unwrap_or_default().to_string(),
+            buffer: Vec::new(),
+            width: 0,
+            height: 0,
+        })
+    }
+
+    /// Check if Spout is available on this system
+    pub fn is_available() -> bool {
+        SpoutOutput::is_available()
+    }
+
+    unsafe fn call_bool(&self, index: usize) -> bool {
+        let f: ffi::HandleBoolFn = std::mem::transmute(*self.vtable.add(index));
+        f(self.handle)
+    }
+
+    unsafe fn call_size(&self, index: usize) -> u32 {
+        let f: ffi::HandleSizeFn = std::mem::transmute(*self.vtable.add(index));
+        f(self.handle)
+    }
+
+    /// Call ReceiveImage via vtable, copying the sender's frame into `buffer`
+    unsafe fn call_receive_image(&mut self) -> bool {
+        let receive_image: ffi::ReceiveImageFn =
+            std::mem::transmute(*self.vtable.add(ffi::VTABLE_RECEIVE_IMAGE));
+        let pixels = if self.buffer.is_empty() {
+            std::ptr::null_mut()
+        } else {
+            self.buffer.as_mut_ptr()
+        };
+        receive_image(
+            self.handle,
+            pixels,
+            ffi::GL_RGBA,
+            false, // bInvert - DirectX order, top row first
+            0,     // HostFBO - 0 = use default FBO
+        )
+    }
+}
+
+#[cfg(target_os = "windows")]
+impl VideoInput for SpoutInput {
+    fn backend(&self) -> InputBackend {
+        InputBackend::Spout
+    }
+
+    fn source_name(&self) -> &str {
+        &self.source
+    }
+
+    fn receive_frame(&mut self) -> Result<Option<InputFrame<'_>>, VideoOutputError> {
+        // False while no sender is running
+        if !unsafe { self.call_receive_image() } {
+            return Ok(None);
+        }
+
+        // Connected, or the sender changed size: resize and receive next time
+        if unsafe { self.call_bool(ffi::VTABLE_IS_UPDATED) } {
+            self.width = unsafe { self.call_size(ffi::VTABLE_GET_SENDER_WIDTH) };
+            self.height = unsafe { self.call_size(ffi::VTABLE_GET_SENDER_HEIGHT) };
+            self.buffer = vec![0u8; (self.width * self.height * 4) as usize];
+            let name = unsafe {
+                let get_sender_name: ffi::HandleNameFn =
+                    std::mem::transmute(*self.vtable.add(ffi::VTABLE_GET_SENDER_NAME));
+                get_sender_name(self.handle)
+            };
+            if !name.is_null() {
+                self.source = unsafe { std::ffi::CStr::from_ptr(name) }
+                    .to_string_lossy()
+                    .into_owned();
+            }
+            tracing::info!("Spout input connected: {} ({}x{})", self.source, self.width, self.height);
+            return Ok(None);
+        }
+
+        if self.buffer.is_empty() || !unsafe { self.call_bool(ffi::VTABLE_IS_FRAME_NEW) } {
+            return Ok(None);
+        }
+        Ok(Some(InputFrame {
+            pixels: &self.buffer,
+            width: self.width,
+            height: self.height,
+        }))
+    }
+}
+
+#[cfg(target_os = "windows")]
+impl Drop for SpoutInput {
+    fn drop(&mut self) {
+        unsafe {
+            let release_receiver: ffi::ReleaseReceiverFn =
+                std::mem::transmute(*self.vtable.add(ffi::VTABLE_RELEASE_RECEIVER));
+            release_receiver(self.handle);
+            let release: ffi::ReleaseFn = std::mem::transmute(*self.vtable.add(ffi::VTABLE_RELEASE));
+            release();
+        }
+        tracing::debug!("Spout input dropped: {}", self.source);
+    }
+}
+
 // Non-Windows stub implementation
 #[cfg(not(target_os = "windows"))]
 pub struct SpoutOutput {
@@ -430,6 +598,37 @@ impl VideoOutput for SpoutOutput {
     }
 }
 
+#[cfg(not(target_os = "windows"))]
+pub struct SpoutInput {
+    _private: (),
+}
+
+#[cfg(not(target_os = "windows"))]
+impl SpoutInput {
+    pub fn new(_source: Option<&str>) -> Result<Self, VideoOutputError> {
+        Err(VideoOutputError::NotSupported)
+    }
+
+    pub fn is_available() -> bool {
+        false
+    }
+}
+
+#[cfg(not(target_os = "windows"))]
+impl VideoInput for SpoutInput {
+    fn backend(&self) -> InputBackend {
+        InputBackend::Spout
+    }
+
+    fn source_name(&self) -> &str {
+        ""
+    }
+
+    fn receive_frame(&mut self) -> Result<Option<InputFrame<'_>>, VideoOutputError> {
+        Err(VideoOutputError::NotSupported)
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -447,5 +646,6 @@ mod tests {
     fn test_spout_not_supported_on_non_windows() {
         let result = SpoutOutput::new(SpoutConfig::default());
         assert!(matches!(result, Err(VideoOutputError::NotSupported)));
+        assert!(matches!(SpoutInput::new(None), Err(VideoOutputError::NotSupported)));
     }
 }
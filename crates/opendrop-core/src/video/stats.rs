@@ -0,0 +1,153 @@
+//! Render performance stats
+//!
+//! The renderer times every frame with a [`FrameTimer`] and reports a
+//! [`RenderStats`] about once a second, both on its HUD and to the app.
+
+use std::fmt;
+use std::time::{Duration, Instant};
+
+use serde::{Deserialize, Serialize};
+
+use super::output::OutputBackend;
+
+/// How often stats are reported
+pub const STATS_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Performance over the last report interval
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct RenderStats {
+    /// Frames drawn per second
+    pub fps: f32,
+    /// Average time between frames, in milliseconds
+    pub frame_time_ms: f32,
+    /// Longest time between frames, in milliseconds; spikes show stutter
+    pub max_frame_time_ms: f32,
+    /// Preset on screen, by name
+    #[serde(default)]
+    pub preset: Option<String>,
+    /// Outputs taking frames
+    #[serde(default)]
+    pub outputs: Vec<OutputBackend>,
+    /// Share of the frame size projectM renders at, while adaptive
+    /// resolution is on
+    #[serde(default)]
+    pub render_scale: Option<f32>,
+}
+
+impl fmt::Display for RenderStats {
+    /// HUD text: timing, then the preset and outputs on their own lines
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{:.0} fps  {:.1} ms (max {:.1})",
+            self.fps, self.frame_time_ms, self.max_frame_time_ms
+        )?;
+        if let Some(scale) = self.render_scale {
+            write!(f, "  {:.0}% res", scale * 100.0)?;
+        }
+        if let Some(ref preset) = self.preset {
+            write!(f, "\n{}", preset)?;
+        }
+        if self.outputs.is_empty() {
+            write!(f, "\nNo outputs")
+        } else {
+            let outputs: Vec<String> = self.outputs.iter().map(|o| o.to_string()).collect();
+            write!(f, "\nOutputs: {}", outputs.join(", "))
+        }
+    }
+}
+
+/// Times frames and summarizes them once per [`STATS_INTERVAL`]
+#[derive(Debug, Clone, Default)]
+pub struct FrameTimer {
+    /// Start of the current interval
+    interval_start: Option<Instant>,
+    last_frame: Option<Instant>,
+    frames: u32,
+    longest: Duration,
+}
+
+impl FrameTimer {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Record a frame drawn at `now`; returns the interval's timing when it
+    /// is over, with no preset or outputs filled in
+    pub fn tick(&mut self, now: Instant) -> Option<RenderStats> {
+        let start = *self.interval_start.get_or_insert(now);
+        if let Some(last) = self.last_frame {
+            self.frames += 1;
+            self.longest = self.longest.max(now.saturating_duration_since(last));
+        }
+        self.last_frame = Some(now);
+
+        let elapsed = now.saturating_duration_since(start);
+        if elapsed < STATS_INTERVAL || self.frames == 0 {
+            return None;
+        }
+        let secs = elapsed.as_secs_f32();
+        let stats = RenderStats {
+            fps: self.frames as f32 / secs,
+            frame_time_ms: secs * 1000.0 / self.frames as f32,
+            max_frame_time_ms: self.longest.as_secs_f32() * 1000.0,
+            ..RenderStats::default()
+        };
+        self.interval_start = Some(now);
+        self.frames = 0;
+        self.longest = Duration::ZERO;
+        Some(stats)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_frame_timer() {
+        let mut timer = FrameTimer::new();
+        let start = Instant::now();
+        // 60 frames in a second: 16 ms apart with one 56 ms hitch
+        let mut now = start;
+        let mut reports = Vec::new();
+        for i in 0..=60 {
+            reports.extend(timer.tick(now));
+            now += Duration::from_millis(if i == 30 { 56 } else { 16 });
+        }
+        assert_eq!(reports.len(), 1);
+        let stats = &reports[0];
+        assert_eq!(stats.fps.round(), 60.0);
+        assert!((stats.frame_time_ms - 16.67).abs() < 0.01);
+        assert!((stats.max_frame_time_ms - 56.0).abs() < 0.01);
+
+        // A stall still reports once the next frame arrives
+        assert!(timer.tick(now + Duration::from_secs(5)).is_some());
+    }
+
+    #[test]
+    fn test_stats_text() {
+        let mut stats = RenderStats {
+            fps: 59.94,
+            frame_time_ms: 16.68,
+            max_frame_time_ms: 21.0,
+            preset: Some("Flexi - mindblob".to_string()),
+            outputs: vec![OutputBackend::Ndi, OutputBackend::Recording],
+            render_scale: None,
+        };
+        assert_eq!(
+            stats.to_string(),
+            "60 fps  16.7 ms (max 21.0)\nFlexi - mindblob\nOutputs: NDI, Recording"
+        );
+
+        stats.preset = None;
+        stats.outputs.clear();
+        assert_eq!(stats.to_string(), "60 fps  16.7 ms (max 21.0)\nNo outputs");
+
+        stats.render_scale = Some(0.7);
+        assert_eq!(
+            stats.to_string(),
+            "60 fps  16.7 ms (max 21.0)  70% res\nNo outputs"
+        );
+    }
+}
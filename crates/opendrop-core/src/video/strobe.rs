@@ -0,0 +1,251 @@
+//! Strobe and blackout over a deck's output
+//!
+//! Blackout fades the deck to black without stopping it, so it comes back
+//! on the same frame of the same preset. The strobe flashes white on the
+//! beat: the app forwards each beat of its tempo clock and the renderer
+//! times the flashes between them, carrying on at the last tempo when
+//! beats stop arriving.
+
+use std::time::{Duration, Instant};
+
+use serde::{Deserialize, Serialize};
+
+use super::output::VideoOutputError;
+
+/// Longest fade in or out of blackout, in seconds
+pub const MAX_BLACKOUT_FADE_SECS: f64 = 30.0;
+
+/// Tempo the strobe runs at until the first beat arrives
+pub const DEFAULT_STROBE_BPM: f32 = 120.0;
+
+/// Longest a single flash lasts
+const MAX_FLASH: Duration = Duration::from_millis(60);
+
+/// How often the strobe flashes
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum StrobeRate {
+    /// Every other beat
+    HalfTime,
+    /// On every beat
+    #[default]
+    Beat,
+    /// Twice a beat
+    Eighth,
+    /// Four times a beat
+    Sixteenth,
+}
+
+impl StrobeRate {
+    /// Flashes per beat
+    pub fn per_beat(self) -> f64 {
+        match self {
+            StrobeRate::HalfTime => 0.5,
+            StrobeRate::Beat => 1.0,
+            StrobeRate::Eighth => 2.0,
+            StrobeRate::Sixteenth => 4.0,
+        }
+    }
+}
+
+/// Strobe settings for a deck
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct StrobeConfig {
+    pub rate: StrobeRate,
+    /// Brightness of a flash, 0-1
+    pub intensity: f32,
+}
+
+impl Default for StrobeConfig {
+    fn default() -> Self {
+        Self {
+            rate: StrobeRate::Beat,
+            intensity: 1.0,
+        }
+    }
+}
+
+impl StrobeConfig {
+    pub fn validate(&self) -> Result<(), VideoOutputError> {
+        if !(0.0..=1.0).contains(&self.intensity) {
+            return Err(VideoOutputError::InitError(format!(
+                "Invalid strobe intensity {}: use 0-1",
+                self.intensity
+            )));
+        }
+        Ok(())
+    }
+}
+
+/// Flashes timed from the beats the app forwards
+#[derive(Debug, Clone, Copy)]
+pub struct Strobe {
+    config: StrobeConfig,
+    /// A beat a flash landed on
+    anchor: Instant,
+    period: Duration,
+}
+
+impl Strobe {
+    /// Start flashing now, at [`DEFAULT_STROBE_BPM`] until a beat arrives
+    pub fn new(config: StrobeConfig, now: Instant) -> Self {
+        Self {
+            config,
+            anchor: now,
+            period: Duration::from_secs_f32(60.0 / DEFAULT_STROBE_BPM),
+        }
+    }
+
+    pub fn config(&self) -> &StrobeConfig {
+        &self.config
+    }
+
+    pub fn set_config(&mut self, config: StrobeConfig) {
+        self.config = config;
+    }
+
+    /// Line the flashes up with a beat of the tempo clock at `bpm`
+    pub fn beat(&mut self, bpm: f32, now: Instant) {
+        if !bpm.is_finite() || bpm <= 0.0 {
+            return;
+        }
+        // Half time flashes on every other beat, so keep to the same ones
+        let beats =
+            now.saturating_duration_since(self.anchor).as_secs_f64() / self.period.as_secs_f64();
+        let period = Duration::from_secs_f32(60.0 / bpm);
+        self.anchor = if self.config.rate == StrobeRate::HalfTime && beats.round() as u64 % 2 == 1 {
+            now.checked_sub(period).unwrap_or(now)
+        } else {
+            now
+        };
+        self.period = period;
+    }
+
+    /// Brightness of the flash at `now`, 0-1
+    pub fn level(&self, now: Instant) -> f32 {
+        let interval = self.period.as_secs_f64() / self.config.rate.per_beat();
+        let since = now.saturating_duration_since(self.anchor).as_secs_f64() % interval;
+        let flash = (interval / 3.0).min(MAX_FLASH.as_secs_f64());
+        if since >= flash {
+            return 0.0;
+        }
+        self.config.intensity * (1.0 - since / flash) as f32
+    }
+}
+
+/// Fade in and out of black
+#[derive(Debug, Clone, Copy)]
+pub struct Blackout {
+    from: f32,
+    to: f32,
+    started: Instant,
+    duration: Duration,
+}
+
+impl Blackout {
+    /// Black from the start, or clear
+    pub fn new(enabled: bool, now: Instant) -> Self {
+        let level = if enabled { 1.0 } else { 0.0 };
+        Self {
+            from: level,
+            to: level,
+            started: now,
+            duration: Duration::ZERO,
+        }
+    }
+
+    /// Fade to black or back over `fade`, from wherever a fade under way
+    /// got to
+    pub fn set(&mut self, enabled: bool, fade: Duration, now: Instant) {
+        self.from = self.level(now);
+        self.to = if enabled { 1.0 } else { 0.0 };
+        self.started = now;
+        self.duration = fade;
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.to > 0.0
+    }
+
+    /// How black the output is at `now`, 0-1
+    pub fn level(&self, now: Instant) -> f32 {
+        let elapsed = now.saturating_duration_since(self.started);
+        if elapsed >= self.duration {
+            return self.to;
+        }
+        let t = elapsed.as_secs_f32() / self.duration.as_secs_f32();
+        self.from + (self.to - self.from) * t
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_strobe_follows_beats() {
+        let start = Instant::now();
+        let mut strobe = Strobe::new(StrobeConfig::default(), start);
+        assert_eq!(strobe.level(start), 1.0);
+        assert_eq!(strobe.level(start + Duration::from_millis(100)), 0.0);
+        // Free running at 120 BPM: the next flash is half a second on
+        assert_eq!(strobe.level(start + Duration::from_millis(500)), 1.0);
+
+        // A beat at 60 BPM moves the grid
+        let beat = start + Duration::from_millis(700);
+        strobe.beat(60.0, beat);
+        assert_eq!(strobe.level(beat), 1.0);
+        assert_eq!(strobe.level(beat + Duration::from_millis(500)), 0.0);
+        assert_eq!(strobe.level(beat + Duration::from_secs(1)), 1.0);
+        let fading = strobe.level(beat + Duration::from_millis(30));
+        assert!(fading > 0.0 && fading < 1.0);
+
+        strobe.set_config(StrobeConfig {
+            rate: StrobeRate::Eighth,
+            intensity: 0.5,
+        });
+        assert_eq!(strobe.level(beat + Duration::from_millis(500)), 0.5);
+    }
+
+    #[test]
+    fn test_half_time_keeps_to_its_beats() {
+        let start = Instant::now();
+        let config = StrobeConfig {
+            rate: StrobeRate::HalfTime,
+            ..StrobeConfig::default()
+        };
+        let mut strobe = Strobe::new(config, start);
+        // The first beat after the start is an off beat
+        let beat = start + Duration::from_millis(500);
+        strobe.beat(120.0, beat);
+        assert_eq!(strobe.level(beat), 0.0);
+        assert_eq!(strobe.level(beat + Duration::from_millis(500)), 1.0);
+        assert!(StrobeConfig {
+            intensity: 2.0,
+            ..config
+        }
+        .validate()
+        .is_err());
+    }
+
+    #[test]
+    fn test_blackout_fades() {
+        let start = Instant::now();
+        let mut blackout = Blackout::new(false, start);
+        assert_eq!(blackout.level(start), 0.0);
+        blackout.set(true, Duration::from_secs(2), start);
+        assert!(blackout.is_enabled());
+        assert!((blackout.level(start + Duration::from_secs(1)) - 0.5).abs() < 1e-6);
+        assert_eq!(blackout.level(start + Duration::from_secs(2)), 1.0);
+
+        // Back out from halfway, over one second
+        let midway = start + Duration::from_secs(1);
+        blackout.set(false, Duration::from_secs(1), midway);
+        assert!((blackout.level(midway) - 0.5).abs() < 1e-6);
+        assert_eq!(blackout.level(midway + Duration::from_secs(1)), 0.0);
+
+        let cut = Blackout::new(true, start);
+        assert_eq!(cut.level(start), 1.0);
+    }
+}
@@ -0,0 +1,382 @@
+//! Syphon video output for macOS
+//!
+//! Shares frames via Syphon for capture by OBS, Resolume, MadMapper, etc.
+//!
+//! ## Setup (macOS only)
+//!
+//! 1. Download Syphon.framework from the Syphon releases:
+//!    <https://github.com/Syphon/Syphon-Framework/releases>
+//!
+//! 2. Place `Syphon.framework` in one of these locations:
+//!    - `OpenDrop.app/Contents/Frameworks`
+//!    - `/Library/Frameworks` or `~/Library/Frameworks`
+//!
+//! 3. In OBS: Add a "Syphon Client" source and select "OpenDrop"
+//!
+//! ## Implementation
+//!
+//! Syphon.framework is loaded at runtime, which registers its Objective-C
+//! classes; a `SyphonOpenGLServer` is then created on the renderer's current
+//! OpenGL context and frames are published as textures.
+
+use super::output::{OutputBackend, VideoOutput, VideoOutputError};
+
+/// Syphon output configuration
+#[derive(Debug, Clone)]
+pub struct SyphonConfig {
+    /// Server name (visible in receiving apps like OBS)
+    pub server_name: String,
+    /// Output width
+    pub width: u32,
+    /// Output height
+    pub height: u32,
+}
+
+impl Default for SyphonConfig {
+    fn default() -> Self {
+        Self {
+            server_name: "OpenDrop".to_string(),
+            width: 1920,
+            height: 1080,
+        }
+    }
+}
+
+#[cfg(target_os = "macos")]
+mod ffi {
+    use objc2::encode::{Encoding, RefEncode};
+
+    /// Opaque CGL context (`CGLContextObj` points to one)
+    #[repr(C)]
+    pub struct CGLContextObject {
+        _private: [u8; 0],
+    }
+
+    unsafe impl RefEncode for CGLContextObject {
+        const ENCODING_REF: Encoding =
+            Encoding::Pointer(&Encoding::Struct("_CGLContextObject", &[]));
+    }
+
+    #[link(name = "OpenGL", kind = "framework")]
+    extern "C" {
+        pub fn CGLGetCurrentContext() -> *mut CGLContextObject;
+    }
+}
+
+#[cfg(target_os = "macos")]
+use libloading::Library;
+
+#[cfg(target_os = "macos")]
+use objc2::rc::{Allocated, Retained};
+
+#[cfg(target_os = "macos")]
+use objc2::runtime::{AnyClass, AnyObject};
+
+#[cfg(target_os = "macos")]
+use objc2::msg_send;
+
+#[cfg(target_os = "macos")]
+use objc2_foundation::{NSPoint, NSRect, NSSize, NSString};
+
+/// Load Syphon.framework from the app bundle or the system framework folders
+#[cfg(target_os = "macos")]
+fn load_framework() -> Result<Library, String> {
+    let mut candidates = vec![
+        "@executable_path/../Frameworks/Syphon.framework/Syphon".to_string(),
+        "/Library/Frameworks/Syphon.framework/Syphon".to_string(),
+    ];
+    if let Some(home) = std::env::var_os("HOME") {
+        candidates.push(format!(
+            "{}/Library/Frameworks/Syphon.framework/Syphon",
+            home.to_string_lossy()
+        ));
+    }
+
+    let mut last_error = String::new();
+    for candidate in &candidates {
+        match unsafe { Library::new(candidate) } {
+            Ok(library) => return Ok(library),
+            Err(e) => last_error = e.to_string(),
+        }
+    }
+    Err(last_error)
+}
+
+/// Syphon video output using a SyphonOpenGLServer
+#[cfg(target_os = "macos")]
+pub struct SyphonOutput {
+    /// Keeps the Syphon classes loaded
+    _library: Library,
+    /// SyphonOpenGLServer instance
+    server: Retained<AnyObject>,
+    /// Server name
+    name: String,
+    /// Texture the RGBA fallback uploads frames into
+    upload_texture: u32,
+    /// Size of `upload_texture`
+    upload_size: (u32, u32),
+    /// Output dimensions
+    width: u32,
+    height: u32,
+    /// Whether output is active
+    active: bool,
+}
+
+// SyphonOutput is Send because:
+// - The server is created and used only on the renderer thread, which owns
+//   the OpenGL context it was created on
+// - Ownership moves between threads at most once, before the first frame
+#[cfg(target_os = "macos")]
+unsafe impl Send for SyphonOutput {}
+
+#[cfg(target_os = "macos")]
+impl SyphonOutput {
+    /// Create a new Syphon output on the current OpenGL context
+    pub fn new(config: SyphonConfig) -> Result<Self, VideoOutputError> {
+        let library = load_framework().map_err(|e| {
+            VideoOutputError::InitError(format!(
+                "Failed to load Syphon.framework: {}. \n\
+                Download from https://github.com/Syphon/Syphon-Framework/releases \n\
+                and place in /Library/Frameworks.",
+                e
+            ))
+        })?;
+
+        let class = AnyClass::get(c"SyphonOpenGLServer").ok_or_else(|| {
+            VideoOutputError::InitError(
+                "SyphonOpenGLServer not found in Syphon.framework (version 5 or later required)"
+                    .to_string(),
+            )
+        })?;
+
+        let context = unsafe { ffi::CGLGetCurrentContext() };
+        if context.is_null() {
+            return Err(VideoOutputError::InitError(
+                "No current OpenGL context for the Syphon server".to_string(),
+            ));
+        }
+
+        let name = NSString::from_str(&config.server_name);
+        let server: Option<Retained<AnyObject>> = unsafe {
+            let allocated: Allocated<AnyObject> = msg_send![class, alloc];
+            msg_send![
+                allocated,
+                initWithName: &*name,
+                context: context,
+                options: None::<&AnyObject>
+            ]
+        };
+        let server = server.ok_or_else(|| {
+            VideoOutputError::InitError("Failed to create Syphon server".to_string())
+        })?;
+
+        tracing::info!(
+            "Syphon output created: {} ({}x{})",
+            config.server_name,
+            config.width,
+            config.height
+        );
+
+        Ok(Self {
+            _library: library,
+            server,
+            name: format!("syphon:{}", config.server_name),
+            upload_texture: 0,
+            upload_size: (0, 0),
+            width: config.width,
+            height: config.height,
+            active: true,
+        })
+    }
+
+    /// Check if Syphon.framework is installed
+    pub fn is_available() -> bool {
+        load_framework().is_ok()
+    }
+
+    /// Publish a GL_TEXTURE_2D texture of the current context
+    fn publish(&self, texture_id: u32, width: u32, height: u32, flipped: bool) {
+        let size = NSSize::new(width as f64, height as f64);
+        let region = NSRect::new(NSPoint::new(0.0, 0.0), size);
+        unsafe {
+            let _: () = msg_send![
+                &*self.server,
+                publishFrameTexture: texture_id,
+                textureTarget: gl::TEXTURE_2D,
+                imageRegion: region,
+                textureDimensions: size,
+                flipped: flipped
+            ];
+        }
+    }
+
+    /// Copy pixels into the upload texture, (re)allocating it on size change
+    fn upload(&mut self, pixels: &[u8], width: u32, height: u32) {
+        unsafe {
+            let mut previous = 0;
+            gl::GetIntegerv(gl::TEXTURE_BINDING_2D, &mut previous);
+
+            if self.upload_texture == 0 {
+                gl::GenTextures(1, &mut self.upload_texture);
+            }
+            gl::BindTexture(gl::TEXTURE_2D, self.upload_texture);
+            if self.upload_size != (width, height) {
+                gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_MIN_FILTER, gl::LINEAR as i32);
+                gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_MAG_FILTER, gl::LINEAR as i32);
+                gl::TexImage2D(
+                    gl::TEXTURE_2D,
+                    0,
+                    gl::RGBA8 as i32,
+                    width as i32,
+                    height as i32,
+                    0,
+                    gl::RGBA,
+                    gl::UNSIGNED_BYTE,
+                    pixels.as_ptr() as *const _,
+                );
+                self.upload_size = (width, height);
+            } else {
+                gl::TexSubImage2D(
+                    gl::TEXTURE_2D,
+                    0,
+                    0,
+                    0,
+                    width as i32,
+                    height as i32,
+                    gl::RGBA,
+                    gl::UNSIGNED_BYTE,
+                    pixels.as_ptr() as *const _,
+                );
+            }
+
+            gl::BindTexture(gl::TEXTURE_2D, previous as u32);
+        }
+    }
+}
+
+#[cfg(target_os = "macos")]
+impl VideoOutput for SyphonOutput {
+    fn backend(&self) -> OutputBackend {
+        OutputBackend::Syphon
+    }
+
+    fn send_frame(&mut self, texture_id: u32, width: u32, height: u32) -> Result<(), VideoOutputError> {
+        if !self.active {
+            return Ok(());
+        }
+
+        if width != self.width || height != self.height {
+            tracing::debug!(
+                "Syphon resolution changed: {}x{} -> {}x{}",
+                self.width, self.height, width, height
+            );
+            self.width = width;
+            self.height = height;
+        }
+
+        // GL textures are already bottom-up, as Syphon expects
+        self.publish(texture_id, width, height, false);
+        Ok(())
+    }
+
+    fn send_frame_rgba(&mut self, pixels: &[u8], width: u32, height: u32) -> Result<(), VideoOutputError> {
+        if !self.active {
+            return Ok(());
+        }
+
+        // Validate buffer size
+        let expected_size = (width * height * 4) as usize;
+        if pixels.len() != expected_size {
+            return Err(VideoOutputError::SendError(format!(
+                "Invalid pixel buffer size: got {}, expected {}",
+                pixels.len(), expected_size
+            )));
+        }
+
+        self.width = width;
+        self.height = height;
+
+        // Captured frames are top-down, so the texture is flipped
+        self.upload(pixels, width, height);
+        self.publish(self.upload_texture, width, height, true);
+        Ok(())
+    }
+
+    fn is_active(&self) -> bool {
+        self.active
+    }
+
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn set_active(&mut self, active: bool) {
+        self.active = active;
+    }
+}
+
+#[cfg(target_os = "macos")]
+impl Drop for SyphonOutput {
+    fn drop(&mut self) {
+        unsafe {
+            let _: () = msg_send![&*self.server, stop];
+            if self.upload_texture != 0 {
+                gl::DeleteTextures(1, &self.upload_texture);
+            }
+        }
+        tracing::debug!("Syphon output dropped: {}", self.name);
+    }
+}
+
+// Non-macOS stub implementation
+#[cfg(not(target_os = "macos"))]
+pub struct SyphonOutput {
+    _private: (),
+}
+
+#[cfg(not(target_os = "macos"))]
+impl SyphonOutput {
+    pub fn new(_config: SyphonConfig) -> Result<Self, VideoOutputError> {
+        Err(VideoOutputError::NotSupported)
+    }
+
+    pub fn is_available() -> bool {
+        false
+    }
+}
+
+#[cfg(not(target_os = "macos"))]
+impl VideoOutput for SyphonOutput {
+    fn backend(&self) -> OutputBackend {
+        OutputBackend::Syphon
+    }
+
+    fn send_frame(&mut self, _texture_id: u32, _width: u32, _height: u32) -> Result<(), VideoOutputError> {
+        Err(VideoOutputError::NotSupported)
+    }
+
+    fn is_active(&self) -> bool {
+        false
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_syphon_config_default() {
+        let config = SyphonConfig::default();
+        assert_eq!(config.server_name, "OpenDrop");
+        assert_eq!(config.width, 1920);
+        assert_eq!(config.height, 1080);
+    }
+
+    #[test]
+    #[cfg(not(target_os = "macos"))]
+    fn test_syphon_not_supported_on_non_macos() {
+        let result = SyphonOutput::new(SyphonConfig::default());
+        assert!(matches!(result, Err(VideoOutputError::NotSupported)));
+        assert!(!SyphonOutput::is_available());
+    }
+}
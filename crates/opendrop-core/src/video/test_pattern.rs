@@ -0,0 +1,232 @@
+//! Test pattern for lining up projectors and checking output routing
+//!
+//! SMPTE-style colour bars under a 16x9 grid, with an edge border, a centre
+//! cross and a circle (which shows up stretched if a receiver scales the
+//! picture wrong). The deck number and frame size are printed in the middle,
+//! so each NDI or v4l2 feed can be told apart before doors.
+
+/// 75% colour bars, left to right
+const BARS: [[u8; 3]; 7] = [
+    [191, 191, 191],
+    [191, 191, 0],
+    [0, 191, 191],
+    [0, 191, 0],
+    [191, 0, 191],
+    [191, 0, 0],
+    [0, 0, 191],
+];
+
+/// Reverse blue bars under the main bars
+const CASTELLATIONS: [[u8; 3]; 7] = [
+    [0, 0, 191],
+    [16, 16, 16],
+    [191, 0, 191],
+    [16, 16, 16],
+    [0, 191, 191],
+    [16, 16, 16],
+    [191, 191, 191],
+];
+
+const BLACK: [u8; 3] = [16, 16, 16];
+const WHITE: [u8; 3] = [255, 255, 255];
+
+/// 5x7 glyphs for the label, one byte per row with the leftmost dot in bit 4
+fn glyph(c: char) -> Option<[u8; 7]> {
+    Some(match c {
+        '0' => [0x0e, 0x11, 0x13, 0x15, 0x19, 0x11, 0x0e],
+        '1' => [0x04, 0x0c, 0x04, 0x04, 0x04, 0x04, 0x0e],
+        '2' => [0x0e, 0x11, 0x01, 0x02, 0x04, 0x08, 0x1f],
+        '3' => [0x1f, 0x02, 0x04, 0x02, 0x01, 0x11, 0x0e],
+        '4' => [0x02, 0x06, 0x0a, 0x12, 0x1f, 0x02, 0x02],
+        '5' => [0x1f, 0x10, 0x1e, 0x01, 0x01, 0x11, 0x0e],
+        '6' => [0x06, 0x08, 0x10, 0x1e, 0x11, 0x11, 0x0e],
+        '7' => [0x1f, 0x01, 0x02, 0x04, 0x08, 0x08, 0x08],
+        '8' => [0x0e, 0x11, 0x11, 0x0e, 0x11, 0x11, 0x0e],
+        '9' => [0x0e, 0x11, 0x11, 0x0f, 0x01, 0x02, 0x0c],
+        'C' => [0x0e, 0x11, 0x10, 0x10, 0x10, 0x11, 0x0e],
+        'D' => [0x1c, 0x12, 0x11, 0x11, 0x11, 0x12, 0x1c],
+        'E' => [0x1f, 0x10, 0x10, 0x1e, 0x10, 0x10, 0x1f],
+        'K' => [0x11, 0x12, 0x14, 0x18, 0x14, 0x12, 0x11],
+        'X' => [0x11, 0x11, 0x0a, 0x04, 0x0a, 0x11, 0x11],
+        ' ' => [0; 7],
+        _ => return None,
+    })
+}
+
+/// Colour of the bars at `x`, `y` in a `width`x`height` frame
+fn bars(x: u32, y: u32, width: u32, height: u32) -> [u8; 3] {
+    // Position in bar widths, 0-7
+    let bar = x as f32 * 7.0 / width as f32;
+    let index = (bar as usize).min(6);
+    let row = y as f32 / height as f32;
+    if row < 0.67 {
+        BARS[index]
+    } else if row < 0.75 {
+        CASTELLATIONS[index]
+    } else if bar < 5.0 {
+        // -I, white, +Q and black, each 5/4 of a bar wide
+        match (bar / 1.25) as usize {
+            0 => [0, 33, 76],
+            1 => WHITE,
+            2 => [50, 0, 106],
+            _ => BLACK,
+        }
+    } else if bar < 6.0 {
+        // PLUGE: just below, at and just above black
+        match ((bar - 5.0) * 3.0) as usize {
+            0 => [7, 7, 7],
+            1 => BLACK,
+            _ => [26, 26, 26],
+        }
+    } else {
+        BLACK
+    }
+}
+
+/// Print `text` centred on `center_y`, `scale` pixels per font dot
+fn draw_text(frame: &mut [u8], width: u32, height: u32, text: &str, center_y: u32, scale: u32) {
+    let glyphs: Vec<[u8; 7]> = text.chars().filter_map(glyph).collect();
+    // 5 dots per glyph plus 1 of spacing, less the trailing space
+    let text_width = (glyphs.len() as u32 * 6).saturating_sub(1) * scale;
+    let left = width.saturating_sub(text_width) / 2;
+    let top = center_y.saturating_sub(7 * scale / 2);
+    for (i, rows) in glyphs.iter().enumerate() {
+        let glyph_left = left + i as u32 * 6 * scale;
+        for (row, bits) in rows.iter().enumerate() {
+            for column in 0..5 {
+                if bits & (0x10 >> column) == 0 {
+                    continue;
+                }
+                let x0 = glyph_left + column * scale;
+                let y0 = top + row as u32 * scale;
+                for y in y0..(y0 + scale).min(height) {
+                    for x in x0..(x0 + scale).min(width) {
+                        let i = (y * width + x) as usize * 4;
+                        frame[i..i + 3].copy_from_slice(&WHITE);
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// Which of `size` pixels lie on `cells` evenly spaced grid cells' lines,
+/// `line` pixels thick; the outer lines sit on the frame edge
+fn grid_lines(size: u32, cells: u32, line: u32) -> Vec<bool> {
+    let mut on_line = vec![false; size as usize];
+    for k in 0..=cells {
+        let at = (size as u64 * k as u64 / cells as u64) as u32;
+        let start = at.min(size.saturating_sub(line));
+        for p in start..(start + line).min(size) {
+            on_line[p as usize] = true;
+        }
+    }
+    on_line
+}
+
+/// Draw the test pattern for `deck_id` as a top-down RGBA frame
+pub fn render_test_pattern(width: u32, height: u32, deck_id: u8) -> Vec<u8> {
+    let mut frame = vec![255u8; (width * height * 4) as usize];
+    if width == 0 || height == 0 {
+        return frame;
+    }
+
+    let line = (height / 540).max(1);
+    let grid_columns = grid_lines(width, 16, line);
+    let grid_rows = grid_lines(height, 9, line);
+    let (cx, cy) = (width as f32 / 2.0, height as f32 / 2.0);
+    let radius = height as f32 * 0.4;
+    let half_line = line as f32 / 2.0 + 0.5;
+
+    for y in 0..height {
+        for x in 0..width {
+            let dx = x as f32 + 0.5 - cx;
+            let dy = y as f32 + 0.5 - cy;
+            let on_circle = ((dx * dx + dy * dy).sqrt() - radius).abs() < half_line;
+            let on_cross = (dx.abs() < half_line && dy.abs() < radius)
+                || (dy.abs() < half_line && dx.abs() < radius);
+            let color =
+                if grid_rows[y as usize] || grid_columns[x as usize] || on_circle || on_cross {
+                    WHITE
+                } else {
+                    bars(x, y, width, height)
+                };
+            let i = (y * width + x) as usize * 4;
+            frame[i..i + 3].copy_from_slice(&color);
+        }
+    }
+
+    // Black box behind the label
+    let scale = (height / 90).max(1);
+    let (box_width, box_height) = ((width / 3).max(1), (22 * scale).min(height));
+    let (box_left, box_top) = ((width - box_width) / 2, (height - box_height) / 2);
+    for y in box_top..box_top + box_height {
+        for x in box_left..box_left + box_width {
+            let i = (y * width + x) as usize * 4;
+            frame[i..i + 3].copy_from_slice(&[0, 0, 0]);
+        }
+    }
+    let label = format!("DECK {}", deck_id as u32 + 1);
+    draw_text(
+        &mut frame,
+        width,
+        height,
+        &label,
+        box_top + 7 * scale,
+        scale,
+    );
+    let size = format!("{}X{}", width, height);
+    draw_text(
+        &mut frame,
+        width,
+        height,
+        &size,
+        box_top + 16 * scale,
+        (scale / 2).max(1),
+    );
+    frame
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn pixel(frame: &[u8], width: u32, x: u32, y: u32) -> [u8; 3] {
+        let i = (y * width + x) as usize * 4;
+        [frame[i], frame[i + 1], frame[i + 2]]
+    }
+
+    #[test]
+    fn test_pattern_layout() {
+        let (width, height) = (1920, 1080);
+        let frame = render_test_pattern(width, height, 1);
+        assert_eq!(frame.len(), (width * height * 4) as usize);
+        assert!(frame.chunks_exact(4).all(|p| p[3] == 255));
+
+        // Edge border and grid lines
+        assert_eq!(pixel(&frame, width, 0, 300), WHITE);
+        assert_eq!(pixel(&frame, width, width - 1, 300), WHITE);
+        assert_eq!(pixel(&frame, width, 120, 300), WHITE);
+        // Yellow bar, castellation and -I between the lines
+        assert_eq!(pixel(&frame, width, 400, 300), BARS[1]);
+        assert_eq!(pixel(&frame, width, 400, 770), CASTELLATIONS[1]);
+        assert_eq!(pixel(&frame, width, 100, 1000), [0, 33, 76]);
+        // Label box in the middle
+        assert_eq!(pixel(&frame, width, 700, 540), [0, 0, 0]);
+    }
+
+    #[test]
+    fn test_grid_lines() {
+        let lines = grid_lines(32, 4, 1);
+        let on: Vec<usize> = (0..32).filter(|&p| lines[p]).collect();
+        assert_eq!(on, [0, 8, 16, 24, 31]);
+    }
+
+    #[test]
+    fn test_label_glyphs() {
+        assert!("DECK 12 1920X1080".chars().all(|c| glyph(c).is_some()));
+        assert!(glyph('?').is_none());
+        // Small frames draw without panicking
+        assert_eq!(render_test_pattern(3, 2, 7).len(), 24);
+    }
+}
@@ -0,0 +1,359 @@
+//! Text overlay for track titles and messages
+//!
+//! [`TextOverlayConfig`] describes a line or two of text to show on a deck,
+//! such as a "now playing" title or a shout-out. [`render_text`] rasterizes
+//! it with a TrueType font into a top-down RGBA image, white with a dark
+//! outline so it reads over any visualization; the renderer draws that over
+//! the frame with the fade from [`TextOverlayConfig::opacity_at`].
+
+use std::path::{Path, PathBuf};
+
+use ab_glyph::{point, Font, PxScale, ScaleFont};
+use serde::{Deserialize, Serialize};
+
+use super::output::VideoOutputError;
+use super::overlay::OverlayPosition;
+
+/// Font loaded by [`load_font`]
+pub use ab_glyph::FontVec;
+
+/// Bold sans-serif fonts tried in order when no font is given
+const DEFAULT_FONTS: &[&str] = &[
+    // Linux
+    "/usr/share/fonts/truetype/dejavu/DejaVuSans-Bold.ttf",
+    "/usr/share/fonts/TTF/DejaVuSans-Bold.ttf",
+    "/usr/share/fonts/dejavu/DejaVuSans-Bold.ttf",
+    "/usr/share/fonts/dejavu-sans-fonts/DejaVuSans-Bold.ttf",
+    "/usr/share/fonts/truetype/liberation/LiberationSans-Bold.ttf",
+    "/usr/share/fonts/liberation-sans/LiberationSans-Bold.ttf",
+    "/usr/share/fonts/truetype/noto/NotoSans-Bold.ttf",
+    "/usr/share/fonts/noto/NotoSans-Bold.ttf",
+    // Windows
+    "C:\\Windows\\Fonts\\arialbd.ttf",
+    "C:\\Windows\\Fonts\\segoeuib.ttf",
+    // macOS
+    "/System/Library/Fonts/Supplemental/Arial Bold.ttf",
+    "/Library/Fonts/Arial Bold.ttf",
+];
+
+/// Longest text accepted, in characters
+pub const MAX_TEXT_CHARS: usize = 500;
+
+/// Longest fade accepted, in seconds
+const MAX_FADE_SECS: f32 = 10.0;
+
+/// Longest time text can be shown before fading out, in seconds
+const MAX_DURATION_SECS: f32 = 3600.0;
+
+/// Outline width as a fraction of the line height
+const OUTLINE: f32 = 0.06;
+
+/// Outline opacity under the text
+const OUTLINE_ALPHA: f32 = 0.75;
+
+/// Text overlay settings
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TextOverlayConfig {
+    /// Text to show; `\n` starts a new line
+    pub text: String,
+    #[serde(default = "default_position")]
+    pub position: OverlayPosition,
+    /// Line height as a fraction of the frame height (0.01-0.5)
+    #[serde(default = "default_font_size")]
+    pub font_size: f32,
+    #[serde(default = "default_fade_secs")]
+    pub fade_in_secs: f32,
+    #[serde(default = "default_fade_secs")]
+    pub fade_out_secs: f32,
+    /// Seconds at full opacity before fading out; `None` stays until cleared
+    #[serde(default)]
+    pub duration_secs: Option<f32>,
+    /// TrueType or OpenType font; a system sans-serif when unset
+    #[serde(default)]
+    pub font_path: Option<PathBuf>,
+}
+
+fn default_position() -> OverlayPosition {
+    OverlayPosition::Bottom
+}
+
+fn default_font_size() -> f32 {
+    0.06
+}
+
+fn default_fade_secs() -> f32 {
+    0.5
+}
+
+impl Default for TextOverlayConfig {
+    fn default() -> Self {
+        Self {
+            text: String::new(),
+            position: default_position(),
+            font_size: default_font_size(),
+            fade_in_secs: default_fade_secs(),
+            fade_out_secs: default_fade_secs(),
+            duration_secs: None,
+            font_path: None,
+        }
+    }
+}
+
+impl TextOverlayConfig {
+    /// Reject empty or overlong text and out-of-range sizes or times
+    pub fn validate(&self) -> Result<(), VideoOutputError> {
+        let invalid = |message: String| Err(VideoOutputError::InitError(message));
+        if self.text.trim().is_empty() {
+            return invalid("Overlay text is empty".to_string());
+        }
+        if self.text.chars().count() > MAX_TEXT_CHARS {
+            return invalid(format!(
+                "Overlay text is longer than {} characters",
+                MAX_TEXT_CHARS
+            ));
+        }
+        if !(0.01..=0.5).contains(&self.font_size) {
+            return invalid(format!(
+                "Invalid font size {}: use 0.01-0.5",
+                self.font_size
+            ));
+        }
+        for fade in [self.fade_in_secs, self.fade_out_secs] {
+            if !(0.0..=MAX_FADE_SECS).contains(&fade) {
+                return invalid(format!(
+                    "Invalid fade time {}: use 0-{} seconds",
+                    fade, MAX_FADE_SECS
+                ));
+            }
+        }
+        if let Some(duration) = self.duration_secs {
+            if !(duration > 0.0 && duration <= MAX_DURATION_SECS) {
+                return invalid(format!(
+                    "Invalid duration {}: use up to {} seconds",
+                    duration, MAX_DURATION_SECS
+                ));
+            }
+        }
+        Ok(())
+    }
+
+    /// Opacity `elapsed` seconds after the text appeared; `None` once it has
+    /// faded out
+    pub fn opacity_at(&self, elapsed: f32) -> Option<f32> {
+        let fade_in = ramp(elapsed, self.fade_in_secs);
+        let Some(duration) = self.duration_secs else {
+            return Some(fade_in);
+        };
+        let fading_out = elapsed - self.fade_in_secs - duration;
+        if fading_out <= 0.0 {
+            return Some(fade_in);
+        }
+        self.fade_out(fading_out, 1.0)
+    }
+
+    /// Opacity `since` seconds into a fade out that started at `from`;
+    /// `None` once it is over
+    pub fn fade_out(&self, since: f32, from: f32) -> Option<f32> {
+        if since >= self.fade_out_secs {
+            return None;
+        }
+        Some(from * (1.0 - ramp(since, self.fade_out_secs)))
+    }
+}
+
+/// 0 to 1 over `length` seconds; 1 straight away for no length
+fn ramp(elapsed: f32, length: f32) -> f32 {
+    if length <= 0.0 {
+        1.0
+    } else {
+        (elapsed / length).clamp(0.0, 1.0)
+    }
+}
+
+/// Load `path`, or the first system font found when `None`
+pub fn load_font(path: Option<&Path>) -> Result<FontVec, VideoOutputError> {
+    let path = match path {
+        Some(path) => path,
+        None => DEFAULT_FONTS
+            .iter()
+            .map(Path::new)
+            .find(|path| path.is_file())
+            .ok_or_else(|| {
+                VideoOutputError::InitError(
+                    "No system font found; choose a font file for the text overlay".to_string(),
+                )
+            })?,
+    };
+    let data = std::fs::read(path).map_err(|e| {
+        VideoOutputError::InitError(format!("Failed to read font {}: {}", path.display(), e))
+    })?;
+    FontVec::try_from_vec(data)
+        .map_err(|e| VideoOutputError::InitError(format!("Invalid font {}: {}", path.display(), e)))
+}
+
+/// Width in pixels of one line of text
+fn line_width<F: Font>(font: &ab_glyph::PxScaleFont<&F>, line: &str) -> f32 {
+    let mut width = 0.0;
+    let mut previous = None;
+    for c in line.chars() {
+        let id = font.glyph_id(c);
+        if let Some(previous) = previous {
+            width += font.kern(previous, id);
+        }
+        width += font.h_advance(id);
+        previous = Some(id);
+    }
+    width
+}
+
+/// Rasterize `text` with lines `line_height` pixels tall, centred on each
+/// other, as a top-down RGBA image with straight alpha
+pub fn render_text<F: Font>(font: &F, text: &str, line_height: f32) -> (Vec<u8>, u32, u32) {
+    let scaled = font.as_scaled(PxScale::from(line_height.max(1.0)));
+    let lines: Vec<&str> = text.lines().collect();
+    let line_advance = scaled.height() + scaled.line_gap();
+    let widths: Vec<f32> = lines.iter().map(|line| line_width(&scaled, line)).collect();
+    let text_width = widths.iter().fold(0.0f32, |a, &b| a.max(b));
+
+    let pad = (line_height * OUTLINE).ceil().max(1.0) as u32;
+    let width = text_width.ceil() as u32 + 2 * pad;
+    let height = (line_advance * lines.len() as f32 - scaled.line_gap()).ceil() as u32 + 2 * pad;
+    let mut coverage = vec![0.0f32; (width * height) as usize];
+
+    for (i, (line, line_width)) in lines.iter().zip(&widths).enumerate() {
+        let mut caret = point(
+            pad as f32 + (text_width - line_width) / 2.0,
+            pad as f32 + scaled.ascent() + i as f32 * line_advance,
+        );
+        let mut previous = None;
+        for c in line.chars() {
+            let id = scaled.glyph_id(c);
+            if let Some(previous) = previous {
+                caret.x += scaled.kern(previous, id);
+            }
+            let glyph = id.with_scale_and_position(scaled.scale(), caret);
+            caret.x += scaled.h_advance(id);
+            previous = Some(id);
+
+            let Some(outlined) = scaled.outline_glyph(glyph) else {
+                continue;
+            };
+            let bounds = outlined.px_bounds();
+            outlined.draw(|x, y, c| {
+                let x = bounds.min.x as i32 + x as i32;
+                let y = bounds.min.y as i32 + y as i32;
+                if (0..width as i32).contains(&x) && (0..height as i32).contains(&y) {
+                    let i = (y as u32 * width + x as u32) as usize;
+                    coverage[i] = coverage[i].max(c);
+                }
+            });
+        }
+    }
+
+    (
+        outline(&coverage, width, height, pad / 2 + 1),
+        width,
+        height,
+    )
+}
+
+/// White text from a coverage mask over a dark outline `radius` pixels wide
+fn outline(coverage: &[f32], width: u32, height: u32, radius: u32) -> Vec<u8> {
+    let (w, h, r) = (width as usize, height as usize, radius as usize);
+    // Square dilation, one axis at a time
+    let mut rows = vec![0.0f32; w * h];
+    for y in 0..h {
+        let row = &coverage[y * w..(y + 1) * w];
+        for x in 0..w {
+            let span = &row[x.saturating_sub(r)..(x + r + 1).min(w)];
+            rows[y * w + x] = span.iter().fold(0.0, |a, &b| a.max(b));
+        }
+    }
+
+    let mut rgba = vec![0u8; w * h * 4];
+    for y in 0..h {
+        for x in 0..w {
+            let spread = (y.saturating_sub(r)..(y + r + 1).min(h))
+                .map(|yy| rows[yy * w + x])
+                .fold(0.0f32, f32::max);
+            let text = coverage[y * w + x].clamp(0.0, 1.0);
+            let alpha = text + spread * OUTLINE_ALPHA * (1.0 - text);
+            if alpha <= 0.0 {
+                continue;
+            }
+            let value = (text / alpha * 255.0).round() as u8;
+            let i = (y * w + x) * 4;
+            rgba[i..i + 4].copy_from_slice(&[value, value, value, (alpha * 255.0).round() as u8]);
+        }
+    }
+    rgba
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config(duration_secs: Option<f32>) -> TextOverlayConfig {
+        TextOverlayConfig {
+            text: "Now playing".to_string(),
+            fade_in_secs: 1.0,
+            fade_out_secs: 2.0,
+            duration_secs,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_config_validate() {
+        assert!(config(Some(5.0)).validate().is_ok());
+        assert!(TextOverlayConfig::default().validate().is_err());
+        let mut long = config(None);
+        long.text = "x".repeat(MAX_TEXT_CHARS + 1);
+        assert!(long.validate().is_err());
+        let mut big = config(None);
+        big.font_size = 0.8;
+        assert!(big.validate().is_err());
+        assert!(config(Some(0.0)).validate().is_err());
+
+        let parsed: TextOverlayConfig = serde_json::from_str(r#"{"text":"Hi"}"#).unwrap();
+        assert_eq!(parsed.position, OverlayPosition::Bottom);
+        assert_eq!(parsed.duration_secs, None);
+    }
+
+    #[test]
+    fn test_fades() {
+        let timed = config(Some(3.0));
+        assert_eq!(timed.opacity_at(0.0), Some(0.0));
+        assert_eq!(timed.opacity_at(0.5), Some(0.5));
+        assert_eq!(timed.opacity_at(2.0), Some(1.0));
+        // Fades out over 2 seconds after 1 + 3
+        assert_eq!(timed.opacity_at(5.0), Some(0.5));
+        assert_eq!(timed.opacity_at(6.0), None);
+
+        let held = config(None);
+        assert_eq!(held.opacity_at(600.0), Some(1.0));
+        // Clearing at half opacity fades from there
+        assert_eq!(held.fade_out(1.0, 0.5), Some(0.25));
+        assert_eq!(held.fade_out(2.0, 0.5), None);
+
+        let mut instant = config(Some(1.0));
+        instant.fade_in_secs = 0.0;
+        instant.fade_out_secs = 0.0;
+        assert_eq!(instant.opacity_at(0.0), Some(1.0));
+        assert_eq!(instant.opacity_at(1.5), None);
+    }
+
+    #[test]
+    fn test_outline() {
+        let mut coverage = vec![0.0; 7 * 7];
+        coverage[3 * 7 + 3] = 1.0;
+        let rgba = outline(&coverage, 7, 7, 1);
+        let pixel = |x: usize, y: usize| &rgba[(y * 7 + x) * 4..][..4];
+        // Text pixel white, its neighbours dark outline, the rest clear
+        assert_eq!(pixel(3, 3), [255, 255, 255, 255]);
+        assert_eq!(pixel(2, 2), [0, 0, 0, 191]);
+        assert_eq!(pixel(4, 3), [0, 0, 0, 191]);
+        assert_eq!(pixel(5, 3)[3], 0);
+        assert_eq!(pixel(0, 0)[3], 0);
+    }
+}
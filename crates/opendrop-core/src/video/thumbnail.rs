@@ -0,0 +1,346 @@
+//! Preset thumbnails for the library
+//!
+//! The thumbnailer renderer plays each preset offscreen for a few seconds
+//! against a synthetic beat and keeps either the last frame as a PNG or the
+//! whole clip as a short WebM. Thumbnails are cached by preset path, so the
+//! library only renders presets it hasn't seen.
+
+use std::fs;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::process::{Child, ChildStdin, Command, Stdio};
+
+use serde::{Deserialize, Serialize};
+
+use super::output::VideoOutputError;
+use super::sequence::write_png;
+
+/// Length of the clip a thumbnail is taken from (seconds)
+pub const THUMBNAIL_SECS: f64 = 3.0;
+
+/// Frame rate thumbnails are rendered at
+pub const THUMBNAIL_FPS: u32 = 30;
+
+/// Thumbnail size
+pub const THUMBNAIL_WIDTH: u32 = 320;
+pub const THUMBNAIL_HEIGHT: u32 = 180;
+
+/// File format of a thumbnail
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ThumbnailFormat {
+    /// Still of the clip's last frame
+    #[default]
+    Png,
+    /// The whole clip as VP9 WebM
+    Webm,
+}
+
+impl std::fmt::Display for ThumbnailFormat {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ThumbnailFormat::Png => write!(f, "PNG"),
+            ThumbnailFormat::Webm => write!(f, "WebM"),
+        }
+    }
+}
+
+impl std::str::FromStr for ThumbnailFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "png" => Ok(ThumbnailFormat::Png),
+            "webm" => Ok(ThumbnailFormat::Webm),
+            _ => Err(format!("Unknown thumbnail format: {}. Use: png, webm", s)),
+        }
+    }
+}
+
+impl ThumbnailFormat {
+    /// File extension of the format
+    pub fn extension(self) -> &'static str {
+        match self {
+            ThumbnailFormat::Png => "png",
+            ThumbnailFormat::Webm => "webm",
+        }
+    }
+
+    /// Format of a thumbnail file, from its extension
+    pub fn of(path: &Path) -> Option<Self> {
+        path.extension()?.to_str()?.parse().ok()
+    }
+}
+
+/// Frames rendered for a thumbnail
+pub fn thumbnail_frames() -> u32 {
+    (THUMBNAIL_SECS * THUMBNAIL_FPS as f64).round() as u32
+}
+
+/// Cached thumbnail of `preset` in `dir`; the name keeps the preset's for
+/// browsing and adds a hash of its path, as presets in different folders
+/// often share names
+pub fn thumbnail_path(dir: &Path, preset: &Path, format: ThumbnailFormat) -> PathBuf {
+    // FNV-1a: stable across runs and builds, unlike the std hasher
+    let hash = preset
+        .to_string_lossy()
+        .bytes()
+        .fold(0xcbf2_9ce4_8422_2325u64, |hash, byte| {
+            (hash ^ byte as u64).wrapping_mul(0x0100_0000_01b3)
+        });
+    let stem: String = preset
+        .file_stem()
+        .map(|stem| stem.to_string_lossy())
+        .unwrap_or_default()
+        .chars()
+        .map(|c| {
+            if c.is_alphanumeric() || c == '-' {
+                c
+            } else {
+                '_'
+            }
+        })
+        .take(48)
+        .collect();
+    dir.join(format!("{}-{:016x}.{}", stem, hash, format.extension()))
+}
+
+/// ffmpeg arguments reading raw `width`x`height` RGBA from stdin and
+/// writing a WebM to `path`
+pub fn webm_args(path: &Path, width: u32, height: u32) -> Vec<String> {
+    [
+        "-hide_banner",
+        "-loglevel",
+        "error",
+        "-f",
+        "rawvideo",
+        "-pixel_format",
+        "rgba",
+        "-video_size",
+        &format!("{}x{}", width, height),
+        "-framerate",
+        &THUMBNAIL_FPS.to_string(),
+        "-i",
+        "-",
+        "-c:v",
+        "libvpx-vp9",
+        "-b:v",
+        "0",
+        "-crf",
+        "36",
+        "-deadline",
+        "realtime",
+        "-pix_fmt",
+        "yuv420p",
+        "-an",
+        // The file has a temporary extension until it is complete
+        "-f",
+        "webm",
+        "-y",
+        &path.to_string_lossy(),
+    ]
+    .iter()
+    .map(|arg| arg.to_string())
+    .collect()
+}
+
+enum Encoder {
+    /// Only the latest frame is kept
+    Png {
+        last: Option<Vec<u8>>,
+    },
+    Webm {
+        child: Child,
+        stdin: ChildStdin,
+    },
+}
+
+/// Writes one thumbnail; the file only appears under its name once
+/// finished, so a half-written one is never taken for cached
+pub struct ThumbnailWriter {
+    path: PathBuf,
+    partial: PathBuf,
+    width: u32,
+    height: u32,
+    encoder: Encoder,
+}
+
+impl ThumbnailWriter {
+    /// Start a `width`x`height` thumbnail at `path`, in the format its
+    /// extension names; its directory is created if missing
+    pub fn create(
+        path: impl Into<PathBuf>,
+        width: u32,
+        height: u32,
+    ) -> Result<Self, VideoOutputError> {
+        let path = path.into();
+        let format = ThumbnailFormat::of(&path).ok_or_else(|| {
+            VideoOutputError::InitError(format!(
+                "{}: thumbnails are .png or .webm files",
+                path.display()
+            ))
+        })?;
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)
+                .map_err(|e| VideoOutputError::InitError(format!("{}: {}", dir.display(), e)))?;
+        }
+        let partial = path.with_extension(format!("{}.part", format.extension()));
+
+        let encoder = match format {
+            ThumbnailFormat::Png => Encoder::Png { last: None },
+            ThumbnailFormat::Webm => {
+                let mut child = Command::new("ffmpeg")
+                    .args(webm_args(&partial, width, height))
+                    .stdin(Stdio::piped())
+                    .stdout(Stdio::null())
+                    .stderr(Stdio::inherit())
+                    .spawn()
+                    .map_err(|e| {
+                        VideoOutputError::InitError(format!(
+                            "Failed to start ffmpeg: {}. Install ffmpeg (with libvpx) for WebM thumbnails",
+                            e
+                        ))
+                    })?;
+                let stdin = child.stdin.take().ok_or_else(|| {
+                    VideoOutputError::InitError("ffmpeg has no stdin".to_string())
+                })?;
+                Encoder::Webm { child, stdin }
+            }
+        };
+
+        Ok(Self {
+            path,
+            partial,
+            width,
+            height,
+            encoder,
+        })
+    }
+
+    /// Whether every frame is needed, or only the last one
+    pub fn wants_every_frame(&self) -> bool {
+        matches!(self.encoder, Encoder::Webm { .. })
+    }
+
+    /// Add a frame of RGBA pixels, top row first
+    pub fn push(&mut self, pixels: &[u8]) -> Result<(), VideoOutputError> {
+        if pixels.len() != (self.width * self.height * 4) as usize {
+            return Err(VideoOutputError::SendError(format!(
+                "Thumbnail frame is {} bytes, expected {}x{} RGBA",
+                pixels.len(),
+                self.width,
+                self.height
+            )));
+        }
+        match self.encoder {
+            Encoder::Png { ref mut last } => {
+                let frame = last.get_or_insert_with(Vec::new);
+                frame.clear();
+                frame.extend_from_slice(pixels);
+                Ok(())
+            }
+            Encoder::Webm { ref mut stdin, .. } => stdin.write_all(pixels).map_err(|e| {
+                VideoOutputError::SendError(format!("ffmpeg stopped accepting frames: {}", e))
+            }),
+        }
+    }
+
+    /// Write out the thumbnail and return its path
+    pub fn finish(self) -> Result<PathBuf, VideoOutputError> {
+        let finished = match self.encoder {
+            Encoder::Png { last: None } => Err("no frames were rendered".to_string()),
+            Encoder::Png { last: Some(pixels) } => {
+                write_png(&self.partial, &pixels, self.width, self.height)
+            }
+            Encoder::Webm { mut child, stdin } => {
+                // Closing stdin ends the stream so ffmpeg finalizes the file
+                drop(stdin);
+                match child.wait() {
+                    Ok(status) if status.success() => Ok(()),
+                    Ok(status) => Err(format!("ffmpeg exited with {}", status)),
+                    Err(e) => Err(e.to_string()),
+                }
+            }
+        };
+        let result = finished.and_then(|()| {
+            fs::rename(&self.partial, &self.path)
+                .map_err(|e| format!("{}: {}", self.path.display(), e))
+        });
+        match result {
+            Ok(()) => Ok(self.path),
+            Err(e) => {
+                let _ = fs::remove_file(&self.partial);
+                Err(VideoOutputError::SendError(e))
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_thumbnail_path() {
+        let dir = Path::new("/cache/thumbnails");
+        let a = thumbnail_path(
+            dir,
+            Path::new("/presets/a/Geiss - Spiral.milk"),
+            ThumbnailFormat::Png,
+        );
+        let b = thumbnail_path(
+            dir,
+            Path::new("/presets/b/Geiss - Spiral.milk"),
+            ThumbnailFormat::Png,
+        );
+        assert_ne!(a, b);
+        assert_eq!(a.parent(), Some(dir));
+        let name = a.file_name().unwrap().to_string_lossy().into_owned();
+        assert!(name.starts_with("Geiss_-_Spiral-"));
+        assert!(name.ends_with(".png"));
+        // Same preset, same file, in either format
+        assert_eq!(
+            a,
+            thumbnail_path(
+                dir,
+                Path::new("/presets/a/Geiss - Spiral.milk"),
+                ThumbnailFormat::Png
+            )
+        );
+        assert_eq!(
+            thumbnail_path(
+                dir,
+                Path::new("/presets/a/Geiss - Spiral.milk"),
+                ThumbnailFormat::Webm
+            ),
+            a.with_extension("webm")
+        );
+    }
+
+    #[test]
+    fn test_format() {
+        assert_eq!("WebM".parse::<ThumbnailFormat>(), Ok(ThumbnailFormat::Webm));
+        assert!("gif".parse::<ThumbnailFormat>().is_err());
+        assert_eq!(
+            ThumbnailFormat::of(Path::new("/tmp/x.PNG")),
+            Some(ThumbnailFormat::Png)
+        );
+        assert_eq!(ThumbnailFormat::of(Path::new("/tmp/x")), None);
+        assert_eq!(thumbnail_frames(), 90);
+    }
+
+    #[test]
+    fn test_webm_args() {
+        let args = webm_args(Path::new("/tmp/x.webm.part"), 320, 180);
+        let joined = args.join(" ");
+        assert!(joined.contains("-video_size 320x180"));
+        assert!(joined.contains("-framerate 30"));
+        assert!(joined.contains("-c:v libvpx-vp9"));
+        assert!(joined.contains("-f webm -y /tmp/x.webm.part"));
+    }
+
+    #[test]
+    fn test_writer_rejects_other_formats() {
+        assert!(ThumbnailWriter::create("/tmp/x.gif", 4, 4).is_err());
+    }
+}
@@ -0,0 +1,127 @@
+//! Playback speed of a deck's visualization
+//!
+//! projectM animates by the time it is given for each frame. Instead of the
+//! system clock, decks give it a clock that runs at an adjustable speed, so
+//! presets can play in slow motion or fast forward. The clock only moves
+//! while frames are drawn, so a frozen deck picks up where it stopped.
+
+use std::ops::RangeInclusive;
+use std::time::Instant;
+
+use super::output::VideoOutputError;
+
+/// Slowest and fastest playback speeds
+pub const TIME_SCALE_RANGE: RangeInclusive<f64> = 0.25..=4.0;
+
+/// Whether `scale` is a playback speed decks accept
+pub fn validate_time_scale(scale: f64) -> Result<(), VideoOutputError> {
+    if TIME_SCALE_RANGE.contains(&scale) {
+        Ok(())
+    } else {
+        Err(VideoOutputError::InitError(format!(
+            "Invalid playback speed {}: use {}-{}",
+            scale,
+            TIME_SCALE_RANGE.start(),
+            TIME_SCALE_RANGE.end()
+        )))
+    }
+}
+
+/// Playback speed for a control position (0-1); the range is spread
+/// evenly in octaves, so the middle plays at normal speed
+pub fn time_scale_from_control(value: f32) -> f64 {
+    let (min, max) = (*TIME_SCALE_RANGE.start(), *TIME_SCALE_RANGE.end());
+    min * (max / min).powf(value.clamp(0.0, 1.0) as f64)
+}
+
+/// Control position (0-1) of a playback speed
+pub fn time_scale_to_control(scale: f64) -> f32 {
+    let (min, max) = (*TIME_SCALE_RANGE.start(), *TIME_SCALE_RANGE.end());
+    let scale = scale.clamp(min, max);
+    ((scale / min).ln() / (max / min).ln()) as f32
+}
+
+/// Animation time of a deck, running at its playback speed
+#[derive(Debug, Clone)]
+pub struct PresetClock {
+    scale: f64,
+    /// Animation time at the last tick (seconds)
+    elapsed: f64,
+    last: Option<Instant>,
+}
+
+impl PresetClock {
+    pub fn new(scale: f64) -> Self {
+        Self {
+            scale: scale.clamp(*TIME_SCALE_RANGE.start(), *TIME_SCALE_RANGE.end()),
+            elapsed: 0.0,
+            last: None,
+        }
+    }
+
+    pub fn scale(&self) -> f64 {
+        self.scale
+    }
+
+    /// Change speed from now on; time already passed stays as it was
+    pub fn set_scale(&mut self, scale: f64) {
+        self.scale = scale.clamp(*TIME_SCALE_RANGE.start(), *TIME_SCALE_RANGE.end());
+    }
+
+    /// Animation time for a frame drawn at `now` (seconds, from 0 at the
+    /// first frame)
+    pub fn tick(&mut self, now: Instant) -> f64 {
+        if let Some(last) = self.last {
+            self.elapsed += now.saturating_duration_since(last).as_secs_f64() * self.scale;
+        }
+        self.last = Some(now);
+        self.elapsed
+    }
+
+    /// Stop the clock up to `now`, for frames that don't animate
+    pub fn hold(&mut self, now: Instant) {
+        self.last = Some(now);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    #[test]
+    fn test_clock_runs_at_scale() {
+        let start = Instant::now();
+        let mut clock = PresetClock::new(1.0);
+        assert_eq!(clock.tick(start), 0.0);
+        assert!((clock.tick(start + Duration::from_secs(1)) - 1.0).abs() < 1e-9);
+
+        // Slowed down, the next second only moves it on by half
+        clock.set_scale(0.5);
+        assert!((clock.tick(start + Duration::from_secs(2)) - 1.5).abs() < 1e-9);
+
+        // Held frames don't count
+        clock.hold(start + Duration::from_secs(10));
+        assert!((clock.tick(start + Duration::from_secs(11)) - 2.0).abs() < 1e-9);
+
+        clock.set_scale(100.0);
+        assert_eq!(clock.scale(), 4.0);
+    }
+
+    #[test]
+    fn test_control_mapping() {
+        assert_eq!(time_scale_from_control(0.0), 0.25);
+        assert!((time_scale_from_control(0.5) - 1.0).abs() < 1e-9);
+        assert_eq!(time_scale_from_control(1.0), 4.0);
+        assert!((time_scale_to_control(2.0) - 0.75).abs() < 1e-6);
+        assert!((time_scale_to_control(time_scale_from_control(0.3)) - 0.3).abs() < 1e-6);
+    }
+
+    #[test]
+    fn test_validate() {
+        assert!(validate_time_scale(1.0).is_ok());
+        assert!(validate_time_scale(4.0).is_ok());
+        assert!(validate_time_scale(0.1).is_err());
+        assert!(validate_time_scale(f64::NAN).is_err());
+    }
+}
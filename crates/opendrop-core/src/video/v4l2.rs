@@ -13,17 +13,23 @@
 //! # Verify device exists
 //! ls /dev/video10
 //! ```
+//!
+//! [`setup_loopback`] can also load the module itself, asking for the admin
+//! password through polkit (`pkexec`).
 
 use std::fs::OpenOptions;
 use std::io::Write as IoWrite;
 use std::os::unix::fs::OpenOptionsExt;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::time::{Duration, Instant};
 
 use v4l::capability::Flags;
 use v4l::video::Output as V4lOutput;
 use v4l::{Device, Format, FourCC};
 
 use super::output::{OutputBackend, VideoOutput, VideoOutputError};
+use super::pixel_format::{convert_rgba, PixelFormat};
 
 /// v4l2loopback output configuration
 #[derive(Debug, Clone)]
@@ -34,6 +40,8 @@ pub struct V4l2Config {
     pub width: u32,
     /// Output height
     pub height: u32,
+    /// Frame format written to the device
+    pub pixel_format: PixelFormat,
 }
 
 impl Default for V4l2Config {
@@ -42,10 +50,21 @@ impl Default for V4l2Config {
             device_path: PathBuf::from("/dev/video10"),
             width: 1920,
             height: 1080,
+            pixel_format: PixelFormat::Yuyv,
         }
     }
 }
 
+/// V4L2 code of a pixel format
+fn v4l2_fourcc(format: PixelFormat) -> FourCC {
+    match format {
+        // V4L2 names packed RGB formats by their 32-bit word, not byte order
+        PixelFormat::Rgba => FourCC::new(b"AB24"),
+        PixelFormat::Bgra => FourCC::new(b"AR24"),
+        _ => FourCC::new(&format.fourcc()),
+    }
+}
+
 /// v4l2loopback video output
 pub struct V4l2Output {
     /// File handle for writing frames
@@ -54,13 +73,16 @@ pub struct V4l2Output {
     height: u32,
     active: bool,
     name: String,
-    /// Buffer for YUYV conversion
-    yuyv_buffer: Vec<u8>,
+    pixel_format: PixelFormat,
+    /// Buffer for the converted frame
+    frame_buffer: Vec<u8>,
 }
 
 impl V4l2Output {
     /// Create a new v4l2loopback output
     pub fn new(config: V4l2Config) -> Result<Self, VideoOutputError> {
+        config.pixel_format.check_size(config.width, config.height)?;
+
         // First check if device exists and is a v4l2loopback device
         let device = Device::with_path(&config.device_path)
             .map_err(|e| VideoOutputError::InitError(format!(
@@ -79,8 +101,8 @@ impl V4l2Output {
             )));
         }
 
-        // Set output format (YUYV is widely supported)
-        let format = Format::new(config.width, config.height, FourCC::new(b"YUYV"));
+        // Set output format (YUYV, the default, is widely supported)
+        let format = Format::new(config.width, config.height, v4l2_fourcc(config.pixel_format));
         device.set_format(&format)
             .map_err(|e| VideoOutputError::InitError(format!(
                 "Failed to set v4l2 format: {}",
@@ -99,16 +121,16 @@ impl V4l2Output {
                 "Failed to open device for writing: {}", e
             )))?;
 
-        // Pre-allocate YUYV buffer (2 bytes per pixel)
-        let yuyv_buffer = vec![0u8; (config.width * config.height * 2) as usize];
+        let frame_buffer = vec![0u8; config.pixel_format.frame_size(config.width, config.height)];
 
         let name = format!("v4l2:{}", config.device_path.display());
 
         tracing::info!(
-            "Opened v4l2loopback device: {} ({}x{} YUYV)",
+            "Opened v4l2loopback device: {} ({}x{} {})",
             config.device_path.display(),
             config.width,
-            config.height
+            config.height,
+            config.pixel_format
         );
 
         Ok(Self {
@@ -117,7 +139,8 @@ impl V4l2Output {
             height: config.height,
             active: true,
             name,
-            yuyv_buffer,
+            pixel_format: config.pixel_format,
+            frame_buffer,
         })
     }
 
@@ -147,45 +170,6 @@ impl V4l2Output {
 
         devices
     }
-
-    /// Convert RGBA pixels to YUYV format
-    fn rgba_to_yuyv(rgba: &[u8], yuyv: &mut [u8], width: u32, height: u32) {
-        let pixels = (width * height) as usize;
-
-        for i in 0..pixels / 2 {
-            let rgba_idx = i * 2 * 4; // 2 pixels, 4 bytes each
-            let yuyv_idx = i * 4;     // 2 pixels, 2 bytes each (4 total)
-
-            if rgba_idx + 7 >= rgba.len() || yuyv_idx + 3 >= yuyv.len() {
-                break;
-            }
-
-            // First pixel
-            let r1 = rgba[rgba_idx] as f32;
-            let g1 = rgba[rgba_idx + 1] as f32;
-            let b1 = rgba[rgba_idx + 2] as f32;
-
-            // Second pixel
-            let r2 = rgba[rgba_idx + 4] as f32;
-            let g2 = rgba[rgba_idx + 5] as f32;
-            let b2 = rgba[rgba_idx + 6] as f32;
-
-            // Convert to YUV (BT.601)
-            let y1 = (0.299 * r1 + 0.587 * g1 + 0.114 * b1) as u8;
-            let y2 = (0.299 * r2 + 0.587 * g2 + 0.114 * b2) as u8;
-
-            // Average U and V for the two pixels
-            let u = ((-0.169 * r1 - 0.331 * g1 + 0.5 * b1 + 128.0) +
-                     (-0.169 * r2 - 0.331 * g2 + 0.5 * b2 + 128.0)) / 2.0;
-            let v = ((0.5 * r1 - 0.419 * g1 - 0.081 * b1 + 128.0) +
-                     (0.5 * r2 - 0.419 * g2 - 0.081 * b2 + 128.0)) / 2.0;
-
-            yuyv[yuyv_idx] = y1;
-            yuyv[yuyv_idx + 1] = u.clamp(0.0, 255.0) as u8;
-            yuyv[yuyv_idx + 2] = y2;
-            yuyv[yuyv_idx + 3] = v.clamp(0.0, 255.0) as u8;
-        }
-    }
 }
 
 impl VideoOutput for V4l2Output {
@@ -222,11 +206,15 @@ impl VideoOutput for V4l2Output {
             )));
         }
 
-        // Convert RGBA to YUYV
-        Self::rgba_to_yuyv(pixels, &mut self.yuyv_buffer, width, height);
+        let frame = if self.pixel_format == PixelFormat::Rgba {
+            pixels
+        } else {
+            convert_rgba(pixels, width, height, self.pixel_format, &mut self.frame_buffer)?;
+            &self.frame_buffer
+        };
 
         // Write to v4l2 device file
-        self.file.write_all(&self.yuyv_buffer)
+        self.file.write_all(frame)
             .map_err(|e| VideoOutputError::SendError(format!("Failed to write frame: {}", e)))?;
 
         Ok(())
@@ -253,6 +241,191 @@ pub struct V4l2DeviceInfo {
     pub driver: String,
 }
 
+/// Time to wait for udev to create the device node after loading the module
+const DEVICE_WAIT: Duration = Duration::from_secs(3);
+
+/// Device to create when loading v4l2loopback
+#[derive(Debug, Clone)]
+pub struct V4l2SetupOptions {
+    /// Device number (10 creates /dev/video10)
+    pub video_nr: u32,
+    /// Name shown by OBS, browsers, etc.
+    pub card_label: String,
+}
+
+impl Default for V4l2SetupOptions {
+    fn default() -> Self {
+        Self {
+            video_nr: 10,
+            card_label: "OpenDrop".to_string(),
+        }
+    }
+}
+
+impl V4l2SetupOptions {
+    /// Reject values that modprobe or the driver would not accept
+    pub fn validate(&self) -> Result<(), VideoOutputError> {
+        if self.video_nr > 255 {
+            return Err(VideoOutputError::InitError(format!(
+                "Invalid device number {}: use 0-255",
+                self.video_nr
+            )));
+        }
+        // v4l2 card names are 32 bytes including the terminator
+        let label_ok = !self.card_label.is_empty()
+            && self.card_label.len() <= 31
+            && self
+                .card_label
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || c == ' ' || c == '-' || c == '_');
+        if !label_ok {
+            return Err(VideoOutputError::InitError(format!(
+                "Invalid device label {:?}: use up to 31 letters, digits, spaces, '-' or '_'",
+                self.card_label
+            )));
+        }
+        Ok(())
+    }
+
+    /// Arguments to `modprobe`
+    pub fn modprobe_args(&self) -> Vec<String> {
+        vec![
+            "v4l2loopback".to_string(),
+            "devices=1".to_string(),
+            format!("video_nr={}", self.video_nr),
+            // The kernel strips the quotes, which keep spaces in the label
+            format!("card_label=\"{}\"", self.card_label),
+        ]
+    }
+
+    /// Shell command that creates the device by hand
+    pub fn manual_command(&self) -> String {
+        format!("sudo modprobe {}", self.modprobe_args().join(" "))
+    }
+
+    /// Path of the device this creates
+    pub fn device_path(&self) -> PathBuf {
+        PathBuf::from(format!("/dev/video{}", self.video_nr))
+    }
+}
+
+/// State of v4l2loopback on this system
+#[derive(Debug, Clone)]
+pub struct V4l2SetupStatus {
+    /// The module is available for the running kernel
+    pub module_installed: bool,
+    /// The module is loaded
+    pub module_loaded: bool,
+    /// Loopback output devices
+    pub devices: Vec<V4l2DeviceInfo>,
+}
+
+/// Check whether v4l2loopback is installed, loaded and has devices
+pub fn loopback_status() -> V4l2SetupStatus {
+    let module_loaded = Path::new("/sys/module/v4l2loopback").exists();
+    // modinfo lives in /sbin, which is often not on a user's PATH
+    let module_installed = module_loaded
+        || ["modinfo", "/usr/sbin/modinfo", "/sbin/modinfo"].iter().any(|modinfo| {
+            Command::new(modinfo)
+                .args(["-F", "filename", "v4l2loopback"])
+                .output()
+                .map(|out| out.status.success())
+                .unwrap_or(false)
+        });
+
+    V4l2SetupStatus {
+        module_installed,
+        module_loaded,
+        devices: V4l2Output::list_devices(),
+    }
+}
+
+/// Load v4l2loopback with a device if there is none yet.
+///
+/// Runs `modprobe` through `pkexec`, so the desktop asks the user for the
+/// admin password; cancelling the prompt returns an error.
+pub fn setup_loopback(options: &V4l2SetupOptions) -> Result<V4l2SetupStatus, VideoOutputError> {
+    let status = loopback_status();
+    if !status.devices.is_empty() {
+        return Ok(status);
+    }
+    options.validate()?;
+
+    if status.module_loaded {
+        // Devices can only be added by reloading the module
+        return Err(VideoOutputError::InitError(format!(
+            "v4l2loopback is loaded but has no output devices. Reload it: sudo modprobe -r v4l2loopback && {}",
+            options.manual_command()
+        )));
+    }
+    if !status.module_installed {
+        return Err(VideoOutputError::InitError(
+            "v4l2loopback is not installed. Install v4l2loopback-dkms (Arch, Debian, Ubuntu) \
+             or akmod-v4l2loopback (Fedora), then try again"
+                .to_string(),
+        ));
+    }
+
+    // pkexec resets PATH, so hand it modprobe's full path where possible
+    let modprobe = ["/usr/sbin/modprobe", "/sbin/modprobe"]
+        .into_iter()
+        .find(|path| Path::new(path).exists())
+        .unwrap_or("modprobe");
+    tracing::info!("Loading v4l2loopback: pkexec {} {}", modprobe, options.modprobe_args().join(" "));
+    let output = Command::new("pkexec")
+        .arg(modprobe)
+        .args(options.modprobe_args())
+        .output()
+        .map_err(|e| {
+            VideoOutputError::InitError(format!(
+                "Could not run pkexec to ask for permission ({}). Run it by hand: {}",
+                e,
+                options.manual_command()
+            ))
+        })?;
+    if !output.status.success() {
+        return Err(pkexec_error(
+            output.status.code(),
+            &String::from_utf8_lossy(&output.stderr),
+            options,
+        ));
+    }
+
+    // udev creates the node shortly after the module loads
+    let device_path = options.device_path();
+    let deadline = Instant::now() + DEVICE_WAIT;
+    loop {
+        let status = loopback_status();
+        if status.devices.iter().any(|d| d.path == device_path) {
+            tracing::info!("Created v4l2loopback device {}", device_path.display());
+            return Ok(status);
+        }
+        if Instant::now() >= deadline {
+            return Err(VideoOutputError::InitError(format!(
+                "v4l2loopback loaded but {} did not appear",
+                device_path.display()
+            )));
+        }
+        std::thread::sleep(Duration::from_millis(100));
+    }
+}
+
+/// Explain a failed `pkexec modprobe`
+fn pkexec_error(code: Option<i32>, stderr: &str, options: &V4l2SetupOptions) -> VideoOutputError {
+    let message = match code {
+        // pkexec: the authentication dialog was dismissed
+        Some(126) => "Permission was not granted".to_string(),
+        // pkexec: not authorized, or no polkit agent to ask
+        Some(127) => format!("Not authorized to load v4l2loopback. Run it by hand: {}", options.manual_command()),
+        _ => format!(
+            "modprobe v4l2loopback failed: {}. Run it by hand to see why: {}",
+            stderr.trim(),
+            options.manual_command()
+        ),
+    };
+    VideoOutputError::InitError(message)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -266,7 +439,7 @@ mod tests {
         ];
         let mut yuyv = vec![0u8; 4];
 
-        V4l2Output::rgba_to_yuyv(&rgba, &mut yuyv, 2, 1);
+        convert_rgba(&rgba, 2, 1, PixelFormat::Yuyv, &mut yuyv).unwrap();
 
         // Y values should be different for red vs green
         assert!(yuyv[0] != yuyv[2], "Y values should differ for red and green");
@@ -281,7 +454,7 @@ mod tests {
         ];
         let mut yuyv = vec![0u8; 4];
 
-        V4l2Output::rgba_to_yuyv(&rgba, &mut yuyv, 2, 1);
+        convert_rgba(&rgba, 2, 1, PixelFormat::Yuyv, &mut yuyv).unwrap();
 
         // Y should be around 16 for black (BT.601 limited range)
         assert!(yuyv[0] < 30, "Black pixel Y should be low, got {}", yuyv[0]);
@@ -297,7 +470,7 @@ mod tests {
         ];
         let mut yuyv = vec![0u8; 4];
 
-        V4l2Output::rgba_to_yuyv(&rgba, &mut yuyv, 2, 1);
+        convert_rgba(&rgba, 2, 1, PixelFormat::Yuyv, &mut yuyv).unwrap();
 
         // Y should be around 235 for white (BT.601 limited range)
         assert!(yuyv[0] > 200, "White pixel Y should be high, got {}", yuyv[0]);
@@ -313,7 +486,7 @@ mod tests {
         ];
         let mut yuyv = vec![0u8; 4];
 
-        V4l2Output::rgba_to_yuyv(&rgba, &mut yuyv, 2, 1);
+        convert_rgba(&rgba, 2, 1, PixelFormat::Yuyv, &mut yuyv).unwrap();
 
         // U and V should be near 128 for gray
         let u = yuyv[1];
@@ -339,7 +512,7 @@ mod tests {
         ];
         let mut yuyv = vec![0u8; 16]; // 4 pixels * 2 bytes/pixel * 2 rows
 
-        V4l2Output::rgba_to_yuyv(&rgba, &mut yuyv, 4, 2);
+        convert_rgba(&rgba, 4, 2, PixelFormat::Yuyv, &mut yuyv).unwrap();
 
         // Just verify no panic and output is filled
         assert_eq!(yuyv.len(), 16);
@@ -355,6 +528,47 @@ mod tests {
         assert_eq!(config.device_path, std::path::PathBuf::from("/dev/video10"));
         assert_eq!(config.width, 1920);
         assert_eq!(config.height, 1080);
+        assert_eq!(config.pixel_format, PixelFormat::Yuyv);
+    }
+
+    #[test]
+    fn test_v4l2_fourcc() {
+        assert_eq!(v4l2_fourcc(PixelFormat::Nv12), FourCC::new(b"NV12"));
+        assert_eq!(v4l2_fourcc(PixelFormat::Bgra), FourCC::new(b"AR24"));
+    }
+
+    #[test]
+    fn test_setup_options_modprobe_args() {
+        let options = V4l2SetupOptions::default();
+        assert_eq!(
+            options.manual_command(),
+            r#"sudo modprobe v4l2loopback devices=1 video_nr=10 card_label="OpenDrop""#
+        );
+        assert_eq!(options.device_path(), std::path::PathBuf::from("/dev/video10"));
+    }
+
+    #[test]
+    fn test_setup_options_validate() {
+        assert!(V4l2SetupOptions::default().validate().is_ok());
+        let label = |card_label: &str| V4l2SetupOptions {
+            card_label: card_label.to_string(),
+            ..Default::default()
+        };
+        assert!(label("Open Drop_2").validate().is_ok());
+        assert!(label("").validate().is_err());
+        assert!(label("bad\"label").validate().is_err());
+        assert!(label(&"x".repeat(32)).validate().is_err());
+        let nr = V4l2SetupOptions { video_nr: 256, ..Default::default() };
+        assert!(nr.validate().is_err());
+    }
+
+    #[test]
+    fn test_pkexec_error_messages() {
+        let options = V4l2SetupOptions::default();
+        let message = |code| pkexec_error(code, "modprobe: FATAL: busy\n", &options).to_string();
+        assert!(message(Some(126)).contains("not granted"));
+        assert!(message(Some(127)).contains("sudo modprobe"));
+        assert!(message(Some(1)).contains("FATAL: busy."));
     }
 
     #[test]
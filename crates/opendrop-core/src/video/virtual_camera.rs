@@ -0,0 +1,287 @@
+//! Virtual webcam output for Windows
+//!
+//! Presents deck output as a camera that Zoom, Teams, OBS and browsers can
+//! select, like the v4l2loopback path on Linux.
+//!
+//! ## Setup (Windows only)
+//!
+//! 1. Download softcam.dll (64-bit) from the softcam releases:
+//!    <https://github.com/tshino/softcam/releases>
+//!
+//! 2. Place `softcam.dll` next to opendrop.exe and register it once from an
+//!    administrator prompt: `regsvr32 softcam.dll`
+//!
+//! 3. Pick "DirectShow Softcam" as the camera in the receiving app
+//!
+//! ## Implementation
+//!
+//! softcam.dll exports a small C API (`scCreateCamera`, `scSendFrame`,
+//! `scDeleteCamera`) and takes 24-bit BGR frames, top row first. Only one
+//! softcam camera can exist on the system at a time.
+
+use super::output::{OutputBackend, VideoOutput, VideoOutputError};
+
+/// Virtual camera configuration
+#[derive(Debug, Clone)]
+pub struct VirtualCameraConfig {
+    /// Camera frame size
+    pub width: u32,
+    pub height: u32,
+    /// Frame rate advertised to receiving apps
+    pub fps: f32,
+}
+
+impl Default for VirtualCameraConfig {
+    fn default() -> Self {
+        Self {
+            width: 1280,
+            height: 720,
+            fps: 30.0,
+        }
+    }
+}
+
+/// Convert RGBA pixels to the 24-bit BGR softcam expects
+pub fn rgba_to_bgr(rgba: &[u8], bgr: &mut [u8]) {
+    for (dst, src) in bgr.chunks_exact_mut(3).zip(rgba.chunks_exact(4)) {
+        dst[0] = src[2];
+        dst[1] = src[1];
+        dst[2] = src[0];
+    }
+}
+
+#[cfg(target_os = "windows")]
+mod ffi {
+    use std::ffi::c_void;
+
+    /// Opaque camera handle
+    pub type ScCamera = *mut c_void;
+
+    pub type CreateCameraFn = unsafe extern "C" fn(i32, i32, f32) -> ScCamera;
+    pub type DeleteCameraFn = unsafe extern "C" fn(ScCamera);
+    pub type SendFrameFn = unsafe extern "C" fn(ScCamera, *const c_void);
+}
+
+#[cfg(target_os = "windows")]
+use libloading::Library;
+
+/// Load softcam.dll from the application folder or the system PATH
+#[cfg(target_os = "windows")]
+fn load_softcam() -> Result<Library, libloading::Error> {
+    unsafe {
+        Library::new("softcam.dll")
+            .or_else(|_| Library::new("./softcam.dll"))
+            .or_else(|_| Library::new("bin/softcam.dll"))
+    }
+}
+
+/// Virtual webcam output using softcam
+#[cfg(target_os = "windows")]
+pub struct VirtualCameraOutput {
+    /// Handle to loaded softcam.dll
+    library: Library,
+    /// Camera created by scCreateCamera
+    camera: ffi::ScCamera,
+    /// Frame converted to BGR
+    frame_buffer: Vec<u8>,
+    width: u32,
+    height: u32,
+    /// Whether output is active
+    active: bool,
+}
+
+// VirtualCameraOutput is Send because:
+// - The camera handle is only used from one thread (renderer)
+// - softcam's shared-memory transport has no thread affinity
+#[cfg(target_os = "windows")]
+unsafe impl Send for VirtualCameraOutput {}
+
+#[cfg(target_os = "windows")]
+impl VirtualCameraOutput {
+    /// Create the virtual camera
+    pub fn new(config: VirtualCameraConfig) -> Result<Self, VideoOutputError> {
+        let library = load_softcam().map_err(|e| {
+            VideoOutputError::InitError(format!(
+                "Failed to load softcam.dll: {}. \n\
+                Download from https://github.com/tshino/softcam/releases, \n\
+                place it in the application folder and run: regsvr32 softcam.dll",
+                e
+            ))
+        })?;
+
+        let camera = unsafe {
+            let create_camera: libloading::Symbol<ffi::CreateCameraFn> =
+                library.get(b"scCreateCamera\0").map_err(|e| {
+                    VideoOutputError::InitError(format!(
+                        "Failed to find scCreateCamera in softcam.dll: {}",
+                        e
+                    ))
+                })?;
+            create_camera(config.width as i32, config.height as i32, config.fps)
+        };
+        if camera.is_null() {
+            return Err(VideoOutputError::InitError(
+                "scCreateCamera failed - is another virtual camera already running?".to_string(),
+            ));
+        }
+
+        tracing::info!(
+            "Virtual camera created ({}x{} @ {} fps)",
+            config.width,
+            config.height,
+            config.fps
+        );
+
+        Ok(Self {
+            library,
+            camera,
+            frame_buffer: vec![0u8; (config.width * config.height * 3) as usize],
+            width: config.width,
+            height: config.height,
+            active: true,
+        })
+    }
+
+    /// Check if softcam.dll can be loaded
+    pub fn is_available() -> bool {
+        load_softcam().is_ok()
+    }
+}
+
+#[cfg(target_os = "windows")]
+impl VideoOutput for VirtualCameraOutput {
+    fn backend(&self) -> OutputBackend {
+        OutputBackend::VirtualCamera
+    }
+
+    fn send_frame(
+        &mut self,
+        _texture_id: u32,
+        _width: u32,
+        _height: u32,
+    ) -> Result<(), VideoOutputError> {
+        Err(VideoOutputError::SendError(
+            "Virtual camera requires RGBA pixel data. Use send_frame_rgba instead.".to_string(),
+        ))
+    }
+
+    fn send_frame_rgba(
+        &mut self,
+        pixels: &[u8],
+        width: u32,
+        height: u32,
+    ) -> Result<(), VideoOutputError> {
+        if !self.active {
+            return Ok(());
+        }
+
+        // The camera's size is fixed when it is created
+        if width != self.width || height != self.height {
+            return Err(VideoOutputError::SendError(format!(
+                "Frame size {}x{} doesn't match the {}x{} camera",
+                width, height, self.width, self.height
+            )));
+        }
+        let expected_size = (width * height * 4) as usize;
+        if pixels.len() != expected_size {
+            return Err(VideoOutputError::SendError(format!(
+                "Invalid pixel buffer size: got {}, expected {}",
+                pixels.len(),
+                expected_size
+            )));
+        }
+
+        rgba_to_bgr(pixels, &mut self.frame_buffer);
+        unsafe {
+            let send_frame: libloading::Symbol<ffi::SendFrameFn> = self
+                .library
+                .get(b"scSendFrame\0")
+                .map_err(|e| VideoOutputError::SendError(e.to_string()))?;
+            send_frame(self.camera, self.frame_buffer.as_ptr() as *const _);
+        }
+        Ok(())
+    }
+
+    fn is_active(&self) -> bool {
+        self.active
+    }
+
+    fn name(&self) -> &str {
+        "virtual-camera"
+    }
+
+    fn set_active(&mut self, active: bool) {
+        self.active = active;
+    }
+}
+
+#[cfg(target_os = "windows")]
+impl Drop for VirtualCameraOutput {
+    fn drop(&mut self) {
+        unsafe {
+            if let Ok(delete_camera) = self.library.get::<ffi::DeleteCameraFn>(b"scDeleteCamera\0")
+            {
+                delete_camera(self.camera);
+            }
+        }
+        tracing::debug!("Virtual camera dropped");
+    }
+}
+
+// Non-Windows stub implementation
+#[cfg(not(target_os = "windows"))]
+pub struct VirtualCameraOutput {
+    _private: (),
+}
+
+#[cfg(not(target_os = "windows"))]
+impl VirtualCameraOutput {
+    pub fn new(_config: VirtualCameraConfig) -> Result<Self, VideoOutputError> {
+        Err(VideoOutputError::NotSupported)
+    }
+
+    pub fn is_available() -> bool {
+        false
+    }
+}
+
+#[cfg(not(target_os = "windows"))]
+impl VideoOutput for VirtualCameraOutput {
+    fn backend(&self) -> OutputBackend {
+        OutputBackend::VirtualCamera
+    }
+
+    fn send_frame(
+        &mut self,
+        _texture_id: u32,
+        _width: u32,
+        _height: u32,
+    ) -> Result<(), VideoOutputError> {
+        Err(VideoOutputError::NotSupported)
+    }
+
+    fn is_active(&self) -> bool {
+        false
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_rgba_to_bgr() {
+        let rgba = [1, 2, 3, 255, 4, 5, 6, 128];
+        let mut bgr = [0; 6];
+        rgba_to_bgr(&rgba, &mut bgr);
+        assert_eq!(bgr, [3, 2, 1, 6, 5, 4]);
+    }
+
+    #[test]
+    #[cfg(not(target_os = "windows"))]
+    fn test_virtual_camera_not_supported_on_non_windows() {
+        let result = VirtualCameraOutput::new(VirtualCameraConfig::default());
+        assert!(matches!(result, Err(VideoOutputError::NotSupported)));
+        assert!(!VirtualCameraOutput::is_available());
+    }
+}
@@ -0,0 +1,213 @@
+//! Background image or video under the visualization
+//!
+//! projectM clears its frame when it draws, so the background can't go
+//! first. It is drawn after the visualization instead, with blending that
+//! puts it underneath: it fills in by the frame's alpha, or adds or screens
+//! its light with the visualization's, which gives the same result in
+//! either order.
+
+use opendrop_core::video::{BackgroundBlend, BackgroundConfig, BackgroundMedia};
+
+use crate::shader::{link_program, GlStateGuard};
+
+const VERTEX_SHADER: &str = r#"#version 330 core
+layout(location = 0) in vec2 position;
+layout(location = 1) in vec2 tex_coord;
+out vec2 uv;
+void main() {
+    uv = tex_coord;
+    gl_Position = vec4(position, 0.0, 1.0);
+}
+"#;
+
+/// Premultiplied by the picture's alpha and the opacity, for every blend
+const FRAGMENT_SHADER: &str = r#"#version 330 core
+in vec2 uv;
+out vec4 color;
+uniform sampler2D frame;
+uniform float opacity;
+void main() {
+    vec4 picture = texture(frame, uv);
+    float alpha = picture.a * opacity;
+    color = vec4(picture.rgb * alpha, alpha);
+}
+"#;
+
+/// A picture drawn under the visualization
+pub struct BackgroundLayer {
+    media: BackgroundMedia,
+    config: BackgroundConfig,
+    program: u32,
+    vao: u32,
+    vbo: u32,
+    texture: u32,
+    /// Size of the uploaded picture, (0, 0) until the first arrives
+    frame_size: (u32, u32),
+    opacity_location: i32,
+}
+
+impl BackgroundLayer {
+    /// Open the file and set up GL resources on the current context
+    pub fn new(config: BackgroundConfig) -> Result<Self, String> {
+        config.validate().map_err(|e| e.to_string())?;
+        let media = BackgroundMedia::open(&config.path).map_err(|e| e.to_string())?;
+        let program = unsafe { link_program("Background", VERTEX_SHADER, FRAGMENT_SHADER)? };
+        let mut layer = Self {
+            media,
+            config,
+            program,
+            vao: 0,
+            vbo: 0,
+            texture: 0,
+            frame_size: (0, 0),
+            opacity_location: -1,
+        };
+
+        unsafe {
+            layer.opacity_location = gl::GetUniformLocation(program, c"opacity".as_ptr());
+
+            gl::GenVertexArrays(1, &mut layer.vao);
+            gl::GenBuffers(1, &mut layer.vbo);
+            gl::BindVertexArray(layer.vao);
+            gl::BindBuffer(gl::ARRAY_BUFFER, layer.vbo);
+            gl::BufferData(
+                gl::ARRAY_BUFFER,
+                (16 * std::mem::size_of::<f32>()) as isize,
+                std::ptr::null(),
+                gl::DYNAMIC_DRAW,
+            );
+            let stride = (4 * std::mem::size_of::<f32>()) as i32;
+            gl::VertexAttribPointer(0, 2, gl::FLOAT, gl::FALSE, stride, std::ptr::null());
+            gl::EnableVertexAttribArray(0);
+            gl::VertexAttribPointer(
+                1,
+                2,
+                gl::FLOAT,
+                gl::FALSE,
+                stride,
+                (2 * std::mem::size_of::<f32>()) as *const _,
+            );
+            gl::EnableVertexAttribArray(1);
+            gl::BindVertexArray(0);
+            gl::BindBuffer(gl::ARRAY_BUFFER, 0);
+
+            gl::GenTextures(1, &mut layer.texture);
+        }
+
+        Ok(layer)
+    }
+
+    pub fn config(&self) -> &BackgroundConfig {
+        &self.config
+    }
+
+    /// Change the blend, fit or opacity of the same file
+    pub fn set_config(&mut self, config: BackgroundConfig) -> Result<(), String> {
+        config.validate().map_err(|e| e.to_string())?;
+        self.config = config;
+        Ok(())
+    }
+
+    /// Take the latest picture and draw it under the visualization in the
+    /// bound `width`x`height` framebuffer
+    pub fn draw(&mut self, width: u32, height: u32) -> Result<(), String> {
+        if let Some(frame) = self.media.receive_frame().map_err(|e| e.to_string())? {
+            let size = (frame.width, frame.height);
+            unsafe {
+                let mut previous = 0;
+                gl::GetIntegerv(gl::TEXTURE_BINDING_2D, &mut previous);
+                gl::BindTexture(gl::TEXTURE_2D, self.texture);
+                gl::PixelStorei(gl::UNPACK_ALIGNMENT, 4);
+                if size != self.frame_size {
+                    gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_MIN_FILTER, gl::LINEAR as i32);
+                    gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_MAG_FILTER, gl::LINEAR as i32);
+                    gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_WRAP_S, gl::CLAMP_TO_EDGE as i32);
+                    gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_WRAP_T, gl::CLAMP_TO_EDGE as i32);
+                    gl::TexImage2D(
+                        gl::TEXTURE_2D,
+                        0,
+                        gl::RGBA8 as i32,
+                        frame.width as i32,
+                        frame.height as i32,
+                        0,
+                        gl::RGBA,
+                        gl::UNSIGNED_BYTE,
+                        frame.pixels.as_ptr() as *const _,
+                    );
+                } else {
+                    gl::TexSubImage2D(
+                        gl::TEXTURE_2D,
+                        0,
+                        0,
+                        0,
+                        frame.width as i32,
+                        frame.height as i32,
+                        gl::RGBA,
+                        gl::UNSIGNED_BYTE,
+                        frame.pixels.as_ptr() as *const _,
+                    );
+                }
+                gl::BindTexture(gl::TEXTURE_2D, previous as u32);
+            }
+            self.frame_size = size;
+        }
+
+        if self.frame_size == (0, 0) || self.config.opacity <= 0.0 {
+            return Ok(());
+        }
+
+        // Pictures are top row first, so the top of the quad samples v = 0
+        let (x0, y0, x1, y1) =
+            self.config
+                .fit
+                .rect(self.frame_size.0, self.frame_size.1, width, height);
+        #[rustfmt::skip]
+        let vertices: [f32; 16] = [
+            x0, y0, 0.0, 1.0,
+            x1, y0, 1.0, 1.0,
+            x0, y1, 0.0, 0.0,
+            x1, y1, 1.0, 0.0,
+        ];
+
+        // Each puts the picture behind what is already in the frame
+        let (src, dst, src_alpha) = match self.config.blend {
+            BackgroundBlend::Alpha => (gl::ONE_MINUS_DST_ALPHA, gl::ONE, gl::ONE_MINUS_DST_ALPHA),
+            BackgroundBlend::Add => (gl::ONE, gl::ONE, gl::ZERO),
+            BackgroundBlend::Screen => (gl::ONE_MINUS_DST_COLOR, gl::ONE, gl::ZERO),
+        };
+
+        unsafe {
+            let _state = GlStateGuard::save();
+
+            gl::BindBuffer(gl::ARRAY_BUFFER, self.vbo);
+            gl::BufferSubData(
+                gl::ARRAY_BUFFER,
+                0,
+                std::mem::size_of_val(&vertices) as isize,
+                vertices.as_ptr() as *const _,
+            );
+            gl::BindBuffer(gl::ARRAY_BUFFER, 0);
+
+            gl::Enable(gl::BLEND);
+            gl::BlendFuncSeparate(src, dst, src_alpha, gl::ONE);
+            gl::UseProgram(self.program);
+            gl::Uniform1f(self.opacity_location, self.config.opacity);
+            gl::BindTexture(gl::TEXTURE_2D, self.texture);
+            gl::BindVertexArray(self.vao);
+            gl::DrawArrays(gl::TRIANGLE_STRIP, 0, 4);
+        }
+
+        Ok(())
+    }
+}
+
+impl Drop for BackgroundLayer {
+    fn drop(&mut self) {
+        unsafe {
+            gl::DeleteTextures(1, &self.texture);
+            gl::DeleteBuffers(1, &self.vbo);
+            gl::DeleteVertexArrays(1, &self.vao);
+            gl::DeleteProgram(self.program);
+        }
+    }
+}
@@ -0,0 +1,152 @@
+//! Brightness, contrast and saturation over the finished frame
+//!
+//! Like the post effects, the frame is copied to a texture and drawn back
+//! through a shader. Brightness is applied last, so 0 is black whatever the
+//! contrast.
+
+use opendrop_core::video::ColorAdjust;
+
+use crate::shader::{link_program, GlStateGuard};
+
+const VERTEX_SHADER: &str = r#"#version 330 core
+layout(location = 0) in vec2 position;
+out vec2 uv;
+void main() {
+    uv = position * 0.5 + 0.5;
+    gl_Position = vec4(position, 0.0, 1.0);
+}
+"#;
+
+/// Rec. 709 luma for saturation
+const FRAGMENT_SHADER: &str = r#"#version 330 core
+in vec2 uv;
+out vec4 color;
+uniform sampler2D frame;
+uniform float brightness;
+uniform float contrast;
+uniform float saturation;
+void main() {
+    vec4 picture = texture(frame, uv);
+    vec3 rgb = (picture.rgb - 0.5) * contrast + 0.5;
+    float luma = dot(rgb, vec3(0.2126, 0.7152, 0.0722));
+    rgb = mix(vec3(luma), rgb, saturation) * brightness;
+    color = vec4(clamp(rgb, 0.0, 1.0), picture.a);
+}
+"#;
+
+/// Colour correction pass for one deck
+pub struct ColorPass {
+    program: u32,
+    vao: u32,
+    vbo: u32,
+    texture: u32,
+    /// Size the texture was allocated for
+    size: (u32, u32),
+    brightness_location: i32,
+    contrast_location: i32,
+    saturation_location: i32,
+}
+
+impl ColorPass {
+    /// Set up GL resources on the current context
+    pub fn new() -> Result<Self, String> {
+        let program = unsafe { link_program("Color adjust", VERTEX_SHADER, FRAGMENT_SHADER)? };
+        let mut pass = Self {
+            program,
+            vao: 0,
+            vbo: 0,
+            texture: 0,
+            size: (0, 0),
+            brightness_location: -1,
+            contrast_location: -1,
+            saturation_location: -1,
+        };
+
+        #[rustfmt::skip]
+        let vertices: [f32; 8] = [
+            -1.0, -1.0,
+             1.0, -1.0,
+            -1.0,  1.0,
+             1.0,  1.0,
+        ];
+        unsafe {
+            pass.brightness_location = gl::GetUniformLocation(program, c"brightness".as_ptr());
+            pass.contrast_location = gl::GetUniformLocation(program, c"contrast".as_ptr());
+            pass.saturation_location = gl::GetUniformLocation(program, c"saturation".as_ptr());
+
+            gl::GenVertexArrays(1, &mut pass.vao);
+            gl::GenBuffers(1, &mut pass.vbo);
+            gl::BindVertexArray(pass.vao);
+            gl::BindBuffer(gl::ARRAY_BUFFER, pass.vbo);
+            gl::BufferData(
+                gl::ARRAY_BUFFER,
+                std::mem::size_of_val(&vertices) as isize,
+                vertices.as_ptr() as *const _,
+                gl::STATIC_DRAW,
+            );
+            let stride = (2 * std::mem::size_of::<f32>()) as i32;
+            gl::VertexAttribPointer(0, 2, gl::FLOAT, gl::FALSE, stride, std::ptr::null());
+            gl::EnableVertexAttribArray(0);
+            gl::BindVertexArray(0);
+            gl::BindBuffer(gl::ARRAY_BUFFER, 0);
+
+            gl::GenTextures(1, &mut pass.texture);
+            gl::BindTexture(gl::TEXTURE_2D, pass.texture);
+            // Pixel for pixel, so nearest is exact
+            gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_MIN_FILTER, gl::NEAREST as i32);
+            gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_MAG_FILTER, gl::NEAREST as i32);
+            gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_WRAP_S, gl::CLAMP_TO_EDGE as i32);
+            gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_WRAP_T, gl::CLAMP_TO_EDGE as i32);
+            gl::BindTexture(gl::TEXTURE_2D, 0);
+        }
+
+        Ok(pass)
+    }
+
+    /// Correct the bound `width`x`height` framebuffer
+    pub fn draw(&mut self, adjust: &ColorAdjust, width: u32, height: u32) {
+        if width == 0 || height == 0 || adjust.is_neutral() {
+            return;
+        }
+
+        unsafe {
+            let _state = GlStateGuard::save();
+
+            gl::BindTexture(gl::TEXTURE_2D, self.texture);
+            if self.size != (width, height) {
+                gl::TexImage2D(
+                    gl::TEXTURE_2D,
+                    0,
+                    gl::RGBA8 as i32,
+                    width as i32,
+                    height as i32,
+                    0,
+                    gl::RGBA,
+                    gl::UNSIGNED_BYTE,
+                    std::ptr::null(),
+                );
+                self.size = (width, height);
+            }
+            gl::CopyTexSubImage2D(gl::TEXTURE_2D, 0, 0, 0, 0, 0, width as i32, height as i32);
+
+            gl::Disable(gl::BLEND);
+            gl::UseProgram(self.program);
+            gl::Uniform1f(self.brightness_location, adjust.brightness);
+            gl::Uniform1f(self.contrast_location, adjust.contrast);
+            gl::Uniform1f(self.saturation_location, adjust.saturation);
+            gl::BindVertexArray(self.vao);
+            gl::DrawArrays(gl::TRIANGLE_STRIP, 0, 4);
+        }
+    }
+}
+
+impl Drop for ColorPass {
+    fn drop(&mut self) {
+        unsafe {
+            gl::DeleteTextures(1, &self.texture);
+            gl::DeleteBuffers(1, &self.vbo);
+            gl::DeleteVertexArrays(1, &self.vao);
+            gl::DeleteProgram(self.program);
+        }
+    }
+}
@@ -0,0 +1,368 @@
+//! Compositor blending the decks' frames into one picture
+//!
+//! Used instead of projectM when the renderer runs as the compositor. Each
+//! layer is drawn with a shader that reads the picture so far, so Overlay and
+//! the other modes that need the destination work: layers ping-pong between
+//! two framebuffers, bottom to top, and the result is blitted to the window,
+//! where outputs capture it like any deck.
+//!
+//! Decks that share textures (see `shared_texture`) are drawn straight from
+//! them; the others send pixels, which are uploaded here.
+
+use std::collections::HashMap;
+
+use opendrop_core::video::input::fit_rect;
+use opendrop_core::video::{BlendMode, CompositeLayer, CompositorReceiver};
+
+use crate::shader::link_program;
+use crate::shared_texture::{Egl, SharedTextureImporter};
+
+const VERTEX_SHADER: &str = r#"#version 330 core
+layout(location = 0) in vec2 position;
+out vec2 uv;
+void main() {
+    uv = position * 0.5 + 0.5;
+    gl_Position = vec4(position, 0.0, 1.0);
+}
+"#;
+
+/// `rect` is where the layer sits (letterboxed) in normalized device
+/// coordinates; outside it the picture so far shows through
+const FRAGMENT_SHADER: &str = r#"#version 330 core
+in vec2 uv;
+out vec4 color;
+uniform sampler2D base;
+uniform sampler2D layer;
+uniform float opacity;
+uniform int mode;
+uniform vec4 rect;
+vec3 blend(vec3 b, vec3 l) {
+    if (mode == 1) return min(b + l, 1.0);
+    if (mode == 2) return b * l;
+    if (mode == 3) return 1.0 - (1.0 - b) * (1.0 - l);
+    if (mode == 4) return mix(2.0 * b * l, 1.0 - 2.0 * (1.0 - b) * (1.0 - l), step(0.5, b));
+    return l;
+}
+void main() {
+    vec3 b = texture(base, uv).rgb;
+    vec2 local = (uv * 2.0 - 1.0 - rect.xy) / (rect.zw - rect.xy);
+    if (any(lessThan(local, vec2(0.0))) || any(greaterThan(local, vec2(1.0)))) {
+        color = vec4(b, 1.0);
+        return;
+    }
+    // Frames are top row first
+    vec3 l = texture(layer, vec2(local.x, 1.0 - local.y)).rgb;
+    color = vec4(mix(b, blend(b, l), opacity), 1.0);
+}
+"#;
+
+/// Shader mode number of a blend mode
+fn shader_mode(mode: BlendMode) -> i32 {
+    match mode {
+        BlendMode::Normal => 0,
+        BlendMode::Add => 1,
+        BlendMode::Multiply => 2,
+        BlendMode::Screen => 3,
+        BlendMode::Overlay => 4,
+    }
+}
+
+/// Framebuffer with a colour texture
+struct RenderTarget {
+    framebuffer: u32,
+    texture: u32,
+}
+
+/// Texture holding a deck's latest frame
+struct DeckTexture {
+    texture: u32,
+    size: (u32, u32),
+}
+
+/// Blends deck frames in layer order
+pub struct Compositor {
+    receiver: CompositorReceiver,
+    /// Imports decks' shared textures, when the context can
+    importer: Option<SharedTextureImporter>,
+    /// Socket decks share textures over
+    gpu_socket: Option<String>,
+    /// Bottom layer first
+    layers: Vec<CompositeLayer>,
+    program: u32,
+    vao: u32,
+    vbo: u32,
+    targets: Vec<RenderTarget>,
+    target_size: (u32, u32),
+    decks: HashMap<u8, DeckTexture>,
+    opacity_location: i32,
+    mode_location: i32,
+    rect_location: i32,
+}
+
+impl Compositor {
+    /// Start receiving deck frames and set up GL resources on the current
+    /// context; with `egl`, decks may share textures instead of pixels
+    pub fn new(egl: Option<Egl>) -> Result<Self, String> {
+        let mut receiver = CompositorReceiver::bind().map_err(|e| e.to_string())?;
+        let (importer, gpu_socket) = match SharedTextureImporter::listen(egl, &mut receiver) {
+            Some((importer, socket)) => (Some(importer), Some(socket)),
+            None => (None, None),
+        };
+        let program = unsafe { link_program("Compositor", VERTEX_SHADER, FRAGMENT_SHADER)? };
+        let mut compositor = Self {
+            receiver,
+            importer,
+            gpu_socket,
+            layers: Vec::new(),
+            program,
+            vao: 0,
+            vbo: 0,
+            targets: Vec::new(),
+            target_size: (0, 0),
+            decks: HashMap::new(),
+            opacity_location: -1,
+            mode_location: -1,
+            rect_location: -1,
+        };
+
+        #[rustfmt::skip]
+        let vertices: [f32; 8] = [
+            -1.0, -1.0,
+             1.0, -1.0,
+            -1.0,  1.0,
+             1.0,  1.0,
+        ];
+        unsafe {
+            compositor.opacity_location = gl::GetUniformLocation(program, c"opacity".as_ptr());
+            compositor.mode_location = gl::GetUniformLocation(program, c"mode".as_ptr());
+            compositor.rect_location = gl::GetUniformLocation(program, c"rect".as_ptr());
+            gl::UseProgram(program);
+            gl::Uniform1i(gl::GetUniformLocation(program, c"base".as_ptr()), 0);
+            gl::Uniform1i(gl::GetUniformLocation(program, c"layer".as_ptr()), 1);
+            gl::UseProgram(0);
+
+            gl::GenVertexArrays(1, &mut compositor.vao);
+            gl::GenBuffers(1, &mut compositor.vbo);
+            gl::BindVertexArray(compositor.vao);
+            gl::BindBuffer(gl::ARRAY_BUFFER, compositor.vbo);
+            gl::BufferData(
+                gl::ARRAY_BUFFER,
+                std::mem::size_of_val(&vertices) as isize,
+                vertices.as_ptr() as *const _,
+                gl::STATIC_DRAW,
+            );
+            gl::VertexAttribPointer(0, 2, gl::FLOAT, gl::FALSE, 0, std::ptr::null());
+            gl::EnableVertexAttribArray(0);
+            gl::BindVertexArray(0);
+            gl::BindBuffer(gl::ARRAY_BUFFER, 0);
+        }
+
+        Ok(compositor)
+    }
+
+    /// Port decks stream their frames to
+    pub fn port(&self) -> u16 {
+        self.receiver.port()
+    }
+
+    /// Socket decks share textures over, if they can
+    pub fn gpu_socket(&self) -> Option<&str> {
+        self.gpu_socket.as_deref()
+    }
+
+    /// Decks to draw, bottom layer first
+    pub fn set_layers(&mut self, layers: Vec<CompositeLayer>) {
+        self.layers = layers;
+    }
+
+    /// Draw the composite into the bound `width`x`height` framebuffer (the
+    /// window's, or the offscreen one when headless)
+    pub fn draw(&mut self, width: u32, height: u32) {
+        if width == 0 || height == 0 {
+            return;
+        }
+        unsafe {
+            let mut output = 0;
+            gl::GetIntegerv(gl::DRAW_FRAMEBUFFER_BINDING, &mut output);
+            if self.target_size != (width, height) {
+                self.resize_targets(width, height);
+            }
+
+            // Start from black
+            let mut current = 0;
+            gl::BindFramebuffer(gl::FRAMEBUFFER, self.targets[current].framebuffer);
+            gl::Viewport(0, 0, width as i32, height as i32);
+            gl::ClearColor(0.0, 0.0, 0.0, 1.0);
+            gl::Clear(gl::COLOR_BUFFER_BIT);
+
+            gl::Disable(gl::BLEND);
+            gl::UseProgram(self.program);
+            gl::BindVertexArray(self.vao);
+            for i in 0..self.layers.len() {
+                let layer = self.layers[i];
+                if layer.opacity <= 0.0 {
+                    continue;
+                }
+                let Some((texture, size)) = self.deck_texture(layer.deck_id) else {
+                    continue;
+                };
+                let (x0, y0, x1, y1) = fit_rect(size.0, size.1, width, height);
+
+                let next = 1 - current;
+                gl::BindFramebuffer(gl::FRAMEBUFFER, self.targets[next].framebuffer);
+                gl::ActiveTexture(gl::TEXTURE0);
+                gl::BindTexture(gl::TEXTURE_2D, self.targets[current].texture);
+                gl::ActiveTexture(gl::TEXTURE1);
+                gl::BindTexture(gl::TEXTURE_2D, texture);
+                gl::Uniform1f(self.opacity_location, layer.opacity.clamp(0.0, 1.0));
+                gl::Uniform1i(self.mode_location, shader_mode(layer.blend_mode));
+                gl::Uniform4f(self.rect_location, x0, y0, x1, y1);
+                gl::DrawArrays(gl::TRIANGLE_STRIP, 0, 4);
+                current = next;
+            }
+            gl::BindVertexArray(0);
+            gl::UseProgram(0);
+            gl::ActiveTexture(gl::TEXTURE1);
+            gl::BindTexture(gl::TEXTURE_2D, 0);
+            gl::ActiveTexture(gl::TEXTURE0);
+            gl::BindTexture(gl::TEXTURE_2D, 0);
+
+            // Show the result in the window
+            let (w, h) = (width as i32, height as i32);
+            gl::BindFramebuffer(gl::READ_FRAMEBUFFER, self.targets[current].framebuffer);
+            gl::BindFramebuffer(gl::DRAW_FRAMEBUFFER, output as u32);
+            gl::BlitFramebuffer(0, 0, w, h, 0, 0, w, h, gl::COLOR_BUFFER_BIT, gl::NEAREST);
+            gl::BindFramebuffer(gl::FRAMEBUFFER, output as u32);
+        }
+    }
+
+    /// Texture with the deck's latest frame and its size, or `None` while
+    /// the deck isn't streaming
+    fn deck_texture(&mut self, deck_id: u8) -> Option<(u32, (u32, u32))> {
+        if let Some(shared) = self
+            .importer
+            .as_mut()
+            .and_then(|importer| importer.texture(&self.receiver, deck_id))
+        {
+            return Some(shared);
+        }
+        self.update_deck_texture(deck_id)
+    }
+
+    /// Upload the deck's latest frame if it's new
+    fn update_deck_texture(&mut self, deck_id: u8) -> Option<(u32, (u32, u32))> {
+        let deck = self.decks.entry(deck_id).or_insert_with(|| {
+            let mut texture = 0;
+            unsafe {
+                gl::GenTextures(1, &mut texture);
+                gl::BindTexture(gl::TEXTURE_2D, texture);
+                gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_MIN_FILTER, gl::LINEAR as i32);
+                gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_MAG_FILTER, gl::LINEAR as i32);
+                gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_WRAP_S, gl::CLAMP_TO_EDGE as i32);
+                gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_WRAP_T, gl::CLAMP_TO_EDGE as i32);
+            }
+            DeckTexture {
+                texture,
+                size: (0, 0),
+            }
+        });
+
+        let connected = self.receiver.with_frame(deck_id, |frame, fresh| {
+            let size = (frame.width, frame.height);
+            if !fresh && size == deck.size {
+                return;
+            }
+            unsafe {
+                gl::BindTexture(gl::TEXTURE_2D, deck.texture);
+                gl::PixelStorei(gl::UNPACK_ALIGNMENT, 4);
+                if size != deck.size {
+                    gl::TexImage2D(
+                        gl::TEXTURE_2D,
+                        0,
+                        gl::RGBA8 as i32,
+                        frame.width as i32,
+                        frame.height as i32,
+                        0,
+                        gl::RGBA,
+                        gl::UNSIGNED_BYTE,
+                        frame.pixels.as_ptr() as *const _,
+                    );
+                } else {
+                    gl::TexSubImage2D(
+                        gl::TEXTURE_2D,
+                        0,
+                        0,
+                        0,
+                        frame.width as i32,
+                        frame.height as i32,
+                        gl::RGBA,
+                        gl::UNSIGNED_BYTE,
+                        frame.pixels.as_ptr() as *const _,
+                    );
+                }
+            }
+            deck.size = size;
+        });
+        (connected && deck.size != (0, 0)).then_some((deck.texture, deck.size))
+    }
+
+    unsafe fn resize_targets(&mut self, width: u32, height: u32) {
+        self.delete_targets();
+        for _ in 0..2 {
+            let mut target = RenderTarget {
+                framebuffer: 0,
+                texture: 0,
+            };
+            gl::GenTextures(1, &mut target.texture);
+            gl::BindTexture(gl::TEXTURE_2D, target.texture);
+            gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_MIN_FILTER, gl::NEAREST as i32);
+            gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_MAG_FILTER, gl::NEAREST as i32);
+            gl::TexImage2D(
+                gl::TEXTURE_2D,
+                0,
+                gl::RGBA8 as i32,
+                width as i32,
+                height as i32,
+                0,
+                gl::RGBA,
+                gl::UNSIGNED_BYTE,
+                std::ptr::null(),
+            );
+            gl::GenFramebuffers(1, &mut target.framebuffer);
+            gl::BindFramebuffer(gl::FRAMEBUFFER, target.framebuffer);
+            gl::FramebufferTexture2D(
+                gl::FRAMEBUFFER,
+                gl::COLOR_ATTACHMENT0,
+                gl::TEXTURE_2D,
+                target.texture,
+                0,
+            );
+            self.targets.push(target);
+        }
+        gl::BindFramebuffer(gl::FRAMEBUFFER, 0);
+        gl::BindTexture(gl::TEXTURE_2D, 0);
+        self.target_size = (width, height);
+    }
+
+    unsafe fn delete_targets(&mut self) {
+        for target in self.targets.drain(..) {
+            gl::DeleteFramebuffers(1, &target.framebuffer);
+            gl::DeleteTextures(1, &target.texture);
+        }
+        self.target_size = (0, 0);
+    }
+}
+
+impl Drop for Compositor {
+    fn drop(&mut self) {
+        unsafe {
+            self.delete_targets();
+            for deck in self.decks.values() {
+                gl::DeleteTextures(1, &deck.texture);
+            }
+            gl::DeleteBuffers(1, &self.vbo);
+            gl::DeleteVertexArrays(1, &self.vao);
+            gl::DeleteProgram(self.program);
+        }
+    }
+}
@@ -0,0 +1,218 @@
+//! GPU post-processing over the visualization
+//!
+//! The frame is copied to a texture and redrawn through one shader that
+//! applies every effect in the chain; effects at intensity 0 cost a branch.
+
+use std::ffi::CString;
+
+use opendrop_core::video::{Effect, EffectChain};
+
+use crate::shader::{link_program, GlStateGuard};
+
+const VERTEX_SHADER: &str = r#"#version 330 core
+layout(location = 0) in vec2 position;
+out vec2 uv;
+void main() {
+    // Copied from the framebuffer, so bottom row first like the quad
+    uv = position * 0.5 + 0.5;
+    gl_Position = vec4(position, 0.0, 1.0);
+}
+"#;
+
+/// Uniforms are named after the effects; sizes are relative to the frame
+/// height so effects look the same at any resolution
+const FRAGMENT_SHADER: &str = r#"#version 330 core
+in vec2 uv;
+out vec4 color;
+uniform sampler2D frame;
+uniform float aspect;
+uniform float pixelate;
+uniform float kaleidoscope;
+uniform float rgb_shift;
+uniform float chromatic_aberration;
+uniform float bloom;
+
+vec2 warp(vec2 p) {
+    if (pixelate > 0.0) {
+        // Blocks from 0.4% to 6% of the height
+        vec2 block = vec2(1.0 / aspect, 1.0) * mix(0.004, 0.06, pixelate);
+        p = (floor(p / block) + 0.5) * block;
+    }
+    if (kaleidoscope > 0.0) {
+        // 2 to 12 mirrored segments
+        float segment = 6.28318531 / floor(mix(2.0, 12.0, kaleidoscope) + 0.5);
+        vec2 d = (p - 0.5) * vec2(aspect, 1.0);
+        float angle = mod(atan(d.y, d.x), segment);
+        angle = min(angle, segment - angle);
+        p = vec2(cos(angle), sin(angle)) * length(d) / vec2(aspect, 1.0) + 0.5;
+    }
+    return p;
+}
+
+void main() {
+    vec2 p = warp(uv);
+    vec2 shift = vec2(rgb_shift * 0.02, 0.0);
+    vec2 fringe = (p - 0.5) * chromatic_aberration * 0.03;
+    vec4 centre = texture(frame, p);
+    vec3 rgb = vec3(
+        texture(frame, p + shift + fringe).r,
+        centre.g,
+        texture(frame, p - shift - fringe).b
+    );
+    if (bloom > 0.0) {
+        // Bright parts of a two-ring blur, 1% and 2% of the height out
+        vec3 glow = vec3(0.0);
+        for (int i = 0; i < 16; i++) {
+            float angle = float(i) * 0.39269908;
+            float radius = (i % 2 == 0) ? 0.01 : 0.02;
+            vec2 offset = vec2(cos(angle) / aspect, sin(angle)) * radius;
+            glow += max(texture(frame, p + offset).rgb - 0.6, 0.0);
+        }
+        rgb += glow * 0.25 * bloom;
+    }
+    color = vec4(rgb, centre.a);
+}
+"#;
+
+/// Post-processing chain for one deck
+pub struct PostEffects {
+    chain: EffectChain,
+    program: u32,
+    vao: u32,
+    vbo: u32,
+    texture: u32,
+    /// Size the texture was allocated for
+    size: (u32, u32),
+    aspect_location: i32,
+    /// Intensity uniforms, in [`Effect::ALL`] order
+    effect_locations: [i32; Effect::ALL.len()],
+}
+
+impl PostEffects {
+    /// Set up GL resources on the current context
+    pub fn new() -> Result<Self, String> {
+        let program = unsafe { link_program("Post effects", VERTEX_SHADER, FRAGMENT_SHADER)? };
+        let mut effects = Self {
+            chain: EffectChain::default(),
+            program,
+            vao: 0,
+            vbo: 0,
+            texture: 0,
+            size: (0, 0),
+            aspect_location: -1,
+            effect_locations: [-1; Effect::ALL.len()],
+        };
+
+        #[rustfmt::skip]
+        let vertices: [f32; 8] = [
+            -1.0, -1.0,
+             1.0, -1.0,
+            -1.0,  1.0,
+             1.0,  1.0,
+        ];
+        unsafe {
+            effects.aspect_location = gl::GetUniformLocation(program, c"aspect".as_ptr());
+            for effect in Effect::ALL {
+                let name = CString::new(effect.to_string()).expect("effect names have no NUL");
+                effects.effect_locations[effect.index()] =
+                    gl::GetUniformLocation(program, name.as_ptr());
+            }
+
+            gl::GenVertexArrays(1, &mut effects.vao);
+            gl::GenBuffers(1, &mut effects.vbo);
+            gl::BindVertexArray(effects.vao);
+            gl::BindBuffer(gl::ARRAY_BUFFER, effects.vbo);
+            gl::BufferData(
+                gl::ARRAY_BUFFER,
+                std::mem::size_of_val(&vertices) as isize,
+                vertices.as_ptr() as *const _,
+                gl::STATIC_DRAW,
+            );
+            let stride = (2 * std::mem::size_of::<f32>()) as i32;
+            gl::VertexAttribPointer(0, 2, gl::FLOAT, gl::FALSE, stride, std::ptr::null());
+            gl::EnableVertexAttribArray(0);
+            gl::BindVertexArray(0);
+            gl::BindBuffer(gl::ARRAY_BUFFER, 0);
+
+            gl::GenTextures(1, &mut effects.texture);
+            gl::BindTexture(gl::TEXTURE_2D, effects.texture);
+            // Linear, so sub-pixel channel shifts stay smooth
+            gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_MIN_FILTER, gl::LINEAR as i32);
+            gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_MAG_FILTER, gl::LINEAR as i32);
+            gl::TexParameteri(
+                gl::TEXTURE_2D,
+                gl::TEXTURE_WRAP_S,
+                gl::MIRRORED_REPEAT as i32,
+            );
+            gl::TexParameteri(
+                gl::TEXTURE_2D,
+                gl::TEXTURE_WRAP_T,
+                gl::MIRRORED_REPEAT as i32,
+            );
+            gl::BindTexture(gl::TEXTURE_2D, 0);
+        }
+
+        Ok(effects)
+    }
+
+    /// Set one effect's intensity (0 turns it off)
+    pub fn set(&mut self, effect: Effect, intensity: f32) {
+        self.chain.set(effect, intensity);
+    }
+
+    /// Whether every effect is off
+    pub fn is_empty(&self) -> bool {
+        self.chain.is_empty()
+    }
+
+    /// Run the chain over a `width`x`height` framebuffer
+    pub fn draw(&mut self, width: u32, height: u32) {
+        if width == 0 || height == 0 || self.chain.is_empty() {
+            return;
+        }
+
+        unsafe {
+            let _state = GlStateGuard::save();
+
+            gl::BindTexture(gl::TEXTURE_2D, self.texture);
+            if self.size != (width, height) {
+                gl::TexImage2D(
+                    gl::TEXTURE_2D,
+                    0,
+                    gl::RGBA8 as i32,
+                    width as i32,
+                    height as i32,
+                    0,
+                    gl::RGBA,
+                    gl::UNSIGNED_BYTE,
+                    std::ptr::null(),
+                );
+                self.size = (width, height);
+            }
+            gl::CopyTexSubImage2D(gl::TEXTURE_2D, 0, 0, 0, 0, 0, width as i32, height as i32);
+
+            gl::Disable(gl::BLEND);
+            gl::UseProgram(self.program);
+            gl::Uniform1f(self.aspect_location, width as f32 / height as f32);
+            for effect in Effect::ALL {
+                gl::Uniform1f(
+                    self.effect_locations[effect.index()],
+                    self.chain.get(effect),
+                );
+            }
+            gl::BindVertexArray(self.vao);
+            gl::DrawArrays(gl::TRIANGLE_STRIP, 0, 4);
+        }
+    }
+}
+
+impl Drop for PostEffects {
+    fn drop(&mut self) {
+        unsafe {
+            gl::DeleteTextures(1, &self.texture);
+            gl::DeleteBuffers(1, &self.vbo);
+            gl::DeleteVertexArrays(1, &self.vao);
+            gl::DeleteProgram(self.program);
+        }
+    }
+}
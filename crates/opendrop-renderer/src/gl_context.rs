@@ -0,0 +1,69 @@
+//! OpenGL context creation with an OpenGL ES fallback
+//!
+//! Desktop GPUs get an OpenGL 3.3 core context. Drivers that can't make one,
+//! such as the Raspberry Pi 4/5's V3D (OpenGL 3.1), get OpenGL ES 3.0
+//! instead; the shaders are rewritten for it when they are compiled.
+
+use glutin::context::{ContextApi, ContextAttributesBuilder, Version};
+use glutin::display::GlDisplay;
+use raw_window_handle::RawWindowHandle;
+use tracing::{info, warn};
+
+/// Context versions to try, in order
+const CONTEXT_APIS: [ContextApi; 2] = [
+    ContextApi::OpenGl(Some(Version::new(3, 3))),
+    ContextApi::Gles(Some(Version::new(3, 0))),
+];
+
+/// Create a context for `config`, falling back to OpenGL ES when the driver
+/// has no OpenGL 3.3
+pub fn create_context<D: GlDisplay>(
+    display: &D,
+    config: &D::Config,
+    window: Option<RawWindowHandle>,
+) -> Result<D::NotCurrentContext, String> {
+    let mut last_error = String::new();
+    for api in CONTEXT_APIS {
+        let attrs = ContextAttributesBuilder::new()
+            .with_context_api(api)
+            .build(window);
+        match unsafe { display.create_context(config, &attrs) } {
+            Ok(context) => {
+                info!("Created {:?} context", api);
+                return Ok(context);
+            }
+            Err(e) => {
+                warn!("Can't create {:?} context: {}", api, e);
+                last_error = e.to_string();
+            }
+        }
+    }
+    Err(format!("Failed to create OpenGL context: {}", last_error))
+}
+
+/// Whether the current context is OpenGL ES
+pub fn is_gles() -> bool {
+    unsafe {
+        let version = gl::GetString(gl::VERSION);
+        !version.is_null()
+            && std::ffi::CStr::from_ptr(version as *const _)
+                .to_bytes()
+                .starts_with(b"OpenGL ES")
+    }
+}
+
+/// Warn about functions the driver didn't provide; they would crash when
+/// called rather than fail
+pub fn check_loaded() {
+    let required = [
+        ("glGenVertexArrays", gl::GenVertexArrays::is_loaded()),
+        ("glBlitFramebuffer", gl::BlitFramebuffer::is_loaded()),
+        ("glMapBufferRange", gl::MapBufferRange::is_loaded()),
+        ("glFenceSync", gl::FenceSync::is_loaded()),
+    ];
+    for (name, loaded) in required {
+        if !loaded {
+            warn!("{} is not available on this driver", name);
+        }
+    }
+}
@@ -0,0 +1,195 @@
+//! Rendering without a window
+//!
+//! Server installations often have no display to open a window on. With
+//! `headless: true` the renderer creates an EGL context straight on a GPU
+//! device (`EGL_EXT_platform_device`) with no surface, and draws every frame
+//! into an [`Offscreen`] framebuffer that outputs and recordings read from.
+
+use glutin::context::PossiblyCurrentContext;
+use glutin::display::Display;
+
+/// Frame rate without a window's vsync to pace frames
+pub const HEADLESS_FPS: u32 = 60;
+
+/// Framebuffer object standing in for the window; also holds projectM's
+/// render when it is drawn below the frame size
+pub struct Offscreen {
+    framebuffer: u32,
+    color: u32,
+    depth_stencil: u32,
+    size: (u32, u32),
+}
+
+impl Offscreen {
+    /// Create a `width`x`height` framebuffer on the current context and bind it
+    pub fn new(width: u32, height: u32) -> Result<Self, String> {
+        let mut offscreen = Self {
+            framebuffer: 0,
+            color: 0,
+            depth_stencil: 0,
+            size: (width, height),
+        };
+        let (w, h) = (width as i32, height as i32);
+
+        unsafe {
+            gl::GenRenderbuffers(1, &mut offscreen.color);
+            gl::BindRenderbuffer(gl::RENDERBUFFER, offscreen.color);
+            gl::RenderbufferStorage(gl::RENDERBUFFER, gl::RGBA8, w, h);
+            // projectM presets can use depth and stencil like on a window
+            gl::GenRenderbuffers(1, &mut offscreen.depth_stencil);
+            gl::BindRenderbuffer(gl::RENDERBUFFER, offscreen.depth_stencil);
+            gl::RenderbufferStorage(gl::RENDERBUFFER, gl::DEPTH24_STENCIL8, w, h);
+            gl::BindRenderbuffer(gl::RENDERBUFFER, 0);
+
+            gl::GenFramebuffers(1, &mut offscreen.framebuffer);
+            gl::BindFramebuffer(gl::FRAMEBUFFER, offscreen.framebuffer);
+            gl::FramebufferRenderbuffer(
+                gl::FRAMEBUFFER,
+                gl::COLOR_ATTACHMENT0,
+                gl::RENDERBUFFER,
+                offscreen.color,
+            );
+            gl::FramebufferRenderbuffer(
+                gl::FRAMEBUFFER,
+                gl::DEPTH_STENCIL_ATTACHMENT,
+                gl::RENDERBUFFER,
+                offscreen.depth_stencil,
+            );
+            let status = gl::CheckFramebufferStatus(gl::FRAMEBUFFER);
+            if status != gl::FRAMEBUFFER_COMPLETE {
+                gl::BindFramebuffer(gl::FRAMEBUFFER, 0);
+                return Err(format!("Offscreen framebuffer incomplete (0x{:x})", status));
+            }
+            gl::Viewport(0, 0, w, h);
+        }
+
+        Ok(offscreen)
+    }
+
+    /// Framebuffer object to render into
+    pub fn framebuffer(&self) -> u32 {
+        self.framebuffer
+    }
+
+    pub fn size(&self) -> (u32, u32) {
+        self.size
+    }
+
+    /// Bind for drawing and reading again, after anything bound its own
+    pub fn bind(&self) {
+        unsafe { gl::BindFramebuffer(gl::FRAMEBUFFER, self.framebuffer) };
+    }
+
+    /// Stretch the contents over the `width`x`height` framebuffer `output`
+    /// and leave that bound
+    pub fn blit_to(&self, output: u32, width: u32, height: u32) {
+        let (w, h) = (self.size.0 as i32, self.size.1 as i32);
+        unsafe {
+            gl::BindFramebuffer(gl::READ_FRAMEBUFFER, self.framebuffer);
+            gl::BindFramebuffer(gl::DRAW_FRAMEBUFFER, output);
+            gl::BlitFramebuffer(
+                0,
+                0,
+                w,
+                h,
+                0,
+                0,
+                width as i32,
+                height as i32,
+                gl::COLOR_BUFFER_BIT,
+                gl::LINEAR,
+            );
+            gl::BindFramebuffer(gl::FRAMEBUFFER, output);
+            gl::Viewport(0, 0, width as i32, height as i32);
+        }
+    }
+
+    /// Copy the `width`x`height` framebuffer `input` into this one,
+    /// stretched, and leave `input` bound
+    pub fn blit_from(&self, input: u32, width: u32, height: u32) {
+        let (w, h) = (self.size.0 as i32, self.size.1 as i32);
+        unsafe {
+            gl::BindFramebuffer(gl::READ_FRAMEBUFFER, input);
+            gl::BindFramebuffer(gl::DRAW_FRAMEBUFFER, self.framebuffer);
+            gl::BlitFramebuffer(
+                0,
+                0,
+                width as i32,
+                height as i32,
+                0,
+                0,
+                w,
+                h,
+                gl::COLOR_BUFFER_BIT,
+                gl::LINEAR,
+            );
+            gl::BindFramebuffer(gl::FRAMEBUFFER, input);
+            gl::Viewport(0, 0, width as i32, height as i32);
+        }
+    }
+}
+
+impl Drop for Offscreen {
+    fn drop(&mut self) {
+        unsafe {
+            gl::DeleteFramebuffers(1, &self.framebuffer);
+            gl::DeleteRenderbuffers(1, &self.depth_stencil);
+            gl::DeleteRenderbuffers(1, &self.color);
+        }
+    }
+}
+
+/// Create an OpenGL 3.3 (or OpenGL ES 3.0) context with no surface on the
+/// first GPU that can make one, and make it current
+#[cfg(target_os = "linux")]
+pub fn create_context() -> Result<(Display, PossiblyCurrentContext), String> {
+    use glutin::api::egl::device::Device;
+    use tracing::{info, warn};
+
+    let devices = Device::query_devices().map_err(|e| format!("No EGL devices: {}", e))?;
+    let mut last_error = "no EGL devices found".to_string();
+    for device in devices {
+        let name = device.name().unwrap_or("unknown device");
+        match context_on(&device) {
+            Ok(result) => {
+                info!("Rendering headless on EGL device {}", name);
+                return Ok(result);
+            }
+            Err(e) => {
+                warn!("Can't render headless on EGL device {}: {}", name, e);
+                last_error = e;
+            }
+        }
+    }
+    Err(format!("Headless rendering unavailable: {}", last_error))
+}
+
+#[cfg(target_os = "linux")]
+fn context_on(
+    device: &glutin::api::egl::device::Device,
+) -> Result<(Display, PossiblyCurrentContext), String> {
+    use glutin::api::egl::display::Display as EglDisplay;
+    use glutin::config::{Api, ConfigSurfaceTypes, ConfigTemplateBuilder};
+    use glutin::display::GlDisplay;
+
+    let display = unsafe { EglDisplay::with_device(device, None) }.map_err(|e| e.to_string())?;
+    let template = ConfigTemplateBuilder::new()
+        .with_api(Api::OPENGL | Api::GLES3)
+        .with_surface_type(ConfigSurfaceTypes::empty())
+        .build();
+    let config = unsafe { display.find_configs(template) }
+        .map_err(|e| e.to_string())?
+        .next()
+        .ok_or("no OpenGL config without a surface")?;
+    let context = crate::gl_context::create_context(&display, &config, None)?
+        .make_current_surfaceless()
+        .map_err(|e| e.to_string())?;
+
+    Ok((Display::Egl(display), PossiblyCurrentContext::Egl(context)))
+}
+
+/// Headless rendering needs EGL devices, which only Linux drivers offer
+#[cfg(not(target_os = "linux"))]
+pub fn create_context() -> Result<(Display, PossiblyCurrentContext), String> {
+    Err("Headless rendering is only supported on Linux".to_string())
+}
@@ -0,0 +1,162 @@
+//! Idle visual drawn while a deck has no preset
+//!
+//! A dark gradient with a row of spectrum bars along the bottom and the logo
+//! in the middle, all drawn by this module so it works with an empty preset
+//! folder. The bars breathe slightly with no audio, so the deck still looks
+//! alive while waiting for it.
+
+use std::time::Instant;
+
+use tracing::warn;
+
+use opendrop_core::video::{IdleBars, OverlayPosition, TextOverlayConfig, IDLE_BARS};
+
+use crate::shader::{link_program, GlStateGuard};
+use crate::text_layer::TextLayer;
+
+const VERTEX_SHADER: &str = r#"#version 330 core
+layout(location = 0) in vec2 position;
+out vec2 uv;
+void main() {
+    uv = position * 0.5 + 0.5;
+    gl_Position = vec4(position, 0.0, 1.0);
+}
+"#;
+
+/// Bar colours go round the hue circle from bass to treble
+const FRAGMENT_SHADER: &str = r#"#version 330 core
+in vec2 uv;
+out vec4 color;
+uniform float levels[32];
+uniform float time;
+void main() {
+    vec3 rgb = mix(vec3(0.10, 0.02, 0.16), vec3(0.02, 0.02, 0.08), uv.y);
+    rgb *= 0.85 + 0.15 * sin(time * 0.5);
+
+    float slot = uv.x * 32.0;
+    int bar = int(min(slot, 31.0));
+    float breathe = 0.02 + 0.015 * sin(time * 2.0 + float(bar) * 0.4);
+    float height = max(levels[bar], breathe) * 0.35;
+    float inside = step(0.15, fract(slot)) * step(fract(slot), 0.85);
+    float base = 0.08;
+    if (inside > 0.0 && uv.y > base && uv.y < base + height) {
+        vec3 hue = 0.5 + 0.5 * cos(6.2832 * (float(bar) / 32.0 + vec3(0.0, 0.33, 0.67)));
+        rgb = mix(rgb, hue, 0.35 + 0.65 * (uv.y - base) / 0.35);
+    }
+    color = vec4(rgb, 1.0);
+}
+"#;
+
+/// Logo and spectrum bars for one deck
+pub struct IdleLayer {
+    bars: IdleBars,
+    /// None when no font could be loaded; the bars are drawn regardless
+    logo: Option<TextLayer>,
+    started: Instant,
+    program: u32,
+    vao: u32,
+    vbo: u32,
+    levels_location: i32,
+    time_location: i32,
+}
+
+impl IdleLayer {
+    /// Set up GL resources on the current context
+    pub fn new() -> Result<Self, String> {
+        let program = unsafe { link_program("Idle", VERTEX_SHADER, FRAGMENT_SHADER)? };
+        let logo = TextLayer::new(TextOverlayConfig {
+            text: "OpenDrop".to_string(),
+            position: OverlayPosition::Center,
+            font_size: 0.12,
+            fade_in_secs: 1.0,
+            fade_out_secs: 0.0,
+            duration_secs: None,
+            font_path: None,
+        })
+        .map_err(|e| warn!("Idle visual without logo: {}", e))
+        .ok();
+        let mut layer = Self {
+            bars: IdleBars::new(),
+            logo,
+            started: Instant::now(),
+            program,
+            vao: 0,
+            vbo: 0,
+            levels_location: -1,
+            time_location: -1,
+        };
+
+        #[rustfmt::skip]
+        let vertices: [f32; 8] = [
+            -1.0, -1.0,
+             1.0, -1.0,
+            -1.0,  1.0,
+             1.0,  1.0,
+        ];
+        unsafe {
+            layer.levels_location = gl::GetUniformLocation(program, c"levels".as_ptr());
+            layer.time_location = gl::GetUniformLocation(program, c"time".as_ptr());
+
+            gl::GenVertexArrays(1, &mut layer.vao);
+            gl::GenBuffers(1, &mut layer.vbo);
+            gl::BindVertexArray(layer.vao);
+            gl::BindBuffer(gl::ARRAY_BUFFER, layer.vbo);
+            gl::BufferData(
+                gl::ARRAY_BUFFER,
+                std::mem::size_of_val(&vertices) as isize,
+                vertices.as_ptr() as *const _,
+                gl::STATIC_DRAW,
+            );
+            let stride = (2 * std::mem::size_of::<f32>()) as i32;
+            gl::VertexAttribPointer(0, 2, gl::FLOAT, gl::FALSE, stride, std::ptr::null());
+            gl::EnableVertexAttribArray(0);
+            gl::BindVertexArray(0);
+            gl::BindBuffer(gl::ARRAY_BUFFER, 0);
+        }
+
+        Ok(layer)
+    }
+
+    /// Feed the deck's audio to the bars
+    pub fn push_audio(&mut self, samples: &[f32], channels: u16) {
+        self.bars.push(samples, channels);
+    }
+
+    /// Fill the bound `width`x`height` framebuffer
+    pub fn draw(&mut self, width: u32, height: u32) {
+        if width == 0 || height == 0 {
+            return;
+        }
+        let now = Instant::now();
+        let levels = self.bars.update(now);
+
+        unsafe {
+            let _state = GlStateGuard::save();
+
+            gl::Viewport(0, 0, width as i32, height as i32);
+            gl::Disable(gl::BLEND);
+            gl::UseProgram(self.program);
+            gl::Uniform1fv(self.levels_location, IDLE_BARS as i32, levels.as_ptr());
+            gl::Uniform1f(
+                self.time_location,
+                now.saturating_duration_since(self.started).as_secs_f32(),
+            );
+            gl::BindVertexArray(self.vao);
+            gl::DrawArrays(gl::TRIANGLE_STRIP, 0, 4);
+        }
+
+        if let Some(ref mut logo) = self.logo {
+            logo.draw(width, height);
+        }
+    }
+}
+
+impl Drop for IdleLayer {
+    fn drop(&mut self) {
+        unsafe {
+            gl::DeleteBuffers(1, &self.vbo);
+            gl::DeleteVertexArrays(1, &self.vao);
+            gl::DeleteProgram(self.program);
+        }
+    }
+}
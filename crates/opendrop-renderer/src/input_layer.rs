@@ -0,0 +1,246 @@
+//! Input layer mixing a received video source into the visualization
+//!
+//! Each frame the latest picture from the input (Spout, NDI, screen capture)
+//! is uploaded to a texture and drawn over projectM's output, letterboxed to
+//! keep its aspect ratio, with fixed-function blending for the blend mode.
+//! Placed under the visualization, it is drawn the same way before the post
+//! effects; add, multiply and screen give the same result in either order,
+//! and the normal blend fills in by the frame's alpha instead.
+
+use opendrop_core::video::input::fit_rect;
+use opendrop_core::video::{InputBlend, InputPlacement, VideoInput};
+
+use crate::shader::{link_program, GlStateGuard};
+
+const VERTEX_SHADER: &str = r#"#version 330 core
+layout(location = 0) in vec2 position;
+layout(location = 1) in vec2 tex_coord;
+out vec2 uv;
+void main() {
+    uv = tex_coord;
+    gl_Position = vec4(position, 0.0, 1.0);
+}
+"#;
+
+/// `mode` 0 fades by alpha, 1 scales the colour for additive modes and
+/// normal under the visualization, and 2 fades towards white for multiply
+const FRAGMENT_SHADER: &str = r#"#version 330 core
+in vec2 uv;
+out vec4 color;
+uniform sampler2D frame;
+uniform float opacity;
+uniform int mode;
+void main() {
+    vec3 rgb = texture(frame, uv).rgb;
+    if (mode == 0) {
+        color = vec4(rgb, opacity);
+    } else if (mode == 2) {
+        color = vec4(mix(vec3(1.0), rgb, opacity), 1.0);
+    } else {
+        color = vec4(rgb * opacity, opacity);
+    }
+}
+"#;
+
+/// A video input drawn over or under the visualization
+pub struct InputLayer {
+    input: Box<dyn VideoInput>,
+    blend: InputBlend,
+    placement: InputPlacement,
+    opacity: f32,
+    program: u32,
+    vao: u32,
+    vbo: u32,
+    texture: u32,
+    /// Size of the last received frame, (0, 0) until one arrives
+    frame_size: (u32, u32),
+    opacity_location: i32,
+    mode_location: i32,
+}
+
+impl InputLayer {
+    /// Set up GL resources on the current context
+    pub fn new(
+        input: Box<dyn VideoInput>,
+        blend: InputBlend,
+        placement: InputPlacement,
+        opacity: f32,
+    ) -> Result<Self, String> {
+        let program = unsafe { link_program("Input layer", VERTEX_SHADER, FRAGMENT_SHADER)? };
+        let mut layer = Self {
+            input,
+            blend,
+            placement,
+            opacity: opacity.clamp(0.0, 1.0),
+            program,
+            vao: 0,
+            vbo: 0,
+            texture: 0,
+            frame_size: (0, 0),
+            opacity_location: -1,
+            mode_location: -1,
+        };
+
+        unsafe {
+            layer.opacity_location = gl::GetUniformLocation(program, c"opacity".as_ptr());
+            layer.mode_location = gl::GetUniformLocation(program, c"mode".as_ptr());
+
+            gl::GenVertexArrays(1, &mut layer.vao);
+            gl::GenBuffers(1, &mut layer.vbo);
+            gl::BindVertexArray(layer.vao);
+            gl::BindBuffer(gl::ARRAY_BUFFER, layer.vbo);
+            gl::BufferData(
+                gl::ARRAY_BUFFER,
+                (16 * std::mem::size_of::<f32>()) as isize,
+                std::ptr::null(),
+                gl::DYNAMIC_DRAW,
+            );
+            let stride = (4 * std::mem::size_of::<f32>()) as i32;
+            gl::VertexAttribPointer(0, 2, gl::FLOAT, gl::FALSE, stride, std::ptr::null());
+            gl::EnableVertexAttribArray(0);
+            gl::VertexAttribPointer(
+                1,
+                2,
+                gl::FLOAT,
+                gl::FALSE,
+                stride,
+                (2 * std::mem::size_of::<f32>()) as *const _,
+            );
+            gl::EnableVertexAttribArray(1);
+            gl::BindVertexArray(0);
+            gl::BindBuffer(gl::ARRAY_BUFFER, 0);
+
+            gl::GenTextures(1, &mut layer.texture);
+        }
+
+        Ok(layer)
+    }
+
+    /// Name of the source being received
+    pub fn source_name(&self) -> &str {
+        self.input.source_name()
+    }
+
+    pub fn placement(&self) -> InputPlacement {
+        self.placement
+    }
+
+    /// Move the layer over or under the visualization
+    pub fn set_placement(&mut self, placement: InputPlacement) {
+        self.placement = placement;
+    }
+
+    /// Change how the layer is mixed in
+    pub fn set_mix(&mut self, blend: InputBlend, opacity: f32) {
+        self.blend = blend;
+        self.opacity = opacity.clamp(0.0, 1.0);
+    }
+
+    /// Take the latest frame from the input and draw the layer over a
+    /// `width`x`height` framebuffer
+    pub fn draw(&mut self, width: u32, height: u32) -> Result<(), String> {
+        if let Some(frame) = self.input.receive_frame().map_err(|e| e.to_string())? {
+            let size = (frame.width, frame.height);
+            unsafe {
+                let mut previous = 0;
+                gl::GetIntegerv(gl::TEXTURE_BINDING_2D, &mut previous);
+                gl::BindTexture(gl::TEXTURE_2D, self.texture);
+                gl::PixelStorei(gl::UNPACK_ALIGNMENT, 4);
+                if size != self.frame_size {
+                    gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_MIN_FILTER, gl::LINEAR as i32);
+                    gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_MAG_FILTER, gl::LINEAR as i32);
+                    gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_WRAP_S, gl::CLAMP_TO_EDGE as i32);
+                    gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_WRAP_T, gl::CLAMP_TO_EDGE as i32);
+                    gl::TexImage2D(
+                        gl::TEXTURE_2D,
+                        0,
+                        gl::RGBA8 as i32,
+                        frame.width as i32,
+                        frame.height as i32,
+                        0,
+                        gl::RGBA,
+                        gl::UNSIGNED_BYTE,
+                        frame.pixels.as_ptr() as *const _,
+                    );
+                } else {
+                    gl::TexSubImage2D(
+                        gl::TEXTURE_2D,
+                        0,
+                        0,
+                        0,
+                        frame.width as i32,
+                        frame.height as i32,
+                        gl::RGBA,
+                        gl::UNSIGNED_BYTE,
+                        frame.pixels.as_ptr() as *const _,
+                    );
+                }
+                gl::BindTexture(gl::TEXTURE_2D, previous as u32);
+            }
+            self.frame_size = size;
+        }
+
+        if self.frame_size == (0, 0) || self.opacity <= 0.0 {
+            return Ok(());
+        }
+
+        // Frames are top row first, so the top of the quad samples v = 0
+        let (x0, y0, x1, y1) = fit_rect(self.frame_size.0, self.frame_size.1, width, height);
+        #[rustfmt::skip]
+        let vertices: [f32; 16] = [
+            x0, y0, 0.0, 1.0,
+            x1, y0, 1.0, 1.0,
+            x0, y1, 0.0, 0.0,
+            x1, y1, 1.0, 0.0,
+        ];
+
+        let (mode, src, dst, src_alpha) = match (self.blend, self.placement) {
+            (InputBlend::Normal, InputPlacement::Over) => {
+                (0, gl::SRC_ALPHA, gl::ONE_MINUS_SRC_ALPHA, gl::ZERO)
+            }
+            (InputBlend::Normal, InputPlacement::Under) => {
+                (1, gl::ONE_MINUS_DST_ALPHA, gl::ONE, gl::ONE_MINUS_DST_ALPHA)
+            }
+            (InputBlend::Add, _) => (1, gl::ONE, gl::ONE, gl::ZERO),
+            (InputBlend::Screen, _) => (1, gl::ONE_MINUS_DST_COLOR, gl::ONE, gl::ZERO),
+            (InputBlend::Multiply, _) => (2, gl::DST_COLOR, gl::ZERO, gl::ZERO),
+        };
+
+        unsafe {
+            let _state = GlStateGuard::save();
+
+            gl::BindBuffer(gl::ARRAY_BUFFER, self.vbo);
+            gl::BufferSubData(
+                gl::ARRAY_BUFFER,
+                0,
+                std::mem::size_of_val(&vertices) as isize,
+                vertices.as_ptr() as *const _,
+            );
+            gl::BindBuffer(gl::ARRAY_BUFFER, 0);
+
+            gl::Enable(gl::BLEND);
+            // Keep the visualization's alpha for captured frames, except
+            // where the input fills in underneath it
+            gl::BlendFuncSeparate(src, dst, src_alpha, gl::ONE);
+            gl::UseProgram(self.program);
+            gl::Uniform1f(self.opacity_location, self.opacity);
+            gl::Uniform1i(self.mode_location, mode);
+            gl::BindTexture(gl::TEXTURE_2D, self.texture);
+            gl::BindVertexArray(self.vao);
+            gl::DrawArrays(gl::TRIANGLE_STRIP, 0, 4);
+        }
+
+        Ok(())
+    }
+}
+
+impl Drop for InputLayer {
+    fn drop(&mut self) {
+        unsafe {
+            gl::DeleteTextures(1, &self.texture);
+            gl::DeleteBuffers(1, &self.vbo);
+            gl::DeleteVertexArrays(1, &self.vao);
+            gl::DeleteProgram(self.program);
+        }
+    }
+}
@@ -9,45 +9,195 @@ use std::num::NonZeroU32;
 use std::path::PathBuf;
 use std::sync::mpsc::{self, Receiver, Sender, TryRecvError};
 use std::thread;
+use std::time::{Duration, Instant};
 
 use glutin::config::{ConfigTemplateBuilder, GlConfig};
-use glutin::context::{ContextApi, ContextAttributesBuilder, PossiblyCurrentContext, Version};
-use glutin::display::GetGlDisplay;
+use glutin::context::PossiblyCurrentContext;
+use glutin::display::{Display, GetGlDisplay};
 use glutin::prelude::*;
 use glutin::surface::{Surface, SurfaceAttributesBuilder, SwapInterval, WindowSurface};
 use glutin_winit::{DisplayBuilder, GlWindow};
+#[cfg(target_os = "linux")]
+use glutin_winit::ApiPreference;
 use raw_window_handle::HasWindowHandle;
+use serde::de::DeserializeOwned;
 use serde::{Deserialize, Serialize};
 use tracing::{debug, error, info, warn};
 use winit::application::ApplicationHandler;
-use winit::dpi::{LogicalSize, PhysicalSize};
-use winit::event::{ElementState, KeyEvent, WindowEvent};
+use winit::dpi::{LogicalPosition, LogicalSize, PhysicalSize};
+use winit::event::{ElementState, KeyEvent, MouseButton, WindowEvent};
 use winit::event_loop::{ActiveEventLoop, ControlFlow, EventLoop};
 use winit::keyboard::{Key, NamedKey};
-use winit::window::{Window, WindowAttributes, WindowId};
+use winit::monitor::MonitorHandle;
+use winit::window::{Fullscreen, Window, WindowAttributes, WindowId, WindowLevel};
+
+use projectm_rs::{Channels, ProjectM, TouchType};
+
+mod background_layer;
+use background_layer::BackgroundLayer;
+
+mod color_adjust;
+use color_adjust::ColorPass;
+
+mod compositor;
+use compositor::Compositor;
+
+mod effects;
+use effects::PostEffects;
+
+mod gl_context;
+
+mod headless;
+use headless::{Offscreen, HEADLESS_FPS};
+
+mod idle_layer;
+use idle_layer::IdleLayer;
+
+mod input_layer;
+use input_layer::InputLayer;
+
+mod multi_deck;
+
+mod preset_watcher;
+use preset_watcher::PresetWatcher;
+
+mod readback;
+use readback::FrameReadback;
+
+mod shader;
+
+mod shared_texture;
+use shared_texture::{Egl, SharedTextureFeed};
+
+mod strobe_layer;
+use strobe_layer::StrobeLayer;
 
-use projectm_rs::ProjectM;
+mod test_pattern;
+use test_pattern::TestPattern;
+
+mod text_layer;
+use text_layer::TextLayer;
+
+mod thumbnailer;
+
+mod transition;
+use transition::{PresetTransition, CUT_REQUEST_TIMEOUT, DEFAULT_TRANSITION_SECS, MAX_TRANSITION_SECS};
 
 // Video output support
+use opendrop_core::video::{OutputBackend, PixelFormat, VideoOutput};
+use opendrop_core::video::output::key_alpha_from_luma;
+use opendrop_core::video::recorder::FramePacer;
+
 #[cfg(target_os = "linux")]
-use opendrop_core::video::{V4l2Config, V4l2Output, VideoOutput};
+use opendrop_core::video::{V4l2Config, V4l2Output};
 
 #[cfg(target_os = "windows")]
-use opendrop_core::video::{SpoutConfig, SpoutOutput, VideoOutput};
+use opendrop_core::video::{SpoutConfig, SpoutOutput};
 
 // NDI output (cross-platform)
-use opendrop_core::video::{NdiConfig, NdiOutput};
+use opendrop_core::video::{NdiConfig, NdiMetadata, NdiOutput};
+
+// Syphon output (macOS; fails with an error elsewhere)
+use opendrop_core::video::{SyphonConfig, SyphonOutput};
+
+// Virtual webcam (Windows softcam; fails with an error elsewhere)
+use opendrop_core::video::{VirtualCameraConfig, VirtualCameraOutput};
+
+// MP4 recording (through ffmpeg)
+use opendrop_core::video::{RecordingCodec, RecordingConfig, VideoRecorder};
+
+// PNG image-sequence export
+use opendrop_core::video::sequence::DEFAULT_SEQUENCE_FPS;
+use opendrop_core::video::ImageSequenceExporter;
+
+// Seamless loop export (WebM / GIF through ffmpeg)
+use opendrop_core::video::{LoopExportConfig, LoopFormat, LoopRecorder};
+
+// Video input layer (Spout / NDI receivers)
+use opendrop_core::video::{InputBackend, InputBlend, InputPlacement};
+
+// Compositor (deck frames streamed to the compositor renderer)
+use opendrop_core::video::{CompositeLayer, CompositorFeed};
+
+// Logo overlay on output frames
+use opendrop_core::video::{LogoOverlay, OverlayConfig};
+
+// Post-processing effects
+use opendrop_core::video::Effect;
+
+// Text overlay
+use opendrop_core::video::TextOverlayConfig;
+
+// Performance stats and HUD
+use opendrop_core::video::{FrameTimer, OverlayPosition, RenderStats};
+
+// Background image or video
+use opendrop_core::video::BackgroundConfig;
+
+// Exclusive fullscreen
+use opendrop_core::video::{DisplayMode, ExclusiveMode};
+
+// Colour correction
+use opendrop_core::video::color_adjust::MAX_COLOR_FADE_SECS;
+use opendrop_core::video::{ColorAdjust, ColorFade};
+
+// GPU reporting
+use opendrop_core::video::GpuInfo;
+
+// Strobe and blackout
+use opendrop_core::video::strobe::MAX_BLACKOUT_FADE_SECS;
+use opendrop_core::video::{Blackout, Strobe, StrobeConfig};
+
+// Playback speed
+use opendrop_core::video::time_scale::validate_time_scale;
+use opendrop_core::video::PresetClock;
+
+// projectM's own preset switches
+use opendrop_core::video::PresetCuts;
+
+// projectM quality
+use opendrop_core::video::adaptive::scale_size;
+use opendrop_core::video::{AdaptiveScale, RenderQuality};
 
 /// Commands received from the parent process via stdin
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Clone, Deserialize)]
 #[serde(tag = "type")]
 enum Command {
     #[serde(rename = "load_preset")]
     LoadPreset { path: String },
+    /// Crossfade length for later preset loads; 0 cuts
+    #[serde(rename = "set_transition_time")]
+    SetTransitionTime { seconds: f64 },
+    /// When projectM asks for the next preset, and how that switch looks
+    #[serde(rename = "set_preset_cuts")]
+    SetPresetCuts { cuts: PresetCuts },
     #[serde(rename = "audio")]
-    Audio { samples: Vec<f32> },
+    Audio {
+        samples: Vec<f32>,
+        /// Interleaved channels in `samples` (1 or 2)
+        #[serde(default = "default_audio_channels")]
+        channels: usize,
+    },
     #[serde(rename = "toggle_fullscreen")]
     ToggleFullscreen,
+    /// Keep the window above other apps' windows
+    #[serde(rename = "set_always_on_top")]
+    SetAlwaysOnTop { enabled: bool },
+    /// Let clicks pass through the window to whatever is below
+    #[serde(rename = "set_click_through")]
+    SetClickThrough { enabled: bool },
+    /// Move and/or resize the window, in logical pixels
+    #[serde(rename = "set_window_geometry")]
+    SetWindowGeometry {
+        #[serde(default)]
+        x: Option<i32>,
+        #[serde(default)]
+        y: Option<i32>,
+        #[serde(default)]
+        width: Option<u32>,
+        #[serde(default)]
+        height: Option<u32>,
+    },
     #[serde(rename = "set_beat_sensitivity")]
     SetBeatSensitivity { value: f32 },
     #[serde(rename = "set_video_output")]
@@ -55,19 +205,216 @@ enum Command {
         enabled: bool,
         #[serde(default)]
         device_path: Option<String>,
+        #[serde(default)]
+        pixel_format: Option<PixelFormat>,
     },
     #[serde(rename = "set_ndi_output")]
     SetNdiOutput {
         enabled: bool,
         #[serde(default)]
         name: Option<String>,
+        #[serde(default)]
+        groups: Option<String>,
+        #[serde(default)]
+        failover: Option<String>,
+        #[serde(default)]
+        clock_video: Option<bool>,
+        #[serde(default)]
+        pixel_format: Option<PixelFormat>,
+    },
+    #[serde(rename = "set_ndi_metadata")]
+    SetNdiMetadata {
+        #[serde(default)]
+        bpm: Option<f32>,
+    },
+    #[serde(rename = "set_syphon_output")]
+    SetSyphonOutput {
+        enabled: bool,
+        #[serde(default)]
+        name: Option<String>,
+    },
+    #[serde(rename = "set_output_fps")]
+    SetOutputFps {
+        output: OutputBackend,
+        #[serde(default)]
+        fps: Option<u32>,
+    },
+    #[serde(rename = "set_transparent_background")]
+    SetTransparentBackground {
+        enabled: bool,
+    },
+    #[serde(rename = "set_overlay")]
+    SetOverlay {
+        /// None removes the logo
+        #[serde(default)]
+        overlay: Option<OverlayConfig>,
+    },
+    /// Draw an image or looping video under the visualization
+    #[serde(rename = "set_background")]
+    SetBackground {
+        /// None removes the background
+        #[serde(default)]
+        background: Option<BackgroundConfig>,
+    },
+    #[serde(rename = "set_test_pattern")]
+    SetTestPattern {
+        enabled: bool,
+    },
+    /// Set a post-processing effect's intensity (0 turns it off)
+    #[serde(rename = "set_effect")]
+    SetEffect { effect: Effect, intensity: f32 },
+    /// Fade the brightness, contrast and saturation to new values over
+    /// `fade_secs` (0 cuts)
+    #[serde(rename = "set_color_adjust")]
+    SetColorAdjust {
+        adjust: ColorAdjust,
+        #[serde(default)]
+        fade_secs: f64,
+    },
+    /// Fade to black, or back, over `fade_secs` (0 cuts)
+    #[serde(rename = "set_blackout")]
+    SetBlackout {
+        enabled: bool,
+        #[serde(default)]
+        fade_secs: f64,
+    },
+    /// Play presets slower or faster; 1 is normal speed
+    #[serde(rename = "set_time_scale")]
+    SetTimeScale { scale: f64 },
+    /// Hold the last frame, or play on
+    #[serde(rename = "set_freeze")]
+    SetFreeze { enabled: bool },
+    /// Flash on the beat, or stop (None)
+    #[serde(rename = "set_strobe")]
+    SetStrobe { strobe: Option<StrobeConfig> },
+    /// A beat of the app's tempo clock, to time strobe flashes by
+    #[serde(rename = "beat")]
+    Beat { bpm: f32 },
+    /// Show frame timing, the preset and outputs on the deck window
+    #[serde(rename = "set_hud")]
+    SetHud {
+        enabled: bool,
+    },
+    /// Keep projectM from switching away from the current preset by itself
+    #[serde(rename = "lock_preset")]
+    LockPreset {
+        locked: bool,
+    },
+    /// Mesh size, FPS hint and internal render size for projectM
+    #[serde(rename = "set_quality")]
+    SetQuality {
+        quality: RenderQuality,
+    },
+    /// Draw at most this many frames per second (None = as fast as vsync allows)
+    #[serde(rename = "set_fps_cap")]
+    SetFpsCap {
+        #[serde(default)]
+        fps: Option<u32>,
+    },
+    #[serde(rename = "set_overlay_text")]
+    SetOverlayText {
+        /// None fades the current text out
+        #[serde(default)]
+        text: Option<TextOverlayConfig>,
+    },
+    #[serde(rename = "set_virtual_camera")]
+    SetVirtualCamera {
+        enabled: bool,
+    },
+    #[serde(rename = "start_recording")]
+    StartRecording {
+        path: String,
+        #[serde(default)]
+        fps: Option<u32>,
+        #[serde(default)]
+        bitrate_kbps: Option<u32>,
+        #[serde(default)]
+        codec: RecordingCodec,
+    },
+    #[serde(rename = "stop_recording")]
+    StopRecording,
+    #[serde(rename = "start_frame_export")]
+    StartFrameExport {
+        directory: String,
+        #[serde(default)]
+        fps: Option<u32>,
+    },
+    #[serde(rename = "stop_frame_export")]
+    StopFrameExport,
+    #[serde(rename = "start_loop_export")]
+    StartLoopExport {
+        path: String,
+        #[serde(default)]
+        format: LoopFormat,
+        duration_secs: f64,
+        #[serde(default)]
+        crossfade_secs: Option<f64>,
+    },
+    #[serde(rename = "cancel_loop_export")]
+    CancelLoopExport,
+    #[serde(rename = "set_input_layer")]
+    SetInputLayer {
+        enabled: bool,
+        backend: InputBackend,
+        #[serde(default)]
+        source: Option<String>,
+        #[serde(default)]
+        blend: InputBlend,
+        #[serde(default)]
+        placement: InputPlacement,
+        #[serde(default = "default_input_opacity")]
+        opacity: f32,
+    },
+    #[serde(rename = "set_input_mix")]
+    SetInputMix {
+        blend: InputBlend,
+        opacity: f32,
+        /// None keeps the layer where it is
+        #[serde(default)]
+        placement: Option<InputPlacement>,
     },
     #[serde(rename = "set_texture_paths")]
     SetTexturePaths { paths: Vec<String> },
+    /// Compositor only: decks to draw, bottom layer first
+    #[serde(rename = "set_compositor_layers")]
+    SetCompositorLayers { layers: Vec<CompositeLayer> },
+    /// Stream this deck's frames to the compositor on `port`, or stop;
+    /// with `gpu_socket`, share textures instead where the driver allows
+    #[serde(rename = "set_compositor_feed")]
+    SetCompositorFeed {
+        #[serde(default)]
+        port: Option<u16>,
+        #[serde(default)]
+        gpu_socket: Option<String>,
+    },
+    /// Open another deck window; multi-deck renderers only
+    #[serde(rename = "open_deck")]
+    OpenDeck { config: Box<Config> },
+    /// Render a preset's thumbnail to `output_path`; thumbnailers only
+    #[serde(rename = "thumbnail")]
+    Thumbnail {
+        preset_path: String,
+        output_path: String,
+    },
     #[serde(rename = "stop")]
     Stop,
 }
 
+/// Audio without a channel count is interleaved stereo
+fn default_audio_channels() -> usize {
+    2
+}
+
+/// Input layers start fully mixed in
+fn default_input_opacity() -> f32 {
+    1.0
+}
+
+/// Presets play at normal speed unless told otherwise
+fn default_time_scale() -> f64 {
+    1.0
+}
+
 /// Events sent to the parent process via stdout
 #[derive(Debug, Serialize)]
 #[serde(tag = "type")]
@@ -80,10 +427,78 @@ enum Event {
     Error { message: String },
     #[serde(rename = "preset_loaded")]
     PresetLoaded { path: String },
+    /// A preset couldn't be loaded; `current` is the preset still showing
+    #[serde(rename = "preset_failed")]
+    PresetFailed {
+        path: String,
+        error: String,
+        current: Option<String>,
+    },
+    /// Sent when a recording ends, whether stopped or failed
+    #[serde(rename = "recording_stopped")]
+    RecordingStopped {
+        path: String,
+        frames: u64,
+        dropped: u64,
+        duration_secs: f64,
+        error: Option<String>,
+    },
+    /// Sent when a PNG export ends, whether stopped or failed
+    #[serde(rename = "frame_export_stopped")]
+    FrameExportStopped {
+        directory: String,
+        frames: u64,
+        dropped: u64,
+        error: Option<String>,
+    },
+    /// Sent by the compositor once decks can connect
+    #[serde(rename = "compositor_ready")]
+    CompositorReady {
+        port: u16,
+        gpu_socket: Option<String>,
+    },
+    /// Sent when a loop export ends, whether encoded, cancelled or failed
+    #[serde(rename = "loop_export_finished")]
+    LoopExportFinished {
+        path: String,
+        duration_secs: f64,
+        error: Option<String>,
+    },
+    /// Sent about once a second while frames are drawn
+    #[serde(rename = "stats")]
+    Stats(RenderStats),
+    /// Sent once the window settles after being moved or resized, in
+    /// logical pixels; the position is unknown on Wayland
+    #[serde(rename = "window_geometry")]
+    WindowGeometry {
+        x: Option<i32>,
+        y: Option<i32>,
+        width: u32,
+        height: u32,
+    },
+    /// Preset keys pressed in the window; the app owns the playlist, so it
+    /// picks the preset and sends it back to load
+    #[serde(rename = "request_next_preset")]
+    RequestNextPreset,
+    #[serde(rename = "request_previous_preset")]
+    RequestPreviousPreset,
+    #[serde(rename = "request_random_preset")]
+    RequestRandomPreset,
+    /// The GPU the GL context runs on, sent after `ready`
+    #[serde(rename = "gpu_info")]
+    GpuInfo(GpuInfo),
+    /// Sent by thumbnailers once a thumbnail is written
+    #[serde(rename = "thumbnail_done")]
+    ThumbnailDone {
+        preset_path: String,
+        output_path: String,
+    },
+    #[serde(rename = "thumbnail_failed")]
+    ThumbnailFailed { preset_path: String, error: String },
 }
 
 /// Configuration passed via command line
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Clone, Deserialize)]
 struct Config {
     width: u32,
     height: u32,
@@ -97,10 +512,76 @@ struct Config {
     /// Texture search paths for presets that reference external textures
     #[serde(default)]
     texture_paths: Vec<String>,
+    /// Blend the decks' frames instead of running projectM
+    #[serde(default)]
+    compositor: bool,
+    /// Render offscreen with no window, for outputs and recordings only
+    #[serde(default)]
+    headless: bool,
+    /// projectM quality settings
+    #[serde(default)]
+    quality: RenderQuality,
+    /// Start with the preset locked
+    #[serde(default)]
+    preset_locked: bool,
+    /// Timed switches and hard cuts asked for by projectM
+    #[serde(default)]
+    preset_cuts: PresetCuts,
+    /// Keep the window above other apps' windows
+    #[serde(default)]
+    always_on_top: bool,
+    /// Let clicks pass through the window
+    #[serde(default)]
+    click_through: bool,
+    /// Window position in logical pixels; the window system places it
+    /// unless both are set
+    #[serde(default)]
+    x: Option<i32>,
+    #[serde(default)]
+    y: Option<i32>,
+    /// Host several deck windows in this process, see [`multi_deck`]
+    #[serde(default)]
+    multi_deck: bool,
+    /// Frames per second to draw at most, below the display's refresh rate
+    #[serde(default)]
+    fps_cap: Option<u32>,
+    /// Image or video drawn under the visualization
+    #[serde(default)]
+    background: Option<BackgroundConfig>,
+    /// Switch the monitor to this mode in fullscreen instead of covering
+    /// the desktop borderless
+    #[serde(default)]
+    fullscreen_mode: Option<ExclusiveMode>,
+    /// Brightness, contrast and saturation to start with
+    #[serde(default)]
+    color_adjust: ColorAdjust,
+    /// Start blacked out
+    #[serde(default)]
+    blackout: bool,
+    /// Playback speed to start at
+    #[serde(default = "default_time_scale")]
+    time_scale: f64,
+    /// Render preset thumbnails on request instead of a deck, see
+    /// [`thumbnailer`]
+    #[serde(default)]
+    thumbnailer: bool,
+}
+
+/// An event line, with the deck it came from when decks share the process
+#[derive(Serialize)]
+struct DeckEvent<'a> {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    deck_id: Option<u8>,
+    #[serde(flatten)]
+    event: &'a Event,
 }
 
 fn send_event(event: Event) {
-    if let Ok(json) = serde_json::to_string(&event) {
+    send_deck_event(None, event);
+}
+
+fn send_deck_event(deck_id: Option<u8>, event: Event) {
+    if let Ok(json) = serde_json::to_string(&DeckEvent { deck_id, event: &event }) {
         let mut stdout = io::stdout().lock();
         let _ = writeln!(stdout, "{}", json);
         let _ = stdout.flush();
@@ -111,10 +592,31 @@ fn send_event(event: Event) {
 struct RenderApp {
     config: Config,
     command_rx: Receiver<Command>,
+    /// Deck ID added to events, when decks share the process
+    event_deck: Option<u8>,
     gl_context: Option<PossiblyCurrentContext>,
     gl_surface: Option<Surface<WindowSurface>>,
+    /// Framebuffer drawn into instead of a window when headless
+    offscreen: Option<Offscreen>,
+    /// When the window last moved or resized, until its geometry is reported
+    geometry_changed: Option<Instant>,
+    /// When the next frame is due while the frame rate is capped
+    next_frame: Instant,
+    /// projectM's render, when the quality's texture size is below the frame
+    scaled: Option<Offscreen>,
+    /// Render scale while the quality's adaptive resolution is on
+    adaptive: AdaptiveScale,
     window: Option<Window>,
     projectm: Option<ProjectM>,
+    /// Loaded preset's file, reloaded when it is saved
+    preset_watcher: Option<PresetWatcher>,
+    /// Crossfade between presets, set up on the first preset load
+    transition: Option<PresetTransition>,
+    /// Crossfade length in seconds (0 = hard cut)
+    transition_secs: f64,
+    /// Blend time for the load answering projectM's last switch request,
+    /// and when it asked
+    cut_request: Option<(f64, Instant)>,
     should_exit: bool,
     // Video output state (platform-specific)
     #[cfg(target_os = "linux")]
@@ -123,8 +625,80 @@ struct RenderApp {
     video_output: Option<SpoutOutput>,
     // NDI output (cross-platform)
     ndi_output: Option<NdiOutput>,
+    /// What's playing, attached to NDI frames
+    ndi_metadata: NdiMetadata,
+    // Syphon output (macOS)
+    syphon_output: Option<SyphonOutput>,
+    // Virtual webcam (Windows)
+    virtual_camera: Option<VirtualCameraOutput>,
+    // MP4 recording
+    recorder: Option<VideoRecorder>,
+    // PNG image-sequence export
+    frame_export: Option<ImageSequenceExporter>,
+    // Loop being recorded, and the thread encoding the last one
+    loop_export: Option<LoopRecorder>,
+    loop_encoder: Option<thread::JoinHandle<()>>,
+    // Received video mixed into the visualization
+    input_layer: Option<InputLayer>,
+    // Deck blending, when running as the compositor
+    compositor: Option<Compositor>,
+    // This deck's frames streamed to the compositor
+    compositor_feed: Option<CompositorFeed>,
+    // This deck's frames shared with the compositor as textures
+    shared_texture_feed: Option<SharedTextureFeed>,
+    // Compositor port, to fall back to pixels if texture sharing fails
+    compositor_port: Option<u16>,
+    // EGL texture sharing, when the context supports it
+    egl: Option<Egl>,
+    /// PBO readback for frame capture, while any output needs frames
+    readback: Option<FrameReadback>,
     /// Pixel buffer for frame capture (RGBA)
     pixel_buffer: Vec<u8>,
+    /// FPS caps of live outputs; uncapped outputs take every frame
+    output_fps: Vec<(OutputBackend, FramePacer)>,
+    /// Key the dark background out of alpha-capable outputs (NDI, Spout, Syphon)
+    transparent_background: bool,
+    /// Captured frame with alpha keyed from brightness
+    keyed_buffer: Vec<u8>,
+    /// Logo blended onto captured frames
+    overlay: Option<LogoOverlay>,
+    /// Test pattern drawn instead of the visualization
+    test_pattern: Option<TestPattern>,
+    /// Post-processing, while any effect is on
+    effects: Option<PostEffects>,
+    /// Colour correction applied now, moving towards the fade's target
+    color: ColorAdjust,
+    color_fade: Option<ColorFade>,
+    /// Set up while the correction isn't neutral
+    color_pass: Option<ColorPass>,
+    strobe: Option<Strobe>,
+    blackout: Blackout,
+    /// Set up while strobing or blacked out
+    strobe_layer: Option<StrobeLayer>,
+    /// Animation time given to projectM, at the deck's playback speed
+    clock: PresetClock,
+    /// projectM paused on its last frame, for cutting away from the deck
+    frozen: bool,
+    /// The held frame, copied on the first frame drawn after freezing
+    frozen_frame: Option<Offscreen>,
+    /// Text shown over the output until it fades out
+    text_layer: Option<TextLayer>,
+    /// Stands in for projectM until a preset is loaded
+    idle_layer: Option<IdleLayer>,
+    /// Set when the idle visual couldn't be set up, so it isn't retried every frame
+    idle_failed: bool,
+    /// Image or video under the visualization
+    background: Option<BackgroundLayer>,
+    /// Times frames for stats reports
+    frame_timer: FrameTimer,
+    /// Show stats on the deck window
+    hud_enabled: bool,
+    /// Stats text, once the first report is in
+    hud: Option<TextLayer>,
+    /// Last cursor position over the window, in physical pixels
+    cursor: Option<(f64, f64)>,
+    /// The left button is down, painting a waveform
+    touching: bool,
     /// Current framebuffer dimensions for capture
     capture_width: u32,
     capture_height: u32,
@@ -132,50 +706,114 @@ struct RenderApp {
 
 impl RenderApp {
     fn new(config: Config, command_rx: Receiver<Command>) -> Self {
+        let ndi_metadata = NdiMetadata {
+            preset: config.preset_path.as_deref().and_then(preset_name),
+            deck_id: config.deck_id,
+            bpm: None,
+        };
         Self {
-            config,
             command_rx,
+            event_deck: None,
             gl_context: None,
             gl_surface: None,
+            offscreen: None,
+            geometry_changed: None,
+            next_frame: Instant::now(),
+            scaled: None,
             window: None,
             projectm: None,
+            preset_watcher: None,
+            transition: None,
+            transition_secs: DEFAULT_TRANSITION_SECS,
+            cut_request: None,
             should_exit: false,
             #[cfg(target_os = "linux")]
             video_output: None,
             #[cfg(target_os = "windows")]
             video_output: None,
             ndi_output: None,
+            ndi_metadata,
+            syphon_output: None,
+            virtual_camera: None,
+            recorder: None,
+            frame_export: None,
+            loop_export: None,
+            loop_encoder: None,
+            input_layer: None,
+            compositor: None,
+            compositor_feed: None,
+            shared_texture_feed: None,
+            compositor_port: None,
+            egl: None,
+            readback: None,
             pixel_buffer: Vec::new(),
+            output_fps: Vec::new(),
+            transparent_background: false,
+            keyed_buffer: Vec::new(),
+            overlay: None,
+            test_pattern: None,
+            effects: None,
+            color: config.color_adjust,
+            color_fade: None,
+            color_pass: None,
+            strobe: None,
+            blackout: Blackout::new(config.blackout, Instant::now()),
+            strobe_layer: None,
+            clock: PresetClock::new(config.time_scale),
+            frozen: false,
+            frozen_frame: None,
+            config,
+            text_layer: None,
+            idle_layer: None,
+            idle_failed: false,
+            background: None,
+            frame_timer: FrameTimer::new(),
+            adaptive: AdaptiveScale::new(),
+            hud_enabled: false,
+            hud: None,
+            cursor: None,
+            touching: false,
             capture_width: 0,
             capture_height: 0,
         }
     }
 
+    fn send_event(&self, event: Event) {
+        send_deck_event(self.event_deck, event);
+    }
+
     /// Enable or disable video output to v4l2loopback
     #[cfg(target_os = "linux")]
-    fn set_video_output(&mut self, enabled: bool, device_path: Option<String>) {
+    fn set_video_output(
+        &mut self,
+        enabled: bool,
+        device_path: Option<String>,
+        pixel_format: Option<PixelFormat>,
+    ) {
         if enabled {
             let path = device_path
                 .map(PathBuf::from)
                 .unwrap_or_else(|| PathBuf::from("/dev/video10"));
+            let pixel_format = pixel_format.unwrap_or(PixelFormat::Yuyv);
 
-            // Get current window size
+            // Get current window size, rounded down to even for YUV chroma pairs
             let (width, height) = if let Some(ref window) = self.window {
                 let size = window.inner_size();
-                (size.width, size.height)
+                (size.width & !1, size.height & !1)
             } else {
-                (self.config.width, self.config.height)
+                (self.config.width & !1, self.config.height & !1)
             };
 
             let config = V4l2Config {
                 device_path: path.clone(),
                 width,
                 height,
+                pixel_format,
             };
 
             match V4l2Output::new(config) {
                 Ok(output) => {
-                    info!("Video output enabled: {:?} ({}x{})", path, width, height);
+                    info!("Video output enabled: {:?} ({}x{} {})", path, width, height, pixel_format);
                     self.video_output = Some(output);
                     self.capture_width = width;
                     self.capture_height = height;
@@ -184,7 +822,7 @@ impl RenderApp {
                 }
                 Err(e) => {
                     error!("Failed to enable video output: {}", e);
-                    send_event(Event::Error {
+                    self.send_event(Event::Error {
                         message: format!("Video output error: {}", e),
                     });
                 }
@@ -198,7 +836,12 @@ impl RenderApp {
 
     /// Enable or disable video output to Spout (Windows)
     #[cfg(target_os = "windows")]
-    fn set_video_output(&mut self, enabled: bool, device_path: Option<String>) {
+    fn set_video_output(
+        &mut self,
+        enabled: bool,
+        device_path: Option<String>,
+        _pixel_format: Option<PixelFormat>,
+    ) {
         if enabled {
             // device_path is ignored for Spout, but we can use it as sender name
             let sender_name = device_path
@@ -230,7 +873,7 @@ impl RenderApp {
                 }
                 Err(e) => {
                     error!("Failed to enable Spout output: {}", e);
-                    send_event(Event::Error {
+                    self.send_event(Event::Error {
                         message: format!("Spout output error: {}", e),
                     });
                 }
@@ -244,22 +887,35 @@ impl RenderApp {
 
     /// Stub for other platforms (macOS, etc.)
     #[cfg(not(any(target_os = "linux", target_os = "windows")))]
-    fn set_video_output(&mut self, enabled: bool, _device_path: Option<String>) {
+    fn set_video_output(
+        &mut self,
+        enabled: bool,
+        _device_path: Option<String>,
+        _pixel_format: Option<PixelFormat>,
+    ) {
         if enabled {
             warn!("Video output not supported on this platform");
-            send_event(Event::Error {
+            self.send_event(Event::Error {
                 message: "Video output not supported on this platform".to_string(),
             });
         }
     }
 
     /// Enable or disable NDI output (cross-platform)
-    fn set_ndi_output(&mut self, enabled: bool, name: Option<String>) {
+    fn set_ndi_output(
+        &mut self,
+        enabled: bool,
+        name: Option<String>,
+        groups: Option<String>,
+        failover: Option<String>,
+        clock_video: Option<bool>,
+        pixel_format: Option<PixelFormat>,
+    ) {
         if enabled {
             // Check if NDI is available
             if !NdiOutput::is_available() {
                 warn!("NDI runtime not found. Install NDI Tools from https://ndi.video/tools/");
-                send_event(Event::Error {
+                self.send_event(Event::Error {
                     message: "NDI runtime not installed. Get it from https://ndi.video/tools/".to_string(),
                 });
                 return;
@@ -277,17 +933,21 @@ impl RenderApp {
 
             let config = NdiConfig {
                 name: sender_name.clone(),
-                groups: None,
-                clock_video: true,
+                groups,
+                failover,
+                clock_video: clock_video.unwrap_or(true),
+                pixel_format: pixel_format.unwrap_or_default(),
             };
 
             match NdiOutput::with_config(config) {
                 Ok(mut output) => {
+                    output.set_metadata(&self.ndi_metadata);
                     output.set_active(true);
                     info!("NDI output enabled: {} ({}x{})", sender_name, width, height);
                     self.ndi_output = Some(output);
-                    // Ensure pixel buffer is allocated
+                    // Ensure pixel buffer is allocated (even, for YUV chroma pairs)
                     if self.pixel_buffer.is_empty() {
+                        let (width, height) = (width & !1, height & !1);
                         self.capture_width = width;
                         self.capture_height = height;
                         self.pixel_buffer = vec![0u8; (width * height * 4) as usize];
@@ -295,7 +955,7 @@ impl RenderApp {
                 }
                 Err(e) => {
                     error!("Failed to enable NDI output: {}", e);
-                    send_event(Event::Error {
+                    self.send_event(Event::Error {
                         message: format!("NDI output error: {}", e),
                     });
                 }
@@ -308,109 +968,1577 @@ impl RenderApp {
         }
     }
 
-    /// Capture current framebuffer to pixel buffer
-    fn capture_frame(&mut self) {
-        // Early exit if no video output configured
-        #[cfg(target_os = "linux")]
-        let has_platform_output = self.video_output.is_some();
-        #[cfg(target_os = "windows")]
-        let has_platform_output = self.video_output.is_some();
-        #[cfg(not(any(target_os = "linux", target_os = "windows")))]
-        let has_platform_output = false;
+    /// Enable or disable Syphon output (macOS)
+    fn set_syphon_output(&mut self, enabled: bool, name: Option<String>) {
+        if enabled {
+            let server_name = name.unwrap_or_else(|| format!("OpenDrop Deck {}", self.config.deck_id + 1));
 
-        let has_ndi_output = self.ndi_output.is_some();
-        let has_output = has_platform_output || has_ndi_output;
+            // Get current window size
+            let (width, height) = if let Some(ref window) = self.window {
+                let size = window.inner_size();
+                (size.width, size.height)
+            } else {
+                (self.config.width, self.config.height)
+            };
 
-        if !has_output || self.pixel_buffer.is_empty() {
-            return;
-        }
+            let config = SyphonConfig {
+                server_name: server_name.clone(),
+                width,
+                height,
+            };
 
-        // Read pixels from framebuffer
-        unsafe {
-            gl::ReadPixels(
-                0,
-                0,
-                self.capture_width as i32,
-                self.capture_height as i32,
-                gl::RGBA,
-                gl::UNSIGNED_BYTE,
-                self.pixel_buffer.as_mut_ptr() as *mut _,
-            );
+            match SyphonOutput::new(config) {
+                Ok(output) => {
+                    info!("Syphon output enabled: {} ({}x{})", server_name, width, height);
+                    self.syphon_output = Some(output);
+                    // Ensure pixel buffer is allocated
+                    if self.pixel_buffer.is_empty() {
+                        self.capture_width = width;
+                        self.capture_height = height;
+                        self.pixel_buffer = vec![0u8; (width * height * 4) as usize];
+                    }
+                }
+                Err(e) => {
+                    error!("Failed to enable Syphon output: {}", e);
+                    self.send_event(Event::Error {
+                        message: format!("Syphon output error: {}", e),
+                    });
+                }
+            }
+        } else {
+            info!("Syphon output disabled");
+            self.syphon_output = None;
         }
+    }
+
+    /// Enable or disable the virtual webcam (Windows)
+    fn set_virtual_camera(&mut self, enabled: bool) {
+        if enabled {
+            // The camera's size is fixed, so match frames already being captured
+            let (width, height) = if !self.pixel_buffer.is_empty() {
+                (self.capture_width, self.capture_height)
+            } else if let Some(ref window) = self.window {
+                let size = window.inner_size();
+                (size.width, size.height)
+            } else {
+                (self.config.width, self.config.height)
+            };
+
+            // Release the previous camera first; only one can exist at a time
+            self.virtual_camera = None;
+            let config = VirtualCameraConfig {
+                width,
+                height,
+                ..VirtualCameraConfig::default()
+            };
 
-        // Flip vertically (OpenGL has origin at bottom-left)
-        let row_size = (self.capture_width * 4) as usize;
-        let half_height = self.capture_height as usize / 2;
-        for y in 0..half_height {
-            let top_start = y * row_size;
-            let bottom_start = (self.capture_height as usize - 1 - y) * row_size;
-            for x in 0..row_size {
-                self.pixel_buffer.swap(top_start + x, bottom_start + x);
+            match VirtualCameraOutput::new(config) {
+                Ok(output) => {
+                    info!("Virtual camera enabled ({}x{})", width, height);
+                    self.virtual_camera = Some(output);
+                    // Ensure pixel buffer is allocated
+                    if self.pixel_buffer.is_empty() {
+                        self.capture_width = width;
+                        self.capture_height = height;
+                        self.pixel_buffer = vec![0u8; (width * height * 4) as usize];
+                    }
+                }
+                Err(e) => {
+                    error!("Failed to enable virtual camera: {}", e);
+                    self.send_event(Event::Error {
+                        message: format!("Virtual camera error: {}", e),
+                    });
+                }
             }
+        } else {
+            info!("Virtual camera disabled");
+            self.virtual_camera = None;
         }
+    }
 
-        // Send to video output (Linux - v4l2loopback)
-        #[cfg(target_os = "linux")]
-        if let Some(ref mut output) = self.video_output {
-            if let Err(e) = output.send_frame_rgba(
-                &self.pixel_buffer,
-                self.capture_width,
-                self.capture_height,
-            ) {
-                // Don't spam errors, just log occasionally
-                debug!("Video output frame error: {}", e);
+    /// Start recording the deck output to an MP4 file
+    fn start_recording(
+        &mut self,
+        path: String,
+        fps: Option<u32>,
+        bitrate_kbps: Option<u32>,
+        codec: RecordingCodec,
+    ) {
+        // Finish any recording in progress first
+        self.stop_recording(None);
+
+        let (width, height) = if self.pixel_buffer.is_empty() {
+            if let Some(ref window) = self.window {
+                let size = window.inner_size();
+                (size.width, size.height)
+            } else {
+                (self.config.width, self.config.height)
+            }
+        } else {
+            (self.capture_width, self.capture_height)
+        };
+
+        let mut config = RecordingConfig::new(&path, width, height);
+        if let Some(fps) = fps {
+            config.fps = fps;
+        }
+        if let Some(bitrate_kbps) = bitrate_kbps {
+            config.bitrate_kbps = bitrate_kbps;
+        }
+        config.codec = codec;
+
+        match VideoRecorder::start(config) {
+            Ok(recorder) => {
+                info!("Recording started: {} ({}x{} {})", path, width, height, codec);
+                self.recorder = Some(recorder);
+                // Ensure pixel buffer is allocated
+                if self.pixel_buffer.is_empty() {
+                    self.capture_width = width;
+                    self.capture_height = height;
+                    self.pixel_buffer = vec![0u8; (width * height * 4) as usize];
+                }
+            }
+            Err(e) => {
+                error!("Failed to start recording: {}", e);
+                self.send_event(Event::RecordingStopped {
+                    path,
+                    frames: 0,
+                    dropped: 0,
+                    duration_secs: 0.0,
+                    error: Some(e.to_string()),
+                });
             }
         }
+    }
 
-        // Send to video output (Windows - Spout)
-        #[cfg(target_os = "windows")]
-        if let Some(ref mut output) = self.video_output {
-            if let Err(e) = output.send_frame_rgba(
-                &self.pixel_buffer,
-                self.capture_width,
-                self.capture_height,
-            ) {
-                // Don't spam errors, just log occasionally
-                debug!("Spout output frame error: {}", e);
+    /// Finish the current recording, if any, reporting how it ended
+    fn stop_recording(&mut self, error: Option<String>) {
+        let Some(recorder) = self.recorder.take() else {
+            return;
+        };
+        let path = recorder.path().display().to_string();
+        let (info, error) = match recorder.stop() {
+            Ok(info) => (Some(info), error),
+            Err(e) => (None, Some(error.unwrap_or_else(|| e.to_string()))),
+        };
+        match error {
+            Some(ref message) => error!("Recording failed: {}", message),
+            None => info!("Recording stopped: {}", path),
+        }
+        self.send_event(Event::RecordingStopped {
+            path,
+            frames: info.as_ref().map_or(0, |i| i.frames),
+            dropped: info.as_ref().map_or(0, |i| i.dropped),
+            duration_secs: info.as_ref().map_or(0.0, |i| i.duration_secs()),
+            error,
+        });
+    }
+
+    /// Start writing the deck output as numbered PNGs to a directory
+    fn start_frame_export(&mut self, directory: String, fps: Option<u32>) {
+        // Finish any export in progress first
+        self.stop_frame_export(None);
+
+        let fps = fps.unwrap_or(DEFAULT_SEQUENCE_FPS);
+        match ImageSequenceExporter::start(&directory, fps) {
+            Ok(exporter) => {
+                info!("Frame export started: {} at {} fps", directory, fps);
+                self.frame_export = Some(exporter);
+                // Ensure pixel buffer is allocated
+                if self.pixel_buffer.is_empty() {
+                    let (width, height) = if let Some(ref window) = self.window {
+                        let size = window.inner_size();
+                        (size.width, size.height)
+                    } else {
+                        (self.config.width, self.config.height)
+                    };
+                    self.capture_width = width;
+                    self.capture_height = height;
+                    self.pixel_buffer = vec![0u8; (width * height * 4) as usize];
+                }
+            }
+            Err(e) => {
+                error!("Failed to start frame export: {}", e);
+                self.send_event(Event::FrameExportStopped {
+                    directory,
+                    frames: 0,
+                    dropped: 0,
+                    error: Some(e.to_string()),
+                });
+            }
+        }
+    }
+
+    /// Finish the current PNG export, if any, reporting how it ended
+    fn stop_frame_export(&mut self, error: Option<String>) {
+        let Some(exporter) = self.frame_export.take() else {
+            return;
+        };
+        let directory = exporter.directory().display().to_string();
+        let (info, error) = match exporter.stop() {
+            Ok(info) => (Some(info), error),
+            Err(e) => (None, Some(error.unwrap_or_else(|| e.to_string()))),
+        };
+        match error {
+            Some(ref message) => error!("Frame export failed: {}", message),
+            None => info!("Frame export stopped: {}", directory),
+        }
+        self.send_event(Event::FrameExportStopped {
+            directory,
+            frames: info.as_ref().map_or(0, |i| i.frames),
+            dropped: info.as_ref().map_or(0, |i| i.dropped),
+            error,
+        });
+    }
+
+    /// Start recording a loop of `duration_secs` for WebM/GIF export
+    fn start_loop_export(
+        &mut self,
+        path: String,
+        format: LoopFormat,
+        duration_secs: f64,
+        crossfade_secs: Option<f64>,
+    ) {
+        self.cancel_loop_export();
+
+        let (width, height) = if self.pixel_buffer.is_empty() {
+            if let Some(ref window) = self.window {
+                let size = window.inner_size();
+                (size.width, size.height)
+            } else {
+                (self.config.width, self.config.height)
+            }
+        } else {
+            (self.capture_width, self.capture_height)
+        };
+
+        let mut config = LoopExportConfig::new(&path, format, duration_secs, width, height);
+        if let Some(crossfade_secs) = crossfade_secs {
+            config.crossfade_secs = crossfade_secs;
+        }
+
+        match LoopRecorder::start(config) {
+            Ok(recorder) => {
+                info!("Loop export started: {} ({:.1}s {})", path, duration_secs, format);
+                self.loop_export = Some(recorder);
+                // Ensure pixel buffer is allocated
+                if self.pixel_buffer.is_empty() {
+                    self.capture_width = width;
+                    self.capture_height = height;
+                    self.pixel_buffer = vec![0u8; (width * height * 4) as usize];
+                }
+            }
+            Err(e) => {
+                error!("Failed to start loop export: {}", e);
+                self.send_event(Event::LoopExportFinished {
+                    path,
+                    duration_secs: 0.0,
+                    error: Some(e.to_string()),
+                });
+            }
+        }
+    }
+
+    /// Discard the loop being recorded, if any
+    fn cancel_loop_export(&mut self) {
+        let Some(recorder) = self.loop_export.take() else {
+            return;
+        };
+        let path = recorder.path().display().to_string();
+        drop(recorder);
+        info!("Loop export cancelled: {}", path);
+        self.send_event(Event::LoopExportFinished {
+            path,
+            duration_secs: 0.0,
+            error: Some("Cancelled".to_string()),
+        });
+    }
+
+    /// Encode the recorded loop on a thread so rendering carries on
+    fn finish_loop_export(&mut self, error: Option<String>) {
+        let Some(recorder) = self.loop_export.take() else {
+            return;
+        };
+        let path = recorder.path().display().to_string();
+        if let Some(error) = error {
+            error!("Loop export failed: {}", error);
+            drop(recorder);
+            self.send_event(Event::LoopExportFinished {
+                path,
+                duration_secs: 0.0,
+                error: Some(error),
+            });
+            return;
+        }
+
+        self.wait_for_loop_encoder();
+        let deck_id = self.event_deck;
+        self.loop_encoder = Some(thread::spawn(move || {
+            let (duration_secs, error) = match recorder.finish() {
+                Ok(info) => (info.duration_secs, None),
+                Err(e) => (0.0, Some(e.to_string())),
+            };
+            match error {
+                Some(ref message) => error!("Loop export failed: {}", message),
+                None => info!("Loop exported: {} ({:.1}s)", path, duration_secs),
+            }
+            send_deck_event(deck_id, Event::LoopExportFinished {
+                path,
+                duration_secs,
+                error,
+            });
+        }));
+    }
+
+    /// Let a loop still being encoded finish writing its file
+    fn wait_for_loop_encoder(&mut self) {
+        if let Some(handle) = self.loop_encoder.take() {
+            let _ = handle.join();
+        }
+    }
+
+    /// Stream frames to the compositor on `port`, or stop streaming;
+    /// textures are shared over `gpu_socket` instead when possible
+    fn set_compositor_feed(&mut self, port: Option<u16>, gpu_socket: Option<String>) {
+        self.compositor_feed = None;
+        self.shared_texture_feed = None;
+        self.compositor_port = port;
+        let Some(port) = port else {
+            info!("Compositor feed stopped");
+            return;
+        };
+
+        if let (Some(egl), Some(socket)) = (&self.egl, gpu_socket) {
+            match SharedTextureFeed::connect(egl, &socket, self.config.deck_id) {
+                Ok(feed) => {
+                    self.shared_texture_feed = Some(feed);
+                    return;
+                }
+                Err(e) => info!("Sending pixels to the compositor: {}", e),
+            }
+        }
+
+        match CompositorFeed::connect(port, self.config.deck_id) {
+            Ok(feed) => {
+                self.compositor_feed = Some(feed);
+                // Ensure pixel buffer is allocated
+                if self.pixel_buffer.is_empty() {
+                    let (width, height) = if let Some(ref window) = self.window {
+                        let size = window.inner_size();
+                        (size.width, size.height)
+                    } else {
+                        (self.config.width, self.config.height)
+                    };
+                    self.capture_width = width;
+                    self.capture_height = height;
+                    self.pixel_buffer = vec![0u8; (width * height * 4) as usize];
+                }
+            }
+            Err(e) => {
+                error!("Failed to connect to the compositor: {}", e);
+                self.send_event(Event::Error {
+                    message: format!("Compositor feed error: {}", e),
+                });
+            }
+        }
+    }
+
+    /// Copy this frame into the textures shared with the compositor; on
+    /// failure, e.g. the compositor can't import them, fall back to pixels
+    fn share_frame(&mut self) {
+        let (width, height) = self.frame_size();
+        if self.shared_texture_feed.is_none()
+            || !self.output_due(OutputBackend::Compositor, Instant::now())
+        {
+            return;
+        }
+        let Some(feed) = self.shared_texture_feed.as_mut() else {
+            return;
+        };
+        if let Err(e) = feed.publish(width, height) {
+            warn!("Texture sharing stopped, sending pixels instead: {}", e);
+            self.shared_texture_feed = None;
+            self.set_compositor_feed(self.compositor_port, None);
+        }
+    }
+
+    /// Enable or disable the input layer receiving from Spout, NDI or the screen
+    fn set_input_layer(
+        &mut self,
+        enabled: bool,
+        backend: InputBackend,
+        source: Option<String>,
+        blend: InputBlend,
+        placement: InputPlacement,
+        opacity: f32,
+    ) {
+        // Release the current receiver before opening another
+        self.input_layer = None;
+        if !enabled {
+            info!("Input layer disabled");
+            return;
+        }
+
+        let layer = backend
+            .open(source.as_deref())
+            .map_err(|e| e.to_string())
+            .and_then(|input| InputLayer::new(input, blend, placement, opacity));
+        match layer {
+            Ok(layer) => {
+                info!(
+                    "Input layer enabled: {} {}",
+                    backend,
+                    source.as_deref().unwrap_or("(first source)")
+                );
+                self.input_layer = Some(layer);
+            }
+            Err(e) => {
+                error!("Failed to enable {} input: {}", backend, e);
+                self.send_event(Event::Error {
+                    message: format!("{} input error: {}", backend, e),
+                });
+            }
+        }
+    }
+
+    /// Show or hide the test pattern in place of the visualization
+    fn set_test_pattern(&mut self, enabled: bool) {
+        if !enabled {
+            if self.test_pattern.take().is_some() {
+                info!("Test pattern off");
+            }
+            return;
+        }
+        if self.test_pattern.is_some() {
+            return;
+        }
+
+        match TestPattern::new(self.config.deck_id) {
+            Ok(pattern) => {
+                info!("Test pattern on");
+                self.test_pattern = Some(pattern);
+            }
+            Err(e) => {
+                error!("Failed to set up test pattern: {}", e);
+                self.send_event(Event::Error {
+                    message: format!("Test pattern error: {}", e),
+                });
+            }
+        }
+    }
+
+    /// Change one post-processing effect; the chain is freed once all are off
+    fn set_effect(&mut self, effect: Effect, intensity: f32) {
+        if self.effects.is_none() {
+            if intensity <= 0.0 {
+                return;
+            }
+            match PostEffects::new() {
+                Ok(effects) => self.effects = Some(effects),
+                Err(e) => {
+                    error!("Failed to set up effects: {}", e);
+                    self.send_event(Event::Error {
+                        message: format!("Effects error: {}", e),
+                    });
+                    return;
+                }
+            }
+        }
+
+        if let Some(ref mut effects) = self.effects {
+            effects.set(effect, intensity);
+            debug!("Effect {} at {:.2}", effect, intensity);
+            if effects.is_empty() {
+                info!("Effects off");
+                self.effects = None;
+            }
+        }
+    }
+
+    /// Show text over the output, replacing any shown now, or fade it out
+    fn set_overlay_text(&mut self, text: Option<TextOverlayConfig>) {
+        let Some(text) = text else {
+            if let Some(ref mut layer) = self.text_layer {
+                info!("Overlay text cleared");
+                layer.clear();
+            }
+            return;
+        };
+        match TextLayer::new(text) {
+            Ok(layer) => {
+                info!("Overlay text shown");
+                self.text_layer = Some(layer);
+            }
+            Err(e) => {
+                error!("Failed to show overlay text: {}", e);
+                self.send_event(Event::Error {
+                    message: format!("Text overlay error: {}", e),
+                });
+            }
+        }
+    }
+
+    /// Fade to a colour correction over `fade_secs` (0 cuts)
+    fn set_color_adjust(&mut self, adjust: ColorAdjust, fade_secs: f64) {
+        if let Err(e) = adjust.validate() {
+            error!("Invalid color adjust: {}", e);
+            self.send_event(Event::Error {
+                message: e.to_string(),
+            });
+            return;
+        }
+        let now = Instant::now();
+        // A fade under way carries on from where it got to
+        let from = self.color_fade.map_or(self.color, |fade| fade.at(now));
+        let fade_secs = if fade_secs.is_finite() {
+            fade_secs.clamp(0.0, MAX_COLOR_FADE_SECS)
+        } else {
+            0.0
+        };
+        debug!("Color adjust to {:?} over {:.1}s", adjust, fade_secs);
+        self.color = from;
+        self.color_fade = Some(ColorFade::new(from, adjust, Duration::from_secs_f64(fade_secs), now));
+    }
+
+    /// Apply the colour correction to the finished frame; the pass is freed
+    /// while the correction is neutral
+    fn draw_color_adjust(&mut self, width: u32, height: u32) {
+        if let Some(fade) = self.color_fade {
+            let now = Instant::now();
+            self.color = fade.at(now);
+            if fade.is_done(now) {
+                self.color_fade = None;
+            }
+        }
+        if self.color.is_neutral() {
+            self.color_pass = None;
+            return;
+        }
+
+        if self.color_pass.is_none() {
+            match ColorPass::new() {
+                Ok(pass) => self.color_pass = Some(pass),
+                Err(e) => {
+                    error!("Failed to set up color adjust: {}", e);
+                    self.send_event(Event::Error {
+                        message: format!("Color adjust error: {}", e),
+                    });
+                    self.color = ColorAdjust::default();
+                    self.color_fade = None;
+                    return;
+                }
+            }
+        }
+        if let Some(ref mut pass) = self.color_pass {
+            pass.draw(&self.color, width, height);
+        }
+    }
+
+    /// Fade to black or back over `fade_secs` (0 cuts)
+    fn set_blackout(&mut self, enabled: bool, fade_secs: f64) {
+        let fade_secs = if fade_secs.is_finite() {
+            fade_secs.clamp(0.0, MAX_BLACKOUT_FADE_SECS)
+        } else {
+            0.0
+        };
+        debug!("Blackout {} over {:.1}s", if enabled { "on" } else { "off" }, fade_secs);
+        self.blackout.set(enabled, Duration::from_secs_f64(fade_secs), Instant::now());
+    }
+
+    /// Play presets at `scale` times normal speed
+    fn set_time_scale(&mut self, scale: f64) {
+        if let Err(e) = validate_time_scale(scale) {
+            error!("{}", e);
+            self.send_event(Event::Error {
+                message: e.to_string(),
+            });
+            return;
+        }
+        debug!("Playback speed {:.2}x", scale);
+        self.clock.set_scale(scale);
+    }
+
+    /// Hold projectM's last frame, or play on; everything drawn over it,
+    /// and the outputs, keep going
+    fn set_freeze(&mut self, enabled: bool) {
+        debug!("Freeze {}", if enabled { "on" } else { "off" });
+        self.frozen = enabled;
+        if !enabled {
+            self.frozen_frame = None;
+        }
+    }
+
+    /// Start, change or stop the strobe; a running strobe keeps its beat
+    fn set_strobe(&mut self, strobe: Option<StrobeConfig>) {
+        let Some(config) = strobe else {
+            self.strobe = None;
+            return;
+        };
+        if let Err(e) = config.validate() {
+            error!("Invalid strobe: {}", e);
+            self.send_event(Event::Error {
+                message: e.to_string(),
+            });
+            return;
+        }
+        match self.strobe {
+            Some(ref mut strobe) => strobe.set_config(config),
+            None => self.strobe = Some(Strobe::new(config, Instant::now())),
+        }
+    }
+
+    /// Flash and black out the finished frame; the layer is freed while
+    /// neither is on
+    fn draw_strobe(&mut self) {
+        let now = Instant::now();
+        let flash = self.strobe.map_or(0.0, |strobe| strobe.level(now));
+        let blackout = self.blackout.level(now);
+        if self.strobe.is_none() && blackout <= 0.0 {
+            self.strobe_layer = None;
+            return;
+        }
+
+        if self.strobe_layer.is_none() {
+            match StrobeLayer::new() {
+                Ok(layer) => self.strobe_layer = Some(layer),
+                Err(e) => {
+                    error!("Failed to set up strobe: {}", e);
+                    self.send_event(Event::Error {
+                        message: format!("Strobe error: {}", e),
+                    });
+                    self.strobe = None;
+                    return;
+                }
+            }
+        }
+        if let Some(ref mut layer) = self.strobe_layer {
+            layer.draw(flash, blackout);
+        }
+    }
+
+    /// Draw the idle visual in place of projectM's frame
+    fn draw_idle(&mut self, width: u32, height: u32) {
+        if self.idle_layer.is_none() {
+            match IdleLayer::new() {
+                Ok(layer) => {
+                    info!("No preset loaded, showing the idle visual");
+                    self.idle_layer = Some(layer);
+                }
+                Err(e) => {
+                    error!("Failed to set up idle visual: {}", e);
+                    self.idle_failed = true;
+                    return;
+                }
+            }
+        }
+        if let Some(ref mut layer) = self.idle_layer {
+            layer.draw(width, height);
+        }
+    }
+
+    /// Draw the overlay text, dropping it once it has faded out
+    fn draw_text_layer(&mut self) {
+        let Some(ref mut layer) = self.text_layer else {
+            return;
+        };
+        let (width, height) = if let Some(ref window) = self.window {
+            let size = window.inner_size();
+            (size.width, size.height)
+        } else {
+            (self.config.width, self.config.height)
+        };
+        if !layer.draw(width, height) {
+            debug!("Overlay text finished");
+            self.text_layer = None;
+        }
+    }
+
+    /// Fullscreen on `monitor`, or the window's own: exclusive in the
+    /// configured mode when the monitor offers it, borderless otherwise
+    fn fullscreen(&self, window: &Window, monitor: Option<MonitorHandle>) -> Fullscreen {
+        let Some(wanted) = self.config.fullscreen_mode else {
+            return Fullscreen::Borderless(monitor);
+        };
+        let Some(target) = monitor.clone().or_else(|| window.current_monitor()) else {
+            warn!("No monitor to switch modes on, going borderless fullscreen");
+            return Fullscreen::Borderless(monitor);
+        };
+
+        // Deepest colour first, so it wins between modes of the same rate
+        let mut handles: Vec<_> = target.video_modes().collect();
+        handles.sort_by_key(|mode| std::cmp::Reverse(mode.bit_depth()));
+        let modes: Vec<DisplayMode> = handles
+            .iter()
+            .map(|mode| DisplayMode {
+                width: mode.size().width,
+                height: mode.size().height,
+                refresh_millihertz: mode.refresh_rate_millihertz(),
+            })
+            .collect();
+        match wanted.pick(&modes) {
+            Some(index) => {
+                info!("Exclusive fullscreen at {}", modes[index]);
+                Fullscreen::Exclusive(handles.swap_remove(index))
+            }
+            None => {
+                warn!(
+                    "Monitor has no {}x{} mode, going borderless fullscreen",
+                    wanted.width, wanted.height
+                );
+                Fullscreen::Borderless(monitor)
+            }
+        }
+    }
+
+    fn set_always_on_top(&mut self, enabled: bool) {
+        info!("Always on top {}", if enabled { "on" } else { "off" });
+        self.config.always_on_top = enabled;
+        if let Some(ref window) = self.window {
+            window.set_window_level(window_level(enabled));
+        }
+    }
+
+    fn set_click_through(&mut self, enabled: bool) {
+        info!("Click-through {}", if enabled { "on" } else { "off" });
+        self.config.click_through = enabled;
+        if let Some(ref window) = self.window {
+            if let Err(e) = window.set_cursor_hittest(!enabled) {
+                warn!("Click-through unavailable: {}", e);
+                self.send_event(Event::Error {
+                    message: format!("Click-through unavailable: {}", e),
+                });
+            }
+        }
+    }
+
+    fn set_window_geometry(
+        &mut self,
+        x: Option<i32>,
+        y: Option<i32>,
+        width: Option<u32>,
+        height: Option<u32>,
+    ) {
+        let Some(ref window) = self.window else {
+            return;
+        };
+        let scale = window.scale_factor();
+        if x.is_some() || y.is_some() {
+            // A missing coordinate stays where it is
+            let current = window.outer_position().ok().map(|p| p.to_logical::<i32>(scale));
+            match (x.or(current.map(|p| p.x)), y.or(current.map(|p| p.y))) {
+                (Some(x), Some(y)) => window.set_outer_position(LogicalPosition::new(x, y)),
+                _ => warn!("Window position unknown, can't move the window"),
+            }
+        }
+        if width.is_some() || height.is_some() {
+            let current = window.inner_size().to_logical::<u32>(scale);
+            let _ = window.request_inner_size(LogicalSize::new(
+                width.unwrap_or(current.width),
+                height.unwrap_or(current.height),
+            ));
+        }
+    }
+
+    /// Report the window's geometry once it has stopped moving for a moment,
+    /// so a drag sends one event
+    fn report_geometry(&mut self) {
+        const SETTLE: Duration = Duration::from_millis(500);
+        let Some(changed) = self.geometry_changed else {
+            return;
+        };
+        if changed.elapsed() < SETTLE {
+            return;
+        }
+        self.geometry_changed = None;
+        let Some(ref window) = self.window else {
+            return;
+        };
+        // Fullscreen covers the monitor; the windowed geometry is what's kept
+        if window.fullscreen().is_some() {
+            return;
+        }
+        let scale = window.scale_factor();
+        let position = window.outer_position().ok().map(|p| p.to_logical::<i32>(scale));
+        let size = window.inner_size().to_logical::<u32>(scale);
+        self.send_event(Event::WindowGeometry {
+            x: position.map(|p| p.x),
+            y: position.map(|p| p.y),
+            width: size.width,
+            height: size.height,
+        });
+    }
+
+    /// Change when projectM asks for preset switches, on both instances
+    fn set_preset_cuts(&mut self, cuts: PresetCuts) {
+        if let Err(e) = cuts.validate() {
+            error!("{}", e);
+            self.send_event(Event::Error {
+                message: e.to_string(),
+            });
+            return;
+        }
+        info!("Preset cuts: {:?}", cuts);
+        self.config.preset_cuts = cuts;
+        if let Some(ref mut pm) = self.projectm {
+            apply_cuts(pm, &cuts);
+        }
+        if let Some(spare) = self.transition.as_mut().and_then(|t| t.spare_mut()) {
+            apply_cuts(spare, &cuts);
+        }
+    }
+
+    /// Pass a preset switch projectM asked for on as a request for the next
+    /// playlist preset; the load that answers it cuts or blends as asked
+    fn forward_switch_request(&mut self) {
+        let Some(hard_cut) = self.projectm.as_mut().and_then(ProjectM::take_switch_request) else {
+            return;
+        };
+        let fade_secs = if hard_cut { 0.0 } else { self.config.preset_cuts.soft_cut_duration };
+        debug!("projectM asked for a {} cut", if hard_cut { "hard" } else { "soft" });
+        self.cut_request = Some((fade_secs, Instant::now()));
+        self.send_event(Event::RequestNextPreset);
+    }
+
+    fn lock_preset(&mut self, locked: bool) {
+        info!("Preset {}", if locked { "locked" } else { "unlocked" });
+        self.config.preset_locked = locked;
+        if let Some(ref mut pm) = self.projectm {
+            pm.set_preset_locked(locked);
+        }
+        if let Some(spare) = self.transition.as_mut().and_then(|t| t.spare_mut()) {
+            spare.set_preset_locked(locked);
+        }
+    }
+
+    fn set_fps_cap(&mut self, fps: Option<u32>) {
+        let fps = fps.map(|fps| fps.max(1));
+        match fps {
+            Some(fps) => info!("Frame rate capped at {} fps", fps),
+            None => info!("Frame rate cap removed"),
+        }
+        self.config.fps_cap = fps;
+        self.next_frame = Instant::now();
+    }
+
+    /// Whether a frame is due; always, unless the frame rate is capped
+    fn frame_due(&mut self, now: Instant) -> bool {
+        let Some(fps) = self.config.fps_cap else {
+            return true;
+        };
+        if now < self.next_frame {
+            return false;
+        }
+        let interval = Duration::from_secs(1) / fps;
+        self.next_frame += interval;
+        if self.next_frame <= now {
+            // Running behind; don't rush to catch up
+            self.next_frame = now + interval;
+        }
+        true
+    }
+
+    /// Time a frame may take: the FPS cap's, or else the display's refresh
+    fn frame_budget_ms(&self) -> f32 {
+        let fps = match self.window {
+            Some(ref window) => self.config.fps_cap.map(|fps| fps as f32).unwrap_or_else(|| {
+                window
+                    .current_monitor()
+                    .and_then(|monitor| monitor.refresh_rate_millihertz())
+                    .map_or(60.0, |millihertz| millihertz as f32 / 1000.0)
+            }),
+            None => self.config.fps_cap.unwrap_or(HEADLESS_FPS).min(HEADLESS_FPS) as f32,
+        };
+        1000.0 / fps
+    }
+
+    /// When the event loop should wake for the next frame; None to keep
+    /// polling at the vsync rate
+    fn next_frame_at(&self) -> Option<Instant> {
+        self.config.fps_cap.map(|_| self.next_frame)
+    }
+
+    fn set_quality(&mut self, quality: RenderQuality) {
+        if let Err(e) = quality.validate() {
+            self.send_event(Event::Error {
+                message: e.to_string(),
+            });
+            return;
+        }
+        info!(
+            "Quality: mesh {}x{}, {} fps, texture size {}, adaptive {}",
+            quality.mesh_width,
+            quality.mesh_height,
+            quality.fps,
+            quality.texture_size,
+            quality.adaptive
+        );
+        self.config.quality = quality;
+        if !quality.adaptive {
+            self.adaptive = AdaptiveScale::new();
+        }
+        if let Some(ref mut pm) = self.projectm {
+            apply_quality(pm, &quality);
+        }
+        if let Some(spare) = self.transition.as_mut().and_then(|t| t.spare_mut()) {
+            apply_quality(spare, &quality);
+        }
+        self.apply_render_size();
+    }
+
+    /// Size projectM for the frame and point it at the reduced-size render,
+    /// or straight at the frame when the texture size doesn't reduce it
+    fn apply_render_size(&mut self) {
+        if self.projectm.is_none() {
+            return;
+        }
+        let (width, height) = self.frame_size();
+        let output = self.offscreen.as_ref().map_or(0, Offscreen::framebuffer);
+        let size = scale_size(
+            self.config.quality.render_size(width, height),
+            self.adaptive.scale(),
+        );
+        if size == (width, height) {
+            self.scaled = None;
+        } else if self.scaled.as_ref().map(Offscreen::size) != Some(size) {
+            self.scaled = None;
+            match Offscreen::new(size.0, size.1) {
+                Ok(scaled) => self.scaled = Some(scaled),
+                Err(e) => warn!("Rendering at the frame size: {}", e),
+            }
+            unsafe {
+                gl::BindFramebuffer(gl::FRAMEBUFFER, output);
+                gl::Viewport(0, 0, width as i32, height as i32);
+            }
+        }
+
+        let (framebuffer, (width, height)) = match self.scaled {
+            Some(ref scaled) => (scaled.framebuffer(), scaled.size()),
+            None => (output, (width, height)),
+        };
+        if let Some(ref mut pm) = self.projectm {
+            pm.set_framebuffer(framebuffer);
+            pm.resize(width, height);
+        }
+        if let Some(ref mut transition) = self.transition {
+            if let Some(spare) = transition.spare_mut() {
+                spare.set_framebuffer(framebuffer);
+            }
+            transition.resize(width, height);
+        }
+    }
+
+    /// Ask the app for another preset from the deck's playlist
+    fn request_preset(&self, request: Event) {
+        if self.projectm.is_none() {
+            return;
+        }
+        debug!("Preset key pressed: {:?}", request);
+        self.send_event(request);
+    }
+
+    /// Cursor position as a share of the window, from the top left
+    fn touch_position(&self) -> Option<(f32, f32)> {
+        let (x, y) = self.cursor?;
+        let size = self.window.as_ref()?.inner_size();
+        if size.width == 0 || size.height == 0 {
+            return None;
+        }
+        Some((
+            (x / size.width as f64).clamp(0.0, 1.0) as f32,
+            (y / size.height as f64).clamp(0.0, 1.0) as f32,
+        ))
+    }
+
+    /// Paint projectM waveforms with the mouse: drag with the left button,
+    /// right-click removes the nearest, middle-click removes them all
+    fn handle_mouse(&mut self, button: MouseButton, state: ElementState) {
+        let position = self.touch_position();
+        let Some(ref mut projectm) = self.projectm else {
+            return;
+        };
+        match (button, state) {
+            (MouseButton::Left, ElementState::Pressed) => {
+                if let Some((x, y)) = position {
+                    projectm.touch(x, y, 1, TouchType::Random);
+                    self.touching = true;
+                }
+            }
+            (MouseButton::Left, ElementState::Released) => {
+                self.touching = false;
+            }
+            (MouseButton::Right, ElementState::Pressed) => {
+                if let Some((x, y)) = position {
+                    projectm.touch_destroy(x, y);
+                }
+            }
+            (MouseButton::Middle, ElementState::Pressed) => {
+                projectm.touch_destroy_all();
+            }
+            _ => {}
+        }
+    }
+
+    fn set_hud(&mut self, enabled: bool) {
+        info!("HUD {}", if enabled { "shown" } else { "hidden" });
+        self.hud_enabled = enabled;
+        if !enabled {
+            self.hud = None;
+        }
+    }
+
+    /// Outputs taking this deck's frames
+    fn active_outputs(&self) -> Vec<OutputBackend> {
+        let mut outputs = Vec::new();
+        #[cfg(target_os = "linux")]
+        if self.video_output.is_some() {
+            outputs.push(OutputBackend::V4l2Loopback);
+        }
+        #[cfg(target_os = "windows")]
+        if self.video_output.is_some() {
+            outputs.push(OutputBackend::Spout);
+        }
+        if self.ndi_output.is_some() {
+            outputs.push(OutputBackend::Ndi);
+        }
+        if self.syphon_output.is_some() {
+            outputs.push(OutputBackend::Syphon);
+        }
+        if self.virtual_camera.is_some() {
+            outputs.push(OutputBackend::VirtualCamera);
+        }
+        if self.compositor_feed.is_some() || self.shared_texture_feed.is_some() {
+            outputs.push(OutputBackend::Compositor);
+        }
+        if self.recorder.is_some() {
+            outputs.push(OutputBackend::Recording);
+        }
+        if self.frame_export.is_some() {
+            outputs.push(OutputBackend::ImageSequence);
+        }
+        outputs
+    }
+
+    /// Time the frame just drawn; once a second, report stats to the parent
+    /// and refresh the HUD
+    fn update_stats(&mut self) {
+        let Some(mut stats) = self.frame_timer.tick(Instant::now()) else {
+            return;
+        };
+        stats.preset = self.ndi_metadata.preset.clone();
+        stats.outputs = self.active_outputs();
+        if self.config.quality.adaptive && self.projectm.is_some() {
+            let budget = self.frame_budget_ms();
+            if let Some(scale) = self.adaptive.update(stats.frame_time_ms, budget) {
+                info!("Rendering at {:.0}% of the frame size", scale * 100.0);
+                self.apply_render_size();
+            }
+            stats.render_scale = Some(self.adaptive.scale());
+        }
+
+        if self.hud_enabled {
+            let text = stats.to_string();
+            match self.hud {
+                Some(ref mut hud) => hud.set_text(text),
+                None => {
+                    let config = TextOverlayConfig {
+                        text,
+                        position: OverlayPosition::Top,
+                        font_size: 0.03,
+                        fade_in_secs: 0.0,
+                        fade_out_secs: 0.0,
+                        ..TextOverlayConfig::default()
+                    };
+                    match TextLayer::new(config) {
+                        Ok(hud) => self.hud = Some(hud),
+                        Err(e) => {
+                            error!("Failed to show HUD: {}", e);
+                            self.send_event(Event::Error {
+                                message: format!("HUD error: {}", e),
+                            });
+                            self.hud_enabled = false;
+                        }
+                    }
+                }
+            }
+        }
+
+        self.send_event(Event::Stats(stats));
+    }
+
+    /// Mix the received input into the frame if it goes at `placement`; a
+    /// failing input is dropped
+    fn draw_input_layer(&mut self, placement: InputPlacement) {
+        let Some(ref mut layer) = self.input_layer else {
+            return;
+        };
+        if layer.placement() != placement {
+            return;
+        }
+        let (width, height) = if let Some(ref window) = self.window {
+            let size = window.inner_size();
+            (size.width, size.height)
+        } else {
+            (self.config.width, self.config.height)
+        };
+        if let Err(e) = layer.draw(width, height) {
+            error!("Input layer {} failed: {}", layer.source_name(), e);
+            self.send_event(Event::Error {
+                message: format!("Input layer error: {}", e),
+            });
+            self.input_layer = None;
+        }
+    }
+
+    /// Capture current framebuffer to pixel buffer
+    fn capture_frame(&mut self) {
+        // Early exit if no video output configured
+        #[cfg(target_os = "linux")]
+        let has_platform_output = self.video_output.is_some();
+        #[cfg(target_os = "windows")]
+        let has_platform_output = self.video_output.is_some();
+        #[cfg(not(any(target_os = "linux", target_os = "windows")))]
+        let has_platform_output = false;
+
+        let has_ndi_output = self.ndi_output.is_some();
+        let has_syphon_output = self.syphon_output.is_some();
+        let has_virtual_camera = self.virtual_camera.is_some();
+        let has_compositor_feed = self.compositor_feed.is_some();
+        let has_recorder =
+            self.recorder.is_some() || self.frame_export.is_some() || self.loop_export.is_some();
+        let has_output = has_platform_output
+            || has_ndi_output
+            || has_syphon_output
+            || has_virtual_camera
+            || has_compositor_feed
+            || has_recorder;
+
+        if !has_output || self.pixel_buffer.is_empty() {
+            // Free the PBOs while nothing is captured
+            self.readback = None;
+            return;
+        }
+
+        // Capped outputs skip frames between their slots (recordings pace themselves)
+        let now = Instant::now();
+        #[cfg(target_os = "windows")]
+        let platform_backend = OutputBackend::Spout;
+        #[cfg(not(target_os = "windows"))]
+        let platform_backend = OutputBackend::V4l2Loopback;
+        let platform_due = has_platform_output && self.output_due(platform_backend, now);
+        let ndi_due = has_ndi_output && self.output_due(OutputBackend::Ndi, now);
+        let syphon_due = has_syphon_output && self.output_due(OutputBackend::Syphon, now);
+        let virtual_camera_due =
+            has_virtual_camera && self.output_due(OutputBackend::VirtualCamera, now);
+        let compositor_due =
+            has_compositor_feed && self.output_due(OutputBackend::Compositor, now);
+        if !(platform_due
+            || ndi_due
+            || syphon_due
+            || virtual_camera_due
+            || compositor_due
+            || has_recorder)
+        {
+            return;
+        }
+
+        // Read pixels from framebuffer asynchronously; outputs get the
+        // previous frame, flipped to top-down while copying it out
+        let readback = self.readback.get_or_insert_with(FrameReadback::new);
+        if !readback.read(self.capture_width, self.capture_height, &mut self.pixel_buffer) {
+            return;
+        }
+
+        // Send to the compositor before the logo goes on (it composites clean
+        // decks, as shared textures do); a closed compositor ends the feed
+        if let Some(feed) = self.compositor_feed.as_mut().filter(|_| compositor_due) {
+            if let Err(e) = feed.send_frame_rgba(
+                &self.pixel_buffer,
+                self.capture_width,
+                self.capture_height,
+            ) {
+                warn!("Compositor feed stopped: {}", e);
+                self.compositor_feed = None;
+            }
+        }
+
+        // Brand everything else: live outputs, recordings and exports
+        if let Some(ref mut overlay) = self.overlay {
+            overlay.apply(&mut self.pixel_buffer, self.capture_width, self.capture_height);
+        }
+
+        // Outputs that carry alpha get the keyed frame when the background is transparent
+        // (the platform output is Spout on Windows; v4l2 has no alpha)
+        let has_alpha_output =
+            ndi_due || syphon_due || (cfg!(target_os = "windows") && platform_due);
+        let key_alpha = self.transparent_background && has_alpha_output;
+        if key_alpha {
+            self.keyed_buffer.resize(self.pixel_buffer.len(), 0);
+            key_alpha_from_luma(&self.pixel_buffer, &mut self.keyed_buffer);
+        }
+        let alpha_pixels = if key_alpha {
+            &self.keyed_buffer
+        } else {
+            &self.pixel_buffer
+        };
+
+        // Send to video output (Linux - v4l2loopback)
+        #[cfg(target_os = "linux")]
+        if let Some(output) = self.video_output.as_mut().filter(|_| platform_due) {
+            if let Err(e) = output.send_frame_rgba(
+                &self.pixel_buffer,
+                self.capture_width,
+                self.capture_height,
+            ) {
+                // Don't spam errors, just log occasionally
+                debug!("Video output frame error: {}", e);
+            }
+        }
+
+        // Send to video output (Windows - Spout)
+        #[cfg(target_os = "windows")]
+        if let Some(output) = self.video_output.as_mut().filter(|_| platform_due) {
+            if let Err(e) = output.send_frame_rgba(
+                alpha_pixels,
+                self.capture_width,
+                self.capture_height,
+            ) {
+                // Don't spam errors, just log occasionally
+                debug!("Spout output frame error: {}", e);
+            }
+        }
+
+        // Send to NDI output (cross-platform)
+        if let Some(output) = self.ndi_output.as_mut().filter(|_| ndi_due) {
+            if let Err(e) = output.send_frame_rgba(
+                alpha_pixels,
+                self.capture_width,
+                self.capture_height,
+            ) {
+                // Don't spam errors, just log occasionally
+                debug!("NDI output frame error: {}", e);
+            }
+        }
+
+        // Send to Syphon output (macOS)
+        if let Some(output) = self.syphon_output.as_mut().filter(|_| syphon_due) {
+            if let Err(e) = output.send_frame_rgba(
+                alpha_pixels,
+                self.capture_width,
+                self.capture_height,
+            ) {
+                // Don't spam errors, just log occasionally
+                debug!("Syphon output frame error: {}", e);
+            }
+        }
+
+        // Send to virtual webcam (Windows)
+        if let Some(output) = self.virtual_camera.as_mut().filter(|_| virtual_camera_due) {
+            if let Err(e) = output.send_frame_rgba(
+                &self.pixel_buffer,
+                self.capture_width,
+                self.capture_height,
+            ) {
+                // Don't spam errors, just log occasionally
+                debug!("Virtual camera frame error: {}", e);
+            }
+        }
+
+        // Send to MP4 recording; a failed recording is stopped and reported
+        if let Some(ref mut recorder) = self.recorder {
+            if let Err(e) = recorder.send_frame_rgba(
+                &self.pixel_buffer,
+                self.capture_width,
+                self.capture_height,
+            ) {
+                self.stop_recording(Some(e.to_string()));
+            }
+        }
+
+        // Send to PNG export; a failed export is stopped and reported
+        if let Some(ref mut exporter) = self.frame_export {
+            if let Err(e) = exporter.send_frame_rgba(
+                &self.pixel_buffer,
+                self.capture_width,
+                self.capture_height,
+            ) {
+                self.stop_frame_export(Some(e.to_string()));
+            }
+        }
+
+        // Send to loop export; once the loop is long enough it is encoded
+        if let Some(ref mut recorder) = self.loop_export {
+            match recorder.send_frame_rgba(
+                &self.pixel_buffer,
+                self.capture_width,
+                self.capture_height,
+            ) {
+                Ok(()) if recorder.is_complete() => self.finish_loop_export(None),
+                Ok(()) => {}
+                Err(e) => self.finish_loop_export(Some(e.to_string())),
+            }
+        }
+    }
+
+    /// Whether `backend`'s FPS cap lets it take the frame captured at `now`
+    fn output_due(&mut self, backend: OutputBackend, now: Instant) -> bool {
+        match self.output_fps.iter_mut().find(|(b, _)| *b == backend) {
+            Some((_, pacer)) => pacer.frames_due(now) > 0,
+            None => true,
+        }
+    }
+
+    /// Show, move or remove the logo on output frames
+    fn set_overlay(&mut self, config: Option<OverlayConfig>) {
+        let Some(config) = config else {
+            info!("Logo overlay removed");
+            self.overlay = None;
+            return;
+        };
+        // The same file keeps its decoded logo
+        let result = match self.overlay.as_mut() {
+            Some(overlay) if overlay.config().path == config.path => overlay.set_config(config),
+            _ => LogoOverlay::load(config).map(|overlay| self.overlay = Some(overlay)),
+        };
+        match result {
+            Ok(()) => {
+                if let Some(ref overlay) = self.overlay {
+                    let config = overlay.config();
+                    info!(
+                        "Logo overlay: {} at {}, {:.0}% wide, {:.0}% opacity",
+                        config.path.display(),
+                        config.position,
+                        config.scale * 100.0,
+                        config.opacity * 100.0
+                    );
+                }
+            }
+            Err(e) => {
+                error!("Failed to set logo overlay: {}", e);
+                self.send_event(Event::Error {
+                    message: format!("Logo overlay error: {}", e),
+                });
+            }
+        }
+    }
+
+    /// Show, change or remove the background
+    fn set_background(&mut self, config: Option<BackgroundConfig>) {
+        let Some(config) = config else {
+            info!("Background removed");
+            self.background = None;
+            return;
+        };
+        // The same file keeps playing
+        let result = match self.background.as_mut() {
+            Some(background) if background.config().path == config.path => {
+                background.set_config(config)
+            }
+            _ => BackgroundLayer::new(config).map(|background| self.background = Some(background)),
+        };
+        match result {
+            Ok(()) => {
+                if let Some(ref background) = self.background {
+                    let config = background.config();
+                    info!(
+                        "Background: {} ({:?}, {:?}, {:.0}% opacity)",
+                        config.path.display(),
+                        config.blend,
+                        config.fit,
+                        config.opacity * 100.0
+                    );
+                }
+            }
+            Err(e) => {
+                error!("Failed to set background: {}", e);
+                self.send_event(Event::Error {
+                    message: format!("Background error: {}", e),
+                });
+            }
+        }
+    }
+
+    /// Draw the background under the frame; a failing video is dropped
+    fn draw_background(&mut self, width: u32, height: u32) {
+        let Some(ref mut background) = self.background else {
+            return;
+        };
+        if let Err(e) = background.draw(width, height) {
+            error!("Background {} failed: {}", background.config().path.display(), e);
+            self.send_event(Event::Error {
+                message: format!("Background error: {}", e),
+            });
+            self.background = None;
+        }
+    }
+
+    /// Cap `backend` at `fps` frames per second, or remove its cap
+    fn set_output_fps(&mut self, backend: OutputBackend, fps: Option<u32>) {
+        self.output_fps.retain(|(b, _)| *b != backend);
+        match fps.filter(|&fps| fps > 0) {
+            Some(fps) => {
+                info!("{} output capped at {} fps", backend, fps);
+                self.output_fps.push((backend, FramePacer::new(fps)));
+            }
+            None => info!("{} output runs at the render rate", backend),
+        }
+    }
+
+    /// Load a preset, crossfading from the current one unless the
+    /// transition time is 0
+    fn load_preset(&mut self, path: String) {
+        let Some(mut current) = self.projectm.take() else {
+            return;
+        };
+        // A load answering projectM's request blends the way it asked
+        let fade_secs = match self.cut_request.take() {
+            Some((fade_secs, asked)) if asked.elapsed() < CUT_REQUEST_TIMEOUT => fade_secs,
+            _ => self.transition_secs,
+        };
+        let result = if fade_secs > 0.0 {
+            self.crossfade(&mut current, &path, fade_secs)
+        } else {
+            current.load_preset(&path, false).map_err(|e| e.to_string())
+        };
+        self.projectm = Some(current);
+
+        match result {
+            Ok(()) => {
+                info!("Loaded preset: {}", path);
+                self.preset_watcher = Some(PresetWatcher::new(&path));
+                self.ndi_metadata.preset = preset_name(&path);
+                if let Some(ref mut output) = self.ndi_output {
+                    output.set_metadata(&self.ndi_metadata);
+                }
+                self.send_event(Event::PresetLoaded { path });
+            }
+            Err(e) => {
+                error!("Failed to load preset {}: {}", path, e);
+                let current = self
+                    .projectm
+                    .as_ref()
+                    .and_then(|pm| pm.current_preset())
+                    .map(str::to_string);
+                self.send_event(Event::PresetFailed {
+                    path,
+                    error: e,
+                    current,
+                });
+            }
+        }
+    }
+
+    /// Reload the preset if its file was saved since the last check, cutting
+    /// straight to it so edits show at once
+    fn reload_changed_preset(&mut self) {
+        let Some(ref mut watcher) = self.preset_watcher else {
+            return;
+        };
+        if !watcher.changed(Instant::now()) {
+            return;
+        }
+        let path = watcher.path().to_string_lossy().into_owned();
+        let Some(ref mut pm) = self.projectm else {
+            return;
+        };
+        match pm.load_preset(&path, false) {
+            Ok(()) => {
+                info!("Preset file changed, reloaded: {}", path);
+                self.send_event(Event::PresetLoaded { path });
+            }
+            Err(e) => {
+                warn!("Failed to reload changed preset {}: {}", path, e);
+                self.send_event(Event::Error {
+                    message: format!("Preset reload failed: {}", e),
+                });
+            }
+        }
+    }
+
+    /// Load `path` on the second instance and fade to it over `fade_secs`;
+    /// `current` ends up drawing the new preset and the old instance becomes
+    /// the spare
+    fn crossfade(&mut self, current: &mut ProjectM, path: &str, fade_secs: f64) -> Result<(), String> {
+        let transition = match self.transition {
+            Some(ref mut transition) => transition,
+            None => match PresetTransition::new() {
+                Ok(transition) => self.transition.insert(transition),
+                Err(e) => {
+                    warn!("Preset crossfade unavailable, cutting instead: {}", e);
+                    return current.load_preset(path, false).map_err(|e| e.to_string());
+                }
+            },
+        };
+
+        // A load during a fade cuts the fade short
+        let mut incoming = match transition.take_spare() {
+            Some(spare) => spare,
+            None => {
+                let (width, height) = current.dimensions();
+                let mut pm = match ProjectM::new(width, height) {
+                    Ok(pm) => pm,
+                    Err(e) => {
+                        warn!("Preset crossfade unavailable, cutting instead: {}", e);
+                        return current.load_preset(path, false).map_err(|e| e.to_string());
+                    }
+                };
+                if !self.config.texture_paths.is_empty() {
+                    let path_refs: Vec<&str> =
+                        self.config.texture_paths.iter().map(|s| s.as_str()).collect();
+                    pm.set_texture_search_paths(&path_refs);
+                }
+                pm.set_framebuffer(current.framebuffer());
+                apply_quality(&mut pm, &self.config.quality);
+                apply_cuts(&mut pm, &self.config.preset_cuts);
+                pm
             }
+        };
+        // Whatever it asked for while fading out is stale
+        incoming.take_switch_request();
+        incoming.set_beat_sensitivity(current.beat_sensitivity());
+        incoming.set_preset_locked(self.config.preset_locked);
+        if let Err(e) = incoming.load_preset(path, false) {
+            transition.put_spare(incoming);
+            return Err(e.to_string());
         }
 
-        // Send to NDI output (cross-platform)
-        if let Some(ref mut output) = self.ndi_output {
-            if let Err(e) = output.send_frame_rgba(
-                &self.pixel_buffer,
-                self.capture_width,
-                self.capture_height,
-            ) {
-                // Don't spam errors, just log occasionally
-                debug!("NDI output frame error: {}", e);
-            }
-        }
+        let outgoing = std::mem::replace(current, incoming);
+        transition.start(outgoing, Duration::from_secs_f64(fade_secs));
+        Ok(())
     }
 
-    fn process_commands(&mut self, event_loop: &ActiveEventLoop) {
+    /// Apply pending commands; sets `should_exit` on stop or when the parent
+    /// goes away
+    fn process_commands(&mut self) {
         loop {
             match self.command_rx.try_recv() {
                 Ok(cmd) => match cmd {
                     Command::LoadPreset { path } => {
+                        self.load_preset(path);
+                    }
+                    Command::SetTransitionTime { seconds } => {
+                        self.transition_secs = seconds.clamp(0.0, MAX_TRANSITION_SECS);
+                        if self.transition_secs == 0.0 {
+                            // Free the second projectM instance
+                            self.transition = None;
+                        }
+                        info!("Preset transition time set to {:.1}s", self.transition_secs);
+                    }
+                    Command::SetPresetCuts { cuts } => {
+                        self.set_preset_cuts(cuts);
+                    }
+                    Command::Audio { samples, channels } => {
+                        // A frozen deck isn't fed, so it picks up from live audio
                         if let Some(ref mut pm) = self.projectm {
-                            match pm.load_preset(&path, true) {
-                                Ok(()) => {
-                                    info!("Loaded preset: {}", path);
-                                    send_event(Event::PresetLoaded { path });
-                                }
-                                Err(e) => {
-                                    error!("Failed to load preset: {}", e);
-                                    send_event(Event::Error {
-                                        message: e.to_string(),
-                                    });
+                            if !self.frozen {
+                                let channels = Channels::from_count(channels).unwrap_or(Channels::Stereo);
+                                pm.add_pcm(&samples, channels);
+                                if let Some(ref mut transition) = self.transition {
+                                    transition.add_pcm(&samples, channels);
                                 }
                             }
                         }
-                    }
-                    Command::Audio { samples } => {
-                        if let Some(ref mut pm) = self.projectm {
-                            pm.add_pcm_stereo(&samples);
+                        if let Some(ref mut layer) = self.idle_layer {
+                            layer.push_audio(&samples, channels as u16);
                         }
                     }
                     Command::ToggleFullscreen => {
@@ -419,33 +2547,161 @@ impl RenderApp {
                             if is_fullscreen {
                                 window.set_fullscreen(None);
                             } else {
-                                window.set_fullscreen(Some(
-                                    winit::window::Fullscreen::Borderless(None),
-                                ));
+                                window.set_fullscreen(Some(self.fullscreen(window, None)));
                             }
                         }
                     }
+                    Command::SetAlwaysOnTop { enabled } => {
+                        self.set_always_on_top(enabled);
+                    }
+                    Command::SetClickThrough { enabled } => {
+                        self.set_click_through(enabled);
+                    }
+                    Command::SetWindowGeometry { x, y, width, height } => {
+                        self.set_window_geometry(x, y, width, height);
+                    }
                     Command::SetBeatSensitivity { value } => {
                         if let Some(ref mut pm) = self.projectm {
                             pm.set_beat_sensitivity(value);
                         }
                     }
-                    Command::SetVideoOutput { enabled, device_path } => {
-                        self.set_video_output(enabled, device_path);
+                    Command::SetVideoOutput { enabled, device_path, pixel_format } => {
+                        self.set_video_output(enabled, device_path, pixel_format);
+                    }
+                    Command::SetNdiOutput { enabled, name, groups, failover, clock_video, pixel_format } => {
+                        self.set_ndi_output(enabled, name, groups, failover, clock_video, pixel_format);
+                    }
+                    Command::SetNdiMetadata { bpm } => {
+                        self.ndi_metadata.bpm = bpm;
+                        if let Some(ref mut output) = self.ndi_output {
+                            output.set_metadata(&self.ndi_metadata);
+                        }
+                    }
+                    Command::SetSyphonOutput { enabled, name } => {
+                        self.set_syphon_output(enabled, name);
+                    }
+                    Command::SetCompositorLayers { layers } => {
+                        if let Some(ref mut compositor) = self.compositor {
+                            compositor.set_layers(layers);
+                        }
+                    }
+                    Command::SetCompositorFeed { port, gpu_socket } => {
+                        self.set_compositor_feed(port, gpu_socket);
+                    }
+                    Command::SetOutputFps { output, fps } => {
+                        self.set_output_fps(output, fps);
+                    }
+                    Command::SetTransparentBackground { enabled } => {
+                        info!("Transparent background {}", if enabled { "enabled" } else { "disabled" });
+                        self.transparent_background = enabled;
+                        if !enabled {
+                            self.keyed_buffer = Vec::new();
+                        }
+                    }
+                    Command::SetOverlay { overlay } => {
+                        self.set_overlay(overlay);
+                    }
+                    Command::SetBackground { background } => {
+                        self.set_background(background);
+                    }
+                    Command::SetTestPattern { enabled } => {
+                        self.set_test_pattern(enabled);
+                    }
+                    Command::SetEffect { effect, intensity } => {
+                        self.set_effect(effect, intensity);
+                    }
+                    Command::SetColorAdjust { adjust, fade_secs } => {
+                        self.set_color_adjust(adjust, fade_secs);
+                    }
+                    Command::SetBlackout { enabled, fade_secs } => {
+                        self.set_blackout(enabled, fade_secs);
+                    }
+                    Command::SetTimeScale { scale } => {
+                        self.set_time_scale(scale);
+                    }
+                    Command::SetFreeze { enabled } => {
+                        self.set_freeze(enabled);
+                    }
+                    Command::SetStrobe { strobe } => {
+                        self.set_strobe(strobe);
+                    }
+                    Command::Beat { bpm } => {
+                        if let Some(ref mut strobe) = self.strobe {
+                            strobe.beat(bpm, Instant::now());
+                        }
+                    }
+                    Command::SetHud { enabled } => {
+                        self.set_hud(enabled);
+                    }
+                    Command::LockPreset { locked } => {
+                        self.lock_preset(locked);
+                    }
+                    Command::SetQuality { quality } => {
+                        self.set_quality(quality);
+                    }
+                    Command::SetFpsCap { fps } => {
+                        self.set_fps_cap(fps);
                     }
-                    Command::SetNdiOutput { enabled, name } => {
-                        self.set_ndi_output(enabled, name);
+                    Command::SetOverlayText { text } => {
+                        self.set_overlay_text(text);
+                    }
+                    Command::SetVirtualCamera { enabled } => {
+                        self.set_virtual_camera(enabled);
+                    }
+                    Command::StartRecording { path, fps, bitrate_kbps, codec } => {
+                        self.start_recording(path, fps, bitrate_kbps, codec);
+                    }
+                    Command::StopRecording => {
+                        self.stop_recording(None);
+                    }
+                    Command::StartFrameExport { directory, fps } => {
+                        self.start_frame_export(directory, fps);
+                    }
+                    Command::StopFrameExport => {
+                        self.stop_frame_export(None);
+                    }
+                    Command::StartLoopExport { path, format, duration_secs, crossfade_secs } => {
+                        self.start_loop_export(path, format, duration_secs, crossfade_secs);
+                    }
+                    Command::CancelLoopExport => {
+                        self.cancel_loop_export();
+                    }
+                    Command::SetInputLayer { enabled, backend, source, blend, placement, opacity } => {
+                        self.set_input_layer(enabled, backend, source, blend, placement, opacity);
+                    }
+                    Command::SetInputMix { blend, opacity, placement } => {
+                        if let Some(ref mut layer) = self.input_layer {
+                            layer.set_mix(blend, opacity);
+                            if let Some(placement) = placement {
+                                layer.set_placement(placement);
+                            }
+                        }
                     }
                     Command::SetTexturePaths { paths } => {
                         if let Some(ref mut pm) = self.projectm {
                             let path_refs: Vec<&str> = paths.iter().map(|s| s.as_str()).collect();
                             pm.set_texture_search_paths(&path_refs);
+                            if let Some(spare) = self.transition.as_mut().and_then(|t| t.spare_mut()) {
+                                spare.set_texture_search_paths(&path_refs);
+                            }
                             info!("Set {} texture search paths", paths.len());
                         }
+                        // Instances created for crossfades start with these
+                        self.config.texture_paths = paths;
+                    }
+                    Command::OpenDeck { .. } => {
+                        warn!("Opening decks needs a multi-deck renderer");
+                    }
+                    Command::Thumbnail { .. } => {
+                        warn!("Thumbnails need a thumbnailer renderer");
                     }
                     Command::Stop => {
+                        // Finalize files before the parent stops waiting
+                        self.stop_recording(None);
+                        self.stop_frame_export(None);
+                        self.cancel_loop_export();
+                        self.wait_for_loop_encoder();
                         self.should_exit = true;
-                        event_loop.exit();
                         return;
                     }
                 },
@@ -453,7 +2709,6 @@ impl RenderApp {
                 Err(TryRecvError::Disconnected) => {
                     // Parent process closed, exit
                     self.should_exit = true;
-                    event_loop.exit();
                     return;
                 }
             }
@@ -461,14 +2716,109 @@ impl RenderApp {
     }
 
     fn render(&mut self) {
-        // Render projectM frame
-        if let Some(ref mut pm) = self.projectm {
-            pm.render_frame();
+        let (width, height) = self.frame_size();
+        if let Some(ref offscreen) = self.offscreen {
+            offscreen.bind();
+        }
+
+        // Render projectM frame, or the blended decks when compositing;
+        // the test pattern replaces both, so outputs can be lined up, and
+        // the idle visual stands in for projectM until it has a preset
+        let idle = !self.idle_failed
+            && self.projectm.as_ref().is_some_and(|pm| pm.current_preset().is_none());
+        if let Some(ref mut pattern) = self.test_pattern {
+            pattern.draw(width, height);
+        } else if idle {
+            self.draw_idle(width, height);
+        } else if let Some(ref mut pm) = self.projectm {
+            self.idle_layer = None;
+            let output = self.offscreen.as_ref().map_or(0, Offscreen::framebuffer);
+            let now = Instant::now();
+            match self.frozen_frame {
+                Some(ref frame) if self.frozen => {
+                    self.clock.hold(now);
+                    frame.blit_to(output, width, height);
+                }
+                _ => {
+                    // Both instances of a crossfade run on the deck's clock
+                    let time = self.clock.tick(now);
+                    pm.set_frame_time(Some(time));
+                    if let Some(spare) = self.transition.as_mut().and_then(PresetTransition::spare_mut) {
+                        spare.set_frame_time(Some(time));
+                    }
+                    if let Some(ref scaled) = self.scaled {
+                        scaled.bind();
+                    }
+                    match self.transition {
+                        Some(ref mut transition) => {
+                            let (width, height) = pm.dimensions();
+                            transition.render(pm, width, height);
+                        }
+                        None => pm.render_frame(),
+                    }
+                    if let Some(ref scaled) = self.scaled {
+                        scaled.blit_to(output, width, height);
+                    }
+                    // Keep this frame to show until unfrozen
+                    if self.frozen {
+                        match Offscreen::new(width, height) {
+                            Ok(frame) => {
+                                frame.blit_from(output, width, height);
+                                self.frozen_frame = Some(frame);
+                            }
+                            Err(e) => {
+                                error!("Failed to hold frame: {}", e);
+                                self.frozen = false;
+                            }
+                        }
+                    }
+                }
+            }
+        } else if let Some(ref mut compositor) = self.compositor {
+            compositor.draw(width, height);
+        }
+        // projectM can leave one of its own framebuffers bound
+        if let Some(ref offscreen) = self.offscreen {
+            offscreen.bind();
+        }
+
+        if self.test_pattern.is_none() {
+            // Under the visualization, so effects apply to both
+            self.draw_background(width, height);
+            self.draw_input_layer(InputPlacement::Under);
+
+            // Post-process the visualization before anything is mixed in over it
+            if let Some(ref mut effects) = self.effects {
+                effects.draw(width, height);
+            }
+
+            // Mix in the input layer, so outputs see it too
+            self.draw_input_layer(InputPlacement::Over);
+
+            // Text goes on top of everything
+            self.draw_text_layer();
         }
 
+        // Corrected last, so outputs match the window; the test pattern
+        // too, to line projectors up by
+        self.draw_color_adjust(width, height);
+
+        // Flashes and blackout go over everything, outputs included
+        self.draw_strobe();
+
+        // Hand the frame to the compositor without leaving the GPU
+        self.share_frame();
+
         // Capture frame for video output (before swap)
         self.capture_frame();
 
+        // The HUD goes on the window only, after outputs have their copy
+        self.update_stats();
+        if let (Some(ref mut hud), Some(ref window)) = (&mut self.hud, &self.window) {
+            let size = window.inner_size();
+            hud.draw(size.width, size.height);
+        }
+
         // Swap buffers
         if let (Some(ref surface), Some(ref context)) = (&self.gl_surface, &self.gl_context) {
             if let Err(e) = surface.swap_buffers(context) {
@@ -477,6 +2827,174 @@ impl RenderApp {
         }
     }
 
+    /// Set up the compositor or projectM on the current context; returns
+    /// the compositor's ready event to send after `Ready`
+    fn init_gl(
+        &mut self,
+        display: &Display,
+        context: &PossiblyCurrentContext,
+        width: u32,
+        height: u32,
+    ) -> Option<Event> {
+        // The compositor blends decks' frames instead of running projectM
+        self.egl = Egl::from_context(display, context);
+        let mut compositor_ready = None;
+        if self.config.compositor {
+            match Compositor::new(self.egl) {
+                Ok(compositor) => {
+                    compositor_ready = Some(Event::CompositorReady {
+                        port: compositor.port(),
+                        gpu_socket: compositor.gpu_socket().map(str::to_string),
+                    });
+                    self.compositor = Some(compositor);
+                }
+                Err(e) => {
+                    error!("Failed to create compositor: {}", e);
+                    self.send_event(Event::Error { message: e });
+                }
+            }
+        } else {
+            // Create projectM
+            match ProjectM::new(width, height) {
+                Ok(mut pm) => {
+                    info!("ProjectM {} initialized", ProjectM::version());
+
+                    // Set texture search paths before loading preset
+                    if !self.config.texture_paths.is_empty() {
+                        let path_refs: Vec<&str> =
+                            self.config.texture_paths.iter().map(|s| s.as_str()).collect();
+                        pm.set_texture_search_paths(&path_refs);
+                        info!("Set {} texture search paths from config", self.config.texture_paths.len());
+                    }
+
+                    apply_quality(&mut pm, &self.config.quality);
+                    apply_cuts(&mut pm, &self.config.preset_cuts);
+                    pm.set_preset_locked(self.config.preset_locked);
+
+                    if let Some(ref preset_path) = self.config.preset_path {
+                        match pm.load_preset(preset_path, false) {
+                            Ok(()) => self.preset_watcher = Some(PresetWatcher::new(preset_path)),
+                            Err(e) => {
+                                warn!("Failed to load initial preset: {}", e);
+                                self.send_event(Event::PresetFailed {
+                                    path: preset_path.clone(),
+                                    error: e.to_string(),
+                                    current: None,
+                                });
+                            }
+                        }
+                    }
+
+                    self.projectm = Some(pm);
+                }
+                Err(e) => {
+                    error!("Failed to create ProjectM instance: {}", e);
+                    self.send_event(Event::Error {
+                        message: e.to_string(),
+                    });
+                }
+            }
+        }
+        if self.config.background.is_some() {
+            self.set_background(self.config.background.clone());
+        }
+        compositor_ready
+    }
+
+    /// Render without a window until stopped, paced to [`HEADLESS_FPS`] or
+    /// a lower FPS cap
+    fn run_headless(&mut self) {
+        info!(
+            "Rendering headless at {}x{}",
+            self.config.width, self.config.height
+        );
+        let (display, context) = match headless::create_context() {
+            Ok(result) => result,
+            Err(e) => {
+                error!("Failed to create headless context: {}", e);
+                self.send_event(Event::Error { message: e });
+                return;
+            }
+        };
+        let gpu = load_gl(&display);
+        let offscreen = match Offscreen::new(self.config.width, self.config.height) {
+            Ok(offscreen) => offscreen,
+            Err(e) => {
+                error!("Failed to create offscreen framebuffer: {}", e);
+                self.send_event(Event::Error { message: e });
+                return;
+            }
+        };
+
+        let compositor_ready = self.init_gl(&display, &context, self.config.width, self.config.height);
+        self.offscreen = Some(offscreen);
+        self.gl_context = Some(context);
+        self.apply_render_size();
+
+        self.send_event(Event::Ready);
+        if let Some(gpu) = gpu {
+            self.send_event(Event::GpuInfo(gpu));
+        }
+        if let Some(event) = compositor_ready {
+            self.send_event(event);
+        }
+
+        let mut next_frame = Instant::now();
+        while !self.should_exit {
+            self.process_commands();
+            if self.should_exit {
+                break;
+            }
+            self.reload_changed_preset();
+            self.forward_switch_request();
+            self.render();
+
+            let fps = self.config.fps_cap.unwrap_or(HEADLESS_FPS).min(HEADLESS_FPS);
+            next_frame += Duration::from_secs(1) / fps;
+            let now = Instant::now();
+            if next_frame > now {
+                thread::sleep(next_frame - now);
+            } else {
+                // Running behind; don't rush to catch up
+                next_frame = now;
+            }
+        }
+
+        self.free_gl();
+    }
+
+    /// Free GL objects while the context is still alive
+    fn free_gl(&mut self) {
+        self.input_layer = None;
+        self.test_pattern = None;
+        self.effects = None;
+        self.color_pass = None;
+        self.strobe_layer = None;
+        self.frozen_frame = None;
+        self.text_layer = None;
+        self.idle_layer = None;
+        self.hud = None;
+        self.transition = None;
+        self.compositor = None;
+        self.compositor_feed = None;
+        self.shared_texture_feed = None;
+        self.readback = None;
+        self.scaled = None;
+        self.offscreen = None;
+    }
+
+    /// Size of the window, or of the offscreen framebuffer when headless
+    fn frame_size(&self) -> (u32, u32) {
+        match (&self.window, &self.offscreen) {
+            (Some(window), _) => {
+                let size = window.inner_size();
+                (size.width, size.height)
+            }
+            (None, Some(offscreen)) => offscreen.size(),
+            (None, None) => (self.config.width, self.config.height),
+        }
+    }
+
     fn handle_resize(&mut self, size: PhysicalSize<u32>) {
         if size.width == 0 || size.height == 0 {
             return;
@@ -491,13 +3009,51 @@ impl RenderApp {
             surface.resize(context, w, h);
         }
 
-        if let Some(ref mut pm) = self.projectm {
-            pm.resize(size.width, size.height);
-        }
-
         unsafe {
             gl::Viewport(0, 0, size.width as i32, size.height as i32);
         }
+        self.apply_render_size();
+    }
+
+    /// Make this deck's context current again after another deck's
+    fn make_current(&self) {
+        if let (Some(ref surface), Some(ref context)) = (&self.gl_surface, &self.gl_context) {
+            if let Err(e) = context.make_current(surface) {
+                error!("Failed to make context current: {}", e);
+            }
+        }
+    }
+
+    /// Wait for vsync on buffer swaps; with several windows on one thread
+    /// only one should, or each waits its turn
+    fn set_vsync(&self, enabled: bool) {
+        if let (Some(ref surface), Some(ref context)) = (&self.gl_surface, &self.gl_context) {
+            let interval = if enabled {
+                SwapInterval::Wait(NonZeroU32::new(1).unwrap())
+            } else {
+                SwapInterval::DontWait
+            };
+            if let Err(e) = surface.set_swap_interval(context, interval) {
+                warn!("Failed to set swap interval: {}", e);
+            }
+        }
+    }
+
+    /// Work done between window events: commands, preset reloads and
+    /// geometry reports, then ask for the next frame
+    fn update(&mut self) {
+        self.process_commands();
+        if self.should_exit {
+            return;
+        }
+        self.reload_changed_preset();
+        self.forward_switch_request();
+        self.report_geometry();
+        if self.frame_due(Instant::now()) {
+            if let Some(ref window) = self.window {
+                window.request_redraw();
+            }
+        }
     }
 }
 
@@ -512,10 +3068,19 @@ impl ApplicationHandler for RenderApp {
             self.config.width, self.config.height
         );
 
-        let window_title = format!("OpenDrop - Deck {}", self.config.deck_id + 1);
+        let window_title = if self.config.compositor {
+            "OpenDrop - Compositor".to_string()
+        } else {
+            format!("OpenDrop - Deck {}", self.config.deck_id + 1)
+        };
         let window_attrs = WindowAttributes::default()
             .with_title(window_title)
-            .with_inner_size(LogicalSize::new(self.config.width, self.config.height));
+            .with_inner_size(LogicalSize::new(self.config.width, self.config.height))
+            .with_window_level(window_level(self.config.always_on_top));
+        let window_attrs = match (self.config.x, self.config.y) {
+            (Some(x), Some(y)) => window_attrs.with_position(LogicalPosition::new(x, y)),
+            _ => window_attrs,
+        };
 
         let template = ConfigTemplateBuilder::new()
             .with_alpha_size(8)
@@ -523,6 +3088,9 @@ impl ApplicationHandler for RenderApp {
             .with_stencil_size(8);
 
         let display_builder = DisplayBuilder::new().with_window_attributes(Some(window_attrs));
+        // EGL can share textures with the compositor as DMA-BUFs; GLX can't
+        #[cfg(target_os = "linux")]
+        let display_builder = display_builder.with_preference(ApiPreference::PreferEgl);
 
         let (window, gl_config) = match display_builder.build(event_loop, template, |configs| {
             configs
@@ -538,10 +3106,10 @@ impl ApplicationHandler for RenderApp {
             Ok(result) => result,
             Err(e) => {
                 error!("Failed to create window: {}", e);
-                send_event(Event::Error {
+                self.send_event(Event::Error {
                     message: e.to_string(),
                 });
-                event_loop.exit();
+                self.should_exit = true;
                 return;
             }
         };
@@ -550,15 +3118,16 @@ impl ApplicationHandler for RenderApp {
         let raw_window_handle = window.window_handle().ok().map(|h| h.as_raw());
 
         let gl_display = gl_config.display();
-        let context_attrs = ContextAttributesBuilder::new()
-            .with_context_api(ContextApi::OpenGl(Some(Version::new(3, 3))))
-            .build(raw_window_handle);
-
-        let not_current_context = unsafe {
-            gl_display
-                .create_context(&gl_config, &context_attrs)
-                .expect("Failed to create OpenGL context")
-        };
+        let not_current_context =
+            match gl_context::create_context(&gl_display, &gl_config, raw_window_handle) {
+                Ok(context) => context,
+                Err(e) => {
+                    error!("{}", e);
+                    self.send_event(Event::Error { message: e });
+                    self.should_exit = true;
+                    return;
+                }
+            };
 
         let attrs = window
             .build_surface_attributes(SurfaceAttributesBuilder::new())
@@ -574,57 +3143,13 @@ impl ApplicationHandler for RenderApp {
             .make_current(&surface)
             .expect("Failed to make context current");
 
-        gl::load_with(|s| {
-            let c_str = CString::new(s).unwrap();
-            gl_display.get_proc_address(&c_str) as *const _
-        });
+        let gpu = load_gl(&gl_display);
 
         // Vsync
         let _ = surface.set_swap_interval(&context, SwapInterval::Wait(NonZeroU32::new(1).unwrap()));
 
-        unsafe {
-            let version_ptr = gl::GetString(gl::VERSION);
-            let renderer_ptr = gl::GetString(gl::RENDERER);
-
-            if !version_ptr.is_null() && !renderer_ptr.is_null() {
-                let version = std::ffi::CStr::from_ptr(version_ptr as *const _);
-                let renderer = std::ffi::CStr::from_ptr(renderer_ptr as *const _);
-                info!("OpenGL version: {:?}", version);
-                info!("OpenGL renderer: {:?}", renderer);
-            } else {
-                warn!("Failed to get OpenGL version/renderer strings (null pointer)");
-            }
-        }
-
-        // Create projectM
         let size = window.inner_size();
-        match ProjectM::new(size.width, size.height) {
-            Ok(mut pm) => {
-                info!("ProjectM {} initialized", ProjectM::version());
-
-                // Set texture search paths before loading preset
-                if !self.config.texture_paths.is_empty() {
-                    let path_refs: Vec<&str> =
-                        self.config.texture_paths.iter().map(|s| s.as_str()).collect();
-                    pm.set_texture_search_paths(&path_refs);
-                    info!("Set {} texture search paths from config", self.config.texture_paths.len());
-                }
-
-                if let Some(ref preset_path) = self.config.preset_path {
-                    if let Err(e) = pm.load_preset(preset_path, false) {
-                        warn!("Failed to load initial preset: {}", e);
-                    }
-                }
-
-                self.projectm = Some(pm);
-            }
-            Err(e) => {
-                error!("Failed to create ProjectM instance: {}", e);
-                send_event(Event::Error {
-                    message: e.to_string(),
-                });
-            }
-        }
+        let compositor_ready = self.init_gl(&gl_display, &context, size.width, size.height);
 
         // Set fullscreen if requested
         if self.config.fullscreen {
@@ -637,26 +3162,42 @@ impl ApplicationHandler for RenderApp {
                 None // Use primary (Borderless(None) means primary)
             };
 
-            window.set_fullscreen(Some(winit::window::Fullscreen::Borderless(monitor)));
+            window.set_fullscreen(Some(self.fullscreen(&window, monitor)));
         }
 
         self.gl_context = Some(context);
         self.gl_surface = Some(surface);
         self.window = Some(window);
+        self.apply_render_size();
+        // Hit testing can only be changed once the window exists
+        if self.config.click_through {
+            self.set_click_through(true);
+        }
+        self.geometry_changed = Some(Instant::now());
 
-        send_event(Event::Ready);
+        self.send_event(Event::Ready);
+        if let Some(gpu) = gpu {
+            self.send_event(Event::GpuInfo(gpu));
+        }
+        if let Some(event) = compositor_ready {
+            self.send_event(event);
+        }
     }
 
-    fn window_event(&mut self, event_loop: &ActiveEventLoop, _id: WindowId, event: WindowEvent) {
+    fn window_event(&mut self, _event_loop: &ActiveEventLoop, _id: WindowId, event: WindowEvent) {
         match event {
             WindowEvent::CloseRequested => {
                 info!("Window close requested");
-                send_event(Event::Closed);
-                event_loop.exit();
+                self.send_event(Event::Closed);
+                self.should_exit = true;
             }
             WindowEvent::Resized(size) => {
                 debug!("Window resized to {:?}", size);
                 self.handle_resize(size);
+                self.geometry_changed = Some(Instant::now());
+            }
+            WindowEvent::Moved(_) => {
+                self.geometry_changed = Some(Instant::now());
             }
             WindowEvent::KeyboardInput {
                 event:
@@ -669,8 +3210,8 @@ impl ApplicationHandler for RenderApp {
             } => match key {
                 Key::Named(NamedKey::Escape) => {
                     info!("Escape pressed, closing window");
-                    send_event(Event::Closed);
-                    event_loop.exit();
+                    self.send_event(Event::Closed);
+                    self.should_exit = true;
                 }
                 Key::Named(NamedKey::F11) => {
                     if let Some(ref window) = self.window {
@@ -678,26 +3219,60 @@ impl ApplicationHandler for RenderApp {
                         if is_fullscreen {
                             window.set_fullscreen(None);
                         } else {
-                            window.set_fullscreen(Some(winit::window::Fullscreen::Borderless(None)));
+                            window.set_fullscreen(Some(self.fullscreen(window, None)));
                         }
                     }
                 }
+                Key::Character(ref c) if c == "h" => {
+                    self.set_hud(!self.hud_enabled);
+                }
+                Key::Character(ref c) if c == "n" => {
+                    self.request_preset(Event::RequestNextPreset);
+                }
+                Key::Named(NamedKey::Space) => {
+                    self.request_preset(Event::RequestNextPreset);
+                }
+                Key::Character(ref c) if c == "p" => {
+                    self.request_preset(Event::RequestPreviousPreset);
+                }
+                Key::Character(ref c) if c == "r" => {
+                    self.request_preset(Event::RequestRandomPreset);
+                }
                 Key::Character(ref c) if c == "f" => {
                     if let Some(ref window) = self.window {
                         let is_fullscreen = window.fullscreen().is_some();
                         if is_fullscreen {
                             window.set_fullscreen(None);
                         } else {
-                            window.set_fullscreen(Some(winit::window::Fullscreen::Borderless(None)));
+                            window.set_fullscreen(Some(self.fullscreen(window, None)));
                         }
                     }
                 }
                 _ => {}
             },
+            WindowEvent::CursorMoved { position, .. } => {
+                self.cursor = Some((position.x, position.y));
+                if self.touching {
+                    let position = self.touch_position();
+                    if let (Some(projectm), Some((x, y))) = (self.projectm.as_mut(), position) {
+                        projectm.touch_drag(x, y, 1);
+                    }
+                }
+            }
+            WindowEvent::CursorLeft { .. } => {
+                self.cursor = None;
+                self.touching = false;
+            }
+            WindowEvent::MouseInput { button, state, .. } => {
+                self.handle_mouse(button, state);
+            }
             WindowEvent::RedrawRequested => {
                 self.render();
-                if let Some(ref window) = self.window {
-                    window.request_redraw();
+                // Capped frames are requested when due
+                if self.config.fps_cap.is_none() {
+                    if let Some(ref window) = self.window {
+                        window.request_redraw();
+                    }
                 }
             }
             _ => {}
@@ -705,23 +3280,102 @@ impl ApplicationHandler for RenderApp {
     }
 
     fn about_to_wait(&mut self, event_loop: &ActiveEventLoop) {
-        self.process_commands(event_loop);
-        if !self.should_exit {
-            if let Some(ref window) = self.window {
-                window.request_redraw();
+        self.update();
+        if self.should_exit {
+            event_loop.exit();
+            return;
+        }
+        event_loop.set_control_flow(match self.next_frame_at() {
+            Some(at) => ControlFlow::WaitUntil(at),
+            None => ControlFlow::Poll,
+        });
+    }
+
+    fn exiting(&mut self, _event_loop: &ActiveEventLoop) {
+        self.free_gl();
+    }
+}
+
+fn window_level(always_on_top: bool) -> WindowLevel {
+    if always_on_top {
+        WindowLevel::AlwaysOnTop
+    } else {
+        WindowLevel::Normal
+    }
+}
+
+/// Mesh size and FPS hint; the texture size is applied by sizing the render
+fn apply_quality(pm: &mut ProjectM, quality: &RenderQuality) {
+    pm.set_mesh_size(quality.mesh_width as usize, quality.mesh_height as usize);
+    pm.set_fps(quality.fps as i32);
+}
+
+/// When projectM asks for preset switches; it's never left to time presets
+/// when the duration is 0
+fn apply_cuts(pm: &mut ProjectM, cuts: &PresetCuts) {
+    const NEVER_SECS: f64 = 1.0e9;
+    let duration = if cuts.preset_duration > 0.0 { cuts.preset_duration } else { NEVER_SECS };
+    pm.set_preset_duration(duration);
+    pm.set_soft_cut_duration(cuts.soft_cut_duration);
+    pm.set_hard_cut_enabled(cuts.hard_cut_enabled);
+    pm.set_hard_cut_duration(cuts.hard_cut_duration);
+    pm.set_hard_cut_sensitivity(cuts.hard_cut_sensitivity);
+}
+
+/// Load GL functions from the display and log what the driver is
+fn load_gl(display: &Display) -> Option<GpuInfo> {
+    gl::load_with(|s| {
+        let c_str = CString::new(s).unwrap();
+        display.get_proc_address(&c_str) as *const _
+    });
+    gl_context::check_loaded();
+
+    unsafe {
+        let version_ptr = gl::GetString(gl::VERSION);
+        let renderer_ptr = gl::GetString(gl::RENDERER);
+        let vendor_ptr = gl::GetString(gl::VENDOR);
+
+        if !version_ptr.is_null() && !renderer_ptr.is_null() {
+            let version = std::ffi::CStr::from_ptr(version_ptr as *const _);
+            let renderer = std::ffi::CStr::from_ptr(renderer_ptr as *const _);
+            info!("OpenGL version: {:?}", version);
+            info!("OpenGL renderer: {:?}", renderer);
+            let vendor = if vendor_ptr.is_null() {
+                String::new()
+            } else {
+                std::ffi::CStr::from_ptr(vendor_ptr as *const _).to_string_lossy().into_owned()
+            };
+            let gpu = GpuInfo {
+                vendor,
+                renderer: renderer.to_string_lossy().into_owned(),
+                version: version.to_string_lossy().into_owned(),
+            };
+            if gpu.is_software() {
+                warn!("OpenGL is rendering in software; check the GPU drivers");
             }
+            Some(gpu)
+        } else {
+            warn!("Failed to get OpenGL version/renderer strings (null pointer)");
+            None
         }
     }
 }
 
+/// Preset name shown to receivers: the file name without extension
+fn preset_name(path: &str) -> Option<String> {
+    std::path::Path::new(path)
+        .file_stem()
+        .map(|stem| stem.to_string_lossy().into_owned())
+}
+
 /// Read commands from stdin in a separate thread
-fn spawn_stdin_reader(tx: Sender<Command>) {
+fn spawn_stdin_reader<T: DeserializeOwned + Send + 'static>(tx: Sender<T>) {
     thread::spawn(move || {
         let stdin = io::stdin();
         for line in stdin.lock().lines() {
             match line {
                 Ok(line) if !line.is_empty() => {
-                    match serde_json::from_str::<Command>(&line) {
+                    match serde_json::from_str::<T>(&line) {
                         Ok(cmd) => {
                             if tx.send(cmd).is_err() {
                                 break; // Channel closed
@@ -759,6 +3413,23 @@ fn main() {
                 deck_id: 0,
                 monitor_index: None,
                 texture_paths: Vec::new(),
+                compositor: false,
+                headless: false,
+                quality: RenderQuality::default(),
+                preset_locked: false,
+                preset_cuts: PresetCuts::default(),
+                always_on_top: false,
+                click_through: false,
+                x: None,
+                y: None,
+                multi_deck: false,
+                fps_cap: None,
+                background: None,
+                fullscreen_mode: None,
+                color_adjust: ColorAdjust::default(),
+                blackout: false,
+                time_scale: 1.0,
+                thumbnailer: false,
             }
         })
     } else {
@@ -770,28 +3441,65 @@ fn main() {
             deck_id: 0,
             monitor_index: None,
             texture_paths: Vec::new(),
+            compositor: false,
+            headless: false,
+            quality: RenderQuality::default(),
+            preset_locked: false,
+            preset_cuts: PresetCuts::default(),
+            always_on_top: false,
+            click_through: false,
+            x: None,
+            y: None,
+            multi_deck: false,
+            fps_cap: None,
+            background: None,
+            fullscreen_mode: None,
+            color_adjust: ColorAdjust::default(),
+            blackout: false,
+            time_scale: 1.0,
+            thumbnailer: false,
         }
     };
 
     info!("Starting renderer with config: {:?}", config);
 
+    if config.thumbnailer {
+        thumbnailer::run(config);
+        send_event(Event::Closed);
+        return;
+    }
+
+    if config.multi_deck {
+        if config.headless || config.compositor {
+            warn!("Multi-deck mode hosts deck windows only, running a single renderer");
+        } else {
+            multi_deck::run(config);
+            send_event(Event::Closed);
+            return;
+        }
+    }
+
     // Create command channel
     let (command_tx, command_rx) = mpsc::channel();
 
     // Start stdin reader thread
     spawn_stdin_reader(command_tx);
 
-    // Create and run event loop
-    let event_loop = EventLoop::new().expect("Failed to create event loop");
-    event_loop.set_control_flow(ControlFlow::Poll);
-
     let mut app = RenderApp::new(config, command_rx);
 
-    if let Err(e) = event_loop.run_app(&mut app) {
-        error!("Event loop error: {}", e);
-        send_event(Event::Error {
-            message: e.to_string(),
-        });
+    if app.config.headless {
+        app.run_headless();
+    } else {
+        // Create and run event loop
+        let event_loop = EventLoop::new().expect("Failed to create event loop");
+        event_loop.set_control_flow(ControlFlow::Poll);
+
+        if let Err(e) = event_loop.run_app(&mut app) {
+            error!("Event loop error: {}", e);
+            send_event(Event::Error {
+                message: e.to_string(),
+            });
+        }
     }
 
     send_event(Event::Closed);
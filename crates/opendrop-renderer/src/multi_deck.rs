@@ -0,0 +1,326 @@
+//! Several decks in one renderer process
+//!
+//! A process per deck costs a projectM's worth of memory each and has the
+//! app pipe audio to every deck separately. With `multi_deck: true` one
+//! process hosts up to [`MAX_DECKS`] deck windows on a single event loop,
+//! each with its own GL context and projectM. Command lines name the deck
+//! they are for with `deck_id` and events carry the deck they came from; a
+//! command without a deck, such as audio every deck shares, goes to all of
+//! them.
+
+use std::collections::BTreeMap;
+use std::sync::mpsc::{self, Receiver, Sender, TryRecvError};
+
+use serde::Deserialize;
+use tracing::{error, info, warn};
+use winit::application::ApplicationHandler;
+use winit::event::WindowEvent;
+use winit::event_loop::{ActiveEventLoop, ControlFlow, EventLoop};
+use winit::window::WindowId;
+
+use crate::{send_deck_event, send_event, spawn_stdin_reader, Command, Config, Event, RenderApp};
+
+/// Decks one process hosts
+const MAX_DECKS: usize = 4;
+
+/// A command line, for one deck or, without `deck_id`, all of them
+#[derive(Debug, Deserialize)]
+struct DeckCommand {
+    #[serde(default)]
+    deck_id: Option<u8>,
+    #[serde(flatten)]
+    command: Command,
+}
+
+/// A deck's renderer and the channel its commands go through
+struct HostedDeck {
+    app: RenderApp,
+    commands: Sender<Command>,
+}
+
+/// Runs every deck's window on one event loop
+struct DeckHost {
+    command_rx: Receiver<DeckCommand>,
+    decks: BTreeMap<u8, HostedDeck>,
+    /// Stopped or the parent went away: close every deck and exit
+    should_exit: bool,
+}
+
+/// Run the deck from `config`, and those opened with `open_deck`, until
+/// stopped
+pub fn run(config: Config) {
+    let (command_tx, command_rx) = mpsc::channel();
+    spawn_stdin_reader(command_tx);
+
+    let mut host = DeckHost {
+        command_rx,
+        decks: BTreeMap::new(),
+        should_exit: false,
+    };
+    host.open_deck(config);
+
+    let event_loop = EventLoop::new().expect("Failed to create event loop");
+    event_loop.set_control_flow(ControlFlow::Poll);
+    if let Err(e) = event_loop.run_app(&mut host) {
+        error!("Event loop error: {}", e);
+        send_event(Event::Error {
+            message: e.to_string(),
+        });
+    }
+}
+
+impl DeckHost {
+    /// Add a deck; its window opens on the next pass of the event loop
+    fn open_deck(&mut self, config: Config) {
+        let deck_id = config.deck_id;
+        if self.decks.contains_key(&deck_id) {
+            warn!("Deck {} is already open", deck_id);
+            return;
+        }
+        if config.headless || config.compositor {
+            send_deck_event(
+                Some(deck_id),
+                Event::Error {
+                    message: "Headless decks and the compositor run in their own renderer"
+                        .to_string(),
+                },
+            );
+            return;
+        }
+        if self.decks.len() >= MAX_DECKS {
+            send_deck_event(
+                Some(deck_id),
+                Event::Error {
+                    message: format!("A renderer hosts at most {} decks", MAX_DECKS),
+                },
+            );
+            return;
+        }
+
+        info!("Opening deck {}", deck_id);
+        let (commands, command_rx) = mpsc::channel();
+        let mut app = RenderApp::new(config, command_rx);
+        app.event_deck = Some(deck_id);
+        self.decks.insert(deck_id, HostedDeck { app, commands });
+    }
+
+    /// Hand commands from stdin to their decks
+    fn route_commands(&mut self) {
+        loop {
+            match self.command_rx.try_recv() {
+                Ok(DeckCommand {
+                    command: Command::OpenDeck { config },
+                    ..
+                }) => self.open_deck(*config),
+                Ok(DeckCommand { deck_id, command }) => {
+                    // Stopping every deck stops the process too
+                    if deck_id.is_none() && matches!(command, Command::Stop) {
+                        self.should_exit = true;
+                    }
+                    let decks = self.decks.iter().map(|(&id, deck)| (id, &deck.commands));
+                    if !dispatch(decks, deck_id, command) {
+                        warn!("Deck {} is not open", deck_id.unwrap_or_default());
+                    }
+                }
+                Err(TryRecvError::Empty) => break,
+                Err(TryRecvError::Disconnected) => {
+                    // Parent process closed, exit
+                    self.should_exit = true;
+                    break;
+                }
+            }
+        }
+    }
+
+    /// Create windows for decks opened since the last pass
+    fn open_windows(&mut self, event_loop: &ActiveEventLoop) {
+        let mut opened = false;
+        for deck in self.decks.values_mut() {
+            if deck.app.window.is_none() && !deck.app.should_exit {
+                deck.app.resumed(event_loop);
+                opened = true;
+            }
+        }
+        if opened {
+            self.sync_vsync();
+        }
+    }
+
+    /// Free a deck's GL objects on its own context and close its window
+    fn close_deck(&mut self, deck_id: u8) {
+        let Some(mut deck) = self.decks.remove(&deck_id) else {
+            return;
+        };
+        deck.app.make_current();
+        deck.app.free_gl();
+        // projectM frees its GL objects while the context is still current
+        drop(deck);
+        info!("Closed deck {}", deck_id);
+        send_deck_event(Some(deck_id), Event::Closed);
+    }
+
+    /// Only the first deck waits for vsync; if every window did, each swap
+    /// would wait for the one before it
+    fn sync_vsync(&self) {
+        for (index, deck) in self.decks.values().enumerate() {
+            deck.app.make_current();
+            deck.app.set_vsync(index == 0);
+        }
+    }
+}
+
+/// Send `command` to deck `deck_id`, or to every deck without one; false if
+/// the deck isn't open
+fn dispatch<'a>(
+    decks: impl IntoIterator<Item = (u8, &'a Sender<Command>)>,
+    deck_id: Option<u8>,
+    command: Command,
+) -> bool {
+    let mut decks = decks.into_iter();
+    match deck_id {
+        Some(deck_id) => match decks.find(|&(id, _)| id == deck_id) {
+            Some((_, commands)) => {
+                let _ = commands.send(command);
+                true
+            }
+            None => false,
+        },
+        None => {
+            for (_, commands) in decks {
+                let _ = commands.send(command.clone());
+            }
+            true
+        }
+    }
+}
+
+impl ApplicationHandler for DeckHost {
+    fn resumed(&mut self, event_loop: &ActiveEventLoop) {
+        self.open_windows(event_loop);
+    }
+
+    fn window_event(&mut self, event_loop: &ActiveEventLoop, id: WindowId, event: WindowEvent) {
+        let deck = self
+            .decks
+            .values_mut()
+            .find(|deck| deck.app.window.as_ref().is_some_and(|w| w.id() == id));
+        if let Some(deck) = deck {
+            deck.app.make_current();
+            deck.app.window_event(event_loop, id, event);
+        }
+    }
+
+    fn about_to_wait(&mut self, event_loop: &ActiveEventLoop) {
+        self.route_commands();
+        self.open_windows(event_loop);
+        for deck in self.decks.values_mut() {
+            deck.app.make_current();
+            deck.app.update();
+        }
+
+        let closed: Vec<u8> = self
+            .decks
+            .iter()
+            .filter(|(_, deck)| self.should_exit || deck.app.should_exit)
+            .map(|(&deck_id, _)| deck_id)
+            .collect();
+        if !closed.is_empty() {
+            for deck_id in closed {
+                self.close_deck(deck_id);
+            }
+            self.sync_vsync();
+        }
+        if self.should_exit {
+            event_loop.exit();
+            return;
+        }
+
+        // Wake for the first frame due; any uncapped deck keeps polling
+        let next_frames: Option<Vec<_>> = self
+            .decks
+            .values()
+            .map(|deck| deck.app.next_frame_at())
+            .collect();
+        event_loop.set_control_flow(match next_frames.and_then(|at| at.into_iter().min()) {
+            Some(at) => ControlFlow::WaitUntil(at),
+            None => ControlFlow::Poll,
+        });
+    }
+
+    fn exiting(&mut self, _event_loop: &ActiveEventLoop) {
+        let open: Vec<u8> = self.decks.keys().copied().collect();
+        for deck_id in open {
+            self.close_deck(deck_id);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Each deck's command channel, both ends
+    type Decks = BTreeMap<u8, (Sender<Command>, Receiver<Command>)>;
+
+    fn decks(ids: &[u8]) -> Decks {
+        ids.iter().map(|&id| (id, mpsc::channel())).collect()
+    }
+
+    fn senders(decks: &Decks) -> impl Iterator<Item = (u8, &Sender<Command>)> {
+        decks.iter().map(|(&id, (tx, _))| (id, tx))
+    }
+
+    fn received(decks: &Decks, id: u8) -> Vec<Command> {
+        decks[&id].1.try_iter().collect()
+    }
+
+    #[test]
+    fn test_deck_command_goes_to_its_deck_only() {
+        let decks = decks(&[0, 1, 2]);
+        let line: DeckCommand =
+            serde_json::from_str(r#"{"type":"load_preset","deck_id":1,"path":"a.milk"}"#).unwrap();
+        assert_eq!(line.deck_id, Some(1));
+
+        assert!(dispatch(senders(&decks), line.deck_id, line.command));
+        assert!(received(&decks, 0).is_empty());
+        assert!(matches!(
+            received(&decks, 1).as_slice(),
+            [Command::LoadPreset { path }] if path == "a.milk"
+        ));
+        assert!(received(&decks, 2).is_empty());
+    }
+
+    #[test]
+    fn test_command_without_deck_goes_to_every_deck() {
+        let decks = decks(&[0, 3]);
+        let line: DeckCommand = serde_json::from_str(r#"{"type":"stop"}"#).unwrap();
+        assert_eq!(line.deck_id, None);
+
+        assert!(dispatch(senders(&decks), line.deck_id, line.command));
+        for id in [0, 3] {
+            assert!(matches!(received(&decks, id).as_slice(), [Command::Stop]));
+        }
+    }
+
+    #[test]
+    fn test_command_for_closed_deck_is_dropped() {
+        let decks = decks(&[0]);
+        assert!(!dispatch(senders(&decks), Some(2), Command::Stop));
+        assert!(received(&decks, 0).is_empty());
+    }
+
+    #[test]
+    fn test_open_deck_carries_config() {
+        let line: DeckCommand = serde_json::from_str(
+            r#"{"type":"open_deck","config":{"width":640,"height":360,"preset_path":null,"fullscreen":false,"deck_id":2}}"#,
+        )
+        .unwrap();
+        match line.command {
+            Command::OpenDeck { config } => {
+                assert_eq!(config.deck_id, 2);
+                assert_eq!((config.width, config.height), (640, 360));
+            }
+            other => panic!("expected open_deck, got {:?}", other),
+        }
+    }
+}
@@ -0,0 +1,58 @@
+//! Reload the preset when its file changes
+//!
+//! Preset authors edit a .milk in a text editor and see the result on the
+//! deck on save. Only one file is watched per deck, so its modification time
+//! and size are polled twice a second instead of using OS file events.
+
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant, SystemTime};
+
+/// How often the file is checked
+const POLL_INTERVAL: Duration = Duration::from_millis(500);
+
+/// Watches the loaded preset file for saves
+pub struct PresetWatcher {
+    path: PathBuf,
+    /// Modification time and size when last checked
+    stamp: Option<(SystemTime, u64)>,
+    last_check: Instant,
+}
+
+impl PresetWatcher {
+    pub fn new(path: &str) -> Self {
+        let path = PathBuf::from(path);
+        Self {
+            stamp: stamp(&path),
+            path,
+            last_check: Instant::now(),
+        }
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Whether the file was saved since the last check; checks at most once
+    /// per poll interval
+    pub fn changed(&mut self, now: Instant) -> bool {
+        if now.saturating_duration_since(self.last_check) < POLL_INTERVAL {
+            return false;
+        }
+        self.last_check = now;
+
+        // Editors that save by replacing the file leave it missing briefly
+        let Some(current) = stamp(&self.path) else {
+            return false;
+        };
+        if self.stamp == Some(current) {
+            return false;
+        }
+        self.stamp = Some(current);
+        true
+    }
+}
+
+fn stamp(path: &Path) -> Option<(SystemTime, u64)> {
+    let metadata = std::fs::metadata(path).ok()?;
+    Some((metadata.modified().ok()?, metadata.len()))
+}
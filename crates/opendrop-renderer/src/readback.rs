@@ -0,0 +1,100 @@
+//! Asynchronous framebuffer readback through pixel buffer objects
+//!
+//! `glReadPixels` into client memory waits for the GPU to finish the frame.
+//! Reading into one of two PBOs instead returns immediately; the pixels are
+//! mapped a frame later, once the transfer is done, so outputs run one frame
+//! behind the window without stalling it.
+
+use opendrop_core::video::output::copy_flipped;
+
+/// Double-buffered PBO readback
+pub struct FrameReadback {
+    pbos: [u32; 2],
+    /// PBO the next read goes into
+    index: usize,
+    /// Size the PBOs are allocated for
+    size: (u32, u32),
+    /// Whether the other PBO holds a frame not yet copied out
+    pending: bool,
+}
+
+impl FrameReadback {
+    /// Create without GL resources; PBOs are allocated on the first read
+    pub fn new() -> Self {
+        Self {
+            pbos: [0; 2],
+            index: 0,
+            size: (0, 0),
+            pending: false,
+        }
+    }
+
+    /// Start reading the current framebuffer and copy the previous frame,
+    /// top row first, into `out`. Returns false while no earlier frame is
+    /// ready (the first frame, or after a size change).
+    pub fn read(&mut self, width: u32, height: u32, out: &mut [u8]) -> bool {
+        let bytes = (width * height * 4) as usize;
+        if bytes == 0 || out.len() != bytes {
+            return false;
+        }
+
+        unsafe {
+            if self.size != (width, height) {
+                if self.pbos[0] == 0 {
+                    gl::GenBuffers(2, self.pbos.as_mut_ptr());
+                }
+                for &pbo in &self.pbos {
+                    gl::BindBuffer(gl::PIXEL_PACK_BUFFER, pbo);
+                    gl::BufferData(
+                        gl::PIXEL_PACK_BUFFER,
+                        bytes as isize,
+                        std::ptr::null(),
+                        gl::STREAM_READ,
+                    );
+                }
+                self.size = (width, height);
+                self.pending = false;
+            }
+
+            // Queue this frame's transfer; with a pack buffer bound the
+            // pointer is an offset into it
+            gl::BindBuffer(gl::PIXEL_PACK_BUFFER, self.pbos[self.index]);
+            gl::ReadPixels(
+                0,
+                0,
+                width as i32,
+                height as i32,
+                gl::RGBA,
+                gl::UNSIGNED_BYTE,
+                std::ptr::null_mut(),
+            );
+
+            // Collect last frame's, which has had a frame to complete
+            let mut ready = false;
+            if self.pending {
+                gl::BindBuffer(gl::PIXEL_PACK_BUFFER, self.pbos[1 - self.index]);
+                let ptr =
+                    gl::MapBufferRange(gl::PIXEL_PACK_BUFFER, 0, bytes as isize, gl::MAP_READ_BIT);
+                if !ptr.is_null() {
+                    let pixels = std::slice::from_raw_parts(ptr as *const u8, bytes);
+                    copy_flipped(pixels, out, (width * 4) as usize);
+                    gl::UnmapBuffer(gl::PIXEL_PACK_BUFFER);
+                    ready = true;
+                }
+            }
+            gl::BindBuffer(gl::PIXEL_PACK_BUFFER, 0);
+
+            self.pending = true;
+            self.index = 1 - self.index;
+            ready
+        }
+    }
+}
+
+impl Drop for FrameReadback {
+    fn drop(&mut self) {
+        if self.pbos[0] != 0 {
+            unsafe { gl::DeleteBuffers(2, self.pbos.as_ptr()) };
+        }
+    }
+}
@@ -0,0 +1,134 @@
+//! Shader compilation and GL state saving shared by the GL layers drawn over or instead of projectM
+
+use std::borrow::Cow;
+use std::ffi::CString;
+
+use crate::gl_context::is_gles;
+
+/// Shaders are written for GLSL 3.30; OpenGL ES 3.0 takes the same code
+/// under its own version line, with float and int precision declared
+fn for_context(source: &str) -> Cow<'_, str> {
+    match source.strip_prefix("#version 330 core\n") {
+        Some(body) if is_gles() => Cow::Owned(format!(
+            "#version 300 es\nprecision highp float;\nprecision highp int;\n{}",
+            body
+        )),
+        _ => Cow::Borrowed(source),
+    }
+}
+
+unsafe fn compile_shader(name: &str, kind: gl::types::GLenum, source: &str) -> Result<u32, String> {
+    let shader = gl::CreateShader(kind);
+    let source = CString::new(for_context(source).as_ref()).map_err(|e| e.to_string())?;
+    gl::ShaderSource(shader, 1, &source.as_ptr(), std::ptr::null());
+    gl::CompileShader(shader);
+
+    let mut status = 0;
+    gl::GetShaderiv(shader, gl::COMPILE_STATUS, &mut status);
+    if status == 0 {
+        let mut len = 0;
+        gl::GetShaderiv(shader, gl::INFO_LOG_LENGTH, &mut len);
+        let mut log = vec![0u8; len.max(1) as usize];
+        gl::GetShaderInfoLog(
+            shader,
+            len,
+            std::ptr::null_mut(),
+            log.as_mut_ptr() as *mut _,
+        );
+        gl::DeleteShader(shader);
+        return Err(format!(
+            "{} shader failed to compile: {}",
+            name,
+            String::from_utf8_lossy(&log).trim_end_matches('\0')
+        ));
+    }
+    Ok(shader)
+}
+
+/// Compile and link a vertex/fragment program; `name` is used in errors
+pub unsafe fn link_program(name: &str, vertex: &str, fragment: &str) -> Result<u32, String> {
+    let vertex = compile_shader(name, gl::VERTEX_SHADER, vertex)?;
+    let fragment = match compile_shader(name, gl::FRAGMENT_SHADER, fragment) {
+        Ok(fragment) => fragment,
+        Err(e) => {
+            gl::DeleteShader(vertex);
+            return Err(e);
+        }
+    };
+
+    let program = gl::CreateProgram();
+    gl::AttachShader(program, vertex);
+    gl::AttachShader(program, fragment);
+    gl::LinkProgram(program);
+    gl::DeleteShader(vertex);
+    gl::DeleteShader(fragment);
+
+    let mut status = 0;
+    gl::GetProgramiv(program, gl::LINK_STATUS, &mut status);
+    if status == 0 {
+        gl::DeleteProgram(program);
+        return Err(format!("{} shader failed to link", name));
+    }
+    Ok(program)
+}
+
+/// Puts back the GL state a layer's draw changes when dropped
+///
+/// projectM keeps its own GL state between frames, so layers drawn around it
+/// leave the program, vertex array, array buffer, texture unit 0 and
+/// blending as they found them.
+pub struct GlStateGuard {
+    program: i32,
+    vao: i32,
+    array_buffer: i32,
+    active_texture: i32,
+    texture: i32,
+    blend_enabled: bool,
+    blend_func: [i32; 4],
+}
+
+impl GlStateGuard {
+    /// Save the current state and make texture unit 0 active
+    pub unsafe fn save() -> Self {
+        let mut saved = Self {
+            program: 0,
+            vao: 0,
+            array_buffer: 0,
+            active_texture: 0,
+            texture: 0,
+            blend_enabled: gl::IsEnabled(gl::BLEND) == gl::TRUE,
+            blend_func: [0; 4],
+        };
+        gl::GetIntegerv(gl::CURRENT_PROGRAM, &mut saved.program);
+        gl::GetIntegerv(gl::VERTEX_ARRAY_BINDING, &mut saved.vao);
+        gl::GetIntegerv(gl::ARRAY_BUFFER_BINDING, &mut saved.array_buffer);
+        gl::GetIntegerv(gl::ACTIVE_TEXTURE, &mut saved.active_texture);
+        gl::ActiveTexture(gl::TEXTURE0);
+        gl::GetIntegerv(gl::TEXTURE_BINDING_2D, &mut saved.texture);
+        gl::GetIntegerv(gl::BLEND_SRC_RGB, &mut saved.blend_func[0]);
+        gl::GetIntegerv(gl::BLEND_DST_RGB, &mut saved.blend_func[1]);
+        gl::GetIntegerv(gl::BLEND_SRC_ALPHA, &mut saved.blend_func[2]);
+        gl::GetIntegerv(gl::BLEND_DST_ALPHA, &mut saved.blend_func[3]);
+        saved
+    }
+}
+
+impl Drop for GlStateGuard {
+    fn drop(&mut self) {
+        let [src_rgb, dst_rgb, src_alpha, dst_alpha] = self.blend_func.map(|f| f as u32);
+        unsafe {
+            gl::BindVertexArray(self.vao as u32);
+            gl::BindBuffer(gl::ARRAY_BUFFER, self.array_buffer as u32);
+            gl::ActiveTexture(gl::TEXTURE0);
+            gl::BindTexture(gl::TEXTURE_2D, self.texture as u32);
+            gl::ActiveTexture(self.active_texture as u32);
+            gl::UseProgram(self.program as u32);
+            gl::BlendFuncSeparate(src_rgb, dst_rgb, src_alpha, dst_alpha);
+            if self.blend_enabled {
+                gl::Enable(gl::BLEND);
+            } else {
+                gl::Disable(gl::BLEND);
+            }
+        }
+    }
+}
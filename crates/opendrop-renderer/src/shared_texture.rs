@@ -0,0 +1,566 @@
+//! Zero-copy frame sharing with the compositor through DMA-BUF (Linux)
+//!
+//! A deck copies each frame into one of a few textures exported as DMA-BUFs
+//! with `EGL_MESA_image_dma_buf_export`, and the compositor imports them with
+//! `EGL_EXT_image_dma_buf_import`, so frames never leave the GPU. The copy
+//! is fenced, and a buffer is only announced once the GPU has finished it.
+//! GLX contexts, drivers without the extensions and other platforms keep
+//! sending pixels through `CompositorFeed`.
+
+#[cfg(target_os = "linux")]
+mod imp {
+    use std::collections::HashMap;
+    use std::ffi::{c_void, CStr};
+    use std::os::fd::{AsFd, AsRawFd, FromRawFd, OwnedFd};
+    use std::path::Path;
+
+    use glutin::context::{AsRawContext, PossiblyCurrentContext, RawContext};
+    use glutin::display::{AsRawDisplay, Display, GlDisplay, RawDisplay};
+    use opendrop_core::video::compositor::{
+        SharedBuffers, SharedFrame, SharedPlane, DRM_FORMAT_MOD_INVALID, MAX_SHARED_BUFFERS,
+    };
+    use opendrop_core::video::{CompositorGpuFeed, CompositorReceiver};
+    use tracing::{info, warn};
+
+    const EGL_NONE: i32 = 0x3038;
+    const EGL_EXTENSIONS: i32 = 0x3055;
+    const EGL_HEIGHT: i32 = 0x3056;
+    const EGL_WIDTH: i32 = 0x3057;
+    const EGL_GL_TEXTURE_2D_KHR: u32 = 0x30B1;
+    const EGL_LINUX_DMA_BUF_EXT: u32 = 0x3270;
+    const EGL_LINUX_DRM_FOURCC_EXT: i32 = 0x3271;
+    const EGL_DMA_BUF_PLANE0_FD_EXT: i32 = 0x3272;
+    const EGL_DMA_BUF_PLANE0_OFFSET_EXT: i32 = 0x3273;
+    const EGL_DMA_BUF_PLANE0_PITCH_EXT: i32 = 0x3274;
+    const EGL_DMA_BUF_PLANE0_MODIFIER_LO_EXT: i32 = 0x3443;
+    const EGL_DMA_BUF_PLANE0_MODIFIER_HI_EXT: i32 = 0x3444;
+
+    /// How long to wait for last frame's copy before skipping its announcement
+    const FENCE_TIMEOUT_NS: u64 = 20_000_000;
+
+    type QueryStringFn = unsafe extern "C" fn(*const c_void, i32) -> *const i8;
+    type CreateImageFn = unsafe extern "C" fn(
+        *const c_void,
+        *const c_void,
+        u32,
+        *const c_void,
+        *const i32,
+    ) -> *const c_void;
+    type DestroyImageFn = unsafe extern "C" fn(*const c_void, *const c_void) -> u32;
+    type ExportQueryFn =
+        unsafe extern "C" fn(*const c_void, *const c_void, *mut i32, *mut i32, *mut u64) -> u32;
+    type ExportFn =
+        unsafe extern "C" fn(*const c_void, *const c_void, *mut i32, *mut i32, *mut i32) -> u32;
+    type ImageTargetTextureFn = unsafe extern "C" fn(u32, *const c_void);
+
+    /// EGL entry points for sharing textures, on the renderer's context
+    #[derive(Clone, Copy)]
+    pub struct Egl {
+        display: *const c_void,
+        context: *const c_void,
+        create_image: CreateImageFn,
+        destroy_image: DestroyImageFn,
+        image_target_texture: ImageTargetTextureFn,
+        /// Present when the driver can export DMA-BUFs
+        export: Option<(ExportQueryFn, ExportFn)>,
+    }
+
+    /// Look up an EGL or GL extension function
+    unsafe fn load<F>(display: &Display, name: &CStr) -> Option<F> {
+        let ptr = display.get_proc_address(name);
+        (!ptr.is_null()).then(|| std::mem::transmute_copy::<*const c_void, F>(&ptr))
+    }
+
+    impl Egl {
+        /// Load the entry points if the context is EGL and can import DMA-BUFs
+        pub fn from_context(display: &Display, context: &PossiblyCurrentContext) -> Option<Self> {
+            let (RawDisplay::Egl(egl_display), RawContext::Egl(egl_context)) =
+                (display.raw_display(), context.raw_context())
+            else {
+                info!("Not an EGL context, frames are shared as pixels");
+                return None;
+            };
+
+            unsafe {
+                let query_string: QueryStringFn = load(display, c"eglQueryString")?;
+                let extensions = query_string(egl_display, EGL_EXTENSIONS);
+                if extensions.is_null() {
+                    return None;
+                }
+                let extensions = CStr::from_ptr(extensions).to_string_lossy();
+                let has = |name: &str| extensions.split_whitespace().any(|e| e == name);
+                if !has("EGL_KHR_image_base") || !has("EGL_EXT_image_dma_buf_import") {
+                    info!("EGL can't import DMA-BUFs, frames are shared as pixels");
+                    return None;
+                }
+
+                let export = if has("EGL_MESA_image_dma_buf_export") {
+                    load(display, c"eglExportDMABUFImageQueryMESA")
+                        .zip(load(display, c"eglExportDMABUFImageMESA"))
+                } else {
+                    None
+                };
+                Some(Self {
+                    display: egl_display,
+                    context: egl_context,
+                    create_image: load(display, c"eglCreateImageKHR")?,
+                    destroy_image: load(display, c"eglDestroyImageKHR")?,
+                    image_target_texture: load(display, c"glEGLImageTargetTexture2DOES")?,
+                    export,
+                })
+            }
+        }
+
+        /// Create an RGBA texture of `width`x`height`
+        unsafe fn new_texture(width: u32, height: u32) -> u32 {
+            let mut texture = 0;
+            gl::GenTextures(1, &mut texture);
+            gl::BindTexture(gl::TEXTURE_2D, texture);
+            gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_MIN_FILTER, gl::LINEAR as i32);
+            gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_MAG_FILTER, gl::LINEAR as i32);
+            gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_WRAP_S, gl::CLAMP_TO_EDGE as i32);
+            gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_WRAP_T, gl::CLAMP_TO_EDGE as i32);
+            gl::TexImage2D(
+                gl::TEXTURE_2D,
+                0,
+                gl::RGBA8 as i32,
+                width as i32,
+                height as i32,
+                0,
+                gl::RGBA,
+                gl::UNSIGNED_BYTE,
+                std::ptr::null(),
+            );
+            gl::BindTexture(gl::TEXTURE_2D, 0);
+            texture
+        }
+
+        /// Create a texture and export it as a single-plane DMA-BUF
+        unsafe fn export_texture(
+            &self,
+            width: u32,
+            height: u32,
+        ) -> Result<(SharedTexture, OwnedFd, u32, u64, SharedPlane), String> {
+            let (query, export) = self.export.ok_or("EGL can't export DMA-BUFs")?;
+            let texture = SharedTexture {
+                texture: Self::new_texture(width, height),
+                image: std::ptr::null(),
+            };
+            let attribs = [EGL_NONE];
+            let image = (self.create_image)(
+                self.display,
+                self.context,
+                EGL_GL_TEXTURE_2D_KHR,
+                texture.texture as usize as *const c_void,
+                attribs.as_ptr(),
+            );
+            let texture = SharedTexture { image, ..texture };
+            if image.is_null() {
+                texture.delete(self);
+                return Err("eglCreateImageKHR failed for a texture".to_string());
+            }
+
+            let (mut fourcc, mut planes, mut modifier) = (0, 0, 0);
+            if query(self.display, image, &mut fourcc, &mut planes, &mut modifier) == 0
+                || planes != 1
+            {
+                texture.delete(self);
+                return Err(format!("Can't export a {}-plane texture", planes));
+            }
+            let (mut fd, mut stride, mut offset) = (-1, 0, 0);
+            if export(self.display, image, &mut fd, &mut stride, &mut offset) == 0 || fd < 0 {
+                texture.delete(self);
+                return Err("eglExportDMABUFImageMESA failed".to_string());
+            }
+            let plane = SharedPlane {
+                stride: stride as u32,
+                offset: offset as u32,
+            };
+            Ok((
+                texture,
+                OwnedFd::from_raw_fd(fd),
+                fourcc as u32,
+                modifier,
+                plane,
+            ))
+        }
+
+        /// Import every buffer a deck shares as a texture
+        unsafe fn import(&self, frame: &SharedFrame<'_>) -> Result<Vec<SharedTexture>, String> {
+            let buffers = frame.buffers;
+            let mut textures = Vec::new();
+            for (plane, fd) in buffers.planes.iter().zip(frame.fds) {
+                let mut attribs = vec![
+                    EGL_WIDTH,
+                    buffers.width as i32,
+                    EGL_HEIGHT,
+                    buffers.height as i32,
+                    EGL_LINUX_DRM_FOURCC_EXT,
+                    buffers.fourcc as i32,
+                    EGL_DMA_BUF_PLANE0_FD_EXT,
+                    fd.as_raw_fd(),
+                    EGL_DMA_BUF_PLANE0_OFFSET_EXT,
+                    plane.offset as i32,
+                    EGL_DMA_BUF_PLANE0_PITCH_EXT,
+                    plane.stride as i32,
+                ];
+                if buffers.modifier != DRM_FORMAT_MOD_INVALID {
+                    attribs.extend([
+                        EGL_DMA_BUF_PLANE0_MODIFIER_LO_EXT,
+                        buffers.modifier as u32 as i32,
+                        EGL_DMA_BUF_PLANE0_MODIFIER_HI_EXT,
+                        (buffers.modifier >> 32) as u32 as i32,
+                    ]);
+                }
+                attribs.push(EGL_NONE);
+
+                let image = (self.create_image)(
+                    self.display,
+                    std::ptr::null(),
+                    EGL_LINUX_DMA_BUF_EXT,
+                    std::ptr::null(),
+                    attribs.as_ptr(),
+                );
+                if image.is_null() {
+                    for texture in textures {
+                        SharedTexture::delete(texture, self);
+                    }
+                    return Err("eglCreateImageKHR failed for a DMA-BUF".to_string());
+                }
+                let mut texture = 0;
+                gl::GenTextures(1, &mut texture);
+                gl::BindTexture(gl::TEXTURE_2D, texture);
+                gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_MIN_FILTER, gl::LINEAR as i32);
+                gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_MAG_FILTER, gl::LINEAR as i32);
+                gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_WRAP_S, gl::CLAMP_TO_EDGE as i32);
+                gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_WRAP_T, gl::CLAMP_TO_EDGE as i32);
+                (self.image_target_texture)(gl::TEXTURE_2D, image);
+                gl::BindTexture(gl::TEXTURE_2D, 0);
+                textures.push(SharedTexture { texture, image });
+            }
+            Ok(textures)
+        }
+    }
+
+    /// A texture backed by an EGL image
+    struct SharedTexture {
+        texture: u32,
+        image: *const c_void,
+    }
+
+    impl SharedTexture {
+        unsafe fn delete(self, egl: &Egl) {
+            gl::DeleteTextures(1, &self.texture);
+            if !self.image.is_null() {
+                (egl.destroy_image)(egl.display, self.image);
+            }
+        }
+    }
+
+    /// Deck side: copies frames into textures shared with the compositor
+    pub struct SharedTextureFeed {
+        egl: Egl,
+        feed: CompositorGpuFeed,
+        textures: Vec<SharedTexture>,
+        framebuffer: u32,
+        size: (u32, u32),
+        /// Texture the next frame goes into
+        next: usize,
+        /// Texture copied into last frame, announced once its fence signals
+        pending: Option<(usize, gl::types::GLsync)>,
+    }
+
+    impl SharedTextureFeed {
+        /// Connect to the compositor's GPU sharing socket
+        pub fn connect(egl: &Egl, socket: &str, deck_id: u8) -> Result<Self, String> {
+            if egl.export.is_none() {
+                return Err("EGL can't export DMA-BUFs".to_string());
+            }
+            let feed = CompositorGpuFeed::connect(Path::new(socket), deck_id)
+                .map_err(|e| e.to_string())?;
+            let mut framebuffer = 0;
+            unsafe { gl::GenFramebuffers(1, &mut framebuffer) };
+            Ok(Self {
+                egl: *egl,
+                feed,
+                textures: Vec::new(),
+                framebuffer,
+                size: (0, 0),
+                next: 0,
+                pending: None,
+            })
+        }
+
+        /// Copy the window's `width`x`height` framebuffer into the next
+        /// shared texture and announce the one finished last frame
+        pub fn publish(&mut self, width: u32, height: u32) -> Result<(), String> {
+            if width == 0 || height == 0 {
+                return Ok(());
+            }
+            unsafe {
+                if self.size != (width, height) {
+                    self.share_textures(width, height)?;
+                }
+
+                if let Some((index, fence)) = self.pending.take() {
+                    let status =
+                        gl::ClientWaitSync(fence, gl::SYNC_FLUSH_COMMANDS_BIT, FENCE_TIMEOUT_NS);
+                    gl::DeleteSync(fence);
+                    if status == gl::ALREADY_SIGNALED || status == gl::CONDITION_SATISFIED {
+                        self.feed
+                            .send_frame(index as u8)
+                            .map_err(|e| e.to_string())?;
+                    }
+                }
+
+                // Flipped, so the texture is top row first like pixel frames
+                let index = self.next;
+                let (w, h) = (width as i32, height as i32);
+                let mut frame = 0;
+                gl::GetIntegerv(gl::DRAW_FRAMEBUFFER_BINDING, &mut frame);
+                gl::BindFramebuffer(gl::READ_FRAMEBUFFER, frame as u32);
+                gl::BindFramebuffer(gl::DRAW_FRAMEBUFFER, self.framebuffer);
+                gl::FramebufferTexture2D(
+                    gl::DRAW_FRAMEBUFFER,
+                    gl::COLOR_ATTACHMENT0,
+                    gl::TEXTURE_2D,
+                    self.textures[index].texture,
+                    0,
+                );
+                gl::BlitFramebuffer(0, 0, w, h, 0, h, w, 0, gl::COLOR_BUFFER_BIT, gl::NEAREST);
+                gl::BindFramebuffer(gl::FRAMEBUFFER, frame as u32);
+
+                let fence = gl::FenceSync(gl::SYNC_GPU_COMMANDS_COMPLETE, 0);
+                gl::Flush();
+                self.pending = Some((index, fence));
+                self.next = (index + 1) % self.textures.len();
+            }
+            Ok(())
+        }
+
+        /// Replace the shared textures with new ones of `width`x`height`
+        unsafe fn share_textures(&mut self, width: u32, height: u32) -> Result<(), String> {
+            self.delete_textures();
+
+            let mut fds = Vec::new();
+            let mut planes = Vec::new();
+            let mut format = None;
+            for _ in 0..MAX_SHARED_BUFFERS {
+                let (texture, fd, fourcc, modifier, plane) =
+                    self.egl.export_texture(width, height)?;
+                self.textures.push(texture);
+                if format.is_some_and(|format| format != (fourcc, modifier)) {
+                    return Err("Shared textures differ in format".to_string());
+                }
+                format = Some((fourcc, modifier));
+                fds.push(fd);
+                planes.push(plane);
+            }
+            let (fourcc, modifier) = format.unwrap_or((0, DRM_FORMAT_MOD_INVALID));
+
+            let borrowed: Vec<_> = fds.iter().map(|fd| fd.as_fd()).collect();
+            self.feed
+                .send_buffers(
+                    SharedBuffers {
+                        width,
+                        height,
+                        fourcc,
+                        modifier,
+                        planes,
+                    },
+                    &borrowed,
+                )
+                .map_err(|e| e.to_string())?;
+            // The compositor holds its own copies of the descriptors now
+            self.size = (width, height);
+            info!("Sharing {}x{} textures with the compositor", width, height);
+            Ok(())
+        }
+
+        unsafe fn delete_textures(&mut self) {
+            if let Some((_, fence)) = self.pending.take() {
+                gl::DeleteSync(fence);
+            }
+            for texture in self.textures.drain(..) {
+                texture.delete(&self.egl);
+            }
+            self.size = (0, 0);
+            self.next = 0;
+        }
+    }
+
+    impl Drop for SharedTextureFeed {
+        fn drop(&mut self) {
+            unsafe {
+                self.delete_textures();
+                gl::DeleteFramebuffers(1, &self.framebuffer);
+            }
+        }
+    }
+
+    /// A deck's imported textures
+    struct ImportedDeck {
+        generation: u64,
+        textures: Vec<SharedTexture>,
+        size: (u32, u32),
+    }
+
+    /// Compositor side: imports the textures decks share
+    pub struct SharedTextureImporter {
+        egl: Egl,
+        decks: HashMap<u8, ImportedDeck>,
+    }
+
+    impl SharedTextureImporter {
+        /// Accept shared textures on `receiver`; returns the importer and the
+        /// socket decks connect to, or `None` when textures can't be imported
+        pub fn listen(
+            egl: Option<Egl>,
+            receiver: &mut CompositorReceiver,
+        ) -> Option<(Self, String)> {
+            let egl = egl?;
+            match receiver.listen_gpu() {
+                Ok(socket) => {
+                    let socket = socket.to_string_lossy().into_owned();
+                    let importer = Self {
+                        egl,
+                        decks: HashMap::new(),
+                    };
+                    Some((importer, socket))
+                }
+                Err(e) => {
+                    warn!("Decks will send pixels: {}", e);
+                    None
+                }
+            }
+        }
+
+        /// Texture with the newest frame `deck_id` shared, and its size
+        pub fn texture(
+            &mut self,
+            receiver: &CompositorReceiver,
+            deck_id: u8,
+        ) -> Option<(u32, (u32, u32))> {
+            let mut result = None;
+            let mut failed = false;
+            let sharing = receiver.with_shared_frame(deck_id, |frame| {
+                let current = self.decks.get(&deck_id).map(|deck| deck.generation);
+                if current != Some(frame.generation) {
+                    if let Some(old) = self.decks.remove(&deck_id) {
+                        unsafe { old.delete(&self.egl) };
+                    }
+                    match unsafe { self.egl.import(&frame) } {
+                        Ok(textures) => {
+                            info!("Imported deck {}'s shared textures", deck_id + 1);
+                            self.decks.insert(
+                                deck_id,
+                                ImportedDeck {
+                                    generation: frame.generation,
+                                    textures,
+                                    size: (frame.buffers.width, frame.buffers.height),
+                                },
+                            );
+                        }
+                        Err(e) => {
+                            warn!("Can't import deck {}'s textures: {}", deck_id + 1, e);
+                            failed = true;
+                            return;
+                        }
+                    }
+                }
+                if let (Some(deck), Some(index)) = (self.decks.get(&deck_id), frame.ready) {
+                    result = deck
+                        .textures
+                        .get(index as usize)
+                        .map(|t| (t.texture, deck.size));
+                }
+            });
+
+            if failed {
+                receiver.reject_shared(deck_id);
+            }
+            if !sharing {
+                if let Some(old) = self.decks.remove(&deck_id) {
+                    unsafe { old.delete(&self.egl) };
+                }
+            }
+            result
+        }
+    }
+
+    impl ImportedDeck {
+        unsafe fn delete(self, egl: &Egl) {
+            for texture in self.textures {
+                texture.delete(egl);
+            }
+        }
+    }
+
+    impl Drop for SharedTextureImporter {
+        fn drop(&mut self) {
+            for (_, deck) in self.decks.drain() {
+                unsafe { deck.delete(&self.egl) };
+            }
+        }
+    }
+}
+
+#[cfg(target_os = "linux")]
+pub use imp::{Egl, SharedTextureFeed, SharedTextureImporter};
+
+// Other platforms share frames as pixels
+#[cfg(not(target_os = "linux"))]
+mod imp {
+    use glutin::context::PossiblyCurrentContext;
+    use glutin::display::Display;
+    use opendrop_core::video::CompositorReceiver;
+
+    #[derive(Clone, Copy)]
+    pub struct Egl {
+        _private: (),
+    }
+
+    impl Egl {
+        pub fn from_context(_display: &Display, _context: &PossiblyCurrentContext) -> Option<Self> {
+            None
+        }
+    }
+
+    pub struct SharedTextureFeed {
+        _private: (),
+    }
+
+    impl SharedTextureFeed {
+        pub fn connect(_egl: &Egl, _socket: &str, _deck_id: u8) -> Result<Self, String> {
+            Err("Shared textures not supported on this platform".to_string())
+        }
+
+        pub fn publish(&mut self, _width: u32, _height: u32) -> Result<(), String> {
+            Ok(())
+        }
+    }
+
+    pub struct SharedTextureImporter {
+        _private: (),
+    }
+
+    impl SharedTextureImporter {
+        pub fn listen(
+            _egl: Option<Egl>,
+            _receiver: &mut CompositorReceiver,
+        ) -> Option<(Self, String)> {
+            None
+        }
+
+        pub fn texture(
+            &mut self,
+            _receiver: &CompositorReceiver,
+            _deck_id: u8,
+        ) -> Option<(u32, (u32, u32))> {
+            None
+        }
+    }
+}
+
+#[cfg(not(target_os = "linux"))]
+pub use imp::{Egl, SharedTextureFeed, SharedTextureImporter};
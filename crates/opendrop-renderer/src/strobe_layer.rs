@@ -0,0 +1,106 @@
+//! Strobe flashes and blackout over the finished frame
+//!
+//! Both are a solid colour blended over the frame: white by the flash's
+//! brightness, then black by how far the blackout has faded. The frame's
+//! alpha is left alone, so a transparent background stays transparent.
+
+use crate::shader::{link_program, GlStateGuard};
+
+const VERTEX_SHADER: &str = r#"#version 330 core
+layout(location = 0) in vec2 position;
+void main() {
+    gl_Position = vec4(position, 0.0, 1.0);
+}
+"#;
+
+const FRAGMENT_SHADER: &str = r#"#version 330 core
+out vec4 color;
+uniform vec4 fill;
+void main() {
+    color = fill;
+}
+"#;
+
+/// Solid colour pass for one deck
+pub struct StrobeLayer {
+    program: u32,
+    vao: u32,
+    vbo: u32,
+    fill_location: i32,
+}
+
+impl StrobeLayer {
+    /// Set up GL resources on the current context
+    pub fn new() -> Result<Self, String> {
+        let program = unsafe { link_program("Strobe", VERTEX_SHADER, FRAGMENT_SHADER)? };
+        let mut layer = Self {
+            program,
+            vao: 0,
+            vbo: 0,
+            fill_location: -1,
+        };
+
+        #[rustfmt::skip]
+        let vertices: [f32; 8] = [
+            -1.0, -1.0,
+             1.0, -1.0,
+            -1.0,  1.0,
+             1.0,  1.0,
+        ];
+        unsafe {
+            layer.fill_location = gl::GetUniformLocation(program, c"fill".as_ptr());
+
+            gl::GenVertexArrays(1, &mut layer.vao);
+            gl::GenBuffers(1, &mut layer.vbo);
+            gl::BindVertexArray(layer.vao);
+            gl::BindBuffer(gl::ARRAY_BUFFER, layer.vbo);
+            gl::BufferData(
+                gl::ARRAY_BUFFER,
+                std::mem::size_of_val(&vertices) as isize,
+                vertices.as_ptr() as *const _,
+                gl::STATIC_DRAW,
+            );
+            let stride = (2 * std::mem::size_of::<f32>()) as i32;
+            gl::VertexAttribPointer(0, 2, gl::FLOAT, gl::FALSE, stride, std::ptr::null());
+            gl::EnableVertexAttribArray(0);
+            gl::BindVertexArray(0);
+            gl::BindBuffer(gl::ARRAY_BUFFER, 0);
+        }
+
+        Ok(layer)
+    }
+
+    /// Flash white by `flash`, then darken by `blackout` (both 0-1)
+    pub fn draw(&mut self, flash: f32, blackout: f32) {
+        if flash <= 0.0 && blackout <= 0.0 {
+            return;
+        }
+
+        unsafe {
+            let _state = GlStateGuard::save();
+
+            gl::Enable(gl::BLEND);
+            gl::BlendFuncSeparate(gl::SRC_ALPHA, gl::ONE_MINUS_SRC_ALPHA, gl::ZERO, gl::ONE);
+            gl::UseProgram(self.program);
+            gl::BindVertexArray(self.vao);
+            if flash > 0.0 {
+                gl::Uniform4f(self.fill_location, 1.0, 1.0, 1.0, flash.min(1.0));
+                gl::DrawArrays(gl::TRIANGLE_STRIP, 0, 4);
+            }
+            if blackout > 0.0 {
+                gl::Uniform4f(self.fill_location, 0.0, 0.0, 0.0, blackout.min(1.0));
+                gl::DrawArrays(gl::TRIANGLE_STRIP, 0, 4);
+            }
+        }
+    }
+}
+
+impl Drop for StrobeLayer {
+    fn drop(&mut self) {
+        unsafe {
+            gl::DeleteBuffers(1, &self.vbo);
+            gl::DeleteVertexArrays(1, &self.vao);
+            gl::DeleteProgram(self.program);
+        }
+    }
+}
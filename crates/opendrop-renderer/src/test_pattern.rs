@@ -0,0 +1,133 @@
+//! Test pattern drawn instead of the visualization
+//!
+//! The pattern is drawn on the CPU once per window size, uploaded to a
+//! texture and drawn over the whole framebuffer, so the window, every output
+//! and the compositor all show it.
+
+use opendrop_core::video::test_pattern::render_test_pattern;
+
+use crate::shader::{link_program, GlStateGuard};
+
+const VERTEX_SHADER: &str = r#"#version 330 core
+layout(location = 0) in vec2 position;
+out vec2 uv;
+void main() {
+    // Frames are top row first, so the top of the quad samples v = 0
+    uv = vec2(position.x * 0.5 + 0.5, 0.5 - position.y * 0.5);
+    gl_Position = vec4(position, 0.0, 1.0);
+}
+"#;
+
+const FRAGMENT_SHADER: &str = r#"#version 330 core
+in vec2 uv;
+out vec4 color;
+uniform sampler2D frame;
+void main() {
+    color = vec4(texture(frame, uv).rgb, 1.0);
+}
+"#;
+
+/// Full-window test pattern for one deck
+pub struct TestPattern {
+    deck_id: u8,
+    program: u32,
+    vao: u32,
+    vbo: u32,
+    texture: u32,
+    /// Size the texture was drawn for
+    size: (u32, u32),
+}
+
+impl TestPattern {
+    /// Set up GL resources on the current context
+    pub fn new(deck_id: u8) -> Result<Self, String> {
+        let program = unsafe { link_program("Test pattern", VERTEX_SHADER, FRAGMENT_SHADER)? };
+        let mut pattern = Self {
+            deck_id,
+            program,
+            vao: 0,
+            vbo: 0,
+            texture: 0,
+            size: (0, 0),
+        };
+
+        #[rustfmt::skip]
+        let vertices: [f32; 8] = [
+            -1.0, -1.0,
+             1.0, -1.0,
+            -1.0,  1.0,
+             1.0,  1.0,
+        ];
+        unsafe {
+            gl::GenVertexArrays(1, &mut pattern.vao);
+            gl::GenBuffers(1, &mut pattern.vbo);
+            gl::BindVertexArray(pattern.vao);
+            gl::BindBuffer(gl::ARRAY_BUFFER, pattern.vbo);
+            gl::BufferData(
+                gl::ARRAY_BUFFER,
+                std::mem::size_of_val(&vertices) as isize,
+                vertices.as_ptr() as *const _,
+                gl::STATIC_DRAW,
+            );
+            let stride = (2 * std::mem::size_of::<f32>()) as i32;
+            gl::VertexAttribPointer(0, 2, gl::FLOAT, gl::FALSE, stride, std::ptr::null());
+            gl::EnableVertexAttribArray(0);
+            gl::BindVertexArray(0);
+            gl::BindBuffer(gl::ARRAY_BUFFER, 0);
+
+            gl::GenTextures(1, &mut pattern.texture);
+        }
+
+        Ok(pattern)
+    }
+
+    /// Draw the pattern over a `width`x`height` framebuffer
+    pub fn draw(&mut self, width: u32, height: u32) {
+        if width == 0 || height == 0 {
+            return;
+        }
+
+        unsafe {
+            let _state = GlStateGuard::save();
+
+            gl::BindTexture(gl::TEXTURE_2D, self.texture);
+            if self.size != (width, height) {
+                // Pixel for pixel, so lines stay one pixel sharp
+                let pixels = render_test_pattern(width, height, self.deck_id);
+                gl::PixelStorei(gl::UNPACK_ALIGNMENT, 4);
+                gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_MIN_FILTER, gl::NEAREST as i32);
+                gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_MAG_FILTER, gl::NEAREST as i32);
+                gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_WRAP_S, gl::CLAMP_TO_EDGE as i32);
+                gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_WRAP_T, gl::CLAMP_TO_EDGE as i32);
+                gl::TexImage2D(
+                    gl::TEXTURE_2D,
+                    0,
+                    gl::RGBA8 as i32,
+                    width as i32,
+                    height as i32,
+                    0,
+                    gl::RGBA,
+                    gl::UNSIGNED_BYTE,
+                    pixels.as_ptr() as *const _,
+                );
+                self.size = (width, height);
+            }
+
+            gl::Disable(gl::BLEND);
+            gl::UseProgram(self.program);
+            gl::BindVertexArray(self.vao);
+            gl::DrawArrays(gl::TRIANGLE_STRIP, 0, 4);
+        }
+    }
+}
+
+impl Drop for TestPattern {
+    fn drop(&mut self) {
+        unsafe {
+            gl::DeleteTextures(1, &self.texture);
+            gl::DeleteBuffers(1, &self.vbo);
+            gl::DeleteVertexArrays(1, &self.vao);
+            gl::DeleteProgram(self.program);
+        }
+    }
+}
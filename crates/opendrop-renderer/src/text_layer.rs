@@ -0,0 +1,237 @@
+//! Text overlay drawn over the visualization
+//!
+//! The text is rasterized once per frame size, uploaded to a texture and
+//! drawn as a quad at its position, faded in and out by the config.
+
+use std::time::Instant;
+
+use opendrop_core::video::text_overlay::{load_font, render_text, FontVec};
+use opendrop_core::video::TextOverlayConfig;
+
+use crate::shader::{link_program, GlStateGuard};
+
+const VERTEX_SHADER: &str = r#"#version 330 core
+layout(location = 0) in vec2 position;
+layout(location = 1) in vec2 tex_coord;
+out vec2 uv;
+void main() {
+    uv = tex_coord;
+    gl_Position = vec4(position, 0.0, 1.0);
+}
+"#;
+
+const FRAGMENT_SHADER: &str = r#"#version 330 core
+in vec2 uv;
+out vec4 color;
+uniform sampler2D frame;
+uniform float opacity;
+void main() {
+    vec4 text = texture(frame, uv);
+    color = vec4(text.rgb, text.a * opacity);
+}
+"#;
+
+/// Text shown on the deck until it fades out
+pub struct TextLayer {
+    config: TextOverlayConfig,
+    font: FontVec,
+    shown: Instant,
+    /// When clearing started and the opacity it fades from
+    clearing: Option<(Instant, f32)>,
+    /// Opacity of the last frame drawn
+    opacity: f32,
+    program: u32,
+    vao: u32,
+    vbo: u32,
+    texture: u32,
+    /// Frame size the text was rasterized for
+    frame_size: (u32, u32),
+    opacity_location: i32,
+}
+
+impl TextLayer {
+    /// Load the font and set up GL resources on the current context
+    pub fn new(config: TextOverlayConfig) -> Result<Self, String> {
+        config.validate().map_err(|e| e.to_string())?;
+        let font = load_font(config.font_path.as_deref()).map_err(|e| e.to_string())?;
+        let program = unsafe { link_program("Text overlay", VERTEX_SHADER, FRAGMENT_SHADER)? };
+        let mut layer = Self {
+            config,
+            font,
+            shown: Instant::now(),
+            clearing: None,
+            opacity: 0.0,
+            program,
+            vao: 0,
+            vbo: 0,
+            texture: 0,
+            frame_size: (0, 0),
+            opacity_location: -1,
+        };
+
+        unsafe {
+            layer.opacity_location = gl::GetUniformLocation(program, c"opacity".as_ptr());
+
+            // Position and texture coordinates, filled in once the text is placed
+            gl::GenVertexArrays(1, &mut layer.vao);
+            gl::GenBuffers(1, &mut layer.vbo);
+            gl::BindVertexArray(layer.vao);
+            gl::BindBuffer(gl::ARRAY_BUFFER, layer.vbo);
+            gl::BufferData(
+                gl::ARRAY_BUFFER,
+                (16 * std::mem::size_of::<f32>()) as isize,
+                std::ptr::null(),
+                gl::DYNAMIC_DRAW,
+            );
+            let stride = (4 * std::mem::size_of::<f32>()) as i32;
+            gl::VertexAttribPointer(0, 2, gl::FLOAT, gl::FALSE, stride, std::ptr::null());
+            gl::EnableVertexAttribArray(0);
+            gl::VertexAttribPointer(
+                1,
+                2,
+                gl::FLOAT,
+                gl::FALSE,
+                stride,
+                (2 * std::mem::size_of::<f32>()) as *const _,
+            );
+            gl::EnableVertexAttribArray(1);
+            gl::BindVertexArray(0);
+            gl::BindBuffer(gl::ARRAY_BUFFER, 0);
+
+            gl::GenTextures(1, &mut layer.texture);
+            gl::BindTexture(gl::TEXTURE_2D, layer.texture);
+            gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_MIN_FILTER, gl::LINEAR as i32);
+            gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_MAG_FILTER, gl::LINEAR as i32);
+            gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_WRAP_S, gl::CLAMP_TO_EDGE as i32);
+            gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_WRAP_T, gl::CLAMP_TO_EDGE as i32);
+            gl::BindTexture(gl::TEXTURE_2D, 0);
+        }
+
+        Ok(layer)
+    }
+
+    /// Change the text in place, keeping its fade
+    pub fn set_text(&mut self, text: String) {
+        self.config.text = text;
+        // Rasterized again on the next draw
+        self.frame_size = (0, 0);
+    }
+
+    /// Fade the text out from where it is now
+    pub fn clear(&mut self) {
+        if self.clearing.is_none() {
+            self.clearing = Some((Instant::now(), self.opacity));
+        }
+    }
+
+    /// Draw over a `width`x`height` framebuffer; false once the text has
+    /// faded out and the layer can be dropped
+    pub fn draw(&mut self, width: u32, height: u32) -> bool {
+        let opacity = match self.clearing {
+            Some((started, from)) => self.config.fade_out(started.elapsed().as_secs_f32(), from),
+            None => self.config.opacity_at(self.shown.elapsed().as_secs_f32()),
+        };
+        let Some(opacity) = opacity else {
+            return false;
+        };
+        self.opacity = opacity;
+        if width == 0 || height == 0 {
+            return true;
+        }
+        if self.frame_size != (width, height) {
+            self.rasterize(width, height);
+        }
+
+        unsafe {
+            let _state = GlStateGuard::save();
+
+            gl::Enable(gl::BLEND);
+            // Keep the visualization's alpha for captured frames
+            gl::BlendFuncSeparate(gl::SRC_ALPHA, gl::ONE_MINUS_SRC_ALPHA, gl::ZERO, gl::ONE);
+            gl::UseProgram(self.program);
+            gl::Uniform1f(self.opacity_location, opacity);
+            gl::BindTexture(gl::TEXTURE_2D, self.texture);
+            gl::BindVertexArray(self.vao);
+            gl::DrawArrays(gl::TRIANGLE_STRIP, 0, 4);
+        }
+
+        true
+    }
+
+    /// Rasterize the text for a frame size, upload it and place the quad
+    fn rasterize(&mut self, width: u32, height: u32) {
+        let line_height = self.config.font_size * height as f32;
+        let (mut pixels, mut w, mut h) = render_text(&self.font, &self.config.text, line_height);
+        // Long lines are shrunk to fit the frame width
+        let max_width = width as f32 * 0.94;
+        if w as f32 > max_width {
+            let shrink = max_width / w as f32;
+            (pixels, w, h) = render_text(&self.font, &self.config.text, line_height * shrink);
+        }
+        let row_length = w;
+        let (w, h) = (w.min(width), h.min(height));
+        let (left, top) = self.config.position.place(width, height, w, h);
+
+        // Frame rows are top-down, GL's y points up
+        let x0 = left as f32 / width as f32 * 2.0 - 1.0;
+        let x1 = (left + w) as f32 / width as f32 * 2.0 - 1.0;
+        let y0 = 1.0 - (top + h) as f32 / height as f32 * 2.0;
+        let y1 = 1.0 - top as f32 / height as f32 * 2.0;
+        #[rustfmt::skip]
+        let vertices: [f32; 16] = [
+            x0, y0, 0.0, 1.0,
+            x1, y0, 1.0, 1.0,
+            x0, y1, 0.0, 0.0,
+            x1, y1, 1.0, 0.0,
+        ];
+
+        unsafe {
+            let mut previous_texture = 0;
+            let mut previous_active_texture = 0;
+            gl::GetIntegerv(gl::ACTIVE_TEXTURE, &mut previous_active_texture);
+            gl::ActiveTexture(gl::TEXTURE0);
+            gl::GetIntegerv(gl::TEXTURE_BINDING_2D, &mut previous_texture);
+
+            gl::BindTexture(gl::TEXTURE_2D, self.texture);
+            gl::PixelStorei(gl::UNPACK_ALIGNMENT, 4);
+            // Rows of the full rasterized width, in case it was cropped
+            gl::PixelStorei(gl::UNPACK_ROW_LENGTH, row_length as i32);
+            gl::TexImage2D(
+                gl::TEXTURE_2D,
+                0,
+                gl::RGBA8 as i32,
+                w as i32,
+                h as i32,
+                0,
+                gl::RGBA,
+                gl::UNSIGNED_BYTE,
+                pixels.as_ptr() as *const _,
+            );
+            gl::PixelStorei(gl::UNPACK_ROW_LENGTH, 0);
+            gl::BindTexture(gl::TEXTURE_2D, previous_texture as u32);
+            gl::ActiveTexture(previous_active_texture as u32);
+
+            gl::BindBuffer(gl::ARRAY_BUFFER, self.vbo);
+            gl::BufferSubData(
+                gl::ARRAY_BUFFER,
+                0,
+                std::mem::size_of_val(&vertices) as isize,
+                vertices.as_ptr() as *const _,
+            );
+            gl::BindBuffer(gl::ARRAY_BUFFER, 0);
+        }
+
+        self.frame_size = (width, height);
+    }
+}
+
+impl Drop for TextLayer {
+    fn drop(&mut self) {
+        unsafe {
+            gl::DeleteTextures(1, &self.texture);
+            gl::DeleteBuffers(1, &self.vbo);
+            gl::DeleteVertexArrays(1, &self.vao);
+            gl::DeleteProgram(self.program);
+        }
+    }
+}
@@ -0,0 +1,244 @@
+//! Preset thumbnails rendered offscreen
+//!
+//! With `thumbnailer: true` the renderer opens no deck. It waits for
+//! `thumbnail` commands and, for each, plays the preset into an [`Offscreen`]
+//! framebuffer for [`THUMBNAIL_SECS`] against a synthetic beat, then saves
+//! the thumbnail and reports it. Frames are paced in real time, as projectM
+//! animates by the clock. A context without a surface is used where the
+//! drivers offer one; elsewhere a hidden window provides it.
+
+use std::path::Path;
+use std::sync::mpsc::{self, Receiver};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use glutin::config::ConfigTemplateBuilder;
+use glutin::display::{Display, GetGlDisplay};
+use glutin::prelude::*;
+use glutin::surface::{Surface, SurfaceAttributesBuilder, WindowSurface};
+use glutin_winit::{DisplayBuilder, GlWindow};
+use raw_window_handle::HasWindowHandle;
+use tracing::{debug, error, info, warn};
+use winit::application::ApplicationHandler;
+use winit::dpi::PhysicalSize;
+use winit::event::WindowEvent;
+use winit::event_loop::{ActiveEventLoop, EventLoop};
+use winit::window::{Window, WindowAttributes, WindowId};
+
+use opendrop_core::audio::generator::DEFAULT_TEST_BPM;
+use opendrop_core::audio::{AudioConfig, SignalGenerator, TestSignal};
+use opendrop_core::video::output::copy_flipped;
+use opendrop_core::video::thumbnail::{thumbnail_frames, THUMBNAIL_FPS, THUMBNAIL_SECS};
+use opendrop_core::video::ThumbnailWriter;
+use projectm_rs::{Channels, ProjectM};
+
+use crate::headless::{self, Offscreen};
+use crate::{
+    apply_quality, gl_context, load_gl, send_event, spawn_stdin_reader, Command, Config, Event,
+};
+
+/// Render thumbnails as they are asked for, until stopped or the parent
+/// goes away
+pub fn run(config: Config) {
+    let (command_tx, command_rx) = mpsc::channel();
+    spawn_stdin_reader(command_tx);
+
+    match headless::create_context() {
+        Ok((display, _context)) => serve(&config, &display, &command_rx),
+        Err(e) => {
+            info!("{}; rendering thumbnails in a hidden window", e);
+            let event_loop = EventLoop::new().expect("Failed to create event loop");
+            let mut app = HiddenWindow {
+                config,
+                command_rx,
+                window: None,
+            };
+            if let Err(e) = event_loop.run_app(&mut app) {
+                error!("Event loop error: {}", e);
+                send_event(Event::Error {
+                    message: e.to_string(),
+                });
+            }
+        }
+    }
+}
+
+/// Provides a GL context where there is no headless one; all the work
+/// happens once it is up
+struct HiddenWindow {
+    config: Config,
+    command_rx: Receiver<Command>,
+    /// Kept alive while thumbnails render
+    window: Option<(Window, Surface<WindowSurface>)>,
+}
+
+impl ApplicationHandler for HiddenWindow {
+    fn resumed(&mut self, event_loop: &ActiveEventLoop) {
+        if self.window.is_some() {
+            return;
+        }
+        let window_attrs = WindowAttributes::default()
+            .with_title("OpenDrop - Thumbnails")
+            .with_inner_size(PhysicalSize::new(self.config.width, self.config.height))
+            .with_visible(false);
+        let template = ConfigTemplateBuilder::new()
+            .with_alpha_size(8)
+            .with_depth_size(24)
+            .with_stencil_size(8);
+        let display_builder = DisplayBuilder::new().with_window_attributes(Some(window_attrs));
+
+        let built = display_builder
+            .build(event_loop, template, |mut configs| configs.next().unwrap())
+            .map_err(|e| e.to_string())
+            .and_then(|(window, gl_config)| {
+                let window = window.ok_or("no window was created")?;
+                let raw_window_handle = window.window_handle().ok().map(|h| h.as_raw());
+                let display = gl_config.display();
+                let context = gl_context::create_context(&display, &gl_config, raw_window_handle)?;
+                let attrs = window
+                    .build_surface_attributes(SurfaceAttributesBuilder::new())
+                    .map_err(|e| e.to_string())?;
+                let surface = unsafe { display.create_window_surface(&gl_config, &attrs) }
+                    .map_err(|e| e.to_string())?;
+                let context = context.make_current(&surface).map_err(|e| e.to_string())?;
+                Ok((window, surface, display, context))
+            });
+
+        match built {
+            Ok((window, surface, display, _context)) => {
+                self.window = Some((window, surface));
+                serve(&self.config, &display, &self.command_rx);
+            }
+            Err(e) => {
+                error!("Failed to create thumbnail window: {}", e);
+                send_event(Event::Error { message: e });
+            }
+        }
+        event_loop.exit();
+    }
+
+    fn window_event(&mut self, _: &ActiveEventLoop, _: WindowId, _: WindowEvent) {}
+}
+
+/// Answer thumbnail commands on the current context
+fn serve(config: &Config, display: &Display, commands: &Receiver<Command>) {
+    let gpu = load_gl(display);
+    let offscreen = match Offscreen::new(config.width, config.height) {
+        Ok(offscreen) => offscreen,
+        Err(e) => {
+            error!("Failed to create offscreen framebuffer: {}", e);
+            send_event(Event::Error { message: e });
+            return;
+        }
+    };
+    send_event(Event::Ready);
+    if let Some(gpu) = gpu {
+        send_event(Event::GpuInfo(gpu));
+    }
+
+    // Ends when stdin closes
+    for command in commands {
+        match command {
+            Command::Thumbnail {
+                preset_path,
+                output_path,
+            } => {
+                let started = Instant::now();
+                let event = match render(config, &offscreen, &preset_path, &output_path) {
+                    Ok(()) => {
+                        info!(
+                            "Thumbnail of {} done in {:.1}s",
+                            preset_path,
+                            started.elapsed().as_secs_f32()
+                        );
+                        Event::ThumbnailDone {
+                            preset_path,
+                            output_path,
+                        }
+                    }
+                    Err(error) => {
+                        warn!("No thumbnail of {}: {}", preset_path, error);
+                        Event::ThumbnailFailed { preset_path, error }
+                    }
+                };
+                send_event(event);
+            }
+            Command::Stop => break,
+            other => debug!("Thumbnailer ignores {:?}", other),
+        }
+    }
+}
+
+/// Play `preset_path` and write its thumbnail to `output_path`
+fn render(
+    config: &Config,
+    offscreen: &Offscreen,
+    preset_path: &str,
+    output_path: &str,
+) -> Result<(), String> {
+    let (width, height) = offscreen.size();
+    // A fresh instance each time, so no preset inherits another's state
+    let mut pm = ProjectM::new(width, height).map_err(|e| e.to_string())?;
+    if !config.texture_paths.is_empty() {
+        let path_refs: Vec<&str> = config.texture_paths.iter().map(|s| s.as_str()).collect();
+        pm.set_texture_search_paths(&path_refs);
+    }
+    apply_quality(&mut pm, &config.quality);
+    pm.set_preset_locked(true);
+    pm.set_framebuffer(offscreen.framebuffer());
+    pm.load_preset(preset_path, false)
+        .map_err(|e| e.to_string())?;
+
+    let mut writer = ThumbnailWriter::create(Path::new(output_path), width, height)
+        .map_err(|e| e.to_string())?;
+    let sample_rate = AudioConfig::default().sample_rate;
+    let mut signal = SignalGenerator::new(
+        TestSignal::Beats {
+            bpm: DEFAULT_TEST_BPM,
+        },
+        sample_rate,
+    );
+    let samples_per_frame = (sample_rate / THUMBNAIL_FPS) as usize;
+    let frame_time = Duration::from_secs_f64(1.0 / THUMBNAIL_FPS as f64);
+    let frames = thumbnail_frames();
+    let row_bytes = (width * 4) as usize;
+    let mut raw = vec![0u8; row_bytes * height as usize];
+    let mut pixels = vec![0u8; raw.len()];
+    debug!("Rendering {} for {}s", preset_path, THUMBNAIL_SECS);
+
+    let mut next_frame = Instant::now();
+    for frame in 0..frames {
+        pm.add_pcm(&signal.generate(samples_per_frame), Channels::Stereo);
+        offscreen.bind();
+        pm.render_frame();
+
+        if writer.wants_every_frame() || frame + 1 == frames {
+            offscreen.bind();
+            unsafe {
+                gl::PixelStorei(gl::PACK_ALIGNMENT, 1);
+                gl::ReadPixels(
+                    0,
+                    0,
+                    width as i32,
+                    height as i32,
+                    gl::RGBA,
+                    gl::UNSIGNED_BYTE,
+                    raw.as_mut_ptr() as *mut _,
+                );
+            }
+            copy_flipped(&raw, &mut pixels, row_bytes);
+            writer.push(&pixels).map_err(|e| e.to_string())?;
+        }
+
+        next_frame += frame_time;
+        let now = Instant::now();
+        if next_frame > now {
+            thread::sleep(next_frame - now);
+        } else {
+            next_frame = now;
+        }
+    }
+
+    drop(pm);
+    writer.finish().map(|_| ()).map_err(|e| e.to_string())
+}
@@ -0,0 +1,232 @@
+//! Crossfade between presets
+//!
+//! projectM draws one preset per instance, so during a transition the
+//! outgoing preset keeps running on a second instance. Each frame it is drawn
+//! and copied to a texture, the incoming preset is drawn over the framebuffer,
+//! and the copy is blended on top with falling opacity. The second instance is
+//! kept afterwards for the next switch, so switching doesn't set up projectM
+//! every time.
+
+use std::time::{Duration, Instant};
+
+use projectm_rs::{Channels, ProjectM};
+
+use crate::shader::{link_program, GlStateGuard};
+
+/// Crossfade length until the parent sets one
+pub const DEFAULT_TRANSITION_SECS: f64 = 2.0;
+
+/// Longest crossfade accepted
+pub const MAX_TRANSITION_SECS: f64 = 30.0;
+
+/// How long a switch projectM asked for waits for the parent's load; later
+/// loads fade as usual
+pub const CUT_REQUEST_TIMEOUT: Duration = Duration::from_secs(2);
+
+const VERTEX_SHADER: &str = r#"#version 330 core
+layout(location = 0) in vec2 position;
+out vec2 uv;
+void main() {
+    // Copied from the framebuffer, so bottom row first like the quad
+    uv = position * 0.5 + 0.5;
+    gl_Position = vec4(position, 0.0, 1.0);
+}
+"#;
+
+const FRAGMENT_SHADER: &str = r#"#version 330 core
+in vec2 uv;
+out vec4 color;
+uniform sampler2D frame;
+uniform float opacity;
+void main() {
+    color = vec4(texture(frame, uv).rgb, opacity);
+}
+"#;
+
+/// Second projectM instance and the fade from it to the current one
+pub struct PresetTransition {
+    /// Previous preset's instance; idle between transitions
+    outgoing: Option<ProjectM>,
+    /// Start and length of the running fade
+    fade: Option<(Instant, Duration)>,
+    program: u32,
+    vao: u32,
+    vbo: u32,
+    texture: u32,
+    /// Size the texture was allocated for
+    size: (u32, u32),
+    opacity_location: i32,
+}
+
+impl PresetTransition {
+    /// Set up GL resources on the current context
+    pub fn new() -> Result<Self, String> {
+        let program = unsafe { link_program("Preset transition", VERTEX_SHADER, FRAGMENT_SHADER)? };
+        let mut transition = Self {
+            outgoing: None,
+            fade: None,
+            program,
+            vao: 0,
+            vbo: 0,
+            texture: 0,
+            size: (0, 0),
+            opacity_location: -1,
+        };
+
+        #[rustfmt::skip]
+        let vertices: [f32; 8] = [
+            -1.0, -1.0,
+             1.0, -1.0,
+            -1.0,  1.0,
+             1.0,  1.0,
+        ];
+        unsafe {
+            transition.opacity_location = gl::GetUniformLocation(program, c"opacity".as_ptr());
+
+            gl::GenVertexArrays(1, &mut transition.vao);
+            gl::GenBuffers(1, &mut transition.vbo);
+            gl::BindVertexArray(transition.vao);
+            gl::BindBuffer(gl::ARRAY_BUFFER, transition.vbo);
+            gl::BufferData(
+                gl::ARRAY_BUFFER,
+                std::mem::size_of_val(&vertices) as isize,
+                vertices.as_ptr() as *const _,
+                gl::STATIC_DRAW,
+            );
+            let stride = (2 * std::mem::size_of::<f32>()) as i32;
+            gl::VertexAttribPointer(0, 2, gl::FLOAT, gl::FALSE, stride, std::ptr::null());
+            gl::EnableVertexAttribArray(0);
+            gl::BindVertexArray(0);
+            gl::BindBuffer(gl::ARRAY_BUFFER, 0);
+
+            gl::GenTextures(1, &mut transition.texture);
+            gl::BindTexture(gl::TEXTURE_2D, transition.texture);
+            gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_MIN_FILTER, gl::NEAREST as i32);
+            gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_MAG_FILTER, gl::NEAREST as i32);
+            gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_WRAP_S, gl::CLAMP_TO_EDGE as i32);
+            gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_WRAP_T, gl::CLAMP_TO_EDGE as i32);
+            gl::BindTexture(gl::TEXTURE_2D, 0);
+        }
+
+        Ok(transition)
+    }
+
+    /// Take the idle instance to load the next preset into, ending any fade
+    pub fn take_spare(&mut self) -> Option<ProjectM> {
+        self.fade = None;
+        self.outgoing.take()
+    }
+
+    /// Hand back an instance that couldn't be used
+    pub fn put_spare(&mut self, projectm: ProjectM) {
+        self.fade = None;
+        self.outgoing = Some(projectm);
+    }
+
+    /// Fade from `outgoing` to the instance drawn next, over `duration`
+    pub fn start(&mut self, outgoing: ProjectM, duration: Duration) {
+        self.outgoing = Some(outgoing);
+        self.fade = Some((Instant::now(), duration));
+    }
+
+    /// Feed the outgoing preset while it is still on screen
+    pub fn add_pcm(&mut self, samples: &[f32], channels: Channels) {
+        if let (Some(ref mut outgoing), Some(_)) = (&mut self.outgoing, self.fade) {
+            outgoing.add_pcm(samples, channels);
+        }
+    }
+
+    /// Keep the idle instance at the window size
+    pub fn resize(&mut self, width: u32, height: u32) {
+        if let Some(ref mut outgoing) = self.outgoing {
+            outgoing.resize(width, height);
+        }
+    }
+
+    /// Idle instance, to keep its settings in step with the current one
+    pub fn spare_mut(&mut self) -> Option<&mut ProjectM> {
+        self.outgoing.as_mut()
+    }
+
+    /// Draw `incoming` over a `width`x`height` framebuffer, faded in from
+    /// the outgoing preset while a transition runs
+    pub fn render(&mut self, incoming: &mut ProjectM, width: u32, height: u32) {
+        let opacity = match self.fade {
+            Some((started, duration)) => {
+                let t = started.elapsed().as_secs_f32() / duration.as_secs_f32().max(f32::EPSILON);
+                if t >= 1.0 {
+                    self.fade = None;
+                    None
+                } else {
+                    // Ease in and out, so the cut has no visible start or end
+                    Some(1.0 - t * t * (3.0 - 2.0 * t))
+                }
+            }
+            None => None,
+        };
+        let (Some(opacity), Some(ref mut outgoing)) = (opacity, &mut self.outgoing) else {
+            incoming.render_frame();
+            return;
+        };
+        if width == 0 || height == 0 {
+            incoming.render_frame();
+            return;
+        }
+
+        outgoing.render_frame();
+        unsafe {
+            let mut previous_texture = 0;
+            let mut previous_active_texture = 0;
+            gl::GetIntegerv(gl::ACTIVE_TEXTURE, &mut previous_active_texture);
+            gl::ActiveTexture(gl::TEXTURE0);
+            gl::GetIntegerv(gl::TEXTURE_BINDING_2D, &mut previous_texture);
+
+            gl::BindTexture(gl::TEXTURE_2D, self.texture);
+            if self.size != (width, height) {
+                gl::TexImage2D(
+                    gl::TEXTURE_2D,
+                    0,
+                    gl::RGBA8 as i32,
+                    width as i32,
+                    height as i32,
+                    0,
+                    gl::RGBA,
+                    gl::UNSIGNED_BYTE,
+                    std::ptr::null(),
+                );
+                self.size = (width, height);
+            }
+            gl::CopyTexSubImage2D(gl::TEXTURE_2D, 0, 0, 0, 0, 0, width as i32, height as i32);
+
+            gl::BindTexture(gl::TEXTURE_2D, previous_texture as u32);
+            gl::ActiveTexture(previous_active_texture as u32);
+        }
+        incoming.render_frame();
+
+        unsafe {
+            let _state = GlStateGuard::save();
+
+            gl::Enable(gl::BLEND);
+            // Keep the visualization's alpha for captured frames
+            gl::BlendFuncSeparate(gl::SRC_ALPHA, gl::ONE_MINUS_SRC_ALPHA, gl::ZERO, gl::ONE);
+            gl::UseProgram(self.program);
+            gl::Uniform1f(self.opacity_location, opacity);
+            gl::BindTexture(gl::TEXTURE_2D, self.texture);
+            gl::BindVertexArray(self.vao);
+            gl::DrawArrays(gl::TRIANGLE_STRIP, 0, 4);
+        }
+    }
+}
+
+impl Drop for PresetTransition {
+    fn drop(&mut self) {
+        // Free the instance's GL objects before ours
+        self.outgoing = None;
+        unsafe {
+            gl::DeleteTextures(1, &self.texture);
+            gl::DeleteBuffers(1, &self.vbo);
+            gl::DeleteVertexArrays(1, &self.vao);
+            gl::DeleteProgram(self.program);
+        }
+    }
+}
@@ -1,14 +1,14 @@
 //! ProjectM instance wrapper
 
-use std::cell::UnsafeCell;
-use std::ffi::CString;
+use std::cell::{Cell, RefCell, UnsafeCell};
+use std::ffi::{c_char, c_void, CStr, CString};
 use std::marker::PhantomData;
 use std::path::Path;
 use std::ptr::NonNull;
 
 use tracing::{debug, error};
 
-use crate::{Channels, Error, Preset};
+use crate::{Channels, Error, Preset, TouchType};
 
 /// ProjectM visualization instance
 ///
@@ -19,6 +19,11 @@ pub struct ProjectM {
     width: u32,
     height: u32,
     preset_path: Option<String>,
+    /// Framebuffer object frames are drawn to (0 = default framebuffer)
+    framebuffer: u32,
+    /// Written by projectM's callbacks; boxed so the address it was given
+    /// stays put
+    events: Box<Events>,
     // Prevent Send/Sync - ProjectM must stay on one thread
     _marker: PhantomData<UnsafeCell<()>>,
 }
@@ -47,9 +52,13 @@ impl ProjectM {
             width,
             height,
             preset_path: None,
+            framebuffer: 0,
+            events: Box::default(),
             _marker: PhantomData,
         };
 
+        instance.connect_events();
+
         // Set initial window size
         instance.resize(width, height);
 
@@ -57,6 +66,46 @@ impl ProjectM {
         Ok(instance)
     }
 
+    /// Register the callbacks that write into `events`
+    fn connect_events(&self) {
+        let events = &*self.events as *const Events as *mut c_void;
+
+        // projectM reports presets it can't parse through a callback and
+        // keeps the current one
+        unsafe {
+            projectm_sys::projectm_set_preset_switch_failed_event_callback(
+                self.handle.as_ptr(),
+                Some(preset_switch_failed),
+                events,
+            );
+        }
+
+        // When the preset duration runs out or a hard cut hits, projectM
+        // only asks for a switch; the caller picks the preset
+        unsafe {
+            projectm_sys::projectm_set_preset_switch_requested_event_callback(
+                self.handle.as_ptr(),
+                Some(preset_switch_requested),
+                events,
+            );
+        }
+    }
+
+    /// Note the playlist that now owns projectM's callbacks, or take them
+    /// back once it's gone
+    ///
+    /// Creating a libprojectM playlist points projectM's callbacks at it;
+    /// they're left there so it can switch and skip presets on its own.
+    pub(crate) fn set_playlist(
+        &mut self,
+        playlist: Option<NonNull<projectm_sys::projectm_playlist>>,
+    ) {
+        self.events.playlist.set(playlist);
+        if playlist.is_none() {
+            self.connect_events();
+        }
+    }
+
     /// Get the raw projectm handle (for advanced usage)
     pub fn raw_handle(&self) -> projectm_sys::projectm_handle {
         self.handle.as_ptr()
@@ -82,6 +131,8 @@ impl ProjectM {
     }
 
     /// Load a preset from file
+    ///
+    /// A preset projectM can't parse is an error, and the current one stays.
     pub fn load_preset<P: AsRef<Path>>(&mut self, path: P, smooth: bool) -> Result<(), Error> {
         let path = path.as_ref();
         debug!("Loading preset: {}", path.display());
@@ -98,6 +149,13 @@ impl ProjectM {
             Error::PresetLoadFailed("Invalid path encoding".to_string())
         })?;
 
+        // Borrow projectM's callbacks from a connected playlist to hear
+        // about a failed load, then hand them back
+        let playlist = self.events.playlist.get();
+        if playlist.is_some() {
+            self.connect_events();
+        }
+        self.events.load_failure.set(None);
         unsafe {
             projectm_sys::projectm_load_preset_file(
                 self.handle.as_ptr(),
@@ -105,6 +163,14 @@ impl ProjectM {
                 smooth,
             );
         }
+        if let Some(playlist) = playlist {
+            unsafe {
+                projectm_sys::projectm_playlist_connect(playlist.as_ptr(), self.handle.as_ptr());
+            }
+        }
+        if let Some(message) = self.events.load_failure.take() {
+            return Err(Error::PresetLoadFailed(message));
+        }
 
         self.preset_path = Some(path_str.to_string());
         Ok(())
@@ -152,17 +218,73 @@ impl ProjectM {
         }
     }
 
+    /// Add signed 16-bit PCM audio data, interleaved if stereo
+    pub fn add_pcm_i16(&mut self, samples: &[i16], channels: Channels) {
+        unsafe {
+            projectm_sys::projectm_pcm_add_int16(
+                self.handle.as_ptr(),
+                samples.as_ptr(),
+                (samples.len() / channels.count()) as u32, // samples per channel
+                channels.raw(),
+            );
+        }
+    }
+
+    /// Add unsigned 8-bit PCM audio data (128 is silence), interleaved if
+    /// stereo
+    pub fn add_pcm_u8(&mut self, samples: &[u8], channels: Channels) {
+        unsafe {
+            projectm_sys::projectm_pcm_add_uint8(
+                self.handle.as_ptr(),
+                samples.as_ptr(),
+                (samples.len() / channels.count()) as u32, // samples per channel
+                channels.raw(),
+            );
+        }
+    }
+
     /// Render a single frame
     ///
     /// # Note
     /// An OpenGL context must be current when calling this function.
-    /// The output is rendered to the currently bound framebuffer.
+    /// The output is rendered to the framebuffer set with
+    /// [`set_framebuffer`](Self::set_framebuffer), the default one unless set.
     pub fn render_frame(&mut self) {
         unsafe {
-            projectm_sys::projectm_opengl_render_frame(self.handle.as_ptr());
+            if self.framebuffer == 0 {
+                projectm_sys::projectm_opengl_render_frame(self.handle.as_ptr());
+            } else {
+                projectm_sys::projectm_opengl_render_frame_fbo(
+                    self.handle.as_ptr(),
+                    self.framebuffer,
+                );
+            }
+        }
+    }
+
+    /// Time the next frame is drawn at, in seconds since the first frame;
+    /// None goes back to the system clock. Animation follows this time,
+    /// so a clock that runs slower or faster changes the playback speed.
+    pub fn set_frame_time(&mut self, seconds: Option<f64>) {
+        // projectM takes a negative time to mean the system clock
+        let seconds = seconds.filter(|s| s.is_finite() && *s >= 0.0).unwrap_or(-1.0);
+        unsafe {
+            projectm_sys::projectm_set_frame_time(self.handle.as_ptr(), seconds);
         }
     }
 
+    /// Render into a framebuffer object instead of the default framebuffer,
+    /// e.g. when there is no window; 0 goes back to the default
+    pub fn set_framebuffer(&mut self, framebuffer: u32) {
+        debug!("Rendering to framebuffer {}", framebuffer);
+        self.framebuffer = framebuffer;
+    }
+
+    /// Framebuffer object frames are rendered to (0 = default framebuffer)
+    pub fn framebuffer(&self) -> u32 {
+        self.framebuffer
+    }
+
     /// Set the beat sensitivity (0.0 to 2.0, default 1.0)
     pub fn set_beat_sensitivity(&mut self, sensitivity: f32) {
         let sensitivity = sensitivity.clamp(0.0, 2.0);
@@ -198,6 +320,37 @@ impl ProjectM {
         }
     }
 
+    /// Check if hard cuts are enabled
+    pub fn is_hard_cut_enabled(&self) -> bool {
+        unsafe { projectm_sys::projectm_get_hard_cut_enabled(self.handle.as_ptr()) }
+    }
+
+    /// Set the minimum time between hard cuts in seconds
+    pub fn set_hard_cut_duration(&mut self, seconds: f64) {
+        debug!("Setting hard cut duration to {} seconds", seconds);
+        unsafe {
+            projectm_sys::projectm_set_hard_cut_duration(self.handle.as_ptr(), seconds);
+        }
+    }
+
+    /// Get the minimum time between hard cuts in seconds
+    pub fn hard_cut_duration(&self) -> f64 {
+        unsafe { projectm_sys::projectm_get_hard_cut_duration(self.handle.as_ptr()) }
+    }
+
+    /// Set how loud a beat must be to trigger a hard cut (higher = louder)
+    pub fn set_hard_cut_sensitivity(&mut self, sensitivity: f32) {
+        debug!("Setting hard cut sensitivity to {}", sensitivity);
+        unsafe {
+            projectm_sys::projectm_set_hard_cut_sensitivity(self.handle.as_ptr(), sensitivity);
+        }
+    }
+
+    /// Get the hard cut sensitivity
+    pub fn hard_cut_sensitivity(&self) -> f32 {
+        unsafe { projectm_sys::projectm_get_hard_cut_sensitivity(self.handle.as_ptr()) }
+    }
+
     /// Set the soft cut duration (transition time) in seconds
     pub fn set_soft_cut_duration(&mut self, seconds: f64) {
         debug!("Setting soft cut duration to {} seconds", seconds);
@@ -206,6 +359,48 @@ impl ProjectM {
         }
     }
 
+    /// Get the soft cut duration in seconds
+    pub fn soft_cut_duration(&self) -> f64 {
+        unsafe { projectm_sys::projectm_get_soft_cut_duration(self.handle.as_ptr()) }
+    }
+
+    /// Take the preset switch projectM asked for since the last call, if
+    /// any: true for a hard cut, false when the preset duration ran out
+    ///
+    /// Always None while a [`crate::Playlist`] drives the instance, as
+    /// projectM asks the playlist instead.
+    pub fn take_switch_request(&mut self) -> Option<bool> {
+        self.events.switch_request.take()
+    }
+
+    /// Call `callback` whenever projectM asks to switch preset, with true
+    /// for a hard cut; [`ProjectM::take_switch_request`] still sees the
+    /// request. Replaces the previous callback.
+    ///
+    /// Not called while a [`crate::Playlist`] drives the instance, as
+    /// projectM asks the playlist instead; see [`crate::Playlist::on_switch`].
+    pub fn on_switch_requested<F>(&mut self, callback: F)
+    where
+        F: FnMut(bool) + 'static,
+    {
+        *self.events.on_switch_requested.borrow_mut() = Some(Box::new(callback));
+    }
+
+    /// Call `callback` with the file and projectM's message whenever a
+    /// preset fails to load, including loads projectM starts itself;
+    /// [`ProjectM::load_preset`] still returns the error. Replaces the
+    /// previous callback.
+    ///
+    /// While a [`crate::Playlist`] drives the instance this only sees
+    /// presets passed to [`ProjectM::load_preset`]; the playlist reports its
+    /// own through [`crate::Playlist::on_switch_failed`].
+    pub fn on_switch_failed<F>(&mut self, callback: F)
+    where
+        F: FnMut(&str, &str) + 'static,
+    {
+        *self.events.on_switch_failed.borrow_mut() = Some(Box::new(callback));
+    }
+
     /// Lock the current preset (prevent automatic changes)
     pub fn set_preset_locked(&mut self, locked: bool) {
         debug!("Setting preset locked: {}", locked);
@@ -227,6 +422,15 @@ impl ProjectM {
         }
     }
 
+    /// Get the mesh size as (width, height)
+    pub fn mesh_size(&self) -> (usize, usize) {
+        let (mut width, mut height) = (0, 0);
+        unsafe {
+            projectm_sys::projectm_get_mesh_size(self.handle.as_ptr(), &mut width, &mut height);
+        }
+        (width, height)
+    }
+
     /// Set the target FPS
     pub fn set_fps(&mut self, fps: i32) {
         debug!("Setting target FPS to {}", fps);
@@ -247,6 +451,40 @@ impl ProjectM {
         }
     }
 
+    /// Check if aspect correction is enabled
+    pub fn aspect_correction(&self) -> bool {
+        unsafe { projectm_sys::projectm_get_aspect_correction(self.handle.as_ptr()) }
+    }
+
+    /// Start a waveform where the visualization is touched; `x` and `y` run
+    /// from 0 to 1 across the frame from the top left
+    pub fn touch(&mut self, x: f32, y: f32, pressure: i32, touch_type: TouchType) {
+        unsafe {
+            projectm_sys::projectm_touch(self.handle.as_ptr(), x, y, pressure, touch_type.raw());
+        }
+    }
+
+    /// Move the touched waveform nearest to `x`, `y` there
+    pub fn touch_drag(&mut self, x: f32, y: f32, pressure: i32) {
+        unsafe {
+            projectm_sys::projectm_touch_drag(self.handle.as_ptr(), x, y, pressure);
+        }
+    }
+
+    /// Remove the touched waveform nearest to `x`, `y`
+    pub fn touch_destroy(&mut self, x: f32, y: f32) {
+        unsafe {
+            projectm_sys::projectm_touch_destroy(self.handle.as_ptr(), x, y);
+        }
+    }
+
+    /// Remove all touched waveforms
+    pub fn touch_destroy_all(&mut self) {
+        unsafe {
+            projectm_sys::projectm_touch_destroy_all(self.handle.as_ptr());
+        }
+    }
+
     /// Get projectM version string
     pub fn version() -> String {
         unsafe {
@@ -305,6 +543,66 @@ impl ProjectM {
     }
 }
 
+/// Called with true for a hard cut
+type SwitchRequestedCallback = Box<dyn FnMut(bool)>;
+
+/// Called with the preset that failed to load and projectM's message
+type SwitchFailedCallback = Box<dyn FnMut(&str, &str)>;
+
+/// What projectM's callbacks report for an instance
+#[derive(Default)]
+struct Events {
+    /// projectM's message for a preset it couldn't load
+    load_failure: Cell<Option<String>>,
+    /// Preset switch projectM asked for (true for a hard cut)
+    switch_request: Cell<Option<bool>>,
+    on_switch_requested: RefCell<Option<SwitchRequestedCallback>>,
+    on_switch_failed: RefCell<Option<SwitchFailedCallback>>,
+    /// Playlist driving the instance, whose callbacks projectM calls instead
+    playlist: Cell<Option<NonNull<projectm_sys::projectm_playlist>>>,
+}
+
+/// Keeps the message of a failed preset load for [`ProjectM::load_preset`]
+/// and passes it on to the caller's callback
+unsafe extern "C" fn preset_switch_failed(
+    preset_filename: *const c_char,
+    message: *const c_char,
+    user_data: *mut c_void,
+) {
+    let message = if message.is_null() {
+        "Preset could not be parsed".to_string()
+    } else {
+        CStr::from_ptr(message).to_string_lossy().into_owned()
+    };
+    let events = &*(user_data as *const Events);
+    // Skipped if the callback itself made projectM load a preset
+    if let Ok(mut callback) = events.on_switch_failed.try_borrow_mut() {
+        if let Some(ref mut callback) = *callback {
+            let preset = if preset_filename.is_null() {
+                String::new()
+            } else {
+                CStr::from_ptr(preset_filename).to_string_lossy().into_owned()
+            };
+            callback(&preset, &message);
+        }
+    }
+    events.load_failure.set(Some(message));
+}
+
+/// Keeps a preset switch request for [`ProjectM::take_switch_request`] and
+/// passes it on to the caller's callback; a hard cut outranks a timed switch
+/// asked for in the same frame
+unsafe extern "C" fn preset_switch_requested(is_hard_cut: bool, user_data: *mut c_void) {
+    let events = &*(user_data as *const Events);
+    if let Ok(mut callback) = events.on_switch_requested.try_borrow_mut() {
+        if let Some(ref mut callback) = *callback {
+            callback(is_hard_cut);
+        }
+    }
+    let hard_cut = is_hard_cut || events.switch_request.get().unwrap_or(false);
+    events.switch_request.set(Some(hard_cut));
+}
+
 impl Drop for ProjectM {
     fn drop(&mut self) {
         debug!("Destroying ProjectM instance");
@@ -313,3 +611,66 @@ impl Drop for ProjectM {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::rc::Rc;
+
+    use super::*;
+
+    fn user_data(events: &Events) -> *mut c_void {
+        events as *const Events as *mut c_void
+    }
+
+    #[test]
+    fn test_switch_failed_keeps_message_and_calls_back() {
+        let events = Events::default();
+        let seen = Rc::new(RefCell::new(Vec::new()));
+        let sink = Rc::clone(&seen);
+        *events.on_switch_failed.borrow_mut() = Some(Box::new(move |preset, message| {
+            sink.borrow_mut().push((preset.to_string(), message.to_string()));
+        }));
+
+        let preset = CString::new("broken.milk").unwrap();
+        let message = CString::new("no per-frame code").unwrap();
+        unsafe {
+            preset_switch_failed(preset.as_ptr(), message.as_ptr(), user_data(&events));
+        }
+        assert_eq!(events.load_failure.take().as_deref(), Some("no per-frame code"));
+        assert_eq!(
+            *seen.borrow(),
+            [("broken.milk".to_string(), "no per-frame code".to_string())]
+        );
+
+        unsafe {
+            preset_switch_failed(std::ptr::null(), std::ptr::null(), user_data(&events));
+        }
+        assert_eq!(
+            events.load_failure.take().as_deref(),
+            Some("Preset could not be parsed")
+        );
+        assert_eq!(seen.borrow()[1].0, "");
+    }
+
+    #[test]
+    fn test_switch_requested_calls_back_and_keeps_hard_cut() {
+        let events = Events::default();
+        let seen = Rc::new(RefCell::new(Vec::new()));
+        let sink = Rc::clone(&seen);
+        *events.on_switch_requested.borrow_mut() =
+            Some(Box::new(move |hard_cut| sink.borrow_mut().push(hard_cut)));
+
+        unsafe {
+            preset_switch_requested(false, user_data(&events));
+            preset_switch_requested(true, user_data(&events));
+            preset_switch_requested(false, user_data(&events));
+        }
+        assert_eq!(*seen.borrow(), [false, true, false]);
+        assert_eq!(events.switch_request.take(), Some(true));
+
+        unsafe {
+            preset_switch_requested(false, user_data(&events));
+        }
+        assert_eq!(events.switch_request.take(), Some(false));
+    }
+}
@@ -4,10 +4,12 @@
 //! projectM visualization library.
 
 mod instance;
+mod playlist;
 mod preset;
 mod error;
 
 pub use instance::ProjectM;
+pub use playlist::Playlist;
 pub use preset::{Preset, scan_presets};
 pub use error::Error;
 
@@ -19,10 +21,64 @@ pub enum Channels {
 }
 
 impl Channels {
+    /// Channel configuration for an interleaved channel count
+    pub fn from_count(count: usize) -> Option<Self> {
+        match count {
+            1 => Some(Channels::Mono),
+            2 => Some(Channels::Stereo),
+            _ => None,
+        }
+    }
+
     pub fn count(&self) -> usize {
         match self {
             Channels::Mono => 1,
             Channels::Stereo => 2,
         }
     }
+
+    pub(crate) fn raw(self) -> projectm_sys::projectm_channels {
+        match self {
+            Channels::Mono => projectm_sys::projectm_channels_PROJECTM_MONO,
+            Channels::Stereo => projectm_sys::projectm_channels_PROJECTM_STEREO,
+        }
+    }
+}
+
+/// Waveform drawn where the visualization is touched
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum TouchType {
+    /// A different type for each touch
+    #[default]
+    Random,
+    Circle,
+    RadialBlob,
+    Blob2,
+    Blob3,
+    DerivativeLine,
+    Blob5,
+    Line,
+    DoubleLine,
+}
+
+impl TouchType {
+    pub(crate) fn raw(self) -> projectm_sys::projectm_touch_type {
+        match self {
+            TouchType::Random => projectm_sys::projectm_touch_type_PROJECTM_TOUCH_TYPE_RANDOM,
+            TouchType::Circle => projectm_sys::projectm_touch_type_PROJECTM_TOUCH_TYPE_CIRCLE,
+            TouchType::RadialBlob => {
+                projectm_sys::projectm_touch_type_PROJECTM_TOUCH_TYPE_RADIAL_BLOB
+            }
+            TouchType::Blob2 => projectm_sys::projectm_touch_type_PROJECTM_TOUCH_TYPE_BLOB2,
+            TouchType::Blob3 => projectm_sys::projectm_touch_type_PROJECTM_TOUCH_TYPE_BLOB3,
+            TouchType::DerivativeLine => {
+                projectm_sys::projectm_touch_type_PROJECTM_TOUCH_TYPE_DERIVATIVE_LINE
+            }
+            TouchType::Blob5 => projectm_sys::projectm_touch_type_PROJECTM_TOUCH_TYPE_BLOB5,
+            TouchType::Line => projectm_sys::projectm_touch_type_PROJECTM_TOUCH_TYPE_LINE,
+            TouchType::DoubleLine => {
+                projectm_sys::projectm_touch_type_PROJECTM_TOUCH_TYPE_DOUBLE_LINE
+            }
+        }
+    }
 }
@@ -0,0 +1,468 @@
+//! libprojectM's native playlist
+//!
+//! The playlist library keeps a list of presets, loads the next one when
+//! projectM asks for a switch (preset duration, hard cuts) and skips presets
+//! that fail to load. It takes over projectM's preset switch callbacks, so a
+//! [`Playlist`] owns the [`ProjectM`] instance it drives.
+
+use std::ffi::{c_char, c_uint, c_void, CStr, CString};
+use std::path::Path;
+use std::ptr::NonNull;
+
+use tracing::{debug, error};
+
+use crate::{Error, ProjectM};
+
+/// Called with the new position, and whether it was a hard cut
+type SwitchCallback = Box<dyn FnMut(u32, bool)>;
+
+/// Called with the preset that failed to load and projectM's message
+type FailureCallback = Box<dyn FnMut(&str, &str)>;
+
+/// A playlist driving a projectM instance
+///
+/// Presets loaded through the playlist don't update
+/// [`ProjectM::current_preset`]; use [`Playlist::current`] instead.
+pub struct Playlist {
+    handle: NonNull<projectm_sys::projectm_playlist>,
+    // Boxed twice so the C side gets a thin pointer that stays put
+    on_switch: Option<Box<SwitchCallback>>,
+    on_switch_failed: Option<Box<FailureCallback>>,
+    // Dropped after the playlist is destroyed
+    projectm: ProjectM,
+}
+
+impl Playlist {
+    /// Create an empty playlist connected to `projectm`
+    ///
+    /// libprojectM's playlist registers its own preset switch callbacks on
+    /// the instance in place of [`ProjectM`]'s, and they stay there: the
+    /// playlist answers switch requests and skips presets that fail to load
+    /// itself. [`ProjectM::on_switch_requested`] and
+    /// [`ProjectM::take_switch_request`] see nothing while it's connected;
+    /// use [`Playlist::on_switch`] and [`Playlist::on_switch_failed`].
+    pub fn new(mut projectm: ProjectM) -> Result<Self, Error> {
+        let handle = unsafe { projectm_sys::projectm_playlist_create(projectm.raw_handle()) };
+        let handle = NonNull::new(handle).ok_or_else(|| {
+            error!("Failed to create projectM playlist");
+            Error::InitFailed("projectm_playlist_create returned null".to_string())
+        })?;
+        debug!("ProjectM playlist created");
+
+        projectm.set_playlist(Some(handle));
+
+        Ok(Self {
+            handle,
+            on_switch: None,
+            on_switch_failed: None,
+            projectm,
+        })
+    }
+
+    /// The projectM instance the playlist drives
+    pub fn projectm(&self) -> &ProjectM {
+        &self.projectm
+    }
+
+    /// The projectM instance, e.g. for rendering; presets loaded on it
+    /// directly bypass the playlist, and one that fails to load is an error
+    /// rather than skipped
+    pub fn projectm_mut(&mut self) -> &mut ProjectM {
+        &mut self.projectm
+    }
+
+    /// Number of presets in the playlist
+    pub fn len(&self) -> u32 {
+        unsafe { projectm_sys::projectm_playlist_size(self.handle.as_ptr()) }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Preset file at `index`
+    pub fn item(&self, index: u32) -> Option<String> {
+        unsafe {
+            let ptr = projectm_sys::projectm_playlist_item(self.handle.as_ptr(), index);
+            if ptr.is_null() {
+                return None;
+            }
+            let item = CStr::from_ptr(ptr).to_string_lossy().into_owned();
+            projectm_sys::projectm_playlist_free_string(ptr);
+            Some(item)
+        }
+    }
+
+    /// All preset files, in playlist order
+    pub fn items(&self) -> Vec<String> {
+        let mut items = Vec::new();
+        unsafe {
+            let array = projectm_sys::projectm_playlist_items(self.handle.as_ptr(), 0, self.len());
+            if array.is_null() {
+                return items;
+            }
+            // Null-terminated
+            let mut entry = array;
+            while !(*entry).is_null() {
+                items.push(CStr::from_ptr(*entry).to_string_lossy().into_owned());
+                entry = entry.add(1);
+            }
+            projectm_sys::projectm_playlist_free_string_array(array);
+        }
+        items
+    }
+
+    /// Preset file at the current position
+    pub fn current(&self) -> Option<String> {
+        if self.is_empty() {
+            return None;
+        }
+        self.item(self.position())
+    }
+
+    /// Add every preset in a directory, optionally with its subdirectories;
+    /// returns how many were added
+    pub fn add_path<P: AsRef<Path>>(
+        &mut self,
+        path: P,
+        recursive: bool,
+        allow_duplicates: bool,
+    ) -> Result<u32, Error> {
+        let c_path = c_path(path.as_ref())?;
+        Ok(unsafe {
+            projectm_sys::projectm_playlist_add_path(
+                self.handle.as_ptr(),
+                c_path.as_ptr(),
+                recursive,
+                allow_duplicates,
+            )
+        })
+    }
+
+    /// Add a preset file at the end; false if it was already there and
+    /// duplicates aren't allowed
+    pub fn add_preset<P: AsRef<Path>>(
+        &mut self,
+        path: P,
+        allow_duplicates: bool,
+    ) -> Result<bool, Error> {
+        let c_path = c_path(path.as_ref())?;
+        Ok(unsafe {
+            projectm_sys::projectm_playlist_add_preset(
+                self.handle.as_ptr(),
+                c_path.as_ptr(),
+                allow_duplicates,
+            )
+        })
+    }
+
+    /// Insert a preset file before `index` (at the end if past it)
+    pub fn insert_preset<P: AsRef<Path>>(
+        &mut self,
+        path: P,
+        index: u32,
+        allow_duplicates: bool,
+    ) -> Result<bool, Error> {
+        let c_path = c_path(path.as_ref())?;
+        Ok(unsafe {
+            projectm_sys::projectm_playlist_insert_preset(
+                self.handle.as_ptr(),
+                c_path.as_ptr(),
+                index,
+                allow_duplicates,
+            )
+        })
+    }
+
+    /// Remove the preset at `index`; false if there was none
+    pub fn remove(&mut self, index: u32) -> bool {
+        unsafe { projectm_sys::projectm_playlist_remove_preset(self.handle.as_ptr(), index) }
+    }
+
+    /// Remove up to `count` presets from `index` on; returns how many went
+    pub fn remove_range(&mut self, index: u32, count: u32) -> u32 {
+        unsafe {
+            projectm_sys::projectm_playlist_remove_presets(self.handle.as_ptr(), index, count)
+        }
+    }
+
+    /// Remove every preset
+    pub fn clear(&mut self) {
+        unsafe {
+            projectm_sys::projectm_playlist_clear(self.handle.as_ptr());
+        }
+    }
+
+    /// Whether the next preset is picked at random
+    pub fn shuffle(&self) -> bool {
+        unsafe { projectm_sys::projectm_playlist_get_shuffle(self.handle.as_ptr()) }
+    }
+
+    pub fn set_shuffle(&mut self, shuffle: bool) {
+        unsafe {
+            projectm_sys::projectm_playlist_set_shuffle(self.handle.as_ptr(), shuffle);
+        }
+    }
+
+    /// Presets tried after one fails to load before reporting the failure
+    /// (0 reports it straight away)
+    pub fn retry_count(&self) -> u32 {
+        unsafe { projectm_sys::projectm_playlist_get_retry_count(self.handle.as_ptr()) }
+    }
+
+    pub fn set_retry_count(&mut self, retries: u32) {
+        unsafe {
+            projectm_sys::projectm_playlist_set_retry_count(self.handle.as_ptr(), retries);
+        }
+    }
+
+    /// Index of the current preset
+    pub fn position(&self) -> u32 {
+        unsafe { projectm_sys::projectm_playlist_get_position(self.handle.as_ptr()) }
+    }
+
+    /// Play the preset at `index`, blending unless `hard_cut`; returns the
+    /// position played, or None if the playlist is empty
+    pub fn set_position(&mut self, index: u32, hard_cut: bool) -> Option<u32> {
+        if self.is_empty() {
+            return None;
+        }
+        Some(unsafe {
+            projectm_sys::projectm_playlist_set_position(self.handle.as_ptr(), index, hard_cut)
+        })
+    }
+
+    /// Play the next preset, or a random one when shuffling
+    pub fn play_next(&mut self, hard_cut: bool) -> Option<u32> {
+        if self.is_empty() {
+            return None;
+        }
+        Some(unsafe { projectm_sys::projectm_playlist_play_next(self.handle.as_ptr(), hard_cut) })
+    }
+
+    /// Play the previous preset, or the one before it when shuffling
+    pub fn play_previous(&mut self, hard_cut: bool) -> Option<u32> {
+        if self.is_empty() {
+            return None;
+        }
+        Some(unsafe {
+            projectm_sys::projectm_playlist_play_previous(self.handle.as_ptr(), hard_cut)
+        })
+    }
+
+    /// Go back to the preset played before the current one
+    pub fn play_last(&mut self, hard_cut: bool) -> Option<u32> {
+        if self.is_empty() {
+            return None;
+        }
+        Some(unsafe { projectm_sys::projectm_playlist_play_last(self.handle.as_ptr(), hard_cut) })
+    }
+
+    /// Call `callback` whenever the playlist moves to a preset, with its
+    /// position and whether it was a hard cut; this includes switches
+    /// projectM asks for while rendering
+    pub fn on_switch<F>(&mut self, callback: F)
+    where
+        F: FnMut(u32, bool) + 'static,
+    {
+        let callback: Box<SwitchCallback> = Box::new(Box::new(callback));
+        unsafe {
+            projectm_sys::projectm_playlist_set_preset_switched_event_callback(
+                self.handle.as_ptr(),
+                Some(preset_switched),
+                &*callback as *const SwitchCallback as *mut c_void,
+            );
+        }
+        // The previous callback is dropped only once it's unregistered
+        self.on_switch = Some(callback);
+    }
+
+    /// Call `callback` with the file and projectM's message when a preset
+    /// fails to load and the playlist gives up on it, after
+    /// [`Playlist::retry_count`] more presets failed too
+    pub fn on_switch_failed<F>(&mut self, callback: F)
+    where
+        F: FnMut(&str, &str) + 'static,
+    {
+        let callback: Box<FailureCallback> = Box::new(Box::new(callback));
+        unsafe {
+            projectm_sys::projectm_playlist_set_preset_switch_failed_event_callback(
+                self.handle.as_ptr(),
+                Some(preset_switch_failed),
+                &*callback as *const FailureCallback as *mut c_void,
+            );
+        }
+        self.on_switch_failed = Some(callback);
+    }
+}
+
+fn c_path(path: &Path) -> Result<CString, Error> {
+    CString::new(path.to_string_lossy().as_bytes())
+        .map_err(|_| Error::InvalidParameter(format!("Invalid path: {}", path.display())))
+}
+
+unsafe extern "C" fn preset_switched(is_hard_cut: bool, index: c_uint, user_data: *mut c_void) {
+    let callback = &mut *(user_data as *mut SwitchCallback);
+    callback(index, is_hard_cut);
+}
+
+unsafe extern "C" fn preset_switch_failed(
+    preset_filename: *const c_char,
+    message: *const c_char,
+    user_data: *mut c_void,
+) {
+    let text = |ptr: *const c_char| {
+        if ptr.is_null() {
+            String::new()
+        } else {
+            CStr::from_ptr(ptr).to_string_lossy().into_owned()
+        }
+    };
+    let callback = &mut *(user_data as *mut FailureCallback);
+    callback(&text(preset_filename), &text(message));
+}
+
+impl Drop for Playlist {
+    fn drop(&mut self) {
+        debug!("Destroying ProjectM playlist");
+        unsafe {
+            projectm_sys::projectm_playlist_destroy(self.handle.as_ptr());
+        }
+        self.projectm.set_playlist(None);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::cell::{Cell, RefCell};
+    use std::rc::Rc;
+
+    use super::*;
+
+    #[test]
+    fn test_switched_callback_gets_position() {
+        let seen = Rc::new(Cell::new(None));
+        let sink = Rc::clone(&seen);
+        let mut callback: SwitchCallback = Box::new(move |index, hard_cut| {
+            sink.set(Some((index, hard_cut)));
+        });
+        unsafe {
+            preset_switched(true, 7, &mut callback as *mut SwitchCallback as *mut c_void);
+        }
+        assert_eq!(seen.get(), Some((7, true)));
+    }
+
+    #[test]
+    fn test_failed_callback_gets_preset_and_message() {
+        let seen = Rc::new(RefCell::new(Vec::new()));
+        let sink = Rc::clone(&seen);
+        let mut callback: FailureCallback = Box::new(move |preset, message| {
+            sink.borrow_mut()
+                .push((preset.to_string(), message.to_string()));
+        });
+        let user_data = &mut callback as *mut FailureCallback as *mut c_void;
+        let preset = CString::new("broken.milk").unwrap();
+        let message = CString::new("no per-frame code").unwrap();
+        unsafe {
+            preset_switch_failed(preset.as_ptr(), message.as_ptr(), user_data);
+            preset_switch_failed(std::ptr::null(), std::ptr::null(), user_data);
+        }
+        assert_eq!(
+            *seen.borrow(),
+            [
+                ("broken.milk".to_string(), "no per-frame code".to_string()),
+                (String::new(), String::new()),
+            ]
+        );
+    }
+
+    #[cfg(target_os = "linux")]
+    mod egl {
+        use glutin::api::egl::context::PossiblyCurrentContext;
+        use glutin::api::egl::device::Device;
+        use glutin::api::egl::display::Display;
+        use glutin::config::{Api, ConfigSurfaceTypes, ConfigTemplateBuilder};
+        use glutin::context::{ContextApi, ContextAttributesBuilder, Version};
+        use glutin::display::GlDisplay;
+
+        use super::super::*;
+        use super::*;
+
+        /// An OpenGL 3.3 context with no surface on the first EGL device,
+        /// made current
+        fn gl_context() -> (Display, PossiblyCurrentContext) {
+            let device = Device::query_devices().unwrap().next().unwrap();
+            let display = unsafe { Display::with_device(&device, None) }.unwrap();
+            let template = ConfigTemplateBuilder::new()
+                .with_api(Api::OPENGL)
+                .with_surface_type(ConfigSurfaceTypes::empty())
+                .build();
+            let config = unsafe { display.find_configs(template) }
+                .unwrap()
+                .next()
+                .unwrap();
+            let attrs = ContextAttributesBuilder::new()
+                .with_context_api(ContextApi::OpenGl(Some(Version::new(3, 3))))
+                .build(None);
+            let context = unsafe { display.create_context(&config, &attrs) }
+                .unwrap()
+                .make_current_surfaceless()
+                .unwrap();
+            (display, context)
+        }
+
+        #[test]
+        #[ignore = "needs libprojectM and an EGL device"]
+        fn test_broken_preset_under_playlist() {
+            let (_display, _context) = gl_context();
+
+            let failures = Rc::new(Cell::new(0));
+            let mut projectm = ProjectM::new(64, 64).unwrap();
+            let counter = Rc::clone(&failures);
+            projectm.on_switch_failed(move |_, _| counter.set(counter.get() + 1));
+            let mut playlist = Playlist::new(projectm).unwrap();
+
+            // projectM has no parser for .txt files
+            let dir = std::env::temp_dir();
+            let broken: Vec<_> = (0..2)
+                .map(|i| dir.join(format!("opendrop-broken-{}-{i}.txt", std::process::id())))
+                .collect();
+            for path in &broken {
+                std::fs::write(path, "not a preset").unwrap();
+            }
+
+            // Loaded directly, the instance reports it
+            assert!(playlist
+                .projectm_mut()
+                .load_preset(&broken[0], false)
+                .is_err());
+            assert_eq!(failures.get(), 1);
+
+            // Through the playlist, libprojectM retries the next preset and
+            // reports once it runs out
+            let gave_up = Rc::new(RefCell::new(Vec::new()));
+            let sink = Rc::clone(&gave_up);
+            playlist.on_switch_failed(move |preset, _| sink.borrow_mut().push(preset.to_string()));
+            playlist.set_retry_count(1);
+            for path in &broken {
+                assert!(playlist.add_preset(path, false).unwrap());
+            }
+            playlist.set_position(0, true);
+            assert_eq!(gave_up.borrow().len(), 1);
+            assert_eq!(failures.get(), 1);
+
+            // The playlist's callbacks survived the direct load above
+            assert!(playlist
+                .projectm_mut()
+                .load_preset(&broken[1], false)
+                .is_err());
+            assert_eq!(failures.get(), 2);
+            playlist.play_next(true);
+            assert_eq!(gave_up.borrow().len(), 2);
+
+            for path in &broken {
+                std::fs::remove_file(path).unwrap();
+            }
+        }
+    }
+}
@@ -144,6 +144,7 @@ fn main() {
         // because pkg-config returns "-l:projectM-4" which Cargo doesn't handle well
         if target_os != "windows" {
             println!("cargo:rustc-link-lib=dylib=projectM-4");
+            println!("cargo:rustc-link-lib=dylib=projectM-4-playlist");
         }
         lib.include_paths.clone()
     } else {
@@ -239,6 +240,7 @@ fn main() {
             } else {
                 // Linux/macOS with dynamic vcpkg libs
                 println!("cargo:rustc-link-lib=dylib={}", projectm_lib_name);
+                println!("cargo:rustc-link-lib=dylib={}", playlist_lib_name);
                 println!("cargo:rustc-link-lib=dylib=GL");
                 println!("cargo:rustc-link-lib=dylib=stdc++");
             }
@@ -251,6 +253,7 @@ fn main() {
             include_paths.push(PathBuf::from("/usr/include/projectM-4"));
             include_paths.push(PathBuf::from("/usr/local/include"));
             println!("cargo:rustc-link-lib=dylib=projectM-4");
+            println!("cargo:rustc-link-lib=dylib=projectM-4-playlist");
             println!("cargo:rustc-link-lib=dylib=GL");
         } else {
             // Windows without vcpkg - error out
@@ -5,3 +5,5 @@
 #include <projectM-4/parameters.h>
 #include <projectM-4/callbacks.h>
 #include <projectM-4/render_opengl.h>
+#include <projectM-4/touch.h>
+#include <projectM-4/playlist.h>
@@ -2,25 +2,87 @@
 //!
 //! Multi-deck visualization controller supporting up to 4 simultaneous decks.
 
-use std::collections::HashMap;
+use std::collections::{HashMap, VecDeque};
 use std::io::{BufRead, BufReader, Write};
-use std::process::{Child, Command, Stdio};
-use std::sync::{Arc, Mutex};
+use std::process::{Child, ChildStdin, ChildStdout, Command, ExitStatus, Stdio};
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::sync::{Arc, Mutex, MutexGuard};
 use std::thread::{self, JoinHandle};
 
 use serde::{Deserialize, Serialize};
-use tauri::State;
-use tracing::{info, warn};
-
-use opendrop_core::audio::{AudioConfig, AudioEngine, DeviceInfo};
+use tauri::{AppHandle, Emitter, Manager, State};
+use tracing::{error, info, warn};
+
+use opendrop_core::audio::energy::DEFAULT_ENERGY_WINDOW_SECS;
+use opendrop_core::audio::network::to_mono;
+use opendrop_core::audio::silence::{DEFAULT_IDLE_TIMEOUT_SECS, DEFAULT_SILENCE_THRESHOLD};
+use opendrop_core::audio::spectrum::{DEFAULT_BANDS, DEFAULT_FFT_SIZE};
+use opendrop_core::audio::tempo::{MAX_MANUAL_BPM, MIN_MANUAL_BPM};
+use opendrop_core::audio::waveform::{DEFAULT_WAVEFORM_POINTS_PER_SEC, DEFAULT_WAVEFORM_SECS};
+use opendrop_core::audio::{
+    soft_clip, AgcConfig, AudioConfig, AudioEngine, AudioLevels, AudioSource, AutoGain, BandEnergy, BeatDetector,
+    CaptureFormat, DeviceEvent, DeviceInfo, Energy, EnergyInfo, EnergyTracker, LatencyReport, LatencyStats, LevelMeter, MeterConfig, RecordingInfo,
+    SilenceDetector, SpectrumAnalyzer, TempoClock, TempoInfo, Waveform, WaveformHistory,
+};
 use opendrop_core::midi::{
-    list_midi_ports as core_list_midi_ports, create_apc_mini_preset, create_generic_dj_preset,
-    create_launchpad_preset, create_nanokontrol2_preset, MidiAction, MidiController, MidiMapping,
-    MidiMessageType, MidiPortInfo, MidiPreset,
+    list_midi_ports as core_list_midi_ports, create_apc_mini_preset, create_ddj400_preset,
+    create_generic_dj_preset, create_launchpad_preset, create_midi_fighter_twister_preset,
+    create_nanokontrol2_preset, create_pad_bank_mappings, create_traktor_kontrol_preset,
+    list_playlist_banks, playlist_banks_dir, CrossfaderSide, MacroStep, MidiAction, MidiController,
+    MidiMapping, MidiMessageType, MidiOut, MidiPortInfo, MidiPreset, MidiSession, OutputEvent,
+    PadGrid, RelativeEncoding, TransformCurve, DEFAULT_OSC_PORT,
 };
+use opendrop_core::video::color_adjust::{COLOR_RANGE, MAX_COLOR_FADE_SECS};
+use opendrop_core::video::time_scale::{time_scale_from_control, time_scale_to_control, validate_time_scale};
+use opendrop_core::video::loop_export::{loop_duration_secs, MAX_LOOP_SECS};
+use opendrop_core::video::strobe::MAX_BLACKOUT_FADE_SECS;
+use opendrop_core::video::thumbnail::{thumbnail_path, THUMBNAIL_HEIGHT, THUMBNAIL_WIDTH};
+use opendrop_core::video::{
+    BackgroundConfig, ColorAdjust, CompositeLayer, DisplayMode, Effect, EffectChain, ExclusiveMode, InputBackend,
+    GpuInfo, GpuPreference, InputBlend, LoopFormat, OutputBackend, OverlayConfig, PixelFormat, RecordingCodec,
+    InputPlacement, PresetCuts, RenderQuality, RenderStats, ScreenRegion, StrobeConfig, TextOverlayConfig,
+    ThumbnailFormat,
+};
+pub use opendrop_core::video::BlendMode;
 
 /// Maximum number of decks supported
 pub const MAX_DECKS: u8 = 4;
+/// Highest per-deck input gain trim (about +12 dB)
+const MAX_DECK_GAIN: f32 = 4.0;
+/// Beat sensitivity of a MIDI control at full travel (centred gives the default 1.0)
+const MIDI_MAX_BEAT_SENSITIVITY: f32 = 2.0;
+/// Glide between MIDI colour steps, so a fader dims smoothly
+const MIDI_COLOR_FADE_SECS: f64 = 0.1;
+/// How often the backend pump thread distributes audio to decks
+const AUDIO_PUMP_INTERVAL: std::time::Duration = std::time::Duration::from_millis(10);
+/// Consecutive pump failures before the frontend is notified
+const AUDIO_PUMP_ERROR_THRESHOLD: u32 = 5;
+/// How often the pump thread emits `audio_levels` (about the UI's frame rate)
+const AUDIO_LEVELS_INTERVAL: std::time::Duration = std::time::Duration::from_millis(33);
+/// Tempo assumed for beat-synced auto-cycle while none is detected
+const FALLBACK_CYCLE_BPM: f32 = 120.0;
+/// Highest frame rate accepted for deck recordings
+const MAX_RECORDING_FPS: u32 = 120;
+/// Highest FPS cap accepted for live video outputs and deck rendering
+const MAX_OUTPUT_FPS: u32 = 240;
+/// Longest loop export in beats
+const MAX_LOOP_BEATS: u32 = 64;
+/// Longest crossfade accepted for loop exports (seconds)
+const MAX_LOOP_CROSSFADE_SECS: f64 = 4.0;
+/// How long a stopping renderer gets to finish writing a recording
+const RECORDING_FINALIZE_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(5);
+/// How long a shared renderer gets to close a deck's window, or to exit
+/// once its last deck is gone
+const SHARED_RENDERER_CLOSE_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(2);
+/// Deck window size when none is given or remembered
+const DEFAULT_DECK_SIZE: (u32, u32) = (1280, 720);
+/// Wait before the first automatic restart of a crashed renderer; doubled
+/// for each crash in a row
+const RESTART_BASE_DELAY: std::time::Duration = std::time::Duration::from_secs(1);
+/// Longest wait between automatic restarts
+const RESTART_MAX_DELAY: std::time::Duration = std::time::Duration::from_secs(30);
+/// How long a restarted renderer runs before its crashes stop counting as in a row
+const RESTART_STABLE_AFTER: std::time::Duration = std::time::Duration::from_secs(60);
 
 /// Deck identifier (0-3)
 pub type DeckId = u8;
@@ -42,14 +104,231 @@ pub enum RendererHealth {
 
 /// Renderer process handle
 pub struct RendererProcess {
-    child: Child,
+    link: RendererLink,
     running: bool,
     health: Arc<Mutex<RendererHealth>>,
     started_at: std::time::Instant,
     crash_count: u32,
+    /// Recording in progress, cleared when the renderer reports it ended
+    recording: Arc<Mutex<Option<ActiveRecording>>>,
+    /// PNG export in progress, cleared like `recording`
+    frame_export: Arc<Mutex<Option<ActiveFrameExport>>>,
+    /// Last loop export, kept after it ends so its outcome can be shown
+    loop_export: Arc<Mutex<Option<ActiveLoopExport>>>,
+    /// NDI output enabled, so tempo changes are forwarded as metadata
+    ndi_enabled: bool,
+    /// Tempo last sent as NDI metadata
+    ndi_bpm: Option<f32>,
+    /// Where the compositor takes deck frames, once it reports ready
+    compositor_endpoint: Arc<Mutex<Option<CompositorEndpoint>>>,
+    /// Compositor endpoint last sent to a deck (None while not streaming)
+    compositor_feed: Option<CompositorEndpoint>,
+    /// Layers last sent to the compositor
+    compositor_layers: Vec<CompositeLayer>,
+    /// Latest performance report
+    stats: Arc<Mutex<Option<RenderStats>>>,
+    /// Window position and size last reported
+    geometry: Arc<Mutex<Option<WindowGeometry>>>,
+    /// Preset keys pressed in the window, until the audio pump handles them
+    preset_requests: Arc<Mutex<Vec<PresetRequest>>>,
+    /// Presets the renderer couldn't load, until the audio pump records them
+    preset_failures: Arc<Mutex<Vec<PresetFailure>>>,
+    /// GPU the renderer's GL context runs on, once it reports ready
+    gpu: Arc<Mutex<Option<GpuInfo>>>,
     stdout_reader: Option<JoinHandle<()>>,
 }
 
+/// Playlist move asked for from a deck window's keyboard
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PresetRequest {
+    Next,
+    Previous,
+    Random,
+}
+
+/// Payload of the `deck_preset_request` event
+#[derive(Serialize, Clone)]
+pub struct DeckPresetRequestEvent {
+    pub deck_id: u8,
+    pub request: PresetRequest,
+    /// Preset loaded, None when the playlist had nothing to move to
+    pub preset: Option<String>,
+}
+
+/// A preset a renderer couldn't load
+#[derive(Debug, Clone)]
+struct PresetFailure {
+    path: String,
+    error: String,
+    /// Preset still showing instead
+    current: Option<String>,
+}
+
+/// Payload of the `deck_preset_failed` event
+#[derive(Serialize, Clone)]
+pub struct DeckPresetFailedEvent {
+    pub deck_id: u8,
+    pub path: String,
+    /// projectM's parse error
+    pub error: String,
+}
+
+/// Where decks send their frames to the compositor
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct CompositorEndpoint {
+    /// TCP port taking pixels
+    port: u16,
+    /// Unix socket taking shared textures, if the compositor can import them
+    gpu_socket: Option<String>,
+}
+
+/// Position and size of a deck window in logical pixels
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct WindowGeometry {
+    /// Position, unknown on Wayland
+    pub x: Option<i32>,
+    pub y: Option<i32>,
+    pub width: u32,
+    pub height: u32,
+}
+
+impl WindowGeometry {
+    /// `current` with the given fields replaced; a deck that never had a
+    /// window starts from the default size
+    fn merged(
+        current: Option<Self>,
+        x: Option<i32>,
+        y: Option<i32>,
+        width: Option<u32>,
+        height: Option<u32>,
+    ) -> Self {
+        let current = current.unwrap_or(Self {
+            x: None,
+            y: None,
+            width: DEFAULT_DECK_SIZE.0,
+            height: DEFAULT_DECK_SIZE.1,
+        });
+        Self {
+            x: x.or(current.x),
+            y: y.or(current.y),
+            width: width.unwrap_or(current.width),
+            height: height.unwrap_or(current.height),
+        }
+    }
+}
+
+/// Video recording running in a renderer
+struct ActiveRecording {
+    path: String,
+    fps: u32,
+    bitrate_kbps: u32,
+    codec: RecordingCodec,
+    started_at: std::time::Instant,
+}
+
+/// Recording status reported in `DeckInfo`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DeckRecordingInfo {
+    pub path: String,
+    pub fps: u32,
+    pub bitrate_kbps: u32,
+    pub codec: RecordingCodec,
+    pub elapsed_secs: u64,
+}
+
+impl From<&ActiveRecording> for DeckRecordingInfo {
+    fn from(r: &ActiveRecording) -> Self {
+        Self {
+            path: r.path.clone(),
+            fps: r.fps,
+            bitrate_kbps: r.bitrate_kbps,
+            codec: r.codec,
+            elapsed_secs: r.started_at.elapsed().as_secs(),
+        }
+    }
+}
+
+/// PNG image-sequence export running in a renderer
+struct ActiveFrameExport {
+    directory: String,
+    fps: u32,
+    started_at: std::time::Instant,
+}
+
+/// Frame export status reported in `DeckInfo`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DeckFrameExportInfo {
+    pub directory: String,
+    pub fps: u32,
+    pub elapsed_secs: u64,
+}
+
+impl From<&ActiveFrameExport> for DeckFrameExportInfo {
+    fn from(e: &ActiveFrameExport) -> Self {
+        Self {
+            directory: e.directory.clone(),
+            fps: e.fps,
+            elapsed_secs: e.started_at.elapsed().as_secs(),
+        }
+    }
+}
+
+/// Loop export started on a renderer
+struct ActiveLoopExport {
+    path: String,
+    format: LoopFormat,
+    beats: u32,
+    duration_secs: f64,
+    /// Loop plus crossfade, after which the renderer starts encoding
+    record_secs: f64,
+    started_at: std::time::Instant,
+    /// Set when the renderer reports the export ended
+    result: Option<Result<(), String>>,
+}
+
+/// Where a loop export is
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum LoopExportStatus {
+    Recording,
+    Encoding,
+    Done,
+    Failed,
+}
+
+/// Loop export status reported in `DeckInfo`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DeckLoopExportInfo {
+    pub path: String,
+    pub format: LoopFormat,
+    pub beats: u32,
+    pub duration_secs: f64,
+    pub status: LoopExportStatus,
+    pub error: Option<String>,
+}
+
+impl From<&ActiveLoopExport> for DeckLoopExportInfo {
+    fn from(e: &ActiveLoopExport) -> Self {
+        let status = match e.result {
+            Some(Ok(())) => LoopExportStatus::Done,
+            Some(Err(_)) => LoopExportStatus::Failed,
+            None if e.started_at.elapsed().as_secs_f64() < e.record_secs => {
+                LoopExportStatus::Recording
+            }
+            None => LoopExportStatus::Encoding,
+        };
+        Self {
+            path: e.path.clone(),
+            format: e.format,
+            beats: e.beats,
+            duration_secs: e.duration_secs,
+            status,
+            error: e.result.as_ref().and_then(|r| r.clone().err()),
+        }
+    }
+}
+
 /// Events received from renderer process
 #[derive(Debug, Deserialize)]
 #[serde(tag = "type")]
@@ -62,18 +341,122 @@ enum RendererEvent {
     Error { message: String },
     #[serde(rename = "preset_loaded")]
     PresetLoaded { path: String },
+    #[serde(rename = "preset_failed")]
+    PresetFailed {
+        path: String,
+        error: String,
+        current: Option<String>,
+    },
+    #[serde(rename = "recording_stopped")]
+    RecordingStopped {
+        path: String,
+        frames: u64,
+        dropped: u64,
+        duration_secs: f64,
+        error: Option<String>,
+    },
+    #[serde(rename = "frame_export_stopped")]
+    FrameExportStopped {
+        directory: String,
+        frames: u64,
+        dropped: u64,
+        error: Option<String>,
+    },
+    #[serde(rename = "loop_export_finished")]
+    LoopExportFinished {
+        path: String,
+        duration_secs: f64,
+        error: Option<String>,
+    },
+    #[serde(rename = "compositor_ready")]
+    CompositorReady {
+        port: u16,
+        #[serde(default)]
+        gpu_socket: Option<String>,
+    },
+    #[serde(rename = "stats")]
+    Stats(RenderStats),
+    #[serde(rename = "window_geometry")]
+    WindowGeometry(WindowGeometry),
+    #[serde(rename = "gpu_info")]
+    GpuInfo(GpuInfo),
+    #[serde(rename = "request_next_preset")]
+    RequestNextPreset,
+    #[serde(rename = "request_previous_preset")]
+    RequestPreviousPreset,
+    #[serde(rename = "request_random_preset")]
+    RequestRandomPreset,
+    #[serde(rename = "thumbnail_done")]
+    ThumbnailDone { preset_path: String, output_path: String },
+    #[serde(rename = "thumbnail_failed")]
+    ThumbnailFailed { preset_path: String, error: String },
+}
+
+/// Where a renderer's commands go and its events come from
+enum RendererLink {
+    /// A renderer process of its own
+    Process(Child),
+    /// A deck window in a shared renderer, see [`SharedRenderer`]
+    Hosted {
+        host: Arc<SharedRenderer>,
+        deck_id: u8,
+        /// Tells this renderer's deck slot from a later one for the same deck
+        token: u64,
+    },
+}
+
+impl RendererLink {
+    fn try_wait(&mut self) -> std::io::Result<Option<ExitStatus>> {
+        match self {
+            RendererLink::Process(child) => child.try_wait(),
+            RendererLink::Hosted { host, .. } => host.try_wait(),
+        }
+    }
 }
 
+/// Event lines from a renderer
+type EventLines = Box<dyn Iterator<Item = std::io::Result<String>> + Send>;
+
 impl RendererProcess {
     fn new(mut child: Child) -> Self {
+        let events = child.stdout.take().map(|stdout| -> EventLines {
+            Box::new(BufReader::new(stdout).lines())
+        });
+        Self::with_link(RendererLink::Process(child), events)
+    }
+
+    /// A deck in a shared renderer, reading the event lines routed to it
+    fn hosted(host: Arc<SharedRenderer>, deck_id: u8, token: u64, events: Receiver<String>) -> Self {
+        let events: EventLines = Box::new(events.into_iter().map(Ok));
+        Self::with_link(RendererLink::Hosted { host, deck_id, token }, Some(events))
+    }
+
+    fn with_link(link: RendererLink, events: Option<EventLines>) -> Self {
         let health = Arc::new(Mutex::new(RendererHealth::Starting));
         let health_clone = Arc::clone(&health);
+        let recording: Arc<Mutex<Option<ActiveRecording>>> = Arc::new(Mutex::new(None));
+        let recording_clone = Arc::clone(&recording);
+        let frame_export: Arc<Mutex<Option<ActiveFrameExport>>> = Arc::new(Mutex::new(None));
+        let frame_export_clone = Arc::clone(&frame_export);
+        let loop_export: Arc<Mutex<Option<ActiveLoopExport>>> = Arc::new(Mutex::new(None));
+        let loop_export_clone = Arc::clone(&loop_export);
+        let compositor_endpoint: Arc<Mutex<Option<CompositorEndpoint>>> = Arc::new(Mutex::new(None));
+        let compositor_endpoint_clone = Arc::clone(&compositor_endpoint);
+        let stats: Arc<Mutex<Option<RenderStats>>> = Arc::new(Mutex::new(None));
+        let stats_clone = Arc::clone(&stats);
+        let geometry: Arc<Mutex<Option<WindowGeometry>>> = Arc::new(Mutex::new(None));
+        let geometry_clone = Arc::clone(&geometry);
+        let preset_requests: Arc<Mutex<Vec<PresetRequest>>> = Arc::new(Mutex::new(Vec::new()));
+        let preset_requests_clone = Arc::clone(&preset_requests);
+        let preset_failures: Arc<Mutex<Vec<PresetFailure>>> = Arc::new(Mutex::new(Vec::new()));
+        let preset_failures_clone = Arc::clone(&preset_failures);
+        let gpu: Arc<Mutex<Option<GpuInfo>>> = Arc::new(Mutex::new(None));
+        let gpu_clone = Arc::clone(&gpu);
 
         // Spawn thread to read stdout events from renderer
-        let stdout_reader = child.stdout.take().map(|stdout| {
+        let stdout_reader = events.map(|lines| {
             thread::spawn(move || {
-                let reader = BufReader::new(stdout);
-                for line in reader.lines() {
+                for line in lines {
                     match line {
                         Ok(line) => {
                             if let Ok(event) = serde_json::from_str::<RendererEvent>(&line) {
@@ -97,6 +480,92 @@ impl RendererProcess {
                                     RendererEvent::PresetLoaded { path } => {
                                         info!("Renderer loaded preset: {}", path);
                                     }
+                                    RendererEvent::PresetFailed { path, error, current } => {
+                                        warn!("Renderer couldn't load preset {}: {}", path, error);
+                                        if let Ok(mut f) = preset_failures_clone.lock() {
+                                            f.push(PresetFailure { path, error, current });
+                                        }
+                                    }
+                                    RendererEvent::RecordingStopped { path, frames, dropped, duration_secs, error } => {
+                                        if let Ok(mut r) = recording_clone.lock() {
+                                            // A newer recording may already have started
+                                            if r.as_ref().is_some_and(|active| active.path == path) {
+                                                *r = None;
+                                            }
+                                        }
+                                        match error {
+                                            Some(e) => warn!("Recording {} failed: {}", path, e),
+                                            None => info!(
+                                                "Recorded {} ({:.1}s, {} frames, {} dropped)",
+                                                path, duration_secs, frames, dropped
+                                            ),
+                                        }
+                                    }
+                                    RendererEvent::FrameExportStopped { directory, frames, dropped, error } => {
+                                        if let Ok(mut e) = frame_export_clone.lock() {
+                                            if e.as_ref().is_some_and(|active| active.directory == directory) {
+                                                *e = None;
+                                            }
+                                        }
+                                        match error {
+                                            Some(e) => warn!("Frame export to {} failed: {}", directory, e),
+                                            None => info!(
+                                                "Exported {} frames to {} ({} dropped)",
+                                                frames, directory, dropped
+                                            ),
+                                        }
+                                    }
+                                    RendererEvent::LoopExportFinished { path, duration_secs, error } => {
+                                        match error {
+                                            Some(ref e) => warn!("Loop export to {} failed: {}", path, e),
+                                            None => info!("Exported {:.1}s loop to {}", duration_secs, path),
+                                        }
+                                        if let Ok(mut e) = loop_export_clone.lock() {
+                                            if let Some(active) = e.as_mut().filter(|active| active.path == path) {
+                                                active.result = Some(error.map_or(Ok(()), Err));
+                                            }
+                                        }
+                                    }
+                                    RendererEvent::CompositorReady { port, gpu_socket } => {
+                                        info!(
+                                            "Compositor taking deck frames on port {}{}",
+                                            port,
+                                            if gpu_socket.is_some() { " and shared textures" } else { "" }
+                                        );
+                                        if let Ok(mut e) = compositor_endpoint_clone.lock() {
+                                            *e = Some(CompositorEndpoint { port, gpu_socket });
+                                        }
+                                    }
+                                    RendererEvent::Stats(report) => {
+                                        if let Ok(mut s) = stats_clone.lock() {
+                                            *s = Some(report);
+                                        }
+                                    }
+                                    RendererEvent::WindowGeometry(report) => {
+                                        if let Ok(mut g) = geometry_clone.lock() {
+                                            *g = Some(report);
+                                        }
+                                    }
+                                    RendererEvent::GpuInfo(info) => {
+                                        info!("Renderer is drawing on {} ({})", info.renderer, info.vendor);
+                                        if let Ok(mut g) = gpu_clone.lock() {
+                                            *g = Some(info);
+                                        }
+                                    }
+                                    RendererEvent::RequestNextPreset
+                                    | RendererEvent::RequestPreviousPreset
+                                    | RendererEvent::RequestRandomPreset => {
+                                        let request = match event {
+                                            RendererEvent::RequestPreviousPreset => PresetRequest::Previous,
+                                            RendererEvent::RequestRandomPreset => PresetRequest::Random,
+                                            _ => PresetRequest::Next,
+                                        };
+                                        if let Ok(mut r) = preset_requests_clone.lock() {
+                                            r.push(request);
+                                        }
+                                    }
+                                    // Only thumbnailers send these, see ThumbnailRenderer
+                                    RendererEvent::ThumbnailDone { .. } | RendererEvent::ThumbnailFailed { .. } => {}
                                 }
                             }
                         }
@@ -107,17 +576,34 @@ impl RendererProcess {
         });
 
         Self {
-            child,
+            link,
             running: true,
             health,
             started_at: std::time::Instant::now(),
             crash_count: 0,
+            recording,
+            frame_export,
+            loop_export,
+            ndi_enabled: false,
+            ndi_bpm: None,
+            compositor_endpoint,
+            compositor_feed: None,
+            compositor_layers: Vec::new(),
+            stats,
+            geometry,
+            preset_requests,
+            preset_failures,
+            gpu,
             stdout_reader,
         }
     }
 
     fn send_command(&mut self, cmd: &RendererCommand) -> Result<(), String> {
-        if let Some(ref mut stdin) = self.child.stdin {
+        let child = match self.link {
+            RendererLink::Process(ref mut child) => child,
+            RendererLink::Hosted { ref host, deck_id, .. } => return host.send(Some(deck_id), cmd),
+        };
+        if let Some(ref mut stdin) = child.stdin {
             let json = serde_json::to_string(cmd).map_err(|e| e.to_string())?;
             writeln!(stdin, "{}", json).map_err(|e| e.to_string())?;
             stdin.flush().map_err(|e| e.to_string())?;
@@ -131,7 +617,13 @@ impl RendererProcess {
         if !self.running {
             return false;
         }
-        match self.child.try_wait() {
+        // A deck in a shared renderer is done once its window closes, while
+        // the process carries on
+        if matches!(self.link, RendererLink::Hosted { .. }) && self.get_health() == RendererHealth::Stopped {
+            self.running = false;
+            return false;
+        }
+        match self.link.try_wait() {
             Ok(Some(exit_status)) => {
                 self.running = false;
                 // Check if it was a crash or normal exit
@@ -174,21 +666,58 @@ impl RendererProcess {
     fn stop(&mut self) {
         if self.running {
             let _ = self.send_command(&RendererCommand::Stop);
-            // Give it a moment to close gracefully
-            std::thread::sleep(std::time::Duration::from_millis(100));
-            let _ = self.child.kill();
-            let _ = self.child.wait();
+            // Give it a moment to close gracefully, longer while a recording
+            // is being finalized
+            let loop_busy = self.loop_export().is_some_and(|e| {
+                matches!(e.status, LoopExportStatus::Recording | LoopExportStatus::Encoding)
+            });
+            let busy = self.recording().is_some() || self.frame_export().is_some() || loop_busy;
+            match self.link {
+                RendererLink::Process(ref mut child) => {
+                    if busy {
+                        let deadline = std::time::Instant::now() + RECORDING_FINALIZE_TIMEOUT;
+                        while std::time::Instant::now() < deadline && matches!(child.try_wait(), Ok(None)) {
+                            std::thread::sleep(std::time::Duration::from_millis(50));
+                        }
+                    } else {
+                        std::thread::sleep(std::time::Duration::from_millis(100));
+                    }
+                    let _ = child.kill();
+                    let _ = child.wait();
+                }
+                RendererLink::Hosted { ref host, .. } => {
+                    // Only the window closes; wait for it, so the deck can
+                    // open again in the same process
+                    let timeout = if busy { RECORDING_FINALIZE_TIMEOUT } else { SHARED_RENDERER_CLOSE_TIMEOUT };
+                    let deadline = std::time::Instant::now() + timeout;
+                    while std::time::Instant::now() < deadline
+                        && self.get_health() != RendererHealth::Stopped
+                        && host.is_running()
+                    {
+                        std::thread::sleep(std::time::Duration::from_millis(50));
+                    }
+                }
+            }
             self.running = false;
             if let Ok(mut h) = self.health.lock() {
                 *h = RendererHealth::Stopped;
             }
         }
+        self.release();
         // Wait for stdout reader thread to finish
         if let Some(handle) = self.stdout_reader.take() {
             let _ = handle.join();
         }
     }
 
+    /// Give up a shared renderer's slot for the deck, so the deck can open
+    /// in it again; the process exits with its last deck
+    fn release(&mut self) {
+        if let RendererLink::Hosted { ref host, token, .. } = self.link {
+            host.release(token);
+        }
+    }
+
     fn get_health(&self) -> RendererHealth {
         self.health.lock().map(|h| *h).unwrap_or(RendererHealth::Crashed)
     }
@@ -196,6 +725,128 @@ impl RendererProcess {
     fn uptime_secs(&self) -> u64 {
         self.started_at.elapsed().as_secs()
     }
+
+    fn recording(&self) -> Option<DeckRecordingInfo> {
+        self.recording
+            .lock()
+            .ok()
+            .and_then(|r| r.as_ref().map(DeckRecordingInfo::from))
+    }
+
+    fn frame_export(&self) -> Option<DeckFrameExportInfo> {
+        self.frame_export
+            .lock()
+            .ok()
+            .and_then(|e| e.as_ref().map(DeckFrameExportInfo::from))
+    }
+
+    fn loop_export(&self) -> Option<DeckLoopExportInfo> {
+        self.loop_export
+            .lock()
+            .ok()
+            .and_then(|e| e.as_ref().map(DeckLoopExportInfo::from))
+    }
+
+    fn stats(&self) -> Option<RenderStats> {
+        self.stats.lock().ok().and_then(|s| s.clone())
+    }
+
+    fn geometry(&self) -> Option<WindowGeometry> {
+        self.geometry.lock().ok().and_then(|g| *g)
+    }
+
+    fn gpu(&self) -> Option<GpuInfo> {
+        self.gpu.lock().ok().and_then(|g| g.clone())
+    }
+
+    /// Preset keys pressed since the last call
+    fn take_preset_requests(&self) -> Vec<PresetRequest> {
+        self.preset_requests
+            .lock()
+            .map(|mut r| std::mem::take(&mut *r))
+            .unwrap_or_default()
+    }
+
+    /// Presets that failed to load since the last call
+    fn take_preset_failures(&self) -> Vec<PresetFailure> {
+        self.preset_failures
+            .lock()
+            .map(|mut f| std::mem::take(&mut *f))
+            .unwrap_or_default()
+    }
+
+    /// Where decks send frames, when this is the compositor
+    fn compositor_endpoint(&self) -> Option<CompositorEndpoint> {
+        self.compositor_endpoint.lock().ok().and_then(|e| e.clone())
+    }
+
+    fn start_loop_export(
+        &mut self,
+        path: String,
+        format: LoopFormat,
+        beats: u32,
+        duration_secs: f64,
+        crossfade_secs: f64,
+    ) -> Result<(), String> {
+        self.send_command(&RendererCommand::StartLoopExport {
+            path: path.clone(),
+            format,
+            duration_secs,
+            crossfade_secs,
+        })?;
+        if let Ok(mut e) = self.loop_export.lock() {
+            *e = Some(ActiveLoopExport {
+                path,
+                format,
+                beats,
+                duration_secs,
+                record_secs: duration_secs + crossfade_secs.min(duration_secs),
+                started_at: std::time::Instant::now(),
+                result: None,
+            });
+        }
+        Ok(())
+    }
+
+    fn start_frame_export(&mut self, directory: String, fps: u32) -> Result<(), String> {
+        self.send_command(&RendererCommand::StartFrameExport {
+            directory: directory.clone(),
+            fps,
+        })?;
+        if let Ok(mut e) = self.frame_export.lock() {
+            *e = Some(ActiveFrameExport {
+                directory,
+                fps,
+                started_at: std::time::Instant::now(),
+            });
+        }
+        Ok(())
+    }
+
+    fn start_recording(
+        &mut self,
+        path: String,
+        fps: u32,
+        bitrate_kbps: u32,
+        codec: RecordingCodec,
+    ) -> Result<(), String> {
+        self.send_command(&RendererCommand::StartRecording {
+            path: path.clone(),
+            fps,
+            bitrate_kbps,
+            codec,
+        })?;
+        if let Ok(mut r) = self.recording.lock() {
+            *r = Some(ActiveRecording {
+                path,
+                fps,
+                bitrate_kbps,
+                codec,
+                started_at: std::time::Instant::now(),
+            });
+        }
+        Ok(())
+    }
 }
 
 impl Drop for RendererProcess {
@@ -204,36 +855,331 @@ impl Drop for RendererProcess {
     }
 }
 
-/// Commands sent to the renderer process
+/// A renderer process hosting several decks' windows on one event loop,
+/// started with `multi_deck` set
+///
+/// Command lines name the deck they're for, and each event line the
+/// renderer prints is handed to the deck it names.
+pub struct SharedRenderer {
+    child: Mutex<Child>,
+    stdin: Mutex<ChildStdin>,
+    decks: Arc<Mutex<HostedDecks>>,
+    stdout_reader: Mutex<Option<JoinHandle<()>>>,
+}
+
+/// Decks open in a shared renderer
+#[derive(Default)]
+struct HostedDecks {
+    /// Where each deck's event lines go, with its renderer's token
+    events: HashMap<u8, (u64, Sender<String>)>,
+    next_token: u64,
+    /// Exiting after its last deck closed; decks open in a new process
+    closing: bool,
+}
+
+/// A command line for one deck of a shared renderer, or all of them
 #[derive(Serialize)]
+struct DeckCommandLine<'a> {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    deck_id: Option<u8>,
+    #[serde(flatten)]
+    command: &'a RendererCommand,
+}
+
+impl SharedRenderer {
+    /// Start a shared renderer with `config`'s deck as its first window
+    fn spawn(config: &RendererConfig, gpu: GpuPreference) -> Result<(Arc<Self>, RendererProcess), String> {
+        let renderer_path = find_renderer_executable()?;
+        let mut config_json = serde_json::to_value(config).map_err(|e| e.to_string())?;
+        config_json["multi_deck"] = true.into();
+
+        info!("Starting shared renderer with deck {}", config.deck_id);
+        let mut child = renderer_command(&renderer_path, gpu)
+            .arg(config_json.to_string())
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::inherit())
+            .spawn()
+            .map_err(|e| format!("Failed to start shared renderer: {}", e))?;
+        let stdin = child.stdin.take().ok_or("Shared renderer has no stdin")?;
+        let stdout = child.stdout.take().ok_or("Shared renderer has no stdout")?;
+
+        let host = Arc::new(Self {
+            child: Mutex::new(child),
+            stdin: Mutex::new(stdin),
+            decks: Arc::default(),
+            stdout_reader: Mutex::new(None),
+        });
+        // Registered before any of its events can arrive
+        let renderer = host.register(config.deck_id)?;
+        let decks = Arc::clone(&host.decks);
+        let reader = thread::spawn(move || route_deck_events(stdout, &decks));
+        if let Ok(mut r) = host.stdout_reader.lock() {
+            *r = Some(reader);
+        }
+        Ok((host, renderer))
+    }
+
+    /// Open `config`'s deck in another window
+    fn open_deck(self: &Arc<Self>, config: &RendererConfig) -> Result<RendererProcess, String> {
+        let renderer = self.register(config.deck_id)?;
+        info!("Opening deck {} in the shared renderer", config.deck_id);
+        self.send(None, &RendererCommand::OpenDeck { config: Box::new(config.clone()) })?;
+        Ok(renderer)
+    }
+
+    /// Whether decks can still open in this process
+    fn accepts_decks(&self) -> bool {
+        self.is_running() && self.decks.lock().is_ok_and(|d| !d.closing)
+    }
+
+    /// Give a deck a slot for its events and a renderer handle reading them
+    fn register(self: &Arc<Self>, deck_id: u8) -> Result<RendererProcess, String> {
+        let mut decks = self.decks.lock().map_err(|e| e.to_string())?;
+        if decks.closing {
+            return Err("Shared renderer is exiting".to_string());
+        }
+        if decks.events.contains_key(&deck_id) {
+            return Err(format!("Deck {} is already open in the shared renderer", deck_id));
+        }
+        let token = decks.next_token;
+        decks.next_token += 1;
+        let (tx, rx) = mpsc::channel();
+        decks.events.insert(deck_id, (token, tx));
+        Ok(RendererProcess::hosted(Arc::clone(self), deck_id, token, rx))
+    }
+
+    /// Send a command to deck `deck_id`, or to every deck without one
+    fn send(&self, deck_id: Option<u8>, cmd: &RendererCommand) -> Result<(), String> {
+        let json = serde_json::to_string(&DeckCommandLine { deck_id, command: cmd }).map_err(|e| e.to_string())?;
+        let mut stdin = self.stdin.lock().map_err(|e| e.to_string())?;
+        writeln!(stdin, "{}", json).map_err(|e| e.to_string())?;
+        stdin.flush().map_err(|e| e.to_string())
+    }
+
+    fn try_wait(&self) -> std::io::Result<Option<ExitStatus>> {
+        match self.child.lock() {
+            Ok(mut child) => child.try_wait(),
+            Err(e) => Err(std::io::Error::other(e.to_string())),
+        }
+    }
+
+    fn is_running(&self) -> bool {
+        matches!(self.try_wait(), Ok(None))
+    }
+
+    /// Free the slot of the deck renderer holding `token`; the last deck out
+    /// stops the process
+    fn release(&self, token: u64) {
+        let Ok(mut decks) = self.decks.lock() else {
+            return;
+        };
+        decks.events.retain(|_, (t, _)| *t != token);
+        if !decks.events.is_empty() || decks.closing {
+            return;
+        }
+        decks.closing = true;
+        drop(decks);
+
+        info!("Last deck closed, stopping the shared renderer");
+        let _ = self.send(None, &RendererCommand::Stop);
+        let deadline = std::time::Instant::now() + SHARED_RENDERER_CLOSE_TIMEOUT;
+        while std::time::Instant::now() < deadline && self.is_running() {
+            std::thread::sleep(std::time::Duration::from_millis(50));
+        }
+        if let Ok(mut child) = self.child.lock() {
+            let _ = child.kill();
+            let _ = child.wait();
+        }
+        if let Some(handle) = self.stdout_reader.lock().ok().and_then(|mut r| r.take()) {
+            let _ = handle.join();
+        }
+    }
+}
+
+/// Hand each event line of a shared renderer to the deck it names, or to
+/// every deck when it names none
+fn route_deck_events(stdout: ChildStdout, decks: &Mutex<HostedDecks>) {
+    #[derive(Deserialize)]
+    struct EventDeck {
+        deck_id: Option<u8>,
+    }
+
+    for line in BufReader::new(stdout).lines() {
+        let Ok(line) = line else {
+            break;
+        };
+        let Ok(EventDeck { deck_id }) = serde_json::from_str(&line) else {
+            continue;
+        };
+        let Ok(decks) = decks.lock() else {
+            break;
+        };
+        for (&id, (_, events)) in &decks.events {
+            if deck_id.is_none() || deck_id == Some(id) {
+                let _ = events.send(line.clone());
+            }
+        }
+    }
+    // The process is gone; the decks' readers end with their channels
+    if let Ok(mut decks) = decks.lock() {
+        decks.events.clear();
+    }
+}
+
+/// Commands sent to the renderer process
+#[derive(Clone, Serialize)]
 #[serde(tag = "type")]
 enum RendererCommand {
     #[serde(rename = "load_preset")]
     LoadPreset { path: String },
+    #[serde(rename = "set_transition_time")]
+    SetTransitionTime { seconds: f64 },
+    #[serde(rename = "set_preset_cuts")]
+    SetPresetCuts { cuts: PresetCuts },
     #[serde(rename = "audio")]
-    Audio { samples: Vec<f32> },
+    Audio { samples: Vec<f32>, channels: u16 },
     #[serde(rename = "toggle_fullscreen")]
     ToggleFullscreen,
+    #[serde(rename = "set_always_on_top")]
+    SetAlwaysOnTop { enabled: bool },
+    #[serde(rename = "set_click_through")]
+    SetClickThrough { enabled: bool },
+    #[serde(rename = "set_window_geometry")]
+    SetWindowGeometry {
+        x: Option<i32>,
+        y: Option<i32>,
+        width: Option<u32>,
+        height: Option<u32>,
+    },
     #[serde(rename = "set_beat_sensitivity")]
     SetBeatSensitivity { value: f32 },
     #[serde(rename = "set_video_output")]
     SetVideoOutput {
         enabled: bool,
         device_path: Option<String>,
+        pixel_format: Option<PixelFormat>,
     },
     #[serde(rename = "set_ndi_output")]
     SetNdiOutput {
         enabled: bool,
         name: Option<String>,
+        groups: Option<String>,
+        failover: Option<String>,
+        clock_video: Option<bool>,
+        pixel_format: Option<PixelFormat>,
+    },
+    #[serde(rename = "set_ndi_metadata")]
+    SetNdiMetadata { bpm: Option<f32> },
+    #[serde(rename = "set_syphon_output")]
+    SetSyphonOutput {
+        enabled: bool,
+        name: Option<String>,
+    },
+    #[serde(rename = "set_output_fps")]
+    SetOutputFps {
+        output: OutputBackend,
+        fps: Option<u32>,
+    },
+    #[serde(rename = "set_transparent_background")]
+    SetTransparentBackground {
+        enabled: bool,
+    },
+    #[serde(rename = "set_overlay")]
+    SetOverlay { overlay: Option<OverlayConfig> },
+    #[serde(rename = "set_background")]
+    SetBackground { background: Option<BackgroundConfig> },
+    #[serde(rename = "set_test_pattern")]
+    SetTestPattern {
+        enabled: bool,
+    },
+    #[serde(rename = "set_effect")]
+    SetEffect { effect: Effect, intensity: f32 },
+    #[serde(rename = "set_color_adjust")]
+    SetColorAdjust { adjust: ColorAdjust, fade_secs: f64 },
+    #[serde(rename = "set_blackout")]
+    SetBlackout { enabled: bool, fade_secs: f64 },
+    #[serde(rename = "set_freeze")]
+    SetFreeze { enabled: bool },
+    #[serde(rename = "set_time_scale")]
+    SetTimeScale { scale: f64 },
+    #[serde(rename = "set_strobe")]
+    SetStrobe { strobe: Option<StrobeConfig> },
+    #[serde(rename = "beat")]
+    Beat { bpm: f32 },
+    #[serde(rename = "set_hud")]
+    SetHud {
+        enabled: bool,
+    },
+    #[serde(rename = "set_quality")]
+    SetQuality {
+        quality: RenderQuality,
+    },
+    #[serde(rename = "set_fps_cap")]
+    SetFpsCap { fps: Option<u32> },
+    #[serde(rename = "lock_preset")]
+    LockPreset {
+        locked: bool,
+    },
+    #[serde(rename = "set_overlay_text")]
+    SetOverlayText { text: Option<TextOverlayConfig> },
+    #[serde(rename = "set_virtual_camera")]
+    SetVirtualCamera {
+        enabled: bool,
+    },
+    #[serde(rename = "start_recording")]
+    StartRecording {
+        path: String,
+        fps: u32,
+        bitrate_kbps: u32,
+        codec: RecordingCodec,
+    },
+    #[serde(rename = "stop_recording")]
+    StopRecording,
+    #[serde(rename = "start_frame_export")]
+    StartFrameExport { directory: String, fps: u32 },
+    #[serde(rename = "set_input_layer")]
+    SetInputLayer {
+        enabled: bool,
+        backend: InputBackend,
+        source: Option<String>,
+        blend: InputBlend,
+        placement: InputPlacement,
+        opacity: f32,
     },
+    #[serde(rename = "set_input_mix")]
+    SetInputMix { blend: InputBlend, opacity: f32, placement: Option<InputPlacement> },
+    #[serde(rename = "stop_frame_export")]
+    StopFrameExport,
+    #[serde(rename = "start_loop_export")]
+    StartLoopExport {
+        path: String,
+        format: LoopFormat,
+        duration_secs: f64,
+        crossfade_secs: f64,
+    },
+    #[serde(rename = "cancel_loop_export")]
+    CancelLoopExport,
     #[serde(rename = "set_texture_paths")]
     SetTexturePaths { paths: Vec<String> },
+    #[serde(rename = "set_compositor_layers")]
+    SetCompositorLayers { layers: Vec<CompositeLayer> },
+    #[serde(rename = "set_compositor_feed")]
+    SetCompositorFeed {
+        port: Option<u16>,
+        gpu_socket: Option<String>,
+    },
+    #[serde(rename = "thumbnail")]
+    Thumbnail { preset_path: String, output_path: String },
+    /// Open another deck window in a shared renderer
+    #[serde(rename = "open_deck")]
+    OpenDeck { config: Box<RendererConfig> },
     #[serde(rename = "stop")]
     Stop,
 }
 
 /// Config sent to renderer on startup
-#[derive(Debug, Serialize)]
+#[derive(Debug, Clone, Serialize)]
 struct RendererConfig {
     width: u32,
     height: u32,
@@ -246,6 +1192,52 @@ struct RendererConfig {
     /// Texture search paths for presets that reference external textures
     #[serde(default)]
     texture_paths: Vec<String>,
+    /// Run as the compositor, blending the decks' frames
+    #[serde(default)]
+    compositor: bool,
+    /// Render offscreen with no window, for outputs only (Linux)
+    #[serde(default)]
+    headless: bool,
+    /// projectM mesh size, FPS hint and texture size
+    #[serde(default)]
+    quality: RenderQuality,
+    /// Frames per second to draw at most (None = vsync rate)
+    #[serde(default)]
+    fps_cap: Option<u32>,
+    /// Start with the preset locked
+    #[serde(default)]
+    preset_locked: bool,
+    /// Timed switches and hard cuts asked for by projectM
+    #[serde(default)]
+    preset_cuts: PresetCuts,
+    /// Keep the window above other apps' windows
+    #[serde(default)]
+    always_on_top: bool,
+    /// Let clicks pass through the window
+    #[serde(default)]
+    click_through: bool,
+    /// Window position in logical pixels, when both are known
+    #[serde(default)]
+    x: Option<i32>,
+    #[serde(default)]
+    y: Option<i32>,
+    /// Image or video drawn under the visualization
+    #[serde(default)]
+    background: Option<BackgroundConfig>,
+    /// Monitor mode for exclusive fullscreen (None = borderless)
+    #[serde(default)]
+    fullscreen_mode: Option<ExclusiveMode>,
+    /// Brightness, contrast and saturation
+    #[serde(default)]
+    color_adjust: ColorAdjust,
+    /// Start blacked out
+    #[serde(default)]
+    blackout: bool,
+    /// Playback speed to start at
+    time_scale: f64,
+    /// Render preset thumbnails on request instead of a deck
+    #[serde(default)]
+    thumbnailer: bool,
 }
 
 /// A preset item in a playlist
@@ -255,6 +1247,22 @@ pub struct PlaylistItem {
     pub path: String,
 }
 
+/// When auto-cycle advances to the next preset
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum CycleOn {
+    /// Every N seconds
+    Seconds(u32),
+    /// Every N beats of the tempo clock
+    Beats(u32),
+}
+
+impl Default for CycleOn {
+    fn default() -> Self {
+        CycleOn::Seconds(30)
+    }
+}
+
 /// Playlist for a deck
 #[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub struct Playlist {
@@ -263,7 +1271,10 @@ pub struct Playlist {
     pub current_index: usize,
     pub shuffle: bool,
     pub auto_cycle: bool,
-    pub cycle_duration_secs: u32,
+    pub cycle_on: CycleOn,
+    /// Bias shuffle toward presets tagged with the music's energy
+    #[serde(default)]
+    pub energy_match: bool,
 }
 
 impl Playlist {
@@ -274,7 +1285,8 @@ impl Playlist {
             current_index: 0,
             shuffle: false,
             auto_cycle: false,
-            cycle_duration_secs: 30,
+            cycle_on: CycleOn::default(),
+            energy_match: false,
         }
     }
 
@@ -283,6 +1295,14 @@ impl Playlist {
     }
 
     pub fn advance(&mut self) -> Option<&PlaylistItem> {
+        self.advance_weighted(|_| 1.0)
+    }
+
+    /// Advance, picking shuffled items with a chance proportional to `weight`
+    pub fn advance_weighted(
+        &mut self,
+        weight: impl Fn(&PlaylistItem) -> f32,
+    ) -> Option<&PlaylistItem> {
         if self.items.is_empty() {
             return None;
         }
@@ -298,15 +1318,52 @@ impl Playlist {
                 .unwrap_or_else(|_| std::time::Duration::from_secs(0))
                 .as_nanos();
             nanos.hash(&mut hasher);
-            self.current_index = (hasher.finish() as usize) % self.items.len();
+            let weights: Vec<f32> = self.items.iter().map(|item| weight(item).max(0.0)).collect();
+            let total: f32 = weights.iter().sum();
+            self.current_index = if total > 0.0 {
+                let mut pick = (hasher.finish() as f64 / u64::MAX as f64) as f32 * total;
+                weights
+                    .iter()
+                    .position(|&w| {
+                        if pick < w {
+                            return true;
+                        }
+                        pick -= w;
+                        false
+                    })
+                    .unwrap_or(self.items.len() - 1)
+            } else {
+                (hasher.finish() as usize) % self.items.len()
+            };
         } else {
             self.current_index = (self.current_index + 1) % self.items.len();
         }
         self.items.get(self.current_index)
     }
 
-    pub fn previous(&mut self) -> Option<&PlaylistItem> {
-        if self.items.is_empty() {
+    /// Advance, favouring presets tagged with the music's energy when
+    /// energy matching is on
+    pub fn advance_for_energy(
+        &mut self,
+        target: Option<Energy>,
+        preset_energy: &HashMap<String, Energy>,
+    ) -> Option<&PlaylistItem> {
+        if !self.energy_match {
+            return self.advance();
+        }
+        self.advance_weighted(|item| Energy::match_weight(target, preset_energy.get(&item.path).copied()))
+    }
+
+    /// Jump to a random item, whether or not shuffle is on
+    pub fn random(&mut self) -> Option<&PlaylistItem> {
+        let shuffle = std::mem::replace(&mut self.shuffle, true);
+        self.advance();
+        self.shuffle = shuffle;
+        self.current_preset()
+    }
+
+    pub fn previous(&mut self) -> Option<&PlaylistItem> {
+        if self.items.is_empty() {
             return None;
         }
         if self.current_index == 0 {
@@ -324,10 +1381,55 @@ pub struct DeckState {
     pub renderer: Option<RendererProcess>,
     pub preset_path: Option<String>,
     pub volume: f32,
+    /// Input gain trim applied before AGC and the crossfader (linear)
+    pub gain: f32,
     pub beat_sensitivity: f32,
+    /// Post-processing intensities sent to the running renderer
+    pub effects: EffectChain,
+    /// projectM quality, kept across restarts
+    pub quality: RenderQuality,
+    /// Frame rate limit below vsync, kept across restarts
+    pub fps_cap: Option<u32>,
+    /// Image or video under the visualization, kept across restarts
+    pub background: Option<BackgroundConfig>,
+    /// Brightness, contrast and saturation, kept across restarts
+    pub color_adjust: ColorAdjust,
+    /// Output faded to black, kept across restarts so a restart stays dark
+    pub blackout: bool,
+    /// Holding its last frame; a new renderer has none to hold, so it starts
+    /// playing
+    pub frozen: bool,
+    /// Playback speed (1 = normal), kept across restarts
+    pub time_scale: f64,
+    /// Strobe rate and brightness, used whenever the strobe is turned on
+    pub strobe: StrobeConfig,
+    /// Strobe flashing on the running renderer
+    pub strobing: bool,
+    /// Auto-cycle and projectM leave the current preset alone
+    pub preset_locked: bool,
+    /// When projectM asks for the next preset on its own, kept across
+    /// restarts
+    pub preset_cuts: PresetCuts,
+    /// Window floats above other apps
+    pub always_on_top: bool,
+    /// Clicks pass through the window
+    pub click_through: bool,
+    /// Where the window was last, to open it there again
+    pub window_geometry: Option<WindowGeometry>,
+    /// Renderer settings brought back when it restarts after a crash
+    pub restore: RendererRestore,
+    /// Automatic restarts in a row, and when the next one is due
+    pub restart_attempts: u32,
+    pub restart_at: Option<std::time::Instant>,
     pub active: bool,
     pub playlist: Playlist,
     pub last_cycle_time: Option<std::time::Instant>,
+    /// Tempo clock beat count at the last beat-synced cycle
+    pub last_cycle_beat: Option<u64>,
+    pub agc_enabled: bool,
+    pub agc: AutoGain,
+    /// Preset to restore when audio returns after idle
+    pub pre_idle_preset: Option<String>,
 }
 
 impl DeckState {
@@ -337,16 +1439,224 @@ impl DeckState {
             renderer: None,
             preset_path: None,
             volume: 1.0,
+            gain: 1.0,
             beat_sensitivity: 1.0,
+            effects: EffectChain::default(),
+            quality: RenderQuality::default(),
+            fps_cap: None,
+            background: None,
+            color_adjust: ColorAdjust::default(),
+            blackout: false,
+            frozen: false,
+            time_scale: 1.0,
+            strobe: StrobeConfig::default(),
+            strobing: false,
+            preset_locked: false,
+            preset_cuts: PresetCuts::default(),
+            always_on_top: false,
+            click_through: false,
+            window_geometry: None,
+            restore: RendererRestore::default(),
+            restart_attempts: 0,
+            restart_at: None,
             active: false,
             playlist: Playlist::new(),
             last_cycle_time: None,
+            last_cycle_beat: None,
+            agc_enabled: false,
+            agc: AutoGain::new(AgcConfig::default(), AudioConfig::default().sample_rate, 2),
+            pre_idle_preset: None,
+        }
+    }
+
+    /// Keep the window's last reported geometry for the deck's next start
+    fn remember_geometry(&mut self) {
+        if let Some(geometry) = self.renderer.as_ref().and_then(|r| r.geometry()) {
+            self.window_geometry = Some(geometry);
         }
     }
 
     pub fn is_running(&mut self) -> bool {
         self.renderer.as_mut().is_some_and(|r| r.is_running())
     }
+
+    /// Turn auto-cycle on (restarting its timer) or off
+    pub fn set_auto_cycle(&mut self, auto_cycle: bool) {
+        self.playlist.auto_cycle = auto_cycle;
+        if auto_cycle {
+            self.last_cycle_time = Some(std::time::Instant::now());
+            self.last_cycle_beat = None;
+        }
+    }
+
+    /// Whether auto-cycle should advance to the next preset now
+    pub fn cycle_due(&mut self, now: std::time::Instant, tempo: &TempoInfo) -> bool {
+        let beats = match self.playlist.cycle_on {
+            CycleOn::Seconds(secs) => {
+                return match self.last_cycle_time {
+                    Some(last_time) => now.duration_since(last_time).as_secs() >= secs as u64,
+                    None => true, // First time, start the timer
+                };
+            }
+            CycleOn::Beats(beats) => beats.max(1) as u64,
+        };
+
+        if tempo.beat.bpm <= 0.0 {
+            // No tempo yet: time the beats at a fallback tempo, and start
+            // counting real beats once one is known
+            self.last_cycle_beat = None;
+            let secs = beats as f32 * 60.0 / FALLBACK_CYCLE_BPM;
+            return match self.last_cycle_time {
+                Some(last_time) => now.duration_since(last_time).as_secs_f32() >= secs,
+                None => true,
+            };
+        }
+
+        let count = tempo.beat.beat_count;
+        match self.last_cycle_beat {
+            Some(last_beat) if count >= last_beat => count - last_beat >= beats,
+            // Start counting (again, if the beat detector was reset)
+            _ => {
+                self.last_cycle_beat = Some(count);
+                false
+            }
+        }
+    }
+}
+
+/// How a deck's renderer was started and the outputs turned on since, to
+/// start it the same way after a crash
+#[derive(Clone, Default)]
+pub struct RendererRestore {
+    fullscreen: bool,
+    monitor_index: Option<usize>,
+    fullscreen_mode: Option<ExclusiveMode>,
+    headless: bool,
+    texture_paths: Option<Vec<String>>,
+    /// Commands that turned the v4l2/Spout and NDI outputs on
+    video_output: Option<RendererCommand>,
+    ndi_output: Option<RendererCommand>,
+}
+
+/// Relaunching deck renderers that crash
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct AutoRestartSettings {
+    pub enabled: bool,
+    /// Restarts in a row before giving up on a deck
+    pub max_attempts: u32,
+}
+
+impl Default for AutoRestartSettings {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            max_attempts: 5,
+        }
+    }
+}
+
+/// Stage of an automatic restart
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DeckRestartStatus {
+    /// The renderer crashed; it is relaunched after `delay_secs`
+    Scheduled,
+    Restarted,
+    Failed,
+    /// Too many crashes in a row; the deck stays stopped
+    GaveUp,
+}
+
+/// Payload of the `deck_restart` event
+#[derive(Serialize, Clone)]
+pub struct DeckRestartEvent {
+    pub deck_id: u8,
+    pub status: DeckRestartStatus,
+    /// Restarts in a row, counting this one
+    pub attempt: u32,
+    pub delay_secs: Option<f64>,
+    pub error: Option<String>,
+}
+
+/// What decks do while the audio input is silent
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct IdleSettings {
+    /// RMS level below which audio counts as silent
+    pub threshold: f32,
+    /// Seconds of silence before going idle
+    pub timeout_secs: f32,
+    /// Preset loaded on running decks while idle (previous preset is restored)
+    pub idle_preset: Option<String>,
+    /// Pause playlist auto-cycle while idle (always paused with an idle preset)
+    pub freeze_auto_cycle: bool,
+}
+
+impl Default for IdleSettings {
+    fn default() -> Self {
+        Self {
+            threshold: DEFAULT_SILENCE_THRESHOLD,
+            timeout_secs: DEFAULT_IDLE_TIMEOUT_SECS,
+            idle_preset: None,
+            freeze_auto_cycle: false,
+        }
+    }
+}
+
+/// Payload of the `audio_idle` event
+#[derive(Serialize, Clone)]
+pub struct AudioIdleEvent {
+    pub idle: bool,
+    pub silent_secs: f32,
+}
+
+/// Payload of the `audio_device_lost` event
+#[derive(Serialize, Clone)]
+pub struct AudioDeviceLostEvent {
+    pub device: String,
+    pub reason: String,
+    /// Device capture moved to while waiting for the lost one
+    pub fallback: Option<String>,
+}
+
+/// Payload of the `audio_capture_error` event
+#[derive(Serialize, Clone)]
+pub struct AudioCaptureErrorEvent {
+    pub device: String,
+    pub reason: String,
+}
+
+/// Format the capture device delivers next to the format decks receive
+#[derive(Serialize)]
+pub struct AudioFormatInfo {
+    pub capture: CaptureFormat,
+    pub sample_rate: u32,
+    pub channels: u16,
+}
+
+/// Latency of captured audio on its way to the renderers
+#[derive(Serialize)]
+pub struct AudioStats {
+    /// From capture until read by the audio pump
+    pub read: LatencyReport,
+    /// From capture until written to the deck renderers
+    pub delivery: LatencyReport,
+    pub buffer_frames: usize,
+    pub max_latency_ms: u32,
+}
+
+/// Latency measurements taken by the audio pump
+#[derive(Default)]
+struct AudioLatency {
+    read: LatencyStats,
+    delivery: LatencyStats,
+}
+
+impl AudioLatency {
+    fn clear(&mut self) {
+        self.read.clear();
+        self.delivery.clear();
+    }
 }
 
 /// Crossfader curve types
@@ -359,6 +1669,16 @@ pub enum CrossfaderCurve {
     EqualPower,
 }
 
+impl CrossfaderCurve {
+    /// Following curve, wrapping around (for stepping through curves from MIDI)
+    pub fn next(self) -> Self {
+        match self {
+            CrossfaderCurve::Linear => CrossfaderCurve::EqualPower,
+            CrossfaderCurve::EqualPower => CrossfaderCurve::Linear,
+        }
+    }
+}
+
 /// Crossfader configuration
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct CrossfaderConfig {
@@ -372,6 +1692,9 @@ pub struct CrossfaderConfig {
     pub curve: CrossfaderCurve,
     /// Whether crossfader is enabled
     pub enabled: bool,
+    /// Position to return to when a held cut button is released
+    #[serde(skip)]
+    pub cut_from: Option<f32>,
 }
 
 impl Default for CrossfaderConfig {
@@ -382,11 +1705,23 @@ impl Default for CrossfaderConfig {
             side_b: vec![2, 3], // Decks 3 & 4 on Side B
             curve: CrossfaderCurve::EqualPower,
             enabled: false, // Disabled by default
+            cut_from: None,
         }
     }
 }
 
 impl CrossfaderConfig {
+    /// Put a deck on one side of the fader, or on neither
+    pub fn assign_deck(&mut self, deck_id: DeckId, side: Option<CrossfaderSide>) {
+        self.side_a.retain(|&id| id != deck_id);
+        self.side_b.retain(|&id| id != deck_id);
+        match side {
+            Some(CrossfaderSide::A) => self.side_a.push(deck_id),
+            Some(CrossfaderSide::B) => self.side_b.push(deck_id),
+            None => {}
+        }
+    }
+
     /// Calculate volume multiplier for a deck based on crossfader position
     pub fn volume_for_deck(&self, deck_id: DeckId) -> f32 {
         if !self.enabled {
@@ -423,17 +1758,6 @@ impl CrossfaderConfig {
     }
 }
 
-/// Blend mode for compositor
-#[derive(Debug, Clone, Copy, Serialize, Deserialize, Default, PartialEq)]
-pub enum BlendMode {
-    #[default]
-    Normal,     // Standard alpha blend
-    Add,        // Additive blend (bright)
-    Multiply,   // Darken blend
-    Screen,     // Lighten blend
-    Overlay,    // Contrast blend
-}
-
 /// Per-deck compositor settings
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct DeckCompositorSettings {
@@ -490,15 +1814,77 @@ impl Default for CompositorConfig {
     }
 }
 
+impl CompositorConfig {
+    /// Decks to draw, bottom layer first, with the crossfader applied when linked
+    pub fn layers(&self, crossfader: &CrossfaderConfig) -> Vec<CompositeLayer> {
+        let mut decks: Vec<_> = self.deck_settings.iter()
+            .filter(|(_, s)| s.enabled)
+            .collect();
+        decks.sort_by_key(|(id, s)| (s.layer_order, **id));
+        decks.into_iter()
+            .map(|(&deck_id, s)| {
+                let fade = if self.link_to_crossfader {
+                    crossfader.volume_for_deck(deck_id)
+                } else {
+                    1.0
+                };
+                CompositeLayer {
+                    deck_id,
+                    opacity: (s.opacity * fade).clamp(0.0, 1.0),
+                    blend_mode: s.blend_mode,
+                }
+            })
+            .collect()
+    }
+}
+
 /// Application state shared across Tauri commands
+///
+/// The audio pump thread holds several of these at once, so anything that
+/// holds more than one takes them in this order: `audio_engine`, `decks`,
+/// `crossfader`, then the rest. Taking `decks` before `audio_engine`
+/// deadlocks against the pump.
 pub struct AppState {
     decks: Mutex<HashMap<DeckId, DeckState>>,
     audio_engine: Mutex<AudioEngine>,
     crossfader: Mutex<CrossfaderConfig>,
     compositor: Mutex<CompositorConfig>,
+    /// Renderer blending the decks while the compositor is enabled
+    compositor_renderer: Mutex<Option<RendererProcess>>,
     midi_controller: Mutex<MidiController>,
-    /// Current audio levels (left, right) for VU meters - updated by pump_audio
-    audio_levels: Mutex<(f32, f32)>,
+    /// MIDI output shared with the controller, locked without it (and last)
+    midi_output: Arc<Mutex<Option<MidiOut>>>,
+    /// RMS, held peak and clip state for VU meters - updated by pump_audio
+    level_meter: Mutex<LevelMeter>,
+    /// Tempo tracker fed from the captured stream - updated by pump_audio
+    beat_detector: Mutex<BeatDetector>,
+    /// Tempo used by beat-synced features: detected, or set/tapped by hand
+    tempo_clock: Mutex<TempoClock>,
+    /// Sliding FFT window over the captured stream - updated by pump_audio
+    spectrum: Mutex<SpectrumAnalyzer>,
+    /// Rolling waveform of the captured stream - updated by pump_audio
+    waveform: Mutex<WaveformHistory>,
+    /// Silence tracker driving idle behaviour - updated by pump_audio
+    silence_detector: Mutex<SilenceDetector>,
+    idle_settings: Mutex<IdleSettings>,
+    auto_restart: Mutex<AutoRestartSettings>,
+    /// GPU asked for when renderers start
+    gpu_preference: Mutex<GpuPreference>,
+    /// Open deck windows in one shared renderer instead of one each
+    multi_deck_renderer: Mutex<bool>,
+    /// Renderer hosting the decks while `multi_deck_renderer` is on
+    shared_renderer: Mutex<Option<Arc<SharedRenderer>>>,
+    /// Capture-to-renderer latency - updated by pump_audio
+    audio_latency: Mutex<AudioLatency>,
+    /// Rolling loudness of the captured stream - updated by pump_audio
+    energy: Mutex<EnergyTracker>,
+    /// Presets tagged calm or intense in the frontend tag store
+    preset_energy: Mutex<HashMap<String, Energy>>,
+    /// Presets a renderer couldn't load, with projectM's error, for the
+    /// frontend to blacklist
+    failed_presets: Mutex<HashMap<String, String>>,
+    /// Presets waiting for thumbnails
+    thumbnail_queue: Mutex<ThumbnailQueue>,
 }
 
 impl Default for AppState {
@@ -514,14 +1900,45 @@ impl AppState {
         for id in 0..MAX_DECKS {
             decks.insert(id, DeckState::new(id));
         }
+        let midi_controller = MidiController::new();
 
         Self {
             decks: Mutex::new(decks),
             audio_engine: Mutex::new(AudioEngine::new()),
             crossfader: Mutex::new(CrossfaderConfig::default()),
             compositor: Mutex::new(CompositorConfig::default()),
-            midi_controller: Mutex::new(MidiController::new()),
-            audio_levels: Mutex::new((0.0, 0.0)),
+            compositor_renderer: Mutex::new(None),
+            midi_output: midi_controller.output(),
+            midi_controller: Mutex::new(midi_controller),
+            level_meter: Mutex::new(LevelMeter::new(MeterConfig::default(), AudioConfig::default().sample_rate)),
+            beat_detector: Mutex::new(BeatDetector::new(AudioConfig::default().sample_rate, 2)),
+            tempo_clock: Mutex::new(TempoClock::new()),
+            spectrum: Mutex::new(SpectrumAnalyzer::new(
+                DEFAULT_FFT_SIZE,
+                AudioConfig::default().sample_rate,
+                2,
+            )),
+            waveform: Mutex::new(WaveformHistory::new(
+                DEFAULT_WAVEFORM_SECS,
+                DEFAULT_WAVEFORM_POINTS_PER_SEC,
+                AudioConfig::default().sample_rate,
+                2,
+            )),
+            silence_detector: Mutex::new(SilenceDetector::default()),
+            idle_settings: Mutex::new(IdleSettings::default()),
+            auto_restart: Mutex::new(AutoRestartSettings::default()),
+            gpu_preference: Mutex::new(GpuPreference::default()),
+            multi_deck_renderer: Mutex::new(false),
+            shared_renderer: Mutex::new(None),
+            audio_latency: Mutex::new(AudioLatency::default()),
+            energy: Mutex::new(EnergyTracker::new(
+                DEFAULT_ENERGY_WINDOW_SECS,
+                AudioConfig::default().sample_rate,
+                2,
+            )),
+            preset_energy: Mutex::new(HashMap::new()),
+            failed_presets: Mutex::new(HashMap::new()),
+            thumbnail_queue: Mutex::new(ThumbnailQueue::default()),
         }
     }
 }
@@ -599,6 +2016,9 @@ pub struct CompositorInfo {
     pub output_height: u32,
     pub link_to_crossfader: bool,
     pub deck_settings: HashMap<u8, DeckCompositorInfo>,
+    /// Compositor window open and blending
+    #[serde(default)]
+    pub running: bool,
 }
 
 impl From<&CompositorConfig> for CompositorInfo {
@@ -611,6 +2031,7 @@ impl From<&CompositorConfig> for CompositorInfo {
             deck_settings: c.deck_settings.iter()
                 .map(|(k, v)| (*k, DeckCompositorInfo::from(v)))
                 .collect(),
+            running: false,
         }
     }
 }
@@ -633,7 +2054,13 @@ pub struct PlaylistInfo {
     pub current_index: usize,
     pub shuffle: bool,
     pub auto_cycle: bool,
-    pub cycle_duration_secs: u32,
+    #[serde(default)]
+    pub cycle_on: CycleOn,
+    /// Cycle duration of playlists exported before `cycle_on`
+    #[serde(default, skip_serializing)]
+    pub cycle_duration_secs: Option<u32>,
+    #[serde(default)]
+    pub energy_match: bool,
 }
 
 impl From<&Playlist> for PlaylistInfo {
@@ -644,7 +2071,9 @@ impl From<&Playlist> for PlaylistInfo {
             current_index: p.current_index,
             shuffle: p.shuffle,
             auto_cycle: p.auto_cycle,
-            cycle_duration_secs: p.cycle_duration_secs,
+            cycle_on: p.cycle_on,
+            cycle_duration_secs: None,
+            energy_match: p.energy_match,
         }
     }
 }
@@ -655,11 +2084,49 @@ pub struct DeckInfo {
     pub running: bool,
     pub preset: Option<String>,
     pub volume: f32,
+    pub gain: f32,
     pub beat_sensitivity: f32,
     pub playlist: PlaylistInfo,
     pub health: Option<RendererHealth>,
     pub uptime_secs: Option<u64>,
     pub crash_count: Option<u32>,
+    /// Video recording in progress
+    pub recording: Option<DeckRecordingInfo>,
+    /// PNG image-sequence export in progress
+    pub frame_export: Option<DeckFrameExportInfo>,
+    /// Current or last loop export
+    pub loop_export: Option<DeckLoopExportInfo>,
+    /// FPS, frame times and outputs, reported about once a second
+    pub stats: Option<RenderStats>,
+    /// GPU the renderer draws on, as GL reports it
+    pub gpu: Option<GpuInfo>,
+    /// projectM quality settings
+    pub quality: RenderQuality,
+    /// Frame rate limit (None = vsync rate)
+    pub fps_cap: Option<u32>,
+    /// Image or video under the visualization
+    pub background: Option<BackgroundConfig>,
+    /// Brightness, contrast and saturation
+    pub color_adjust: ColorAdjust,
+    /// Output faded to black
+    pub blackout: bool,
+    /// Holding its last frame
+    pub frozen: bool,
+    /// Playback speed (1 = normal)
+    pub time_scale: f64,
+    /// Strobe settings, and whether it is flashing now
+    pub strobe: StrobeConfig,
+    pub strobing: bool,
+    /// Preset held against auto-cycle
+    pub preset_locked: bool,
+    /// Timed switches and hard cuts asked for by projectM
+    pub preset_cuts: PresetCuts,
+    /// Window floats above other apps
+    pub always_on_top: bool,
+    /// Clicks pass through the window
+    pub click_through: bool,
+    /// Window position and size, live or from the last run
+    pub window_geometry: Option<WindowGeometry>,
 }
 
 #[derive(Serialize, Deserialize)]
@@ -668,7 +2135,7 @@ pub struct AudioDeviceInfo {
     pub description: String,
     pub is_default: bool,
     pub is_monitor: bool,
-    pub device_type: String, // "input", "output", or "monitor"
+    pub device_type: String, // "input", "output", "monitor" or "application"
 }
 
 impl From<DeviceInfo> for AudioDeviceInfo {
@@ -677,6 +2144,7 @@ impl From<DeviceInfo> for AudioDeviceInfo {
             opendrop_core::audio::DeviceType::Input => "input",
             opendrop_core::audio::DeviceType::Output => "output",
             opendrop_core::audio::DeviceType::Monitor => "monitor",
+            opendrop_core::audio::DeviceType::Application => "application",
         };
         Self {
             name: d.name,
@@ -688,6 +2156,25 @@ impl From<DeviceInfo> for AudioDeviceInfo {
     }
 }
 
+#[derive(Serialize, Deserialize)]
+pub struct AudioRecordingInfo {
+    pub path: String,
+    pub duration_secs: f64,
+    pub sample_rate: u32,
+    pub channels: u16,
+}
+
+impl From<RecordingInfo> for AudioRecordingInfo {
+    fn from(r: RecordingInfo) -> Self {
+        Self {
+            duration_secs: r.duration_secs(),
+            path: r.path.to_string_lossy().to_string(),
+            sample_rate: r.sample_rate,
+            channels: r.channels,
+        }
+    }
+}
+
 #[derive(Serialize, Deserialize)]
 pub struct PresetInfo {
     pub name: String,
@@ -698,6 +2185,7 @@ pub struct PresetInfo {
 
 /// Start visualization on a specific deck
 #[tauri::command]
+#[allow(clippy::too_many_arguments)]
 fn start_deck(
     state: State<'_, AppState>,
     deck_id: Option<u8>,
@@ -706,23 +2194,89 @@ fn start_deck(
     fullscreen: Option<bool>,
     preset_path: Option<String>,
     monitor_index: Option<usize>,
+    headless: Option<bool>,
+    x: Option<i32>,
+    y: Option<i32>,
+    fullscreen_mode: Option<ExclusiveMode>,
 ) -> Result<String, String> {
     let deck_id = deck_id.unwrap_or(0);
     if deck_id >= MAX_DECKS {
         return Err(format!("Invalid deck ID: {}. Must be 0-{}", deck_id, MAX_DECKS - 1));
     }
+    if let Some(ref mode) = fullscreen_mode {
+        mode.validate().map_err(|e| e.to_string())?;
+    }
+    let launcher = DeckLauncher::new(&state)?;
 
     let mut decks_guard = state.decks.lock().map_err(|e| e.to_string())?;
     let deck = decks_guard.get_mut(&deck_id).ok_or("Deck not found")?;
+    deck.remember_geometry();
+    if x.is_some() || y.is_some() || width.is_some() || height.is_some() {
+        deck.window_geometry = Some(WindowGeometry::merged(deck.window_geometry, x, y, width, height));
+    }
+    start_deck_renderer(
+        deck,
+        width,
+        height,
+        fullscreen,
+        preset_path,
+        monitor_index,
+        fullscreen_mode,
+        headless,
+        launcher,
+    )
+}
+
+/// Spawn the renderer of a deck (shared by `start_deck` and MIDI)
+#[allow(clippy::too_many_arguments)]
+fn start_deck_renderer(
+    deck: &mut DeckState,
+    width: Option<u32>,
+    height: Option<u32>,
+    fullscreen: Option<bool>,
+    preset_path: Option<String>,
+    monitor_index: Option<usize>,
+    fullscreen_mode: Option<ExclusiveMode>,
+    headless: Option<bool>,
+    launcher: DeckLauncher,
+) -> Result<String, String> {
+    let config = deck_renderer_config(
+        deck,
+        width,
+        height,
+        fullscreen,
+        preset_path,
+        monitor_index,
+        fullscreen_mode,
+        headless,
+    )?;
+    let renderer = spawn_deck_renderer(&config, launcher)?;
+    install_deck_renderer(deck, config, renderer);
+    Ok(format!("Deck {} started", deck.id))
+}
+
+/// Renderer config for a deck, from its settings and the given overrides
+#[allow(clippy::too_many_arguments)]
+fn deck_renderer_config(
+    deck: &mut DeckState,
+    width: Option<u32>,
+    height: Option<u32>,
+    fullscreen: Option<bool>,
+    preset_path: Option<String>,
+    monitor_index: Option<usize>,
+    fullscreen_mode: Option<ExclusiveMode>,
+    headless: Option<bool>,
+) -> Result<RendererConfig, String> {
+    let deck_id = deck.id;
 
     // Check if already running
     if deck.is_running() {
         return Err(format!("Deck {} already running", deck_id));
     }
-
-    // Find renderer executable
-    let renderer_path = find_renderer_executable()?;
-    info!("Using renderer at: {}", renderer_path);
+    // Its window may be opening again in a shared renderer
+    if let Some(ref mut renderer) = deck.renderer {
+        renderer.release();
+    }
 
     // Use a default preset if none specified - search all preset directories
     let preset = preset_path.or_else(|| {
@@ -766,36 +2320,426 @@ fn start_deck(
         .map(|p| p.to_string_lossy().to_string())
         .collect();
 
-    // Build config
+    // Build config, opening the window where it was last
+    let geometry = WindowGeometry::merged(deck.window_geometry, None, None, width, height);
     let config = RendererConfig {
-        width: width.unwrap_or(1280),
-        height: height.unwrap_or(720),
-        preset_path: preset.clone(),
+        width: geometry.width,
+        height: geometry.height,
+        preset_path: preset,
         fullscreen: fullscreen.unwrap_or(false),
         deck_id,
         monitor_index,
         texture_paths,
+        compositor: false,
+        headless: headless.unwrap_or(false),
+        quality: deck.quality,
+        fps_cap: deck.fps_cap,
+        preset_locked: deck.preset_locked,
+        preset_cuts: deck.preset_cuts,
+        always_on_top: deck.always_on_top,
+        click_through: deck.click_through,
+        x: geometry.x,
+        y: geometry.y,
+        background: deck.background.clone(),
+        fullscreen_mode,
+        color_adjust: deck.color_adjust,
+        blackout: deck.blackout,
+        time_scale: deck.time_scale,
+        thumbnailer: false,
     };
+    Ok(config)
+}
 
-    let config_json = serde_json::to_string(&config).map_err(|e| e.to_string())?;
+/// How deck renderers start: the GPU they ask for, and where their windows
+/// open while one renderer hosts every deck
+#[derive(Clone, Copy)]
+struct DeckLauncher<'a> {
+    gpu: GpuPreference,
+    shared: Option<&'a Mutex<Option<Arc<SharedRenderer>>>>,
+}
 
-    info!("Starting deck {} with config: {:?}", deck_id, config);
+impl<'a> DeckLauncher<'a> {
+    fn new(state: &'a AppState) -> Result<Self, String> {
+        let gpu = *state.gpu_preference.lock().map_err(|e| e.to_string())?;
+        let multi_deck = *state.multi_deck_renderer.lock().map_err(|e| e.to_string())?;
+        Ok(Self {
+            gpu,
+            shared: multi_deck.then_some(&state.shared_renderer),
+        })
+    }
+}
+
+/// Launch a deck renderer; takes no locks but the shared renderer's
+///
+/// Headless decks always run in their own process.
+fn spawn_deck_renderer(config: &RendererConfig, launcher: DeckLauncher) -> Result<RendererProcess, String> {
+    if let Some(shared) = launcher.shared.filter(|_| !config.headless) {
+        let mut shared = shared.lock().map_err(|e| e.to_string())?;
+        if let Some(host) = shared.as_ref().filter(|host| host.accepts_decks()) {
+            return host.open_deck(config);
+        }
+        let (host, renderer) = SharedRenderer::spawn(config, launcher.gpu)?;
+        *shared = Some(host);
+        return Ok(renderer);
+    }
+
+    // Find renderer executable
+    let renderer_path = find_renderer_executable()?;
+    info!("Using renderer at: {}", renderer_path);
+
+    let config_json = serde_json::to_string(config).map_err(|e| e.to_string())?;
 
-    // Spawn renderer process
-    let child = Command::new(&renderer_path)
+    info!("Starting deck {} with config: {:?}", config.deck_id, config);
+
+    let child = renderer_command(&renderer_path, launcher.gpu)
         .arg(&config_json)
         .stdin(Stdio::piped())
         .stdout(Stdio::piped())
         .stderr(Stdio::inherit())
         .spawn()
-        .map_err(|e| format!("Failed to start renderer for deck {}: {}", deck_id, e))?;
+        .map_err(|e| format!("Failed to start renderer for deck {}: {}", config.deck_id, e))?;
+    Ok(RendererProcess::new(child))
+}
 
-    // Update deck state
-    deck.preset_path = preset;
-    deck.renderer = Some(RendererProcess::new(child));
+/// Attach a spawned renderer to its deck
+fn install_deck_renderer(deck: &mut DeckState, config: RendererConfig, renderer: RendererProcess) {
+    deck.preset_path = config.preset_path;
+    deck.renderer = Some(renderer);
     deck.active = true;
+    deck.frozen = false;
+    deck.restore = RendererRestore {
+        fullscreen: config.fullscreen,
+        monitor_index: config.monitor_index,
+        fullscreen_mode: config.fullscreen_mode,
+        headless: config.headless,
+        ..RendererRestore::default()
+    };
+}
+
+/// Spawn the compositor renderer at the configured output size
+fn start_compositor_renderer(config: &CompositorConfig, gpu: GpuPreference) -> Result<RendererProcess, String> {
+    let renderer_path = find_renderer_executable()?;
+    let config = RendererConfig {
+        width: config.output_width,
+        height: config.output_height,
+        preset_path: None,
+        fullscreen: false,
+        deck_id: 0,
+        monitor_index: None,
+        texture_paths: Vec::new(),
+        compositor: true,
+        headless: false,
+        quality: RenderQuality::default(),
+        fps_cap: None,
+        preset_locked: false,
+        preset_cuts: PresetCuts::default(),
+        always_on_top: false,
+        click_through: false,
+        x: None,
+        y: None,
+        background: None,
+        fullscreen_mode: None,
+        color_adjust: ColorAdjust::default(),
+        blackout: false,
+        time_scale: 1.0,
+        thumbnailer: false,
+    };
+    let config_json = serde_json::to_string(&config).map_err(|e| e.to_string())?;
+
+    info!("Starting compositor with config: {:?}", config);
+
+    let child = renderer_command(&renderer_path, gpu)
+        .arg(&config_json)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::inherit())
+        .spawn()
+        .map_err(|e| format!("Failed to start compositor: {}", e))?;
+
+    Ok(RendererProcess::new(child))
+}
+
+/// A preset thumbnail to render
+#[derive(Debug, Clone, PartialEq)]
+struct ThumbnailJob {
+    preset_path: String,
+    output_path: String,
+}
+
+/// Thumbnails waiting for the worker thread
+#[derive(Debug, Default)]
+struct ThumbnailQueue {
+    pending: VecDeque<ThumbnailJob>,
+    /// Whether a worker thread is rendering the queue
+    running: bool,
+}
+
+/// A finished thumbnail, sent to the frontend as `preset_thumbnail`
+#[derive(Debug, Clone, Serialize)]
+struct PresetThumbnail {
+    preset_path: String,
+    /// None when the preset couldn't be rendered
+    thumbnail_path: Option<String>,
+    error: Option<String>,
+}
+
+/// A renderer in thumbnailer mode, answering one thumbnail at a time
+struct ThumbnailRenderer {
+    child: Child,
+    stdout: BufReader<ChildStdout>,
+}
+
+impl ThumbnailRenderer {
+    fn start(gpu: GpuPreference) -> Result<Self, String> {
+        let renderer_path = find_renderer_executable()?;
+        let texture_paths: Vec<String> = get_default_texture_dirs()
+            .into_iter()
+            .filter(|p| p.exists() && p.is_dir())
+            .map(|p| p.to_string_lossy().to_string())
+            .collect();
+        let config = RendererConfig {
+            width: THUMBNAIL_WIDTH,
+            height: THUMBNAIL_HEIGHT,
+            preset_path: None,
+            fullscreen: false,
+            deck_id: 0,
+            monitor_index: None,
+            texture_paths,
+            compositor: false,
+            headless: false,
+            quality: RenderQuality::default(),
+            fps_cap: None,
+            preset_locked: true,
+            preset_cuts: PresetCuts::default(),
+            always_on_top: false,
+            click_through: false,
+            x: None,
+            y: None,
+            background: None,
+            fullscreen_mode: None,
+            color_adjust: ColorAdjust::default(),
+            blackout: false,
+            time_scale: 1.0,
+            thumbnailer: true,
+        };
+        let config_json = serde_json::to_string(&config).map_err(|e| e.to_string())?;
+
+        let mut child = renderer_command(&renderer_path, gpu)
+            .arg(&config_json)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::inherit())
+            .spawn()
+            .map_err(|e| format!("Failed to start thumbnail renderer: {}", e))?;
+        let stdout = child.stdout.take().ok_or("Thumbnail renderer has no stdout")?;
+        Ok(Self {
+            child,
+            stdout: BufReader::new(stdout),
+        })
+    }
+
+    /// Render `job`; Err when the renderer went away instead of answering
+    fn render(&mut self, job: &ThumbnailJob) -> Result<PresetThumbnail, String> {
+        let command = RendererCommand::Thumbnail {
+            preset_path: job.preset_path.clone(),
+            output_path: job.output_path.clone(),
+        };
+        let stdin = self.child.stdin.as_mut().ok_or("No stdin available")?;
+        let json = serde_json::to_string(&command).map_err(|e| e.to_string())?;
+        writeln!(stdin, "{}", json).map_err(|e| e.to_string())?;
+        stdin.flush().map_err(|e| e.to_string())?;
+
+        let mut line = String::new();
+        loop {
+            line.clear();
+            if self.stdout.read_line(&mut line).map_err(|e| e.to_string())? == 0 {
+                return Err("Thumbnail renderer exited".to_string());
+            }
+            match serde_json::from_str::<RendererEvent>(&line) {
+                Ok(RendererEvent::ThumbnailDone { preset_path, output_path }) if preset_path == job.preset_path => {
+                    return Ok(PresetThumbnail {
+                        preset_path,
+                        thumbnail_path: Some(output_path),
+                        error: None,
+                    });
+                }
+                Ok(RendererEvent::ThumbnailFailed { preset_path, error }) if preset_path == job.preset_path => {
+                    return Ok(PresetThumbnail {
+                        preset_path,
+                        thumbnail_path: None,
+                        error: Some(error),
+                    });
+                }
+                Ok(RendererEvent::Error { message }) => return Err(message),
+                Ok(RendererEvent::Closed) => return Err("Thumbnail renderer closed".to_string()),
+                Ok(RendererEvent::GpuInfo(info)) => {
+                    info!("Thumbnails are drawn on {} ({})", info.renderer, info.vendor);
+                }
+                _ => {}
+            }
+        }
+    }
+
+    fn stop(mut self) {
+        // Closing stdin stops it too, should the command not get through
+        if let Some(mut stdin) = self.child.stdin.take() {
+            if let Ok(json) = serde_json::to_string(&RendererCommand::Stop) {
+                let _ = writeln!(stdin, "{}", json);
+            }
+        }
+        let deadline = std::time::Instant::now() + std::time::Duration::from_secs(2);
+        while std::time::Instant::now() < deadline && matches!(self.child.try_wait(), Ok(None)) {
+            thread::sleep(std::time::Duration::from_millis(50));
+        }
+        let _ = self.child.kill();
+        let _ = self.child.wait();
+    }
+}
 
-    Ok(format!("Deck {} started", deck_id))
+/// Directory thumbnails are cached in
+fn thumbnail_dir(app: &AppHandle) -> Result<std::path::PathBuf, String> {
+    app.path()
+        .app_cache_dir()
+        .map(|dir| dir.join("thumbnails"))
+        .map_err(|e| format!("No cache directory for thumbnails: {}", e))
+}
+
+/// Render the queue until it is empty. A renderer is started for the
+/// batch; a preset that takes it down is reported failed and the rest go
+/// to a new one.
+fn run_thumbnail_worker(app: AppHandle, gpu: GpuPreference) {
+    let state = app.state::<AppState>();
+    let mut renderer: Option<ThumbnailRenderer> = None;
+
+    loop {
+        let job = match state.thumbnail_queue.lock() {
+            Ok(mut queue) => {
+                let job = queue.pending.pop_front();
+                if job.is_none() {
+                    queue.running = false;
+                }
+                job
+            }
+            Err(_) => None,
+        };
+        let Some(job) = job else { break };
+
+        // Queued again while it was rendering
+        if std::path::Path::new(&job.output_path).exists() {
+            let _ = app.emit(
+                "preset_thumbnail",
+                PresetThumbnail {
+                    preset_path: job.preset_path,
+                    thumbnail_path: Some(job.output_path),
+                    error: None,
+                },
+            );
+            continue;
+        }
+
+        let result = match renderer.take() {
+            Some(r) => Ok(r),
+            None => ThumbnailRenderer::start(gpu),
+        }
+        .and_then(|mut r| {
+            let result = r.render(&job);
+            if result.is_ok() {
+                renderer = Some(r);
+            } else {
+                r.stop();
+            }
+            result
+        });
+        let thumbnail = result.unwrap_or_else(|e| {
+            warn!("No thumbnail of {}: {}", job.preset_path, e);
+            PresetThumbnail {
+                preset_path: job.preset_path.clone(),
+                thumbnail_path: None,
+                error: Some(e),
+            }
+        });
+        let _ = app.emit("preset_thumbnail", thumbnail);
+    }
+
+    if let Some(renderer) = renderer {
+        renderer.stop();
+    }
+}
+
+/// Queue thumbnails of presets for the library, as PNG (default) or WebM.
+/// Each is reported with a `preset_thumbnail` event: cached ones at once,
+/// the rest as they are rendered, about 3 seconds each. Returns how many
+/// were queued.
+#[tauri::command]
+fn generate_preset_thumbnails(
+    app: AppHandle,
+    state: State<'_, AppState>,
+    paths: Vec<String>,
+    format: Option<String>,
+) -> Result<usize, String> {
+    let format = match format {
+        Some(format) => format.parse::<ThumbnailFormat>()?,
+        None => ThumbnailFormat::default(),
+    };
+    let dir = thumbnail_dir(&app)?;
+    let gpu = *state.gpu_preference.lock().map_err(|e| e.to_string())?;
+
+    let mut queue = state.thumbnail_queue.lock().map_err(|e| e.to_string())?;
+    let mut queued = 0;
+    for preset_path in paths {
+        let output = thumbnail_path(&dir, std::path::Path::new(&preset_path), format);
+        if output.exists() {
+            let _ = app.emit(
+                "preset_thumbnail",
+                PresetThumbnail {
+                    preset_path,
+                    thumbnail_path: Some(output.to_string_lossy().to_string()),
+                    error: None,
+                },
+            );
+            continue;
+        }
+        let job = ThumbnailJob {
+            preset_path,
+            output_path: output.to_string_lossy().to_string(),
+        };
+        if !queue.pending.contains(&job) {
+            queue.pending.push_back(job);
+            queued += 1;
+        }
+    }
+
+    if !queue.pending.is_empty() && !queue.running {
+        let app = app.clone();
+        thread::Builder::new()
+            .name("thumbnails".to_string())
+            .spawn(move || run_thumbnail_worker(app, gpu))
+            .map_err(|e| format!("Failed to start thumbnail worker: {}", e))?;
+        queue.running = true;
+    }
+    Ok(queued)
+}
+
+/// Read a thumbnail reported by `preset_thumbnail` so the library can show it.
+/// Only files in the thumbnail cache are served.
+#[tauri::command]
+fn read_preset_thumbnail(app: AppHandle, path: String) -> Result<tauri::ipc::Response, String> {
+    let dir = thumbnail_dir(&app)?.canonicalize().map_err(|e| e.to_string())?;
+    let path = std::path::Path::new(&path).canonicalize().map_err(|e| e.to_string())?;
+    if !path.starts_with(&dir) {
+        return Err("Not a preset thumbnail".to_string());
+    }
+    std::fs::read(&path)
+        .map(tauri::ipc::Response::new)
+        .map_err(|e| format!("Failed to read thumbnail: {}", e))
+}
+
+/// Whether the compositor window is open
+fn compositor_running(state: &AppState) -> bool {
+    state.compositor_renderer
+        .lock()
+        .ok()
+        .is_some_and(|mut c| c.as_mut().is_some_and(|renderer| renderer.is_running()))
 }
 
 /// Stop visualization on a specific deck
@@ -808,14 +2752,25 @@ fn stop_deck(state: State<'_, AppState>, deck_id: Option<u8>) -> Result<String,
 
     let mut decks_guard = state.decks.lock().map_err(|e| e.to_string())?;
     let deck = decks_guard.get_mut(&deck_id).ok_or("Deck not found")?;
+    stop_deck_renderer(deck);
+
+    Ok(format!("Deck {} stopped", deck_id))
+}
 
+/// Stop the renderer of a deck
+fn stop_deck_renderer(deck: &mut DeckState) {
     if let Some(ref mut renderer) = deck.renderer {
         renderer.stop();
     }
+    deck.remember_geometry();
     deck.renderer = None;
     deck.active = false;
-
-    Ok(format!("Deck {} stopped", deck_id))
+    deck.restart_attempts = 0;
+    deck.restart_at = None;
+    // A new renderer starts without effects, strobe or freeze
+    deck.effects = EffectChain::default();
+    deck.strobing = false;
+    deck.frozen = false;
 }
 
 /// Load a preset on a specific deck
@@ -863,52 +2818,378 @@ fn load_preset(
     Err(format!("Deck {} not running", deck_id))
 }
 
-/// Set beat sensitivity on a specific deck
+/// Longest preset crossfade the renderer accepts, in seconds
+const MAX_TRANSITION_SECS: f64 = 30.0;
+
+/// Set how long a deck crossfades between presets (0 cuts)
 #[tauri::command]
-fn set_beat_sensitivity(
+fn set_deck_transition_time(
     state: State<'_, AppState>,
-    sensitivity: f32,
-    deck_id: Option<u8>,
+    deck_id: u8,
+    seconds: f64,
 ) -> Result<String, String> {
-    let deck_id = deck_id.unwrap_or(0);
     if deck_id >= MAX_DECKS {
         return Err(format!("Invalid deck ID: {}", deck_id));
     }
+    if !(0.0..=MAX_TRANSITION_SECS).contains(&seconds) {
+        return Err(format!(
+            "Transition time must be between 0 and {} seconds",
+            MAX_TRANSITION_SECS
+        ));
+    }
 
     let mut decks_guard = state.decks.lock().map_err(|e| e.to_string())?;
     let deck = decks_guard.get_mut(&deck_id).ok_or("Deck not found")?;
 
     if let Some(ref mut renderer) = deck.renderer {
         if renderer.is_running() {
-            renderer.send_command(&RendererCommand::SetBeatSensitivity { value: sensitivity })?;
-            deck.beat_sensitivity = sensitivity;
-            return Ok(format!("Deck {} beat sensitivity set to {}", deck_id, sensitivity));
+            renderer.send_command(&RendererCommand::SetTransitionTime { seconds })?;
+            return Ok(format!("Deck {} preset transition set to {}s", deck_id, seconds));
         }
     }
 
     Err(format!("Deck {} not running", deck_id))
 }
 
-/// Set volume on a specific deck (0.0 to 1.0)
+/// Set when projectM asks for the next preset on its own: after a preset
+/// has played for a while, or on loud beats. The deck's playlist answers
+/// with the preset. Kept for the deck's next start.
 #[tauri::command]
-fn set_deck_volume(
-    state: State<'_, AppState>,
-    volume: f32,
-    deck_id: Option<u8>,
-) -> Result<String, String> {
-    let deck_id = deck_id.unwrap_or(0);
+fn set_deck_preset_cuts(state: State<'_, AppState>, deck_id: u8, cuts: PresetCuts) -> Result<String, String> {
     if deck_id >= MAX_DECKS {
         return Err(format!("Invalid deck ID: {}", deck_id));
     }
+    cuts.validate().map_err(|e| e.to_string())?;
 
     let mut decks_guard = state.decks.lock().map_err(|e| e.to_string())?;
     let deck = decks_guard.get_mut(&deck_id).ok_or("Deck not found")?;
 
-    deck.volume = volume.clamp(0.0, 1.0);
-    Ok(format!("Deck {} volume set to {}", deck_id, deck.volume))
-}
-
-/// Toggle fullscreen on a specific deck
+    if let Some(ref mut renderer) = deck.renderer {
+        if renderer.is_running() {
+            renderer.send_command(&RendererCommand::SetPresetCuts { cuts })?;
+        }
+    }
+    deck.preset_cuts = cuts;
+    Ok(format!(
+        "Deck {} preset duration {}s, hard cuts {}",
+        deck_id,
+        cuts.preset_duration,
+        if cuts.hard_cut_enabled { "on" } else { "off" }
+    ))
+}
+
+/// Set beat sensitivity on a specific deck
+#[tauri::command]
+fn set_beat_sensitivity(
+    state: State<'_, AppState>,
+    sensitivity: f32,
+    deck_id: Option<u8>,
+) -> Result<String, String> {
+    let deck_id = deck_id.unwrap_or(0);
+    if deck_id >= MAX_DECKS {
+        return Err(format!("Invalid deck ID: {}", deck_id));
+    }
+
+    let mut decks_guard = state.decks.lock().map_err(|e| e.to_string())?;
+    let deck = decks_guard.get_mut(&deck_id).ok_or("Deck not found")?;
+
+    apply_beat_sensitivity(deck, sensitivity)?;
+    Ok(format!("Deck {} beat sensitivity set to {}", deck_id, sensitivity))
+}
+
+/// Send a beat sensitivity to a running deck's renderer
+fn apply_beat_sensitivity(deck: &mut DeckState, sensitivity: f32) -> Result<(), String> {
+    if let Some(ref mut renderer) = deck.renderer {
+        if renderer.is_running() {
+            renderer.send_command(&RendererCommand::SetBeatSensitivity { value: sensitivity })?;
+            deck.beat_sensitivity = sensitivity;
+            return Ok(());
+        }
+    }
+
+    Err(format!("Deck {} not running", deck.id))
+}
+
+/// Set a post-processing effect's intensity on a deck (0 turns it off)
+#[tauri::command]
+fn set_deck_effect(
+    state: State<'_, AppState>,
+    deck_id: u8,
+    effect: Effect,
+    intensity: f32,
+) -> Result<String, String> {
+    if deck_id >= MAX_DECKS {
+        return Err(format!("Invalid deck ID: {}", deck_id));
+    }
+    if !(0.0..=1.0).contains(&intensity) {
+        return Err("Effect intensity must be between 0 and 1".to_string());
+    }
+
+    let mut decks_guard = state.decks.lock().map_err(|e| e.to_string())?;
+    let deck = decks_guard.get_mut(&deck_id).ok_or("Deck not found")?;
+
+    apply_deck_effect(deck, effect, intensity)?;
+    Ok(format!("Deck {} {} set to {:.2}", deck_id, effect, intensity))
+}
+
+/// Send an effect intensity to a running deck's renderer
+fn apply_deck_effect(deck: &mut DeckState, effect: Effect, intensity: f32) -> Result<(), String> {
+    if let Some(ref mut renderer) = deck.renderer {
+        if renderer.is_running() {
+            renderer.send_command(&RendererCommand::SetEffect { effect, intensity })?;
+            deck.effects.set(effect, intensity);
+            return Ok(());
+        }
+    }
+
+    Err(format!("Deck {} not running", deck.id))
+}
+
+/// Fade a deck's brightness, contrast and saturation (0-2, 1 leaves the
+/// picture as rendered) over `fade_secs`, 0 by default. Kept for the deck's
+/// next start.
+#[tauri::command]
+fn set_deck_color_adjust(
+    state: State<'_, AppState>,
+    deck_id: u8,
+    adjust: ColorAdjust,
+    fade_secs: Option<f64>,
+) -> Result<String, String> {
+    if deck_id >= MAX_DECKS {
+        return Err(format!("Invalid deck ID: {}", deck_id));
+    }
+    adjust.validate().map_err(|e| e.to_string())?;
+    let fade_secs = fade_secs.unwrap_or(0.0);
+    if !(0.0..=MAX_COLOR_FADE_SECS).contains(&fade_secs) {
+        return Err(format!("Fade must be between 0 and {} seconds", MAX_COLOR_FADE_SECS));
+    }
+
+    let mut decks_guard = state.decks.lock().map_err(|e| e.to_string())?;
+    let deck = decks_guard.get_mut(&deck_id).ok_or("Deck not found")?;
+
+    apply_color_adjust(deck, adjust, fade_secs)?;
+    Ok(format!(
+        "Deck {} brightness {:.2}, contrast {:.2}, saturation {:.2}",
+        deck_id, adjust.brightness, adjust.contrast, adjust.saturation
+    ))
+}
+
+/// Keep a deck's colour correction and send it to its renderer if running
+fn apply_color_adjust(deck: &mut DeckState, adjust: ColorAdjust, fade_secs: f64) -> Result<(), String> {
+    if let Some(ref mut renderer) = deck.renderer {
+        if renderer.is_running() {
+            renderer.send_command(&RendererCommand::SetColorAdjust { adjust, fade_secs })?;
+        }
+    }
+    deck.color_adjust = adjust;
+    Ok(())
+}
+
+/// Fade a deck to black, or back, over `fade_secs` (0 by default cuts)
+/// without stopping it. Kept for the deck's next start.
+#[tauri::command]
+fn blackout(
+    state: State<'_, AppState>,
+    deck_id: u8,
+    enabled: bool,
+    fade_secs: Option<f64>,
+) -> Result<String, String> {
+    if deck_id >= MAX_DECKS {
+        return Err(format!("Invalid deck ID: {}", deck_id));
+    }
+    let fade_secs = fade_secs.unwrap_or(0.0);
+    if !(0.0..=MAX_BLACKOUT_FADE_SECS).contains(&fade_secs) {
+        return Err(format!("Fade must be between 0 and {} seconds", MAX_BLACKOUT_FADE_SECS));
+    }
+
+    let mut decks_guard = state.decks.lock().map_err(|e| e.to_string())?;
+    let deck = decks_guard.get_mut(&deck_id).ok_or("Deck not found")?;
+
+    apply_blackout(deck, enabled, fade_secs)?;
+    Ok(format!("Deck {} blackout {}", deck_id, if enabled { "on" } else { "off" }))
+}
+
+/// Keep a deck's blackout and send it to its renderer if running
+fn apply_blackout(deck: &mut DeckState, enabled: bool, fade_secs: f64) -> Result<(), String> {
+    if let Some(ref mut renderer) = deck.renderer {
+        if renderer.is_running() {
+            renderer.send_command(&RendererCommand::SetBlackout { enabled, fade_secs })?;
+        }
+    }
+    deck.blackout = enabled;
+    Ok(())
+}
+
+/// Hold a deck on its last frame, or play on. Outputs, effects and
+/// overlays keep running over the held frame, so a deck can be frozen
+/// before cutting away from it.
+#[tauri::command]
+fn freeze(state: State<'_, AppState>, deck_id: u8, enabled: bool) -> Result<String, String> {
+    if deck_id >= MAX_DECKS {
+        return Err(format!("Invalid deck ID: {}", deck_id));
+    }
+
+    let mut decks_guard = state.decks.lock().map_err(|e| e.to_string())?;
+    let deck = decks_guard.get_mut(&deck_id).ok_or("Deck not found")?;
+
+    apply_freeze(deck, enabled)?;
+    Ok(format!("Deck {} {}", deck_id, if enabled { "frozen" } else { "playing" }))
+}
+
+/// Freeze or unfreeze a running deck
+fn apply_freeze(deck: &mut DeckState, enabled: bool) -> Result<(), String> {
+    let renderer = deck
+        .renderer
+        .as_mut()
+        .filter(|r| r.running)
+        .ok_or_else(|| format!("Deck {} is not running", deck.id))?;
+    renderer.send_command(&RendererCommand::SetFreeze { enabled })?;
+    deck.frozen = enabled;
+    Ok(())
+}
+
+/// Play a deck's presets slower or faster; 1 is normal speed. Kept for the
+/// deck's next start.
+#[tauri::command]
+fn set_deck_time_scale(state: State<'_, AppState>, deck_id: u8, scale: f64) -> Result<String, String> {
+    if deck_id >= MAX_DECKS {
+        return Err(format!("Invalid deck ID: {}", deck_id));
+    }
+    validate_time_scale(scale).map_err(|e| e.to_string())?;
+
+    let mut decks_guard = state.decks.lock().map_err(|e| e.to_string())?;
+    let deck = decks_guard.get_mut(&deck_id).ok_or("Deck not found")?;
+
+    apply_time_scale(deck, scale)?;
+    Ok(format!("Deck {} playback speed {:.2}x", deck_id, scale))
+}
+
+/// Keep a deck's playback speed and send it to its renderer if running
+fn apply_time_scale(deck: &mut DeckState, scale: f64) -> Result<(), String> {
+    if let Some(ref mut renderer) = deck.renderer {
+        if renderer.is_running() {
+            renderer.send_command(&RendererCommand::SetTimeScale { scale })?;
+        }
+    }
+    deck.time_scale = scale;
+    Ok(())
+}
+
+/// Turn a deck's beat-synced strobe on or off, optionally changing its rate
+/// and brightness (kept for the next time it is turned on)
+#[tauri::command]
+fn set_deck_strobe(
+    state: State<'_, AppState>,
+    deck_id: u8,
+    enabled: bool,
+    strobe: Option<StrobeConfig>,
+) -> Result<String, String> {
+    if deck_id >= MAX_DECKS {
+        return Err(format!("Invalid deck ID: {}", deck_id));
+    }
+    if let Some(ref strobe) = strobe {
+        strobe.validate().map_err(|e| e.to_string())?;
+    }
+
+    let mut decks_guard = state.decks.lock().map_err(|e| e.to_string())?;
+    let deck = decks_guard.get_mut(&deck_id).ok_or("Deck not found")?;
+
+    if let Some(strobe) = strobe {
+        deck.strobe = strobe;
+    }
+    apply_strobe(deck, enabled)?;
+    Ok(format!("Deck {} strobe {}", deck_id, if enabled { "on" } else { "off" }))
+}
+
+/// Start or stop the strobe on a running deck's renderer
+fn apply_strobe(deck: &mut DeckState, enabled: bool) -> Result<(), String> {
+    if let Some(ref mut renderer) = deck.renderer {
+        if renderer.is_running() {
+            let strobe = enabled.then_some(deck.strobe);
+            renderer.send_command(&RendererCommand::SetStrobe { strobe })?;
+            deck.strobing = enabled;
+            return Ok(());
+        }
+    }
+
+    Err(format!("Deck {} not running", deck.id))
+}
+
+/// Set volume on a specific deck (0.0 to 1.0)
+#[tauri::command]
+fn set_deck_volume(
+    state: State<'_, AppState>,
+    volume: f32,
+    deck_id: Option<u8>,
+) -> Result<String, String> {
+    let deck_id = deck_id.unwrap_or(0);
+    if deck_id >= MAX_DECKS {
+        return Err(format!("Invalid deck ID: {}", deck_id));
+    }
+
+    let mut decks_guard = state.decks.lock().map_err(|e| e.to_string())?;
+    let deck = decks_guard.get_mut(&deck_id).ok_or("Deck not found")?;
+
+    deck.volume = volume.clamp(0.0, 1.0);
+    Ok(format!("Deck {} volume set to {}", deck_id, deck.volume))
+}
+
+/// Set the input gain trim on a specific deck (0.0 to 4.0, applied before the crossfader)
+#[tauri::command]
+fn set_deck_gain(
+    state: State<'_, AppState>,
+    gain: f32,
+    deck_id: Option<u8>,
+) -> Result<String, String> {
+    let deck_id = deck_id.unwrap_or(0);
+    if deck_id >= MAX_DECKS {
+        return Err(format!("Invalid deck ID: {}", deck_id));
+    }
+
+    let mut decks_guard = state.decks.lock().map_err(|e| e.to_string())?;
+    let deck = decks_guard.get_mut(&deck_id).ok_or("Deck not found")?;
+
+    deck.gain = gain.clamp(0.0, MAX_DECK_GAIN);
+    Ok(format!("Deck {} gain set to {}", deck_id, deck.gain))
+}
+
+/// Enable or disable automatic gain control on a specific deck
+///
+/// Parameters left unset keep their current values.
+#[tauri::command]
+fn set_deck_agc(
+    state: State<'_, AppState>,
+    enabled: bool,
+    deck_id: Option<u8>,
+    target_level: Option<f32>,
+    attack_ms: Option<f32>,
+    release_ms: Option<f32>,
+    max_gain: Option<f32>,
+) -> Result<AgcConfig, String> {
+    let deck_id = deck_id.unwrap_or(0);
+    if deck_id >= MAX_DECKS {
+        return Err(format!("Invalid deck ID: {}", deck_id));
+    }
+
+    let mut decks_guard = state.decks.lock().map_err(|e| e.to_string())?;
+    let deck = decks_guard.get_mut(&deck_id).ok_or("Deck not found")?;
+
+    let current = deck.agc.config();
+    deck.agc.set_config(AgcConfig {
+        target_level: target_level.unwrap_or(current.target_level),
+        attack_secs: attack_ms.map_or(current.attack_secs, |ms| ms / 1000.0),
+        release_secs: release_ms.map_or(current.release_secs, |ms| ms / 1000.0),
+        max_gain: max_gain.unwrap_or(current.max_gain),
+    });
+    if enabled && !deck.agc_enabled {
+        deck.agc.reset();
+    }
+    deck.agc_enabled = enabled;
+
+    Ok(deck.agc.config())
+}
+
+/// Toggle fullscreen on a specific deck
 #[tauri::command]
 fn toggle_fullscreen(state: State<'_, AppState>, deck_id: Option<u8>) -> Result<String, String> {
     let deck_id = deck_id.unwrap_or(0);
@@ -919,14 +3200,94 @@ fn toggle_fullscreen(state: State<'_, AppState>, deck_id: Option<u8>) -> Result<
     let mut decks_guard = state.decks.lock().map_err(|e| e.to_string())?;
     let deck = decks_guard.get_mut(&deck_id).ok_or("Deck not found")?;
 
+    toggle_deck_fullscreen(deck)?;
+    Ok(format!("Deck {} fullscreen toggled", deck_id))
+}
+
+/// Toggle fullscreen on a running deck's renderer
+fn toggle_deck_fullscreen(deck: &mut DeckState) -> Result<(), String> {
     if let Some(ref mut renderer) = deck.renderer {
         if renderer.is_running() {
             renderer.send_command(&RendererCommand::ToggleFullscreen)?;
-            return Ok(format!("Deck {} fullscreen toggled", deck_id));
+            deck.restore.fullscreen = !deck.restore.fullscreen;
+            return Ok(());
         }
     }
 
-    Err(format!("Deck {} not running", deck_id))
+    Err(format!("Deck {} not running", deck.id))
+}
+
+/// Float a deck's window above other apps and/or let clicks through it, e.g.
+/// over a screenshared app. Kept for the deck's next start.
+#[tauri::command]
+fn set_deck_window_options(
+    state: State<'_, AppState>,
+    deck_id: u8,
+    always_on_top: Option<bool>,
+    click_through: Option<bool>,
+) -> Result<String, String> {
+    if deck_id >= MAX_DECKS {
+        return Err(format!("Invalid deck ID: {}", deck_id));
+    }
+
+    let mut decks_guard = state.decks.lock().map_err(|e| e.to_string())?;
+    let deck = decks_guard.get_mut(&deck_id).ok_or("Deck not found")?;
+
+    if let Some(ref mut renderer) = deck.renderer {
+        if renderer.is_running() {
+            if let Some(enabled) = always_on_top {
+                renderer.send_command(&RendererCommand::SetAlwaysOnTop { enabled })?;
+            }
+            if let Some(enabled) = click_through {
+                renderer.send_command(&RendererCommand::SetClickThrough { enabled })?;
+            }
+        }
+    }
+    if let Some(enabled) = always_on_top {
+        deck.always_on_top = enabled;
+    }
+    if let Some(enabled) = click_through {
+        deck.click_through = enabled;
+    }
+    Ok(format!(
+        "Deck {} window: always on top {}, click-through {}",
+        deck_id, deck.always_on_top, deck.click_through
+    ))
+}
+
+/// Move and/or resize a deck's window, in logical pixels. Kept for the
+/// deck's next start.
+#[tauri::command]
+fn set_deck_window_geometry(
+    state: State<'_, AppState>,
+    deck_id: u8,
+    x: Option<i32>,
+    y: Option<i32>,
+    width: Option<u32>,
+    height: Option<u32>,
+) -> Result<String, String> {
+    if deck_id >= MAX_DECKS {
+        return Err(format!("Invalid deck ID: {}", deck_id));
+    }
+    if width == Some(0) || height == Some(0) {
+        return Err("Window width and height must be at least 1".to_string());
+    }
+
+    let mut decks_guard = state.decks.lock().map_err(|e| e.to_string())?;
+    let deck = decks_guard.get_mut(&deck_id).ok_or("Deck not found")?;
+
+    deck.remember_geometry();
+    if let Some(ref mut renderer) = deck.renderer {
+        if renderer.is_running() {
+            renderer.send_command(&RendererCommand::SetWindowGeometry { x, y, width, height })?;
+        }
+    }
+    let geometry = WindowGeometry::merged(deck.window_geometry, x, y, width, height);
+    deck.window_geometry = Some(geometry);
+    Ok(format!(
+        "Deck {} window: {}x{} at {:?},{:?}",
+        deck_id, geometry.width, geometry.height, geometry.x, geometry.y
+    ))
 }
 
 /// Get the renderer executable name for the current platform
@@ -971,14 +3332,93 @@ fn target_triple() -> &'static str {
 }
 
 /// Find the renderer executable
-fn find_renderer_executable() -> Result<String, String> {
-    let exe_name = renderer_executable_name();
-    let triple = target_triple();
-
-    // Tauri sidecar name with target triple (e.g., opendrop-renderer-x86_64-pc-windows-msvc.exe)
+/// Command for a renderer process, steered to the preferred GPU
+fn renderer_command(renderer_path: &str, gpu: GpuPreference) -> Command {
+    let mut command = Command::new(renderer_path);
+    #[cfg(target_os = "linux")]
+    {
+        let nvidia = std::path::Path::new("/proc/driver/nvidia/version").exists();
+        command.envs(gpu.linux_env(nvidia));
+    }
     #[cfg(target_os = "windows")]
-    let sidecar_name = format!("opendrop-renderer-{}.exe", triple);
-    #[cfg(not(target_os = "windows"))]
+    set_windows_gpu_preference(renderer_path, gpu);
+    #[cfg(not(any(target_os = "linux", target_os = "windows")))]
+    let _ = gpu;
+    command
+}
+
+/// Set (or clear, for the default) the renderer's per-app graphics
+/// preference, as the Windows graphics settings page does
+#[cfg(target_os = "windows")]
+fn set_windows_gpu_preference(renderer_path: &str, gpu: GpuPreference) {
+    use windows::core::HSTRING;
+    use windows::Win32::System::Registry::{RegDeleteKeyValueW, RegSetKeyValueW, HKEY_CURRENT_USER, REG_SZ};
+
+    let key = HSTRING::from(r"Software\Microsoft\DirectX\UserGpuPreferences");
+    let name = HSTRING::from(renderer_path);
+    match gpu.windows_value() {
+        Some(value) => {
+            let data: Vec<u16> = value.encode_utf16().chain(std::iter::once(0)).collect();
+            let result = unsafe {
+                RegSetKeyValueW(
+                    HKEY_CURRENT_USER,
+                    &key,
+                    &name,
+                    REG_SZ.0,
+                    Some(data.as_ptr() as *const _),
+                    (data.len() * std::mem::size_of::<u16>()) as u32,
+                )
+            };
+            if let Err(e) = result.ok() {
+                warn!("Failed to set the renderer's GPU preference: {}", e);
+            }
+        }
+        // Missing already is fine
+        None => unsafe {
+            let _ = RegDeleteKeyValueW(HKEY_CURRENT_USER, &key, &name);
+        },
+    }
+}
+
+/// Choose the GPU renderers ask for; applies to renderers started from now
+/// on (Linux and Windows)
+#[tauri::command]
+fn set_gpu_preference(state: State<'_, AppState>, preference: GpuPreference) -> Result<String, String> {
+    *state.gpu_preference.lock().map_err(|e| e.to_string())? = preference;
+    Ok(format!("GPU preference set to {:?}; restart decks to apply", preference))
+}
+
+/// Get the GPU renderers ask for
+#[tauri::command]
+fn get_gpu_preference(state: State<'_, AppState>) -> Result<GpuPreference, String> {
+    Ok(*state.gpu_preference.lock().map_err(|e| e.to_string())?)
+}
+
+/// Open deck windows in one shared renderer process instead of a process
+/// per deck; applies to decks started from now on
+#[tauri::command]
+fn set_multi_deck_renderer(state: State<'_, AppState>, enabled: bool) -> Result<String, String> {
+    *state.multi_deck_renderer.lock().map_err(|e| e.to_string())? = enabled;
+    Ok(format!(
+        "Decks start in {}; restart decks to apply",
+        if enabled { "one shared renderer" } else { "a renderer each" }
+    ))
+}
+
+/// Whether deck windows open in one shared renderer
+#[tauri::command]
+fn get_multi_deck_renderer(state: State<'_, AppState>) -> Result<bool, String> {
+    Ok(*state.multi_deck_renderer.lock().map_err(|e| e.to_string())?)
+}
+
+fn find_renderer_executable() -> Result<String, String> {
+    let exe_name = renderer_executable_name();
+    let triple = target_triple();
+
+    // Tauri sidecar name with target triple (e.g., opendrop-renderer-x86_64-pc-windows-msvc.exe)
+    #[cfg(target_os = "windows")]
+    let sidecar_name = format!("opendrop-renderer-{}.exe", triple);
+    #[cfg(not(target_os = "windows"))]
     let sidecar_name = format!("opendrop-renderer-{}", triple);
 
     let mut candidates: Vec<std::path::PathBuf> = Vec::new();
@@ -1335,6 +3775,8 @@ fn list_audio_devices() -> Vec<AudioDeviceInfo> {
 fn start_audio(
     state: State<'_, AppState>,
     device_name: Option<String>,
+    buffer_size: Option<usize>,
+    max_latency_ms: Option<u32>,
 ) -> Result<String, String> {
     let mut audio_guard = state.audio_engine.lock().map_err(|e| e.to_string())?;
 
@@ -1342,29 +3784,96 @@ fn start_audio(
         return Err("Audio already running".to_string());
     }
 
+    let config = audio_config(buffer_size, max_latency_ms);
     let config = AudioConfig {
         device_name,
-        ..Default::default()
+        ..config
     };
 
     audio_guard.start(config).map_err(|e| e.to_string())?;
+    state.audio_latency.lock().map_err(|e| e.to_string())?.clear();
+    state.level_meter.lock().map_err(|e| e.to_string())?.reset();
+    state.energy.lock().map_err(|e| e.to_string())?.reset();
 
     Ok("Audio capture started".to_string())
 }
 
+/// Start audio capture from several devices mixed into one stream
+#[tauri::command]
+fn start_audio_multi(
+    state: State<'_, AppState>,
+    sources: Vec<AudioSource>,
+    buffer_size: Option<usize>,
+    max_latency_ms: Option<u32>,
+) -> Result<String, String> {
+    let mut audio_guard = state.audio_engine.lock().map_err(|e| e.to_string())?;
+
+    if audio_guard.is_running() {
+        return Err("Audio already running".to_string());
+    }
+
+    let count = sources.len();
+    audio_guard
+        .start_multi(audio_config(buffer_size, max_latency_ms), sources)
+        .map_err(|e| e.to_string())?;
+    state.audio_latency.lock().map_err(|e| e.to_string())?.clear();
+    state.level_meter.lock().map_err(|e| e.to_string())?.reset();
+    state.energy.lock().map_err(|e| e.to_string())?.reset();
+
+    Ok(format!("Audio capture started ({} sources)", count))
+}
+
+/// Switch the capture device while audio keeps running (crossfades to the new device)
+#[tauri::command]
+fn switch_audio_device(
+    state: State<'_, AppState>,
+    device_name: Option<String>,
+) -> Result<String, String> {
+    let mut audio_guard = state.audio_engine.lock().map_err(|e| e.to_string())?;
+    audio_guard
+        .switch_device(device_name.clone())
+        .map_err(|e| e.to_string())?;
+    Ok(format!(
+        "Switching audio to {}",
+        device_name.as_deref().unwrap_or("default device")
+    ))
+}
+
+/// Capture config with optional buffer size (frames) and latency overrides
+fn audio_config(buffer_size: Option<usize>, max_latency_ms: Option<u32>) -> AudioConfig {
+    let defaults = AudioConfig::default();
+    AudioConfig {
+        buffer_size: buffer_size.unwrap_or(defaults.buffer_size),
+        max_latency_ms: max_latency_ms.unwrap_or(defaults.max_latency_ms).max(1),
+        ..defaults
+    }
+}
+
 /// Stop audio capture
 #[tauri::command]
 fn stop_audio(state: State<'_, AppState>) -> Result<String, String> {
     let mut audio_guard = state.audio_engine.lock().map_err(|e| e.to_string())?;
     audio_guard.stop();
+    if let Ok(mut detector) = state.beat_detector.lock() {
+        detector.reset();
+    }
+    if let Ok(mut spectrum) = state.spectrum.lock() {
+        spectrum.clear();
+    }
+    if let Ok(mut waveform) = state.waveform.lock() {
+        waveform.clear();
+    }
+    if let Ok(mut silence) = state.silence_detector.lock() {
+        silence.reset();
+    }
     Ok("Audio capture stopped".to_string())
 }
 
 /// Get status for all decks
 #[tauri::command]
 fn get_multi_deck_status(state: State<'_, AppState>) -> Result<MultiDeckStatus, String> {
-    let mut decks_guard = state.decks.lock().map_err(|e| e.to_string())?;
     let audio_guard = state.audio_engine.lock().map_err(|e| e.to_string())?;
+    let mut decks_guard = state.decks.lock().map_err(|e| e.to_string())?;
     let crossfader_guard = state.crossfader.lock().map_err(|e| e.to_string())?;
     let compositor_guard = state.compositor.lock().map_err(|e| e.to_string())?;
 
@@ -1372,14 +3881,18 @@ fn get_multi_deck_status(state: State<'_, AppState>) -> Result<MultiDeckStatus,
     for id in 0..MAX_DECKS {
         if let Some(deck) = decks_guard.get_mut(&id) {
             // Get health info from renderer if available
-            let (health, uptime, crashes) = if let Some(ref renderer) = deck.renderer {
+            let (health, uptime, crashes, recording, frame_export, loop_export, stats) = if let Some(ref renderer) = deck.renderer {
                 (
                     Some(renderer.get_health()),
                     Some(renderer.uptime_secs()),
                     Some(renderer.crash_count),
+                    renderer.recording(),
+                    renderer.frame_export(),
+                    renderer.loop_export(),
+                    renderer.stats(),
                 )
             } else {
-                (None, None, None)
+                (None, None, None, None, None, None, None)
             };
 
             deck_infos.push(DeckInfo {
@@ -1387,11 +3900,35 @@ fn get_multi_deck_status(state: State<'_, AppState>) -> Result<MultiDeckStatus,
                 running: deck.is_running(),
                 preset: deck.preset_path.clone(),
                 volume: deck.volume,
+                gain: deck.gain,
                 beat_sensitivity: deck.beat_sensitivity,
                 playlist: PlaylistInfo::from(&deck.playlist),
                 health,
                 uptime_secs: uptime,
                 crash_count: crashes,
+                recording,
+                frame_export,
+                loop_export,
+                stats,
+                gpu: deck.renderer.as_ref().and_then(|r| r.gpu()),
+                quality: deck.quality,
+                fps_cap: deck.fps_cap,
+                background: deck.background.clone(),
+                color_adjust: deck.color_adjust,
+                blackout: deck.blackout,
+                frozen: deck.frozen,
+                time_scale: deck.time_scale,
+                strobe: deck.strobe,
+                strobing: deck.strobing,
+                preset_locked: deck.preset_locked,
+                preset_cuts: deck.preset_cuts,
+                always_on_top: deck.always_on_top,
+                click_through: deck.click_through,
+                window_geometry: deck
+                    .renderer
+                    .as_ref()
+                    .and_then(|r| r.geometry())
+                    .or(deck.window_geometry),
             });
         }
     }
@@ -1401,15 +3938,18 @@ fn get_multi_deck_status(state: State<'_, AppState>) -> Result<MultiDeckStatus,
         audio_running: audio_guard.is_running(),
         preset_dir: get_preset_dir(),
         crossfader: CrossfaderInfo::from(&*crossfader_guard),
-        compositor: CompositorInfo::from(&*compositor_guard),
+        compositor: CompositorInfo {
+            running: compositor_running(&state),
+            ..CompositorInfo::from(&*compositor_guard)
+        },
     })
 }
 
 /// Get status (backward compatible - returns deck 0 status)
 #[tauri::command]
 fn get_status(state: State<'_, AppState>) -> Result<VisualizerStatus, String> {
-    let mut decks_guard = state.decks.lock().map_err(|e| e.to_string())?;
     let audio_guard = state.audio_engine.lock().map_err(|e| e.to_string())?;
+    let mut decks_guard = state.decks.lock().map_err(|e| e.to_string())?;
 
     let deck = decks_guard.get_mut(&0).ok_or("Deck 0 not found")?;
 
@@ -1631,7 +4171,9 @@ fn import_playlist(
         deck.playlist.name = imported.name;
         deck.playlist.shuffle = imported.shuffle;
         deck.playlist.auto_cycle = imported.auto_cycle;
-        deck.playlist.cycle_duration_secs = imported.cycle_duration_secs;
+        deck.playlist.cycle_on = imported.cycle_duration_secs.map_or(imported.cycle_on, CycleOn::Seconds);
+        deck.playlist.energy_match = imported.energy_match;
+        deck.last_cycle_beat = None;
     }
 
     let added = deck.playlist.items.len() - initial_count;
@@ -1648,71 +4190,290 @@ pub struct ImportResult {
 }
 
 /// Pump audio from capture to all active decks + handle auto-cycle
+///
+/// The backend pump thread already does this continuously; the command is kept
+/// for frontends that still poll it.
 #[tauri::command]
-fn pump_audio(state: State<'_, AppState>) -> Result<u32, String> {
-    let audio_guard = state.audio_engine.lock().map_err(|e| e.to_string())?;
+fn pump_audio(app: AppHandle, state: State<'_, AppState>) -> Result<u32, String> {
+    pump_audio_frame(&app, &state)
+}
+
+/// Distribute pending audio to decks, run analysis and advance auto-cycle
+fn pump_audio_frame(app: &AppHandle, state: &AppState) -> Result<u32, String> {
+    let mut audio_guard = state.audio_engine.lock().map_err(|e| e.to_string())?;
     let mut decks_guard = state.decks.lock().map_err(|e| e.to_string())?;
     let crossfader_guard = state.crossfader.lock().map_err(|e| e.to_string())?;
 
     let mut total_samples_sent = 0u32;
     let now = std::time::Instant::now();
 
+    // Fall back from (and later return to) capture devices that disappeared
+    for event in audio_guard.poll_device_events() {
+        match event {
+            DeviceEvent::Lost {
+                device,
+                reason,
+                fallback,
+            } => {
+                warn!("Audio device {} lost: {}", device, reason);
+                let _ = app.emit(
+                    "audio_device_lost",
+                    AudioDeviceLostEvent {
+                        device,
+                        reason,
+                        fallback,
+                    },
+                );
+            }
+            DeviceEvent::Reconnected { device } => {
+                info!("Audio device {} reconnected", device);
+                let _ = app.emit("audio_device_reconnected", device);
+            }
+            DeviceEvent::Failed { device, reason } => {
+                let _ = app.emit("audio_capture_error", AudioCaptureErrorEvent { device, reason });
+            }
+        }
+    }
+
     // Collect all audio samples first
     let mut all_samples: Vec<Vec<f32>> = Vec::new();
-    while let Some(samples) = audio_guard.try_recv() {
+    let mut published: Vec<std::time::Instant> = Vec::new();
+    while let Some((samples, published_at)) = audio_guard.try_recv_timed() {
         all_samples.push(samples);
+        published.push(published_at);
+    }
+    let mut latency = state.audio_latency.lock().map_err(|e| e.to_string())?;
+    let read_at = std::time::Instant::now();
+    for published_at in &published {
+        latency.read.record(read_at.duration_since(*published_at));
     }
 
-    // Calculate RMS levels for VU meters from collected samples
+    // Meter levels, peaks and clipping for VU meters from collected samples
     if !all_samples.is_empty() {
-        let mut sum_l = 0.0f32;
-        let mut sum_r = 0.0f32;
-        let mut count = 0usize;
+        if let Ok(mut meter) = state.level_meter.lock() {
+            meter.set_sample_rate(audio_guard.sample_rate());
+            for samples in &all_samples {
+                meter.process(samples);
+            }
+        }
 
-        for samples in &all_samples {
-            // Samples are interleaved stereo: [L, R, L, R, ...]
-            for chunk in samples.chunks(2) {
-                if chunk.len() == 2 {
-                    sum_l += chunk[0] * chunk[0];
-                    sum_r += chunk[1] * chunk[1];
-                    count += 1;
-                }
+        // Track tempo and notify the frontend of onsets
+        if let Ok(mut detector) = state.beat_detector.lock() {
+            detector.set_format(audio_guard.sample_rate(), 2);
+            for samples in &all_samples {
+                detector.process(samples);
+            }
+            for onset in detector.take_onsets() {
+                let _ = app.emit("beat_detected", onset);
+            }
+        }
+
+        // Keep the latest window for get_audio_spectrum and publish band energy
+        if let Ok(mut spectrum) = state.spectrum.lock() {
+            spectrum.set_format(audio_guard.sample_rate(), 2);
+            for samples in &all_samples {
+                spectrum.push(samples);
+            }
+            let _ = app.emit("audio_bands", spectrum.energy());
+        }
+
+        if let Ok(mut waveform) = state.waveform.lock() {
+            waveform.set_format(audio_guard.sample_rate(), 2);
+            for samples in &all_samples {
+                waveform.push(samples);
+            }
+        }
+    }
+
+    // Advance the tempo clock even without audio (a manual tempo keeps running)
+    // and announce every beat of its grid
+    let mut tempo = TempoInfo::default();
+    let mut beat = false;
+    if let (Ok(detector), Ok(mut clock)) = (state.beat_detector.lock(), state.tempo_clock.lock()) {
+        beat = clock.update(detector.info(), now);
+        tempo = clock.info(now);
+        if beat {
+            let _ = app.emit("tempo_update", tempo);
+        }
+    }
+
+    // Publish clock, beats and preset changes on the MIDI output
+    if let Some(out) = state.midi_output.lock().map_err(|e| e.to_string())?.as_mut() {
+        out.tick(tempo.beat.bpm, now);
+        if beat {
+            out.send_event(OutputEvent::Beat);
+        }
+        for (id, deck) in decks_guard.iter() {
+            out.update_preset(*id, deck.preset_path.as_deref());
+        }
+    }
+
+    // Time strobe flashes by the tempo clock's beats
+    if beat {
+        for deck in decks_guard.values_mut().filter(|d| d.strobing) {
+            if let Some(ref mut renderer) = deck.renderer {
+                let _ = renderer.send_command(&RendererCommand::Beat { bpm: tempo.beat.bpm });
+            }
+        }
+    }
+
+    // Keep the tempo in NDI metadata current (the renderer adds the preset)
+    let bpm = Some(tempo.beat.bpm).filter(|bpm| *bpm > 0.0);
+    for deck in decks_guard.values_mut() {
+        if let Some(ref mut renderer) = deck.renderer {
+            if !renderer.ndi_enabled || !renderer.is_running() {
+                continue;
+            }
+            let changed = match (renderer.ndi_bpm, bpm) {
+                (Some(last), Some(bpm)) => (last - bpm).abs() >= 0.1,
+                (last, bpm) => last.is_some() != bpm.is_some(),
+            };
+            if changed && renderer.send_command(&RendererCommand::SetNdiMetadata { bpm }).is_ok() {
+                renderer.ndi_bpm = bpm;
             }
         }
+    }
 
-        if count > 0 {
-            let rms_l = (sum_l / count as f32).sqrt();
-            let rms_r = (sum_r / count as f32).sqrt();
-            // Store levels (clamped to 0-1)
-            if let Ok(mut levels) = state.audio_levels.lock() {
-                *levels = (rms_l.min(1.0), rms_r.min(1.0));
+    // Keep the compositor's layers in step with its settings and the
+    // crossfader, and point the decks it draws at it
+    let compositor_guard = state.compositor.lock().map_err(|e| e.to_string())?;
+    let mut layers = Vec::new();
+    let mut compositor_endpoint = None;
+    if let Some(ref mut compositor) = *state.compositor_renderer.lock().map_err(|e| e.to_string())? {
+        if compositor.is_running() {
+            layers = compositor_guard.layers(&crossfader_guard);
+            if layers != compositor.compositor_layers
+                && compositor
+                    .send_command(&RendererCommand::SetCompositorLayers { layers: layers.clone() })
+                    .is_ok()
+            {
+                compositor.compositor_layers = layers.clone();
+            }
+            compositor_endpoint = compositor.compositor_endpoint();
+        }
+    }
+    for (id, deck) in decks_guard.iter_mut() {
+        if let Some(ref mut renderer) = deck.renderer {
+            let endpoint = compositor_endpoint
+                .clone()
+                .filter(|_| layers.iter().any(|l| l.deck_id == *id));
+            if renderer.compositor_feed == endpoint || !renderer.is_running() {
+                continue;
+            }
+            let cmd = RendererCommand::SetCompositorFeed {
+                port: endpoint.as_ref().map(|e| e.port),
+                gpu_socket: endpoint.as_ref().and_then(|e| e.gpu_socket.clone()),
+            };
+            if renderer.send_command(&cmd).is_ok() {
+                renderer.compositor_feed = endpoint;
             }
         }
     }
 
+    // Watch for prolonged silence and switch decks in and out of idle
+    let idle_settings = state.idle_settings.lock().map_err(|e| e.to_string())?.clone();
+    let mut idle = false;
+    if let Ok(mut silence) = state.silence_detector.lock() {
+        silence.set_format(audio_guard.sample_rate(), 2);
+        let mut change = None;
+        for samples in &all_samples {
+            change = silence.process(samples).or(change);
+        }
+        idle = silence.is_idle();
+
+        if let Some(now_idle) = change {
+            info!("Audio {}", if now_idle { "idle" } else { "resumed" });
+            let _ = app.emit(
+                "audio_idle",
+                AudioIdleEvent {
+                    idle: now_idle,
+                    silent_secs: silence.silent_secs(),
+                },
+            );
+            apply_idle_preset(&mut decks_guard, now_idle, idle_settings.idle_preset.as_deref());
+        }
+    }
+    let freeze_auto_cycle =
+        idle && (idle_settings.freeze_auto_cycle || idle_settings.idle_preset.is_some());
+
+    // Rolling loudness for energy-matched shuffle
+    let mut energy_target = None;
+    if let Ok(mut energy) = state.energy.lock() {
+        energy.set_format(audio_guard.sample_rate(), 2);
+        for samples in &all_samples {
+            energy.process(samples);
+        }
+        energy_target = energy.energy();
+    }
+    let preset_energy = state.preset_energy.lock().map_err(|e| e.to_string())?;
+    let channels = audio_guard.content_channels();
+
     // Send audio to all running decks + check auto-cycle
     for id in 0..MAX_DECKS {
         if let Some(deck) = decks_guard.get_mut(&id) {
             let is_running = deck.renderer.as_mut().is_some_and(|r| r.is_running());
 
             if is_running {
-                // Check auto-cycle timer
-                if deck.playlist.auto_cycle && !deck.playlist.items.is_empty() {
-                    let should_cycle = match deck.last_cycle_time {
-                        Some(last_time) => {
-                            now.duration_since(last_time).as_secs() >= deck.playlist.cycle_duration_secs as u64
-                        }
-                        None => true, // First time, start the timer
+                // The renderer kept showing the preset before a failed one
+                let failures = deck
+                    .renderer
+                    .as_ref()
+                    .map(RendererProcess::take_preset_failures)
+                    .unwrap_or_default();
+                for failure in failures {
+                    if deck.preset_path.as_deref() == Some(failure.path.as_str()) {
+                        deck.preset_path = failure.current;
+                    }
+                    if let Ok(mut failed) = state.failed_presets.lock() {
+                        failed.insert(failure.path.clone(), failure.error.clone());
+                    }
+                    let _ = app.emit(
+                        "deck_preset_failed",
+                        DeckPresetFailedEvent {
+                            deck_id: id,
+                            path: failure.path,
+                            error: failure.error,
+                        },
+                    );
+                }
+
+                // Preset keys pressed in the deck window, played from the playlist
+                let requests = deck
+                    .renderer
+                    .as_ref()
+                    .map(RendererProcess::take_preset_requests)
+                    .unwrap_or_default();
+                for request in requests {
+                    let preset = match request {
+                        PresetRequest::Next => advance_playlist(deck, energy_target, &preset_energy),
+                        PresetRequest::Previous => rewind_playlist(deck),
+                        PresetRequest::Random => random_playlist_item(deck),
                     };
+                    info!("Deck {} window asked for {:?} preset: {:?}", id, request, preset);
+                    let _ = app.emit(
+                        "deck_preset_request",
+                        DeckPresetRequestEvent {
+                            deck_id: id,
+                            request,
+                            preset,
+                        },
+                    );
+                }
 
-                    if should_cycle {
-                        deck.last_cycle_time = Some(now);
-                        if let Some(item) = deck.playlist.advance() {
-                            let path = item.path.clone();
-                            deck.preset_path = Some(path.clone());
-                            if let Some(ref mut renderer) = deck.renderer {
-                                let _ = renderer.send_command(&RendererCommand::LoadPreset { path });
-                            }
+                // Check auto-cycle timer or beat count
+                if deck.playlist.auto_cycle
+                    && !deck.playlist.items.is_empty()
+                    && !freeze_auto_cycle
+                    && !deck.preset_locked
+                    && deck.cycle_due(now, &tempo)
+                {
+                    deck.last_cycle_time = Some(now);
+                    deck.last_cycle_beat = Some(tempo.beat.beat_count);
+                    if let Some(item) = deck.playlist.advance_for_energy(energy_target, &preset_energy) {
+                        let path = item.path.clone();
+                        deck.preset_path = Some(path.clone());
+                        if let Some(ref mut renderer) = deck.renderer {
+                            let _ = renderer.send_command(&RendererCommand::LoadPreset { path });
                         }
                     }
                 }
@@ -1723,15 +4484,44 @@ fn pump_audio(state: State<'_, AppState>) -> Result<u32, String> {
                     let crossfader_vol = crossfader_guard.volume_for_deck(id);
                     let effective_volume = deck.volume * crossfader_vol;
 
+                    if deck.agc_enabled {
+                        deck.agc.set_format(audio_guard.sample_rate(), 2);
+                    }
+
                     if let Some(ref mut renderer) = deck.renderer {
                         for samples in &all_samples {
+                            // Input trim first; boosted audio is soft-clipped rather than wrapped
+                            let samples = if deck.gain == 1.0 {
+                                samples.clone()
+                            } else if deck.gain < 1.0 {
+                                samples.iter().map(|s| s * deck.gain).collect()
+                            } else {
+                                samples.iter().map(|s| soft_clip(s * deck.gain)).collect()
+                            };
+                            let samples = if deck.agc_enabled {
+                                deck.agc.process(&samples)
+                            } else {
+                                samples
+                            };
                             let scaled_samples: Vec<f32> = if effective_volume < 1.0 {
                                 samples.iter().map(|s| s * effective_volume).collect()
                             } else {
-                                samples.clone()
+                                samples
                             };
 
-                            if renderer.send_command(&RendererCommand::Audio { samples: scaled_samples }).is_ok() {
+                            // Mono sources reach projectM as mono rather than duplicated stereo
+                            let command = if channels == 1 {
+                                RendererCommand::Audio {
+                                    samples: to_mono(&scaled_samples, 2),
+                                    channels,
+                                }
+                            } else {
+                                RendererCommand::Audio {
+                                    samples: scaled_samples,
+                                    channels,
+                                }
+                            };
+                            if renderer.send_command(&command).is_ok() {
                                 total_samples_sent += 1;
                             }
                         }
@@ -1741,131 +4531,622 @@ fn pump_audio(state: State<'_, AppState>) -> Result<u32, String> {
         }
     }
 
+    // Everything read this frame has now been written to the renderers
+    if total_samples_sent > 0 {
+        let delivered_at = std::time::Instant::now();
+        for published_at in &published {
+            latency.delivery.record(delivered_at.duration_since(*published_at));
+        }
+    }
+
     Ok(total_samples_sent)
 }
 
-/// Get current audio levels for VU meters
-#[tauri::command]
-fn get_audio_levels(state: State<'_, AppState>) -> Result<(f32, f32), String> {
-    let levels = state.audio_levels.lock().map_err(|e| e.to_string())?;
-    Ok(*levels)
+/// A crashed deck whose restart is due, with what to relaunch it with
+struct DeckRelaunch {
+    deck_id: DeckId,
+    config: RendererConfig,
+    restore: RendererRestore,
+    crash_count: u32,
 }
 
-// ============ Playlist Commands ============
+/// Outcome of checking a deck for a crashed renderer
+enum CrashCheck {
+    Event(DeckRestartEvent),
+    Relaunch(Box<DeckRelaunch>),
+}
 
-/// Add a preset to a deck's playlist
-#[tauri::command]
-fn playlist_add(
-    state: State<'_, AppState>,
-    deck_id: u8,
-    name: String,
-    path: String,
-) -> Result<String, String> {
-    if deck_id >= MAX_DECKS {
-        return Err(format!("Invalid deck ID: {}", deck_id));
+/// Restart crashed deck renderers
+///
+/// Crashes are checked under the decks lock, but renderers are spawned with
+/// no lock held, so a slow launch doesn't hold up the other decks' audio.
+fn restart_crashed_decks(app: &AppHandle, state: &AppState) -> Result<(), String> {
+    let settings = state.auto_restart.lock().map_err(|e| e.to_string())?.clone();
+    if !settings.enabled {
+        return Ok(());
     }
+    let launcher = DeckLauncher::new(state)?;
+    let now = std::time::Instant::now();
 
-    let mut decks_guard = state.decks.lock().map_err(|e| e.to_string())?;
-    let deck = decks_guard.get_mut(&deck_id).ok_or("Deck not found")?;
+    let mut relaunches = Vec::new();
+    for deck in state.decks.lock().map_err(|e| e.to_string())?.values_mut() {
+        match check_crashed_deck(deck, &settings, now) {
+            Some(CrashCheck::Event(event)) => {
+                let _ = app.emit("deck_restart", event);
+            }
+            Some(CrashCheck::Relaunch(relaunch)) => relaunches.push(*relaunch),
+            None => {}
+        }
+    }
 
-    deck.playlist.items.push(PlaylistItem { name, path });
-    Ok(format!("Added to deck {} playlist", deck_id))
+    for relaunch in relaunches {
+        let spawned = spawn_deck_renderer(&relaunch.config, launcher);
+        let mut decks = state.decks.lock().map_err(|e| e.to_string())?;
+        match decks.get_mut(&relaunch.deck_id) {
+            Some(deck) => {
+                if let Some(event) = finish_relaunch(deck, relaunch, spawned) {
+                    let _ = app.emit("deck_restart", event);
+                }
+            }
+            None => {
+                if let Ok(mut renderer) = spawned {
+                    renderer.stop();
+                }
+            }
+        }
+    }
+    Ok(())
 }
 
-/// Remove a preset from a deck's playlist by index
-#[tauri::command]
-fn playlist_remove(
-    state: State<'_, AppState>,
-    deck_id: u8,
-    index: usize,
-) -> Result<String, String> {
-    if deck_id >= MAX_DECKS {
-        return Err(format!("Invalid deck ID: {}", deck_id));
+/// Schedule a crashed deck's restart with exponential backoff, and hand back
+/// what to relaunch it with once due
+fn check_crashed_deck(
+    deck: &mut DeckState,
+    settings: &AutoRestartSettings,
+    now: std::time::Instant,
+) -> Option<CrashCheck> {
+    let deck_id = deck.id;
+
+    if let Some(restart_at) = deck.restart_at {
+        if now < restart_at {
+            return None;
+        }
+        deck.restart_at = None;
+        deck.remember_geometry();
+        let restore = deck.restore.clone();
+        let preset = deck.preset_path.clone();
+        let config = deck_renderer_config(
+            deck,
+            None,
+            None,
+            Some(restore.fullscreen),
+            preset,
+            restore.monitor_index,
+            restore.fullscreen_mode,
+            Some(restore.headless),
+        );
+        return Some(match config {
+            Ok(config) => CrashCheck::Relaunch(Box::new(DeckRelaunch {
+                deck_id,
+                config,
+                restore,
+                crash_count: deck.renderer.as_ref().map_or(0, |r| r.crash_count),
+            })),
+            Err(e) => {
+                warn!("Deck {} restart failed: {}", deck_id, e);
+                CrashCheck::Event(restart_event(deck, DeckRestartStatus::Failed, None, Some(e)))
+            }
+        });
     }
 
-    let mut decks_guard = state.decks.lock().map_err(|e| e.to_string())?;
-    let deck = decks_guard.get_mut(&deck_id).ok_or("Deck not found")?;
-
-    if index >= deck.playlist.items.len() {
-        return Err("Index out of bounds".to_string());
+    let renderer = deck.renderer.as_mut()?;
+    if renderer.is_running() {
+        if deck.restart_attempts > 0 && renderer.uptime_secs() >= RESTART_STABLE_AFTER.as_secs() {
+            deck.restart_attempts = 0;
+        }
+        return None;
+    }
+    // Stopped decks and renderers that exited cleanly stay down
+    if !deck.active || renderer.get_health() != RendererHealth::Crashed {
+        return None;
     }
 
-    deck.playlist.items.remove(index);
-    // Adjust current_index if needed
-    if deck.playlist.current_index >= deck.playlist.items.len() && !deck.playlist.items.is_empty() {
-        deck.playlist.current_index = deck.playlist.items.len() - 1;
+    if deck.restart_attempts >= settings.max_attempts {
+        warn!("Deck {} crashed {} times in a row, not restarting it", deck_id, deck.restart_attempts);
+        deck.active = false;
+        return Some(CrashCheck::Event(restart_event(deck, DeckRestartStatus::GaveUp, None, None)));
     }
-    Ok(format!("Removed from deck {} playlist", deck_id))
+    let delay = RESTART_BASE_DELAY
+        .saturating_mul(1 << deck.restart_attempts.min(16))
+        .min(RESTART_MAX_DELAY);
+    deck.restart_attempts += 1;
+    deck.restart_at = Some(now + delay);
+    warn!("Deck {} renderer crashed, restarting in {:.0?}", deck_id, delay);
+    let delay_secs = Some(delay.as_secs_f64());
+    Some(CrashCheck::Event(restart_event(deck, DeckRestartStatus::Scheduled, delay_secs, None)))
 }
 
-/// Clear a deck's playlist
-#[tauri::command]
-fn playlist_clear(state: State<'_, AppState>, deck_id: u8) -> Result<String, String> {
-    if deck_id >= MAX_DECKS {
-        return Err(format!("Invalid deck ID: {}", deck_id));
+/// Attach a relaunched renderer to its deck, with its last preset and outputs
+fn finish_relaunch(
+    deck: &mut DeckState,
+    relaunch: DeckRelaunch,
+    spawned: Result<RendererProcess, String>,
+) -> Option<DeckRestartEvent> {
+    // Started or stopped by hand while the renderer launched
+    if deck.is_running() || !deck.active {
+        if let Ok(mut renderer) = spawned {
+            renderer.stop();
+        }
+        return None;
     }
+    let renderer = match spawned {
+        Ok(renderer) => renderer,
+        Err(e) => {
+            warn!("Deck {} restart failed: {}", deck.id, e);
+            return Some(restart_event(deck, DeckRestartStatus::Failed, None, Some(e)));
+        }
+    };
 
-    let mut decks_guard = state.decks.lock().map_err(|e| e.to_string())?;
-    let deck = decks_guard.get_mut(&deck_id).ok_or("Deck not found")?;
+    let DeckRelaunch {
+        config,
+        restore,
+        crash_count,
+        ..
+    } = relaunch;
+    install_deck_renderer(deck, config, renderer);
 
-    deck.playlist.items.clear();
-    deck.playlist.current_index = 0;
-    Ok(format!("Deck {} playlist cleared", deck_id))
+    // The renderer queues commands until its window is up
+    if let Some(ref mut renderer) = deck.renderer {
+        renderer.crash_count = crash_count;
+        if let Some(paths) = restore.texture_paths.clone() {
+            let _ = renderer.send_command(&RendererCommand::SetTexturePaths { paths });
+        }
+        if let Some(ref command) = restore.video_output {
+            let _ = renderer.send_command(command);
+        }
+        if let Some(ref command) = restore.ndi_output {
+            renderer.ndi_enabled = renderer.send_command(command).is_ok();
+        }
+        if deck.strobing {
+            let _ = renderer.send_command(&RendererCommand::SetStrobe { strobe: Some(deck.strobe) });
+        }
+    }
+    deck.restore = restore;
+    info!("Deck {} restarted after a crash", deck.id);
+    Some(restart_event(deck, DeckRestartStatus::Restarted, None, None))
 }
 
-/// Move to next preset in playlist
-#[tauri::command]
-fn playlist_next(state: State<'_, AppState>, deck_id: u8) -> Result<Option<String>, String> {
-    if deck_id >= MAX_DECKS {
-        return Err(format!("Invalid deck ID: {}", deck_id));
+fn restart_event(
+    deck: &DeckState,
+    status: DeckRestartStatus,
+    delay_secs: Option<f64>,
+    error: Option<String>,
+) -> DeckRestartEvent {
+    DeckRestartEvent {
+        deck_id: deck.id,
+        status,
+        attempt: deck.restart_attempts,
+        delay_secs,
+        error,
     }
+}
 
-    let mut decks_guard = state.decks.lock().map_err(|e| e.to_string())?;
-    let deck = decks_guard.get_mut(&deck_id).ok_or("Deck not found")?;
+/// Run the audio pump on a backend thread so visuals don't depend on the
+/// webview's frame rate (which drops when the window is hidden or throttled)
+fn spawn_audio_pump(app: AppHandle) {
+    let result = thread::Builder::new()
+        .name("audio-pump".to_string())
+        .spawn(move || {
+            let state = app.state::<AppState>();
+            let mut next = std::time::Instant::now();
+            let mut errors = 0u32;
+            let mut levels_due = next;
+            let mut last_levels = None;
+
+            loop {
+                // Crashed decks are relaunched after the frame has released its locks
+                match pump_audio_frame(&app, &state).and_then(|_| restart_crashed_decks(&app, &state)) {
+                    Ok(()) => errors = 0,
+                    Err(e) => {
+                        errors += 1;
+                        if errors == AUDIO_PUMP_ERROR_THRESHOLD {
+                            warn!("Audio pump failing: {}", e);
+                            let _ = app.emit("audio_pump_error", e);
+                        }
+                    }
+                }
 
-    if let Some(item) = deck.playlist.advance() {
-        let path = item.path.clone();
-        deck.preset_path = Some(path.clone());
+                // VU meters only need the UI's frame rate, not the pump's
+                let now = std::time::Instant::now();
+                if now >= levels_due {
+                    levels_due = now + AUDIO_LEVELS_INTERVAL;
+                    if let Ok(meter) = state.level_meter.lock() {
+                        let levels = meter.levels();
+                        if last_levels != Some(levels) {
+                            last_levels = Some(levels);
+                            let _ = app.emit("audio_levels", levels);
+                        }
+                    }
+                }
 
-        // Load preset if deck is running
-        if let Some(ref mut renderer) = deck.renderer {
-            if renderer.is_running() {
-                let _ = renderer.send_command(&RendererCommand::LoadPreset { path: path.clone() });
+                next += AUDIO_PUMP_INTERVAL;
+                let now = std::time::Instant::now();
+                if next > now {
+                    thread::sleep(next - now);
+                } else {
+                    // Fell behind; don't try to catch up with a burst
+                    next = now;
+                }
             }
-        }
-        Ok(Some(path))
-    } else {
-        Ok(None)
-    }
-}
+        });
 
-/// Move to previous preset in playlist
-#[tauri::command]
-fn playlist_previous(state: State<'_, AppState>, deck_id: u8) -> Result<Option<String>, String> {
-    if deck_id >= MAX_DECKS {
-        return Err(format!("Invalid deck ID: {}", deck_id));
+    if let Err(e) = result {
+        error!("Failed to start audio pump thread: {}", e);
     }
+}
 
-    let mut decks_guard = state.decks.lock().map_err(|e| e.to_string())?;
-    let deck = decks_guard.get_mut(&deck_id).ok_or("Deck not found")?;
+/// Switch running decks to the idle preset, or back to what they showed before
+fn apply_idle_preset(decks: &mut HashMap<DeckId, DeckState>, idle: bool, idle_preset: Option<&str>) {
+    let now = std::time::Instant::now();
+    for deck in decks.values_mut() {
+        if !deck.is_running() {
+            continue;
+        }
 
-    if let Some(item) = deck.playlist.previous() {
-        let path = item.path.clone();
-        deck.preset_path = Some(path.clone());
+        let path = if idle {
+            let Some(preset) = idle_preset else {
+                continue;
+            };
+            deck.pre_idle_preset = deck.preset_path.clone();
+            preset.to_string()
+        } else {
+            // Restart the auto-cycle timer instead of cycling immediately
+            deck.last_cycle_time = Some(now);
+            deck.last_cycle_beat = None;
+            match deck.pre_idle_preset.take() {
+                Some(path) => path,
+                None => continue,
+            }
+        };
 
-        // Load preset if deck is running
+        deck.preset_path = Some(path.clone());
         if let Some(ref mut renderer) = deck.renderer {
-            if renderer.is_running() {
-                let _ = renderer.send_command(&RendererCommand::LoadPreset { path: path.clone() });
-            }
+            let _ = renderer.send_command(&RendererCommand::LoadPreset { path });
         }
-        Ok(Some(path))
-    } else {
-        Ok(None)
     }
 }
 
-/// Set playlist settings (shuffle, auto_cycle, duration)
+/// Get current audio levels for VU meters (RMS, held peak and clip flag per channel)
+#[tauri::command]
+fn get_audio_levels(state: State<'_, AppState>) -> Result<AudioLevels, String> {
+    let meter = state.level_meter.lock().map_err(|e| e.to_string())?;
+    Ok(meter.levels())
+}
+
+/// Configure peak hold, peak decay and clip hold of the level meters
+#[tauri::command]
+fn set_meter_settings(state: State<'_, AppState>, settings: MeterConfig) -> Result<String, String> {
+    let mut meter = state.level_meter.lock().map_err(|e| e.to_string())?;
+    meter.configure(settings);
+    Ok("Meter settings updated".to_string())
+}
+
+/// Get the current level meter settings
+#[tauri::command]
+fn get_meter_settings(state: State<'_, AppState>) -> Result<MeterConfig, String> {
+    let meter = state.level_meter.lock().map_err(|e| e.to_string())?;
+    Ok(meter.config())
+}
+
+/// Get the rolling loudness score used for energy-matched shuffle
+#[tauri::command]
+fn get_audio_energy(state: State<'_, AppState>) -> Result<EnergyInfo, String> {
+    let energy = state.energy.lock().map_err(|e| e.to_string())?;
+    Ok(energy.info())
+}
+
+/// Set which presets are tagged calm or intense (synced from the tag store)
+#[tauri::command]
+fn set_preset_energy_tags(
+    state: State<'_, AppState>,
+    calm: Vec<String>,
+    intense: Vec<String>,
+) -> Result<String, String> {
+    let mut preset_energy = state.preset_energy.lock().map_err(|e| e.to_string())?;
+    preset_energy.clear();
+    preset_energy.extend(calm.into_iter().map(|path| (path, Energy::Calm)));
+    preset_energy.extend(intense.into_iter().map(|path| (path, Energy::Intense)));
+    Ok(format!("{} presets tagged by energy", preset_energy.len()))
+}
+
+/// Presets a renderer couldn't load since startup, with projectM's error
+#[tauri::command]
+fn get_failed_presets(state: State<'_, AppState>) -> Result<HashMap<String, String>, String> {
+    let failed = state.failed_presets.lock().map_err(|e| e.to_string())?;
+    Ok(failed.clone())
+}
+
+/// Forget failed presets, e.g. after fixing them
+#[tauri::command]
+fn clear_failed_presets(state: State<'_, AppState>) -> Result<String, String> {
+    let mut failed = state.failed_presets.lock().map_err(|e| e.to_string())?;
+    let count = failed.len();
+    failed.clear();
+    Ok(format!("Cleared {} failed presets", count))
+}
+
+/// Get FFT spectrum band levels (0.0-1.0, log-spaced from 20 Hz upward)
+///
+/// `bands` defaults to 32 and is clamped to 8-128.
+#[tauri::command]
+fn get_audio_spectrum(state: State<'_, AppState>, bands: Option<usize>) -> Result<Vec<f32>, String> {
+    let spectrum = state.spectrum.lock().map_err(|e| e.to_string())?;
+    Ok(spectrum.bands(bands.unwrap_or(DEFAULT_BANDS).clamp(8, 128)))
+}
+
+/// Get the most recent `window_ms` of waveform history (default 2 s, up to 10 s)
+#[tauri::command]
+fn get_waveform(state: State<'_, AppState>, window_ms: Option<u32>) -> Result<Waveform, String> {
+    let waveform = state.waveform.lock().map_err(|e| e.to_string())?;
+    Ok(waveform.window(window_ms.unwrap_or(2000)))
+}
+
+/// Get the format negotiated with the capture device (None if not running)
+///
+/// Decks receive the configured rate, in mono when a mono device is the only
+/// source and in stereo otherwise; a differing capture format means the
+/// engine is converting.
+#[tauri::command]
+fn get_audio_format(state: State<'_, AppState>) -> Result<Option<AudioFormatInfo>, String> {
+    let engine = state.audio_engine.lock().map_err(|e| e.to_string())?;
+    if !engine.is_running() {
+        return Ok(None);
+    }
+    Ok(Some(AudioFormatInfo {
+        capture: engine.capture_format(),
+        sample_rate: engine.sample_rate(),
+        channels: engine.content_channels(),
+    }))
+}
+
+/// Get capture-to-renderer latency statistics (None if audio isn't running)
+#[tauri::command]
+fn get_audio_stats(state: State<'_, AppState>) -> Result<Option<AudioStats>, String> {
+    let engine = state.audio_engine.lock().map_err(|e| e.to_string())?;
+    if !engine.is_running() {
+        return Ok(None);
+    }
+    let latency = state.audio_latency.lock().map_err(|e| e.to_string())?;
+    Ok(Some(AudioStats {
+        read: latency.read.report(),
+        delivery: latency.delivery.report(),
+        buffer_frames: engine.config().buffer_frames(),
+        max_latency_ms: engine.config().max_latency_ms,
+    }))
+}
+
+/// Get bass/mid/treble energy levels (0.0-1.0)
+#[tauri::command]
+fn get_audio_bands(state: State<'_, AppState>) -> Result<BandEnergy, String> {
+    let spectrum = state.spectrum.lock().map_err(|e| e.to_string())?;
+    Ok(spectrum.energy())
+}
+
+/// Get the current tempo (BPM), beat phase and where the tempo comes from
+#[tauri::command]
+fn get_beat_info(state: State<'_, AppState>) -> Result<TempoInfo, String> {
+    let clock = state.tempo_clock.lock().map_err(|e| e.to_string())?;
+    Ok(clock.info(std::time::Instant::now()))
+}
+
+/// Override the detected tempo with a fixed BPM, or return to detection with `None`
+#[tauri::command]
+fn set_bpm(state: State<'_, AppState>, bpm: Option<f32>) -> Result<TempoInfo, String> {
+    let mut clock = state.tempo_clock.lock().map_err(|e| e.to_string())?;
+    let now = std::time::Instant::now();
+    match bpm {
+        Some(bpm) if !(MIN_MANUAL_BPM..=MAX_MANUAL_BPM).contains(&bpm) => {
+            return Err(format!(
+                "BPM must be between {} and {}",
+                MIN_MANUAL_BPM, MAX_MANUAL_BPM
+            ));
+        }
+        Some(bpm) => clock.set_manual(bpm, now),
+        None => clock.clear_manual(),
+    }
+    Ok(clock.info(now))
+}
+
+/// Tap the tempo; from the second tap on the tapped BPM overrides detection
+#[tauri::command]
+fn tap_tempo(state: State<'_, AppState>) -> Result<TempoInfo, String> {
+    let mut clock = state.tempo_clock.lock().map_err(|e| e.to_string())?;
+    let now = std::time::Instant::now();
+    clock.tap(now);
+    Ok(clock.info(now))
+}
+
+/// Configure silence detection and what decks do while idle
+#[tauri::command]
+fn set_idle_settings(state: State<'_, AppState>, settings: IdleSettings) -> Result<String, String> {
+    let mut silence = state.silence_detector.lock().map_err(|e| e.to_string())?;
+    silence.configure(settings.threshold, settings.timeout_secs);
+
+    let mut idle_settings = state.idle_settings.lock().map_err(|e| e.to_string())?;
+    *idle_settings = settings;
+    Ok("Idle settings updated".to_string())
+}
+
+/// Get the current idle settings
+#[tauri::command]
+fn get_idle_settings(state: State<'_, AppState>) -> Result<IdleSettings, String> {
+    let idle_settings = state.idle_settings.lock().map_err(|e| e.to_string())?;
+    Ok(idle_settings.clone())
+}
+
+/// Configure automatic restarts of crashed deck renderers
+#[tauri::command]
+fn set_auto_restart(state: State<'_, AppState>, settings: AutoRestartSettings) -> Result<String, String> {
+    if settings.max_attempts == 0 {
+        return Err("Allow at least one restart attempt".to_string());
+    }
+    let enabled = settings.enabled;
+    *state.auto_restart.lock().map_err(|e| e.to_string())? = settings;
+    if !enabled {
+        // Restarts already waiting are dropped
+        let mut decks_guard = state.decks.lock().map_err(|e| e.to_string())?;
+        for deck in decks_guard.values_mut() {
+            deck.restart_at = None;
+        }
+    }
+    Ok(format!(
+        "Automatic renderer restart {}",
+        if enabled { "enabled" } else { "disabled" }
+    ))
+}
+
+/// Get the automatic restart settings
+#[tauri::command]
+fn get_auto_restart(state: State<'_, AppState>) -> Result<AutoRestartSettings, String> {
+    let auto_restart = state.auto_restart.lock().map_err(|e| e.to_string())?;
+    Ok(auto_restart.clone())
+}
+
+/// Start recording the captured audio to a WAV file
+#[tauri::command]
+fn start_audio_recording(state: State<'_, AppState>, path: String) -> Result<String, String> {
+    let mut audio_guard = state.audio_engine.lock().map_err(|e| e.to_string())?;
+    audio_guard.start_recording(&path).map_err(|e| e.to_string())?;
+    Ok(format!("Recording audio to {}", path))
+}
+
+/// Stop the active audio recording and finalize the WAV file
+#[tauri::command]
+fn stop_audio_recording(state: State<'_, AppState>) -> Result<AudioRecordingInfo, String> {
+    let mut audio_guard = state.audio_engine.lock().map_err(|e| e.to_string())?;
+    audio_guard
+        .stop_recording()
+        .map_err(|e| e.to_string())?
+        .map(AudioRecordingInfo::from)
+        .ok_or_else(|| "Not recording".to_string())
+}
+
+// ============ Playlist Commands ============
+
+/// Add a preset to a deck's playlist
+#[tauri::command]
+fn playlist_add(
+    state: State<'_, AppState>,
+    deck_id: u8,
+    name: String,
+    path: String,
+) -> Result<String, String> {
+    if deck_id >= MAX_DECKS {
+        return Err(format!("Invalid deck ID: {}", deck_id));
+    }
+
+    let mut decks_guard = state.decks.lock().map_err(|e| e.to_string())?;
+    let deck = decks_guard.get_mut(&deck_id).ok_or("Deck not found")?;
+
+    deck.playlist.items.push(PlaylistItem { name, path });
+    Ok(format!("Added to deck {} playlist", deck_id))
+}
+
+/// Remove a preset from a deck's playlist by index
+#[tauri::command]
+fn playlist_remove(
+    state: State<'_, AppState>,
+    deck_id: u8,
+    index: usize,
+) -> Result<String, String> {
+    if deck_id >= MAX_DECKS {
+        return Err(format!("Invalid deck ID: {}", deck_id));
+    }
+
+    let mut decks_guard = state.decks.lock().map_err(|e| e.to_string())?;
+    let deck = decks_guard.get_mut(&deck_id).ok_or("Deck not found")?;
+
+    if index >= deck.playlist.items.len() {
+        return Err("Index out of bounds".to_string());
+    }
+
+    deck.playlist.items.remove(index);
+    // Adjust current_index if needed
+    if deck.playlist.current_index >= deck.playlist.items.len() && !deck.playlist.items.is_empty() {
+        deck.playlist.current_index = deck.playlist.items.len() - 1;
+    }
+    Ok(format!("Removed from deck {} playlist", deck_id))
+}
+
+/// Clear a deck's playlist
+#[tauri::command]
+fn playlist_clear(state: State<'_, AppState>, deck_id: u8) -> Result<String, String> {
+    if deck_id >= MAX_DECKS {
+        return Err(format!("Invalid deck ID: {}", deck_id));
+    }
+
+    let mut decks_guard = state.decks.lock().map_err(|e| e.to_string())?;
+    let deck = decks_guard.get_mut(&deck_id).ok_or("Deck not found")?;
+
+    deck.playlist.items.clear();
+    deck.playlist.current_index = 0;
+    Ok(format!("Deck {} playlist cleared", deck_id))
+}
+
+/// Move to next preset in playlist
+#[tauri::command]
+fn playlist_next(state: State<'_, AppState>, deck_id: u8) -> Result<Option<String>, String> {
+    if deck_id >= MAX_DECKS {
+        return Err(format!("Invalid deck ID: {}", deck_id));
+    }
+
+    let mut decks_guard = state.decks.lock().map_err(|e| e.to_string())?;
+    let deck = decks_guard.get_mut(&deck_id).ok_or("Deck not found")?;
+    let energy_target = state.energy.lock().map_err(|e| e.to_string())?.energy();
+    let preset_energy = state.preset_energy.lock().map_err(|e| e.to_string())?;
+
+    Ok(advance_playlist(deck, energy_target, &preset_energy))
+}
+
+/// Move a deck to the next preset of its playlist and load it
+fn advance_playlist(
+    deck: &mut DeckState,
+    energy_target: Option<Energy>,
+    preset_energy: &HashMap<String, Energy>,
+) -> Option<String> {
+    deck.playlist.advance_for_energy(energy_target, preset_energy)?;
+    let index = deck.playlist.current_index;
+    jump_to_playlist_index(deck, index)
+}
+
+/// Move to previous preset in playlist
+#[tauri::command]
+fn playlist_previous(state: State<'_, AppState>, deck_id: u8) -> Result<Option<String>, String> {
+    if deck_id >= MAX_DECKS {
+        return Err(format!("Invalid deck ID: {}", deck_id));
+    }
+
+    let mut decks_guard = state.decks.lock().map_err(|e| e.to_string())?;
+    let deck = decks_guard.get_mut(&deck_id).ok_or("Deck not found")?;
+
+    Ok(rewind_playlist(deck))
+}
+
+/// Move a deck to the previous preset of its playlist and load it
+fn rewind_playlist(deck: &mut DeckState) -> Option<String> {
+    deck.playlist.previous()?;
+    let index = deck.playlist.current_index;
+    jump_to_playlist_index(deck, index)
+}
+
+/// Move a deck to a random preset of its playlist and load it
+fn random_playlist_item(deck: &mut DeckState) -> Option<String> {
+    deck.playlist.random()?;
+    let index = deck.playlist.current_index;
+    jump_to_playlist_index(deck, index)
+}
+
+/// Set playlist settings (shuffle, auto_cycle, cycle length in seconds or beats)
 #[tauri::command]
 fn playlist_set_settings(
     state: State<'_, AppState>,
@@ -1873,6 +5154,8 @@ fn playlist_set_settings(
     shuffle: Option<bool>,
     auto_cycle: Option<bool>,
     cycle_duration_secs: Option<u32>,
+    cycle_beats: Option<u32>,
+    energy_match: Option<bool>,
 ) -> Result<String, String> {
     if deck_id >= MAX_DECKS {
         return Err(format!("Invalid deck ID: {}", deck_id));
@@ -1885,13 +5168,17 @@ fn playlist_set_settings(
         deck.playlist.shuffle = s;
     }
     if let Some(ac) = auto_cycle {
-        deck.playlist.auto_cycle = ac;
-        if ac {
-            deck.last_cycle_time = Some(std::time::Instant::now());
-        }
+        deck.set_auto_cycle(ac);
     }
     if let Some(dur) = cycle_duration_secs {
-        deck.playlist.cycle_duration_secs = dur.max(5); // Min 5 seconds
+        deck.playlist.cycle_on = CycleOn::Seconds(dur.max(5)); // Min 5 seconds
+    }
+    if let Some(beats) = cycle_beats {
+        deck.playlist.cycle_on = CycleOn::Beats(beats.max(1));
+        deck.last_cycle_beat = None;
+    }
+    if let Some(em) = energy_match {
+        deck.playlist.energy_match = em;
     }
 
     Ok("Playlist settings updated".to_string())
@@ -1915,8 +5202,13 @@ fn playlist_jump_to(
         return Err("Index out of bounds".to_string());
     }
 
+    Ok(jump_to_playlist_index(deck, index))
+}
+
+/// Make a playlist position current and load its preset if the deck is running
+fn jump_to_playlist_index(deck: &mut DeckState, index: usize) -> Option<String> {
+    let path = deck.playlist.items.get(index)?.path.clone();
     deck.playlist.current_index = index;
-    let path = deck.playlist.items[index].path.clone();
     deck.preset_path = Some(path.clone());
 
     // Load preset if deck is running
@@ -1926,7 +5218,38 @@ fn playlist_jump_to(
         }
     }
 
-    Ok(Some(path))
+    Some(path)
+}
+
+/// Replace a deck's playlist with a saved bank (see `playlist_banks_dir`)
+/// and load its first preset
+fn load_playlist_bank(deck: &mut DeckState, bank: u8) -> Option<String> {
+    let path = list_playlist_banks(playlist_banks_dir()?)
+        .into_iter()
+        .nth(bank as usize)?;
+    let loaded: PlaylistInfo = match std::fs::read_to_string(&path)
+        .map_err(|e| e.to_string())
+        .and_then(|json| serde_json::from_str(&json).map_err(|e| e.to_string()))
+    {
+        Ok(loaded) => loaded,
+        Err(e) => {
+            tracing::warn!("Failed to load playlist bank {}: {}", path.display(), e);
+            return None;
+        }
+    };
+
+    deck.playlist.name = loaded.name;
+    deck.playlist.items = loaded
+        .items
+        .into_iter()
+        .filter(|item| std::path::Path::new(&item.path).exists())
+        .collect();
+    deck.playlist.shuffle = loaded.shuffle;
+    deck.playlist.auto_cycle = loaded.auto_cycle;
+    deck.playlist.cycle_on = loaded.cycle_duration_secs.map_or(loaded.cycle_on, CycleOn::Seconds);
+    deck.playlist.energy_match = loaded.energy_match;
+    deck.last_cycle_beat = None;
+    jump_to_playlist_index(deck, 0)
 }
 
 /// Reorder playlist item (move from one index to another)
@@ -2014,19 +5337,15 @@ fn crossfader_assign_deck(
         return Err(format!("Invalid deck ID: {}", deck_id));
     }
 
-    let mut crossfader_guard = state.crossfader.lock().map_err(|e| e.to_string())?;
-
-    // Remove from both sides first
-    crossfader_guard.side_a.retain(|&id| id != deck_id);
-    crossfader_guard.side_b.retain(|&id| id != deck_id);
-
-    // Add to the specified side
-    match side.to_lowercase().as_str() {
-        "a" => crossfader_guard.side_a.push(deck_id),
-        "b" => crossfader_guard.side_b.push(deck_id),
-        "none" => {} // Already removed from both sides
+    let target = match side.to_lowercase().as_str() {
+        "a" => Some(CrossfaderSide::A),
+        "b" => Some(CrossfaderSide::B),
+        "none" => None,
         _ => return Err(format!("Invalid side: {}. Use 'a', 'b', or 'none'", side)),
-    }
+    };
+
+    let mut crossfader_guard = state.crossfader.lock().map_err(|e| e.to_string())?;
+    crossfader_guard.assign_deck(deck_id, target);
 
     Ok(format!("Deck {} assigned to side {}", deck_id + 1, side.to_uppercase()))
 }
@@ -2046,7 +5365,17 @@ fn compositor_set_enabled(
     state: State<'_, AppState>,
     enabled: bool,
 ) -> Result<String, String> {
+    let gpu = *state.gpu_preference.lock().map_err(|e| e.to_string())?;
     let mut compositor_guard = state.compositor.lock().map_err(|e| e.to_string())?;
+    let mut renderer_guard = state.compositor_renderer.lock().map_err(|e| e.to_string())?;
+    if enabled {
+        // Decks start streaming once the audio pump sees the compositor ready
+        if !renderer_guard.as_mut().is_some_and(|r| r.is_running()) {
+            *renderer_guard = Some(start_compositor_renderer(&compositor_guard, gpu)?);
+        }
+    } else if let Some(mut renderer) = renderer_guard.take() {
+        renderer.stop();
+    }
     compositor_guard.enabled = enabled;
     Ok(format!("Compositor {}", if enabled { "enabled" } else { "disabled" }))
 }
@@ -2058,9 +5387,23 @@ fn compositor_set_resolution(
     width: u32,
     height: u32,
 ) -> Result<String, String> {
+    if width == 0 || height == 0 {
+        return Err("Compositor resolution must be non-zero".to_string());
+    }
+
+    let gpu = *state.gpu_preference.lock().map_err(|e| e.to_string())?;
     let mut compositor_guard = state.compositor.lock().map_err(|e| e.to_string())?;
     compositor_guard.output_width = width;
     compositor_guard.output_height = height;
+
+    // The window is sized at startup, so a running compositor is restarted
+    let mut renderer_guard = state.compositor_renderer.lock().map_err(|e| e.to_string())?;
+    if let Some(mut renderer) = renderer_guard.take() {
+        if renderer.is_running() {
+            renderer.stop();
+            *renderer_guard = Some(start_compositor_renderer(&compositor_guard, gpu)?);
+        }
+    }
     Ok(format!("Compositor resolution set to {}x{}", width, height))
 }
 
@@ -2168,7 +5511,10 @@ fn compositor_link_crossfader(
 #[tauri::command]
 fn compositor_get_config(state: State<'_, AppState>) -> Result<CompositorInfo, String> {
     let compositor_guard = state.compositor.lock().map_err(|e| e.to_string())?;
-    Ok(CompositorInfo::from(&*compositor_guard))
+    Ok(CompositorInfo {
+        running: compositor_running(&state),
+        ..CompositorInfo::from(&*compositor_guard)
+    })
 }
 
 // ============ Monitor Commands ============
@@ -2186,12 +5532,15 @@ pub struct MonitorInfo {
     pub height: u32,
     /// Whether this is the primary monitor
     pub is_primary: bool,
+    /// Modes it can be switched to for exclusive fullscreen (empty when
+    /// they can't be listed)
+    pub modes: Vec<DisplayMode>,
 }
 
 /// List available display monitors
 #[tauri::command]
 fn list_monitors() -> Vec<MonitorInfo> {
-    let mut monitors = Vec::new();
+    let mut monitors: Vec<MonitorInfo> = Vec::new();
 
     #[cfg(target_os = "linux")]
     {
@@ -2202,7 +5551,16 @@ fn list_monitors() -> Vec<MonitorInfo> {
         {
             if let Ok(stdout) = String::from_utf8(output.stdout) {
                 let mut index = 0;
+                // Mode lines follow their output's line
+                let mut listing_modes = false;
                 for line in stdout.lines() {
+                    if line.starts_with(char::is_whitespace) {
+                        if let Some(monitor) = monitors.last_mut().filter(|_| listing_modes) {
+                            monitor.modes.extend(opendrop_core::video::display_mode::parse_xrandr_modes(line));
+                        }
+                        continue;
+                    }
+                    listing_modes = false;
                     // Lines like: "HDMI-1 connected primary 1920x1080+0+0 ..."
                     // or: "DP-1 connected 2560x1440+1920+0 ..."
                     if line.contains(" connected") {
@@ -2235,8 +5593,10 @@ fn list_monitors() -> Vec<MonitorInfo> {
                                     width,
                                     height,
                                     is_primary,
+                                    modes: Vec::new(),
                                 });
                                 index += 1;
+                                listing_modes = true;
                             }
                         }
                     }
@@ -2252,6 +5612,7 @@ fn list_monitors() -> Vec<MonitorInfo> {
                 width: 1920,
                 height: 1080,
                 is_primary: true,
+                modes: Vec::new(),
             });
         }
     }
@@ -2260,8 +5621,10 @@ fn list_monitors() -> Vec<MonitorInfo> {
     {
         use std::mem;
         use windows::Win32::Foundation::{BOOL, LPARAM, RECT};
+        use windows::core::PCWSTR;
         use windows::Win32::Graphics::Gdi::{
-            EnumDisplayMonitors, GetMonitorInfoW, HDC, HMONITOR, MONITORINFOEXW,
+            EnumDisplayMonitors, EnumDisplaySettingsW, GetMonitorInfoW, DEVMODEW, ENUM_DISPLAY_SETTINGS_MODE, HDC,
+            HMONITOR, MONITORINFOEXW,
         };
 
         // Callback data structure
@@ -2292,12 +5655,36 @@ fn list_monitors() -> Vec<MonitorInfo> {
 
                 let is_primary = (info.monitorInfo.dwFlags & 1) != 0; // MONITORINFOF_PRIMARY = 1
 
+                // Modes repeat for each colour depth
+                let mut modes = Vec::new();
+                let mut devmode: DEVMODEW = mem::zeroed();
+                devmode.dmSize = mem::size_of::<DEVMODEW>() as u16;
+                let mut mode_index = 0;
+                while EnumDisplaySettingsW(
+                    PCWSTR(info.szDevice.as_ptr()),
+                    ENUM_DISPLAY_SETTINGS_MODE(mode_index),
+                    &mut devmode,
+                )
+                .as_bool()
+                {
+                    let mode = DisplayMode {
+                        width: devmode.dmPelsWidth,
+                        height: devmode.dmPelsHeight,
+                        refresh_millihertz: devmode.dmDisplayFrequency * 1000,
+                    };
+                    if !modes.contains(&mode) {
+                        modes.push(mode);
+                    }
+                    mode_index += 1;
+                }
+
                 data.monitors.push(MonitorInfo {
                     index: data.monitors.len(),
                     name,
                     width,
                     height,
                     is_primary,
+                    modes,
                 });
             }
 
@@ -2327,13 +5714,14 @@ fn list_monitors() -> Vec<MonitorInfo> {
                 width: 1920,
                 height: 1080,
                 is_primary: true,
+                modes: Vec::new(),
             });
         }
     }
 
     #[cfg(target_os = "macos")]
     {
-        use core_graphics::display::{CGDisplay, CGMainDisplayID};
+        use core_graphics::display::{CGDisplay, CGDisplayMode, CGMainDisplayID};
 
         // Get all active displays
         let max_displays = 16u32;
@@ -2365,77 +5753,633 @@ fn list_monitors() -> Vec<MonitorInfo> {
                         format!("Display {}", i + 1)
                     };
 
+                    let modes = CGDisplayMode::all_display_modes(display_id, std::ptr::null())
+                        .unwrap_or_default()
+                        .iter()
+                        .map(|mode| DisplayMode {
+                            width: mode.width() as u32,
+                            height: mode.height() as u32,
+                            // Built-in panels report 0, and run at 60 Hz
+                            refresh_millihertz: match (mode.refresh_rate() * 1000.0).round() as u32 {
+                                0 => 60_000,
+                                refresh => refresh,
+                            },
+                        })
+                        .collect();
+
                     monitors.push(MonitorInfo {
                         index: i,
                         name,
                         width,
                         height,
                         is_primary: display_id == main_display_id,
+                        modes,
                     });
                 }
             }
         }
 
-        // Fallback if enumeration failed
-        if monitors.is_empty() {
-            monitors.push(MonitorInfo {
-                index: 0,
-                name: "Primary".to_string(),
-                width: 1920,
-                height: 1080,
-                is_primary: true,
-            });
+        // Fallback if enumeration failed
+        if monitors.is_empty() {
+            monitors.push(MonitorInfo {
+                index: 0,
+                name: "Primary".to_string(),
+                width: 1920,
+                height: 1080,
+                is_primary: true,
+                modes: Vec::new(),
+            });
+        }
+    }
+
+    #[cfg(not(any(target_os = "linux", target_os = "windows", target_os = "macos")))]
+    {
+        monitors.push(MonitorInfo {
+            index: 0,
+            name: "Primary".to_string(),
+            width: 1920,
+            height: 1080,
+            is_primary: true,
+        });
+    }
+
+    monitors
+}
+
+// ============ Video Output Commands ============
+
+/// List available video output devices (v4l2loopback on Linux, Spout on Windows)
+#[tauri::command]
+fn list_video_outputs() -> Vec<String> {
+    #[cfg(target_os = "linux")]
+    {
+        opendrop_core::video::V4l2Output::list_devices()
+            .into_iter()
+            .map(|d| format!("{}:{}", d.path.display(), d.name))
+            .collect()
+    }
+    #[cfg(target_os = "windows")]
+    {
+        // Spout is always available if SpoutLibrary.dll is present
+        if opendrop_core::video::SpoutOutput::is_available() {
+            vec!["Spout:OpenDrop".to_string()]
+        } else {
+            vec![]
+        }
+    }
+    #[cfg(not(any(target_os = "linux", target_os = "windows")))]
+    {
+        vec![]
+    }
+}
+
+/// v4l2loopback state reported by `setup_v4l2_device`
+#[derive(Serialize, Deserialize, Clone)]
+pub struct V4l2SetupInfo {
+    /// The module is available for the running kernel
+    pub module_installed: bool,
+    /// The module is loaded
+    pub module_loaded: bool,
+    /// Loopback devices, formatted like `list_video_outputs`
+    pub devices: Vec<String>,
+    /// Command that creates the device by hand
+    pub command: String,
+}
+
+/// Check v4l2loopback and, with `create`, load it with a device.
+/// Creating asks for the admin password through polkit.
+#[tauri::command]
+async fn setup_v4l2_device(
+    create: bool,
+    video_nr: Option<u32>,
+    label: Option<String>,
+) -> Result<V4l2SetupInfo, String> {
+    #[cfg(target_os = "linux")]
+    {
+        use opendrop_core::video::{v4l2, V4l2SetupOptions};
+
+        let mut options = V4l2SetupOptions::default();
+        if let Some(video_nr) = video_nr {
+            options.video_nr = video_nr;
+        }
+        if let Some(label) = label.filter(|l| !l.trim().is_empty()) {
+            options.card_label = label.trim().to_string();
+        }
+        let command = options.manual_command();
+
+        // Waits on the password prompt, so keep it off the main thread
+        let status = tauri::async_runtime::spawn_blocking(move || {
+            if create {
+                v4l2::setup_loopback(&options)
+            } else {
+                Ok(v4l2::loopback_status())
+            }
+        })
+        .await
+        .map_err(|e| e.to_string())?
+        .map_err(|e| e.to_string())?;
+
+        Ok(V4l2SetupInfo {
+            module_installed: status.module_installed,
+            module_loaded: status.module_loaded,
+            devices: status
+                .devices
+                .iter()
+                .map(|d| format!("{}:{}", d.path.display(), d.name))
+                .collect(),
+            command,
+        })
+    }
+    #[cfg(not(target_os = "linux"))]
+    {
+        let _ = (create, video_nr, label);
+        Err("v4l2loopback is only available on Linux".to_string())
+    }
+}
+
+/// Enable video output on a deck (sends frames to v4l2loopback)
+#[tauri::command]
+fn set_deck_video_output(
+    state: State<'_, AppState>,
+    deck_id: u8,
+    enabled: bool,
+    device_path: Option<String>,
+    pixel_format: Option<String>,
+) -> Result<String, String> {
+    if deck_id >= MAX_DECKS {
+        return Err(format!("Invalid deck ID: {}", deck_id));
+    }
+    let pixel_format: Option<PixelFormat> = pixel_format.as_deref().map(str::parse).transpose()?;
+
+    let mut decks_guard = state.decks.lock().map_err(|e| e.to_string())?;
+    let deck = decks_guard.get_mut(&deck_id).ok_or("Deck not found")?;
+
+    if let Some(ref mut renderer) = deck.renderer {
+        if renderer.is_running() {
+            let command = RendererCommand::SetVideoOutput {
+                enabled,
+                device_path: device_path.clone(),
+                pixel_format,
+            };
+            renderer.send_command(&command)?;
+            deck.restore.video_output = enabled.then_some(command);
+            let status = if enabled {
+                format!("Video output enabled on deck {} ({}, {})",
+                    deck_id,
+                    device_path.unwrap_or_else(|| "/dev/video10".to_string()),
+                    pixel_format.unwrap_or(PixelFormat::Yuyv))
+            } else {
+                format!("Video output disabled on deck {}", deck_id)
+            };
+            return Ok(status);
+        }
+    }
+
+    Err(format!("Deck {} not running", deck_id))
+}
+
+// ============ NDI Output Commands ============
+
+/// Check if NDI runtime is available
+#[tauri::command]
+fn is_ndi_available() -> bool {
+    opendrop_core::video::NdiOutput::is_available()
+}
+
+/// Enable NDI output on a deck (streams to network)
+#[tauri::command]
+#[allow(clippy::too_many_arguments)]
+fn set_deck_ndi_output(
+    state: State<'_, AppState>,
+    deck_id: u8,
+    enabled: bool,
+    name: Option<String>,
+    groups: Option<String>,
+    failover: Option<String>,
+    clock_video: Option<bool>,
+    pixel_format: Option<String>,
+) -> Result<String, String> {
+    if deck_id >= MAX_DECKS {
+        return Err(format!("Invalid deck ID: {}", deck_id));
+    }
+    let pixel_format: Option<PixelFormat> = pixel_format.as_deref().map(str::parse).transpose()?;
+    // Empty fields mean the NDI defaults
+    let groups = groups.map(|g| g.trim().to_string()).filter(|g| !g.is_empty());
+    let failover = failover.map(|f| f.trim().to_string()).filter(|f| !f.is_empty());
+
+    let mut decks_guard = state.decks.lock().map_err(|e| e.to_string())?;
+    let deck = decks_guard.get_mut(&deck_id).ok_or("Deck not found")?;
+
+    if let Some(ref mut renderer) = deck.renderer {
+        if renderer.is_running() {
+            let command = RendererCommand::SetNdiOutput {
+                enabled,
+                name: name.clone(),
+                groups,
+                failover,
+                clock_video,
+                pixel_format,
+            };
+            renderer.send_command(&command)?;
+            deck.restore.ndi_output = enabled.then_some(command);
+            renderer.ndi_enabled = enabled;
+            // Resend the tempo to the new sender on the next tick
+            renderer.ndi_bpm = None;
+            let status = if enabled {
+                format!("NDI output enabled on deck {} ({})",
+                    deck_id,
+                    name.unwrap_or_else(|| format!("OpenDrop Deck {}", deck_id + 1)))
+            } else {
+                format!("NDI output disabled on deck {}", deck_id)
+            };
+            return Ok(status);
+        }
+    }
+
+    Err(format!("Deck {} not running", deck_id))
+}
+
+// ============ Syphon Output Commands ============
+
+/// Check if Syphon.framework is available (macOS only)
+#[tauri::command]
+fn is_syphon_available() -> bool {
+    opendrop_core::video::SyphonOutput::is_available()
+}
+
+/// Enable Syphon output on a deck (shares frames with OBS, Resolume, etc.)
+#[tauri::command]
+fn set_deck_syphon_output(
+    state: State<'_, AppState>,
+    deck_id: u8,
+    enabled: bool,
+    name: Option<String>,
+) -> Result<String, String> {
+    if deck_id >= MAX_DECKS {
+        return Err(format!("Invalid deck ID: {}", deck_id));
+    }
+
+    let mut decks_guard = state.decks.lock().map_err(|e| e.to_string())?;
+    let deck = decks_guard.get_mut(&deck_id).ok_or("Deck not found")?;
+
+    if let Some(ref mut renderer) = deck.renderer {
+        if renderer.is_running() {
+            renderer.send_command(&RendererCommand::SetSyphonOutput {
+                enabled,
+                name: name.clone(),
+            })?;
+            let status = if enabled {
+                format!("Syphon output enabled on deck {} ({})",
+                    deck_id,
+                    name.unwrap_or_else(|| format!("OpenDrop Deck {}", deck_id + 1)))
+            } else {
+                format!("Syphon output disabled on deck {}", deck_id)
+            };
+            return Ok(status);
+        }
+    }
+
+    Err(format!("Deck {} not running", deck_id))
+}
+
+/// Cap a live output's frame rate (e.g. NDI at 30 while rendering at 60);
+/// `None` sends every rendered frame again
+#[tauri::command]
+fn set_deck_output_fps(
+    state: State<'_, AppState>,
+    deck_id: u8,
+    output: String,
+    fps: Option<u32>,
+) -> Result<String, String> {
+    if deck_id >= MAX_DECKS {
+        return Err(format!("Invalid deck ID: {}", deck_id));
+    }
+    let backend: OutputBackend = output.parse()?;
+    if !matches!(
+        backend,
+        OutputBackend::V4l2Loopback
+            | OutputBackend::Spout
+            | OutputBackend::Ndi
+            | OutputBackend::Syphon
+            | OutputBackend::VirtualCamera
+    ) {
+        return Err(format!("{} output has no FPS cap", backend));
+    }
+    if let Some(fps) = fps {
+        if fps == 0 || fps > MAX_OUTPUT_FPS {
+            return Err(format!("Output frame rate must be 1-{} fps", MAX_OUTPUT_FPS));
+        }
+    }
+
+    let mut decks_guard = state.decks.lock().map_err(|e| e.to_string())?;
+    let deck = decks_guard.get_mut(&deck_id).ok_or("Deck not found")?;
+
+    if let Some(ref mut renderer) = deck.renderer {
+        if renderer.is_running() {
+            renderer.send_command(&RendererCommand::SetOutputFps { output: backend, fps })?;
+            let status = match fps {
+                Some(fps) => format!("{} output on deck {} capped at {} fps", backend, deck_id, fps),
+                None => format!("{} output on deck {} sends every frame", backend, deck_id),
+            };
+            return Ok(status);
+        }
+    }
+
+    Err(format!("Deck {} not running", deck_id))
+}
+
+/// Key the dark background out of a deck's NDI, Spout and Syphon outputs,
+/// so receivers can composite the visualization over other video
+#[tauri::command]
+fn set_deck_transparent_background(
+    state: State<'_, AppState>,
+    deck_id: u8,
+    enabled: bool,
+) -> Result<String, String> {
+    if deck_id >= MAX_DECKS {
+        return Err(format!("Invalid deck ID: {}", deck_id));
+    }
+
+    let mut decks_guard = state.decks.lock().map_err(|e| e.to_string())?;
+    let deck = decks_guard.get_mut(&deck_id).ok_or("Deck not found")?;
+
+    if let Some(ref mut renderer) = deck.renderer {
+        if renderer.is_running() {
+            renderer.send_command(&RendererCommand::SetTransparentBackground { enabled })?;
+            let status = if enabled {
+                format!("Transparent background enabled on deck {}", deck_id)
+            } else {
+                format!("Transparent background disabled on deck {}", deck_id)
+            };
+            return Ok(status);
+        }
+    }
+
+    Err(format!("Deck {} not running", deck_id))
+}
+
+/// Blend a PNG logo onto a deck's outputs and recordings, or remove it
+/// with `None`
+#[tauri::command]
+fn set_deck_overlay(
+    state: State<'_, AppState>,
+    deck_id: u8,
+    overlay: Option<OverlayConfig>,
+) -> Result<String, String> {
+    if deck_id >= MAX_DECKS {
+        return Err(format!("Invalid deck ID: {}", deck_id));
+    }
+    if let Some(ref config) = overlay {
+        config.validate().map_err(|e| e.to_string())?;
+        if !config.path.is_file() {
+            return Err(format!("Logo not found: {}", config.path.display()));
+        }
+    }
+
+    let mut decks_guard = state.decks.lock().map_err(|e| e.to_string())?;
+    let deck = decks_guard.get_mut(&deck_id).ok_or("Deck not found")?;
+
+    if let Some(ref mut renderer) = deck.renderer {
+        if renderer.is_running() {
+            let status = match overlay {
+                Some(ref config) => format!(
+                    "Logo {} on deck {} ({})",
+                    config.path.display(),
+                    deck_id,
+                    config.position
+                ),
+                None => format!("Logo removed from deck {}", deck_id),
+            };
+            renderer.send_command(&RendererCommand::SetOverlay { overlay })?;
+            return Ok(status);
+        }
+    }
+
+    Err(format!("Deck {} not running", deck_id))
+}
+
+/// Lock or unlock a deck's current preset: while locked, auto-cycle and
+/// projectM don't switch away from it (manual loads still do)
+#[tauri::command]
+fn toggle_preset_lock(state: State<'_, AppState>, deck_id: u8) -> Result<String, String> {
+    if deck_id >= MAX_DECKS {
+        return Err(format!("Invalid deck ID: {}", deck_id));
+    }
+
+    let mut decks_guard = state.decks.lock().map_err(|e| e.to_string())?;
+    let deck = decks_guard.get_mut(&deck_id).ok_or("Deck not found")?;
+
+    let locked = !deck.preset_locked;
+    if let Some(ref mut renderer) = deck.renderer {
+        if renderer.is_running() {
+            renderer.send_command(&RendererCommand::LockPreset { locked })?;
+        }
+    }
+    deck.preset_locked = locked;
+    Ok(format!(
+        "Preset {} on deck {}",
+        if locked { "locked" } else { "unlocked" },
+        deck_id
+    ))
+}
+
+/// Trade a deck's picture quality for frame rate: projectM's warp mesh size,
+/// its FPS hint, and the texture size it renders at before being stretched
+/// to the frame (0 = frame size). Kept for the deck's next start.
+#[tauri::command]
+fn set_deck_quality(
+    state: State<'_, AppState>,
+    deck_id: u8,
+    quality: RenderQuality,
+) -> Result<String, String> {
+    if deck_id >= MAX_DECKS {
+        return Err(format!("Invalid deck ID: {}", deck_id));
+    }
+    quality.validate().map_err(|e| e.to_string())?;
+
+    let mut decks_guard = state.decks.lock().map_err(|e| e.to_string())?;
+    let deck = decks_guard.get_mut(&deck_id).ok_or("Deck not found")?;
+
+    if let Some(ref mut renderer) = deck.renderer {
+        if renderer.is_running() {
+            renderer.send_command(&RendererCommand::SetQuality { quality })?;
+        }
+    }
+    deck.quality = quality;
+    Ok(format!(
+        "Deck {} quality: mesh {}x{}, {} fps, texture size {}",
+        deck_id, quality.mesh_width, quality.mesh_height, quality.fps, quality.texture_size
+    ))
+}
+
+/// Limit how many frames per second a deck draws, below the display's
+/// refresh rate, to save power or match a projector; None removes the limit.
+/// Kept for the deck's next start.
+#[tauri::command]
+fn set_deck_fps_cap(state: State<'_, AppState>, deck_id: u8, fps: Option<u32>) -> Result<String, String> {
+    if deck_id >= MAX_DECKS {
+        return Err(format!("Invalid deck ID: {}", deck_id));
+    }
+    if let Some(fps) = fps {
+        if fps == 0 || fps > MAX_OUTPUT_FPS {
+            return Err(format!("FPS cap must be 1-{} fps", MAX_OUTPUT_FPS));
+        }
+    }
+
+    let mut decks_guard = state.decks.lock().map_err(|e| e.to_string())?;
+    let deck = decks_guard.get_mut(&deck_id).ok_or("Deck not found")?;
+
+    if let Some(ref mut renderer) = deck.renderer {
+        if renderer.is_running() {
+            renderer.send_command(&RendererCommand::SetFpsCap { fps })?;
+        }
+    }
+    deck.fps_cap = fps;
+    Ok(match fps {
+        Some(fps) => format!("Deck {} capped at {} fps", deck_id, fps),
+        None => format!("Deck {} frame rate uncapped", deck_id),
+    })
+}
+
+/// Draw a PNG, still or looping video (through ffmpeg) under a deck's
+/// visualization, or remove it with `None`. Kept for the deck's next start.
+#[tauri::command]
+fn set_deck_background(
+    state: State<'_, AppState>,
+    deck_id: u8,
+    background: Option<BackgroundConfig>,
+) -> Result<String, String> {
+    if deck_id >= MAX_DECKS {
+        return Err(format!("Invalid deck ID: {}", deck_id));
+    }
+    if let Some(ref config) = background {
+        config.validate().map_err(|e| e.to_string())?;
+        if !config.path.is_file() {
+            return Err(format!("Background not found: {}", config.path.display()));
+        }
+    }
+
+    let mut decks_guard = state.decks.lock().map_err(|e| e.to_string())?;
+    let deck = decks_guard.get_mut(&deck_id).ok_or("Deck not found")?;
+
+    if let Some(ref mut renderer) = deck.renderer {
+        if renderer.is_running() {
+            renderer.send_command(&RendererCommand::SetBackground {
+                background: background.clone(),
+            })?;
+        }
+    }
+    let status = match background {
+        Some(ref config) => format!("Background {} on deck {}", config.path.display(), deck_id),
+        None => format!("Background removed from deck {}", deck_id),
+    };
+    deck.background = background;
+    Ok(status)
+}
+
+/// Show FPS, frame times, the preset and active outputs on a deck's window
+/// (not its outputs)
+#[tauri::command]
+fn set_deck_hud(state: State<'_, AppState>, deck_id: u8, enabled: bool) -> Result<String, String> {
+    if deck_id >= MAX_DECKS {
+        return Err(format!("Invalid deck ID: {}", deck_id));
+    }
+
+    let mut decks_guard = state.decks.lock().map_err(|e| e.to_string())?;
+    let deck = decks_guard.get_mut(&deck_id).ok_or("Deck not found")?;
+
+    if let Some(ref mut renderer) = deck.renderer {
+        if renderer.is_running() {
+            renderer.send_command(&RendererCommand::SetHud { enabled })?;
+            return Ok(format!(
+                "HUD {} on deck {}",
+                if enabled { "shown" } else { "hidden" },
+                deck_id
+            ));
+        }
+    }
+
+    Err(format!("Deck {} not running", deck_id))
+}
+
+/// Show text such as a track title over a deck's outputs, or fade it out
+/// with `None`
+#[tauri::command]
+fn set_deck_overlay_text(
+    state: State<'_, AppState>,
+    deck_id: u8,
+    text: Option<TextOverlayConfig>,
+) -> Result<String, String> {
+    if deck_id >= MAX_DECKS {
+        return Err(format!("Invalid deck ID: {}", deck_id));
+    }
+    if let Some(ref config) = text {
+        config.validate().map_err(|e| e.to_string())?;
+        if let Some(ref font) = config.font_path {
+            if !font.is_file() {
+                return Err(format!("Font not found: {}", font.display()));
+            }
         }
     }
 
-    #[cfg(not(any(target_os = "linux", target_os = "windows", target_os = "macos")))]
-    {
-        monitors.push(MonitorInfo {
-            index: 0,
-            name: "Primary".to_string(),
-            width: 1920,
-            height: 1080,
-            is_primary: true,
-        });
+    let mut decks_guard = state.decks.lock().map_err(|e| e.to_string())?;
+    let deck = decks_guard.get_mut(&deck_id).ok_or("Deck not found")?;
+
+    if let Some(ref mut renderer) = deck.renderer {
+        if renderer.is_running() {
+            let status = match text {
+                Some(ref config) => format!("Text on deck {} ({})", deck_id, config.position),
+                None => format!("Text cleared from deck {}", deck_id),
+            };
+            renderer.send_command(&RendererCommand::SetOverlayText { text })?;
+            return Ok(status);
+        }
     }
 
-    monitors
+    Err(format!("Deck {} not running", deck_id))
 }
 
-// ============ Video Output Commands ============
-
-/// List available video output devices (v4l2loopback on Linux, Spout on Windows)
+/// Show a test pattern (bars, grid and deck number) on a deck's window and
+/// outputs instead of the visualization
 #[tauri::command]
-fn list_video_outputs() -> Vec<String> {
-    #[cfg(target_os = "linux")]
-    {
-        opendrop_core::video::V4l2Output::list_devices()
-            .into_iter()
-            .map(|d| format!("{}:{}", d.path.display(), d.name))
-            .collect()
+fn set_output_test_pattern(
+    state: State<'_, AppState>,
+    deck_id: u8,
+    enabled: bool,
+) -> Result<String, String> {
+    if deck_id >= MAX_DECKS {
+        return Err(format!("Invalid deck ID: {}", deck_id));
     }
-    #[cfg(target_os = "windows")]
-    {
-        // Spout is always available if SpoutLibrary.dll is present
-        if opendrop_core::video::SpoutOutput::is_available() {
-            vec!["Spout:OpenDrop".to_string()]
-        } else {
-            vec![]
+
+    let mut decks_guard = state.decks.lock().map_err(|e| e.to_string())?;
+    let deck = decks_guard.get_mut(&deck_id).ok_or("Deck not found")?;
+
+    if let Some(ref mut renderer) = deck.renderer {
+        if renderer.is_running() {
+            renderer.send_command(&RendererCommand::SetTestPattern { enabled })?;
+            return Ok(format!(
+                "Test pattern {} on deck {}",
+                if enabled { "shown" } else { "hidden" },
+                deck_id
+            ));
         }
     }
-    #[cfg(not(any(target_os = "linux", target_os = "windows")))]
-    {
-        vec![]
-    }
+
+    Err(format!("Deck {} not running", deck_id))
 }
 
-/// Enable video output on a deck (sends frames to v4l2loopback)
+/// Check if softcam.dll is available (Windows only)
 #[tauri::command]
-fn set_deck_video_output(
+fn is_virtual_camera_available() -> bool {
+    opendrop_core::video::VirtualCameraOutput::is_available()
+}
+
+/// Present a deck as a virtual webcam (Zoom, Teams, browsers, etc.)
+#[tauri::command]
+fn set_deck_virtual_camera(
     state: State<'_, AppState>,
     deck_id: u8,
     enabled: bool,
-    device_path: Option<String>,
 ) -> Result<String, String> {
     if deck_id >= MAX_DECKS {
         return Err(format!("Invalid deck ID: {}", deck_id));
@@ -2446,16 +6390,11 @@ fn set_deck_video_output(
 
     if let Some(ref mut renderer) = deck.renderer {
         if renderer.is_running() {
-            renderer.send_command(&RendererCommand::SetVideoOutput {
-                enabled,
-                device_path: device_path.clone(),
-            })?;
+            renderer.send_command(&RendererCommand::SetVirtualCamera { enabled })?;
             let status = if enabled {
-                format!("Video output enabled on deck {} ({})",
-                    deck_id,
-                    device_path.unwrap_or_else(|| "/dev/video10".to_string()))
+                format!("Virtual camera enabled on deck {}", deck_id)
             } else {
-                format!("Video output disabled on deck {}", deck_id)
+                format!("Virtual camera disabled on deck {}", deck_id)
             };
             return Ok(status);
         }
@@ -2464,41 +6403,70 @@ fn set_deck_video_output(
     Err(format!("Deck {} not running", deck_id))
 }
 
-// ============ NDI Output Commands ============
+// ============ Video Input Commands ============
 
-/// Check if NDI runtime is available
+/// List the video input backends that can receive on this system
 #[tauri::command]
-fn is_ndi_available() -> bool {
-    opendrop_core::video::NdiOutput::is_available()
+fn list_video_inputs() -> Vec<InputBackend> {
+    [InputBackend::Spout, InputBackend::Ndi, InputBackend::Screen]
+        .into_iter()
+        .filter(|backend| backend.is_available())
+        .collect()
 }
 
-/// Enable NDI output on a deck (streams to network)
+/// Mix a Spout or NDI source or a screen capture into a deck (camera feeds,
+/// other VJ software, slides)
+///
+/// For screen capture `source` is a region as `WIDTHxHEIGHT+X+Y`; `placement`
+/// is over (default) or under the visualization.
 #[tauri::command]
-fn set_deck_ndi_output(
+#[allow(clippy::too_many_arguments)]
+fn set_deck_input_layer(
     state: State<'_, AppState>,
     deck_id: u8,
     enabled: bool,
-    name: Option<String>,
+    backend: InputBackend,
+    source: Option<String>,
+    blend: Option<String>,
+    placement: Option<String>,
+    opacity: Option<f32>,
 ) -> Result<String, String> {
     if deck_id >= MAX_DECKS {
         return Err(format!("Invalid deck ID: {}", deck_id));
     }
+    let blend: InputBlend = blend.as_deref().unwrap_or("normal").parse()?;
+    let placement: InputPlacement = placement.as_deref().unwrap_or("over").parse()?;
+    if backend == InputBackend::Screen {
+        if let Some(region) = source.as_deref().filter(|region| !region.trim().is_empty()) {
+            region.parse::<ScreenRegion>()?;
+        }
+    }
+    let opacity = opacity.unwrap_or(1.0).clamp(0.0, 1.0);
 
     let mut decks_guard = state.decks.lock().map_err(|e| e.to_string())?;
     let deck = decks_guard.get_mut(&deck_id).ok_or("Deck not found")?;
 
     if let Some(ref mut renderer) = deck.renderer {
         if renderer.is_running() {
-            renderer.send_command(&RendererCommand::SetNdiOutput {
+            renderer.send_command(&RendererCommand::SetInputLayer {
                 enabled,
-                name: name.clone(),
+                backend,
+                source: source.clone(),
+                blend,
+                placement,
+                opacity,
             })?;
             let status = if enabled {
-                format!("NDI output enabled on deck {} ({})",
+                format!("{} input enabled on deck {} ({})",
+                    backend,
                     deck_id,
-                    name.unwrap_or_else(|| format!("OpenDrop Deck {}", deck_id + 1)))
+                    source.unwrap_or_else(|| if backend == InputBackend::Screen {
+                        "whole screen".to_string()
+                    } else {
+                        "first source".to_string()
+                    }))
             } else {
-                format!("NDI output disabled on deck {}", deck_id)
+                format!("Input layer disabled on deck {}", deck_id)
             };
             return Ok(status);
         }
@@ -2507,6 +6475,252 @@ fn set_deck_ndi_output(
     Err(format!("Deck {} not running", deck_id))
 }
 
+/// Change how a deck's input layer is mixed in
+#[tauri::command]
+fn set_deck_input_mix(
+    state: State<'_, AppState>,
+    deck_id: u8,
+    blend: String,
+    opacity: f32,
+    placement: Option<String>,
+) -> Result<String, String> {
+    if deck_id >= MAX_DECKS {
+        return Err(format!("Invalid deck ID: {}", deck_id));
+    }
+    let blend: InputBlend = blend.parse()?;
+    let placement = placement.as_deref().map(str::parse::<InputPlacement>).transpose()?;
+
+    let mut decks_guard = state.decks.lock().map_err(|e| e.to_string())?;
+    let deck = decks_guard.get_mut(&deck_id).ok_or("Deck not found")?;
+
+    if let Some(ref mut renderer) = deck.renderer {
+        if renderer.is_running() {
+            renderer.send_command(&RendererCommand::SetInputMix {
+                blend,
+                opacity: opacity.clamp(0.0, 1.0),
+                placement,
+            })?;
+            return Ok(format!("Deck {} input mix set to {:?}", deck_id, blend));
+        }
+    }
+
+    Err(format!("Deck {} not running", deck_id))
+}
+
+// ============ Recording Commands ============
+
+/// Start recording a deck's output (needs ffmpeg)
+///
+/// `codec` is h264 (MP4, default), hap, hap_q or prores (QuickTime); the
+/// path's extension is corrected to match the container.
+#[tauri::command]
+fn start_recording(
+    state: State<'_, AppState>,
+    deck_id: u8,
+    path: String,
+    fps: Option<u32>,
+    bitrate: Option<u32>,
+    codec: Option<String>,
+) -> Result<String, String> {
+    if deck_id >= MAX_DECKS {
+        return Err(format!("Invalid deck ID: {}", deck_id));
+    }
+    let fps = fps.unwrap_or(opendrop_core::video::recorder::DEFAULT_RECORDING_FPS);
+    if fps == 0 || fps > MAX_RECORDING_FPS {
+        return Err(format!("Recording frame rate must be 1-{} fps", MAX_RECORDING_FPS));
+    }
+    let bitrate = bitrate.unwrap_or(opendrop_core::video::recorder::DEFAULT_RECORDING_BITRATE_KBPS);
+    if bitrate == 0 {
+        return Err("Recording bitrate must be positive".to_string());
+    }
+    if !opendrop_core::video::VideoRecorder::is_available() {
+        return Err("Recording needs ffmpeg installed and on the PATH".to_string());
+    }
+    let codec: RecordingCodec = codec.as_deref().unwrap_or("h264").parse()?;
+    if !opendrop_core::video::VideoRecorder::supports_codec(codec) {
+        return Err(format!(
+            "This ffmpeg build has no {} encoder ({}); install a full ffmpeg build",
+            codec,
+            codec.encoder()
+        ));
+    }
+    let path = codec
+        .output_path(std::path::Path::new(&path))
+        .to_string_lossy()
+        .to_string();
+
+    let mut decks_guard = state.decks.lock().map_err(|e| e.to_string())?;
+    let deck = decks_guard.get_mut(&deck_id).ok_or("Deck not found")?;
+
+    if let Some(ref mut renderer) = deck.renderer {
+        if renderer.is_running() {
+            renderer.start_recording(path.clone(), fps, bitrate, codec)?;
+            return Ok(format!("Recording deck {} to {}", deck_id, path));
+        }
+    }
+
+    Err(format!("Deck {} not running", deck_id))
+}
+
+/// Stop a deck's recording; the renderer finalizes the file
+#[tauri::command]
+fn stop_recording(state: State<'_, AppState>, deck_id: u8) -> Result<String, String> {
+    if deck_id >= MAX_DECKS {
+        return Err(format!("Invalid deck ID: {}", deck_id));
+    }
+
+    let mut decks_guard = state.decks.lock().map_err(|e| e.to_string())?;
+    let deck = decks_guard.get_mut(&deck_id).ok_or("Deck not found")?;
+
+    if let Some(ref mut renderer) = deck.renderer {
+        if renderer.is_running() {
+            let recording = renderer.recording().ok_or("Not recording")?;
+            renderer.send_command(&RendererCommand::StopRecording)?;
+            return Ok(format!("Stopped recording {}", recording.path));
+        }
+    }
+
+    Err(format!("Deck {} not running", deck_id))
+}
+
+/// Start writing a deck's output as numbered PNGs to a directory
+#[tauri::command]
+fn start_frame_export(
+    state: State<'_, AppState>,
+    deck_id: u8,
+    directory: String,
+    fps: Option<u32>,
+) -> Result<String, String> {
+    if deck_id >= MAX_DECKS {
+        return Err(format!("Invalid deck ID: {}", deck_id));
+    }
+    let fps = fps.unwrap_or(opendrop_core::video::sequence::DEFAULT_SEQUENCE_FPS);
+    if fps == 0 || fps > MAX_RECORDING_FPS {
+        return Err(format!("Export frame rate must be 1-{} fps", MAX_RECORDING_FPS));
+    }
+
+    let mut decks_guard = state.decks.lock().map_err(|e| e.to_string())?;
+    let deck = decks_guard.get_mut(&deck_id).ok_or("Deck not found")?;
+
+    if let Some(ref mut renderer) = deck.renderer {
+        if renderer.is_running() {
+            renderer.start_frame_export(directory.clone(), fps)?;
+            return Ok(format!("Exporting deck {} frames to {}", deck_id, directory));
+        }
+    }
+
+    Err(format!("Deck {} not running", deck_id))
+}
+
+/// Stop a deck's PNG export
+#[tauri::command]
+fn stop_frame_export(state: State<'_, AppState>, deck_id: u8) -> Result<String, String> {
+    if deck_id >= MAX_DECKS {
+        return Err(format!("Invalid deck ID: {}", deck_id));
+    }
+
+    let mut decks_guard = state.decks.lock().map_err(|e| e.to_string())?;
+    let deck = decks_guard.get_mut(&deck_id).ok_or("Deck not found")?;
+
+    if let Some(ref mut renderer) = deck.renderer {
+        if renderer.is_running() {
+            let export = renderer.frame_export().ok_or("Not exporting frames")?;
+            renderer.send_command(&RendererCommand::StopFrameExport)?;
+            return Ok(format!("Stopped frame export to {}", export.directory));
+        }
+    }
+
+    Err(format!("Deck {} not running", deck_id))
+}
+
+/// Record `beats` beats of a deck at the current tempo and export them as a
+/// seamlessly looping WebM or GIF (needs ffmpeg)
+///
+/// `crossfade` blends the last seconds into the first (default 1 s, 0 for a
+/// hard cut). Progress and the outcome are reported in `DeckInfo`.
+#[tauri::command]
+fn start_loop_export(
+    state: State<'_, AppState>,
+    deck_id: u8,
+    path: String,
+    beats: u32,
+    format: Option<String>,
+    crossfade: Option<f64>,
+) -> Result<String, String> {
+    if deck_id >= MAX_DECKS {
+        return Err(format!("Invalid deck ID: {}", deck_id));
+    }
+    if beats == 0 || beats > MAX_LOOP_BEATS {
+        return Err(format!("Loop length must be 1-{} beats", MAX_LOOP_BEATS));
+    }
+    let format: LoopFormat = format.as_deref().unwrap_or("webm").parse()?;
+    let crossfade = crossfade.unwrap_or(opendrop_core::video::loop_export::DEFAULT_LOOP_CROSSFADE_SECS);
+    if !(0.0..=MAX_LOOP_CROSSFADE_SECS).contains(&crossfade) {
+        return Err(format!("Crossfade must be 0-{} seconds", MAX_LOOP_CROSSFADE_SECS));
+    }
+
+    let bpm = {
+        let clock = state.tempo_clock.lock().map_err(|e| e.to_string())?;
+        clock.info(std::time::Instant::now()).beat.bpm
+    };
+    if bpm <= 0.0 {
+        return Err("No tempo yet: play some audio or set the BPM".to_string());
+    }
+    let duration_secs = loop_duration_secs(beats, bpm);
+    if duration_secs > MAX_LOOP_SECS {
+        return Err(format!(
+            "{} beats at {:.0} BPM is longer than {} seconds",
+            beats, bpm, MAX_LOOP_SECS
+        ));
+    }
+    if !opendrop_core::video::VideoRecorder::is_available() {
+        return Err("Loop export needs ffmpeg installed and on the PATH".to_string());
+    }
+    let path = std::path::Path::new(&path)
+        .with_extension(format.extension())
+        .to_string_lossy()
+        .to_string();
+
+    let mut decks_guard = state.decks.lock().map_err(|e| e.to_string())?;
+    let deck = decks_guard.get_mut(&deck_id).ok_or("Deck not found")?;
+
+    if let Some(ref mut renderer) = deck.renderer {
+        if renderer.is_running() {
+            renderer.start_loop_export(path.clone(), format, beats, duration_secs, crossfade)?;
+            return Ok(format!(
+                "Recording {} beats ({:.1}s) of deck {} to {}",
+                beats, duration_secs, deck_id, path
+            ));
+        }
+    }
+
+    Err(format!("Deck {} not running", deck_id))
+}
+
+/// Discard a deck's loop export while it is still recording
+#[tauri::command]
+fn cancel_loop_export(state: State<'_, AppState>, deck_id: u8) -> Result<String, String> {
+    if deck_id >= MAX_DECKS {
+        return Err(format!("Invalid deck ID: {}", deck_id));
+    }
+
+    let mut decks_guard = state.decks.lock().map_err(|e| e.to_string())?;
+    let deck = decks_guard.get_mut(&deck_id).ok_or("Deck not found")?;
+
+    if let Some(ref mut renderer) = deck.renderer {
+        if renderer.is_running() {
+            let export = renderer
+                .loop_export()
+                .filter(|e| e.status == LoopExportStatus::Recording)
+                .ok_or("Not recording a loop")?;
+            renderer.send_command(&RendererCommand::CancelLoopExport)?;
+            return Ok(format!("Cancelled loop export to {}", export.path));
+        }
+    }
+
+    Err(format!("Deck {} not running", deck_id))
+}
+
 /// Set texture search paths for a deck
 #[tauri::command]
 fn set_deck_texture_paths(
@@ -2526,6 +6740,7 @@ fn set_deck_texture_paths(
             renderer.send_command(&RendererCommand::SetTexturePaths {
                 paths: paths.clone(),
             })?;
+            deck.restore.texture_paths = Some(paths.clone());
             return Ok(format!("Set {} texture paths on deck {}", paths.len(), deck_id));
         }
     }
@@ -2549,6 +6764,7 @@ fn set_all_decks_texture_paths(
                     if renderer.send_command(&RendererCommand::SetTexturePaths {
                         paths: paths.clone(),
                     }).is_ok() {
+                        deck.restore.texture_paths = Some(paths.clone());
                         count += 1;
                     }
                 }
@@ -2566,8 +6782,16 @@ fn set_all_decks_texture_paths(
 pub struct MidiStatus {
     pub connected: bool,
     pub learning: bool,
+    /// Control detected in learn mode, waiting to be confirmed
+    pub detected: Option<String>,
     pub port_name: Option<String>,
     pub mapping_count: usize,
+    /// Port OpenDrop publishes to, if the MIDI output is on
+    pub output_port: Option<String>,
+    /// UDP port of the OSC input, if it is on
+    pub osc_port: Option<u16>,
+    /// Connected gamepads, if gamepad input is on
+    pub gamepads: Option<Vec<String>>,
 }
 
 /// MIDI mapping info for frontend
@@ -2578,6 +6802,19 @@ pub struct MidiMappingInfo {
     pub midi_type: String,
     pub action: String,
     pub enabled: bool,
+    /// Whether the action takes a continuous value (and so can use pickup)
+    pub continuous: bool,
+    pub pickup: bool,
+    /// Increment encoding of an endless encoder, `None` for absolute controls
+    pub relative: Option<RelativeEncoding>,
+    /// Minimum time between presses of a trigger (0 = no limit)
+    pub debounce_ms: u32,
+    /// Response curve of a continuous control
+    pub curve: TransformCurve,
+    /// Whether the mapping responds on every MIDI channel
+    pub any_channel: bool,
+    /// Further actions run by the mapping as a macro
+    pub macro_steps: usize,
 }
 
 impl From<&MidiMapping> for MidiMappingInfo {
@@ -2588,10 +6825,32 @@ impl From<&MidiMapping> for MidiMappingInfo {
             midi_type: format!("{:?}", m.midi_message),
             action: format!("{:?}", m.action),
             enabled: m.enabled,
+            continuous: m.action.is_continuous(),
+            pickup: m.pickup,
+            relative: m.value_transform.as_ref().and_then(|t| t.relative),
+            curve: m.value_transform.as_ref().map(|t| t.curve.clone()).unwrap_or_default(),
+            any_channel: matches!(m.midi_message, MidiMessageType::AnyChannel(_)),
+            debounce_ms: m.debounce_ms,
+            macro_steps: m.macro_steps.len(),
         }
     }
 }
 
+/// MIDI action applied from a hardware control, so the frontend can refresh
+#[derive(Clone, Serialize)]
+pub struct MidiActionEvent {
+    pub action: String,
+    pub value: f32,
+}
+
+/// Control detected in learn mode, before the mapping is confirmed
+#[derive(Clone, Serialize)]
+pub struct MidiLearnDetectedEvent {
+    pub midi_type: String,
+    /// e.g. "CC 7 on channel 1"
+    pub description: String,
+}
+
 /// MIDI preset info for frontend
 #[derive(Serialize, Deserialize)]
 pub struct MidiPresetInfo {
@@ -2601,6 +6860,305 @@ pub struct MidiPresetInfo {
     pub mapping_count: usize,
 }
 
+/// State touched by MIDI actions, locked together so a macro applies atomically
+struct MidiTargets<'a> {
+    state: &'a AppState,
+    decks: MutexGuard<'a, HashMap<DeckId, DeckState>>,
+    crossfader: MutexGuard<'a, CrossfaderConfig>,
+    compositor: MutexGuard<'a, CompositorConfig>,
+}
+
+impl<'a> MidiTargets<'a> {
+    /// Lock decks, crossfader and compositor (in the app's lock order)
+    fn lock(state: &'a AppState) -> Option<Self> {
+        let decks = state.decks.lock().ok()?;
+        let crossfader = state.crossfader.lock().ok()?;
+        let compositor = state.compositor.lock().ok()?;
+        Some(Self {
+            state,
+            decks,
+            crossfader,
+            compositor,
+        })
+    }
+
+    /// Run `update` on a deck; false if the deck doesn't exist or it fails
+    fn deck(&mut self, deck: u8, update: impl FnOnce(&mut DeckState) -> bool) -> bool {
+        self.decks.get_mut(&deck).is_some_and(update)
+    }
+
+    /// How renderers started from MIDI launch
+    fn launcher(&self) -> DeckLauncher<'a> {
+        DeckLauncher::new(self.state).unwrap_or(DeckLauncher {
+            gpu: GpuPreference::default(),
+            shared: None,
+        })
+    }
+
+    /// Change one deck's compositor settings; false if the deck doesn't exist
+    fn compositor_deck(
+        &mut self,
+        deck: u8,
+        update: impl FnOnce(&mut DeckCompositorSettings),
+    ) -> bool {
+        self.compositor.deck_settings.get_mut(&deck).map(update).is_some()
+    }
+
+    /// Apply one action; false if it doesn't apply
+    fn apply(&mut self, action: MidiAction, value: f32) -> bool {
+        // Buttons act on press; their release arrives as zero
+        let pressed = value > 0.0;
+        let crossfader = &mut *self.crossfader;
+        match action {
+            MidiAction::DeckStart(deck) if pressed => {
+                let launcher = self.launcher();
+                self.deck(deck, |d| start_from_midi(d, launcher))
+            }
+            MidiAction::DeckStop(deck) if pressed => self.deck(deck, |d| {
+                stop_deck_renderer(d);
+                true
+            }),
+            MidiAction::DeckToggle(deck) if pressed => {
+                let launcher = self.launcher();
+                self.deck(deck, |d| {
+                    if d.is_running() {
+                        stop_deck_renderer(d);
+                        true
+                    } else {
+                        start_from_midi(d, launcher)
+                    }
+                })
+            }
+            MidiAction::DeckVolume(deck) => self.deck(deck, |d| {
+                d.volume = value.clamp(0.0, 1.0);
+                true
+            }),
+            MidiAction::DeckBeatSensitivity(deck) => self.deck(deck, |d| {
+                apply_beat_sensitivity(d, value.clamp(0.0, 1.0) * MIDI_MAX_BEAT_SENSITIVITY).is_ok()
+            }),
+            MidiAction::DeckEffect { deck, effect } => {
+                self.deck(deck, |d| apply_deck_effect(d, effect, value.clamp(0.0, 1.0)).is_ok())
+            }
+            MidiAction::DeckColor { deck, control } => self.deck(deck, |d| {
+                let mut adjust = d.color_adjust;
+                adjust.set(control, value.clamp(0.0, 1.0) * COLOR_RANGE.end());
+                apply_color_adjust(d, adjust, MIDI_COLOR_FADE_SECS).is_ok()
+            }),
+            MidiAction::DeckBlackout(deck) if pressed => {
+                self.deck(deck, |d| apply_blackout(d, !d.blackout, 0.0).is_ok())
+            }
+            MidiAction::DeckFreeze(deck) if pressed => self.deck(deck, |d| apply_freeze(d, !d.frozen).is_ok()),
+            MidiAction::DeckTimeScale(deck) => {
+                self.deck(deck, |d| apply_time_scale(d, time_scale_from_control(value)).is_ok())
+            }
+            MidiAction::DeckStrobe(deck) => self.deck(deck, |d| apply_strobe(d, pressed).is_ok()),
+            MidiAction::NextPreset(deck) | MidiAction::PlaylistNext(deck) if pressed => {
+                let energy_target = self.state.energy.lock().ok().and_then(|e| e.energy());
+                let Ok(preset_energy) = self.state.preset_energy.lock() else {
+                    return false;
+                };
+                self.deck(deck, |d| advance_playlist(d, energy_target, &preset_energy).is_some())
+            }
+            MidiAction::PreviousPreset(deck) | MidiAction::PlaylistPrevious(deck) if pressed => {
+                self.deck(deck, |d| rewind_playlist(d).is_some())
+            }
+            MidiAction::RandomPreset(deck) if pressed => {
+                self.deck(deck, |d| random_playlist_item(d).is_some())
+            }
+            MidiAction::PlaylistToggleShuffle(deck) if pressed => {
+                self.deck(deck, |d| {
+                    d.playlist.shuffle = !d.playlist.shuffle;
+                    true
+                })
+            }
+            MidiAction::PlaylistToggleAutoCycle(deck) if pressed => {
+                self.deck(deck, |d| {
+                    d.set_auto_cycle(!d.playlist.auto_cycle);
+                    true
+                })
+            }
+            MidiAction::ToggleFullscreen(deck) if pressed => {
+                self.deck(deck, |d| toggle_deck_fullscreen(d).is_ok())
+            }
+            MidiAction::CrossfaderPosition => {
+                crossfader.position = value.clamp(0.0, 1.0);
+                true
+            }
+            MidiAction::CompositorDeckOpacity(deck) => {
+                self.compositor_deck(deck, |s| s.opacity = value.clamp(0.0, 1.0))
+            }
+            MidiAction::CompositorBlendModeNext(deck) if pressed => {
+                self.compositor_deck(deck, |s| s.blend_mode = s.blend_mode.next())
+            }
+            MidiAction::CompositorDeckToggle(deck) if pressed => {
+                self.compositor_deck(deck, |s| s.enabled = !s.enabled)
+            }
+            MidiAction::LoadPlaylistIndex { deck, index } if pressed => {
+                self.deck(deck, |d| jump_to_playlist_index(d, index).is_some())
+            }
+            MidiAction::LoadPlaylistBank { deck, bank } if pressed => {
+                self.deck(deck, |d| load_playlist_bank(d, bank).is_some())
+            }
+            MidiAction::CrossfaderCurve if pressed => {
+                crossfader.curve = crossfader.curve.next();
+                true
+            }
+            MidiAction::CrossfaderToggle if pressed => {
+                crossfader.position = if crossfader.position < 0.5 { 1.0 } else { 0.0 };
+                true
+            }
+            MidiAction::CrossfaderAssign { deck, side } if pressed && deck < MAX_DECKS => {
+                let on_side = match side {
+                    CrossfaderSide::A => crossfader.side_a.contains(&deck),
+                    CrossfaderSide::B => crossfader.side_b.contains(&deck),
+                };
+                crossfader.assign_deck(deck, if on_side { None } else { Some(side) });
+                true
+            }
+            MidiAction::CrossfaderCut(side) => {
+                if pressed {
+                    crossfader.cut_from = crossfader.cut_from.or(Some(crossfader.position));
+                    crossfader.position = match side {
+                        CrossfaderSide::A => 0.0,
+                        CrossfaderSide::B => 1.0,
+                    };
+                } else if let Some(position) = crossfader.cut_from.take() {
+                    crossfader.position = position;
+                }
+                true
+            }
+            _ => false,
+        }
+    }
+}
+
+/// Apply a mapped MIDI action to the app state
+fn dispatch_midi_action(app: &AppHandle, action: MidiAction, value: f32) {
+    dispatch_midi_actions(app, &[(action, value)]);
+}
+
+/// Apply a macro's actions in order under one set of locks, so nothing
+/// renders a half-applied scene
+fn dispatch_midi_actions(app: &AppHandle, actions: &[(MidiAction, f32)]) {
+    let state = app.state::<AppState>();
+    let handled: Vec<(MidiAction, f32)> = {
+        let Some(mut targets) = MidiTargets::lock(&state) else {
+            return;
+        };
+        actions
+            .iter()
+            .copied()
+            .filter(|&(action, value)| targets.apply(action, value))
+            .collect()
+    };
+
+    for (action, value) in handled {
+        let _ = app.emit(
+            "midi_action",
+            MidiActionEvent {
+                action: format!("{:?}", action),
+                value,
+            },
+        );
+    }
+}
+
+/// Start a deck from MIDI with the renderer defaults, on its last preset
+fn start_from_midi(deck: &mut DeckState, launcher: DeckLauncher) -> bool {
+    let preset = deck.preset_path.clone();
+    deck.remember_geometry();
+    match start_deck_renderer(deck, None, None, None, preset, None, None, None, launcher) {
+        Ok(_) => true,
+        Err(e) => {
+            tracing::warn!("MIDI deck start failed: {}", e);
+            false
+        }
+    }
+}
+
+/// Current value of a continuous action's target, for MIDI soft takeover
+fn midi_action_value(app: &AppHandle, action: MidiAction) -> Option<f32> {
+    let state = app.state::<AppState>();
+    match action {
+        MidiAction::DeckVolume(deck) => state.decks.lock().ok()?.get(&deck).map(|d| d.volume),
+        MidiAction::DeckBeatSensitivity(deck) => state
+            .decks
+            .lock()
+            .ok()?
+            .get(&deck)
+            .map(|d| d.beat_sensitivity / MIDI_MAX_BEAT_SENSITIVITY),
+        MidiAction::DeckEffect { deck, effect } => {
+            state.decks.lock().ok()?.get(&deck).map(|d| d.effects.get(effect))
+        }
+        MidiAction::DeckColor { deck, control } => state
+            .decks
+            .lock()
+            .ok()?
+            .get(&deck)
+            .map(|d| d.color_adjust.get(control) / COLOR_RANGE.end()),
+        MidiAction::DeckTimeScale(deck) => {
+            state.decks.lock().ok()?.get(&deck).map(|d| time_scale_to_control(d.time_scale))
+        }
+        MidiAction::CrossfaderPosition => Some(state.crossfader.lock().ok()?.position),
+        MidiAction::CompositorDeckOpacity(deck) => {
+            state.compositor.lock().ok()?.deck_settings.get(&deck).map(|s| s.opacity)
+        }
+        _ => None,
+    }
+}
+
+/// Parse a MIDI action name from the frontend; `deck` is used by deck actions
+fn parse_midi_action(action: &str, deck: u8) -> Result<MidiAction, String> {
+    let action = action.to_lowercase();
+    if let Some(effect) = action.strip_prefix("effect_") {
+        let effect = effect.parse()?;
+        return Ok(MidiAction::DeckEffect { deck, effect });
+    }
+    if let Some(control) = action.strip_prefix("color_") {
+        let control = control.parse()?;
+        return Ok(MidiAction::DeckColor { deck, control });
+    }
+    Ok(match action.as_str() {
+        "deck_volume" => MidiAction::DeckVolume(deck),
+        "deck_start" => MidiAction::DeckStart(deck),
+        "deck_stop" => MidiAction::DeckStop(deck),
+        "deck_toggle" => MidiAction::DeckToggle(deck),
+        "next_preset" => MidiAction::NextPreset(deck),
+        "previous_preset" => MidiAction::PreviousPreset(deck),
+        "random_preset" => MidiAction::RandomPreset(deck),
+        "crossfader" | "crossfader_position" => MidiAction::CrossfaderPosition,
+        "beat_sensitivity" => MidiAction::DeckBeatSensitivity(deck),
+        "blackout" => MidiAction::DeckBlackout(deck),
+        "strobe" => MidiAction::DeckStrobe(deck),
+        "freeze" => MidiAction::DeckFreeze(deck),
+        "time_scale" => MidiAction::DeckTimeScale(deck),
+        "playlist_next" => MidiAction::PlaylistNext(deck),
+        "playlist_previous" => MidiAction::PlaylistPrevious(deck),
+        "playlist_bank" => MidiAction::LoadPlaylistBank { deck, bank: 0 },
+        "shuffle_toggle" => MidiAction::PlaylistToggleShuffle(deck),
+        "auto_cycle_toggle" => MidiAction::PlaylistToggleAutoCycle(deck),
+        "compositor_opacity" => MidiAction::CompositorDeckOpacity(deck),
+        "compositor_blend_next" => MidiAction::CompositorBlendModeNext(deck),
+        "compositor_toggle" => MidiAction::CompositorDeckToggle(deck),
+        "crossfader_curve" => MidiAction::CrossfaderCurve,
+        "crossfader_assign_a" => MidiAction::CrossfaderAssign { deck, side: CrossfaderSide::A },
+        "crossfader_assign_b" => MidiAction::CrossfaderAssign { deck, side: CrossfaderSide::B },
+        "crossfader_cut_a" => MidiAction::CrossfaderCut(CrossfaderSide::A),
+        "crossfader_cut_b" => MidiAction::CrossfaderCut(CrossfaderSide::B),
+        _ => return Err(format!("Unknown action: {}", action)),
+    })
+}
+
+/// Playlist banks selectable by Program Change, in program order
+#[tauri::command]
+fn midi_list_playlist_banks() -> Result<Vec<String>, String> {
+    let dir = playlist_banks_dir().ok_or("No config directory")?;
+    Ok(list_playlist_banks(dir)
+        .iter()
+        .map(|p| p.to_string_lossy().into_owned())
+        .collect())
+}
+
 /// List available MIDI input ports
 #[tauri::command]
 fn list_midi_ports() -> Result<Vec<MidiPortInfo>, String> {
@@ -2630,11 +7188,60 @@ fn midi_get_status(state: State<'_, AppState>) -> Result<MidiStatus, String> {
     Ok(MidiStatus {
         connected: midi_guard.is_connected(),
         learning: midi_guard.is_learning(),
+        detected: midi_guard.learn_detected().map(|t| t.to_string()),
         port_name: midi_guard.connected_port_name().map(String::from),
         mapping_count: midi_guard.get_mappings().len(),
+        output_port: midi_guard.output_name(),
+        osc_port: midi_guard.osc_port(),
+        gamepads: midi_guard.gamepads(),
     })
 }
 
+/// Turn the MIDI output on (the virtual "OpenDrop Out", or an existing port
+/// by name) or off; returns the port in use
+#[tauri::command]
+fn midi_set_output(
+    state: State<'_, AppState>,
+    enabled: bool,
+    port_name: Option<String>,
+) -> Result<Option<String>, String> {
+    let midi_guard = state.midi_controller.lock().map_err(|e| e.to_string())?;
+    if !enabled {
+        midi_guard.disable_output();
+        return Ok(None);
+    }
+    midi_guard
+        .enable_output(port_name.as_deref())
+        .map(Some)
+        .map_err(|e| e.to_string())
+}
+
+/// Turn the OSC input on (default port 8000) or off; returns the port in use
+#[tauri::command]
+fn midi_set_osc(state: State<'_, AppState>, enabled: bool, port: Option<u16>) -> Result<Option<u16>, String> {
+    let mut midi_guard = state.midi_controller.lock().map_err(|e| e.to_string())?;
+    if !enabled {
+        midi_guard.disable_osc();
+        return Ok(None);
+    }
+    midi_guard
+        .enable_osc(port.unwrap_or(DEFAULT_OSC_PORT))
+        .map(Some)
+        .map_err(|e| e.to_string())
+}
+
+/// Turn gamepad input on or off; returns the connected gamepads
+#[tauri::command]
+fn midi_set_gamepad(state: State<'_, AppState>, enabled: bool) -> Result<Option<Vec<String>>, String> {
+    let mut midi_guard = state.midi_controller.lock().map_err(|e| e.to_string())?;
+    if !enabled {
+        midi_guard.disable_gamepads();
+        return Ok(None);
+    }
+    midi_guard.enable_gamepads().map_err(|e| e.to_string())?;
+    Ok(midi_guard.gamepads())
+}
+
 /// Get all MIDI mappings
 #[tauri::command]
 fn midi_get_mappings(state: State<'_, AppState>) -> Result<Vec<MidiMappingInfo>, String> {
@@ -2647,7 +7254,11 @@ fn midi_get_mappings(state: State<'_, AppState>) -> Result<Vec<MidiMappingInfo>,
 }
 
 /// Add a MIDI mapping manually
+///
+/// `lsb_controller` pairs `controller` with a fine CC for a 14-bit control;
+/// `nrpn_parameter` maps an NRPN instead of a CC.
 #[tauri::command]
+#[allow(clippy::too_many_arguments)]
 fn midi_add_mapping(
     state: State<'_, AppState>,
     name: String,
@@ -2655,23 +7266,22 @@ fn midi_add_mapping(
     controller: u8,
     action: String,
     deck_id: Option<u8>,
+    lsb_controller: Option<u8>,
+    nrpn_parameter: Option<u16>,
+    any_channel: Option<bool>,
 ) -> Result<String, String> {
-    let midi_message = MidiMessageType::ControlChange { channel, controller };
+    let midi_message = match (nrpn_parameter, lsb_controller) {
+        (Some(parameter), _) => MidiMessageType::Nrpn { channel, parameter },
+        (None, Some(lsb)) => MidiMessageType::ControlChange14 {
+            channel,
+            msb: controller,
+            lsb,
+        },
+        (None, None) => MidiMessageType::ControlChange { channel, controller },
+    }
+    .with_any_channel(any_channel.unwrap_or(false));
 
-    // Parse action string to MidiAction
-    let deck = deck_id.unwrap_or(0);
-    let action = match action.to_lowercase().as_str() {
-        "deck_volume" => MidiAction::DeckVolume(deck),
-        "deck_start" => MidiAction::DeckStart(deck),
-        "deck_stop" => MidiAction::DeckStop(deck),
-        "deck_toggle" => MidiAction::DeckToggle(deck),
-        "next_preset" => MidiAction::NextPreset(deck),
-        "previous_preset" => MidiAction::PreviousPreset(deck),
-        "random_preset" => MidiAction::RandomPreset(deck),
-        "crossfader" | "crossfader_position" => MidiAction::CrossfaderPosition,
-        "beat_sensitivity" => MidiAction::DeckBeatSensitivity(deck),
-        _ => return Err(format!("Unknown action: {}", action)),
-    };
+    let action = parse_midi_action(&action, deck_id.unwrap_or(0))?;
 
     let mapping = MidiMapping::new(name, midi_message, action);
     let midi_guard = state.midi_controller.lock().map_err(|e| e.to_string())?;
@@ -2680,6 +7290,142 @@ fn midi_add_mapping(
     Ok("Mapping added".to_string())
 }
 
+/// Map a grid of pads to playlist positions of a deck, replacing the deck's
+/// previous pad bank (defaults to the Launchpad's 8x8 grid)
+#[tauri::command]
+fn midi_map_pad_bank(
+    state: State<'_, AppState>,
+    deck_id: u8,
+    grid: Option<PadGrid>,
+) -> Result<String, String> {
+    if deck_id >= MAX_DECKS {
+        return Err(format!("Invalid deck ID: {}", deck_id));
+    }
+
+    let grid = grid.unwrap_or_else(PadGrid::launchpad);
+    let pads = create_pad_bank_mappings(&grid, deck_id);
+    let count = pads.len();
+
+    let midi_guard = state.midi_controller.lock().map_err(|e| e.to_string())?;
+    let mut mappings = midi_guard.get_mappings();
+    mappings.retain(|m| !matches!(m.action, MidiAction::LoadPlaylistIndex { deck, .. } if deck == deck_id));
+    mappings.extend(pads);
+    midi_guard.load_mappings(mappings);
+
+    Ok(format!("Mapped {} pads to deck {} playlist", count, deck_id + 1))
+}
+
+/// Turn soft takeover on or off for a MIDI mapping
+#[tauri::command]
+fn midi_set_pickup(state: State<'_, AppState>, mapping_id: String, pickup: bool) -> Result<String, String> {
+    let id = uuid::Uuid::parse_str(&mapping_id).map_err(|e| e.to_string())?;
+    let midi_guard = state.midi_controller.lock().map_err(|e| e.to_string())?;
+
+    if midi_guard.set_pickup(id, pickup) {
+        Ok(format!("Pickup {}", if pickup { "enabled" } else { "disabled" }))
+    } else {
+        Err("Mapping not found".to_string())
+    }
+}
+
+/// Set the response curve of a MIDI mapping
+#[tauri::command]
+fn midi_set_curve(state: State<'_, AppState>, mapping_id: String, curve: TransformCurve) -> Result<String, String> {
+    if let TransformCurve::Gamma(gamma) = curve {
+        if !(gamma.is_finite() && gamma > 0.0) {
+            return Err(format!("Invalid gamma: {}", gamma));
+        }
+    }
+    let id = uuid::Uuid::parse_str(&mapping_id).map_err(|e| e.to_string())?;
+    let midi_guard = state.midi_controller.lock().map_err(|e| e.to_string())?;
+
+    if midi_guard.set_curve(id, curve) {
+        Ok("Curve updated".to_string())
+    } else {
+        Err("Mapping not found".to_string())
+    }
+}
+
+/// Make a MIDI mapping respond on every channel, or only its own
+#[tauri::command]
+fn midi_set_any_channel(state: State<'_, AppState>, mapping_id: String, any_channel: bool) -> Result<String, String> {
+    let id = uuid::Uuid::parse_str(&mapping_id).map_err(|e| e.to_string())?;
+    let midi_guard = state.midi_controller.lock().map_err(|e| e.to_string())?;
+
+    if midi_guard.set_any_channel(id, any_channel) {
+        Ok(format!("Any channel {}", if any_channel { "enabled" } else { "disabled" }))
+    } else {
+        Err("Mapping not found".to_string())
+    }
+}
+
+/// Limit how often a MIDI trigger mapping fires (0 = no limit)
+#[tauri::command]
+fn midi_set_debounce(state: State<'_, AppState>, mapping_id: String, debounce_ms: u32) -> Result<String, String> {
+    let id = uuid::Uuid::parse_str(&mapping_id).map_err(|e| e.to_string())?;
+    let midi_guard = state.midi_controller.lock().map_err(|e| e.to_string())?;
+
+    if midi_guard.set_debounce(id, debounce_ms) {
+        Ok(format!("Debounce set to {} ms", debounce_ms))
+    } else {
+        Err("Mapping not found".to_string())
+    }
+}
+
+/// Add an action to a MIDI mapping's macro, with a fixed value or the
+/// control's own value when `value` is `None`
+#[tauri::command]
+fn midi_add_macro_step(
+    state: State<'_, AppState>,
+    mapping_id: String,
+    action: String,
+    deck_id: Option<u8>,
+    value: Option<f32>,
+) -> Result<String, String> {
+    let id = uuid::Uuid::parse_str(&mapping_id).map_err(|e| e.to_string())?;
+    let step = MacroStep {
+        action: parse_midi_action(&action, deck_id.unwrap_or(0))?,
+        value: value.map(|v| v.clamp(0.0, 1.0)),
+    };
+    let midi_guard = state.midi_controller.lock().map_err(|e| e.to_string())?;
+
+    if midi_guard.add_macro_step(id, step) {
+        Ok(format!("Added {:?} to macro", step.action))
+    } else {
+        Err("Mapping not found".to_string())
+    }
+}
+
+/// Remove all macro steps of a MIDI mapping
+#[tauri::command]
+fn midi_clear_macro(state: State<'_, AppState>, mapping_id: String) -> Result<String, String> {
+    let id = uuid::Uuid::parse_str(&mapping_id).map_err(|e| e.to_string())?;
+    let midi_guard = state.midi_controller.lock().map_err(|e| e.to_string())?;
+
+    if midi_guard.clear_macro(id) {
+        Ok("Macro cleared".to_string())
+    } else {
+        Err("Mapping not found".to_string())
+    }
+}
+
+/// Make a MIDI mapping read an endless encoder (or absolute again with `None`)
+#[tauri::command]
+fn midi_set_relative(
+    state: State<'_, AppState>,
+    mapping_id: String,
+    encoding: Option<RelativeEncoding>,
+) -> Result<String, String> {
+    let id = uuid::Uuid::parse_str(&mapping_id).map_err(|e| e.to_string())?;
+    let midi_guard = state.midi_controller.lock().map_err(|e| e.to_string())?;
+
+    if midi_guard.set_relative(id, encoding) {
+        Ok("Encoding updated".to_string())
+    } else {
+        Err("Mapping not found".to_string())
+    }
+}
+
 /// Remove a MIDI mapping by ID
 #[tauri::command]
 fn midi_remove_mapping(state: State<'_, AppState>, mapping_id: String) -> Result<String, String> {
@@ -2709,27 +7455,29 @@ fn midi_start_learn(
     name: String,
     deck_id: Option<u8>,
 ) -> Result<String, String> {
-    let deck = deck_id.unwrap_or(0);
-    let midi_action = match action.to_lowercase().as_str() {
-        "deck_volume" => MidiAction::DeckVolume(deck),
-        "deck_start" => MidiAction::DeckStart(deck),
-        "deck_stop" => MidiAction::DeckStop(deck),
-        "deck_toggle" => MidiAction::DeckToggle(deck),
-        "next_preset" => MidiAction::NextPreset(deck),
-        "previous_preset" => MidiAction::PreviousPreset(deck),
-        "random_preset" => MidiAction::RandomPreset(deck),
-        "crossfader" | "crossfader_position" => MidiAction::CrossfaderPosition,
-        "beat_sensitivity" => MidiAction::DeckBeatSensitivity(deck),
-        "playlist_next" => MidiAction::PlaylistNext(deck),
-        "playlist_previous" => MidiAction::PlaylistPrevious(deck),
-        "shuffle_toggle" => MidiAction::PlaylistToggleShuffle(deck),
-        "auto_cycle_toggle" => MidiAction::PlaylistToggleAutoCycle(deck),
-        _ => return Err(format!("Unknown action: {}", action)),
-    };
+    let midi_action = parse_midi_action(&action, deck_id.unwrap_or(0))?;
 
     let midi_guard = state.midi_controller.lock().map_err(|e| e.to_string())?;
     midi_guard.start_learn_mode(midi_action, name);
-    Ok("Learn mode started - move a MIDI control".to_string())
+    Ok("Learn mode started - move a MIDI control, then confirm".to_string())
+}
+
+/// Map the control detected in learn mode
+#[tauri::command]
+fn midi_confirm_learn(state: State<'_, AppState>) -> Result<String, String> {
+    let midi_guard = state.midi_controller.lock().map_err(|e| e.to_string())?;
+    match midi_guard.confirm_learn() {
+        Some(mapping) => Ok(format!("Mapped {}", mapping.midi_message)),
+        None => Err("No MIDI control detected yet".to_string()),
+    }
+}
+
+/// Discard the control detected in learn mode and keep listening
+#[tauri::command]
+fn midi_reject_learn(state: State<'_, AppState>) -> Result<String, String> {
+    let midi_guard = state.midi_controller.lock().map_err(|e| e.to_string())?;
+    midi_guard.reject_learn();
+    Ok("Move a MIDI control".to_string())
 }
 
 /// Cancel MIDI learn mode
@@ -2768,6 +7516,24 @@ fn midi_list_builtin_presets() -> Vec<MidiPresetInfo> {
             controller: "Korg nanoKONTROL2".to_string(),
             mapping_count: 21,
         },
+        MidiPresetInfo {
+            name: "Pioneer DDJ-400/FLX4".to_string(),
+            description: "Mapping for Pioneer DDJ-400 and DDJ-FLX4".to_string(),
+            controller: "Pioneer DDJ-400".to_string(),
+            mapping_count: 29,
+        },
+        MidiPresetInfo {
+            name: "Traktor Kontrol S2/X1".to_string(),
+            description: "Mapping for Traktor Kontrol S2/X1 in MIDI mode (Controller Editor)".to_string(),
+            controller: "Traktor Kontrol".to_string(),
+            mapping_count: 21,
+        },
+        MidiPresetInfo {
+            name: "MIDI Fighter Twister".to_string(),
+            description: "Mapping for DJ TechTools MIDI Fighter Twister".to_string(),
+            controller: "MIDI Fighter Twister".to_string(),
+            mapping_count: 30,
+        },
     ]
 }
 
@@ -2782,6 +7548,9 @@ fn midi_load_builtin_preset(
         "akai" | "akai apc mini" | "apc mini" => create_apc_mini_preset(),
         "launchpad" | "novation launchpad" => create_launchpad_preset(),
         "nanokontrol" | "nanokontrol2" | "korg nanokontrol2" => create_nanokontrol2_preset(),
+        "ddj-400" | "ddj-flx4" | "pioneer ddj-400" | "pioneer ddj-400/flx4" => create_ddj400_preset(),
+        "traktor" | "traktor kontrol" | "traktor kontrol s2/x1" => create_traktor_kontrol_preset(),
+        "twister" | "midi fighter twister" => create_midi_fighter_twister_preset(),
         _ => return Err(format!("Unknown preset: {}", preset_name)),
     };
 
@@ -2837,7 +7606,7 @@ fn start_visualizer(
     fullscreen: Option<bool>,
     preset_path: Option<String>,
 ) -> Result<String, String> {
-    start_deck(state, Some(0), width, height, fullscreen, preset_path, None)
+    start_deck(state, Some(0), width, height, fullscreen, preset_path, None, None, None, None, None)
 }
 
 /// Stop visualizer (backward compatible - uses deck 0)
@@ -2898,15 +7667,51 @@ pub fn run() {
         .plugin(tauri_plugin_dialog::init())
         .plugin(tauri_plugin_opener::init())
         .manage(AppState::new())
+        .setup(|app| {
+            spawn_audio_pump(app.handle().clone());
+            let handle = app.handle().clone();
+            let value_handle = app.handle().clone();
+            let learn_handle = app.handle().clone();
+            let macro_handle = app.handle().clone();
+            if let Ok(mut midi) = app.state::<AppState>().midi_controller.lock() {
+                midi.set_action_callback(move |action, value| dispatch_midi_action(&handle, action, value));
+                midi.set_value_callback(move |action| midi_action_value(&value_handle, action));
+                midi.set_macro_callback(move |actions| dispatch_midi_actions(&macro_handle, actions));
+                midi.set_learn_callback(move |midi_type| {
+                    let _ = learn_handle.emit("midi_learn_detected", MidiLearnDetectedEvent {
+                        midi_type: format!("{:?}", midi_type),
+                        description: midi_type.to_string(),
+                    });
+                });
+
+                // Pick up where the last session left off
+                if let Some(path) = MidiSession::default_path() {
+                    if let Err(e) = midi.restore_session(&path) {
+                        warn!("Failed to restore MIDI session: {}", e);
+                    }
+                }
+            }
+            Ok(())
+        })
         .invoke_handler(tauri::generate_handler![
             greet,
             // Multi-deck commands
             start_deck,
             stop_deck,
             set_deck_volume,
+            set_deck_gain,
+            set_deck_agc,
             get_multi_deck_status,
             // Per-deck commands with deck_id parameter
             load_preset,
+            set_deck_transition_time,
+            set_deck_preset_cuts,
+            set_deck_effect,
+            set_deck_color_adjust,
+            blackout,
+            freeze,
+            set_deck_time_scale,
+            set_deck_strobe,
             set_beat_sensitivity,
             toggle_fullscreen,
             // Playlist commands
@@ -2936,9 +7741,35 @@ pub fn run() {
             // Audio commands
             list_audio_devices,
             start_audio,
+            start_audio_multi,
+            switch_audio_device,
             stop_audio,
             pump_audio,
             get_audio_levels,
+            set_meter_settings,
+            get_meter_settings,
+            get_audio_energy,
+            set_preset_energy_tags,
+            get_failed_presets,
+            clear_failed_presets,
+            get_audio_spectrum,
+            get_audio_bands,
+            get_waveform,
+            get_audio_format,
+            get_audio_stats,
+            get_beat_info,
+            set_bpm,
+            tap_tempo,
+            start_audio_recording,
+            set_idle_settings,
+            get_idle_settings,
+            set_auto_restart,
+            get_auto_restart,
+            set_gpu_preference,
+            get_gpu_preference,
+            set_multi_deck_renderer,
+            get_multi_deck_renderer,
+            stop_audio_recording,
             // Utility commands
             get_status,
             get_projectm_version,
@@ -2951,12 +7782,40 @@ pub fn run() {
             import_playlist,
             // Video output commands
             list_video_outputs,
+            setup_v4l2_device,
             set_deck_video_output,
             // NDI output commands
             is_ndi_available,
             set_deck_ndi_output,
+            is_syphon_available,
+            set_deck_syphon_output,
+            set_deck_output_fps,
+            set_deck_transparent_background,
+            set_deck_overlay,
+            set_deck_background,
+            set_deck_overlay_text,
+            set_deck_hud,
+            set_deck_quality,
+            set_deck_fps_cap,
+            toggle_preset_lock,
+            set_deck_window_options,
+            set_deck_window_geometry,
+            set_output_test_pattern,
+            is_virtual_camera_available,
+            set_deck_virtual_camera,
+            list_video_inputs,
+            set_deck_input_layer,
+            set_deck_input_mix,
+            start_recording,
+            stop_recording,
+            start_frame_export,
+            stop_frame_export,
+            start_loop_export,
+            cancel_loop_export,
             // Texture path commands
             set_deck_texture_paths,
+            generate_preset_thumbnails,
+            read_preset_thumbnail,
             set_all_decks_texture_paths,
             // Monitor commands
             list_monitors,
@@ -2967,9 +7826,23 @@ pub fn run() {
             midi_get_status,
             midi_get_mappings,
             midi_add_mapping,
+            midi_map_pad_bank,
+            midi_set_pickup,
+            midi_set_relative,
+            midi_set_debounce,
+            midi_add_macro_step,
+            midi_list_playlist_banks,
+            midi_clear_macro,
+            midi_set_curve,
+            midi_set_any_channel,
+            midi_set_output,
+            midi_set_osc,
+            midi_set_gamepad,
             midi_remove_mapping,
             midi_clear_mappings,
             midi_start_learn,
+            midi_confirm_learn,
+            midi_reject_learn,
             midi_cancel_learn,
             midi_list_builtin_presets,
             midi_load_builtin_preset,
@@ -15,6 +15,14 @@ export function normalizePath(path: string): string {
   return path.replace(/\\/g, '/');
 }
 
+/** Deck window position and size in logical pixels; no position on Wayland */
+interface DeckWindowGeometry {
+  x: number | null;
+  y: number | null;
+  width: number;
+  height: number;
+}
+
 interface AppSettings {
   /** Custom preset directories to search (in addition to defaults) */
   customPresetPaths: string[];
@@ -29,6 +37,22 @@ interface AppSettings {
   autoStartAudio: boolean;
   /** Preferred audio device name */
   preferredAudioDevice: string | null;
+  /** Where each deck's window was last, by deck ID */
+  deckWindowGeometry: Record<number, DeckWindowGeometry>;
+  /** Relaunch deck renderers that crash */
+  autoRestartDecks: boolean;
+  /** GPU renderers ask for: 'default', 'low_power' or 'high_performance' */
+  gpuPreference: string;
+  /** Open deck windows in one shared renderer process */
+  multiDeckRenderer: boolean;
+  /** RMS level below which audio counts as silent */
+  idleThreshold: number;
+  /** Seconds of silence before decks go idle */
+  idleTimeoutSecs: number;
+  /** Preset shown on running decks while idle, null to keep the current one */
+  idlePreset: string | null;
+  /** Pause playlist auto-cycle while idle */
+  idleFreezeAutoCycle: boolean;
 }
 
 const DEFAULT_SETTINGS: AppSettings = {
@@ -39,6 +63,14 @@ const DEFAULT_SETTINGS: AppSettings = {
   defaultDeckHeight: 720,
   autoStartAudio: false,
   preferredAudioDevice: null,
+  deckWindowGeometry: {},
+  autoRestartDecks: false,
+  gpuPreference: 'default',
+  multiDeckRenderer: false,
+  idleThreshold: 0.002,
+  idleTimeoutSecs: 10,
+  idlePreset: null,
+  idleFreezeAutoCycle: false,
 };
 
 function loadSettings(): AppSettings {
@@ -102,6 +134,30 @@ export const settings = {
   get preferredAudioDevice() {
     return settingsState.preferredAudioDevice;
   },
+  get deckWindowGeometry() {
+    return settingsState.deckWindowGeometry;
+  },
+  get autoRestartDecks() {
+    return settingsState.autoRestartDecks;
+  },
+  get gpuPreference() {
+    return settingsState.gpuPreference;
+  },
+  get multiDeckRenderer() {
+    return settingsState.multiDeckRenderer;
+  },
+  get idleThreshold() {
+    return settingsState.idleThreshold;
+  },
+  get idleTimeoutSecs() {
+    return settingsState.idleTimeoutSecs;
+  },
+  get idlePreset() {
+    return settingsState.idlePreset;
+  },
+  get idleFreezeAutoCycle() {
+    return settingsState.idleFreezeAutoCycle;
+  },
 };
 
 /**
@@ -113,6 +169,20 @@ export function updateSettings(updates: Partial<AppSettings>): void {
   saveSettings(settingsState);
 }
 
+/**
+ * Get the idle settings in the form set_idle_settings takes
+ * @param changes - Settings to apply on top of the stored ones
+ */
+export function getIdleSettings(changes: Partial<AppSettings> = {}) {
+  const merged = { ...settingsState, ...changes };
+  return {
+    threshold: merged.idleThreshold,
+    timeout_secs: merged.idleTimeoutSecs,
+    idle_preset: merged.idlePreset,
+    freeze_auto_cycle: merged.idleFreezeAutoCycle,
+  };
+}
+
 /**
  * Reset all settings to defaults
  */
@@ -197,4 +267,4 @@ export function removeTexturePath(path: string): void {
   saveSettings(settingsState);
 }
 
-export type { AppSettings };
+export type { AppSettings, DeckWindowGeometry };
@@ -65,7 +65,7 @@ describe('AudioPanel', () => {
 			expect(select.disabled).toBe(false);
 		});
 
-		it('disables device selection when running', () => {
+		it('keeps device selection enabled when running', () => {
 			render(AudioPanel, {
 				props: {
 					devices: mockDevices,
@@ -75,7 +75,41 @@ describe('AudioPanel', () => {
 			});
 
 			const select = screen.getByRole('combobox') as HTMLSelectElement;
-			expect(select.disabled).toBe(true);
+			expect(select.disabled).toBe(false);
+		});
+
+		it('switches device when changed while running', async () => {
+			const onSwitchDevice = vi.fn();
+			render(AudioPanel, {
+				props: {
+					devices: mockDevices,
+					selectedDevice: 'Built-in Microphone',
+					running: true,
+					onSwitchDevice
+				}
+			});
+
+			const select = screen.getByRole('combobox');
+			await fireEvent.change(select, { target: { value: 'USB Audio Device' } });
+
+			expect(onSwitchDevice).toHaveBeenCalledWith('USB Audio Device');
+		});
+
+		it('does not switch device when changed while stopped', async () => {
+			const onSwitchDevice = vi.fn();
+			render(AudioPanel, {
+				props: {
+					devices: mockDevices,
+					selectedDevice: 'Built-in Microphone',
+					running: false,
+					onSwitchDevice
+				}
+			});
+
+			const select = screen.getByRole('combobox');
+			await fireEvent.change(select, { target: { value: 'USB Audio Device' } });
+
+			expect(onSwitchDevice).not.toHaveBeenCalled();
 		});
 	});
 
@@ -115,6 +149,71 @@ describe('AudioPanel', () => {
 		});
 	});
 
+	describe('mix mode', () => {
+		it('lists every device with a checkbox', async () => {
+			render(AudioPanel, { props: { devices: mockDevices } });
+
+			await fireEvent.click(screen.getByText('Mix'));
+
+			expect(screen.queryByRole('combobox')).not.toBeInTheDocument();
+			expect(screen.getAllByRole('checkbox')).toHaveLength(3);
+		});
+
+		it('disables Start until a source is picked', async () => {
+			render(AudioPanel, { props: { devices: mockDevices } });
+
+			await fireEvent.click(screen.getByText('Mix'));
+
+			expect(screen.getByText('Start').closest('button')).toBeDisabled();
+		});
+
+		it('starts the picked sources with their gain', async () => {
+			const onStart = vi.fn();
+			const onStartMulti = vi.fn();
+			render(AudioPanel, { props: { devices: mockDevices, onStart, onStartMulti } });
+
+			await fireEvent.click(screen.getByText('Mix'));
+			const checkboxes = screen.getAllByRole('checkbox');
+			await fireEvent.click(checkboxes[0]);
+			await fireEvent.click(checkboxes[1]);
+			await fireEvent.input(screen.getByLabelText('Gain for USB Audio Device'), { target: { value: '0.5' } });
+			await fireEvent.click(screen.getByText('Start').closest('button')!);
+
+			expect(onStart).not.toHaveBeenCalled();
+			expect(onStartMulti).toHaveBeenCalledWith([
+				{ device_name: 'Built-in Microphone', gain: 1 },
+				{ device_name: 'USB Audio Device', gain: 0.5 }
+			]);
+		});
+	});
+
+	describe('recording', () => {
+		it('hides Record button when not running', () => {
+			render(AudioPanel, { props: { running: false } });
+
+			expect(screen.queryByText('Record')).not.toBeInTheDocument();
+		});
+
+		it('calls onStartRecording when Record button is clicked', async () => {
+			const onStartRecording = vi.fn();
+			render(AudioPanel, { props: { running: true, onStartRecording } });
+
+			await fireEvent.click(screen.getByText('Record').closest('button')!);
+
+			expect(onStartRecording).toHaveBeenCalledTimes(1);
+		});
+
+		it('calls onStopRecording while recording', async () => {
+			const onStopRecording = vi.fn();
+			render(AudioPanel, { props: { running: true, recording: true, onStopRecording } });
+
+			expect(screen.queryByText('Record')).not.toBeInTheDocument();
+			await fireEvent.click(screen.getByText('Stop Rec').closest('button')!);
+
+			expect(onStopRecording).toHaveBeenCalledTimes(1);
+		});
+	});
+
 	describe('refresh button', () => {
 		it('renders refresh button', () => {
 			render(AudioPanel);
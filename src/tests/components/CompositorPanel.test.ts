@@ -0,0 +1,76 @@
+import { describe, it, expect, vi, beforeEach } from 'vitest';
+import { render, screen, fireEvent } from '@testing-library/svelte';
+import { invoke } from '@tauri-apps/api/core';
+import CompositorPanel from '$lib/components/CompositorPanel.svelte';
+
+vi.mock('@tauri-apps/api/core');
+
+describe('CompositorPanel', () => {
+	const mockCompositor = {
+		enabled: true,
+		running: true,
+		output_width: 1920,
+		output_height: 1080,
+		link_to_crossfader: true,
+		deck_settings: {
+			0: { opacity: 1, blend_mode: 'normal', layer_order: 0, enabled: true },
+			1: { opacity: 0.5, blend_mode: 'add', layer_order: 1, enabled: false }
+		}
+	};
+
+	beforeEach(() => {
+		vi.clearAllMocks();
+		vi.mocked(invoke).mockResolvedValue(undefined);
+	});
+
+	it('renders panel header', () => {
+		render(CompositorPanel);
+		expect(screen.getByText('Compositor')).toBeInTheDocument();
+		expect(screen.getByText('OFF')).toBeInTheDocument();
+	});
+
+	it('lists decks top layer first', () => {
+		const { container } = render(CompositorPanel, { props: { compositor: mockCompositor } });
+
+		const names = [...container.querySelectorAll('.layer-name')].map((el) => el.textContent?.trim());
+		expect(names).toEqual(['Deck 2', 'Deck 1']);
+	});
+
+	it('calls compositor_set_enabled when toggle is clicked', async () => {
+		const onUpdate = vi.fn();
+		render(CompositorPanel, { props: { compositor: mockCompositor, onUpdate } });
+
+		await fireEvent.click(screen.getByText('ON'));
+
+		expect(invoke).toHaveBeenCalledWith('compositor_set_enabled', { enabled: false });
+		expect(onUpdate).toHaveBeenCalled();
+	});
+
+	it('shows a hint when the compositor window was closed', () => {
+		render(CompositorPanel, { props: { compositor: { ...mockCompositor, running: false } } });
+		expect(screen.getByText(/window closed/)).toBeInTheDocument();
+	});
+
+	it('sets a deck blend mode', async () => {
+		render(CompositorPanel, { props: { compositor: mockCompositor } });
+
+		await fireEvent.change(screen.getByLabelText('Deck 1 blend mode'), {
+			target: { value: 'screen' }
+		});
+
+		expect(invoke).toHaveBeenCalledWith('compositor_set_deck_blend_mode', {
+			deckId: 0,
+			blendMode: 'screen'
+		});
+	});
+
+	it('sets the output resolution', async () => {
+		render(CompositorPanel, { props: { compositor: mockCompositor } });
+
+		await fireEvent.change(screen.getByLabelText('Compositor resolution'), {
+			target: { value: '1280x720' }
+		});
+
+		expect(invoke).toHaveBeenCalledWith('compositor_set_resolution', { width: 1280, height: 720 });
+	});
+});
@@ -56,6 +56,11 @@ describe('DeckMiniCard', () => {
 			expect(screen.getByText('LIVE')).toBeInTheDocument();
 		});
 
+		it('shows the reported FPS while running', () => {
+			render(DeckMiniCard, { props: { ...defaultProps, running: true, fps: 59.6 } });
+			expect(screen.getByText('60 FPS')).toBeInTheDocument();
+		});
+
 		it('does not show LIVE indicator when not running', () => {
 			render(DeckMiniCard, { props: defaultProps });
 			expect(screen.queryByText('LIVE')).not.toBeInTheDocument();
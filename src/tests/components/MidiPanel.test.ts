@@ -252,6 +252,80 @@ describe('MidiPanel', () => {
 				expect(screen.getByText('Move a MIDI control...')).toBeInTheDocument();
 			});
 		});
+
+		it('confirms a detected control', async () => {
+			vi.mocked(invoke).mockImplementation(async (cmd) => {
+				if (cmd === 'list_midi_ports') return mockPorts;
+				if (cmd === 'midi_get_status') {
+					return { ...mockStatus, connected: true, learning: true, detected: 'CC 7 on channel 1' };
+				}
+				if (cmd === 'midi_get_mappings') return [];
+				if (cmd === 'midi_list_builtin_presets') return mockBuiltinPresets;
+				return undefined;
+			});
+
+			render(MidiPanel);
+			await waitFor(() => {
+				expect(screen.getByText('CC 7 on channel 1 detected')).toBeInTheDocument();
+			});
+
+			await fireEvent.click(screen.getByText('Confirm'));
+			expect(invoke).toHaveBeenCalledWith('midi_confirm_learn');
+		});
+	});
+
+	describe('MIDI output', () => {
+		it('turns the virtual output on', async () => {
+			vi.mocked(invoke).mockImplementation(async (cmd) => {
+				if (cmd === 'list_midi_ports') return mockPorts;
+				if (cmd === 'midi_get_status') return mockStatus;
+				if (cmd === 'midi_list_builtin_presets') return mockBuiltinPresets;
+				if (cmd === 'midi_set_output') return 'OpenDrop Out';
+				return [];
+			});
+
+			render(MidiPanel);
+			await fireEvent.click(await screen.findByText('MIDI Out'));
+
+			expect(invoke).toHaveBeenCalledWith('midi_set_output', { enabled: true });
+			await waitFor(() => {
+				expect(screen.getByText('OpenDrop Out')).toBeInTheDocument();
+			});
+		});
+	});
+
+	describe('OSC input', () => {
+		it('turns the OSC input on', async () => {
+			vi.mocked(invoke).mockImplementation(async (cmd) => {
+				if (cmd === 'list_midi_ports') return mockPorts;
+				if (cmd === 'midi_get_status') return mockStatus;
+				if (cmd === 'midi_list_builtin_presets') return mockBuiltinPresets;
+				if (cmd === 'midi_set_osc') return 8000;
+				return [];
+			});
+
+			render(MidiPanel);
+			await fireEvent.click(await screen.findByText('OSC'));
+
+			expect(invoke).toHaveBeenCalledWith('midi_set_osc', { enabled: true });
+		});
+	});
+
+	describe('gamepad input', () => {
+		it('lists the connected gamepads', async () => {
+			vi.mocked(invoke).mockImplementation(async (cmd) => {
+				if (cmd === 'list_midi_ports') return mockPorts;
+				if (cmd === 'midi_get_status') return mockStatus;
+				if (cmd === 'midi_list_builtin_presets') return mockBuiltinPresets;
+				if (cmd === 'midi_set_gamepad') return ['Xbox Wireless Controller'];
+				return [];
+			});
+
+			render(MidiPanel);
+			await fireEvent.click(await screen.findByText('Gamepad'));
+
+			expect(invoke).toHaveBeenCalledWith('midi_set_gamepad', { enabled: true });
+		});
 	});
 
 	describe('mappings list', () => {
@@ -287,6 +361,20 @@ describe('MidiPanel', () => {
 			});
 		});
 
+		it('shows the step count of a macro mapping', async () => {
+			vi.mocked(invoke).mockImplementation(async (cmd) => {
+				if (cmd === 'list_midi_ports') return mockPorts;
+				if (cmd === 'midi_get_status') return { ...mockStatus, connected: true };
+				if (cmd === 'midi_get_mappings') return [{ ...mockMappings[1], macro_steps: 2 }];
+				if (cmd === 'midi_list_builtin_presets') return mockBuiltinPresets;
+				return undefined;
+			});
+
+			render(MidiPanel);
+			await fireEvent.click(await screen.findByText('+2'));
+			expect(invoke).toHaveBeenCalledWith('midi_clear_macro', { mappingId: '2' });
+		});
+
 		it('shows no mappings message when empty', async () => {
 			vi.mocked(invoke).mockImplementation(async (cmd) => {
 				if (cmd === 'list_midi_ports') return mockPorts;
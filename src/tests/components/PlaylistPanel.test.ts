@@ -16,7 +16,8 @@ describe('PlaylistPanel', () => {
 		current_index: 0,
 		shuffle: false,
 		auto_cycle: false,
-		cycle_duration_secs: 30
+		cycle_on: { seconds: 30 },
+		energy_match: false
 	};
 
 	beforeEach(() => {
@@ -147,6 +148,19 @@ describe('PlaylistPanel', () => {
 			const shuffleButton = container.querySelector('[title="Shuffle"].active');
 			expect(shuffleButton).toBeInTheDocument();
 		});
+
+		it('toggles energy matching when shuffle is enabled', async () => {
+			render(PlaylistPanel, {
+				props: { playlist: { ...mockPlaylist, shuffle: true }, deckId: 0 }
+			});
+
+			await fireEvent.click(screen.getByTitle('Match energy'));
+
+			expect(invoke).toHaveBeenCalledWith('playlist_set_settings', {
+				deckId: 0,
+				energyMatch: true
+			});
+		});
 	});
 
 	describe('auto-cycle toggle', () => {
@@ -177,6 +191,18 @@ describe('PlaylistPanel', () => {
 			expect(screen.getByRole('spinbutton')).toBeInTheDocument();
 		});
 
+		it('toggles the preset lock', async () => {
+			const onUpdate = vi.fn();
+			const { container } = render(PlaylistPanel, {
+				props: { playlist: mockPlaylist, deckId: 2, locked: true, onUpdate }
+			});
+			expect(container.querySelector('[title="Lock preset"].active')).toBeInTheDocument();
+
+			await fireEvent.click(screen.getByTitle('Lock preset'));
+
+			expect(invoke).toHaveBeenCalledWith('toggle_preset_lock', { deckId: 2 });
+		});
+
 		it('hides cycle duration input when auto-cycle is disabled', () => {
 			render(PlaylistPanel, {
 				props: { playlist: { ...mockPlaylist, auto_cycle: false } }
@@ -184,6 +210,22 @@ describe('PlaylistPanel', () => {
 
 			expect(screen.queryByText('Cycle every')).not.toBeInTheDocument();
 		});
+
+		it('sends beat-synced cycle length in beats', async () => {
+			render(PlaylistPanel, {
+				props: { playlist: { ...mockPlaylist, auto_cycle: true, cycle_on: { beats: 16 } }, deckId: 0 }
+			});
+
+			const input = screen.getByRole('spinbutton');
+			expect(input).toHaveValue(4);
+			await fireEvent.input(input, { target: { value: '8' } });
+			await fireEvent.change(input);
+
+			expect(invoke).toHaveBeenCalledWith('playlist_set_settings', {
+				deckId: 0,
+				cycleBeats: 32
+			});
+		});
 	});
 
 	describe('clear all', () => {
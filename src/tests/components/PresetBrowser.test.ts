@@ -1,5 +1,6 @@
 import { describe, it, expect, vi, beforeEach } from 'vitest';
 import { render, screen, fireEvent } from '@testing-library/svelte';
+import { invoke } from '@tauri-apps/api/core';
 import PresetBrowser from '$lib/components/PresetBrowser.svelte';
 import * as favoritesStore from '$lib/stores/favorites';
 import * as categoriesStore from '$lib/stores/categories';
@@ -184,6 +185,26 @@ describe('PresetBrowser', () => {
 		});
 	});
 
+	describe('thumbnails', () => {
+		it('generates thumbnails for the presets shown', async () => {
+			vi.mocked(invoke).mockResolvedValue(4);
+			render(PresetBrowser, { props: { presets: mockPresets } });
+
+			await fireEvent.click(screen.getByLabelText('Generate thumbnails'));
+
+			expect(invoke).toHaveBeenCalledWith('generate_preset_thumbnails', {
+				paths: mockPresets.map((p) => p.path),
+				format: null
+			});
+		});
+
+		it('disables the button without presets', () => {
+			render(PresetBrowser);
+
+			expect(screen.getByLabelText('Generate thumbnails')).toBeDisabled();
+		});
+	});
+
 	describe('favorites filter', () => {
 		it('renders favorites filter button', () => {
 			render(PresetBrowser, { props: { presets: mockPresets } });
@@ -51,6 +51,22 @@ describe('PresetCard', () => {
 		});
 	});
 
+	describe('thumbnail', () => {
+		it('shows the thumbnail when there is one', () => {
+			const { container } = render(PresetCard, { props: { ...defaultProps, thumbnail: 'blob:thumb' } });
+
+			expect(container.querySelector('img.thumbnail')).toHaveAttribute('src', 'blob:thumb');
+			expect(container.querySelector('.gradient-bg')).not.toBeInTheDocument();
+		});
+
+		it('shows the placeholder without a thumbnail', () => {
+			const { container } = render(PresetCard, { props: defaultProps });
+
+			expect(container.querySelector('img.thumbnail')).not.toBeInTheDocument();
+			expect(container.querySelector('.gradient-bg')).toBeInTheDocument();
+		});
+	});
+
 	describe('click interactions', () => {
 		it('calls onclick when card is clicked', async () => {
 			const onclick = vi.fn();
@@ -0,0 +1,39 @@
+import { describe, it, expect, vi, beforeEach } from 'vitest';
+import { render, waitFor } from '@testing-library/svelte';
+import { invoke } from '@tauri-apps/api/core';
+import SpectrumAnalyzer from '$lib/components/SpectrumAnalyzer.svelte';
+
+vi.mock('@tauri-apps/api/core');
+
+describe('SpectrumAnalyzer', () => {
+	const mockBands = Array.from({ length: 32 }, (_, i) => i / 32);
+
+	beforeEach(() => {
+		vi.clearAllMocks();
+		vi.mocked(invoke).mockResolvedValue(mockBands);
+	});
+
+	it('does not poll while stopped', () => {
+		const { container } = render(SpectrumAnalyzer, { props: { running: false } });
+
+		expect(invoke).not.toHaveBeenCalled();
+		expect(container.querySelectorAll('.bar')).toHaveLength(0);
+	});
+
+	it('draws a bar per band while running', async () => {
+		const { container } = render(SpectrumAnalyzer, { props: { running: true } });
+
+		await waitFor(() => {
+			expect(invoke).toHaveBeenCalledWith('get_audio_spectrum', { bands: 32 });
+			expect(container.querySelectorAll('.bar')).toHaveLength(32);
+		});
+	});
+
+	it('requests the given number of bands', async () => {
+		render(SpectrumAnalyzer, { props: { running: true, bands: 64 } });
+
+		await waitFor(() => {
+			expect(invoke).toHaveBeenCalledWith('get_audio_spectrum', { bands: 64 });
+		});
+	});
+});
@@ -0,0 +1,77 @@
+import { describe, it, expect, vi, beforeEach } from 'vitest';
+import { render, screen, fireEvent, waitFor } from '@testing-library/svelte';
+import { invoke } from '@tauri-apps/api/core';
+import TempoPanel from '$lib/components/TempoPanel.svelte';
+
+vi.mock('@tauri-apps/api/core');
+
+describe('TempoPanel', () => {
+	const detected = { bpm: 128, phase: 0, confidence: 0.8, beat_count: 12, source: 'detected' };
+
+	beforeEach(() => {
+		vi.clearAllMocks();
+		vi.mocked(invoke).mockResolvedValue(detected);
+	});
+
+	describe('rendering', () => {
+		it('renders panel header', () => {
+			render(TempoPanel);
+			expect(screen.getByText('Tempo')).toBeInTheDocument();
+		});
+
+		it('shows the current tempo on mount', async () => {
+			render(TempoPanel);
+
+			expect(invoke).toHaveBeenCalledWith('get_beat_info');
+			await waitFor(() => {
+				expect(screen.getByText('128.0')).toBeInTheDocument();
+			});
+			expect(screen.getByText('Auto', { selector: '.source-badge' })).toBeInTheDocument();
+		});
+
+		it('disables Auto while the tempo is detected', async () => {
+			render(TempoPanel);
+
+			await waitFor(() => {
+				expect(screen.getByRole('button', { name: 'Auto' })).toBeDisabled();
+			});
+		});
+	});
+
+	describe('tempo control', () => {
+		it('taps the tempo', async () => {
+			render(TempoPanel);
+			vi.mocked(invoke).mockResolvedValue({ ...detected, bpm: 120, source: 'tap' });
+
+			await fireEvent.click(screen.getByRole('button', { name: 'Tap' }));
+
+			expect(invoke).toHaveBeenCalledWith('tap_tempo');
+			await waitFor(() => {
+				expect(screen.getByText('120.0')).toBeInTheDocument();
+			});
+		});
+
+		it('sets a manual BPM', async () => {
+			render(TempoPanel);
+
+			const input = screen.getByLabelText('Set BPM');
+			await fireEvent.input(input, { target: { value: '140' } });
+			await fireEvent.click(screen.getByRole('button', { name: 'Set' }));
+
+			expect(invoke).toHaveBeenCalledWith('set_bpm', { bpm: 140 });
+		});
+
+		it('returns to the detected tempo', async () => {
+			vi.mocked(invoke).mockResolvedValue({ ...detected, source: 'manual' });
+			render(TempoPanel);
+
+			const auto = screen.getByRole('button', { name: 'Auto' });
+			await waitFor(() => {
+				expect(auto).not.toBeDisabled();
+			});
+			await fireEvent.click(auto);
+
+			expect(invoke).toHaveBeenCalledWith('set_bpm', { bpm: null });
+		});
+	});
+});
@@ -3,6 +3,11 @@ import { render, screen, fireEvent, waitFor } from '@testing-library/svelte';
 import { invoke } from '@tauri-apps/api/core';
 import VideoOutputPanel from '$lib/components/VideoOutputPanel.svelte';
 
+vi.mock('@tauri-apps/plugin-dialog', () => ({
+	open: vi.fn(async () => '/videos/frames'),
+	save: vi.fn(async () => '/videos/set.mp4')
+}));
+
 // Get mocked invoke
 const mockInvoke = vi.mocked(invoke);
 
@@ -101,6 +106,49 @@ describe('VideoOutputPanel', () => {
 				expect(screen.getByText(/sudo modprobe v4l2loopback/)).toBeInTheDocument();
 			});
 		});
+
+		it('creates a device when v4l2loopback is installed but not loaded', async () => {
+			mockInvoke.mockImplementation(async (cmd, args) => {
+				if (cmd === 'list_video_outputs') return [];
+				if (cmd === 'list_monitors') return [];
+				if (cmd === 'setup_v4l2_device') {
+					return {
+						module_installed: true,
+						module_loaded: !!args?.create,
+						devices: [],
+						command: 'sudo modprobe v4l2loopback devices=1 video_nr=10 card_label="OpenDrop"'
+					};
+				}
+				return null;
+			});
+			render(VideoOutputPanel);
+
+			await waitFor(() => {
+				expect(screen.getByRole('button', { name: /create device/i })).toBeInTheDocument();
+			});
+			await fireEvent.click(screen.getByRole('button', { name: /create device/i }));
+
+			await waitFor(() => {
+				expect(mockInvoke).toHaveBeenCalledWith('setup_v4l2_device', { create: true });
+			});
+		});
+
+		it('hides Create Device when v4l2loopback is not installed', async () => {
+			mockInvoke.mockImplementation(async (cmd) => {
+				if (cmd === 'list_video_outputs') return [];
+				if (cmd === 'list_monitors') return [];
+				if (cmd === 'setup_v4l2_device') {
+					return { module_installed: false, module_loaded: false, devices: [], command: 'sudo modprobe v4l2loopback' };
+				}
+				return null;
+			});
+			render(VideoOutputPanel);
+
+			await waitFor(() => {
+				expect(screen.getByText(/Install v4l2loopback-dkms/)).toBeInTheDocument();
+			});
+			expect(screen.queryByRole('button', { name: /create device/i })).not.toBeInTheDocument();
+		});
 	});
 
 	describe('video output toggle', () => {
@@ -124,7 +172,8 @@ describe('VideoOutputPanel', () => {
 				expect(mockInvoke).toHaveBeenCalledWith('set_deck_video_output', {
 					deckId: 1,
 					enabled: true,
-					devicePath: '/dev/video10'
+					devicePath: '/dev/video10',
+					pixelFormat: 'yuyv'
 				});
 			});
 		});
@@ -228,11 +277,32 @@ describe('VideoOutputPanel', () => {
 				expect(mockInvoke).toHaveBeenCalledWith('set_deck_ndi_output', {
 					deckId: 0,
 					enabled: true,
-					name: null
+					name: null,
+					groups: null,
+					failover: null,
+					clockVideo: true,
+					pixelFormat: 'rgba'
 				});
 			});
 		});
 
+		it('sends the chosen NDI pixel format', async () => {
+			render(VideoOutputPanel, { props: { deckId: 0 } });
+			await waitFor(() => {
+				expect(screen.getByLabelText('NDI pixel format')).toBeInTheDocument();
+			});
+
+			await fireEvent.change(screen.getByLabelText('NDI pixel format'), { target: { value: 'uyvy' } });
+			await fireEvent.click(screen.getByRole('button', { name: /enable ndi/i }));
+
+			await waitFor(() => {
+				expect(mockInvoke).toHaveBeenCalledWith('set_deck_ndi_output', expect.objectContaining({
+					enabled: true,
+					pixelFormat: 'uyvy'
+				}));
+			});
+		});
+
 		it('shows NDI source name input', async () => {
 			render(VideoOutputPanel);
 			await waitFor(() => {
@@ -256,7 +326,34 @@ describe('VideoOutputPanel', () => {
 				expect(mockInvoke).toHaveBeenCalledWith('set_deck_ndi_output', {
 					deckId: 0,
 					enabled: true,
-					name: 'Custom Source'
+					name: 'Custom Source',
+					groups: null,
+					failover: null,
+					clockVideo: true,
+					pixelFormat: 'rgba'
+				});
+			});
+		});
+
+		it('passes NDI groups and failover source', async () => {
+			render(VideoOutputPanel, { props: { deckId: 0 } });
+			await waitFor(() => {
+				expect(screen.getByPlaceholderText(/groups/i)).toBeInTheDocument();
+			});
+
+			await fireEvent.input(screen.getByPlaceholderText(/groups/i), { target: { value: 'stage,foh' } });
+			await fireEvent.input(screen.getByPlaceholderText(/failover/i), { target: { value: 'BACKUP (Deck 2)' } });
+			await fireEvent.click(screen.getByRole('button', { name: /enable ndi/i }));
+
+			await waitFor(() => {
+				expect(mockInvoke).toHaveBeenCalledWith('set_deck_ndi_output', {
+					deckId: 0,
+					enabled: true,
+					name: null,
+					groups: 'stage,foh',
+					failover: 'BACKUP (Deck 2)',
+					clockVideo: true,
+					pixelFormat: 'rgba'
 				});
 			});
 		});
@@ -337,6 +434,617 @@ describe('VideoOutputPanel', () => {
 		});
 	});
 
+	describe('Syphon output', () => {
+		it('hides Syphon when the framework is not available', async () => {
+			render(VideoOutputPanel);
+			await waitFor(() => {
+				expect(mockInvoke).toHaveBeenCalledWith('is_syphon_available');
+			});
+			expect(screen.queryByRole('button', { name: /enable syphon/i })).not.toBeInTheDocument();
+		});
+
+		it('calls set_deck_syphon_output when enabling Syphon', async () => {
+			mockInvoke.mockImplementation(async (cmd) => {
+				if (cmd === 'list_video_outputs') return mockDevices;
+				if (cmd === 'list_monitors') return mockMonitors;
+				if (cmd === 'is_syphon_available') return true;
+				return null;
+			});
+			render(VideoOutputPanel, { props: { deckId: 1 } });
+
+			await waitFor(() => {
+				expect(screen.getByRole('button', { name: /enable syphon/i })).toBeInTheDocument();
+			});
+			await fireEvent.click(screen.getByRole('button', { name: /enable syphon/i }));
+
+			await waitFor(() => {
+				expect(mockInvoke).toHaveBeenCalledWith('set_deck_syphon_output', {
+					deckId: 1,
+					enabled: true,
+					name: null
+				});
+			});
+		});
+	});
+
+	describe('output frame rate', () => {
+		it('caps NDI output at the chosen frame rate', async () => {
+			render(VideoOutputPanel, { props: { deckId: 1 } });
+
+			const select = await screen.findByRole('combobox', { name: /ndi frame rate/i });
+			await fireEvent.change(select, { target: { value: '30' } });
+
+			await waitFor(() => {
+				expect(mockInvoke).toHaveBeenCalledWith('set_deck_output_fps', {
+					deckId: 1,
+					output: 'ndi',
+					fps: 30
+				});
+			});
+		});
+
+		it('clears the cap for full frame rate', async () => {
+			render(VideoOutputPanel);
+
+			const select = await screen.findByRole('combobox', { name: /video output frame rate/i });
+			await fireEvent.change(select, { target: { value: '0' } });
+
+			await waitFor(() => {
+				expect(mockInvoke).toHaveBeenCalledWith('set_deck_output_fps', {
+					deckId: 0,
+					output: 'v4l2',
+					fps: null
+				});
+			});
+		});
+	});
+
+	describe('transparent background', () => {
+		it('hides the alpha toggle without alpha-capable outputs', async () => {
+			mockInvoke.mockImplementation(async (cmd) => {
+				if (cmd === 'list_video_outputs') return mockDevices;
+				if (cmd === 'list_monitors') return mockMonitors;
+				if (cmd === 'is_ndi_available') return false;
+				return null;
+			});
+			render(VideoOutputPanel);
+			await waitFor(() => {
+				expect(mockInvoke).toHaveBeenCalledWith('is_ndi_available');
+			});
+			expect(screen.queryByRole('button', { name: /transparent background/i })).not.toBeInTheDocument();
+		});
+
+		it('calls set_deck_transparent_background when NDI is available', async () => {
+			mockInvoke.mockImplementation(async (cmd) => {
+				if (cmd === 'list_video_outputs') return mockDevices;
+				if (cmd === 'list_monitors') return mockMonitors;
+				if (cmd === 'is_ndi_available') return true;
+				return null;
+			});
+			render(VideoOutputPanel, { props: { deckId: 1 } });
+
+			await waitFor(() => {
+				expect(screen.getByRole('button', { name: /transparent background/i })).toBeInTheDocument();
+			});
+			await fireEvent.click(screen.getByRole('button', { name: /transparent background/i }));
+
+			await waitFor(() => {
+				expect(mockInvoke).toHaveBeenCalledWith('set_deck_transparent_background', {
+					deckId: 1,
+					enabled: true
+				});
+			});
+		});
+	});
+
+	describe('virtual camera', () => {
+		it('hides the virtual camera without softcam', async () => {
+			render(VideoOutputPanel);
+			await waitFor(() => {
+				expect(mockInvoke).toHaveBeenCalledWith('is_virtual_camera_available');
+			});
+			expect(screen.queryByRole('button', { name: /enable camera/i })).not.toBeInTheDocument();
+		});
+
+		it('calls set_deck_virtual_camera when enabling the camera', async () => {
+			mockInvoke.mockImplementation(async (cmd) => {
+				if (cmd === 'list_video_outputs') return mockDevices;
+				if (cmd === 'list_monitors') return mockMonitors;
+				if (cmd === 'is_virtual_camera_available') return true;
+				return null;
+			});
+			render(VideoOutputPanel, { props: { deckId: 2 } });
+
+			await waitFor(() => {
+				expect(screen.getByRole('button', { name: /enable camera/i })).toBeInTheDocument();
+			});
+			await fireEvent.click(screen.getByRole('button', { name: /enable camera/i }));
+
+			await waitFor(() => {
+				expect(mockInvoke).toHaveBeenCalledWith('set_deck_virtual_camera', {
+					deckId: 2,
+					enabled: true
+				});
+			});
+		});
+	});
+
+	describe('input layer', () => {
+		it('hides the input layer without receivers', async () => {
+			render(VideoOutputPanel);
+			await waitFor(() => {
+				expect(mockInvoke).toHaveBeenCalledWith('list_video_inputs');
+			});
+			expect(screen.queryByText('Input Layer')).not.toBeInTheDocument();
+		});
+
+		it('enables a Spout input on the deck', async () => {
+			mockInvoke.mockImplementation(async (cmd) => {
+				if (cmd === 'list_video_outputs') return mockDevices;
+				if (cmd === 'list_monitors') return mockMonitors;
+				if (cmd === 'list_video_inputs') return ['spout'];
+				return null;
+			});
+			render(VideoOutputPanel, { props: { deckId: 2 } });
+
+			await fireEvent.click(await screen.findByRole('button', { name: /enable input/i }));
+			await waitFor(() => {
+				expect(mockInvoke).toHaveBeenCalledWith('set_deck_input_layer', {
+					deckId: 2,
+					enabled: true,
+					backend: 'spout',
+					source: null,
+					blend: 'normal',
+					placement: 'over',
+					opacity: 1
+				});
+			});
+		});
+
+		it('captures a screen region under the visualization', async () => {
+			mockInvoke.mockImplementation(async (cmd) => {
+				if (cmd === 'list_video_outputs') return mockDevices;
+				if (cmd === 'list_monitors') return mockMonitors;
+				if (cmd === 'list_video_inputs') return ['screen'];
+				return null;
+			});
+			render(VideoOutputPanel, { props: { deckId: 1 } });
+
+			await fireEvent.input(await screen.findByPlaceholderText(/whole screen/i), {
+				target: { value: '1280x720+0+0' }
+			});
+			await fireEvent.change(screen.getByRole('combobox', { name: /input placement/i }), {
+				target: { value: 'under' }
+			});
+			await fireEvent.click(screen.getByRole('button', { name: /enable input/i }));
+			await waitFor(() => {
+				expect(mockInvoke).toHaveBeenCalledWith('set_deck_input_layer', {
+					deckId: 1,
+					enabled: true,
+					backend: 'screen',
+					source: '1280x720+0+0',
+					blend: 'normal',
+					placement: 'under',
+					opacity: 1
+				});
+			});
+		});
+	});
+
+	describe('recording', () => {
+		it('records to the chosen file and stops', async () => {
+			render(VideoOutputPanel, { props: { deckId: 1 } });
+
+			await fireEvent.click(screen.getByRole('button', { name: /^record$/i }));
+			await waitFor(() => {
+				expect(mockInvoke).toHaveBeenCalledWith('start_recording', {
+					deckId: 1,
+					path: '/videos/set.mp4',
+					fps: null,
+					bitrate: null,
+					codec: 'h264'
+				});
+			});
+
+			await fireEvent.click(await screen.findByRole('button', { name: /stop recording/i }));
+			await waitFor(() => {
+				expect(mockInvoke).toHaveBeenCalledWith('stop_recording', { deckId: 1 });
+			});
+		});
+
+		it('records with the selected media-server codec', async () => {
+			render(VideoOutputPanel, { props: { deckId: 0 } });
+
+			await fireEvent.change(screen.getByRole('combobox', { name: /recording codec/i }), {
+				target: { value: 'hap' }
+			});
+			await fireEvent.click(screen.getByRole('button', { name: /^record$/i }));
+			await waitFor(() => {
+				expect(mockInvoke).toHaveBeenCalledWith(
+					'start_recording',
+					expect.objectContaining({ deckId: 0, codec: 'hap' })
+				);
+			});
+		});
+
+		it('exports PNG frames to the chosen folder', async () => {
+			render(VideoOutputPanel, { props: { deckId: 0 } });
+
+			await fireEvent.click(screen.getByRole('button', { name: /export pngs/i }));
+			await waitFor(() => {
+				expect(mockInvoke).toHaveBeenCalledWith('start_frame_export', {
+					deckId: 0,
+					directory: '/videos/frames',
+					fps: null
+				});
+			});
+			expect(await screen.findByRole('button', { name: /stop export/i })).toBeInTheDocument();
+		});
+	});
+
+	describe('logo overlay', () => {
+		it('shows the chosen logo and hides it again', async () => {
+			render(VideoOutputPanel, { props: { deckId: 1 } });
+
+			expect(screen.getByRole('button', { name: /show logo/i })).toBeDisabled();
+			await fireEvent.click(screen.getByRole('button', { name: /choose logo/i }));
+			await fireEvent.change(screen.getByRole('combobox', { name: /logo position/i }), {
+				target: { value: 'top_left' }
+			});
+			await fireEvent.click(await screen.findByRole('button', { name: /show logo/i }));
+			await waitFor(() => {
+				expect(mockInvoke).toHaveBeenCalledWith('set_deck_overlay', {
+					deckId: 1,
+					overlay: { path: '/videos/frames', position: 'top_left', scale: 0.15, opacity: 1 }
+				});
+			});
+
+			await fireEvent.click(await screen.findByRole('button', { name: /hide logo/i }));
+			await waitFor(() => {
+				expect(mockInvoke).toHaveBeenCalledWith('set_deck_overlay', { deckId: 1, overlay: null });
+			});
+		});
+	});
+
+	describe('background', () => {
+		it('shows the chosen background and hides it again', async () => {
+			render(VideoOutputPanel, { props: { deckId: 2 } });
+
+			expect(screen.getByRole('button', { name: /show background/i })).toBeDisabled();
+			await fireEvent.click(screen.getByRole('button', { name: /choose background/i }));
+			await fireEvent.change(screen.getByRole('combobox', { name: /background blend/i }), {
+				target: { value: 'add' }
+			});
+			await fireEvent.click(await screen.findByRole('button', { name: /show background/i }));
+			await waitFor(() => {
+				expect(mockInvoke).toHaveBeenCalledWith('set_deck_background', {
+					deckId: 2,
+					background: { path: '/videos/frames', blend: 'add', fit: 'cover', opacity: 1 }
+				});
+			});
+
+			await fireEvent.click(await screen.findByRole('button', { name: /hide background/i }));
+			await waitFor(() => {
+				expect(mockInvoke).toHaveBeenCalledWith('set_deck_background', { deckId: 2, background: null });
+			});
+		});
+	});
+
+	describe('test pattern', () => {
+		it('shows and hides the test pattern on the deck', async () => {
+			render(VideoOutputPanel, { props: { deckId: 2 } });
+
+			await fireEvent.click(screen.getByRole('button', { name: /show test pattern/i }));
+			await waitFor(() => {
+				expect(mockInvoke).toHaveBeenCalledWith('set_output_test_pattern', {
+					deckId: 2,
+					enabled: true
+				});
+			});
+
+			await fireEvent.click(await screen.findByRole('button', { name: /hide test pattern/i }));
+			await waitFor(() => {
+				expect(mockInvoke).toHaveBeenCalledWith('set_output_test_pattern', {
+					deckId: 2,
+					enabled: false
+				});
+			});
+		});
+	});
+
+	describe('performance hud', () => {
+		it('shows and hides the HUD on the deck window', async () => {
+			render(VideoOutputPanel, { props: { deckId: 3 } });
+
+			await fireEvent.click(screen.getByRole('button', { name: /show hud/i }));
+			await waitFor(() => {
+				expect(mockInvoke).toHaveBeenCalledWith('set_deck_hud', { deckId: 3, enabled: true });
+			});
+
+			await fireEvent.click(await screen.findByRole('button', { name: /hide hud/i }));
+			await waitFor(() => {
+				expect(mockInvoke).toHaveBeenCalledWith('set_deck_hud', { deckId: 3, enabled: false });
+			});
+		});
+	});
+
+	describe('window options', () => {
+		it('floats the deck window and lets clicks through', async () => {
+			render(VideoOutputPanel, { props: { deckId: 1 } });
+
+			await fireEvent.click(screen.getByRole('button', { name: /always on top/i }));
+			await waitFor(() => {
+				expect(mockInvoke).toHaveBeenCalledWith('set_deck_window_options', {
+					deckId: 1,
+					alwaysOnTop: true,
+					clickThrough: null
+				});
+			});
+
+			await fireEvent.click(screen.getByRole('button', { name: /click-through/i }));
+			await waitFor(() => {
+				expect(mockInvoke).toHaveBeenCalledWith('set_deck_window_options', {
+					deckId: 1,
+					alwaysOnTop: null,
+					clickThrough: true
+				});
+			});
+			expect(screen.getByRole('button', { name: /click-through/i })).toHaveAttribute('aria-pressed', 'true');
+		});
+	});
+
+	describe('render quality', () => {
+		it('sends the chosen quality for the deck', async () => {
+			render(VideoOutputPanel, { props: { deckId: 2 } });
+
+			await fireEvent.change(screen.getByLabelText(/texture size/i), { target: { value: '640' } });
+			await waitFor(() => {
+				expect(mockInvoke).toHaveBeenCalledWith('set_deck_quality', {
+					deckId: 2,
+					quality: { mesh_width: 32, mesh_height: 24, fps: 60, texture_size: 640, adaptive: false }
+				});
+			});
+		});
+
+		it('turns on adaptive resolution', async () => {
+			render(VideoOutputPanel, { props: { deckId: 1 } });
+
+			await fireEvent.click(screen.getByRole('button', { name: /adaptive resolution/i }));
+			await waitFor(() => {
+				expect(mockInvoke).toHaveBeenCalledWith('set_deck_quality', {
+					deckId: 1,
+					quality: { mesh_width: 32, mesh_height: 24, fps: 60, texture_size: 0, adaptive: true }
+				});
+			});
+			expect(screen.getByRole('button', { name: /adaptive resolution/i })).toHaveAttribute('aria-pressed', 'true');
+		});
+
+		it('caps the deck frame rate', async () => {
+			render(VideoOutputPanel, { props: { deckId: 1 } });
+
+			await fireEvent.change(screen.getByLabelText(/fps cap/i), { target: { value: '30' } });
+			await waitFor(() => {
+				expect(mockInvoke).toHaveBeenCalledWith('set_deck_fps_cap', { deckId: 1, fps: 30 });
+			});
+
+			await fireEvent.change(screen.getByLabelText(/fps cap/i), { target: { value: '0' } });
+			await waitFor(() => {
+				expect(mockInvoke).toHaveBeenCalledWith('set_deck_fps_cap', { deckId: 1, fps: null });
+			});
+		});
+
+		it('shows the quality stored for the deck', async () => {
+			mockInvoke.mockImplementation(async (cmd) => {
+				if (cmd === 'get_multi_deck_status') {
+					return {
+						decks: [{ id: 1, quality: { mesh_width: 16, mesh_height: 12, fps: 30, texture_size: 960 } }]
+					};
+				}
+				return null;
+			});
+			render(VideoOutputPanel, { props: { deckId: 1 } });
+
+			await waitFor(() => {
+				expect(screen.getByLabelText(/mesh size/i)).toHaveValue('16x12');
+			});
+		});
+	});
+
+	describe('effects', () => {
+		it('sends effect intensities live', async () => {
+			render(VideoOutputPanel, { props: { deckId: 1 } });
+
+			await fireEvent.input(screen.getByLabelText(/kaleidoscope/i), { target: { value: '0.4' } });
+			await waitFor(() => {
+				expect(mockInvoke).toHaveBeenCalledWith('set_deck_effect', {
+					deckId: 1,
+					effect: 'kaleidoscope',
+					intensity: 0.4
+				});
+			});
+		});
+	});
+
+	describe('color adjust', () => {
+		it('sends colour changes and resets them', async () => {
+			render(VideoOutputPanel, { props: { deckId: 1 } });
+
+			expect(screen.getByRole('button', { name: /reset color/i })).toBeDisabled();
+			await fireEvent.input(screen.getByLabelText(/saturation/i), { target: { value: '0.5' } });
+			await waitFor(() => {
+				expect(mockInvoke).toHaveBeenCalledWith('set_deck_color_adjust', {
+					deckId: 1,
+					adjust: { brightness: 1, contrast: 1, saturation: 0.5 },
+					fadeSecs: null
+				});
+			});
+
+			await fireEvent.click(screen.getByRole('button', { name: /reset color/i }));
+			await waitFor(() => {
+				expect(mockInvoke).toHaveBeenCalledWith('set_deck_color_adjust', {
+					deckId: 1,
+					adjust: { brightness: 1, contrast: 1, saturation: 1 },
+					fadeSecs: null
+				});
+			});
+		});
+
+		it('shows the colour stored for the deck', async () => {
+			mockInvoke.mockImplementation(async (cmd) => {
+				if (cmd === 'get_multi_deck_status') {
+					return {
+						decks: [{ id: 1, color_adjust: { brightness: 0.8, contrast: 1.2, saturation: 1 } }]
+					};
+				}
+				return null;
+			});
+			render(VideoOutputPanel, { props: { deckId: 1 } });
+
+			await waitFor(() => {
+				expect(screen.getByLabelText(/brightness/i)).toHaveValue('0.8');
+			});
+		});
+	});
+
+	describe('blackout and strobe', () => {
+		it('blacks out the deck and lifts it again', async () => {
+			render(VideoOutputPanel, { props: { deckId: 2 } });
+
+			await fireEvent.click(screen.getByRole('button', { name: /^blackout$/i }));
+			await waitFor(() => {
+				expect(mockInvoke).toHaveBeenCalledWith('blackout', {
+					deckId: 2,
+					enabled: true,
+					fadeSecs: null
+				});
+			});
+
+			await fireEvent.click(await screen.findByRole('button', { name: /lift blackout/i }));
+			await waitFor(() => {
+				expect(mockInvoke).toHaveBeenCalledWith('blackout', {
+					deckId: 2,
+					enabled: false,
+					fadeSecs: null
+				});
+			});
+		});
+
+		it('starts the strobe at the chosen rate', async () => {
+			render(VideoOutputPanel, { props: { deckId: 0 } });
+
+			await fireEvent.change(screen.getByLabelText(/strobe rate/i), { target: { value: 'sixteenth' } });
+			await fireEvent.click(screen.getByRole('button', { name: /^strobe$/i }));
+			await waitFor(() => {
+				expect(mockInvoke).toHaveBeenCalledWith('set_deck_strobe', {
+					deckId: 0,
+					enabled: true,
+					strobe: { rate: 'sixteenth', intensity: 1 }
+				});
+			});
+			expect(await screen.findByRole('button', { name: /stop strobe/i })).toBeInTheDocument();
+		});
+
+		it('freezes the deck and plays on again', async () => {
+			render(VideoOutputPanel, { props: { deckId: 1 } });
+
+			await fireEvent.click(screen.getByRole('button', { name: /^freeze$/i }));
+			await waitFor(() => {
+				expect(mockInvoke).toHaveBeenCalledWith('freeze', { deckId: 1, enabled: true });
+			});
+
+			await fireEvent.click(await screen.findByRole('button', { name: /unfreeze/i }));
+			await waitFor(() => {
+				expect(mockInvoke).toHaveBeenCalledWith('freeze', { deckId: 1, enabled: false });
+			});
+		});
+	});
+
+	describe('playback speed', () => {
+		it('changes the speed and resets it to normal', async () => {
+			render(VideoOutputPanel, { props: { deckId: 0 } });
+
+			expect(screen.getByRole('button', { name: /normal speed/i })).toBeDisabled();
+			await fireEvent.input(screen.getByLabelText(/speed/i), { target: { value: '0.5' } });
+			await waitFor(() => {
+				expect(mockInvoke).toHaveBeenCalledWith('set_deck_time_scale', { deckId: 0, scale: 0.5 });
+			});
+
+			await fireEvent.click(screen.getByRole('button', { name: /normal speed/i }));
+			await waitFor(() => {
+				expect(mockInvoke).toHaveBeenCalledWith('set_deck_time_scale', { deckId: 0, scale: 1 });
+			});
+		});
+	});
+
+	describe('text overlay', () => {
+		it('shows the typed text and clears it again', async () => {
+			render(VideoOutputPanel, { props: { deckId: 0 } });
+
+			expect(screen.getByRole('button', { name: /show text/i })).toBeDisabled();
+			await fireEvent.input(screen.getByLabelText(/overlay text/i), {
+				target: { value: 'Now playing: Track' }
+			});
+			await fireEvent.input(screen.getByLabelText(/text duration/i), { target: { value: '10' } });
+			await fireEvent.click(screen.getByRole('button', { name: /show text/i }));
+			await waitFor(() => {
+				expect(mockInvoke).toHaveBeenCalledWith('set_deck_overlay_text', {
+					deckId: 0,
+					text: {
+						text: 'Now playing: Track',
+						position: 'bottom',
+						font_size: 0.06,
+						duration_secs: 10
+					}
+				});
+			});
+
+			await fireEvent.click(await screen.findByRole('button', { name: /clear text/i }));
+			await waitFor(() => {
+				expect(mockInvoke).toHaveBeenCalledWith('set_deck_overlay_text', { deckId: 0, text: null });
+			});
+		});
+	});
+
+	describe('loop export', () => {
+		it('records a crossfaded loop and reports when it is saved', async () => {
+			mockInvoke.mockImplementation(async (cmd) => {
+				if (cmd === 'list_video_outputs') return mockDevices;
+				if (cmd === 'list_monitors') return mockMonitors;
+				if (cmd === 'get_multi_deck_status') {
+					return {
+						decks: [
+							{
+								id: 1,
+								loop_export: { path: '/videos/set.webm', status: 'done', error: null }
+							}
+						]
+					};
+				}
+				return null;
+			});
+			render(VideoOutputPanel, { props: { deckId: 1 } });
+
+			await fireEvent.change(screen.getByRole('combobox', { name: /loop length/i }), {
+				target: { value: '8' }
+			});
+			await fireEvent.click(screen.getByRole('button', { name: /record loop/i }));
+			await waitFor(() => {
+				expect(mockInvoke).toHaveBeenCalledWith('start_loop_export', {
+					deckId: 1,
+					path: '/videos/set.mp4',
+					beats: 8,
+					format: 'webm',
+					crossfade: 1
+				});
+			});
+
+			await waitFor(() => {
+				expect(mockInvoke).toHaveBeenCalledWith('get_multi_deck_status');
+			});
+			expect(await screen.findByRole('button', { name: /record loop/i })).toBeInTheDocument();
+		});
+	});
+
 	describe('help text', () => {
 		it('shows v4l2 help text', async () => {
 			render(VideoOutputPanel);
@@ -0,0 +1,49 @@
+import { describe, it, expect, vi, beforeEach } from 'vitest';
+import { render, waitFor } from '@testing-library/svelte';
+import { invoke } from '@tauri-apps/api/core';
+import WaveformView from '$lib/components/WaveformView.svelte';
+
+vi.mock('@tauri-apps/api/core');
+
+describe('WaveformView', () => {
+	const mockWaveform = {
+		points_per_sec: 200,
+		points: [
+			{ min: -0.5, max: 0.5 },
+			{ min: -0.25, max: 0.75 },
+			{ min: 0, max: 0.1 }
+		]
+	};
+
+	beforeEach(() => {
+		vi.clearAllMocks();
+		vi.mocked(invoke).mockResolvedValue(mockWaveform);
+	});
+
+	it('does not poll while stopped', () => {
+		const { container } = render(WaveformView, { props: { running: false } });
+
+		expect(invoke).not.toHaveBeenCalled();
+		expect(container.querySelector('polygon')).not.toBeInTheDocument();
+	});
+
+	it('draws the min/max envelope while running', async () => {
+		const { container } = render(WaveformView, { props: { running: true } });
+
+		await waitFor(() => {
+			expect(invoke).toHaveBeenCalledWith('get_waveform', { windowMs: 2000 });
+			expect(container.querySelector('polygon')).toHaveAttribute(
+				'points',
+				'0,-0.5 1,-0.75 2,-0.1 2,0 1,0.25 0,0.5'
+			);
+		});
+	});
+
+	it('requests the given window', async () => {
+		render(WaveformView, { props: { running: true, windowMs: 5000 } });
+
+		await waitFor(() => {
+			expect(invoke).toHaveBeenCalledWith('get_waveform', { windowMs: 5000 });
+		});
+	});
+});